@@ -16,21 +16,33 @@ pub struct CompilerConfig {
     pub c_run_name: String,
     pub c_file: String,
     pub dat_file: String,
+    pub llvm_folder: String,
+    pub llvm_run_name: String,
+    pub llvm_file: String,
     pub wat_flag: bool,
     pub wasm_flag: bool,
     pub c_flag: bool,
+    pub llvm_flag: bool,
     pub debug_output: bool,
     pub produce_input_log: bool,
+    pub profile_witness: bool,
+    pub witness_optimization: usize,
     pub vcp: VCP,
 }
 
 pub fn compile(config: CompilerConfig) -> Result<(), ()> {
 
 
-    if config.c_flag || config.wat_flag || config.wasm_flag{
+    if config.c_flag || config.llvm_flag || config.wat_flag || config.wasm_flag{
         let circuit = compiler_interface::run_compiler(
             config.vcp,
-            Config { debug_output: config.debug_output, produce_input_log: config.produce_input_log, wat_flag: config.wat_flag },
+            Config {
+                debug_output: config.debug_output,
+                produce_input_log: config.produce_input_log,
+                wat_flag: config.wat_flag,
+                profile_witness: config.profile_witness,
+                witness_optimization: config.witness_optimization,
+            },
             VERSION
         )?;
     
@@ -57,6 +69,16 @@ pub fn compile(config: CompilerConfig) -> Result<(), ()> {
             );
         }
     
+        if config.llvm_flag {
+            compiler_interface::write_llvm_ir(&circuit, &config.llvm_folder, &config.llvm_run_name, &config.llvm_file)?;
+            println!("{} {}", Colour::Green.paint("Written successfully:"), config.llvm_file);
+            println!(
+                "{} {}/main.c, circom.h, fr.h, fr.c and Makefile",
+                Colour::Green.paint("Written successfully:"),
+                &config.llvm_folder
+            );
+        }
+
         match (config.wat_flag, config.wasm_flag) {
             (true, true) => {
                 compiler_interface::write_wasm(&circuit, &config.js_folder, &config.wasm_name, &config.wat_file)?;
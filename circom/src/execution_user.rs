@@ -1,7 +1,9 @@
 use ansi_term::Colour;
 use compiler::hir::very_concrete_program::VCP;
 use constraint_writers::debug_writer::DebugWriter;
-use constraint_writers::ConstraintExporter;
+use constraint_writers::json_writer;
+use constraint_writers::{ConstraintExporter, Provenance};
+use dag::{SimplificationStrategy, StatisticsFormat};
 use program_structure::program_archive::ProgramArchive;
 
 
@@ -15,13 +17,20 @@ pub struct ExecutionConfig {
     pub flag_f: bool,
     pub flag_p: bool,
     pub flag_old_heuristics:bool,
+    pub strategy: SimplificationStrategy,
+    pub keep: Vec<String>,
+    pub public_layout: Vec<String>,
     pub flag_verbose: bool,
     pub inspect_constraints_flag: bool,
     pub sym_flag: bool,
     pub r1cs_flag: bool,
     pub json_substitution_flag: bool,
     pub json_constraint_flag: bool,
+    pub provenance_flag: bool,
+    pub provenance: String,
     pub prime: String,
+    pub statistics: Option<StatisticsFormat>,
+    pub statistics_file: String,
 }
 
 pub fn execute_project(
@@ -37,15 +46,23 @@ pub fn execute_project(
         flag_s: config.flag_s,
         flag_f: config.flag_f,
         flag_p: config.flag_p,
+        no_threads: None,
         flag_verbose: config.flag_verbose,
         inspect_constraints: config.inspect_constraints_flag,
         flag_old_heuristics: config.flag_old_heuristics,
+        strategy: config.strategy,
+        keep: config.keep,
+        public_layout: config.public_layout,
+        provenance: config.provenance_flag,
         prime : config.prime,
+        statistics: config.statistics,
+        statistics_file: config.statistics_file,
     };
     let custom_gates = program_archive.custom_gates;
     let (exporter, vcp) = build_circuit(program_archive, build_config)?;
+    let provenance = if config.provenance_flag { Some(exporter.provenance()) } else { None };
     if config.r1cs_flag {
-        generate_output_r1cs(&config.r1cs, exporter.as_ref(), custom_gates)?;
+        generate_output_r1cs(&config.r1cs, exporter.as_ref(), custom_gates, provenance.as_ref())?;
     }
     if config.sym_flag {
         generate_output_sym(&config.sym, exporter.as_ref())?;
@@ -53,11 +70,19 @@ pub fn execute_project(
     if config.json_constraint_flag {
         generate_json_constraints(&debug, exporter.as_ref())?;
     }
+    if let Some(provenance) = &provenance {
+        generate_output_provenance(&config.provenance, provenance)?;
+    }
     Result::Ok(vcp)
 }
 
-fn generate_output_r1cs(file: &str, exporter: &dyn ConstraintExporter, custom_gates: bool) -> Result<(), ()> {
-    if let Result::Ok(()) = exporter.r1cs(file, custom_gates) {
+fn generate_output_r1cs(
+    file: &str,
+    exporter: &dyn ConstraintExporter,
+    custom_gates: bool,
+    provenance: Option<&Provenance>,
+) -> Result<(), ()> {
+    if let Result::Ok(()) = exporter.r1cs(file, custom_gates, provenance) {
         println!("{} {}", Colour::Green.paint("Written successfully:"), file);
         Result::Ok(())
     } else {
@@ -76,6 +101,16 @@ fn generate_output_sym(file: &str, exporter: &dyn ConstraintExporter) -> Result<
     }
 }
 
+fn generate_output_provenance(file: &str, provenance: &Provenance) -> Result<(), ()> {
+    if let Result::Ok(()) = json_writer::write_provenance(file, provenance) {
+        println!("{} {}", Colour::Green.paint("Written successfully:"), file);
+        Result::Ok(())
+    } else {
+        eprintln!("{}", Colour::Red.paint("Could not write the output in the given path"));
+        Result::Err(())
+    }
+}
+
 fn generate_json_constraints(
     debug: &DebugWriter,
     exporter: &dyn ConstraintExporter,
@@ -0,0 +1,83 @@
+use super::input_user::FormatInput;
+use ansi_term::Colour;
+use program_structure::error_definition::Report;
+use program_structure::file_definition::FileLibrary;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub fn format_files(input: &FormatInput) -> Result<(), ()> {
+    let mut files = Vec::new();
+    for path in &input.paths {
+        if path.is_dir() {
+            collect_files(path, &mut files);
+        } else {
+            files.push(path.clone());
+        }
+    }
+    let mut failed = false;
+    let mut unformatted = 0;
+    for file in files {
+        match format(&file) {
+            Result::Err(()) => failed = true,
+            Result::Ok(None) => {}
+            Result::Ok(Some(formatted)) => {
+                unformatted += 1;
+                if input.check_flag {
+                    println!("{} is not formatted", file.display());
+                } else if fs::write(&file, formatted).is_ok() {
+                    println!("{} {}", Colour::Green.paint("Formatted"), file.display());
+                } else {
+                    eprintln!("{}", Colour::Red.paint(format!("Could not write {}", file.display())));
+                    failed = true;
+                }
+            }
+        }
+    }
+    if input.check_flag && unformatted > 0 {
+        eprintln!("{}", Colour::Red.paint(format!("{} files are not formatted", unformatted)));
+        failed = true;
+    }
+    if failed {
+        Result::Err(())
+    } else {
+        Result::Ok(())
+    }
+}
+
+// Returns the formatted source when it differs from the file. The formatted
+// source is checked to parse into the tree of the file, which is done by the
+// formatter, and to be formatted again into itself before it is used.
+fn format(file: &Path) -> Result<Option<String>, ()> {
+    let src = fs::read_to_string(file).map_err(|_| {
+        eprintln!("{}", Colour::Red.paint(format!("Could not open file {}", file.display())));
+    })?;
+    let mut file_library = FileLibrary::new();
+    let file_id = file_library.add_file(file.display().to_string(), src.clone());
+    let formatted = parser::format_file(&src, file_id).map_err(|reports| {
+        Report::print_reports(&reports, &file_library);
+    })?;
+    if parser::format_file(&formatted, file_id).ok().as_ref() != Some(&formatted) {
+        eprintln!("{}", Colour::Red.paint(format!("The formatting of {} is not stable, the file is left as it is", file.display())));
+        return Result::Err(());
+    }
+    Result::Ok(if formatted == src { None } else { Some(formatted) })
+}
+
+fn collect_files(directory: &Path, files: &mut Vec<PathBuf>) {
+    let mut entries: Vec<PathBuf> = match fs::read_dir(directory) {
+        Ok(entries) => entries.filter_map(|entry| entry.ok().map(|entry| entry.path())).collect(),
+        Err(_) => return,
+    };
+    entries.sort();
+    for path in entries {
+        let name = path.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default();
+        if name.starts_with('.') || name == "node_modules" {
+            continue;
+        }
+        if path.is_dir() {
+            collect_files(&path, files);
+        } else if path.extension().is_some_and(|ext| ext == "circom") {
+            files.push(path);
+        }
+    }
+}
@@ -1,5 +1,46 @@
+use clap::ArgMatches;
+use dag::{SimplificationStrategy, StatisticsFormat, Tampering};
 use std::path::PathBuf;
 
+pub enum Command {
+    Compile(Box<Input>),
+    Format(FormatInput),
+    Test(TestInput),
+}
+
+pub struct FormatInput {
+    pub paths: Vec<PathBuf>,
+    pub check_flag: bool,
+}
+
+pub struct TestInput {
+    pub input_program: PathBuf,
+    pub link_libraries: Vec<PathBuf>,
+    pub prime: String,
+    pub filter: Option<String>,
+    pub tampering: Option<Tampering>,
+}
+
+pub fn get_command() -> Result<Command, ()> {
+    let matches = input_processing::view();
+    if let Some(format_matches) = matches.subcommand_matches("fmt") {
+        return Result::Ok(Command::Format(FormatInput {
+            paths: input_processing::get_paths(format_matches),
+            check_flag: format_matches.is_present("check"),
+        }));
+    }
+    if let Some(test_matches) = matches.subcommand_matches("test") {
+        return Result::Ok(Command::Test(TestInput {
+            input_program: input_processing::get_input(test_matches)?,
+            link_libraries: input_processing::get_link_libraries(test_matches),
+            prime: input_processing::get_prime(test_matches)?,
+            filter: test_matches.value_of("filter").map(String::from),
+            tampering: input_processing::get_tampering(test_matches)?,
+        }));
+    }
+    Input::new(&matches).map(|input| Command::Compile(Box::new(input)))
+}
+
 pub struct Input {
     pub input_program: PathBuf,
     pub out_r1cs: PathBuf,
@@ -13,25 +54,38 @@ pub struct Input {
     pub out_c_folder: PathBuf,
     pub out_c_code: PathBuf,
     pub out_c_dat: PathBuf,
+    pub out_llvm_run_name: String,
+    pub out_llvm_folder: PathBuf,
+    pub out_llvm_code: PathBuf,
     pub out_sym: PathBuf,
+    pub out_statistics: PathBuf,
+    pub out_provenance: PathBuf,
     //pub field: &'static str,
     pub c_flag: bool,
+    pub llvm_flag: bool,
     pub wasm_flag: bool,
     pub wat_flag: bool,
     pub r1cs_flag: bool,
     pub sym_flag: bool,
     pub json_constraint_flag: bool,
     pub json_substitution_flag: bool,
+    pub provenance_flag: bool,
     pub main_inputs_flag: bool,
     pub print_ir_flag: bool,
     pub fast_flag: bool,
     pub reduced_simplification_flag: bool,
     pub parallel_simplification_flag: bool,
     pub flag_old_heuristics: bool,
+    pub strategy: SimplificationStrategy,
+    pub keep: Vec<String>,
+    pub public_layout: Vec<String>,
     pub inspect_constraints_flag: bool,
     pub no_rounds: usize,
     pub flag_verbose: bool,
+    pub profile_witness_flag: bool,
+    pub witness_optimization: usize,
     pub prime: String,
+    pub statistics: Option<StatisticsFormat>,
     pub link_libraries : Vec<PathBuf>
 }
 
@@ -42,29 +96,37 @@ const WASM: &'static str = "wasm";
 const CPP: &'static str = "cpp";
 const JS: &'static str = "js";
 const DAT: &'static str = "dat";
+const LLVM: &str = "llvm";
+const LL: &str = "ll";
 const SYM: &'static str = "sym";
 const JSON: &'static str = "json";
 
 
 impl Input {
-    pub fn new() -> Result<Input, ()> {
+    pub fn new(matches: &ArgMatches) -> Result<Input, ()> {
         use ansi_term::Colour;
         use input_processing::SimplificationStyle;
-        let matches = input_processing::view();
-        let input = input_processing::get_input(&matches)?;
+        let input = input_processing::get_input(matches)?;
         let mut file_name = input.file_stem().unwrap().to_str().unwrap().to_string();
-        let output_path = input_processing::get_output_path(&matches)?;
+        let output_path = input_processing::get_output_path(matches)?;
 
-        let c_flag = input_processing::get_c(&matches);
+        let c_flag = input_processing::get_c(matches);
 
         if c_flag && (file_name == "main" || file_name == "fr" || file_name == "calcwit"){
             println!("{}", Colour::Yellow.paint(format!("The name {} is reserved in Circom when using de --c flag. The files generated for your circuit will use the name {}_c instead of {}.", file_name, file_name, file_name)));
             file_name = format!("{}_c", file_name)
         };
+        let llvm_flag = input_processing::get_llvm(matches);
+
+        if llvm_flag && (file_name == "main" || file_name == "fr" || file_name == "circom"){
+            println!("{}", Colour::Yellow.paint(format!("The name {} is reserved in Circom when using the --llvm flag. The files generated for your circuit will use the name {}_llvm instead of {}.", file_name, file_name, file_name)));
+            file_name = format!("{}_llvm", file_name)
+        };
         let output_c_path = Input::build_folder(&output_path, &file_name, CPP);
+        let output_llvm_path = Input::build_folder(&output_path, &file_name, LLVM);
         let output_js_path = Input::build_folder(&output_path, &file_name, JS);
-        let o_style = input_processing::get_simplification_style(&matches)?;
-        let link_libraries = input_processing::get_link_libraries(&matches);
+        let o_style = input_processing::get_simplification_style(matches)?;
+        let link_libraries = input_processing::get_link_libraries(matches);
         Result::Ok(Input {
             //field: P_BN128,
             input_program: input,
@@ -77,6 +139,9 @@ impl Input {
 	        out_c_run_name: file_name.clone(),
             out_c_code: Input::build_output(&output_c_path, &file_name, CPP),
             out_c_dat: Input::build_output(&output_c_path, &file_name, DAT),
+            out_llvm_folder: output_llvm_path.clone(),
+            out_llvm_run_name: file_name.clone(),
+            out_llvm_code: Input::build_output(&output_llvm_path, &file_name, LL),
             out_sym: Input::build_output(&output_path, &file_name, SYM),
             out_json_constraints: Input::build_output(
                 &output_path,
@@ -88,23 +153,33 @@ impl Input {
                 &format!("{}_substitutions", file_name),
                 JSON,
             ),
-            wat_flag:input_processing::get_wat(&matches),
-            wasm_flag: input_processing::get_wasm(&matches),
+            out_statistics: Input::build_output(&output_path, &format!("{}_stats", file_name), JSON),
+            out_provenance: Input::build_output(&output_path, &format!("{}_provenance", file_name), JSON),
+            wat_flag:input_processing::get_wat(matches),
+            wasm_flag: input_processing::get_wasm(matches),
             c_flag: c_flag,
-            r1cs_flag: input_processing::get_r1cs(&matches),
-            sym_flag: input_processing::get_sym(&matches),
-            main_inputs_flag: input_processing::get_main_inputs_log(&matches),
-            json_constraint_flag: input_processing::get_json_constraints(&matches),
-            json_substitution_flag: input_processing::get_json_substitutions(&matches),
-            print_ir_flag: input_processing::get_ir(&matches),
+            llvm_flag,
+            r1cs_flag: input_processing::get_r1cs(matches),
+            sym_flag: input_processing::get_sym(matches),
+            main_inputs_flag: input_processing::get_main_inputs_log(matches),
+            json_constraint_flag: input_processing::get_json_constraints(matches),
+            json_substitution_flag: input_processing::get_json_substitutions(matches),
+            provenance_flag: input_processing::get_provenance(matches),
+            print_ir_flag: input_processing::get_ir(matches),
             no_rounds: if let SimplificationStyle::O2(r) = o_style { r } else { 0 },
             fast_flag: o_style == SimplificationStyle::O0,
             reduced_simplification_flag: o_style == SimplificationStyle::O1,
-            parallel_simplification_flag: input_processing::get_parallel_simplification(&matches),
-            inspect_constraints_flag: input_processing::get_inspect_constraints(&matches),
-            flag_old_heuristics: input_processing::get_flag_old_heuristics(&matches),
-            flag_verbose: input_processing::get_flag_verbose(&matches), 
-            prime: input_processing::get_prime(&matches)?,
+            parallel_simplification_flag: input_processing::get_parallel_simplification(matches),
+            inspect_constraints_flag: input_processing::get_inspect_constraints(matches),
+            flag_old_heuristics: input_processing::get_flag_old_heuristics(matches),
+            strategy: input_processing::get_strategy(matches),
+            keep: input_processing::get_keep(matches)?,
+            public_layout: input_processing::get_public_layout(matches)?,
+            flag_verbose: input_processing::get_flag_verbose(matches), 
+            profile_witness_flag: input_processing::get_profile_witness(matches),
+            witness_optimization: input_processing::get_witness_optimization(matches),
+            prime: input_processing::get_prime(matches)?,
+            statistics: input_processing::get_statistics(matches),
             link_libraries
         })
     }
@@ -161,12 +236,27 @@ impl Input {
     pub fn dat_file(&self) -> &str {
         self.out_c_dat.to_str().unwrap()
     }
+    pub fn llvm_folder(&self) -> &str {
+        self.out_llvm_folder.to_str().unwrap()
+    }
+    pub fn llvm_run_name(&self) -> String {
+        self.out_llvm_run_name.clone()
+    }
+    pub fn llvm_file(&self) -> &str {
+        self.out_llvm_code.to_str().unwrap()
+    }
     pub fn json_constraints_file(&self) -> &str {
         self.out_json_constraints.to_str().unwrap()
     }
     pub fn json_substitutions_file(&self) -> &str {
         self.out_json_substitutions.to_str().unwrap()
     }
+    pub fn statistics_file(&self) -> &str {
+        self.out_statistics.to_str().unwrap()
+    }
+    pub fn provenance_file(&self) -> &str {
+        self.out_provenance.to_str().unwrap()
+    }
     pub fn wasm_flag(&self) -> bool {
         self.wasm_flag
     }
@@ -176,6 +266,9 @@ impl Input {
     pub fn c_flag(&self) -> bool {
         self.c_flag
     }
+    pub fn llvm_flag(&self) -> bool {
+        self.llvm_flag
+    }
     pub fn unsimplified_flag(&self) -> bool {
         self.fast_flag
     }
@@ -188,6 +281,9 @@ impl Input {
     pub fn json_substitutions_flag(&self) -> bool {
         self.json_substitution_flag
     }
+    pub fn provenance_flag(&self) -> bool {
+        self.provenance_flag
+    }
     pub fn main_inputs_flag(&self) -> bool {
         self.main_inputs_flag
     }
@@ -203,6 +299,12 @@ impl Input {
     pub fn flag_verbose(&self) -> bool {
         self.flag_verbose
     }
+    pub fn profile_witness_flag(&self) -> bool {
+        self.profile_witness_flag
+    }
+    pub fn witness_optimization(&self) -> usize {
+        self.witness_optimization
+    }
     pub fn reduced_simplification_flag(&self) -> bool {
         self.reduced_simplification_flag
     }
@@ -212,18 +314,33 @@ impl Input {
     pub fn flag_old_heuristics(&self) -> bool {
         self.flag_old_heuristics
     }
+    pub fn strategy(&self) -> SimplificationStrategy {
+        self.strategy
+    }
+    pub fn keep(&self) -> &[String] {
+        &self.keep
+    }
+    pub fn public_layout(&self) -> &[String] {
+        &self.public_layout
+    }
     pub fn no_rounds(&self) -> usize {
         self.no_rounds
     }
     pub fn prime(&self) -> String{
         self.prime.clone()
     }
+    pub fn statistics(&self) -> Option<StatisticsFormat> {
+        self.statistics
+    }
 }
 mod input_processing {
     use ansi_term::Colour;
-    use clap::{App, Arg, ArgMatches};
+    use clap::{App, Arg, ArgMatches, SubCommand};
+    use compiler::num_bigint::BigInt;
+    use dag::{SimplificationStrategy, StatisticsFormat, Tampering};
     use std::path::{Path, PathBuf};
     use crate::VERSION;
+    use program_structure::constants::{is_named_prime, is_odd_prime, prime_from_name_or_value, prime_name_of_value};
 
     pub fn get_input(matches: &ArgMatches) -> Result<PathBuf, ()> {
         let route = Path::new(matches.value_of("input").unwrap()).to_path_buf();
@@ -277,6 +394,10 @@ mod input_processing {
         matches.is_present("print_json_sub")
     }
 
+    pub fn get_provenance(matches: &ArgMatches) -> bool {
+        matches.is_present("provenance")
+    }
+
     pub fn get_sym(matches: &ArgMatches) -> bool {
         matches.is_present("print_sym")
     }
@@ -297,6 +418,10 @@ mod input_processing {
         matches.is_present("print_c")
     }
 
+    pub fn get_llvm(matches: &ArgMatches) -> bool {
+        matches.is_present("print_llvm")
+    }
+
     pub fn get_main_inputs_log(matches: &ArgMatches) -> bool {
         matches.is_present("main_inputs_log")
     }
@@ -316,28 +441,90 @@ mod input_processing {
         matches.is_present("flag_verbose")
     }
 
+    pub fn get_profile_witness(matches: &ArgMatches) -> bool {
+        matches.is_present("profile_witness")
+    }
+
+    pub fn get_witness_optimization(matches: &ArgMatches) -> usize {
+        if matches.is_present("witness_optimization_2") {
+            2
+        } else if matches.is_present("witness_optimization_1") {
+            1
+        } else {
+            0
+        }
+    }
+
+    pub fn get_statistics(matches: &ArgMatches) -> Option<StatisticsFormat> {
+        match matches.value_of("statistics") {
+            Some("json") => Some(StatisticsFormat::Json),
+            Some(_) => Some(StatisticsFormat::Table),
+            None => None,
+        }
+    }
+
     pub fn get_flag_old_heuristics(matches: &ArgMatches) -> bool {
         matches.is_present("flag_old_heuristics")
     }
+
+    pub fn get_strategy(matches: &ArgMatches) -> SimplificationStrategy {
+        match matches.value_of("strategy") {
+            Some("terms") => SimplificationStrategy::Terms,
+            _ => SimplificationStrategy::Wires,
+        }
+    }
+
+    pub fn get_keep(matches: &ArgMatches) -> Result<Vec<String>, ()> {
+        read_lines(matches, "keep")
+    }
+
+    pub fn get_public_layout(matches: &ArgMatches) -> Result<Vec<String>, ()> {
+        read_lines(matches, "public_layout")
+    }
+
+    // The lines of the file given in the option, ignoring the empty lines and
+    // the comments
+    fn read_lines(matches: &ArgMatches, option: &str) -> Result<Vec<String>, ()> {
+        let file = match matches.value_of(option) {
+            Some(file) => file,
+            None => return Ok(Vec::new()),
+        };
+        match std::fs::read_to_string(file) {
+            Ok(contents) => Ok(contents
+                .lines()
+                .map(|line| line.trim())
+                .filter(|line| !line.is_empty() && !line.starts_with("//"))
+                .map(|line| line.to_string())
+                .collect()),
+            Err(_) => Result::Err(eprintln!("{}", Colour::Red.paint(format!("invalid {} file: {}", option, file)))),
+        }
+    }
     pub fn get_prime(matches: &ArgMatches) -> Result<String, ()> {
         
         match matches.is_present("prime"){
             true => 
                {
                    let prime_value = matches.value_of("prime").unwrap();
-                   if prime_value == "bn128"
-                      || prime_value == "bls12381"
-                      || prime_value == "goldilocks"
-                      || prime_value == "grumpkin"
-                      || prime_value == "pallas"
-                      || prime_value == "vesta"
-                      || prime_value == "secq256r1"
-                      {
-                        Ok(String::from(matches.value_of("prime").unwrap()))
-                    }
-                    else{
-                        Result::Err(eprintln!("{}", Colour::Red.paint("invalid prime number")))
-                    }
+                   if is_named_prime(prime_value) {
+                       return Ok(String::from(prime_value));
+                   }
+                   match prime_from_name_or_value(prime_value) {
+                       Some(p) if is_odd_prime(&p) => {
+                           // A value matching one of the named curves uses its optimized field code
+                           match prime_name_of_value(&p) {
+                               Some(name) => Ok(String::from(name)),
+                               None => Ok(p.to_str_radix(10)),
+                           }
+                       }
+                       Some(_) => {
+                           eprintln!("{}", Colour::Red.paint(format!("{} is not an odd prime number", prime_value)));
+                           Result::Err(())
+                       }
+                       None => {
+                           eprintln!("{}", Colour::Red.paint("invalid prime number"));
+                           Result::Err(())
+                       }
+                   }
                }
                
             false => Ok(String::from("bn128")),
@@ -387,6 +574,30 @@ mod input_processing {
                     .help("Maximum number of rounds of the simplification process")
                     .display_order(500)
             )
+            .arg(
+                Arg::with_name("witness_optimization_0")
+                    .long("Ow0")
+                    .takes_value(false)
+                    .hidden(false)
+                    .help("No optimization is applied to the witness generator code (default)")
+                    .display_order(520)
+            )
+            .arg(
+                Arg::with_name("witness_optimization_1")
+                    .long("Ow1")
+                    .takes_value(false)
+                    .hidden(false)
+                    .help("Folds constant expressions and removes unreachable code in the witness generator")
+                    .display_order(540)
+            )
+            .arg(
+                Arg::with_name("witness_optimization_2")
+                    .long("Ow2")
+                    .takes_value(false)
+                    .hidden(false)
+                    .help("Also unrolls small loops and inlines tiny functions in the witness generator")
+                    .display_order(560)
+            )
             .arg(
                 Arg::with_name("output")
                     .short("o")
@@ -470,6 +681,13 @@ mod input_processing {
                     .display_order(150)
                     .help("Compiles the circuit to c"),
             )
+            .arg(
+                Arg::with_name("print_llvm")
+                    .long("llvm")
+                    .takes_value(false)
+                    .display_order(160)
+                    .help("Compiles the circuit to LLVM IR with a C driver"),
+            )
             .arg(
                 Arg::with_name("parallel_simplification")
                     .long("parallel")
@@ -493,6 +711,22 @@ mod input_processing {
                     .display_order(800)
                     .help("Shows logs during compilation"),
             )
+            .arg(
+                Arg::with_name("profile_witness")
+                    .long("profile-witness")
+                    .takes_value(false)
+                    .display_order(810)
+                    .help("Adds per-template and per-function counters and timers to the witness generator"),
+            )
+            .arg(
+                Arg::with_name("statistics")
+                    .long("stats")
+                    .takes_value(true)
+                    .possible_values(&["json", "table"])
+                    .value_name("format")
+                    .display_order(805)
+                    .help("Reports the constraints and signals of each template instance and template, before and after the simplification"),
+            )
             .arg(
                 Arg::with_name("flag_old_heuristics")
                     .long("use_old_simplification_heuristics")
@@ -500,6 +734,39 @@ mod input_processing {
                     .display_order(980)
                     .help("Applies the old version of the heuristics when performing linear simplification"),
             )
+            .arg(
+                Arg::with_name("strategy")
+                    .long("simplification_strategy")
+                    .takes_value(true)
+                    .possible_values(&["wires", "terms"])
+                    .value_name("strategy")
+                    .conflicts_with("flag_old_heuristics")
+                    .display_order(990)
+                    .help("Chooses the signals removed by the linear simplification to minimize the wires (default) or the terms of the constraints"),
+            )
+            .arg(
+                Arg::with_name("keep")
+                    .long("keep")
+                    .takes_value(true)
+                    .value_name("file")
+                    .display_order(995)
+                    .help("Keeps the signals whose qualified names match a pattern of the file, one per line, where * matches any sequence of characters"),
+            )
+            .arg(
+                Arg::with_name("public_layout")
+                    .long("public_layout")
+                    .takes_value(true)
+                    .value_name("file")
+                    .display_order(996)
+                    .help("Orders the public signals of the main component as in the file, one name per line, in the r1cs, sym and witness files"),
+            )
+            .arg(
+                Arg::with_name("provenance")
+                    .long("provenance")
+                    .takes_value(false)
+                    .display_order(997)
+                    .help("Outputs the file, line, component and original form of the constraints each constraint comes from, in an r1cs section and in json format"),
+            )
             .arg (
                 Arg::with_name("prime")
                     .short("prime")
@@ -507,11 +774,121 @@ mod input_processing {
                     .takes_value(true)
                     .default_value("bn128")
                     .display_order(300)
-                    .help("To choose the prime number to use to generate the circuit. Receives the name of the curve (bn128, bls12381, goldilocks, grumpkin, pallas, vesta, secq256r1)"),
+                    .help("To choose the prime number to use to generate the circuit. Receives the name of the curve (bn128, bls12381, goldilocks, grumpkin, pallas, vesta, secq256r1) or the value of any odd prime, in decimal or in hexadecimal with a 0x prefix"),
+            )
+            .subcommand(
+                SubCommand::with_name("fmt")
+                    .about("Formats circom files in the canonical layout")
+                    .arg(
+                        Arg::with_name("paths")
+                            .multiple(true)
+                            .default_value(".")
+                            .help("Files to format, or directories with the files to format"),
+                    )
+                    .arg(
+                        Arg::with_name("check")
+                            .long("check")
+                            .takes_value(false)
+                            .help("Only reports the files that are not formatted, failing if there is any"),
+                    ),
+            )
+            .subcommand(
+                SubCommand::with_name("test")
+                    .about("Runs the tests of a circom file")
+                    .arg(
+                        Arg::with_name("input")
+                            .multiple(false)
+                            .default_value("./circuit.circom")
+                            .help("Path to a file with tests"),
+                    )
+                    .arg(
+                        Arg::with_name("filter")
+                            .long("filter")
+                            .takes_value(true)
+                            .help("Only runs the tests whose name contains the given text"),
+                    )
+                    .arg(
+                        Arg::with_name("soundness")
+                            .long("soundness")
+                            .takes_value(false)
+                            .help("Changes every signal of the witness of a test to a random value, one at a time, and fails the test if the constraints still hold"),
+                    )
+                    .arg(
+                        Arg::with_name("tamper")
+                            .long("tamper")
+                            .takes_value(true)
+                            .multiple(true)
+                            .number_of_values(1)
+                            .conflicts_with("soundness")
+                            .value_name("signal[=value]")
+                            .help("Changes the given signal of the witness of a test, to the value or to a random one, and fails the test if the constraints still hold. The signals given in several --tamper are changed together"),
+                    )
+                    .arg(
+                        Arg::with_name("link_libraries")
+                            .short("l")
+                            .takes_value(true)
+                            .multiple(true)
+                            .number_of_values(1)
+                            .help("Adds directory to library search path"),
+                    )
+                    .arg(
+                        Arg::with_name("prime")
+                            .short("prime")
+                            .long("prime")
+                            .takes_value(true)
+                            .default_value("bn128")
+                            .help("To choose the prime number to use to run the tests"),
+                    ),
             )
             .get_matches()
     }
 
+    // Signals are named from the test, like c.out[1], and values are decimal or hexadecimal
+    pub fn get_tampering(matches: &ArgMatches) -> Result<Option<Tampering>, ()> {
+        if matches.is_present("soundness") {
+            return Result::Ok(Some(Tampering::AllSignals));
+        }
+        let tampered = match matches.values_of("tamper") {
+            Some(tampered) => tampered,
+            None => return Result::Ok(None),
+        };
+        let mut signals = Vec::new();
+        for change in tampered {
+            let (signal, value) = match change.split_once('=') {
+                Some((signal, value)) => match parse_value(value.trim()) {
+                    Some(value) => (signal.trim(), Some(value)),
+                    None => {
+                        eprintln!("{}", Colour::Red.paint(format!("invalid value in --tamper {}", change)));
+                        return Result::Err(());
+                    }
+                },
+                None => (change.trim(), None),
+            };
+            signals.push((signal.to_string(), value));
+        }
+        Result::Ok(Some(Tampering::Signals(signals)))
+    }
+
+    fn parse_value(value: &str) -> Option<BigInt> {
+        let (negative, value) = match value.strip_prefix('-') {
+            Some(value) => (true, value),
+            None => (false, value),
+        };
+        let (digits, radix) = match value.strip_prefix("0x").or_else(|| value.strip_prefix("0X")) {
+            Some(hex) => (hex, 16),
+            None => (value, 10),
+        };
+        if digits.is_empty() || digits.starts_with('-') || digits.starts_with('+') {
+            return None;
+        }
+        let value = BigInt::parse_bytes(digits.as_bytes(), radix)?;
+        Some(if negative { -value } else { value })
+    }
+
+    pub fn get_paths(matches: &ArgMatches) -> Vec<PathBuf> {
+        matches.values_of("paths").unwrap().map(PathBuf::from).collect()
+    }
+
     pub fn get_link_libraries(matches: &ArgMatches) -> Vec<PathBuf> {
         let mut link_libraries = Vec::new();
         let m = matches.values_of("link_libraries");
@@ -1,14 +1,16 @@
 mod compilation_user;
 mod execution_user;
+mod format_user;
 mod input_user;
 mod parser_user;
+mod test_user;
 mod type_analysis_user;
 
 const VERSION: &'static str = env!("CARGO_PKG_VERSION");
 
 
 use ansi_term::Colour;
-use input_user::Input;
+use input_user::{Command, Input};
 fn main() {
     let result = start();
     if result.is_err() {
@@ -21,9 +23,16 @@ fn main() {
 }
 
 fn start() -> Result<(), ()> {
+    match input_user::get_command()? {
+        Command::Compile(user_input) => compile(*user_input),
+        Command::Format(format_input) => format_user::format_files(&format_input),
+        Command::Test(test_input) => test_user::run_tests(&test_input),
+    }
+}
+
+fn compile(user_input: Input) -> Result<(), ()> {
     use compilation_user::CompilerConfig;
     use execution_user::ExecutionConfig;
-    let user_input = Input::new()?;
     let mut program_archive = parser_user::parse_project(&user_input)?;
     type_analysis_user::analyse_project(&mut program_archive)?;
 
@@ -33,23 +42,31 @@ fn start() -> Result<(), ()> {
         flag_s: user_input.reduced_simplification_flag(),
         flag_f: user_input.unsimplified_flag(),
         flag_old_heuristics: user_input.flag_old_heuristics(),
+        strategy: user_input.strategy(),
+        keep: user_input.keep().to_vec(),
+        public_layout: user_input.public_layout().to_vec(),
         flag_verbose: user_input.flag_verbose(),
         inspect_constraints_flag: user_input.inspect_constraints_flag(),
         r1cs_flag: user_input.r1cs_flag(),
         json_constraint_flag: user_input.json_constraints_flag(),
         json_substitution_flag: user_input.json_substitutions_flag(),
+        provenance_flag: user_input.provenance_flag(),
         sym_flag: user_input.sym_flag(),
         sym: user_input.sym_file().to_string(),
         r1cs: user_input.r1cs_file().to_string(),
         json_constraints: user_input.json_constraints_file().to_string(),
         json_substitutions: user_input.json_substitutions_file().to_string(),
         prime: user_input.prime(),        
+        statistics: user_input.statistics(),
+        statistics_file: user_input.statistics_file().to_string(),
+        provenance: user_input.provenance_file().to_string(),
     };
     let circuit = execution_user::execute_project(program_archive, config)?;
     let compilation_config = CompilerConfig {
         vcp: circuit,
         debug_output: user_input.print_ir_flag(),
         c_flag: user_input.c_flag(),
+        llvm_flag: user_input.llvm_flag(),
         wasm_flag: user_input.wasm_flag(),
         wat_flag: user_input.wat_flag(),
 	    js_folder: user_input.js_folder().to_string(),
@@ -58,9 +75,14 @@ fn start() -> Result<(), ()> {
 	    c_run_name: user_input.c_run_name().to_string(),
         c_file: user_input.c_file().to_string(),
         dat_file: user_input.dat_file().to_string(),
+        llvm_folder: user_input.llvm_folder().to_string(),
+        llvm_run_name: user_input.llvm_run_name(),
+        llvm_file: user_input.llvm_file().to_string(),
         wat_file: user_input.wat_file().to_string(),
         wasm_file: user_input.wasm_file().to_string(),
         produce_input_log: user_input.main_inputs_flag(),
+        profile_witness: user_input.profile_witness_flag(),
+        witness_optimization: user_input.witness_optimization(),
     };
     compilation_user::compile(compilation_config)?;
     Result::Ok(())
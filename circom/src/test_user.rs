@@ -0,0 +1,218 @@
+use super::input_user::TestInput;
+use ansi_term::Colour;
+use compiler::compiler_interface::{self, Circuit, Config};
+use dag::{Tampering, DAG};
+use parser::TestCase;
+use program_structure::error_definition::{Report, ReportCollection};
+use program_structure::file_definition::FileLibrary;
+use program_structure::program_archive::ProgramArchive;
+use type_analysis::check_types::check_types;
+use crate::VERSION;
+
+pub fn run_tests(input: &TestInput) -> Result<(), ()> {
+    let file = input.input_program.to_str().unwrap().to_string();
+    let (tests, file_library, warnings) =
+        parser::run_parser_for_tests(file, VERSION, input.link_libraries.clone()).map_err(|(library, reports)| {
+            Report::print_reports(&reports, &library);
+        })?;
+    Report::print_reports(&warnings, &file_library);
+    let tests: Vec<TestCase> = tests
+        .into_iter()
+        .filter(|test| input.filter.as_ref().is_none_or(|filter| test.name.contains(filter.as_str())))
+        .collect();
+    println!("running {} tests", tests.len());
+    let mut failed = Vec::new();
+    let total = tests.len();
+    for test in tests {
+        let location = test_location(&test, &file_library);
+        println!("{} test \"{}\" ({})", Colour::Cyan.paint("RUN"), test.name, location);
+        if run_test(test.program, &input.prime, input.tampering.as_ref()) {
+            println!("{} test \"{}\"", Colour::Green.paint("ok"), test.name);
+        } else {
+            println!("{} test \"{}\" ({})", Colour::Red.paint("FAILED"), test.name, location);
+            failed.push((test.name, location));
+        }
+    }
+    println!();
+    if !failed.is_empty() {
+        println!("failures:");
+        for (name, location) in &failed {
+            println!("    test \"{}\" ({})", name, location);
+        }
+        println!();
+    }
+    let result = if failed.is_empty() { Colour::Green.paint("ok") } else { Colour::Red.paint("FAILED") };
+    println!("test result: {}. {} passed; {} failed", result, total - failed.len(), failed.len());
+    if failed.is_empty() {
+        Result::Ok(())
+    } else {
+        Result::Err(())
+    }
+}
+
+// A test passes when its circuit is built, the witness is computed without
+// failing any assert and every constraint holds for the witness. Only the
+// errors are shown, the warnings are the ones of compiling the circuit.
+// With tampering, the constraints must also reject every changed signal.
+fn run_test(
+    program: Result<(ProgramArchive, ReportCollection), (FileLibrary, ReportCollection)>,
+    prime: &String,
+    tampering: Option<&Tampering>,
+) -> bool {
+    let (dag, circuit) = match build_test(program, prime) {
+        Some(built) => built,
+        None => return false,
+    };
+    let run = compiler_interface::compute_witness(&circuit);
+    for log in &run.logs {
+        println!("{}", log);
+    }
+    let witness = match run.result {
+        Result::Ok(witness) => witness,
+        Result::Err(failure) => {
+            eprintln!("{}", Colour::Red.paint(failure.message));
+            if let Some(location) = failure.location {
+                eprintln!("  at {}", location.trim_start_matches('"').replacen("\":", ":", 1));
+            }
+            for (signal, value) in failure.values {
+                eprintln!("  {} = {}", signal, value);
+            }
+            return false;
+        }
+    };
+    let unsatisfied = dag.check_witness(&witness);
+    for constraint in &unsatisfied {
+        eprintln!(
+            "{} {} ({}): {}",
+            Colour::Red.paint("Constraint not satisfied in"),
+            constraint.component,
+            constraint.template,
+            constraint.constraint
+        );
+    }
+    if !unsatisfied.is_empty() {
+        return false;
+    }
+    let tampering = match tampering {
+        Some(tampering) => tampering,
+        None => return true,
+    };
+    let under_constrained = match dag.find_under_constrained_signals(&witness, tampering) {
+        Result::Ok(under_constrained) => under_constrained,
+        Result::Err(signal) => {
+            eprintln!("{}", Colour::Red.paint(format!("The signal {} does not exist in the test", signal)));
+            return false;
+        }
+    };
+    if !under_constrained.is_empty() {
+        eprintln!("{}", Colour::Red.paint("Under-constrained signals, the constraints hold when they change:"));
+        for signal in &under_constrained {
+            eprintln!("  {} = {} (computed {})", signal.signal, signal.value, signal.computed);
+        }
+    }
+    under_constrained.is_empty()
+}
+
+// The constraints and the code of the circuit of a test
+fn build_test(
+    program: Result<(ProgramArchive, ReportCollection), (FileLibrary, ReportCollection)>,
+    prime: &String,
+) -> Option<(DAG, Circuit)> {
+    let mut program_archive = match program {
+        Result::Ok((program_archive, _)) => program_archive,
+        Result::Err((library, reports)) => {
+            Report::print_reports(&reports, &library);
+            return None;
+        }
+    };
+    if let Err(errors) = check_types(&mut program_archive) {
+        Report::print_reports(&errors, program_archive.get_file_library());
+        return None;
+    }
+    let (dag, vcp) = match constraint_generation::build_test_circuit(program_archive, prime) {
+        Result::Ok(built) => built,
+        Result::Err(()) => return None,
+    };
+    let config = Config {
+        debug_output: false,
+        produce_input_log: false,
+        wat_flag: false,
+        profile_witness: false,
+        witness_optimization: 0,
+    };
+    let circuit = match compiler_interface::run_compiler(vcp, config, VERSION) {
+        Result::Ok(circuit) => circuit,
+        Result::Err(()) => return None,
+    };
+    Some((dag, circuit))
+}
+
+fn test_location(test: &TestCase, file_library: &FileLibrary) -> String {
+    let file = file_library.get_file_name(test.file_id).unwrap_or_default();
+    let line = file_library.get_line(test.location.start, test.file_id).unwrap_or(0);
+    let column = file_library.get_column(test.location.start, test.file_id).unwrap_or(0);
+    format!("{}:{}:{}", file.trim_matches('"'), line, column)
+}
+
+#[cfg(test)]
+mod test {
+    use super::run_test;
+    use compiler::num_bigint::BigInt;
+    use dag::Tampering;
+    use std::path::PathBuf;
+
+    const PRIME: &str = "bn128";
+
+    fn tests(file: &str) -> Vec<parser::TestCase> {
+        let mut path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        path.push(file);
+        let (tests, _, _) =
+            parser::run_parser_for_tests(path.to_str().unwrap().to_string(), crate::VERSION, vec![]).ok().unwrap();
+        tests
+    }
+
+    // The name of every test of the file and whether it passes
+    fn results(file: &str, tampering: Option<&Tampering>) -> Vec<(String, bool)> {
+        tests(file).into_iter().map(|test| (test.name, run_test(test.program, &PRIME.to_string(), tampering))).collect()
+    }
+
+    fn expected(results: &[(&str, bool)]) -> Vec<(String, bool)> {
+        results.iter().map(|(name, passes)| (name.to_string(), *passes)).collect()
+    }
+
+    #[test]
+    fn tests_fail_on_failed_asserts_and_constraints() {
+        let results = results("tests/fixtures/failures.circom", None);
+        let expected = expected(&[
+            ("passes", true),
+            ("failed assert", false),
+            ("failed constraint", false),
+            ("free", true),
+            ("is zero", true),
+        ]);
+        assert_eq!(results, expected);
+    }
+
+    #[test]
+    fn soundness_finds_the_under_constrained_signals() {
+        let results = results("tests/fixtures/failures.circom", Some(&Tampering::AllSignals));
+        let expected = expected(&[
+            ("passes", true),
+            ("failed assert", false),
+            ("failed constraint", false),
+            ("free", false),
+            ("is zero", true),
+        ]);
+        assert_eq!(results, expected);
+        // without in * out === 0, the output 1 is accepted for the input 5
+        let tampering = Tampering::Signals(vec![
+            ("z.out".to_string(), Some(BigInt::from(1))),
+            ("z.inv".to_string(), Some(BigInt::from(0))),
+        ]);
+        let is_zero = tests("tests/fixtures/failures.circom").pop().unwrap();
+        assert!(!run_test(is_zero.program, &PRIME.to_string(), Some(&tampering)));
+        let missing = Tampering::Signals(vec![("z.missing".to_string(), None)]);
+        let is_zero = tests("tests/fixtures/failures.circom").pop().unwrap();
+        assert!(!run_test(is_zero.program, &PRIME.to_string(), Some(&missing)));
+    }
+}
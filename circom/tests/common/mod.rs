@@ -0,0 +1,146 @@
+// Builds the circuits of the tests of a fixture as `circom test` does, and
+// runs the witness generators produced for them. The generators need node,
+// make, llc, cc, g++ and the GMP and nlohmann/json headers, so the tests that
+// run them are ignored by default: cargo test -p circom -- --ignored
+#![allow(dead_code)]
+
+use compiler::compiler_interface::{self, Circuit, Config};
+use compiler::num_bigint::{BigInt, Sign};
+use compiler::num_traits::ToPrimitive;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use type_analysis::check_types::check_types;
+
+const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+pub const PRIME: &str = "bn128";
+
+pub fn tests(file: &str) -> Vec<parser::TestCase> {
+    let mut path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    path.push(file);
+    let (tests, _, _) = parser::run_parser_for_tests(path.to_str().unwrap().to_string(), VERSION, vec![]).ok().unwrap();
+    tests
+}
+
+pub fn config() -> Config {
+    Config {
+        debug_output: false,
+        produce_input_log: false,
+        wat_flag: false,
+        profile_witness: false,
+        witness_optimization: 0,
+    }
+}
+
+// The code of the circuit of a test
+pub fn build(test: parser::TestCase, prime: &str, config: Config) -> Circuit {
+    let (mut program_archive, _) = test.program.ok().unwrap();
+    assert!(check_types(&mut program_archive).is_ok(), "{}", test.name);
+    let (_, vcp) = constraint_generation::build_test_circuit(program_archive, &prime.to_string()).unwrap();
+    compiler_interface::run_compiler(vcp, config, VERSION).unwrap()
+}
+
+// The witness computed by the interpreter of `circom test`, in the order of
+// the witness list and reduced as in the .wtns files
+pub fn interpreted(circuit: &Circuit, prime: &BigInt) -> Vec<BigInt> {
+    let witness = compiler_interface::compute_witness(circuit).result.ok().unwrap();
+    circuit.wasm_producer.get_witness_to_signal_list().iter().map(|signal| witness[*signal].clone() % prime).collect()
+}
+
+fn folder(name: &str, backend: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("{}_{}_{}", name, std::process::id(), backend))
+}
+
+// The witness computed by the WebAssembly code of the circuit, in the order
+// of the witness list
+pub fn wasm_witness(circuit: &Circuit, name: &str) -> Vec<BigInt> {
+    let run = run_wasm(circuit, name);
+    assert!(run.is_ok(), "{}", run.err().unwrap());
+    run.ok().unwrap().0
+}
+
+// The witness computed by the WebAssembly code with the profile written
+// when the circuit is compiled with --profile-witness, or the output of a
+// failed run
+pub fn run_wasm(circuit: &Circuit, name: &str) -> Result<(Vec<BigInt>, Option<String>), String> {
+    let folder = folder(name, "js");
+    let wat = folder.join(format!("{}.wat", name));
+    let wasm = folder.join(format!("{}.wasm", name));
+    let input = folder.join("input.json");
+    let wtns = folder.join("witness.wtns");
+    compiler_interface::write_wasm(circuit, folder.to_str().unwrap(), name, wat.to_str().unwrap()).unwrap();
+    wat_to_wasm(&wat, &wasm);
+    std::fs::write(&input, "{}").unwrap();
+    let output = Command::new("node")
+        .arg(folder.join("generate_witness.js"))
+        .arg(&wasm)
+        .arg(&input)
+        .arg(&wtns)
+        .output()
+        .expect("node runs the WebAssembly witness generator");
+    let run = if output.status.success() {
+        let profile = std::fs::read_to_string(folder.join("witness.wtns.profile.json")).ok();
+        Result::Ok((wtns_values(&std::fs::read(&wtns).unwrap()), profile))
+    } else {
+        // the values of the signals of a failed check are logged to stdout
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        Result::Err(format!("{}{}", String::from_utf8_lossy(&output.stderr), stdout))
+    };
+    std::fs::remove_dir_all(&folder).unwrap();
+    run
+}
+
+fn wat_to_wasm(wat: &Path, wasm: &Path) {
+    use wast::parser::{self, ParseBuffer};
+    use wast::Wat;
+    let contents = std::fs::read_to_string(wat).unwrap();
+    let buffer = ParseBuffer::new(&contents).unwrap();
+    let mut module = parser::parse::<Wat>(&buffer).unwrap();
+    std::fs::write(wasm, module.module.encode().unwrap()).unwrap();
+}
+
+// The witness computed by the executable built from the LLVM IR of the
+// circuit, in the order of the witness list
+pub fn llvm_witness(circuit: &Circuit, name: &str) -> Vec<BigInt> {
+    let folder = folder(name, "llvm");
+    let ll = folder.join(format!("{}.ll", name));
+    compiler_interface::write_llvm_ir(circuit, folder.to_str().unwrap(), name, ll.to_str().unwrap()).unwrap();
+    run_executable(&folder, name)
+}
+
+// The witness computed by the executable built from the C++ code of the
+// circuit, in the order of the witness list
+pub fn c_witness(circuit: &Circuit, name: &str) -> Vec<BigInt> {
+    let folder = folder(name, "cpp");
+    let cpp = folder.join(format!("{}.cpp", name));
+    let dat = folder.join(format!("{}.dat", name));
+    compiler_interface::write_c(circuit, folder.to_str().unwrap(), name, cpp.to_str().unwrap(), dat.to_str().unwrap())
+        .unwrap();
+    run_executable(&folder, name)
+}
+
+// Builds the executable of the folder with its makefile and runs it
+fn run_executable(folder: &Path, name: &str) -> Vec<BigInt> {
+    let input = folder.join("input.json");
+    let wtns = folder.join("witness.wtns");
+    std::fs::write(&input, "{}").unwrap();
+    let built = Command::new("make").arg("-s").arg("-C").arg(folder).output().expect("make builds the witness generator");
+    assert!(built.status.success(), "{}", String::from_utf8_lossy(&built.stderr));
+    let status = Command::new(folder.join(name)).arg(&input).arg(&wtns).status().unwrap();
+    assert!(status.success());
+    let bytes = std::fs::read(&wtns).unwrap();
+    std::fs::remove_dir_all(folder).unwrap();
+    wtns_values(&bytes)
+}
+
+// The values of the witness section of a .wtns file
+fn wtns_values(bytes: &[u8]) -> Vec<BigInt> {
+    let number = |at: usize, size: usize| BigInt::from_bytes_le(Sign::Plus, &bytes[at..at + size]);
+    let length = |at: usize, size: usize| number(at, size).to_usize().unwrap();
+    // the header section gives the size of the field elements, and the
+    // witness section comes after it
+    let n8 = length(24, 4);
+    let witness_start = 24 + length(16, 8) + 12;
+    let witness_size = length(witness_start - 8, 8);
+    (0..witness_size / n8).map(|i| number(witness_start + i * n8, n8)).collect()
+}
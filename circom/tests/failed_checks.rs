@@ -0,0 +1,24 @@
+mod common;
+
+use common::{build, config, run_wasm, tests, PRIME};
+
+#[test]
+#[ignore = "needs node"]
+fn failed_checks_report_their_location_and_signals() {
+    // the checked expression as it is written and the value of every signal
+    // in it
+    let failures = [
+        ("failed assert", "failures.circom:30:5: out == 10", "out = 9"),
+        ("failed constraint", "failures.circom:36:5: s.out === 10", "s.out = 9"),
+    ];
+    for (index, test) in tests("tests/fixtures/failures.circom").into_iter().enumerate() {
+        let (location, value) = match failures.iter().find(|(name, _, _)| *name == test.name) {
+            Some((_, location, value)) => (location, value),
+            None => continue,
+        };
+        let circuit = build(test, PRIME, config());
+        let output = run_wasm(&circuit, &format!("failure{}", index)).err().unwrap();
+        assert!(output.contains(location), "{}", output);
+        assert!(output.lines().any(|line| line == *value), "{}", output);
+    }
+}
@@ -0,0 +1,47 @@
+pragma circom 2.1.0;
+
+template Square() {
+    signal input in;
+    signal output out;
+    out <== in * in;
+}
+
+template Free() {
+    signal input in;
+    signal output out;
+    out <-- in * 2;
+}
+
+template IsZero() {
+    signal input in;
+    signal output out;
+    signal inv;
+    inv <-- in != 0 ? 1 / in : 0;
+    out <== -in * inv + 1;
+}
+
+test "passes" {
+    signal out <== Square()(3);
+    assert(out == 9);
+}
+
+test "failed assert" {
+    signal out <== Square()(3);
+    assert(out == 10);
+}
+
+test "failed constraint" {
+    component s = Square();
+    s.in <== 3;
+    s.out === 10;
+}
+
+test "free" {
+    component f = Free();
+    f.in <== 3;
+}
+
+test "is zero" {
+    component z = IsZero();
+    z.in <== 5;
+}
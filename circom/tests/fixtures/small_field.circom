@@ -0,0 +1,17 @@
+pragma circom 2.1.0;
+
+// The values wrap around a field of a few bits
+template Inverse() {
+    signal input in;
+    signal output out;
+    out <-- 1 / in;
+    out * in === 1;
+}
+
+test "wrapping arithmetic" {
+    signal a <== 5 * 6;
+    signal b <== a - 3;
+    signal c <== Inverse()(b);
+    signal d <== c * c + a;
+    signal e <-- b \ 2;
+}
@@ -0,0 +1,126 @@
+pragma circom 2.1.0;
+
+function sum(n, values) {
+    if (n == 0) {
+        return 0;
+    }
+    return values[n - 1] + sum(n - 1, values);
+}
+
+function powers(x) {
+    var result[4] = [1, x, x * x, x * x * x];
+    return result;
+}
+
+template Square() {
+    signal input in;
+    signal output out;
+    out <== in * in;
+}
+
+template Bits(n) {
+    signal input in;
+    signal output out[n];
+    var lc = 0;
+    var e2 = 1;
+    for (var i = 0; i < n; i++) {
+        out[i] <-- (in >> i) & 1;
+        out[i] * (out[i] - 1) === 0;
+        lc += out[i] * e2;
+        e2 = e2 + e2;
+    }
+    lc === in;
+}
+
+template Select(n) {
+    signal input in[n];
+    signal input index;
+    signal output out;
+    var value = 0;
+    for (var i = 0; i < n; i++) {
+        if (i % 2 == 0) {
+            value += in[i] * (index == i ? 1 : 0);
+        } else {
+            value += in[i] * (index == i ? 1 : 0);
+        }
+    }
+    out <-- value;
+}
+
+test "loops and calls" {
+    signal values[4];
+    var p[4] = powers(3);
+    for (var i = 0; i < 4; i++) {
+        values[i] <== p[i];
+    }
+    signal total <== sum(4, p) + 1;
+    assert(total == 41);
+}
+
+test "arrays of components" {
+    component squares[3];
+    signal out[3];
+    for (var i = 0; i < 3; i++) {
+        squares[i] = Square();
+        squares[i].in <== i + 2;
+        out[i] <== squares[i].out;
+    }
+    component bits = Bits(8);
+    bits.in <== out[2];
+    assert(bits.out[4] == 1);
+}
+
+test "branches and anonymous components" {
+    component select = Select(4);
+    select.in <== [5, 6, 7, 8];
+    select.index <== 2;
+    signal q <== Square()(select.out);
+    signal r <== q - 40;
+    assert(r == 9);
+}
+
+function step(i, x) {
+    if (i % 3 == 0) {
+        return x * x + i;
+    } else if (i % 3 == 1) {
+        return x + i;
+    }
+    return x - i;
+}
+
+function power(x, n) {
+    if (n == 0) {
+        return 1;
+    }
+    return x * power(x, n - 1);
+}
+
+template Poly(n) {
+    signal input in;
+    signal output out;
+    signal t[n];
+    var acc = 1;
+    t[0] <== in * power(2, n);
+    for (var i = 1; i < n; i++) {
+        acc = step(i, acc);
+        if (i < 3) {
+            t[i] <== t[i - 1] * in + acc;
+        } else {
+            t[i] <== t[i - 1] + power(i, 2);
+        }
+    }
+    var folded = 0;
+    var j = 0;
+    while (j < n) {
+        folded = step(j, folded) + power(in, j % 2);
+        j += 1;
+    }
+    out <-- folded + t[n - 1];
+}
+
+test "loops branches and calls on signals" {
+    component p = Poly(5);
+    p.in <== 7;
+    signal r <== p.out;
+    assert(r > 0);
+}
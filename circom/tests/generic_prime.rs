@@ -0,0 +1,46 @@
+mod common;
+
+use common::{build, c_witness, config, interpreted, llvm_witness, tests, wasm_witness};
+use compiler::compiler_interface::Circuit;
+use compiler::num_bigint::BigInt;
+use program_structure::constants::UsefulConstants;
+
+// The primes without a hand written field implementation use the generic
+// field code of every backend: a prime below 2^24, whose field elements take
+// less bytes than a limb, a prime of 31 bits and 2^255 - 19
+const PRIMES: [(&str, &str); 3] = [
+    ("7", "tests/fixtures/small_field.circom"),
+    ("2013265921", "tests/fixtures/witness.circom"),
+    ("57896044618658097711785492504343953926634992332820282019728792003956564819949", "tests/fixtures/witness.circom"),
+];
+
+#[test]
+#[ignore = "needs node"]
+fn generic_prime_wasm_witness_is_the_interpreted_witness() {
+    check_witnesses("wasm", wasm_witness);
+}
+
+#[test]
+#[ignore = "needs g++, make and the GMP and nlohmann/json headers"]
+fn generic_prime_c_witness_is_the_interpreted_witness() {
+    check_witnesses("cpp", c_witness);
+}
+
+#[test]
+#[ignore = "needs llc, cc and make"]
+fn generic_prime_llvm_witness_is_the_interpreted_witness() {
+    check_witnesses("llvm", llvm_witness);
+}
+
+fn check_witnesses(backend: &str, witness: impl Fn(&Circuit, &str) -> Vec<BigInt>) {
+    for (prime_index, (prime, fixture)) in PRIMES.iter().enumerate() {
+        let value = UsefulConstants::new(prime).get_p().clone();
+        for (index, test) in tests(fixture).into_iter().enumerate() {
+            let name = test.name.clone();
+            let circuit = build(test, prime, config());
+            let expected = interpreted(&circuit, &value);
+            let generated = witness(&circuit, &format!("generic_{}{}_{}", backend, prime_index, index));
+            assert_eq!(generated, expected, "{} in {} with p = {}", name, backend, prime);
+        }
+    }
+}
@@ -0,0 +1,18 @@
+mod common;
+
+use common::{build, config, interpreted, tests, wasm_witness, PRIME};
+use program_structure::constants::UsefulConstants;
+
+// `circom test` computes the witness with an interpreter of the code of the
+// circuit instead of its WebAssembly code
+#[test]
+#[ignore = "needs node"]
+fn interpreted_witness_is_the_wasm_witness() {
+    let prime = UsefulConstants::new(PRIME).get_p().clone();
+    for (index, test) in tests("tests/fixtures/witness.circom").into_iter().enumerate() {
+        let name = test.name.clone();
+        let circuit = build(test, PRIME, config());
+        let expected = interpreted(&circuit, &prime);
+        assert_eq!(wasm_witness(&circuit, &format!("test{}", index)), expected, "{}", name);
+    }
+}
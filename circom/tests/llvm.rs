@@ -0,0 +1,16 @@
+mod common;
+
+use common::{build, config, interpreted, llvm_witness, tests, PRIME};
+use program_structure::constants::UsefulConstants;
+
+#[test]
+#[ignore = "needs llc, cc and make"]
+fn llvm_witness_is_the_interpreted_witness() {
+    let prime = UsefulConstants::new(PRIME).get_p().clone();
+    for (index, test) in tests("tests/fixtures/witness.circom").into_iter().enumerate() {
+        let name = test.name.clone();
+        let circuit = build(test, PRIME, config());
+        let expected = interpreted(&circuit, &prime);
+        assert_eq!(llvm_witness(&circuit, &format!("llvm{}", index)), expected, "{}", name);
+    }
+}
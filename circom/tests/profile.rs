@@ -0,0 +1,50 @@
+mod common;
+
+use common::{build, config, run_wasm, tests, PRIME};
+use compiler::compiler_interface::Config;
+
+#[test]
+#[ignore = "needs node"]
+fn profile_counts_the_template_runs_and_function_calls() {
+    // the calls of every template instance and function that is run,
+    // the ones evaluated during the compilation are not part of the code
+    let expected: [&[(&str, &str, usize)]; 4] = [
+        &[("templates", "test__loops_and_calls__0", 1), ("functions", "powers_0", 1)],
+        &[
+            ("templates", "Square_0", 3),
+            ("templates", "Bits_1", 1),
+            ("templates", "test__arrays_of_components__2", 1),
+        ],
+        &[
+            ("templates", "Select_0", 1),
+            ("templates", "Square_1", 1),
+            ("templates", "test__branches_and_anonymous_components__2", 1),
+        ],
+        &[
+            ("templates", "Poly_0", 1),
+            ("templates", "test__loops_branches_and_calls_on_signals__1", 1),
+            ("functions", "step_0", 9),
+            ("functions", "power_1", 13),
+        ],
+    ];
+    let profiled = tests("tests/fixtures/witness.circom");
+    assert_eq!(profiled.len(), expected.len());
+    for (index, (test, expected)) in profiled.into_iter().zip(expected).enumerate() {
+        let name = test.name.clone();
+        let circuit = build(test, PRIME, Config { profile_witness: true, ..config() });
+        let (_, profile) = run_wasm(&circuit, &format!("profile{}", index)).ok().unwrap();
+        let profile = json::parse(&profile.unwrap()).unwrap();
+        let entries = profile["templates"].len() + profile["functions"].len();
+        assert_eq!(entries, expected.len(), "{}", name);
+        for (kind, header, calls) in expected {
+            let entry = &profile[*kind][*header];
+            assert_eq!(entry["calls"].as_usize(), Some(*calls), "{} in {}", header, name);
+            let time = entry["time_ns"].as_u64().unwrap();
+            assert!(entry["self_time_ns"].as_u64().unwrap() <= time, "{} in {}", header, name);
+        }
+    }
+    // without the flag the witness generator does not write a profile
+    let test = tests("tests/fixtures/witness.circom").swap_remove(0);
+    let circuit = build(test, PRIME, config());
+    assert!(run_wasm(&circuit, "unprofiled").ok().unwrap().1.is_none());
+}
@@ -0,0 +1,34 @@
+mod common;
+
+use common::{build, config, interpreted, tests, wasm_witness, PRIME};
+use compiler::compiler_interface::{Circuit, Config};
+use program_structure::constants::UsefulConstants;
+
+// The circuit of the index-th test of the fixture, with its code optimized
+// as with --Ow1 or --Ow2 when witness_optimization is 1 or 2
+fn optimized(index: usize, witness_optimization: usize) -> Circuit {
+    let test = tests("tests/fixtures/witness.circom").swap_remove(index);
+    build(test, PRIME, Config { witness_optimization, ..config() })
+}
+
+#[test]
+fn witness_does_not_depend_on_the_witness_optimization() {
+    let prime = UsefulConstants::new(PRIME).get_p().clone();
+    for (index, test) in tests("tests/fixtures/witness.circom").into_iter().enumerate() {
+        let unoptimized = interpreted(&optimized(index, 0), &prime);
+        assert_eq!(interpreted(&optimized(index, 1), &prime), unoptimized, "{} with --Ow1", test.name);
+        assert_eq!(interpreted(&optimized(index, 2), &prime), unoptimized, "{} with --Ow2", test.name);
+    }
+}
+
+// The WebAssembly code is generated from the optimized code
+#[test]
+#[ignore = "needs node"]
+fn wasm_witness_of_the_optimized_code_is_the_interpreted_witness() {
+    let prime = UsefulConstants::new(PRIME).get_p().clone();
+    for (index, test) in tests("tests/fixtures/witness.circom").into_iter().enumerate() {
+        let expected = interpreted(&optimized(index, 0), &prime);
+        let circuit = optimized(index, 2);
+        assert_eq!(wasm_witness(&circuit, &format!("optimized{}", index)), expected, "{}", test.name);
+    }
+}
@@ -3,6 +3,7 @@ use crate::num_traits::ToPrimitive;
 use crate::modular_arithmetic;
 use std::collections::{BTreeMap, HashMap, HashSet, LinkedList};
 use std::mem::replace;
+use std::sync::Arc;
 
 type C = crate::algebra::Constraint<usize>;
 type S = crate::algebra::Substitution<usize>;
@@ -24,6 +25,9 @@ fn debug_check_keys_in_order(tree: &BTreeMap<usize, S>) -> bool {
 struct SignalDefinition<'a> {
     deleted_symbols: HashSet<usize>,
     forbidden: &'a HashSet<usize>,
+    // occurrences of the signals in the non-linear constraints, when the
+    // signals are taken to add as few terms to them as possible
+    non_linear: Option<&'a HashMap<usize, usize>>,
 }
 
 impl<'a> SignalDefinition<'a> {
@@ -267,7 +271,10 @@ fn treat_constraint_3(
         if C::is_empty(&work) {
             break;
         }
-        let out = take_signal_3(signals, &work);
+        let out = match signals.non_linear {
+            Some(occurrences) => take_signal_terms(signals, occurrences, &work),
+            None => take_signal_3(signals, &work),
+        };
         if out.is_none() {
             LinkedList::push_back(lconst, work);
             break;
@@ -376,14 +383,38 @@ fn take_signal_3(signals: &SignalDefinition, constraint: &C) -> Option<usize> {
     ret
 }
 
+// Replacing a signal by the rest of the linear constraint adds its terms but
+// one to every non-linear constraint where the signal occurs, and removing the
+// linear constraint removes its terms. The signal is only taken when the terms
+// added are not more than the ones removed, and among those the one with the
+// fewest occurrences is taken.
+fn take_signal_terms(signals: &SignalDefinition, occurrences: &HashMap<usize, usize>, constraint: &C) -> Option<usize> {
+    let terms = constraint.c().len();
+    let mut ret: Option<(usize, usize)> = Option::None;
+    for k in constraint.c().keys() {
+        let ocurrences_k = occurrences.get(k).map_or(0, |o| *o);
+        if signals.can_be_taken(*k) && ocurrences_k * terms.saturating_sub(2) <= terms {
+            let better = ret.is_none_or(|(ocurrences_ret, signal)| {
+                ocurrences_k < ocurrences_ret || (ocurrences_k == ocurrences_ret && *k > signal)
+            });
+            if better {
+                ret = Some((ocurrences_k, *k));
+            }
+        }
+    }
+    ret.map(|(_, signal)| signal)
+}
+
 fn take_signal_4(signals: &SignalDefinition4, info_ocurrences: &SignalsInformation, constraint: &C) -> Option<usize> {
     let mut ret = Option::None;
     let mut ocurrences_ret: Option<usize> = Option::None;
+    // a deleted signal is taken first, the greatest one so the choice does not
+    // depend on the order of the keys
+    let mut deleted: Option<usize> = Option::None;
     for k in constraint.c().keys() {
         if signals.can_be_taken(*k) {
             if signals.is_deleted(*k) {
-                ret = Some(*k);
-                break;
+                deleted = Some(deleted.map_or(*k, |v| std::cmp::max(*k, v)));
             }
             else {
                 let new_ocurrences = info_ocurrences.signal_to_ocurrences.get(k).unwrap();
@@ -407,7 +438,7 @@ fn take_signal_4(signals: &SignalDefinition4, info_ocurrences: &SignalsInformati
             }
         }
     }
-    ret
+    deleted.or(ret)
 }
 
 
@@ -436,7 +467,7 @@ fn normalize_substitutions(substitutions: SHNotNormalized, field: &BigInt) -> SH
     tree
 }
 
-fn take_substitutions_to_be_applied<'a>(sh: &'a HashMap<usize, S>, subs: &S) -> Vec<&'a S> {
+fn take_substitutions_to_be_applied<'a>(sh: &'a SH, subs: &S) -> Vec<&'a S> {
     let mut to_be_applied = vec![];
     for s in subs.to().keys() {
         if let Option::Some(s) = sh.get(s) {
@@ -448,9 +479,9 @@ fn take_substitutions_to_be_applied<'a>(sh: &'a HashMap<usize, S>, subs: &S) ->
 }
 
 #[allow(dead_code)]
-fn create_nonoverlapping_substitutions(possible_overlap: SH, field: &BigInt) -> HashMap<usize, S> {
+fn create_nonoverlapping_substitutions(possible_overlap: SH, field: &BigInt) -> SH {
     debug_assert!(debug_check_keys_in_order(&possible_overlap));
-    let mut no_overlap = HashMap::with_capacity(possible_overlap.len());
+    let mut no_overlap = SH::new();
     for (s, mut substitution) in possible_overlap {
         let to_be_applied = take_substitutions_to_be_applied(&no_overlap, &substitution);
         for sub in to_be_applied {
@@ -458,14 +489,13 @@ fn create_nonoverlapping_substitutions(possible_overlap: SH, field: &BigInt) ->
         }
         no_overlap.insert(s, substitution);
     }
-    no_overlap.shrink_to_fit();
     no_overlap
 }
 
-fn create_nonoverlapping_substitutions_4(mut possible_overlap: SH, signals: &SignalDefinition4,field: &BigInt) -> HashMap<usize, S> {
+fn create_nonoverlapping_substitutions_4(mut possible_overlap: SH, signals: &SignalDefinition4,field: &BigInt) -> SH {
     debug_assert!(debug_check_keys_in_order(&possible_overlap));
 
-    let mut no_overlap = HashMap::with_capacity(possible_overlap.len());
+    let mut no_overlap = SH::new();
     for s in &signals.order_signals{
         let mut substitution = possible_overlap.remove(s).unwrap();
         let to_be_applied = take_substitutions_to_be_applied(&no_overlap, &substitution);
@@ -474,7 +504,6 @@ fn create_nonoverlapping_substitutions_4(mut possible_overlap: SH, signals: &Sig
         }
         no_overlap.insert(*s, substitution);
     }
-    no_overlap.shrink_to_fit();
     no_overlap
 }
 
@@ -526,12 +555,25 @@ pub fn build_encoded_fast_substitutions(fast_sub: LinkedList<S>) -> HashMap<usiz
     encoded
 }
 
+// How the signal removed with each linear constraint is chosen
+#[derive(Clone)]
+pub enum Heuristics {
+    // the greatest signal
+    Greatest,
+    // the signal with the fewest occurrences in the cluster, in the clusters
+    // with at least 350 constraints, and the greatest signal in the rest
+    Occurrences,
+    // the signal with the fewest occurrences in the non-linear constraints,
+    // given by the map, when it does not add terms to them
+    NonLinearTerms(Arc<HashMap<usize, usize>>),
+}
+
 pub struct Config<T> {
     pub field: BigInt,
     pub constraints: LinkedList<C>,
     pub forbidden: T,
     pub num_signals: usize,
-    pub use_old_heuristics: bool,
+    pub heuristics: Heuristics,
 }
 
 pub struct Simplified {
@@ -550,13 +592,18 @@ where
     let apply_less_ocurrences = 
         config.constraints.len() >= min && 
         config.constraints.len() < max && 
-        !config.use_old_heuristics;
+        matches!(config.heuristics, Heuristics::Occurrences);
+    let non_linear = match &config.heuristics {
+        Heuristics::NonLinearTerms(occurrences) => Some(occurrences.as_ref()),
+        _ => None,
+    };
 
     let field = config.field;
     let mut constraints = config.constraints;
     let mut holder = SHNotNormalized::new();
     let normalized_holder: SH;
-    let non_overlapping: HashMap<usize, S>;
+    // ordered by the signal, so the substitutions are always in the same order
+    let non_overlapping: SH;
 
     if apply_less_ocurrences{
         let mut signals = SignalDefinition4 { forbidden: config.forbidden.as_ref(), deleted_symbols: HashSet::new(),  order_signals: LinkedList::new() };
@@ -565,7 +612,7 @@ where
         non_overlapping = create_nonoverlapping_substitutions_4(normalized_holder, &signals, &field);
     }
     else{
-        let mut signals = SignalDefinition { forbidden: config.forbidden.as_ref(), deleted_symbols: HashSet::new() };
+        let mut signals = SignalDefinition { forbidden: config.forbidden.as_ref(), deleted_symbols: HashSet::new(), non_linear };
         substitution_process_3(&mut signals, &mut constraints, &mut holder, &field);
         normalized_holder = normalize_substitutions(holder, &field);
         non_overlapping = create_nonoverlapping_substitutions(normalized_holder, &field);
@@ -601,7 +648,7 @@ where
 
     // build the subs using the multi-inv and taking the bigger signal 
     let mut signals_3 =
-        SignalDefinition { forbidden: config.forbidden.as_ref(), deleted_symbols: HashSet::new() };
+        SignalDefinition { forbidden: config.forbidden.as_ref(), deleted_symbols: HashSet::new(), non_linear: None };
     let mut constraints_3 = config.constraints.clone();
     let mut holder_3 = SHNotNormalized::new();
     substitution_process_3(&mut signals_3, &mut constraints_3, &mut holder_3, &field);
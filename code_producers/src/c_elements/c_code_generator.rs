@@ -3,7 +3,7 @@ use num_bigint_dig::{BigInt, Sign};
 use serde_json::json;
 use std::fs::File;
 use std::io::prelude::*;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 // Types
 const T_U64: &str = "u64";
@@ -403,6 +403,67 @@ pub fn build_failed_assert_message(line: usize) -> String{
 }
 
 
+pub const ASSERT_HOLDS: &str = "assertHolds";
+pub const ASSERT_MESSAGE_TABLE: &str = "assertMessageTable";
+
+pub fn build_failed_assert_location(assert_id: usize) -> String {
+    let entry = format!("{}[{}]", ASSERT_MESSAGE_TABLE, assert_id);
+    format!("std::cout << \"  at \" << {0}.file << \":\" << {0}.line << \":\" << {0}.column << \": \" << {0}.expression << std::endl",
+        entry
+    )
+}
+
+pub fn generate_assert_message_table(message_list: &AssertMessageList) -> Vec<String> {
+    let mut instructions = vec![];
+    if message_list.is_empty() {
+        return instructions;
+    }
+    instructions.push(format!("Circom_AssertMessage {}[{}] = {{", ASSERT_MESSAGE_TABLE, message_list.len()));
+    let mut entries = vec![];
+    for message in message_list {
+        entries.push(format!(
+            "{{\"{}\", {}, {}, \"{}\"}}",
+            escape_string(&message.file),
+            message.line,
+            message.column,
+            escape_string(&message.expression)
+        ));
+    }
+    instructions.push(entries.join(",\n"));
+    instructions.push("};".to_string());
+    instructions
+}
+
+pub const PROFILE_TABLE: &str = "profileTable";
+pub const PROFILE_SCOPE: &str = "profileScope";
+
+pub fn declare_profile_scope(profile_id: usize) -> String {
+    format!("Circom_ProfileScope {}(&{}[{}])", PROFILE_SCOPE, PROFILE_TABLE, profile_id)
+}
+
+pub fn generate_profile_table(producer: &CProducer) -> Vec<String> {
+    let mut instructions = vec![];
+    if !producer.get_profile_witness() {
+        return instructions;
+    }
+    let profile_list = producer.get_profile_list();
+    // an array of size 0 is not valid C++, the table always has at least one entry
+    instructions.push(format!("Circom_ProfileEntry {}[{}] = {{", PROFILE_TABLE, profile_list.len().max(1)));
+    let mut entries = vec![];
+    for entry in profile_list {
+        entries.push(format!(
+            "{{\"{}\", \"{}\", \"{}\"}}",
+            entry.kind.as_str(),
+            escape_string(&entry.header),
+            escape_string(&entry.name)
+        ));
+    }
+    instructions.push(entries.join(",\n"));
+    instructions.push("};".to_string());
+    instructions.push(format!("uint get_size_of_profile_table() {{return {};}}\n", profile_list.len()));
+    instructions
+}
+
 pub fn build_conditional(
     cond: Vec<String>,
     if_body: Vec<String>,
@@ -816,7 +877,12 @@ pub fn generate_fr_hpp_file(c_folder: &PathBuf, prime: &String) -> std::io::Resu
         "pallas" => include_str!("pallas/fr.hpp"),
         "vesta" => include_str!("vesta/fr.hpp"),
         "secq256r1" => include_str!("secq256r1/fr.hpp"),
-        _ => unreachable!(),
+        _ => {
+            let code = render_generic_field_file(include_str!("generic/fr.hpp"), prime);
+            c_file.write_all(code.as_bytes())?;
+            c_file.flush()?;
+            return Ok(());
+        }
     };
     for line in file.lines() {
         code = format!("{}{}\n", code, line);
@@ -859,8 +925,7 @@ pub fn generate_fr_cpp_file(c_folder: &PathBuf, prime: &String) -> std::io::Resu
         "pallas" => include_str!("pallas/fr.cpp"),
         "vesta" => include_str!("vesta/fr.cpp"),
         "secq256r1" => include_str!("secq256r1/fr.cpp"),
-        
-        _ => unreachable!(),
+        _ => include_str!("generic/fr.cpp"),
     };
     for line in file.lines() {
         code = format!("{}{}\n", code, line);
@@ -913,6 +978,59 @@ pub fn generate_fr_asm_file(c_folder: &PathBuf, prime: &String) -> std::io::Resu
     Ok(())
 }
 
+// The named curves come with a hand written assembly implementation of the field
+pub fn has_asm_field(prime: &str) -> bool {
+    matches!(prime, "bn128" | "bls12381" | "goldilocks" | "grumpkin" | "pallas" | "vesta" | "secq256r1")
+}
+
+// For any other prime fr_raw.cpp replaces fr.asm, its constants are computed from the value of the prime
+pub fn generate_fr_raw_cpp_file(c_folder: &Path, prime: &str) -> std::io::Result<()> {
+    use std::io::BufWriter;
+    let mut c_file = BufWriter::new(File::create(c_folder.join("fr_raw.cpp"))?);
+    let code = render_generic_field_file(include_str!("generic/fr_raw.cpp"), prime);
+    c_file.write_all(code.as_bytes())?;
+    c_file.flush()?;
+    Ok(())
+}
+
+fn render_generic_field_file(file: &str, prime: &str) -> String {
+    let p = prime.parse::<BigInt>().unwrap();
+    let n64 = p.bits().div_ceil(64);
+    let r = BigInt::from(1) << (64 * n64);
+    let limbs = |value: &BigInt| -> Vec<u64> {
+        let (_, bytes) = value.to_bytes_le();
+        let mut limbs = vec![0u64; n64];
+        for (i, byte) in bytes.iter().enumerate() {
+            limbs[i / 8] |= (*byte as u64) << (8 * (i % 8));
+        }
+        limbs
+    };
+    let as_list = |value: &BigInt| -> String {
+        limbs(value).iter().map(|l| format!("0x{:016x}ULL", l)).collect::<Vec<_>>().join(", ")
+    };
+    let q0 = limbs(&p)[0];
+    // -q^(-1) mod 2^64 by Newton iteration, q is odd
+    let mut inverse: u64 = 1;
+    for _ in 0..6 {
+        inverse = inverse.wrapping_mul(2u64.wrapping_sub(q0.wrapping_mul(inverse)));
+    }
+    let template = handlebars::Handlebars::new();
+    template
+        .render_template(
+            file,
+            &json!({
+                "n64": n64,
+                "bits": p.bits(),
+                "prime": prime,
+                "q": as_list(&p),
+                "r2": as_list(&((&r * &r) % &p)),
+                "r3": as_list(&((&r * &r * &r) % &p)),
+                "np": format!("0x{:016x}ULL", inverse.wrapping_neg()),
+            }),
+        )
+        .expect("must render")
+}
+
 pub fn generate_make_file(
     c_folder: &PathBuf,
     run_name: &str,
@@ -929,6 +1047,8 @@ pub fn generate_make_file(
             &json!({
                 "run_name": run_name,
                 "has_parallelism": producer.has_parallelism,
+                "profile_witness": producer.get_profile_witness(),
+                "generic_field": !has_asm_field(&producer.prime_str),
             }),
         )
         .expect("must render");
@@ -112,6 +112,25 @@ std::string Circom_CalcWit::getTrace(u64 id_cmp){
 
 }
 
+thread_local Circom_ProfileScope* Circom_ProfileScope::current = NULL;
+
+Circom_ProfileScope::Circom_ProfileScope(Circom_ProfileEntry *anEntry) {
+  entry = anEntry;
+  parent = current;
+  childNanos = 0;
+  current = this;
+  start = std::chrono::steady_clock::now();
+}
+
+Circom_ProfileScope::~Circom_ProfileScope() {
+  u64 elapsed = std::chrono::duration_cast<std::chrono::nanoseconds>(std::chrono::steady_clock::now() - start).count();
+  entry->calls++;
+  entry->nanos += elapsed;
+  entry->selfNanos += elapsed > childNanos ? elapsed - childNanos : 0;
+  if (parent != NULL) parent->childNanos += elapsed;
+  current = parent;
+}
+
 std::string Circom_CalcWit::generate_position_array(uint* dimensions, uint size_dimensions, uint index){
   std::string positions = "";
 
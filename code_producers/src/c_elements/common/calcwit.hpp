@@ -6,6 +6,7 @@
 #include <functional>
 #include <atomic>
 #include <memory>
+#include <chrono>
 
 #include "circom.hpp"
 #include "fr.hpp"
@@ -64,6 +65,24 @@ private:
 
 };
 
+// Measures the time spent in a template run or a function call. The time spent in
+// nested scopes of the same thread is discounted from the self time of the parent.
+class Circom_ProfileScope {
+
+  Circom_ProfileEntry *entry;
+  Circom_ProfileScope *parent;
+  std::chrono::steady_clock::time_point start;
+  u64 childNanos;
+
+  static thread_local Circom_ProfileScope *current;
+
+public:
+
+  Circom_ProfileScope(Circom_ProfileEntry *anEntry);
+  ~Circom_ProfileScope();
+
+};
+
 typedef void (*Circom_TemplateFunction)(uint __cIdx, Circom_CalcWit* __ctx); 
 
 #endif // CIRCOM_CALCWIT_H
@@ -6,6 +6,7 @@
 #include <mutex>
 #include <condition_variable>
 #include <thread>
+#include <atomic>
 
 #include "fr.hpp"
 
@@ -55,6 +56,25 @@ struct Circom_Component {
   std::thread *sbct = NULL;//subcomponent threads
 };
 
+struct Circom_AssertMessage {
+  const char* file;
+  u32 line;
+  u32 column;
+  const char* expression;
+};
+
+// counters and timers of a template instance or function (only with --profile-witness)
+struct Circom_ProfileEntry {
+  const char* kind;
+  const char* header;
+  const char* name;
+  std::atomic<u64> calls;
+  std::atomic<u64> nanos;
+  std::atomic<u64> selfNanos;
+};
+
+extern Circom_ProfileEntry profileTable[];
+
 /*
 For every template instantiation create two functions:
 - name_create
@@ -81,5 +101,6 @@ uint get_size_of_input_hashmap();
 uint get_size_of_witness();
 uint get_size_of_constants();
 uint get_size_of_io_map();
+uint get_size_of_profile_table();
 
 #endif  // __CIRCOM_H
@@ -160,10 +160,48 @@ void json2FrElements (json val, std::vector<FrElement> & vval){
 }
 
 
+bool containsObject(json val) {
+  while (val.is_array()) {
+    if (val.empty()) return false;
+    val = val[0];
+  }
+  return val.is_object();
+}
+
+// Inputs that are buses are given as objects with the values of their fields,
+// or arrays of them, and are flattened into the signals of the fields (p.x, p[].x)
+void flatBus(std::string name, json &val, json &res) {
+  if (val.is_array() && containsObject(val)) {
+    std::vector<json> elements;
+    for (uint i = 0; i < val.size(); i++) {
+      json fields = json::object();
+      flatBus(name + "[]", val[i], fields);
+      elements.push_back(fields);
+    }
+    for (json::iterator it = elements[0].begin(); it != elements[0].end(); ++it) {
+      json values = json::array();
+      for (uint i = 0; i < elements.size(); i++) {
+        values.push_back(elements[i][it.key()]);
+      }
+      res[it.key()] = values;
+    }
+  } else if (val.is_object()) {
+    for (json::iterator it = val.begin(); it != val.end(); ++it) {
+      flatBus(name + "." + it.key(), it.value(), res);
+    }
+  } else {
+    res[name] = val;
+  }
+}
+
 void loadJson(Circom_CalcWit *ctx, std::string filename) {
   std::ifstream inStream(filename);
-  json j;
-  inStream >> j;
+  json input;
+  inStream >> input;
+  json j = json::object();
+  for (json::iterator it = input.begin(); it != input.end(); ++it) {
+    flatBus(it.key(), it.value(), j);
+  }
   
   u64 nItems = j.size();
   // printf("Items : %llu\n",nItems);
@@ -247,6 +285,25 @@ void writeBinWitness(Circom_CalcWit *ctx, std::string wtnsFileName) {
     fclose(write_ptr);
 }
 
+#ifdef CIRCOM_PROFILE
+void writeProfile(std::string profileFileName) {
+  json profile = {{"templates", json::object()}, {"functions", json::object()}};
+  for (uint i = 0; i < get_size_of_profile_table(); i++) {
+    Circom_ProfileEntry &entry = profileTable[i];
+    if (entry.calls == 0) continue;
+    std::string kind = std::string(entry.kind) + "s";
+    profile[kind][entry.header] = {
+      {"name", entry.name},
+      {"calls", entry.calls.load()},
+      {"time_ns", entry.nanos.load()},
+      {"self_time_ns", entry.selfNanos.load()}
+    };
+  }
+  std::ofstream profileFile(profileFileName);
+  profileFile << profile.dump(1) << std::endl;
+}
+#endif
+
 int main (int argc, char *argv[]) {
   std::string cl(argv[0]);
   if (argc!=3) {
@@ -279,6 +336,9 @@ int main (int argc, char *argv[]) {
    //std::cout << std::chrono::duration<double, std::milli>(t_mid-t_start).count()<<std::endl;
 
    writeBinWitness(ctx,wtnsfile);
+#ifdef CIRCOM_PROFILE
+   writeProfile(wtnsfile + ".profile.json");
+#endif
   
    //auto t_end = std::chrono::high_resolution_clock::now();
    //std::cout << std::chrono::duration<double, std::milli>(t_end-t_mid).count()<<std::endl;
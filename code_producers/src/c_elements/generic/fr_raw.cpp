@@ -0,0 +1,469 @@
+#include "fr.hpp"
+#include <string.h>
+
+// Portable replacement of fr.asm for a prime given by value.
+// q = {{prime}}
+
+#define Fr_BITS {{bits}}
+#define Fr_SHORTMONTGOMERY 0x40000000
+
+typedef unsigned __int128 Fr_u128;
+
+FrElement Fr_q = {0, Fr_LONG, { {{q}} }};
+FrRawElement Fr_rawq = { {{q}} };
+// R^2 mod q and R^3 mod q, with R = 2^(64*Fr_N64)
+static FrRawElement Fr_rawR2 = { {{r2}} };
+FrElement Fr_R3 = {0, Fr_LONG, { {{r3}} }};
+FrRawElement Fr_rawR3 = { {{r3}} };
+// -q^(-1) mod 2^64
+static const uint64_t Fr_np = {{np}};
+
+// Raw operations over Fr_N64 limbs. The result may alias any of the operands.
+
+static int raw_cmp(const uint64_t *a, const uint64_t *b) {
+    for (int i = Fr_N64 - 1; i >= 0; i--) {
+        if (a[i] != b[i]) return a[i] < b[i] ? -1 : 1;
+    }
+    return 0;
+}
+
+static uint64_t raw_add(uint64_t *r, const uint64_t *a, const uint64_t *b) {
+    Fr_u128 carry = 0;
+    for (int i = 0; i < Fr_N64; i++) {
+        carry += (Fr_u128)a[i] + b[i];
+        r[i] = (uint64_t)carry;
+        carry >>= 64;
+    }
+    return (uint64_t)carry;
+}
+
+static uint64_t raw_sub(uint64_t *r, const uint64_t *a, const uint64_t *b) {
+    uint64_t borrow = 0;
+    for (int i = 0; i < Fr_N64; i++) {
+        uint64_t ai = a[i];
+        uint64_t d = ai - b[i] - borrow;
+        borrow = (ai < b[i]) || (ai - b[i] < borrow);
+        r[i] = d;
+    }
+    return borrow;
+}
+
+static int raw_bits(const uint64_t *a) {
+    for (int i = Fr_N64 - 1; i >= 0; i--) {
+        if (a[i] != 0) return i * 64 + 64 - __builtin_clzll(a[i]);
+    }
+    return 0;
+}
+
+static void raw_shl(uint64_t *r, const uint64_t *a, int n) {
+    uint64_t t[Fr_N64];
+    int words = n / 64, bits = n % 64;
+    for (int i = Fr_N64 - 1; i >= 0; i--) {
+        uint64_t v = 0;
+        if (i - words >= 0) {
+            v = a[i - words] << bits;
+            if (bits != 0 && i - words - 1 >= 0) v |= a[i - words - 1] >> (64 - bits);
+        }
+        t[i] = v;
+    }
+    memcpy(r, t, sizeof(t));
+}
+
+static void raw_shr(uint64_t *r, const uint64_t *a, int n) {
+    uint64_t t[Fr_N64];
+    int words = n / 64, bits = n % 64;
+    for (int i = 0; i < Fr_N64; i++) {
+        uint64_t v = 0;
+        if (i + words < Fr_N64) {
+            v = a[i + words] >> bits;
+            if (bits != 0 && i + words + 1 < Fr_N64) v |= a[i + words + 1] << (64 - bits);
+        }
+        t[i] = v;
+    }
+    memcpy(r, t, sizeof(t));
+}
+
+// Brings a value below 2q back to [0, q)
+static void raw_reduce_once(uint64_t *r, uint64_t carry) {
+    if (carry || raw_cmp(r, Fr_rawq) >= 0) raw_sub(r, r, Fr_rawq);
+}
+
+static void raw_mask(uint64_t *r) {
+    for (int i = 0; i < Fr_N64; i++) {
+        int low = i * 64;
+        if (low >= Fr_BITS) r[i] = 0;
+        else if (Fr_BITS - low < 64) r[i] &= (((uint64_t)1) << (Fr_BITS - low)) - 1;
+    }
+}
+
+// Montgomery product a*b/R mod q (CIOS)
+static void raw_mont_mul(uint64_t *r, const uint64_t *a, const uint64_t *b) {
+    uint64_t t[Fr_N64 + 2];
+    memset(t, 0, sizeof(t));
+    for (int i = 0; i < Fr_N64; i++) {
+        Fr_u128 c = 0;
+        for (int j = 0; j < Fr_N64; j++) {
+            c += (Fr_u128)a[j] * b[i] + t[j];
+            t[j] = (uint64_t)c;
+            c >>= 64;
+        }
+        c += t[Fr_N64];
+        t[Fr_N64] = (uint64_t)c;
+        t[Fr_N64 + 1] = (uint64_t)(c >> 64);
+        uint64_t m = t[0] * Fr_np;
+        c = (Fr_u128)m * Fr_rawq[0] + t[0];
+        c >>= 64;
+        for (int j = 1; j < Fr_N64; j++) {
+            c += (Fr_u128)m * Fr_rawq[j] + t[j];
+            t[j - 1] = (uint64_t)c;
+            c >>= 64;
+        }
+        c += t[Fr_N64];
+        t[Fr_N64 - 1] = (uint64_t)c;
+        t[Fr_N64] = t[Fr_N64 + 1] + (uint64_t)(c >> 64);
+    }
+    raw_reduce_once(t, t[Fr_N64]);
+    memcpy(r, t, Fr_N64 * sizeof(uint64_t));
+}
+
+// Elements are read in standard form through their representative in [0, q)
+
+static void normal_value(uint64_t *r, PFrElement a) {
+    if (!(a->type & Fr_LONG)) {
+        uint64_t v[Fr_N64];
+        memset(v, 0, sizeof(v));
+        if (a->shortVal >= 0) {
+            v[0] = (uint64_t)a->shortVal;
+            memcpy(r, v, sizeof(v));
+        } else {
+            v[0] = (uint64_t)(-(int64_t)a->shortVal);
+            raw_sub(r, Fr_rawq, v);
+        }
+    } else if ((a->type & Fr_LONGMONTGOMERY) == Fr_LONGMONTGOMERY) {
+        uint64_t v[Fr_N64], one[Fr_N64];
+        memcpy(v, a->longVal, sizeof(v));
+        memset(one, 0, sizeof(one));
+        one[0] = 1;
+        raw_mont_mul(r, v, one);
+    } else {
+        memcpy(r, a->longVal, sizeof(FrRawElement));
+    }
+}
+
+static void set_long(PFrElement r, const uint64_t *v) {
+    r->shortVal = 0;
+    r->type = Fr_LONG;
+    memcpy(r->longVal, v, sizeof(FrRawElement));
+}
+
+static void set_short(PFrElement r, int value) {
+    r->shortVal = value;
+    r->type = Fr_SHORT;
+}
+
+// Elements above q/2 represent negative numbers
+static int is_negative(const uint64_t *a) {
+    uint64_t half[Fr_N64];
+    raw_shr(half, Fr_rawq, 1);
+    return raw_cmp(a, half) > 0;
+}
+
+static int lesser(const uint64_t *a, const uint64_t *b) {
+    int negative_a = is_negative(a), negative_b = is_negative(b);
+    if (negative_a != negative_b) return negative_a;
+    return raw_cmp(a, b) < 0;
+}
+
+// Amount of a shift that is known to be small and its direction
+static int shift_amount(const uint64_t *b, int *reversed) {
+    uint64_t n[Fr_N64];
+    *reversed = is_negative(b);
+    if (*reversed) raw_sub(n, Fr_rawq, b);
+    else memcpy(n, b, sizeof(n));
+    if (raw_bits(n) > 31 || n[0] > Fr_BITS) return Fr_BITS;
+    return (int)n[0];
+}
+
+static void shift_left(uint64_t *r, const uint64_t *a, int n) {
+    if (n >= Fr_BITS) {
+        memset(r, 0, sizeof(FrRawElement));
+        return;
+    }
+    raw_shl(r, a, n);
+    raw_mask(r);
+    raw_reduce_once(r, 0);
+}
+
+static void shift_right(uint64_t *r, const uint64_t *a, int n) {
+    if (n >= Fr_BITS) memset(r, 0, sizeof(FrRawElement));
+    else raw_shr(r, a, n);
+}
+
+void Fr_copy(PFrElement r, PFrElement a) {
+    memmove(r, a, sizeof(FrElement));
+}
+
+void Fr_copyn(PFrElement r, PFrElement a, int n) {
+    memmove(r, a, n * sizeof(FrElement));
+}
+
+void Fr_add(PFrElement r, PFrElement a, PFrElement b) {
+    uint64_t x[Fr_N64], y[Fr_N64];
+    normal_value(x, a);
+    normal_value(y, b);
+    uint64_t carry = raw_add(x, x, y);
+    raw_reduce_once(x, carry);
+    set_long(r, x);
+}
+
+void Fr_sub(PFrElement r, PFrElement a, PFrElement b) {
+    uint64_t x[Fr_N64], y[Fr_N64];
+    normal_value(x, a);
+    normal_value(y, b);
+    if (raw_sub(x, x, y)) raw_add(x, x, Fr_rawq);
+    set_long(r, x);
+}
+
+void Fr_neg(PFrElement r, PFrElement a) {
+    uint64_t x[Fr_N64];
+    normal_value(x, a);
+    if (raw_bits(x) != 0) raw_sub(x, Fr_rawq, x);
+    set_long(r, x);
+}
+
+void Fr_mul(PFrElement r, PFrElement a, PFrElement b) {
+    uint64_t x[Fr_N64], y[Fr_N64];
+    normal_value(x, a);
+    normal_value(y, b);
+    raw_mont_mul(x, x, y);
+    raw_mont_mul(x, x, Fr_rawR2);
+    set_long(r, x);
+}
+
+void Fr_square(PFrElement r, PFrElement a) {
+    Fr_mul(r, a, a);
+}
+
+void Fr_band(PFrElement r, PFrElement a, PFrElement b) {
+    uint64_t x[Fr_N64], y[Fr_N64];
+    normal_value(x, a);
+    normal_value(y, b);
+    for (int i = 0; i < Fr_N64; i++) x[i] &= y[i];
+    set_long(r, x);
+}
+
+void Fr_bor(PFrElement r, PFrElement a, PFrElement b) {
+    uint64_t x[Fr_N64], y[Fr_N64];
+    normal_value(x, a);
+    normal_value(y, b);
+    for (int i = 0; i < Fr_N64; i++) x[i] |= y[i];
+    raw_reduce_once(x, 0);
+    set_long(r, x);
+}
+
+void Fr_bxor(PFrElement r, PFrElement a, PFrElement b) {
+    uint64_t x[Fr_N64], y[Fr_N64];
+    normal_value(x, a);
+    normal_value(y, b);
+    for (int i = 0; i < Fr_N64; i++) x[i] ^= y[i];
+    raw_reduce_once(x, 0);
+    set_long(r, x);
+}
+
+void Fr_bnot(PFrElement r, PFrElement a) {
+    uint64_t x[Fr_N64];
+    normal_value(x, a);
+    for (int i = 0; i < Fr_N64; i++) x[i] = ~x[i];
+    raw_mask(x);
+    raw_reduce_once(x, 0);
+    set_long(r, x);
+}
+
+void Fr_shl(PFrElement r, PFrElement a, PFrElement b) {
+    uint64_t x[Fr_N64], y[Fr_N64];
+    int reversed;
+    normal_value(x, a);
+    normal_value(y, b);
+    int n = shift_amount(y, &reversed);
+    if (reversed) shift_right(x, x, n);
+    else shift_left(x, x, n);
+    set_long(r, x);
+}
+
+void Fr_shr(PFrElement r, PFrElement a, PFrElement b) {
+    uint64_t x[Fr_N64], y[Fr_N64];
+    int reversed;
+    normal_value(x, a);
+    normal_value(y, b);
+    int n = shift_amount(y, &reversed);
+    if (reversed) shift_left(x, x, n);
+    else shift_right(x, x, n);
+    set_long(r, x);
+}
+
+void Fr_eq(PFrElement r, PFrElement a, PFrElement b) {
+    uint64_t x[Fr_N64], y[Fr_N64];
+    normal_value(x, a);
+    normal_value(y, b);
+    set_short(r, raw_cmp(x, y) == 0);
+}
+
+void Fr_neq(PFrElement r, PFrElement a, PFrElement b) {
+    uint64_t x[Fr_N64], y[Fr_N64];
+    normal_value(x, a);
+    normal_value(y, b);
+    set_short(r, raw_cmp(x, y) != 0);
+}
+
+void Fr_lt(PFrElement r, PFrElement a, PFrElement b) {
+    uint64_t x[Fr_N64], y[Fr_N64];
+    normal_value(x, a);
+    normal_value(y, b);
+    set_short(r, lesser(x, y));
+}
+
+void Fr_gt(PFrElement r, PFrElement a, PFrElement b) {
+    uint64_t x[Fr_N64], y[Fr_N64];
+    normal_value(x, a);
+    normal_value(y, b);
+    set_short(r, lesser(y, x));
+}
+
+void Fr_leq(PFrElement r, PFrElement a, PFrElement b) {
+    uint64_t x[Fr_N64], y[Fr_N64];
+    normal_value(x, a);
+    normal_value(y, b);
+    set_short(r, !lesser(y, x));
+}
+
+void Fr_geq(PFrElement r, PFrElement a, PFrElement b) {
+    uint64_t x[Fr_N64], y[Fr_N64];
+    normal_value(x, a);
+    normal_value(y, b);
+    set_short(r, !lesser(x, y));
+}
+
+void Fr_land(PFrElement r, PFrElement a, PFrElement b) {
+    set_short(r, Fr_isTrue(a) && Fr_isTrue(b));
+}
+
+void Fr_lor(PFrElement r, PFrElement a, PFrElement b) {
+    set_short(r, Fr_isTrue(a) || Fr_isTrue(b));
+}
+
+void Fr_lnot(PFrElement r, PFrElement a) {
+    set_short(r, !Fr_isTrue(a));
+}
+
+void Fr_toNormal(PFrElement r, PFrElement a) {
+    if ((a->type & Fr_LONGMONTGOMERY) == Fr_LONGMONTGOMERY) {
+        uint64_t x[Fr_N64];
+        normal_value(x, a);
+        set_long(r, x);
+    } else if (!(a->type & Fr_LONG)) {
+        set_short(r, a->shortVal);
+    } else {
+        Fr_copy(r, a);
+    }
+}
+
+void Fr_toLongNormal(PFrElement r, PFrElement a) {
+    uint64_t x[Fr_N64];
+    normal_value(x, a);
+    set_long(r, x);
+}
+
+void Fr_toMontgomery(PFrElement r, PFrElement a) {
+    uint64_t x[Fr_N64];
+    normal_value(x, a);
+    raw_mont_mul(x, x, Fr_rawR2);
+    if (!(a->type & Fr_LONG)) {
+        r->shortVal = a->shortVal;
+        r->type = Fr_SHORTMONTGOMERY;
+    } else {
+        r->shortVal = 0;
+        r->type = Fr_LONGMONTGOMERY;
+    }
+    memcpy(r->longVal, x, sizeof(FrRawElement));
+}
+
+int Fr_isTrue(PFrElement pE) {
+    if (!(pE->type & Fr_LONG)) return pE->shortVal != 0;
+    uint64_t x[Fr_N64];
+    normal_value(x, pE);
+    return raw_bits(x) != 0;
+}
+
+int Fr_toInt(PFrElement pE) {
+    if (!(pE->type & Fr_LONG)) return pE->shortVal;
+    uint64_t x[Fr_N64];
+    normal_value(x, pE);
+    if (is_negative(x)) {
+        raw_sub(x, Fr_rawq, x);
+        return -(int)x[0];
+    }
+    return (int)x[0];
+}
+
+void Fr_rawCopy(FrRawElement pRawResult, const FrRawElement pRawA) {
+    memmove(pRawResult, pRawA, sizeof(FrRawElement));
+}
+
+void Fr_rawZero(FrRawElement pRawResult) {
+    memset(pRawResult, 0, sizeof(FrRawElement));
+}
+
+void Fr_rawSwap(FrRawElement pRawResult, FrRawElement pRawA) {
+    FrRawElement t;
+    memcpy(t, pRawResult, sizeof(FrRawElement));
+    memcpy(pRawResult, pRawA, sizeof(FrRawElement));
+    memcpy(pRawA, t, sizeof(FrRawElement));
+}
+
+void Fr_rawAdd(FrRawElement pRawResult, const FrRawElement pRawA, const FrRawElement pRawB) {
+    uint64_t carry = raw_add(pRawResult, pRawA, pRawB);
+    raw_reduce_once(pRawResult, carry);
+}
+
+void Fr_rawSub(FrRawElement pRawResult, const FrRawElement pRawA, const FrRawElement pRawB) {
+    if (raw_sub(pRawResult, pRawA, pRawB)) raw_add(pRawResult, pRawResult, Fr_rawq);
+}
+
+void Fr_rawNeg(FrRawElement pRawResult, const FrRawElement pRawA) {
+    if (raw_bits(pRawA) == 0) Fr_rawZero(pRawResult);
+    else raw_sub(pRawResult, Fr_rawq, pRawA);
+}
+
+void Fr_rawMMul(FrRawElement pRawResult, const FrRawElement pRawA, const FrRawElement pRawB) {
+    raw_mont_mul(pRawResult, pRawA, pRawB);
+}
+
+void Fr_rawMSquare(FrRawElement pRawResult, const FrRawElement pRawA) {
+    raw_mont_mul(pRawResult, pRawA, pRawA);
+}
+
+void Fr_rawMMul1(FrRawElement pRawResult, const FrRawElement pRawA, uint64_t pRawB) {
+    FrRawElement b;
+    memset(b, 0, sizeof(b));
+    b[0] = pRawB;
+    raw_mont_mul(pRawResult, pRawA, b);
+}
+
+void Fr_rawToMontgomery(FrRawElement pRawResult, const FrRawElement &pRawA) {
+    raw_mont_mul(pRawResult, pRawA, Fr_rawR2);
+}
+
+void Fr_rawFromMontgomery(FrRawElement pRawResult, const FrRawElement &pRawA) {
+    FrRawElement one;
+    memset(one, 0, sizeof(one));
+    one[0] = 1;
+    raw_mont_mul(pRawResult, pRawA, one);
+}
+
+int Fr_rawIsEq(const FrRawElement pRawA, const FrRawElement pRawB) {
+    return raw_cmp(pRawA, pRawB) == 0;
+}
+
+int Fr_rawIsZero(const FrRawElement pRawB) {
+    return raw_bits(pRawB) == 0;
+}
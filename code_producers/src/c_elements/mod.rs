@@ -28,8 +28,11 @@ pub struct CProducer {
     pub major_version: usize,
     pub minor_version: usize,
     pub patch_version: usize,
+    pub profile_witness: bool,
     name_tag: String,
     string_table: Vec<String>,
+    assert_message_list: AssertMessageList,
+    profile_list: ProfileList,
 }
 
 impl Default for CProducer {
@@ -94,8 +97,11 @@ impl Default for CProducer {
             major_version: 0,
             minor_version: 0,
             patch_version: 0,
+            profile_witness: false,
             name_tag: "name".to_string(),
             string_table: Vec::new(),
+            assert_message_list: Vec::new(),
+            profile_list: Vec::new(),
         }
     }
 }
@@ -175,4 +181,24 @@ impl CProducer {
     pub fn set_string_table(&mut self, string_table: Vec<String>) {
         self.string_table = string_table;
     }
+
+    pub fn get_assert_message_list(&self) -> &AssertMessageList {
+        &self.assert_message_list
+    }
+
+    pub fn set_assert_message_list(&mut self, assert_message_list: AssertMessageList) {
+        self.assert_message_list = assert_message_list;
+    }
+
+    pub fn get_profile_witness(&self) -> bool {
+        self.profile_witness
+    }
+
+    pub fn get_profile_list(&self) -> &ProfileList {
+        &self.profile_list
+    }
+
+    pub fn set_profile_list(&mut self, profile_list: ProfileList) {
+        self.profile_list = profile_list;
+    }
 }
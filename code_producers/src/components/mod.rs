@@ -21,6 +21,69 @@ pub type InputOutputList = Vec<IODef>;
 pub type TemplateInstanceIOMap = BTreeMap<usize, InputOutputList>;
 pub type MessageList = Vec<String>;
 
+// Source information attached to every assert and constraint check
+#[derive(Clone)]
+pub struct AssertMessage {
+    pub file: String,
+    pub line: usize,
+    pub column: usize,
+    pub expression: String,
+    // source text of the signals whose values are shown when the check fails
+    pub signals: Vec<String>,
+}
+pub type AssertMessageList = Vec<AssertMessage>;
+
+impl AssertMessage {
+    pub fn location(&self) -> String {
+        format!("{}:{}:{}", self.file, self.line, self.column)
+    }
+}
+
+// Template instance or function measured when the witness generator is profiled.
+// Its position in the ProfileList is the id used by the generated code.
+#[derive(Clone)]
+pub struct ProfileEntry {
+    pub kind: ProfileKind,
+    pub header: String,
+    pub name: String,
+}
+pub type ProfileList = Vec<ProfileEntry>;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum ProfileKind {
+    Template,
+    Function,
+}
+
+impl ProfileKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ProfileKind::Template => "template",
+            ProfileKind::Function => "function",
+        }
+    }
+}
+
+impl ProfileEntry {
+    pub fn description(&self) -> String {
+        format!("{} {} {}", self.kind.as_str(), self.header, self.name)
+    }
+}
+
+// Escapes the characters that cannot appear verbatim inside a C or wat string literal
+pub fn escape_string(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' | '\r' | '\t' => escaped.push(' '),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
 pub fn hasher(value: &str) -> u64 {
     use lz_fnv::FnvHasher;
     let mut fnv_hasher: Fnv1a<u64> = Fnv1a::with_key(14695981039346656037);
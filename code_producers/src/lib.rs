@@ -2,5 +2,7 @@
 pub mod c_elements;
 #[allow(dead_code)]
 pub mod wasm_elements;
+#[allow(dead_code)]
+pub mod llvm_elements;
 
 pub mod components;
@@ -0,0 +1,61 @@
+#ifndef __CIRCOM_H
+#define __CIRCOM_H
+
+#include <stdint.h>
+#include "fr.h"
+
+// Layout shared with the generated LLVM IR, see llvm_code_generator.rs
+
+// %Component = type { i32, i32, i32, i32, ptr, ptr, ptr }
+typedef struct {
+    uint32_t templateId;
+    uint32_t signalStart;
+    uint32_t inputCounter;
+    uint32_t idFather;
+    const char *templateName;
+    const char *componentName;
+    uint32_t *subcomponents;
+} Circom_Component;
+
+// %InputDef = type { ptr, i32, i32 }
+typedef struct {
+    const char *name;
+    uint32_t signalId;
+    uint32_t size;
+} Circom_InputDef;
+
+// %AssertMessage = type { ptr, i32, i32, ptr }
+typedef struct {
+    const char *file;
+    uint32_t line;
+    uint32_t column;
+    const char *expression;
+} Circom_AssertMessage;
+
+// Memory of the witness generator, allocated by the driver
+extern FrElement *circom_signals;
+extern Circom_Component *circom_components;
+
+// Defined by the generated module
+extern const uint32_t circom_number_of_signals;
+extern const uint32_t circom_number_of_components;
+extern const uint32_t circom_number_of_main_inputs;
+extern const uint32_t circom_number_of_input_defs;
+extern const Circom_InputDef circom_input_defs[];
+extern const uint32_t circom_witness_size;
+extern const uint32_t circom_witness_to_signal[];
+extern const Circom_AssertMessage circom_assert_messages[];
+void circom_run(void);
+
+// Called by the generated module
+uint32_t *circom_new_subcomponents(uint32_t number);
+const char *circom_component_name(const char *name, uint32_t position, const uint32_t *dimensions, uint32_t number_of_dimensions);
+void circom_assert_failed(const char *name, uint32_t line, uint32_t assert_id, uint32_t component);
+void circom_assert_signal(const char *label, const FrElement *value);
+void circom_abort(void);
+void circom_log_element(const FrElement *value);
+void circom_log_string(const char *value);
+void circom_log_separator(void);
+void circom_log_end(void);
+
+#endif // __CIRCOM_H
@@ -0,0 +1,397 @@
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+#include "fr.h"
+
+typedef unsigned __int128 u128;
+
+// Raw operations over Fr_N64 limbs. The result may alias any of the operands.
+
+static int raw_is_zero(const uint64_t *a) {
+    for (int i = 0; i < Fr_N64; i++) {
+        if (a[i] != 0) return 0;
+    }
+    return 1;
+}
+
+static int raw_cmp(const uint64_t *a, const uint64_t *b) {
+    for (int i = Fr_N64 - 1; i >= 0; i--) {
+        if (a[i] != b[i]) return a[i] < b[i] ? -1 : 1;
+    }
+    return 0;
+}
+
+static uint64_t raw_add(uint64_t *r, const uint64_t *a, const uint64_t *b) {
+    u128 carry = 0;
+    for (int i = 0; i < Fr_N64; i++) {
+        carry += (u128)a[i] + b[i];
+        r[i] = (uint64_t)carry;
+        carry >>= 64;
+    }
+    return (uint64_t)carry;
+}
+
+static uint64_t raw_sub(uint64_t *r, const uint64_t *a, const uint64_t *b) {
+    uint64_t borrow = 0;
+    for (int i = 0; i < Fr_N64; i++) {
+        uint64_t ai = a[i];
+        uint64_t d = ai - b[i] - borrow;
+        borrow = (ai < b[i]) || (ai - b[i] < borrow);
+        r[i] = d;
+    }
+    return borrow;
+}
+
+static int raw_bit(const uint64_t *a, int i) {
+    return (a[i / 64] >> (i % 64)) & 1;
+}
+
+static int raw_bits(const uint64_t *a) {
+    for (int i = Fr_N64 - 1; i >= 0; i--) {
+        if (a[i] != 0) return i * 64 + 64 - __builtin_clzll(a[i]);
+    }
+    return 0;
+}
+
+static void raw_shl(uint64_t *r, const uint64_t *a, int n) {
+    uint64_t t[Fr_N64];
+    int words = n / 64, bits = n % 64;
+    for (int i = Fr_N64 - 1; i >= 0; i--) {
+        uint64_t v = 0;
+        if (i - words >= 0) {
+            v = a[i - words] << bits;
+            if (bits != 0 && i - words - 1 >= 0) v |= a[i - words - 1] >> (64 - bits);
+        }
+        t[i] = v;
+    }
+    memcpy(r, t, sizeof(t));
+}
+
+static void raw_shr(uint64_t *r, const uint64_t *a, int n) {
+    uint64_t t[Fr_N64];
+    int words = n / 64, bits = n % 64;
+    for (int i = 0; i < Fr_N64; i++) {
+        uint64_t v = 0;
+        if (i + words < Fr_N64) {
+            v = a[i + words] >> bits;
+            if (bits != 0 && i + words + 1 < Fr_N64) v |= a[i + words + 1] << (64 - bits);
+        }
+        t[i] = v;
+    }
+    memcpy(r, t, sizeof(t));
+}
+
+// Brings a value below 2q back to [0, q)
+static void raw_reduce_once(uint64_t *r, uint64_t carry) {
+    if (carry || raw_cmp(r, Fr_rawq) >= 0) raw_sub(r, r, Fr_rawq);
+}
+
+static void raw_mask(uint64_t *r) {
+    for (int i = 0; i < Fr_N64; i++) {
+        int low = i * 64;
+        if (low >= Fr_BITS) r[i] = 0;
+        else if (Fr_BITS - low < 64) r[i] &= (((uint64_t)1) << (Fr_BITS - low)) - 1;
+    }
+}
+
+// Montgomery product a*b/R mod q (CIOS)
+static void raw_mont_mul(uint64_t *r, const uint64_t *a, const uint64_t *b) {
+    uint64_t t[Fr_N64 + 2];
+    memset(t, 0, sizeof(t));
+    for (int i = 0; i < Fr_N64; i++) {
+        u128 c = 0;
+        for (int j = 0; j < Fr_N64; j++) {
+            c += (u128)a[j] * b[i] + t[j];
+            t[j] = (uint64_t)c;
+            c >>= 64;
+        }
+        c += t[Fr_N64];
+        t[Fr_N64] = (uint64_t)c;
+        t[Fr_N64 + 1] = (uint64_t)(c >> 64);
+        uint64_t m = t[0] * Fr_np;
+        c = (u128)m * Fr_rawq[0] + t[0];
+        c >>= 64;
+        for (int j = 1; j < Fr_N64; j++) {
+            c += (u128)m * Fr_rawq[j] + t[j];
+            t[j - 1] = (uint64_t)c;
+            c >>= 64;
+        }
+        c += t[Fr_N64];
+        t[Fr_N64 - 1] = (uint64_t)c;
+        t[Fr_N64] = t[Fr_N64 + 1] + (uint64_t)(c >> 64);
+    }
+    raw_reduce_once(t, t[Fr_N64]);
+    memcpy(r, t, Fr_N64 * sizeof(uint64_t));
+}
+
+// Integer division of the representatives in [0, q), b must not be zero
+static void raw_divmod(uint64_t *quotient, uint64_t *remainder, const uint64_t *a, const uint64_t *b) {
+    uint64_t q[Fr_N64], rem[Fr_N64];
+    memset(q, 0, sizeof(q));
+    memset(rem, 0, sizeof(rem));
+    for (int i = raw_bits(a) - 1; i >= 0; i--) {
+        uint64_t out = rem[Fr_N64 - 1] >> 63;
+        raw_shl(rem, rem, 1);
+        rem[0] |= raw_bit(a, i);
+        if (out || raw_cmp(rem, b) >= 0) {
+            raw_sub(rem, rem, b);
+            q[i / 64] |= ((uint64_t)1) << (i % 64);
+        }
+    }
+    if (quotient) memcpy(quotient, q, sizeof(q));
+    if (remainder) memcpy(remainder, rem, sizeof(rem));
+}
+
+// Elements above q/2 represent negative numbers
+static int is_negative(const FrElement *a) {
+    uint64_t half[Fr_N64];
+    raw_shr(half, Fr_rawq, 1);
+    return raw_cmp(a->v, half) > 0;
+}
+
+static void set_bool(PFrElement r, int value) {
+    memset(r->v, 0, sizeof(r->v));
+    r->v[0] = value ? 1 : 0;
+}
+
+static void fatal(const char *message) {
+    fprintf(stderr, "%s\n", message);
+    exit(1);
+}
+
+// Amount of a shift that is known to be small and its direction
+static int shift_amount(const FrElement *b, int *reversed) {
+    uint64_t n[Fr_N64];
+    *reversed = is_negative(b);
+    if (*reversed) raw_sub(n, Fr_rawq, b->v);
+    else memcpy(n, b->v, sizeof(n));
+    if (raw_bits(n) > 31 || n[0] > Fr_BITS) return Fr_BITS;
+    return (int)n[0];
+}
+
+void Fr_copy(PFrElement r, const FrElement *a) {
+    memmove(r, a, sizeof(FrElement));
+}
+
+void Fr_add(PFrElement r, const FrElement *a, const FrElement *b) {
+    uint64_t carry = raw_add(r->v, a->v, b->v);
+    raw_reduce_once(r->v, carry);
+}
+
+void Fr_sub(PFrElement r, const FrElement *a, const FrElement *b) {
+    if (raw_sub(r->v, a->v, b->v)) raw_add(r->v, r->v, Fr_rawq);
+}
+
+void Fr_neg(PFrElement r, const FrElement *a) {
+    if (raw_is_zero(a->v)) memset(r->v, 0, sizeof(r->v));
+    else raw_sub(r->v, Fr_rawq, a->v);
+}
+
+void Fr_mul(PFrElement r, const FrElement *a, const FrElement *b) {
+    uint64_t t[Fr_N64];
+    raw_mont_mul(t, a->v, b->v);
+    raw_mont_mul(r->v, t, Fr_rawR2);
+}
+
+void Fr_pow(PFrElement r, const FrElement *a, const FrElement *b) {
+    FrElement result, base = *a;
+    set_bool(&result, 1);
+    for (int i = raw_bits(b->v) - 1; i >= 0; i--) {
+        Fr_mul(&result, &result, &result);
+        if (raw_bit(b->v, i)) Fr_mul(&result, &result, &base);
+    }
+    *r = result;
+}
+
+void Fr_inv(PFrElement r, const FrElement *a) {
+    FrElement exponent, two;
+    set_bool(&two, 1);
+    two.v[0] = 2;
+    raw_sub(exponent.v, Fr_rawq, two.v);
+    Fr_pow(r, a, &exponent);
+}
+
+void Fr_div(PFrElement r, const FrElement *a, const FrElement *b) {
+    FrElement inverse;
+    Fr_inv(&inverse, b);
+    Fr_mul(r, a, &inverse);
+}
+
+void Fr_idiv(PFrElement r, const FrElement *a, const FrElement *b) {
+    if (raw_is_zero(b->v)) fatal("Division by zero");
+    raw_divmod(r->v, NULL, a->v, b->v);
+}
+
+void Fr_mod(PFrElement r, const FrElement *a, const FrElement *b) {
+    if (raw_is_zero(b->v)) fatal("Division by zero");
+    raw_divmod(NULL, r->v, a->v, b->v);
+}
+
+static void shift_left(PFrElement r, const FrElement *a, int n) {
+    if (n >= Fr_BITS) {
+        memset(r->v, 0, sizeof(r->v));
+        return;
+    }
+    raw_shl(r->v, a->v, n);
+    raw_mask(r->v);
+    raw_reduce_once(r->v, 0);
+}
+
+static void shift_right(PFrElement r, const FrElement *a, int n) {
+    if (n >= Fr_BITS) memset(r->v, 0, sizeof(r->v));
+    else raw_shr(r->v, a->v, n);
+}
+
+void Fr_shl(PFrElement r, const FrElement *a, const FrElement *b) {
+    int reversed;
+    int n = shift_amount(b, &reversed);
+    if (reversed) shift_right(r, a, n);
+    else shift_left(r, a, n);
+}
+
+void Fr_shr(PFrElement r, const FrElement *a, const FrElement *b) {
+    int reversed;
+    int n = shift_amount(b, &reversed);
+    if (reversed) shift_left(r, a, n);
+    else shift_right(r, a, n);
+}
+
+void Fr_band(PFrElement r, const FrElement *a, const FrElement *b) {
+    for (int i = 0; i < Fr_N64; i++) r->v[i] = a->v[i] & b->v[i];
+}
+
+void Fr_bor(PFrElement r, const FrElement *a, const FrElement *b) {
+    for (int i = 0; i < Fr_N64; i++) r->v[i] = a->v[i] | b->v[i];
+    raw_reduce_once(r->v, 0);
+}
+
+void Fr_bxor(PFrElement r, const FrElement *a, const FrElement *b) {
+    for (int i = 0; i < Fr_N64; i++) r->v[i] = a->v[i] ^ b->v[i];
+    raw_reduce_once(r->v, 0);
+}
+
+void Fr_bnot(PFrElement r, const FrElement *a) {
+    for (int i = 0; i < Fr_N64; i++) r->v[i] = ~a->v[i];
+    raw_mask(r->v);
+    raw_reduce_once(r->v, 0);
+}
+
+void Fr_eq(PFrElement r, const FrElement *a, const FrElement *b) {
+    set_bool(r, raw_cmp(a->v, b->v) == 0);
+}
+
+void Fr_eqn(PFrElement r, const FrElement *a, const FrElement *b, int n) {
+    int equal = 1;
+    for (int i = 0; i < n && equal; i++) equal = raw_cmp(a[i].v, b[i].v) == 0;
+    set_bool(r, equal);
+}
+
+void Fr_neq(PFrElement r, const FrElement *a, const FrElement *b) {
+    set_bool(r, raw_cmp(a->v, b->v) != 0);
+}
+
+static int lesser(const FrElement *a, const FrElement *b) {
+    int negative_a = is_negative(a), negative_b = is_negative(b);
+    if (negative_a != negative_b) return negative_a;
+    return raw_cmp(a->v, b->v) < 0;
+}
+
+void Fr_lt(PFrElement r, const FrElement *a, const FrElement *b) {
+    set_bool(r, lesser(a, b));
+}
+
+void Fr_gt(PFrElement r, const FrElement *a, const FrElement *b) {
+    set_bool(r, lesser(b, a));
+}
+
+void Fr_leq(PFrElement r, const FrElement *a, const FrElement *b) {
+    set_bool(r, !lesser(b, a));
+}
+
+void Fr_geq(PFrElement r, const FrElement *a, const FrElement *b) {
+    set_bool(r, !lesser(a, b));
+}
+
+void Fr_land(PFrElement r, const FrElement *a, const FrElement *b) {
+    set_bool(r, Fr_isTrue(a) && Fr_isTrue(b));
+}
+
+void Fr_lor(PFrElement r, const FrElement *a, const FrElement *b) {
+    set_bool(r, Fr_isTrue(a) || Fr_isTrue(b));
+}
+
+void Fr_lnot(PFrElement r, const FrElement *a) {
+    set_bool(r, !Fr_isTrue(a));
+}
+
+int Fr_isTrue(const FrElement *a) {
+    return !raw_is_zero(a->v);
+}
+
+int Fr_toInt(const FrElement *a) {
+    if (is_negative(a)) {
+        uint64_t n[Fr_N64];
+        raw_sub(n, Fr_rawq, a->v);
+        return -(int)n[0];
+    }
+    return (int)a->v[0];
+}
+
+void Fr_fromInt(PFrElement r, int64_t a) {
+    FrElement value;
+    set_bool(&value, 0);
+    value.v[0] = a < 0 ? (uint64_t)(-a) : (uint64_t)a;
+    raw_divmod(NULL, value.v, value.v, Fr_rawq);
+    if (a < 0) Fr_neg(&value, &value);
+    *r = value;
+}
+
+int Fr_str2element(PFrElement r, const char *s, int base) {
+    FrElement result, radix, digit;
+    int negative = 0;
+    if (*s == '-') {
+        negative = 1;
+        s++;
+    }
+    if (*s == '\0') return 0;
+    Fr_fromInt(&result, 0);
+    Fr_fromInt(&radix, base);
+    for (; *s; s++) {
+        int d;
+        if ('0' <= *s && *s <= '9') d = *s - '0';
+        else if ('a' <= *s && *s <= 'f') d = *s - 'a' + 10;
+        else if ('A' <= *s && *s <= 'F') d = *s - 'A' + 10;
+        else return 0;
+        if (d >= base) return 0;
+        Fr_fromInt(&digit, d);
+        Fr_mul(&result, &result, &radix);
+        Fr_add(&result, &result, &digit);
+    }
+    if (negative) Fr_neg(&result, &result);
+    *r = result;
+    return 1;
+}
+
+char *Fr_element2str(const FrElement *a) {
+    // 20 decimal digits are enough for every limb
+    char *digits = malloc(Fr_N64 * 20 + 2);
+    uint64_t n[Fr_N64];
+    int length = 0;
+    memcpy(n, a->v, sizeof(n));
+    do {
+        u128 rem = 0;
+        for (int i = Fr_N64 - 1; i >= 0; i--) {
+            u128 current = (rem << 64) | n[i];
+            n[i] = (uint64_t)(current / 10);
+            rem = current % 10;
+        }
+        digits[length++] = (char)('0' + (int)rem);
+    } while (!raw_is_zero(n));
+    for (int i = 0; i < length / 2; i++) {
+        char c = digits[i];
+        digits[i] = digits[length - 1 - i];
+        digits[length - 1 - i] = c;
+    }
+    digits[length] = '\0';
+    return digits;
+}
@@ -0,0 +1,59 @@
+#ifndef __FR_H
+#define __FR_H
+
+#include <stdint.h>
+
+// Field elements are kept in standard form, as Fr_N64 little endian 64 bit limbs in [0, q).
+// The generated LLVM IR uses the same layout: [{{n64}} x i64]
+#define Fr_N64 {{n64}}
+#define Fr_BITS {{bits}}
+
+typedef struct {
+    uint64_t v[Fr_N64];
+} FrElement;
+typedef FrElement *PFrElement;
+
+// q = {{prime}}
+static const uint64_t Fr_rawq[Fr_N64] = { {{q}} };
+// R^2 mod q, with R = 2^(64*Fr_N64)
+static const uint64_t Fr_rawR2[Fr_N64] = { {{r2}} };
+// -q^(-1) mod 2^64
+static const uint64_t Fr_np = {{np}};
+
+void Fr_copy(PFrElement r, const FrElement *a);
+void Fr_add(PFrElement r, const FrElement *a, const FrElement *b);
+void Fr_sub(PFrElement r, const FrElement *a, const FrElement *b);
+void Fr_neg(PFrElement r, const FrElement *a);
+void Fr_mul(PFrElement r, const FrElement *a, const FrElement *b);
+void Fr_div(PFrElement r, const FrElement *a, const FrElement *b);
+void Fr_inv(PFrElement r, const FrElement *a);
+void Fr_pow(PFrElement r, const FrElement *a, const FrElement *b);
+void Fr_idiv(PFrElement r, const FrElement *a, const FrElement *b);
+void Fr_mod(PFrElement r, const FrElement *a, const FrElement *b);
+void Fr_shl(PFrElement r, const FrElement *a, const FrElement *b);
+void Fr_shr(PFrElement r, const FrElement *a, const FrElement *b);
+void Fr_band(PFrElement r, const FrElement *a, const FrElement *b);
+void Fr_bor(PFrElement r, const FrElement *a, const FrElement *b);
+void Fr_bxor(PFrElement r, const FrElement *a, const FrElement *b);
+void Fr_bnot(PFrElement r, const FrElement *a);
+void Fr_eq(PFrElement r, const FrElement *a, const FrElement *b);
+void Fr_eqn(PFrElement r, const FrElement *a, const FrElement *b, int n);
+void Fr_neq(PFrElement r, const FrElement *a, const FrElement *b);
+void Fr_lt(PFrElement r, const FrElement *a, const FrElement *b);
+void Fr_gt(PFrElement r, const FrElement *a, const FrElement *b);
+void Fr_leq(PFrElement r, const FrElement *a, const FrElement *b);
+void Fr_geq(PFrElement r, const FrElement *a, const FrElement *b);
+void Fr_land(PFrElement r, const FrElement *a, const FrElement *b);
+void Fr_lor(PFrElement r, const FrElement *a, const FrElement *b);
+void Fr_lnot(PFrElement r, const FrElement *a);
+
+int Fr_isTrue(const FrElement *a);
+int Fr_toInt(const FrElement *a);
+void Fr_fromInt(PFrElement r, int64_t a);
+
+// Returns 0 when s is not a valid number in the given base
+int Fr_str2element(PFrElement r, const char *s, int base);
+// The returned string must be released with free
+char *Fr_element2str(const FrElement *a);
+
+#endif // __FR_H
@@ -0,0 +1,361 @@
+#include <ctype.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+#include "circom.h"
+
+FrElement *circom_signals;
+Circom_Component *circom_components;
+
+static void fail(const char *message, const char *detail) {
+    fprintf(stderr, "%s%s\n", message, detail);
+    exit(1);
+}
+
+// Runtime functions used by the generated code
+
+uint32_t *circom_new_subcomponents(uint32_t number) {
+    uint32_t *subcomponents = calloc(number == 0 ? 1 : number, sizeof(uint32_t));
+    if (subcomponents == NULL) fail("Not enough memory", "");
+    return subcomponents;
+}
+
+const char *circom_component_name(const char *name, uint32_t position, const uint32_t *dimensions, uint32_t number_of_dimensions) {
+    // position is the index in the flattened array of components
+    size_t length = strlen(name) + 12 * number_of_dimensions + 1;
+    char *result = malloc(length);
+    uint32_t indexes[number_of_dimensions == 0 ? 1 : number_of_dimensions];
+    for (int i = (int)number_of_dimensions - 1; i >= 0; i--) {
+        indexes[i] = position % dimensions[i];
+        position /= dimensions[i];
+    }
+    size_t written = (size_t)snprintf(result, length, "%s", name);
+    for (uint32_t i = 0; i < number_of_dimensions; i++) {
+        written += (size_t)snprintf(result + written, length - written, "[%u]", indexes[i]);
+    }
+    return result;
+}
+
+static void print_trace(uint32_t component) {
+    if (component != 0) {
+        print_trace(circom_components[component].idFather);
+        printf(".");
+    }
+    printf("%s", circom_components[component].componentName);
+}
+
+void circom_assert_failed(const char *name, uint32_t line, uint32_t assert_id, uint32_t component) {
+    const Circom_AssertMessage *message = &circom_assert_messages[assert_id];
+    printf("Failed assert in template/function %s line %u. Followed trace of components: ", name, line);
+    print_trace(component);
+    printf("\n");
+    printf("  at %s:%u:%u: %s\n", message->file, message->line, message->column, message->expression);
+}
+
+void circom_assert_signal(const char *label, const FrElement *value) {
+    char *text = Fr_element2str(value);
+    printf("  %s = %s\n", label, text);
+    free(text);
+}
+
+void circom_abort(void) {
+    fflush(stdout);
+    abort();
+}
+
+void circom_log_element(const FrElement *value) {
+    char *text = Fr_element2str(value);
+    printf("%s", text);
+    free(text);
+}
+
+void circom_log_string(const char *value) {
+    printf("%s", value);
+}
+
+void circom_log_separator(void) {
+    printf(" ");
+}
+
+void circom_log_end(void) {
+    printf("\n");
+    fflush(stdout);
+}
+
+// Minimal reader for the input json: an object whose values are numbers,
+// strings with a number in base 10 or prefixed by 0x, 0o or 0b, nested arrays
+// of them and, for the inputs that are buses, objects with the values of the fields
+
+typedef struct {
+    const char *text;
+    size_t position;
+} Reader;
+
+static void skip_spaces(Reader *reader) {
+    while (isspace((unsigned char)reader->text[reader->position])) reader->position++;
+}
+
+static char peek(Reader *reader) {
+    skip_spaces(reader);
+    return reader->text[reader->position];
+}
+
+static void expect(Reader *reader, char c) {
+    if (peek(reader) != c) {
+        char detail[2] = {c, '\0'};
+        fail("Invalid json input, expected ", detail);
+    }
+    reader->position++;
+}
+
+static char *read_string(Reader *reader) {
+    expect(reader, '"');
+    size_t start = reader->position;
+    while (reader->text[reader->position] != '"') {
+        if (reader->text[reader->position] == '\0') fail("Invalid json input, unterminated string", "");
+        reader->position++;
+    }
+    size_t length = reader->position - start;
+    char *value = malloc(length + 1);
+    memcpy(value, reader->text + start, length);
+    value[length] = '\0';
+    reader->position++;
+    return value;
+}
+
+static void read_element(Reader *reader, FrElement *element) {
+    char *number;
+    int base = 10;
+    if (peek(reader) == '"') {
+        number = read_string(reader);
+    } else {
+        size_t start = reader->position;
+        while (reader->text[reader->position] == '-' || isdigit((unsigned char)reader->text[reader->position])) {
+            reader->position++;
+        }
+        size_t length = reader->position - start;
+        number = malloc(length + 1);
+        memcpy(number, reader->text + start, length);
+        number[length] = '\0';
+    }
+    const char *digits = number;
+    if (digits[0] == '0' && (digits[1] == 'x' || digits[1] == 'X')) base = 16;
+    if (digits[0] == '0' && (digits[1] == 'o' || digits[1] == 'O')) base = 8;
+    if (digits[0] == '0' && (digits[1] == 'b' || digits[1] == 'B')) base = 2;
+    if (base != 10) digits += 2;
+    if (!Fr_str2element(element, digits, base)) fail("Invalid number in JSON input: ", number);
+    free(number);
+}
+
+// Reads a value flattening the arrays, returns the number of elements read
+static uint32_t read_value(Reader *reader, FrElement *values, uint32_t capacity, const char *name) {
+    if (peek(reader) != '[') {
+        if (capacity == 0) fail("Too many values for input signal ", name);
+        read_element(reader, values);
+        return 1;
+    }
+    uint32_t read = 0;
+    expect(reader, '[');
+    if (peek(reader) != ']') {
+        for (;;) {
+            read += read_value(reader, values + read, capacity - read, name);
+            if (peek(reader) != ',') break;
+            reader->position++;
+        }
+    }
+    expect(reader, ']');
+    return read;
+}
+
+static char *read_file(const char *file_name) {
+    FILE *file = fopen(file_name, "rb");
+    if (file == NULL) fail("Could not open ", file_name);
+    fseek(file, 0, SEEK_END);
+    long size = ftell(file);
+    fseek(file, 0, SEEK_SET);
+    char *text = malloc((size_t)size + 1);
+    if (fread(text, 1, (size_t)size, file) != (size_t)size) fail("Could not read ", file_name);
+    text[size] = '\0';
+    fclose(file);
+    return text;
+}
+
+static void skip_value(Reader *reader) {
+    char c = peek(reader);
+    if (c == '"') {
+        free(read_string(reader));
+    } else if (c == '[' || c == '{') {
+        char close = c == '[' ? ']' : '}';
+        reader->position++;
+        if (peek(reader) != close) {
+            for (;;) {
+                if (close == '}') {
+                    free(read_string(reader));
+                    expect(reader, ':');
+                }
+                skip_value(reader);
+                if (peek(reader) != ',') break;
+                reader->position++;
+            }
+        }
+        expect(reader, close);
+    } else {
+        while (reader->text[reader->position] == '-' || isalnum((unsigned char)reader->text[reader->position])) {
+            reader->position++;
+        }
+    }
+}
+
+// Returns the number of objects in the (nested) array that starts at the position
+// of the reader, or 0 if its elements are not objects
+static uint32_t count_objects(Reader reader) {
+    expect(&reader, '[');
+    uint32_t count = 0;
+    if (peek(&reader) != ']') {
+        for (;;) {
+            char c = peek(&reader);
+            if (c == '[') {
+                uint32_t inner = count_objects(reader);
+                if (inner == 0) return 0;
+                count += inner;
+            } else if (c != '{') {
+                return 0;
+            } else {
+                count++;
+            }
+            skip_value(&reader);
+            if (peek(&reader) != ',') break;
+            reader.position++;
+        }
+    }
+    return count;
+}
+
+static char *join_name(const char *prefix, const char *separator, const char *name) {
+    char *joined = malloc(strlen(prefix) + strlen(separator) + strlen(name) + 1);
+    strcpy(joined, prefix);
+    strcat(joined, separator);
+    strcat(joined, name);
+    return joined;
+}
+
+static uint32_t read_input(Reader *reader, const char *name, uint32_t index, uint32_t total);
+
+// Reads the objects of an array of buses, the k-th object gives the values of
+// the index * count + k position of the signals of its fields
+static uint32_t read_bus_array(Reader *reader, const char *name, uint32_t index, uint32_t total,
+                               uint32_t count, uint32_t *position) {
+    uint32_t assigned = 0;
+    expect(reader, '[');
+    if (peek(reader) != ']') {
+        for (;;) {
+            char *element = join_name(name, "", "[]");
+            if (peek(reader) == '[') {
+                assigned += read_bus_array(reader, element, index, total, count, position);
+            } else {
+                assigned += read_input(reader, element, index * count + *position, total * count);
+                *position += 1;
+            }
+            free(element);
+            if (peek(reader) != ',') break;
+            reader->position++;
+        }
+    }
+    expect(reader, ']');
+    return assigned;
+}
+
+// Reads the value of the input signal name, buses are given as objects with the
+// values of their fields or as arrays of them. The signal receives the values at
+// position index of the total positions given by the arrays of buses it belongs to.
+static uint32_t read_input(Reader *reader, const char *name, uint32_t index, uint32_t total) {
+    uint32_t assigned = 0;
+    if (peek(reader) == '{') {
+        reader->position++;
+        if (peek(reader) != '}') {
+            for (;;) {
+                char *field = read_string(reader);
+                char *field_name = join_name(name, name[0] == '\0' ? "" : ".", field);
+                expect(reader, ':');
+                assigned += read_input(reader, field_name, index, total);
+                free(field);
+                free(field_name);
+                if (peek(reader) != ',') break;
+                reader->position++;
+            }
+        }
+        expect(reader, '}');
+        return assigned;
+    }
+    if (peek(reader) == '[') {
+        uint32_t count = count_objects(*reader);
+        if (count > 0) {
+            uint32_t position = 0;
+            return read_bus_array(reader, name, index, total, count, &position);
+        }
+    }
+    const Circom_InputDef *def = NULL;
+    for (uint32_t i = 0; i < circom_number_of_input_defs; i++) {
+        if (strcmp(circom_input_defs[i].name, name) == 0) def = &circom_input_defs[i];
+    }
+    if (def == NULL) fail("Unknown input signal ", name);
+    uint32_t size = def->size / total;
+    uint32_t read = read_value(reader, &circom_signals[def->signalId + index * size], size, name);
+    if (read < size) fail("Not enough values for input signal ", name);
+    return read;
+}
+
+static void load_inputs(const char *file_name) {
+    Reader reader = {read_file(file_name), 0};
+    uint32_t assigned = read_input(&reader, "", 0, 1);
+    if (assigned != circom_number_of_main_inputs) {
+        fprintf(stderr, "Not all inputs have been set. Only %u out of %u\n", assigned, circom_number_of_main_inputs);
+        exit(1);
+    }
+    free((void *)reader.text);
+}
+
+static void write_u32(FILE *file, uint32_t value) {
+    fwrite(&value, 4, 1, file);
+}
+
+static void write_u64(FILE *file, uint64_t value) {
+    fwrite(&value, 8, 1, file);
+}
+
+static void write_witness(const char *file_name) {
+    FILE *file = fopen(file_name, "wb");
+    if (file == NULL) fail("Could not create ", file_name);
+    uint32_t n8 = Fr_N64 * 8;
+    fwrite("wtns", 4, 1, file);
+    write_u32(file, 2);
+    write_u32(file, 2);
+    // Header
+    write_u32(file, 1);
+    write_u64(file, 8 + n8);
+    write_u32(file, n8);
+    fwrite(Fr_rawq, n8, 1, file);
+    write_u32(file, circom_witness_size);
+    // Data
+    write_u32(file, 2);
+    write_u64(file, (uint64_t)n8 * circom_witness_size);
+    for (uint32_t i = 0; i < circom_witness_size; i++) {
+        fwrite(circom_signals[circom_witness_to_signal[i]].v, n8, 1, file);
+    }
+    fclose(file);
+}
+
+int main(int argc, char *argv[]) {
+    if (argc != 3) {
+        printf("Usage: %s <input.json> <output.wtns>\n", argv[0]);
+        return 1;
+    }
+    circom_signals = calloc(circom_number_of_signals, sizeof(FrElement));
+    circom_components = calloc(circom_number_of_components == 0 ? 1 : circom_number_of_components, sizeof(Circom_Component));
+    if (circom_signals == NULL || circom_components == NULL) fail("Not enough memory", "");
+    Fr_fromInt(&circom_signals[0], 1);
+    load_inputs(argv[1]);
+    circom_run();
+    write_witness(argv[2]);
+    return 0;
+}
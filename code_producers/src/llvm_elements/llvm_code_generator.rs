@@ -0,0 +1,606 @@
+use super::*;
+use num_bigint_dig::BigInt;
+use serde_json::json;
+use std::fs::File;
+use std::io::prelude::*;
+use std::path::Path;
+
+// The generated module uses opaque pointers. Field elements are arrays of 64 bit limbs
+// in standard form and every field operation is a call to the runtime in fr.c.
+
+// Types
+pub const FR_ELEMENT: &str = "%FrElement";
+pub const COMPONENT: &str = "%Component";
+const INPUT_DEF: &str = "%InputDef";
+const ASSERT_MESSAGE: &str = "%AssertMessage";
+const IO_DEF: &str = "%IODef";
+
+// Fields of %Component, they must match Circom_Component in circom.h
+pub const COMPONENT_TEMPLATE_ID: usize = 0;
+pub const COMPONENT_SIGNAL_START: usize = 1;
+pub const COMPONENT_INPUT_COUNTER: usize = 2;
+pub const COMPONENT_ID_FATHER: usize = 3;
+pub const COMPONENT_TEMPLATE_NAME: usize = 4;
+pub const COMPONENT_NAME: usize = 5;
+pub const COMPONENT_SUBCOMPONENTS: usize = 6;
+
+// Global variables
+pub const G_SIGNALS: &str = "@circom_signals";
+pub const G_COMPONENTS: &str = "@circom_components";
+pub const G_CONSTANTS: &str = "@circuit_constants";
+pub const G_RUN_TABLE: &str = "@circom_run_table";
+pub const G_IO_MAP: &str = "@circom_io_map";
+
+// Values available in the code of templates and functions
+pub const SIGNALS: &str = "%signals";
+pub const COMPONENTS: &str = "%components";
+pub const MY_SIGNAL_START: &str = "%my_signal_start";
+pub const MY_SUBCOMPONENTS: &str = "%my_subcomponents";
+pub const MY_ID: &str = "%my_id";
+pub const LVAR: &str = "%lvar";
+pub const EXPAUX: &str = "%expaux";
+pub const CTX_INDEX: &str = "%ctx_index";
+pub const SIGNAL_OFFSET: &str = "%signal_offset";
+pub const COMPONENT_OFFSET: &str = "%component_offset";
+pub const COMPONENT_NAME_PARAM: &str = "%component_name";
+pub const COMPONENT_FATHER: &str = "%component_father";
+pub const FUNCTION_DESTINATION: &str = "%dest";
+pub const FUNCTION_DESTINATION_SIZE: &str = "%dest_size";
+
+// Fresh names, stack slots and private constants of the function being generated.
+// The stack slots are placed in the entry block so that loops do not grow the stack.
+pub struct LLVMContext {
+    symbol: String,
+    next_id: usize,
+    allocas: Vec<LLVMInstruction>,
+    constants: Vec<LLVMInstruction>,
+}
+
+impl LLVMContext {
+    // name is the circom name of the template or function, shown when an assert fails
+    pub fn new(symbol: &str, name: &str) -> LLVMContext {
+        let constants = vec![string_global(&name_global(symbol), name)];
+        LLVMContext { symbol: symbol.to_string(), next_id: 0, allocas: vec![], constants }
+    }
+    pub fn get_name(&self) -> String {
+        name_global(&self.symbol)
+    }
+    pub fn fresh_value(&mut self) -> String {
+        self.next_id += 1;
+        format!("%t{}", self.next_id)
+    }
+    // labels are returned without the leading %
+    pub fn fresh_label(&mut self, prefix: &str) -> String {
+        self.next_id += 1;
+        format!("{}{}", prefix, self.next_id)
+    }
+    pub fn alloca(&mut self, ty: &str) -> String {
+        let slot = self.fresh_value();
+        self.allocas.push(format!("{} = alloca {}", slot, ty));
+        slot
+    }
+    pub fn private_constant(&mut self, ty: &str, value: &str) -> String {
+        self.next_id += 1;
+        let name = format!("@{}.c{}", self.symbol, self.next_id);
+        self.constants.push(format!("{} = private unnamed_addr constant {} {}", name, ty, value));
+        name
+    }
+    pub fn string_constant(&mut self, value: &str) -> String {
+        let (ty, literal) = string_literal(value);
+        self.private_constant(&ty, &literal)
+    }
+    pub fn take_allocas(&mut self) -> Vec<LLVMInstruction> {
+        std::mem::take(&mut self.allocas)
+    }
+    pub fn take_constants(&mut self) -> Vec<LLVMInstruction> {
+        std::mem::take(&mut self.constants)
+    }
+}
+
+pub fn fr_array(size: usize) -> String {
+    format!("[{} x {}]", size, FR_ELEMENT)
+}
+
+pub fn label(name: &str) -> LLVMInstruction {
+    format!("{}:", name)
+}
+
+pub fn branch(label: &str) -> LLVMInstruction {
+    format!("br label %{}", label)
+}
+
+pub fn conditional_branch(condition: &str, if_label: &str, else_label: &str) -> LLVMInstruction {
+    format!("br i1 {}, label %{}, label %{}", condition, if_label, else_label)
+}
+
+// Address of the element index of the array of field elements that starts at base
+pub fn element_address(base: &str, index: &str, context: &mut LLVMContext) -> (Vec<LLVMInstruction>, String) {
+    let address = context.fresh_value();
+    let instruction = format!("{} = getelementptr inbounds {}, ptr {}, i32 {}", address, FR_ELEMENT, base, index);
+    (vec![instruction], address)
+}
+
+pub fn signal_address(index: &str, context: &mut LLVMContext) -> (Vec<LLVMInstruction>, String) {
+    let position = context.fresh_value();
+    let mut instructions = vec![format!("{} = add i32 {}, {}", position, MY_SIGNAL_START, index)];
+    let (mut address_instructions, address) = element_address(SIGNALS, &position, context);
+    instructions.append(&mut address_instructions);
+    (instructions, address)
+}
+
+pub fn component_field(component: &str, field: usize, context: &mut LLVMContext) -> (Vec<LLVMInstruction>, String) {
+    let address = context.fresh_value();
+    let instruction = format!(
+        "{} = getelementptr inbounds {}, ptr {}, i32 {}, i32 {}",
+        address, COMPONENT, COMPONENTS, component, field
+    );
+    (vec![instruction], address)
+}
+
+pub fn load(ty: &str, address: &str, context: &mut LLVMContext) -> (Vec<LLVMInstruction>, String) {
+    let value = context.fresh_value();
+    (vec![format!("{} = load {}, ptr {}", value, ty, address)], value)
+}
+
+pub fn store(ty: &str, value: &str, address: &str) -> LLVMInstruction {
+    format!("store {} {}, ptr {}", ty, value, address)
+}
+
+pub fn load_component_field(component: &str, field: usize, ty: &str, context: &mut LLVMContext) -> (Vec<LLVMInstruction>, String) {
+    let (mut instructions, address) = component_field(component, field, context);
+    let (mut load_instructions, value) = load(ty, &address, context);
+    instructions.append(&mut load_instructions);
+    (instructions, value)
+}
+
+// Position in the component memory of the subcomponent cmp_index of the running component
+pub fn subcomponent_id(cmp_index: &str, context: &mut LLVMContext) -> (Vec<LLVMInstruction>, String) {
+    let address = context.fresh_value();
+    let mut instructions =
+        vec![format!("{} = getelementptr inbounds i32, ptr {}, i32 {}", address, MY_SUBCOMPONENTS, cmp_index)];
+    let (mut load_instructions, value) = load("i32", &address, context);
+    instructions.append(&mut load_instructions);
+    (instructions, value)
+}
+
+pub fn subcomponent_signal_address(
+    sub_component: &str,
+    index: &str,
+    context: &mut LLVMContext,
+) -> (Vec<LLVMInstruction>, String) {
+    let (mut instructions, start) = load_component_field(sub_component, COMPONENT_SIGNAL_START, "i32", context);
+    let position = context.fresh_value();
+    instructions.push(format!("{} = add i32 {}, {}", position, start, index));
+    let (mut address_instructions, address) = element_address(SIGNALS, &position, context);
+    instructions.append(&mut address_instructions);
+    (instructions, address)
+}
+
+// Offset of the signal signal_code of a subcomponent whose template is only known at execution.
+// Each index is scaled by the length of the next dimension of the signal
+pub fn mapped_signal_offset(
+    sub_component: &str,
+    signal_code: usize,
+    indexes: Vec<String>,
+    context: &mut LLVMContext,
+) -> (Vec<LLVMInstruction>, String) {
+    let (mut instructions, template_id) =
+        load_component_field(sub_component, COMPONENT_TEMPLATE_ID, "i32", context);
+    let defs_address = context.fresh_value();
+    instructions.push(format!(
+        "{} = getelementptr inbounds ptr, ptr {}, i32 {}",
+        defs_address, G_IO_MAP, template_id
+    ));
+    let (mut defs_instructions, defs) = load("ptr", &defs_address, context);
+    instructions.append(&mut defs_instructions);
+    let offset_address = context.fresh_value();
+    instructions.push(format!(
+        "{} = getelementptr inbounds {}, ptr {}, i32 {}, i32 0",
+        offset_address, IO_DEF, defs, signal_code
+    ));
+    let (mut offset_instructions, offset) = load("i32", &offset_address, context);
+    instructions.append(&mut offset_instructions);
+    if indexes.is_empty() {
+        return (instructions, offset);
+    }
+    let lengths_address = context.fresh_value();
+    instructions.push(format!(
+        "{} = getelementptr inbounds {}, ptr {}, i32 {}, i32 1",
+        lengths_address, IO_DEF, defs, signal_code
+    ));
+    let (mut lengths_instructions, lengths) = load("ptr", &lengths_address, context);
+    instructions.append(&mut lengths_instructions);
+    let mut map_index = indexes[0].clone();
+    for (i, index) in indexes.iter().enumerate().skip(1) {
+        let length_address = context.fresh_value();
+        instructions.push(format!(
+            "{} = getelementptr inbounds i32, ptr {}, i32 {}",
+            length_address, lengths, i - 1
+        ));
+        let (mut length_instructions, length) = load("i32", &length_address, context);
+        instructions.append(&mut length_instructions);
+        let scaled = context.fresh_value();
+        instructions.push(format!("{} = mul i32 {}, {}", scaled, map_index, length));
+        let added = context.fresh_value();
+        instructions.push(format!("{} = add i32 {}, {}", added, scaled, index));
+        map_index = added;
+    }
+    let result = context.fresh_value();
+    instructions.push(format!("{} = add i32 {}, {}", result, offset, map_index));
+    (instructions, result)
+}
+
+pub fn copy_elements(dest: &str, src: &str, size: usize, producer: &LLVMProducer) -> LLVMInstruction {
+    format!(
+        "call void @llvm.memcpy.p0.p0.i64(ptr {}, ptr {}, i64 {}, i1 false)",
+        dest,
+        src,
+        size * producer.get_size_of_field_element()
+    )
+}
+
+pub fn copy_elements_dynamic(
+    dest: &str,
+    src: &str,
+    size: &str,
+    producer: &LLVMProducer,
+    context: &mut LLVMContext,
+) -> Vec<LLVMInstruction> {
+    let elements = context.fresh_value();
+    let bytes = context.fresh_value();
+    vec![
+        format!("{} = zext i32 {} to i64", elements, size),
+        format!("{} = mul i64 {}, {}", bytes, elements, producer.get_size_of_field_element()),
+        format!("call void @llvm.memcpy.p0.p0.i64(ptr {}, ptr {}, i64 {}, i1 false)", dest, src, bytes),
+    ]
+}
+
+pub fn is_true(value: &str, context: &mut LLVMContext) -> (Vec<LLVMInstruction>, String) {
+    let result = context.fresh_value();
+    let condition = context.fresh_value();
+    let instructions = vec![
+        format!("{} = call i32 @Fr_isTrue(ptr {})", result, value),
+        format!("{} = icmp ne i32 {}, 0", condition, result),
+    ];
+    (instructions, condition)
+}
+
+pub fn build_call(function: &str, arguments: Vec<String>) -> LLVMInstruction {
+    format!("call void {}({})", function, arguments.join(", "))
+}
+
+pub fn typed(ty: &str, value: &str) -> String {
+    format!("{} {}", ty, value)
+}
+
+// Lines that are not labels are indented
+pub fn build_function(header: String, body: Vec<LLVMInstruction>) -> LLVMInstruction {
+    let mut function = format!("{} {{\n", header);
+    for instruction in body {
+        if instruction.ends_with(':') {
+            function.push_str(&format!("{}\n", instruction));
+        } else {
+            function.push_str(&format!("  {}\n", instruction));
+        }
+    }
+    function.push_str("}\n");
+    function
+}
+
+pub fn name_global(header: &str) -> String {
+    format!("@{}.name", header)
+}
+
+pub fn string_table_global(id: usize) -> String {
+    format!("@circom_string.{}", id)
+}
+
+pub fn assert_signal_label_global(assert_id: usize, signal: usize) -> String {
+    format!("@circom_assert.{}.{}", assert_id, signal)
+}
+
+// Escapes the bytes that cannot appear in an LLVM string literal and adds the ending zero
+pub fn string_literal(value: &str) -> (String, String) {
+    let bytes = value.as_bytes();
+    let mut literal = String::with_capacity(bytes.len() + 8);
+    for byte in bytes {
+        if *byte == b'"' || *byte == b'\\' || *byte < 0x20 || *byte >= 0x7f {
+            literal.push_str(&format!("\\{:02X}", byte));
+        } else {
+            literal.push(*byte as char);
+        }
+    }
+    (format!("[{} x i8]", bytes.len() + 1), format!("c\"{}\\00\"", literal))
+}
+
+pub fn string_global(name: &str, value: &str) -> LLVMInstruction {
+    let (ty, literal) = string_literal(value);
+    format!("{} = private unnamed_addr constant {} {}", name, ty, literal)
+}
+
+// Little endian 64 bit limbs of a value in [0, prime)
+fn limbs(value: &BigInt, prime: &BigInt, size: usize) -> Vec<u64> {
+    raw_limbs(&(((value % prime) + prime) % prime), size)
+}
+
+fn raw_limbs(value: &BigInt, size: usize) -> Vec<u64> {
+    let (_, bytes) = value.to_bytes_le();
+    let mut limbs = vec![0u64; size];
+    for (i, byte) in bytes.iter().enumerate() {
+        limbs[i / 8] |= (*byte as u64) << (8 * (i % 8));
+    }
+    limbs
+}
+
+// Typed constant array, LLVM has no literal for empty arrays
+pub fn array_constant(ty: &str, elements: Vec<String>) -> String {
+    if elements.is_empty() {
+        format!("[0 x {}] zeroinitializer", ty)
+    } else {
+        let values: Vec<String> = elements.iter().map(|e| format!("{} {}", ty, e)).collect();
+        format!("[{} x {}] [{}]", elements.len(), ty, values.join(", "))
+    }
+}
+
+fn element_literal(limbs: &[u64]) -> String {
+    let values: Vec<String> = limbs.iter().map(|l| format!("i64 {}", l)).collect();
+    format!("[{}]", values.join(", "))
+}
+
+pub fn generate_module_prologue(producer: &LLVMProducer) -> Vec<LLVMInstruction> {
+    let mut code = vec![];
+    code.push(format!("; witness generator of {} produced by circom {}.{}.{}",
+        producer.get_main_header(), producer.major_version, producer.minor_version, producer.patch_version));
+    code.push(format!("{} = type [{} x i64]", FR_ELEMENT, producer.get_size_64_bit()));
+    code.push(format!("{} = type {{ i32, i32, i32, i32, ptr, ptr, ptr }}", COMPONENT));
+    code.push(format!("{} = type {{ ptr, i32, i32 }}", INPUT_DEF));
+    code.push(format!("{} = type {{ ptr, i32, i32, ptr }}", ASSERT_MESSAGE));
+    code.push(format!("{} = type {{ i32, ptr }}", IO_DEF));
+    code.push("".to_string());
+    code.push(format!("{} = external global ptr", G_SIGNALS));
+    code.push(format!("{} = external global ptr", G_COMPONENTS));
+    code.push("".to_string());
+    code.push("declare void @llvm.memcpy.p0.p0.i64(ptr, ptr, i64, i1)".to_string());
+    for op in FR_BINARY_OPERATIONS {
+        code.push(format!("declare void @{}(ptr, ptr, ptr)", op));
+    }
+    for op in FR_UNARY_OPERATIONS {
+        code.push(format!("declare void @{}(ptr, ptr)", op));
+    }
+    code.push("declare void @Fr_eqn(ptr, ptr, ptr, i32)".to_string());
+    code.push("declare i32 @Fr_isTrue(ptr)".to_string());
+    code.push("declare i32 @Fr_toInt(ptr)".to_string());
+    code.push("declare ptr @circom_new_subcomponents(i32)".to_string());
+    code.push("declare ptr @circom_component_name(ptr, i32, ptr, i32)".to_string());
+    code.push("declare void @circom_assert_failed(ptr, i32, i32, i32)".to_string());
+    code.push("declare void @circom_assert_signal(ptr, ptr)".to_string());
+    code.push("declare void @circom_abort() noreturn".to_string());
+    code.push("declare void @circom_log_element(ptr)".to_string());
+    code.push("declare void @circom_log_string(ptr)".to_string());
+    code.push("declare void @circom_log_separator()".to_string());
+    code.push("declare void @circom_log_end()".to_string());
+    code.push("".to_string());
+    code
+}
+
+const FR_BINARY_OPERATIONS: [&str; 20] = [
+    "Fr_add", "Fr_sub", "Fr_mul", "Fr_div", "Fr_pow", "Fr_idiv", "Fr_mod", "Fr_shl", "Fr_shr",
+    "Fr_band", "Fr_bor", "Fr_bxor", "Fr_eq", "Fr_neq", "Fr_lt", "Fr_gt", "Fr_leq", "Fr_geq",
+    "Fr_land", "Fr_lor",
+];
+const FR_UNARY_OPERATIONS: [&str; 4] = ["Fr_copy", "Fr_neg", "Fr_lnot", "Fr_bnot"];
+
+pub fn generate_constant_table(producer: &LLVMProducer) -> Vec<LLVMInstruction> {
+    let prime = producer.get_prime().parse::<BigInt>().unwrap();
+    let constants = producer.get_field_constant_list();
+    let mut elements = vec![];
+    for constant in constants {
+        let value = constant.parse::<BigInt>().unwrap();
+        let limbs = limbs(&value, &prime, producer.get_size_64_bit());
+        elements.push(element_literal(&limbs));
+    }
+    vec![format!("{} = private unnamed_addr constant {}", G_CONSTANTS, array_constant(FR_ELEMENT, elements))]
+}
+
+// Templates are indexed by their id, used to run subcomponents only known at execution
+pub fn generate_run_table(producer: &LLVMProducer) -> Vec<LLVMInstruction> {
+    let functions: Vec<String> =
+        producer.get_template_instance_list().iter().map(|t| format!("@{}_run", t)).collect();
+    vec![format!("{} = private unnamed_addr constant {}", G_RUN_TABLE, array_constant("ptr", functions))]
+}
+
+pub fn generate_io_map(producer: &LLVMProducer) -> Vec<LLVMInstruction> {
+    let mut code = vec![];
+    let mut entries = vec!["null".to_string(); producer.get_number_of_template_instances()];
+    for (template_id, defs) in producer.get_io_map() {
+        let mut def_values = vec![];
+        for (code_id, def) in defs.iter().enumerate() {
+            let lengths_name = format!("@circom_io.{}.{}", template_id, code_id);
+            let lengths: Vec<String> = def.lengths.iter().map(|l| l.to_string()).collect();
+            code.push(format!("{} = private unnamed_addr constant {}", lengths_name, array_constant("i32", lengths)));
+            def_values.push(format!("{{ i32 {}, ptr {} }}", def.offset, lengths_name));
+        }
+        let defs_name = format!("@circom_io.{}", template_id);
+        code.push(format!("{} = private unnamed_addr constant {}", defs_name, array_constant(IO_DEF, def_values)));
+        entries[*template_id] = defs_name;
+    }
+    code.push(format!("{} = private unnamed_addr constant {}", G_IO_MAP, array_constant("ptr", entries)));
+    code
+}
+
+// Tables read by the driver in main.c
+pub fn generate_circuit_description(producer: &LLVMProducer) -> Vec<LLVMInstruction> {
+    let mut code = vec![];
+    code.push(format!("@circom_number_of_signals = constant i32 {}", producer.get_total_number_of_signals()));
+    code.push(format!("@circom_number_of_components = constant i32 {}", producer.get_number_of_components()));
+    code.push(format!("@circom_number_of_main_inputs = constant i32 {}", producer.get_number_of_main_inputs()));
+
+    let mut inputs = vec![];
+    for (i, (name, signal_id, size)) in producer.get_main_input_list().iter().enumerate() {
+        let input_name = format!("@circom_input.{}", i);
+        code.push(string_global(&input_name, name));
+        inputs.push(format!("{{ ptr {}, i32 {}, i32 {} }}", input_name, signal_id, size));
+    }
+    code.push(format!("@circom_number_of_input_defs = constant i32 {}", inputs.len()));
+    code.push(format!("@circom_input_defs = constant {}", array_constant(INPUT_DEF, inputs)));
+
+    let witness: Vec<String> = producer.get_witness_to_signal_list().iter().map(|s| s.to_string()).collect();
+    code.push(format!("@circom_witness_size = constant i32 {}", witness.len()));
+    code.push(format!("@circom_witness_to_signal = constant {}", array_constant("i32", witness)));
+
+    let mut messages = vec![];
+    for (i, message) in producer.get_assert_message_list().iter().enumerate() {
+        let file_global = format!("@circom_assert.{}.file", i);
+        let expression_global = format!("@circom_assert.{}.expression", i);
+        code.push(string_global(&file_global, &message.file));
+        code.push(string_global(&expression_global, &message.expression));
+        for (j, signal) in message.signals.iter().enumerate() {
+            code.push(string_global(&assert_signal_label_global(i, j), signal));
+        }
+        messages.push(format!(
+            "{{ ptr {}, i32 {}, i32 {}, ptr {} }}",
+            file_global, message.line, message.column, expression_global
+        ));
+    }
+    code.push(format!("@circom_assert_messages = constant {}", array_constant(ASSERT_MESSAGE, messages)));
+
+    for (i, string) in producer.get_string_table().iter().enumerate() {
+        code.push(string_global(&string_table_global(i), string));
+    }
+    code.push(string_global("@circom_main_name", "main"));
+    code
+}
+
+// Creates the main component and runs it once the inputs are set
+pub fn generate_run_function(producer: &LLVMProducer) -> LLVMInstruction {
+    let main = producer.get_main_header();
+    let mut body = vec![label("entry")];
+    body.push(build_call(
+        &format!("@{}_create", main),
+        vec![
+            typed("i32", &producer.main_signal_offset.to_string()),
+            typed("i32", "0"),
+            typed("ptr", "@circom_main_name"),
+            typed("i32", "0"),
+        ],
+    ));
+    // templates without inputs are run when created
+    if producer.get_number_of_main_inputs() > 0 {
+        body.push(build_call(&format!("@{}_run", main), vec![typed("i32", "0")]));
+    }
+    body.push("ret void".to_string());
+    build_function("define void @circom_run()".to_string(), body)
+}
+
+pub fn merge_code(instructions: Vec<LLVMInstruction>) -> String {
+    let mut code = String::new();
+    for instruction in instructions {
+        code.push_str(&instruction);
+        code.push('\n');
+    }
+    code
+}
+
+fn write_common_file(llvm_folder: &Path, name: &str, content: &str) -> std::io::Result<()> {
+    use std::io::BufWriter;
+    let file_path = llvm_folder.join(name);
+    let mut file = BufWriter::new(File::create(file_path)?);
+    file.write_all(content.as_bytes())?;
+    file.flush()?;
+    Ok(())
+}
+
+pub fn generate_main_c_file(llvm_folder: &Path) -> std::io::Result<()> {
+    write_common_file(llvm_folder, "main.c", include_str!("common/main.c"))
+}
+
+pub fn generate_circom_h_file(llvm_folder: &Path) -> std::io::Result<()> {
+    write_common_file(llvm_folder, "circom.h", include_str!("common/circom.h"))
+}
+
+pub fn generate_fr_c_file(llvm_folder: &Path) -> std::io::Result<()> {
+    write_common_file(llvm_folder, "fr.c", include_str!("common/fr.c"))
+}
+
+// The runtime is specialized for the prime through the constants of fr.h
+pub fn generate_fr_h_file(llvm_folder: &Path, producer: &LLVMProducer) -> std::io::Result<()> {
+    let prime = producer.get_prime().parse::<BigInt>().unwrap();
+    let size = producer.get_size_64_bit();
+    let r2 = BigInt::from(1) << (128 * size);
+    let as_list = |limbs: Vec<u64>| -> String {
+        limbs.iter().map(|l| format!("0x{:016x}ULL", l)).collect::<Vec<_>>().join(", ")
+    };
+    let q = raw_limbs(&prime, size);
+    // -q^(-1) mod 2^64 by Newton iteration, q is odd
+    let mut inverse: u64 = 1;
+    for _ in 0..6 {
+        inverse = inverse.wrapping_mul(2u64.wrapping_sub(q[0].wrapping_mul(inverse)));
+    }
+    let template = handlebars::Handlebars::new();
+    let code = template
+        .render_template(
+            include_str!("common/fr.h"),
+            &json!({
+                "n64": size,
+                "bits": prime.bits(),
+                "prime": producer.get_prime(),
+                "q": as_list(q),
+                "r2": as_list(limbs(&r2, &prime, size)),
+                "np": format!("0x{:016x}ULL", inverse.wrapping_neg()),
+            }),
+        )
+        .expect("must render");
+    write_common_file(llvm_folder, "fr.h", &code)
+}
+
+pub fn generate_make_file(llvm_folder: &Path, run_name: &str) -> std::io::Result<()> {
+    let template = handlebars::Handlebars::new();
+    let code = template
+        .render_template(include_str!("common/makefile"), &json!({ "run_name": run_name }))
+        .expect("must render");
+    write_common_file(llvm_folder, "Makefile", &code)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn string_literals_escape_the_bytes_that_can_not_be_written() {
+        let (ty, literal) = string_literal("main.circom:3:5: a == \"b\"\n");
+        assert_eq!(ty, "[27 x i8]");
+        assert_eq!(literal, "c\"main.circom:3:5: a == \\22b\\22\\0A\\00\"");
+        let (ty, literal) = string_literal("é\\");
+        assert_eq!(ty, "[4 x i8]");
+        assert_eq!(literal, "c\"\\C3\\A9\\5C\\00\"");
+    }
+
+    #[test]
+    fn constants_are_reduced_to_limbs_of_the_field() {
+        let mut producer = LLVMProducer {
+            prime: "7".to_string(),
+            size_64_bit: 1,
+            field_tracking: vec!["-1".to_string(), "10".to_string()],
+            ..LLVMProducer::default()
+        };
+        assert_eq!(
+            generate_constant_table(&producer),
+            vec![format!("{} = private unnamed_addr constant [2 x {}] [{} [i64 6], {} [i64 3]]",
+                G_CONSTANTS, FR_ELEMENT, FR_ELEMENT, FR_ELEMENT)]
+        );
+        // 2^64 + 5 takes two limbs
+        producer.prime = "18446744073709551621".to_string();
+        producer.size_64_bit = 2;
+        producer.field_tracking = vec!["-1".to_string()];
+        assert_eq!(
+            generate_constant_table(&producer),
+            vec![format!("{} = private unnamed_addr constant [1 x {}] [{} [i64 4, i64 1]]",
+                G_CONSTANTS, FR_ELEMENT, FR_ELEMENT)]
+        );
+        producer.field_tracking.clear();
+        assert_eq!(
+            generate_constant_table(&producer),
+            vec![format!("{} = private unnamed_addr constant [0 x {}] zeroinitializer", G_CONSTANTS, FR_ELEMENT)]
+        );
+    }
+}
@@ -16,5 +16,11 @@ if (process.argv.length != 5) {
 	writeFile(process.argv[4], buff, function(err) {
 	    if (err) throw err;
 	});
+	const profile = witnessCalculator.getProfile();
+	if (profile) {
+	    writeFile(process.argv[4] + ".profile.json", JSON.stringify(profile, null, 1), function(err) {
+		if (err) throw err;
+	    });
+	}
     });
 }
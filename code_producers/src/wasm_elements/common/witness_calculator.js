@@ -15,6 +15,10 @@ module.exports = async function builder(code, options) {
 
     let errStr = "";
     let msgStr = "";
+
+    // only used by circuits compiled with --profile-witness
+    const profile = new Map();
+    const profileStack = [];
     
     const instance = await WebAssembly.instantiate(wasmModule, {
         runtime: {
@@ -58,7 +62,25 @@ module.exports = async function builder(code, options) {
 	    },
 	    showSharedRWMemory : function() {
 		printSharedRWMemory ();
-            }
+            },
+	    profileEnter : function(id) {
+		profileStack.push({ id: id, start: performance.now(), children: 0 });
+	    },
+	    profileExit : function(id) {
+		const frame = profileStack.pop();
+		const elapsed = performance.now() - frame.start;
+		let entry = profile.get(frame.id);
+		if (entry === undefined) {
+		    entry = { calls: 0, time: 0, selfTime: 0 };
+		    profile.set(frame.id, entry);
+		}
+		entry.calls++;
+		entry.time += elapsed;
+		entry.selfTime += elapsed - frame.children;
+		if (profileStack.length > 0) {
+		    profileStack[profileStack.length - 1].children += elapsed;
+		}
+	    }
 
         }
     });
@@ -75,9 +97,28 @@ module.exports = async function builder(code, options) {
 //        );
 
     
-    wc = new WitnessCalculator(instance, sanityCheck);
+    wc = new WitnessCalculator(instance, sanityCheck, getProfile);
     return wc;
 
+    function getProfile() {
+	if (instance.exports.getProfileEntry === undefined) {
+	    return null;
+	}
+	const report = { templates: {}, functions: {} };
+	for (const [id, entry] of profile) {
+	    instance.exports.getProfileEntry(id);
+	    // the description is "<kind> <header> <name>"
+	    const [kind, header, name] = getMessage().split(" ");
+	    report[kind + "s"][header] = {
+		name: name,
+		calls: entry.calls,
+		time_ns: Math.round(entry.time * 1e6),
+		self_time_ns: Math.round(entry.selfTime * 1e6)
+	    };
+	}
+	return report;
+    }
+
     function getMessage() {
         var message = "";
 	var c = instance.exports.getMessageChar();
@@ -106,8 +147,9 @@ module.exports = async function builder(code, options) {
 };
 
 class WitnessCalculator {
-    constructor(instance, sanityCheck) {
+    constructor(instance, sanityCheck, getProfile) {
         this.instance = instance;
+        this.getProfileReport = getProfile;
 
 	this.version = this.instance.exports.getVersion();
         this.n32 = this.instance.exports.getFieldNumLen32();
@@ -128,9 +170,16 @@ class WitnessCalculator {
 	return this.instance.exports.getVersion();
     }
 
+    // counters and timers accumulated by the calculations done so far, null if the
+    // circuit was not compiled with --profile-witness
+    getProfile() {
+	return this.getProfileReport();
+    }
+
     async _doCalculateWitness(input, sanityCheck) {
 	//input is assumed to be a map from signals to arrays of bigints
         this.instance.exports.init((this.sanityCheck || sanityCheck) ? 1 : 0);
+        input = flatBuses(input);
         const keys = Object.keys(input);
 	var input_counter = 0;
         keys.forEach( (k) => {
@@ -316,6 +365,39 @@ function flatArray(a) {
     }
 }
 
+// Inputs that are buses are given as objects with the values of their fields,
+// or arrays of them, and are flattened into the signals of the fields (p.x, p[].x)
+function flatBuses(input) {
+    const res = {};
+    Object.keys(input).forEach( (k) => flatBus(k, input[k], res));
+    return res;
+}
+
+function containsObject(a) {
+    while (Array.isArray(a)) {
+        if (a.length == 0) return false;
+        a = a[0];
+    }
+    return typeof a === "object" && a !== null;
+}
+
+function flatBus(name, value, res) {
+    if (Array.isArray(value) && containsObject(value)) {
+        const elements = value.map( (e) => {
+            const fields = {};
+            flatBus(name + "[]", e, fields);
+            return fields;
+        });
+        Object.keys(elements[0]).forEach( (k) => {
+            res[k] = elements.map( (fields) => fields[k]);
+        });
+    } else if (containsObject(value)) {
+        Object.keys(value).forEach( (k) => flatBus(name + "." + k, value[k], res));
+    } else {
+        res[name] = value;
+    }
+}
+
 function normalize(n, prime) {
     let res = BigInt(n) % prime
     if (res < 0) res += prime
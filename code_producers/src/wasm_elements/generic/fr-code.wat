@@ -0,0 +1,606 @@
+;; Field arithmetic for a prime given by value, {{bits}} bits in {{n32}} words of 32 bits.
+;; Raw numbers are little endian and take {{n8}} bytes. The results of the
+;; operations are stored as long normal elements, except for the boolean ones.
+
+(func $Fr_int_copy (param $px i32) (param $pr i32)
+ (local $i i32)
+ (block $done
+  (loop $next
+   (br_if $done (i32.ge_u (local.get $i) (i32.const {{n8}})))
+   (i64.store (i32.add (local.get $pr) (local.get $i))
+    (i64.load (i32.add (local.get $px) (local.get $i))))
+   (local.set $i (i32.add (local.get $i) (i32.const 8)))
+   (br $next)))
+)
+
+(func $Fr_int_zero (param $pr i32)
+ (local $i i32)
+ (block $done
+  (loop $next
+   (br_if $done (i32.ge_u (local.get $i) (i32.const {{n8}})))
+   (i64.store (i32.add (local.get $pr) (local.get $i)) (i64.const 0))
+   (local.set $i (i32.add (local.get $i) (i32.const 8)))
+   (br $next)))
+)
+
+(func $Fr_int_isZero (param $px i32) (result i32)
+ (local $i i32)
+ (block $done
+  (loop $next
+   (br_if $done (i32.ge_u (local.get $i) (i32.const {{n8}})))
+   (if (i64.ne (i64.load (i32.add (local.get $px) (local.get $i))) (i64.const 0))
+    (then (return (i32.const 0))))
+   (local.set $i (i32.add (local.get $i) (i32.const 8)))
+   (br $next)))
+ (i32.const 1)
+)
+
+;; -1, 0 or 1 as a is lesser, equal or greater than b
+(func $Fr_int_cmp (param $pa i32) (param $pb i32) (result i32)
+ (local $i i32)
+ (local $a i64)
+ (local $b i64)
+ (local.set $i (i32.const {{n8}}))
+ (block $done
+  (loop $next
+   (br_if $done (i32.eqz (local.get $i)))
+   (local.set $i (i32.sub (local.get $i) (i32.const 8)))
+   (local.set $a (i64.load (i32.add (local.get $pa) (local.get $i))))
+   (local.set $b (i64.load (i32.add (local.get $pb) (local.get $i))))
+   (if (i64.lt_u (local.get $a) (local.get $b)) (then (return (i32.const -1))))
+   (if (i64.gt_u (local.get $a) (local.get $b)) (then (return (i32.const 1))))
+   (br $next)))
+ (i32.const 0)
+)
+
+;; r = a + b, returns the carry
+(func $Fr_int_add (param $pa i32) (param $pb i32) (param $pr i32) (result i32)
+ (local $i i32)
+ (local $c i64)
+ (block $done
+  (loop $next
+   (br_if $done (i32.ge_u (local.get $i) (i32.const {{n8}})))
+   (local.set $c (i64.add (local.get $c)
+    (i64.add (i64.load32_u (i32.add (local.get $pa) (local.get $i)))
+             (i64.load32_u (i32.add (local.get $pb) (local.get $i))))))
+   (i64.store32 (i32.add (local.get $pr) (local.get $i)) (local.get $c))
+   (local.set $c (i64.shr_u (local.get $c) (i64.const 32)))
+   (local.set $i (i32.add (local.get $i) (i32.const 4)))
+   (br $next)))
+ (i32.wrap_i64 (local.get $c))
+)
+
+;; r = a - b, returns the borrow
+(func $Fr_int_sub (param $pa i32) (param $pb i32) (param $pr i32) (result i32)
+ (local $i i32)
+ (local $d i64)
+ (local $borrow i64)
+ (block $done
+  (loop $next
+   (br_if $done (i32.ge_u (local.get $i) (i32.const {{n8}})))
+   (local.set $d (i64.sub
+    (i64.sub (i64.load32_u (i32.add (local.get $pa) (local.get $i)))
+             (i64.load32_u (i32.add (local.get $pb) (local.get $i))))
+    (local.get $borrow)))
+   (i64.store32 (i32.add (local.get $pr) (local.get $i)) (local.get $d))
+   (local.set $borrow (i64.extend_i32_u (i64.lt_s (local.get $d) (i64.const 0))))
+   (local.set $i (i32.add (local.get $i) (i32.const 4)))
+   (br $next)))
+ (i32.wrap_i64 (local.get $borrow))
+)
+
+;; Position of the highest bit set plus one
+(func $Fr_int_bits (param $pa i32) (result i32)
+ (local $i i32)
+ (local $a i64)
+ (local.set $i (i32.const {{n8}}))
+ (block $done
+  (loop $next
+   (br_if $done (i32.eqz (local.get $i)))
+   (local.set $i (i32.sub (local.get $i) (i32.const 8)))
+   (local.set $a (i64.load (i32.add (local.get $pa) (local.get $i))))
+   (if (i64.ne (local.get $a) (i64.const 0))
+    (then (return (i32.sub (i32.mul (i32.add (local.get $i) (i32.const 8)) (i32.const 8))
+                           (i32.wrap_i64 (i64.clz (local.get $a)))))))
+   (br $next)))
+ (i32.const 0)
+)
+
+(func $Fr_int_bit (param $pa i32) (param $n i32) (result i32)
+ (i32.wrap_i64 (i64.and
+  (i64.shr_u (i64.load (i32.add (local.get $pa) (i32.shl (i32.shr_u (local.get $n) (i32.const 6)) (i32.const 3))))
+             (i64.extend_i32_u (i32.and (local.get $n) (i32.const 63))))
+  (i64.const 1)))
+)
+
+;; r = a << n for n < {{bits}}, r may be a
+(func $Fr_int_shl (param $pa i32) (param $n i32) (param $pr i32)
+ (local $i i32)
+ (local $src i32)
+ (local $bits i64)
+ (local $v i64)
+ (local.set $bits (i64.extend_i32_u (i32.and (local.get $n) (i32.const 63))))
+ (local.set $i (i32.const {{n8}}))
+ (block $done
+  (loop $next
+   (br_if $done (i32.eqz (local.get $i)))
+   (local.set $i (i32.sub (local.get $i) (i32.const 8)))
+   (local.set $src (i32.sub (local.get $i) (i32.shl (i32.shr_u (local.get $n) (i32.const 6)) (i32.const 3))))
+   (local.set $v (i64.const 0))
+   (if (i32.ge_s (local.get $src) (i32.const 0))
+    (then
+     (local.set $v (i64.shl (i64.load (i32.add (local.get $pa) (local.get $src))) (local.get $bits)))
+     (if (i32.and (i64.ne (local.get $bits) (i64.const 0)) (i32.ge_s (local.get $src) (i32.const 8)))
+      (then
+       (local.set $v (i64.or (local.get $v)
+        (i64.shr_u (i64.load (i32.sub (i32.add (local.get $pa) (local.get $src)) (i32.const 8)))
+                   (i64.sub (i64.const 64) (local.get $bits)))))))))
+   (i64.store (i32.add (local.get $pr) (local.get $i)) (local.get $v))
+   (br $next)))
+)
+
+;; r = a >> n for n < {{bits}}, r may be a
+(func $Fr_int_shr (param $pa i32) (param $n i32) (param $pr i32)
+ (local $i i32)
+ (local $src i32)
+ (local $bits i64)
+ (local $v i64)
+ (local.set $bits (i64.extend_i32_u (i32.and (local.get $n) (i32.const 63))))
+ (block $done
+  (loop $next
+   (br_if $done (i32.ge_u (local.get $i) (i32.const {{n8}})))
+   (local.set $src (i32.add (local.get $i) (i32.shl (i32.shr_u (local.get $n) (i32.const 6)) (i32.const 3))))
+   (local.set $v (i64.const 0))
+   (if (i32.lt_u (local.get $src) (i32.const {{n8}}))
+    (then
+     (local.set $v (i64.shr_u (i64.load (i32.add (local.get $pa) (local.get $src))) (local.get $bits)))
+     (if (i32.and (i64.ne (local.get $bits) (i64.const 0)) (i32.lt_u (i32.add (local.get $src) (i32.const 8)) (i32.const {{n8}})))
+      (then
+       (local.set $v (i64.or (local.get $v)
+        (i64.shl (i64.load (i32.add (i32.add (local.get $pa) (local.get $src)) (i32.const 8)))
+                 (i64.sub (i64.const 64) (local.get $bits)))))))))
+   (i64.store (i32.add (local.get $pr) (local.get $i)) (local.get $v))
+   (local.set $i (i32.add (local.get $i) (i32.const 8)))
+   (br $next)))
+)
+
+;; Brings a value below 2q back to [0, q)
+(func $Fr_int_reduce (param $pr i32) (param $carry i32)
+ (if (i32.or (local.get $carry) (i32.ge_s (call $Fr_int_cmp (local.get $pr) (i32.const {{q}})) (i32.const 0)))
+  (then (drop (call $Fr_int_sub (local.get $pr) (i32.const {{q}}) (local.get $pr)))))
+)
+
+;; Keeps the lowest {{bits}} bits
+(func $Fr_int_mask (param $pr i32)
+ (local $i i32)
+ (block $done
+  (loop $next
+   (br_if $done (i32.ge_u (local.get $i) (i32.const {{n8}})))
+   (i64.store (i32.add (local.get $pr) (local.get $i))
+    (i64.and (i64.load (i32.add (local.get $pr) (local.get $i)))
+             (i64.load (i32.add (i32.const {{mask}}) (local.get $i)))))
+   (local.set $i (i32.add (local.get $i) (i32.const 8)))
+   (br $next)))
+)
+
+;; Montgomery product a*b/R mod q (CIOS with 32 bit words), r may be a or b
+(func $Fr_int_mmul (param $pa i32) (param $pb i32) (param $pr i32)
+ (local $i i32)
+ (local $j i32)
+ (local $b i64)
+ (local $m i64)
+ (local $c i64)
+ (local.set $j (i32.const 0))
+ (block $done
+  (loop $next
+   (br_if $done (i32.ge_u (local.get $j) (i32.const {{mt_size}})))
+   (i32.store (i32.add (i32.const {{mt}}) (local.get $j)) (i32.const 0))
+   (local.set $j (i32.add (local.get $j) (i32.const 4)))
+   (br $next)))
+ (block $done_i
+  (loop $next_i
+   (br_if $done_i (i32.ge_u (local.get $i) (i32.const {{n8}})))
+   (local.set $b (i64.load32_u (i32.add (local.get $pb) (local.get $i))))
+   (local.set $c (i64.const 0))
+   (local.set $j (i32.const 0))
+   (block $done_a
+    (loop $next_a
+     (br_if $done_a (i32.ge_u (local.get $j) (i32.const {{n8}})))
+     (local.set $c (i64.add (local.get $c)
+      (i64.add (i64.mul (i64.load32_u (i32.add (local.get $pa) (local.get $j))) (local.get $b))
+               (i64.load32_u (i32.add (i32.const {{mt}}) (local.get $j))))))
+     (i64.store32 (i32.add (i32.const {{mt}}) (local.get $j)) (local.get $c))
+     (local.set $c (i64.shr_u (local.get $c) (i64.const 32)))
+     (local.set $j (i32.add (local.get $j) (i32.const 4)))
+     (br $next_a)))
+   (local.set $c (i64.add (local.get $c) (i64.load32_u (i32.const {{mt_top}}))))
+   (i64.store32 (i32.const {{mt_top}}) (local.get $c))
+   (i64.store32 (i32.const {{mt_carry}}) (i64.shr_u (local.get $c) (i64.const 32)))
+   (local.set $m (i64.and (i64.mul (i64.load32_u (i32.const {{mt}})) (i64.const {{np}})) (i64.const 0xffffffff)))
+   (local.set $c (i64.shr_u
+    (i64.add (i64.mul (local.get $m) (i64.load32_u (i32.const {{q}}))) (i64.load32_u (i32.const {{mt}})))
+    (i64.const 32)))
+   (local.set $j (i32.const 4))
+   (block $done_q
+    (loop $next_q
+     (br_if $done_q (i32.ge_u (local.get $j) (i32.const {{n8}})))
+     (local.set $c (i64.add (local.get $c)
+      (i64.add (i64.mul (i64.load32_u (i32.add (i32.const {{q}}) (local.get $j))) (local.get $m))
+               (i64.load32_u (i32.add (i32.const {{mt}}) (local.get $j))))))
+     (i64.store32 (i32.sub (i32.add (i32.const {{mt}}) (local.get $j)) (i32.const 4)) (local.get $c))
+     (local.set $c (i64.shr_u (local.get $c) (i64.const 32)))
+     (local.set $j (i32.add (local.get $j) (i32.const 4)))
+     (br $next_q)))
+   (local.set $c (i64.add (local.get $c) (i64.load32_u (i32.const {{mt_top}}))))
+   (i64.store32 (i32.sub (i32.const {{mt_top}}) (i32.const 4)) (local.get $c))
+   (i64.store32 (i32.const {{mt_top}})
+    (i64.add (i64.load32_u (i32.const {{mt_carry}})) (i64.shr_u (local.get $c) (i64.const 32))))
+   (local.set $i (i32.add (local.get $i) (i32.const 4)))
+   (br $next_i)))
+ (call $Fr_int_copy (i32.const {{mt}}) (local.get $pr))
+ (call $Fr_int_reduce (local.get $pr) (i32.load (i32.const {{mt_top}})))
+)
+
+;; r = a*b mod q in standard form, r may be a or b
+(func $Fr_int_mul (param $pa i32) (param $pb i32) (param $pr i32)
+ (call $Fr_int_mmul (local.get $pa) (local.get $pb) (local.get $pr))
+ (call $Fr_int_mmul (local.get $pr) (i32.const {{r2}}) (local.get $pr))
+)
+
+;; r = a^e mod q, r must not be a or e
+(func $Fr_int_pow (param $pa i32) (param $pe i32) (param $pr i32)
+ (local $i i32)
+ (call $Fr_int_copy (i32.const {{one}}) (local.get $pr))
+ (local.set $i (call $Fr_int_bits (local.get $pe)))
+ (block $done
+  (loop $next
+   (br_if $done (i32.eqz (local.get $i)))
+   (local.set $i (i32.sub (local.get $i) (i32.const 1)))
+   (call $Fr_int_mul (local.get $pr) (local.get $pr) (local.get $pr))
+   (if (call $Fr_int_bit (local.get $pe) (local.get $i))
+    (then (call $Fr_int_mul (local.get $pr) (local.get $pa) (local.get $pr))))
+   (br $next)))
+)
+
+;; Integer division of the representatives, b must not be zero and the results must not be a or b
+(func $Fr_int_divmod (param $pa i32) (param $pb i32) (param $pq i32) (param $prem i32)
+ (local $i i32)
+ (local $out i32)
+ (call $Fr_int_zero (local.get $pq))
+ (call $Fr_int_zero (local.get $prem))
+ (local.set $i (call $Fr_int_bits (local.get $pa)))
+ (block $done
+  (loop $next
+   (br_if $done (i32.eqz (local.get $i)))
+   (local.set $i (i32.sub (local.get $i) (i32.const 1)))
+   (local.set $out (i32.wrap_i64 (i64.shr_u (i64.load (i32.add (local.get $prem) (i32.const {{n8_last}}))) (i64.const 63))))
+   (call $Fr_int_shl (local.get $prem) (i32.const 1) (local.get $prem))
+   (i32.store (local.get $prem)
+    (i32.or (i32.load (local.get $prem)) (call $Fr_int_bit (local.get $pa) (local.get $i))))
+   (if (i32.or (local.get $out) (i32.ge_s (call $Fr_int_cmp (local.get $prem) (local.get $pb)) (i32.const 0)))
+    (then
+     (drop (call $Fr_int_sub (local.get $prem) (local.get $pb) (local.get $prem)))
+     (i64.store (i32.add (local.get $pq) (i32.shl (i32.shr_u (local.get $i) (i32.const 6)) (i32.const 3)))
+      (i64.or (i64.load (i32.add (local.get $pq) (i32.shl (i32.shr_u (local.get $i) (i32.const 6)) (i32.const 3))))
+              (i64.shl (i64.const 1) (i64.extend_i32_u (i32.and (local.get $i) (i32.const 63))))))))
+   (br $next)))
+)
+
+;; Elements above q/2 represent negative numbers
+(func $Fr_int_isNeg (param $pa i32) (result i32)
+ (i32.gt_s (call $Fr_int_cmp (local.get $pa) (i32.const {{half}})) (i32.const 0))
+)
+
+(func $Fr_int_lt (param $pa i32) (param $pb i32) (result i32)
+ (local $na i32)
+ (local.set $na (call $Fr_int_isNeg (local.get $pa)))
+ (if (i32.ne (local.get $na) (call $Fr_int_isNeg (local.get $pb)))
+  (then (return (local.get $na))))
+ (i32.lt_s (call $Fr_int_cmp (local.get $pa) (local.get $pb)) (i32.const 0))
+)
+
+;; Amount of a shift that is known to be small, {{bits}} otherwise. r gets |b|
+(func $Fr_int_shiftAmount (param $pb i32) (param $pr i32) (result i32)
+ (if (call $Fr_int_isNeg (local.get $pb))
+  (then (drop (call $Fr_int_sub (i32.const {{q}}) (local.get $pb) (local.get $pr))))
+  (else (call $Fr_int_copy (local.get $pb) (local.get $pr))))
+ (if (i32.gt_u (call $Fr_int_bits (local.get $pr)) (i32.const 31)) (then (return (i32.const {{bits}}))))
+ (if (i32.gt_u (i32.load (local.get $pr)) (i32.const {{bits}})) (then (return (i32.const {{bits}}))))
+ (i32.load (local.get $pr))
+)
+
+(func $Fr_int_shiftLeft (param $pa i32) (param $n i32) (param $pr i32)
+ (if (i32.ge_u (local.get $n) (i32.const {{bits}}))
+  (then (call $Fr_int_zero (local.get $pr)))
+  (else
+   (call $Fr_int_shl (local.get $pa) (local.get $n) (local.get $pr))
+   (call $Fr_int_mask (local.get $pr))
+   (call $Fr_int_reduce (local.get $pr) (i32.const 0))))
+)
+
+(func $Fr_int_shiftRight (param $pa i32) (param $n i32) (param $pr i32)
+ (if (i32.ge_u (local.get $n) (i32.const {{bits}}))
+  (then (call $Fr_int_zero (local.get $pr)))
+  (else (call $Fr_int_shr (local.get $pa) (local.get $n) (local.get $pr))))
+)
+
+;; Representative in [0, q) of the element at pe in standard form
+(func $Fr_load (param $pe i32) (param $pr i32)
+ (local $type i32)
+ (local $s i32)
+ (local.set $type (i32.load offset=4 (local.get $pe)))
+ (if (i32.eqz (i32.and (local.get $type) (i32.const 0x80000000)))
+  (then
+   (local.set $s (i32.load (local.get $pe)))
+   (call $Fr_int_zero (local.get $pr))
+   (if (i32.ge_s (local.get $s) (i32.const 0))
+    (then (i64.store (local.get $pr) (i64.extend_i32_u (local.get $s))))
+    (else
+     (i64.store (local.get $pr) (i64.sub (i64.const 0) (i64.extend_i32_s (local.get $s))))
+     (drop (call $Fr_int_sub (i32.const {{q}}) (local.get $pr) (local.get $pr))))))
+  (else
+   (if (i32.and (local.get $type) (i32.const 0x40000000))
+    (then (call $Fr_int_mmul (i32.add (local.get $pe) (i32.const 8)) (i32.const {{one}}) (local.get $pr)))
+    (else (call $Fr_int_copy (i32.add (local.get $pe) (i32.const 8)) (local.get $pr))))))
+)
+
+(func $Fr_store (param $pe i32) (param $px i32)
+ (i32.store (local.get $pe) (i32.const 0))
+ (i32.store offset=4 (local.get $pe) (i32.const 0x80000000))
+ (call $Fr_int_copy (local.get $px) (i32.add (local.get $pe) (i32.const 8)))
+)
+
+(func $Fr_storeBool (param $pe i32) (param $v i32)
+ (i32.store (local.get $pe) (local.get $v))
+ (i32.store offset=4 (local.get $pe) (i32.const 0))
+)
+
+(func $Fr_copy (param $pr i32) (param $px i32)
+ (i64.store (local.get $pr) (i64.load (local.get $px)))
+ (call $Fr_int_copy (i32.add (local.get $px) (i32.const 8)) (i32.add (local.get $pr) (i32.const 8)))
+)
+
+(func $Fr_copyn (param $pr i32) (param $px i32) (param $n i32)
+ (block $done
+  (loop $next
+   (br_if $done (i32.eqz (local.get $n)))
+   (call $Fr_copy (local.get $pr) (local.get $px))
+   (local.set $pr (i32.add (local.get $pr) (i32.const {{element_size}})))
+   (local.set $px (i32.add (local.get $px) (i32.const {{element_size}})))
+   (local.set $n (i32.sub (local.get $n) (i32.const 1)))
+   (br $next)))
+)
+
+(func $Fr_toLongNormal (param $pr i32)
+ (call $Fr_load (local.get $pr) (i32.const {{ta}}))
+ (call $Fr_store (local.get $pr) (i32.const {{ta}}))
+)
+
+(func $Fr_toInt (param $pa i32) (result i32)
+ (if (i32.eqz (i32.and (i32.load offset=4 (local.get $pa)) (i32.const 0x80000000)))
+  (then (return (i32.load (local.get $pa)))))
+ (call $Fr_load (local.get $pa) (i32.const {{ta}}))
+ (if (call $Fr_int_isNeg (i32.const {{ta}}))
+  (then
+   (drop (call $Fr_int_sub (i32.const {{q}}) (i32.const {{ta}}) (i32.const {{ta}})))
+   (return (i32.sub (i32.const 0) (i32.load (i32.const {{ta}}))))))
+ (i32.load (i32.const {{ta}}))
+)
+
+(func $Fr_isTrue (param $pa i32) (result i32)
+ (call $Fr_load (local.get $pa) (i32.const {{ta}}))
+ (i32.eqz (call $Fr_int_isZero (i32.const {{ta}})))
+)
+
+(func $Fr_eqR (param $pa i32) (param $pb i32) (result i32)
+ (call $Fr_load (local.get $pa) (i32.const {{ta}}))
+ (call $Fr_load (local.get $pb) (i32.const {{tb}}))
+ (i32.eqz (call $Fr_int_cmp (i32.const {{ta}}) (i32.const {{tb}})))
+)
+
+(func $Fr_add (param $pr i32) (param $pa i32) (param $pb i32)
+ (call $Fr_load (local.get $pa) (i32.const {{ta}}))
+ (call $Fr_load (local.get $pb) (i32.const {{tb}}))
+ (call $Fr_int_reduce (i32.const {{tr}})
+  (call $Fr_int_add (i32.const {{ta}}) (i32.const {{tb}}) (i32.const {{tr}})))
+ (call $Fr_store (local.get $pr) (i32.const {{tr}}))
+)
+
+(func $Fr_sub (param $pr i32) (param $pa i32) (param $pb i32)
+ (call $Fr_load (local.get $pa) (i32.const {{ta}}))
+ (call $Fr_load (local.get $pb) (i32.const {{tb}}))
+ (if (call $Fr_int_sub (i32.const {{ta}}) (i32.const {{tb}}) (i32.const {{tr}}))
+  (then (drop (call $Fr_int_add (i32.const {{tr}}) (i32.const {{q}}) (i32.const {{tr}})))))
+ (call $Fr_store (local.get $pr) (i32.const {{tr}}))
+)
+
+(func $Fr_neg (param $pr i32) (param $pa i32)
+ (call $Fr_load (local.get $pa) (i32.const {{ta}}))
+ (if (i32.eqz (call $Fr_int_isZero (i32.const {{ta}})))
+  (then (drop (call $Fr_int_sub (i32.const {{q}}) (i32.const {{ta}}) (i32.const {{ta}})))))
+ (call $Fr_store (local.get $pr) (i32.const {{ta}}))
+)
+
+(func $Fr_mul (param $pr i32) (param $pa i32) (param $pb i32)
+ (call $Fr_load (local.get $pa) (i32.const {{ta}}))
+ (call $Fr_load (local.get $pb) (i32.const {{tb}}))
+ (call $Fr_int_mul (i32.const {{ta}}) (i32.const {{tb}}) (i32.const {{tr}}))
+ (call $Fr_store (local.get $pr) (i32.const {{tr}}))
+)
+
+(func $Fr_pow (param $pr i32) (param $pa i32) (param $pb i32)
+ (call $Fr_load (local.get $pa) (i32.const {{ta}}))
+ (call $Fr_load (local.get $pb) (i32.const {{tb}}))
+ (call $Fr_int_pow (i32.const {{ta}}) (i32.const {{tb}}) (i32.const {{tr}}))
+ (call $Fr_store (local.get $pr) (i32.const {{tr}}))
+)
+
+;; a^(q-2), zero has no inverse and gives zero
+(func $Fr_int_inv (param $pa i32) (param $pr i32)
+ (call $Fr_int_zero (i32.const {{tx}}))
+ (i32.store (i32.const {{tx}}) (i32.const 2))
+ (drop (call $Fr_int_sub (i32.const {{q}}) (i32.const {{tx}}) (i32.const {{tx}})))
+ (call $Fr_int_pow (local.get $pa) (i32.const {{tx}}) (local.get $pr))
+)
+
+(func $Fr_inv (param $pr i32) (param $pa i32)
+ (call $Fr_load (local.get $pa) (i32.const {{ta}}))
+ (call $Fr_int_inv (i32.const {{ta}}) (i32.const {{tr}}))
+ (call $Fr_store (local.get $pr) (i32.const {{tr}}))
+)
+
+(func $Fr_div (param $pr i32) (param $pa i32) (param $pb i32)
+ (call $Fr_load (local.get $pa) (i32.const {{ta}}))
+ (call $Fr_load (local.get $pb) (i32.const {{tb}}))
+ (call $Fr_int_inv (i32.const {{tb}}) (i32.const {{tr}}))
+ (call $Fr_int_mul (i32.const {{ta}}) (i32.const {{tr}}) (i32.const {{tr}}))
+ (call $Fr_store (local.get $pr) (i32.const {{tr}}))
+)
+
+(func $Fr_idiv (param $pr i32) (param $pa i32) (param $pb i32)
+ (call $Fr_load (local.get $pa) (i32.const {{ta}}))
+ (call $Fr_load (local.get $pb) (i32.const {{tb}}))
+ (if (call $Fr_int_isZero (i32.const {{tb}})) (then (unreachable)))
+ (call $Fr_int_divmod (i32.const {{ta}}) (i32.const {{tb}}) (i32.const {{tr}}) (i32.const {{tx}}))
+ (call $Fr_store (local.get $pr) (i32.const {{tr}}))
+)
+
+(func $Fr_mod (param $pr i32) (param $pa i32) (param $pb i32)
+ (call $Fr_load (local.get $pa) (i32.const {{ta}}))
+ (call $Fr_load (local.get $pb) (i32.const {{tb}}))
+ (if (call $Fr_int_isZero (i32.const {{tb}})) (then (unreachable)))
+ (call $Fr_int_divmod (i32.const {{ta}}) (i32.const {{tb}}) (i32.const {{tx}}) (i32.const {{tr}}))
+ (call $Fr_store (local.get $pr) (i32.const {{tr}}))
+)
+
+(func $Fr_shl (param $pr i32) (param $pa i32) (param $pb i32)
+ (local $n i32)
+ (call $Fr_load (local.get $pa) (i32.const {{ta}}))
+ (call $Fr_load (local.get $pb) (i32.const {{tb}}))
+ (local.set $n (call $Fr_int_shiftAmount (i32.const {{tb}}) (i32.const {{tx}})))
+ (if (call $Fr_int_isNeg (i32.const {{tb}}))
+  (then (call $Fr_int_shiftRight (i32.const {{ta}}) (local.get $n) (i32.const {{tr}})))
+  (else (call $Fr_int_shiftLeft (i32.const {{ta}}) (local.get $n) (i32.const {{tr}}))))
+ (call $Fr_store (local.get $pr) (i32.const {{tr}}))
+)
+
+(func $Fr_shr (param $pr i32) (param $pa i32) (param $pb i32)
+ (local $n i32)
+ (call $Fr_load (local.get $pa) (i32.const {{ta}}))
+ (call $Fr_load (local.get $pb) (i32.const {{tb}}))
+ (local.set $n (call $Fr_int_shiftAmount (i32.const {{tb}}) (i32.const {{tx}})))
+ (if (call $Fr_int_isNeg (i32.const {{tb}}))
+  (then (call $Fr_int_shiftLeft (i32.const {{ta}}) (local.get $n) (i32.const {{tr}})))
+  (else (call $Fr_int_shiftRight (i32.const {{ta}}) (local.get $n) (i32.const {{tr}}))))
+ (call $Fr_store (local.get $pr) (i32.const {{tr}}))
+)
+
+(func $Fr_band (param $pr i32) (param $pa i32) (param $pb i32)
+ (local $i i32)
+ (call $Fr_load (local.get $pa) (i32.const {{ta}}))
+ (call $Fr_load (local.get $pb) (i32.const {{tb}}))
+ (block $done
+  (loop $next
+   (br_if $done (i32.ge_u (local.get $i) (i32.const {{n8}})))
+   (i64.store (i32.add (i32.const {{tr}}) (local.get $i))
+    (i64.and (i64.load (i32.add (i32.const {{ta}}) (local.get $i)))
+             (i64.load (i32.add (i32.const {{tb}}) (local.get $i)))))
+   (local.set $i (i32.add (local.get $i) (i32.const 8)))
+   (br $next)))
+ (call $Fr_store (local.get $pr) (i32.const {{tr}}))
+)
+
+(func $Fr_bor (param $pr i32) (param $pa i32) (param $pb i32)
+ (local $i i32)
+ (call $Fr_load (local.get $pa) (i32.const {{ta}}))
+ (call $Fr_load (local.get $pb) (i32.const {{tb}}))
+ (block $done
+  (loop $next
+   (br_if $done (i32.ge_u (local.get $i) (i32.const {{n8}})))
+   (i64.store (i32.add (i32.const {{tr}}) (local.get $i))
+    (i64.or (i64.load (i32.add (i32.const {{ta}}) (local.get $i)))
+            (i64.load (i32.add (i32.const {{tb}}) (local.get $i)))))
+   (local.set $i (i32.add (local.get $i) (i32.const 8)))
+   (br $next)))
+ (call $Fr_int_reduce (i32.const {{tr}}) (i32.const 0))
+ (call $Fr_store (local.get $pr) (i32.const {{tr}}))
+)
+
+(func $Fr_bxor (param $pr i32) (param $pa i32) (param $pb i32)
+ (local $i i32)
+ (call $Fr_load (local.get $pa) (i32.const {{ta}}))
+ (call $Fr_load (local.get $pb) (i32.const {{tb}}))
+ (block $done
+  (loop $next
+   (br_if $done (i32.ge_u (local.get $i) (i32.const {{n8}})))
+   (i64.store (i32.add (i32.const {{tr}}) (local.get $i))
+    (i64.xor (i64.load (i32.add (i32.const {{ta}}) (local.get $i)))
+             (i64.load (i32.add (i32.const {{tb}}) (local.get $i)))))
+   (local.set $i (i32.add (local.get $i) (i32.const 8)))
+   (br $next)))
+ (call $Fr_int_reduce (i32.const {{tr}}) (i32.const 0))
+ (call $Fr_store (local.get $pr) (i32.const {{tr}}))
+)
+
+(func $Fr_bnot (param $pr i32) (param $pa i32)
+ (local $i i32)
+ (call $Fr_load (local.get $pa) (i32.const {{ta}}))
+ (block $done
+  (loop $next
+   (br_if $done (i32.ge_u (local.get $i) (i32.const {{n8}})))
+   (i64.store (i32.add (i32.const {{tr}}) (local.get $i))
+    (i64.xor (i64.load (i32.add (i32.const {{ta}}) (local.get $i))) (i64.const -1)))
+   (local.set $i (i32.add (local.get $i) (i32.const 8)))
+   (br $next)))
+ (call $Fr_int_mask (i32.const {{tr}}))
+ (call $Fr_int_reduce (i32.const {{tr}}) (i32.const 0))
+ (call $Fr_store (local.get $pr) (i32.const {{tr}}))
+)
+
+(func $Fr_eq (param $pr i32) (param $pa i32) (param $pb i32)
+ (call $Fr_storeBool (local.get $pr) (call $Fr_eqR (local.get $pa) (local.get $pb)))
+)
+
+(func $Fr_neq (param $pr i32) (param $pa i32) (param $pb i32)
+ (call $Fr_storeBool (local.get $pr) (i32.eqz (call $Fr_eqR (local.get $pa) (local.get $pb))))
+)
+
+(func $Fr_lt (param $pr i32) (param $pa i32) (param $pb i32)
+ (call $Fr_load (local.get $pa) (i32.const {{ta}}))
+ (call $Fr_load (local.get $pb) (i32.const {{tb}}))
+ (call $Fr_storeBool (local.get $pr) (call $Fr_int_lt (i32.const {{ta}}) (i32.const {{tb}})))
+)
+
+(func $Fr_gt (param $pr i32) (param $pa i32) (param $pb i32)
+ (call $Fr_load (local.get $pa) (i32.const {{ta}}))
+ (call $Fr_load (local.get $pb) (i32.const {{tb}}))
+ (call $Fr_storeBool (local.get $pr) (call $Fr_int_lt (i32.const {{tb}}) (i32.const {{ta}})))
+)
+
+(func $Fr_leq (param $pr i32) (param $pa i32) (param $pb i32)
+ (call $Fr_load (local.get $pa) (i32.const {{ta}}))
+ (call $Fr_load (local.get $pb) (i32.const {{tb}}))
+ (call $Fr_storeBool (local.get $pr) (i32.eqz (call $Fr_int_lt (i32.const {{tb}}) (i32.const {{ta}}))))
+)
+
+(func $Fr_geq (param $pr i32) (param $pa i32) (param $pb i32)
+ (call $Fr_load (local.get $pa) (i32.const {{ta}}))
+ (call $Fr_load (local.get $pb) (i32.const {{tb}}))
+ (call $Fr_storeBool (local.get $pr) (i32.eqz (call $Fr_int_lt (i32.const {{ta}}) (i32.const {{tb}}))))
+)
+
+(func $Fr_land (param $pr i32) (param $pa i32) (param $pb i32)
+ (local $a i32)
+ (local.set $a (call $Fr_isTrue (local.get $pa)))
+ (call $Fr_storeBool (local.get $pr) (i32.and (local.get $a) (call $Fr_isTrue (local.get $pb))))
+)
+
+(func $Fr_lor (param $pr i32) (param $pa i32) (param $pb i32)
+ (local $a i32)
+ (local.set $a (call $Fr_isTrue (local.get $pa)))
+ (call $Fr_storeBool (local.get $pr) (i32.or (local.get $a) (call $Fr_isTrue (local.get $pb))))
+)
+
+(func $Fr_lnot (param $pr i32) (param $pa i32)
+ (call $Fr_storeBool (local.get $pr) (i32.eqz (call $Fr_isTrue (local.get $pa))))
+)
@@ -28,6 +28,7 @@ pub struct WASMProducer {
     pub major_version: usize,
     pub minor_version: usize,
     pub patch_version: usize,
+    pub profile_witness: bool,
     stack_free_pos: usize,
     local_info_size_u32: usize,
     size_of_message_buffer_in_bytes: usize,
@@ -57,6 +58,10 @@ pub struct WASMProducer {
     create_loop_counter_tag: String,
     merror_tag: String,
     string_table:  Vec<String>,
+    assert_message_list: AssertMessageList,
+    // first message slot of every assert, its signal labels use the following slots
+    assert_message_slots: Vec<usize>,
+    profile_list: ProfileList,
 }
 
 impl Default for WASMProducer {
@@ -90,6 +95,7 @@ impl Default for WASMProducer {
             major_version: 0,
             minor_version: 0,
             patch_version: 0,
+            profile_witness: false,
             stack_free_pos: 0,
             local_info_size_u32: 0, // in the future we can add some info like pointer to run father or text father
             size_of_message_buffer_in_bytes: 256,
@@ -119,6 +125,9 @@ impl Default for WASMProducer {
             create_loop_counter_tag: "$createloopcounter".to_string(),
 	        merror_tag: "$merror".to_string(),
             string_table: Vec::new(),
+            assert_message_list: Vec::new(),
+            assert_message_slots: Vec::new(),
+            profile_list: Vec::new(),
         }
     }
 }
@@ -321,9 +330,26 @@ impl WASMProducer {
         self.get_message_list_start() + self.size_of_message_in_bytes * self.message_list.len()
     }
 
-    pub fn get_constant_numbers_start(&self) -> usize {
+    pub fn get_assert_message_list_start(&self) -> usize {
         self.get_string_list_start() + self.size_of_message_in_bytes * self.string_table.len()
     }
+    pub fn get_number_of_assert_message_slots(&self) -> usize {
+        self.assert_message_list.iter().fold(0, |n, m| n + 1 + m.signals.len())
+    }
+    // position of the location message of an assert (slot 0) or of the label of its k-th signal (slot k + 1)
+    pub fn get_assert_message_position(&self, assert_id: usize, slot: usize) -> usize {
+        let slot = self.assert_message_slots[assert_id] + slot;
+        self.get_assert_message_list_start() + self.size_of_message_in_bytes * slot
+    }
+
+    pub fn get_profile_list_start(&self) -> usize {
+        self.get_assert_message_list_start()
+            + self.size_of_message_in_bytes * self.get_number_of_assert_message_slots()
+    }
+
+    pub fn get_constant_numbers_start(&self) -> usize {
+        self.get_profile_list_start() + self.size_of_message_in_bytes * self.profile_list.len()
+    }
     
     pub fn get_var_stack_memory_start(&self) -> usize {
         self.get_constant_numbers_start() + (self.size_32_bit + 2) * 4 * self.field_tracking.len()
@@ -414,4 +440,30 @@ impl WASMProducer {
     pub fn set_string_table(&mut self, string_table: Vec<String>) {
         self.string_table = string_table;
     }
+
+    pub fn get_assert_message_list(&self) -> &AssertMessageList {
+        &self.assert_message_list
+    }
+
+    pub fn set_assert_message_list(&mut self, assert_message_list: AssertMessageList) {
+        let mut next_slot = 0;
+        self.assert_message_slots = Vec::with_capacity(assert_message_list.len());
+        for message in &assert_message_list {
+            self.assert_message_slots.push(next_slot);
+            next_slot += 1 + message.signals.len();
+        }
+        self.assert_message_list = assert_message_list;
+    }
+
+    pub fn get_profile_witness(&self) -> bool {
+        self.profile_witness
+    }
+
+    pub fn get_profile_list(&self) -> &ProfileList {
+        &self.profile_list
+    }
+
+    pub fn set_profile_list(&mut self, profile_list: ProfileList) {
+        self.profile_list = profile_list;
+    }
 }
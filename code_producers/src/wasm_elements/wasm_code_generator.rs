@@ -488,7 +488,7 @@ pub fn to_array_hex(num: &BigInt, size: usize, group_size: usize) -> Vec<String>
 
 // ------ fix elements --------------------------
 
-pub fn generate_imports_list() -> Vec<WasmInstruction> {
+pub fn generate_imports_list(producer: &WASMProducer) -> Vec<WasmInstruction> {
     let mut imports = vec![];
     imports.push(
         "(import \"runtime\" \"exceptionHandler\" (func $exceptionHandler (type $_t_i32)))"
@@ -506,6 +506,14 @@ pub fn generate_imports_list() -> Vec<WasmInstruction> {
         "(import \"runtime\" \"showSharedRWMemory\" (func $showSharedRWMemory (type $_t_void)))"
             .to_string(),
     );
+    if producer.get_profile_witness() {
+        imports.push(
+            "(import \"runtime\" \"profileEnter\" (func $profileEnter (type $_t_i32)))".to_string(),
+        );
+        imports.push(
+            "(import \"runtime\" \"profileExit\" (func $profileExit (type $_t_i32)))".to_string(),
+        );
+    }
     imports
 }
 
@@ -528,7 +536,7 @@ pub fn generate_types_list() -> Vec<WasmInstruction> {
     types
 }
 
-pub fn generate_exports_list() -> Vec<WasmInstruction> {
+pub fn generate_exports_list(producer: &WASMProducer) -> Vec<WasmInstruction> {
     let mut exports = vec![];
     exports.push("(export \"memory\" (memory 0))".to_string());
     exports.push("(export \"getVersion\" (func $getVersion))".to_string());
@@ -546,6 +554,9 @@ pub fn generate_exports_list() -> Vec<WasmInstruction> {
     exports.push("(export \"getInputSize\" (func $getInputSize))".to_string());
     exports.push("(export \"getWitness\" (func $getWitness))".to_string());
     exports.push("(export \"getMessageChar\" (func $getMessageChar))".to_string());
+    if producer.get_profile_witness() {
+        exports.push("(export \"getProfileEntry\" (func $getProfileEntry))".to_string());
+    }
     exports
 }
 
@@ -629,6 +640,28 @@ pub fn generate_data_list(producer: &WASMProducer) -> Vec<WasmInstruction> {
             ));
         }
     }
+    for (i, message) in producer.get_assert_message_list().iter().enumerate() {
+        let location = format!("{}: {}", message.location(), message.expression);
+        wdata.push(format!(
+            "(data (i32.const {}) \"{}\\00\")",
+            producer.get_assert_message_position(i, 0),
+            fit_message(&location, producer.get_size_of_message_in_bytes())
+        ));
+        for (k, signal) in message.signals.iter().enumerate() {
+            wdata.push(format!(
+                "(data (i32.const {}) \"{}\\00\")",
+                producer.get_assert_message_position(i, k + 1),
+                fit_message(&format!("{} =", signal), producer.get_size_of_message_in_bytes())
+            ));
+        }
+    }
+    for (i, entry) in producer.get_profile_list().iter().enumerate() {
+        wdata.push(format!(
+            "(data (i32.const {}) \"{}\\00\")",
+            producer.get_profile_list_start() + i * producer.get_size_of_message_in_bytes(),
+            fit_message(&entry.description(), producer.get_size_of_message_in_bytes())
+        ));
+    }
     wdata.push(format!(
         "(data (i32.const {}) \"{}\")",
         producer.get_constant_numbers_start(),
@@ -637,6 +670,21 @@ pub fn generate_data_list(producer: &WASMProducer) -> Vec<WasmInstruction> {
     wdata
 }
 
+// escapes the message and cuts it so that it fits, with its final \00, in a message slot
+fn fit_message(message: &str, size_of_message_in_bytes: usize) -> String {
+    let mut fitted = String::new();
+    let mut used = 0;
+    for c in message.chars() {
+        let escaped = escape_string(&c.to_string());
+        if used + c.len_utf8() >= size_of_message_in_bytes {
+            break;
+        }
+        used += c.len_utf8();
+        fitted.push_str(&escaped);
+    }
+    fitted
+}
+
 // ------ stack handling operations
 
 pub fn reserve_stack_fr(producer: &WASMProducer, nbytes: usize) -> Vec<WasmInstruction> {
@@ -1206,6 +1254,55 @@ pub fn get_message_char_generator(producer: &WASMProducer) -> Vec<WasmInstructio
     instructions
 }
 
+// leaves the description of a profile entry in the message buffer
+pub fn get_profile_entry_generator(producer: &WASMProducer) -> Vec<WasmInstruction> {
+    let mut instructions = vec![];
+    if !producer.get_profile_witness() {
+        return instructions;
+    }
+    let header = "(func $getProfileEntry (type $_t_i32)".to_string();
+    instructions.push(header);
+    instructions.push(" (param $id i32)".to_string());
+    instructions.push(set_constant(&producer.get_profile_list_start().to_string()));
+    instructions.push(get_local("$id"));
+    instructions.push(set_constant(&producer.get_size_of_message_in_bytes().to_string()));
+    instructions.push(mul32());
+    instructions.push(add32());
+    instructions.push(call("$buildLogMessage"));
+    instructions.push(")".to_string());
+    instructions
+}
+
+// name of the function that holds the actual code of a profiled template run or function
+pub fn profiled_body_name(name: &str) -> String {
+    format!("{}_body", name)
+}
+
+// function with the original name that reports to the runtime when the body starts and ends
+pub fn profile_wrapper_generator(
+    name: &str,
+    type_name: &str,
+    params: &[&str],
+    profile_id: usize,
+) -> Vec<WasmInstruction> {
+    let mut instructions = vec![];
+    instructions.push(format!("(func ${} (type {})", name, type_name));
+    for param in params {
+        instructions.push(format!(" (param {} i32)", param));
+    }
+    instructions.push("(result i32)".to_string());
+    instructions.push(set_constant(&profile_id.to_string()));
+    instructions.push(call("$profileEnter"));
+    for param in params {
+        instructions.push(get_local(param));
+    }
+    instructions.push(call(&format!("${}", profiled_body_name(name))));
+    instructions.push(set_constant(&profile_id.to_string()));
+    instructions.push(call("$profileExit"));
+    instructions.push(")".to_string());
+    instructions
+}
+
 pub fn build_log_message_generator(producer: &WASMProducer) -> Vec<WasmInstruction> {
     let mut instructions = vec![];
     let header = "(func $buildLogMessage (type $_t_i32)".to_string();
@@ -1567,7 +1664,7 @@ pub fn fr_types(prime: &String) -> Vec<WasmInstruction> {
         "pallas" => include_str!("pallas/fr-types.wat"),
         "vesta" => include_str!("vesta/fr-types.wat"),
         "secq256r1" => include_str!("secq256r1/fr-types.wat"),
-        _ => unreachable!(),
+        _ => include_str!("generic/fr-types.wat"),
     };    
     for line in file.lines() {
         instructions.push(line.to_string());
@@ -1585,7 +1682,7 @@ pub fn fr_data(prime: &String) -> Vec<WasmInstruction> {
         "pallas" => include_str!("pallas/fr-data.wat"),
         "vesta" => include_str!("vesta/fr-data.wat"),
         "secq256r1" => include_str!("secq256r1/fr-data.wat"),
-        _ => unreachable!(),
+        _ => return generic_fr_data(prime),
     };    
     for line in file.lines() {
         instructions.push(line.to_string());
@@ -1602,7 +1699,7 @@ pub fn fr_code(prime: &String) -> Vec<WasmInstruction> {
         "pallas" => include_str!("pallas/fr-code.wat"),
         "vesta" => include_str!("vesta/fr-code.wat"),
         "secq256r1" => include_str!("secq256r1/fr-code.wat"),
-        _ => unreachable!(),
+        _ => return generic_fr_code(prime),
     };    
     for line in file.lines() {
         instructions.push(line.to_string());
@@ -1610,6 +1707,110 @@ pub fn fr_code(prime: &String) -> Vec<WasmInstruction> {
     instructions
 }
 
+// Any other prime uses generic/fr-code.wat. Its constants and scratch space are
+// placed from GENERIC_FR_START on, inside the first get_fr_memory_size() bytes
+const GENERIC_FR_START: usize = 8;
+
+struct GenericFrLayout {
+    n8: usize,
+    q: usize,
+    r2: usize,
+    one: usize,
+    half: usize,
+    mask: usize,
+    ta: usize,
+    tb: usize,
+    tr: usize,
+    tx: usize,
+    mt: usize,
+    end: usize,
+}
+
+fn generic_fr_layout(prime: &BigInt) -> GenericFrLayout {
+    let n8 = 8 * prime.bits().div_ceil(64);
+    let slot = |i: usize| GENERIC_FR_START + i * n8;
+    GenericFrLayout {
+        n8,
+        q: slot(0),
+        r2: slot(1),
+        one: slot(2),
+        half: slot(3),
+        mask: slot(4),
+        ta: slot(5),
+        tb: slot(6),
+        tr: slot(7),
+        tx: slot(8),
+        mt: slot(9),
+        // the Montgomery product needs two more words
+        end: slot(10) + 8,
+    }
+}
+
+pub fn generic_fr_memory_size(prime: &BigInt) -> usize {
+    generic_fr_layout(prime).end - 4
+}
+
+fn generic_fr_data(prime: &str) -> Vec<WasmInstruction> {
+    let p = prime.parse::<BigInt>().unwrap();
+    let layout = generic_fr_layout(&p);
+    let one = BigInt::from(1);
+    let r = &one << (8 * layout.n8);
+    let constants = [
+        (layout.q, p.clone()),
+        (layout.r2, (&r * &r) % &p),
+        (layout.one, one.clone()),
+        (layout.half, &p >> 1),
+        (layout.mask, (&one << p.bits()) - &one),
+    ];
+    constants
+        .iter()
+        .map(|(address, value)| format!("(data (i32.const {}) \"{}\")", address, wasm_hexa(layout.n8, value)))
+        .collect()
+}
+
+fn generic_fr_code(prime: &str) -> Vec<WasmInstruction> {
+    use serde_json::json;
+    let p = prime.parse::<BigInt>().unwrap();
+    let layout = generic_fr_layout(&p);
+    // the primes below 2^24 have less bytes than a limb
+    let (_, mut bytes) = p.to_bytes_le();
+    bytes.resize(bytes.len().max(4), 0);
+    let q0 = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+    // -q^(-1) mod 2^32 by Newton iteration, q is odd
+    let mut inverse: u32 = 1;
+    for _ in 0..5 {
+        inverse = inverse.wrapping_mul(2u32.wrapping_sub(q0.wrapping_mul(inverse)));
+    }
+    let template = handlebars::Handlebars::new();
+    let code = template
+        .render_template(
+            include_str!("generic/fr-code.wat"),
+            &json!({
+                "bits": p.bits(),
+                "n8": layout.n8,
+                "n8_last": layout.n8 - 8,
+                "n32": layout.n8 / 4,
+                "element_size": layout.n8 + 8,
+                "np": inverse.wrapping_neg(),
+                "q": layout.q,
+                "r2": layout.r2,
+                "one": layout.one,
+                "half": layout.half,
+                "mask": layout.mask,
+                "ta": layout.ta,
+                "tb": layout.tb,
+                "tr": layout.tr,
+                "tx": layout.tx,
+                "mt": layout.mt,
+                "mt_size": layout.n8 + 8,
+                "mt_top": layout.mt + layout.n8,
+                "mt_carry": layout.mt + layout.n8 + 4,
+            }),
+        )
+        .expect("must render");
+    code.lines().map(|line| line.to_string()).collect()
+}
+
 /*
 pub fn generate_utils_js_file(js_folder: &PathBuf) -> std::io::Result<()> {
     use std::io::BufWriter;
@@ -1723,7 +1924,7 @@ mod tests {
         // test function "write_block" is called.
         let mut code = vec![];
         code.push("(module".to_string());
-        let mut code_aux = generate_imports_list();
+        let mut code_aux = generate_imports_list(&producer);
         code.append(&mut code_aux);
         code_aux = generate_memory_def_list(&producer);
         code.append(&mut code_aux);
@@ -1733,7 +1934,7 @@ mod tests {
 
         code_aux = generate_types_list();
         code.append(&mut code_aux);
-        code_aux = generate_exports_list();
+        code_aux = generate_exports_list(&producer);
         code.append(&mut code_aux);
 
         code_aux = get_instructions_from_file("fr-code");
@@ -1816,4 +2017,88 @@ mod tests {
         // circom_compiler/target/code_generator_test/code.wat
         assert!(true);
     }
+
+    #[test]
+    fn assert_messages_are_in_the_data_of_the_module() {
+        let mut producer = create_producer();
+        let message = |expression: &str, signals: &[&str]| AssertMessage {
+            file: "main.circom".to_string(),
+            line: 3,
+            column: 5,
+            expression: expression.to_string(),
+            signals: signals.iter().map(|s| s.to_string()).collect(),
+        };
+        let long = "x".repeat(300);
+        producer.set_assert_message_list(vec![
+            message("out == 10", &["out"]),
+            message("s.out === \"a\"", &["s.out", "t"]),
+            message(&long, &[]),
+        ]);
+        let data = generate_data_list(&producer);
+        let at = |assert_id: usize, slot: usize, text: &str| {
+            let position = producer.get_assert_message_position(assert_id, slot);
+            format!("(data (i32.const {}) \"{}\\00\")", position, text)
+        };
+        assert!(data.contains(&at(0, 0, "main.circom:3:5: out == 10")));
+        assert!(data.contains(&at(0, 1, "out =")));
+        assert!(data.contains(&at(1, 0, "main.circom:3:5: s.out === \\\"a\\\"")));
+        assert!(data.contains(&at(1, 1, "s.out =")));
+        assert!(data.contains(&at(1, 2, "t =")));
+        // the messages are cut to fit, with their final \00, in a slot
+        let prefix = "main.circom:3:5: ";
+        let fitted = format!("{}{}", prefix, &long[..producer.get_size_of_message_in_bytes() - 1 - prefix.len()]);
+        assert!(data.contains(&at(2, 0, &fitted)));
+        assert_eq!(producer.get_assert_message_position(2, 1), producer.get_constant_numbers_start());
+    }
+
+    #[test]
+    fn profiled_code_reports_the_entries_to_the_runtime() {
+        let mut producer = create_producer();
+        let plain = [generate_imports_list(&producer), generate_exports_list(&producer)].concat();
+        assert!(!plain.iter().any(|line| line.contains("profile") || line.contains("Profile")));
+        assert!(get_profile_entry_generator(&producer).is_empty());
+        producer.profile_witness = true;
+        producer.set_profile_list(vec![
+            ProfileEntry { kind: ProfileKind::Template, header: "Square_0".to_string(), name: "Square".to_string() },
+            ProfileEntry { kind: ProfileKind::Function, header: "power_1".to_string(), name: "power".to_string() },
+        ]);
+        let imports = generate_imports_list(&producer);
+        assert!(imports.contains(&"(import \"runtime\" \"profileEnter\" (func $profileEnter (type $_t_i32)))".to_string()));
+        assert!(imports.contains(&"(import \"runtime\" \"profileExit\" (func $profileExit (type $_t_i32)))".to_string()));
+        let exports = generate_exports_list(&producer);
+        assert!(exports.contains(&"(export \"getProfileEntry\" (func $getProfileEntry))".to_string()));
+        // the descriptions are read by the runtime as "<kind> <header> <name>"
+        let data = generate_data_list(&producer);
+        let start = producer.get_profile_list_start();
+        let size = producer.get_size_of_message_in_bytes();
+        assert!(data.contains(&format!("(data (i32.const {}) \"template Square_0 Square\\00\")", start)));
+        assert!(data.contains(&format!("(data (i32.const {}) \"function power_1 power\\00\")", start + size)));
+        assert_eq!(producer.get_constant_numbers_start(), start + 2 * size);
+        // the wrapper keeps the name and the parameters of the profiled body
+        let wrapper = profile_wrapper_generator("power_1", "$_t_i32i32ri32", &["$a", "$b"], 1);
+        let expected = [
+            "(func $power_1 (type $_t_i32i32ri32)",
+            " (param $a i32)",
+            " (param $b i32)",
+            "(result i32)",
+            &set_constant("1"),
+            &call("$profileEnter"),
+            &get_local("$a"),
+            &get_local("$b"),
+            &call("$power_1_body"),
+            &set_constant("1"),
+            &call("$profileExit"),
+            ")",
+        ];
+        assert_eq!(wrapper, expected);
+    }
+
+    #[test]
+    fn generic_field_code_accepts_primes_smaller_than_a_limb() {
+        // -7^(-1) mod 2^32
+        let code = generic_fr_code("7");
+        assert!(code.iter().any(|line| line.contains("(i64.const 1227133513)")));
+        let code = generic_fr_code("251");
+        assert!(code.iter().any(|line| line.contains("(i64.const 1711142349)")));
+    }
 }
@@ -5,7 +5,9 @@ use crate::hir::very_concrete_program::*;
 use crate::intermediate_representation::translate;
 use crate::intermediate_representation::translate::{CodeInfo, FieldTracker, TemplateDB, ParallelClusters};
 use code_producers::c_elements::*;
+use code_producers::llvm_elements::*;
 use code_producers::wasm_elements::*;
+use num_bigint_dig::BigInt;
 use program_structure::file_definition::FileLibrary;
 use std::collections::{BTreeMap, HashMap};
 
@@ -25,7 +27,7 @@ fn build_template_instances(
     c_info: &CircuitInfo,
     ti: Vec<TemplateInstance>,
     mut field_tracker: FieldTracker,
-) -> (FieldTracker, HashMap<String,usize>) {
+) -> (FieldTracker, HashMap<String,usize>, AssertMessageList) {
 
     fn compute_jump(lengths: &Vec<usize>, indexes: &[usize]) -> usize {
         let mut jump = 0;
@@ -42,6 +44,7 @@ fn build_template_instances(
     let mut cmp_id = 0;
     let mut tmp_id = 0;
     let mut string_table = HashMap::new();
+    let mut assert_messages = AssertMessageList::new();
     for template in ti {
         let header = template.template_header;
         let name = template.template_name;
@@ -97,6 +100,7 @@ fn build_template_instances(
             components: template.components,
             template_database: &c_info.template_database,
             string_table : string_table,
+            assert_messages,
             signals_to_tags: template.signals_to_tags,
         };
         let mut template_info = TemplateCodeInfo {
@@ -121,11 +125,12 @@ fn build_template_instances(
         template_info.var_stack_depth = out.stack_depth;
         template_info.signal_stack_depth = out.signal_depth;
         string_table = out.string_table;
+        assert_messages = out.assert_messages;
         cmp_id = out.next_cmp_id;
         circuit.add_template_code(template_info);
         tmp_id += 1;
     }
-    (field_tracker, string_table)
+    (field_tracker, string_table, assert_messages)
 }
 
 fn build_function_instances(
@@ -133,8 +138,9 @@ fn build_function_instances(
     c_info: &CircuitInfo,
     instances: Vec<VCF>,
     mut field_tracker: FieldTracker,
-    mut string_table : HashMap<String,usize>
-) -> (FieldTracker, HashMap<String, usize>, HashMap<String, usize>) {
+    mut string_table : HashMap<String,usize>,
+    mut assert_messages: AssertMessageList,
+) -> (FieldTracker, HashMap<String, usize>, HashMap<String, usize>, AssertMessageList) {
     let mut function_to_arena_size = HashMap::new();
     for instance in instances {
         let msg = format!("Error in function {}", instance.header);
@@ -161,6 +167,7 @@ fn build_function_instances(
             component_to_parallel: HashMap::with_capacity(0),
             template_database: &c_info.template_database,
             string_table : string_table,
+            assert_messages,
             signals_to_tags: BTreeMap::new(),
         };
         let mut function_info = FunctionCodeInfo {
@@ -168,11 +175,13 @@ fn build_function_instances(
             params,
             returns,
             header: header.clone(),
+            profile_id: circuit.templates.len() + circuit.functions.len(),
             ..FunctionCodeInfo::default()
         };
         let code = instance.body;
         let out = translate::translate_code(code, code_info);
         string_table = out.string_table;
+        assert_messages = out.assert_messages;
         field_tracker = out.constant_tracker;
         function_info.body = out.code;
         function_info.max_number_of_ops_in_expression = out.expression_depth;
@@ -180,7 +189,7 @@ fn build_function_instances(
         function_to_arena_size.insert(header, function_info.max_number_of_vars);
         circuit.add_function_code(function_info);
     }
-    (field_tracker, function_to_arena_size, string_table)
+    (field_tracker, function_to_arena_size, string_table, assert_messages)
 }
 
 // WASM producer builder
@@ -202,13 +211,14 @@ fn initialize_wasm_producer(vcp: &VCP, database: &TemplateDB, wat_flag:bool, ver
         "pallas" => 1948,
         "vesta" => 1948,
         "secq256r1" => 1948,
-        _ => unreachable!()
+        _ => wasm_code_generator::generic_fr_memory_size(&prime),
     };
     //producer.fr_memory_size = 412 if goldilocks and 1948 for bn128 and bls12381
     // for each created component we store three u32, for each son we store a u32 in its father
     producer.size_of_component_tree = stats.all_created_components * 3 + stats.all_needed_subcomponents_indexes;
     producer.total_number_of_signals = stats.all_signals + 1;
-    producer.size_32_bit = prime.bits() / 32 + if prime.bits() % 32 != 0 { 1 } else { 0 };
+    // the field code works with 64 bit limbs, an odd number of 32 bit words is rounded up
+    producer.size_32_bit = 2 * prime.bits().div_ceil(64);
     producer.size_32_shift = 0;
     let mut pow = 1;
     while pow < producer.size_32_bit {
@@ -242,7 +252,8 @@ fn initialize_c_producer(vcp: &VCP, database: &TemplateDB, version: &str) -> CPr
     producer.prime_str = vcp.prime.clone();
     producer.size_of_component_tree = stats.all_created_components * 3 + stats.all_needed_subcomponents_indexes;
     producer.total_number_of_signals = stats.all_signals + 1;
-    producer.size_32_bit = prime.bits() / 32 + if prime.bits() % 32 != 0 { 1 } else { 0 };
+    // the field code works with 64 bit limbs, an odd number of 32 bit words is rounded up
+    producer.size_32_bit = 2 * prime.bits().div_ceil(64);
     producer.size_32_shift = 0;
     let mut pow = 1;
     while pow < producer.size_32_bit {
@@ -263,6 +274,30 @@ fn initialize_c_producer(vcp: &VCP, database: &TemplateDB, version: &str) -> CPr
     producer
 }
 
+fn initialize_llvm_producer(vcp: &VCP, database: &TemplateDB, version: &str) -> LLVMProducer {
+    use program_structure::utils::constants::UsefulConstants;
+    let initial_node = vcp.get_main_id();
+    let prime = UsefulConstants::new(&vcp.prime).get_p().clone();
+    let mut producer = LLVMProducer::default();
+    let stats = vcp.get_stats();
+    producer.main_header = vcp.get_main_instance().unwrap().template_header.clone();
+    producer.main_signal_offset = 1;
+    producer.prime = prime.to_str_radix(10);
+    producer.prime_str = vcp.prime.clone();
+    producer.total_number_of_signals = stats.all_signals + 1;
+    producer.size_64_bit = prime.bits().div_ceil(64);
+    producer.number_of_components = stats.all_created_components;
+    producer.witness_to_signal_list = vcp.get_witness_list().clone();
+    producer.number_of_main_inputs = vcp.templates[initial_node].number_of_inputs;
+    producer.number_of_main_outputs = vcp.templates[initial_node].number_of_outputs;
+    producer.main_input_list = main_input_list(&vcp.templates[initial_node]);
+    producer.io_map = build_io_map(vcp, database);
+    producer.template_instance_list = build_template_list(vcp);
+    producer.field_tracking.clear();
+    (producer.major_version, producer.minor_version, producer.patch_version) = get_number_version(version);
+    producer
+}
+
 fn main_input_list(main: &TemplateInstance) -> InputList {
     use program_structure::ast::SignalType::*;
     let mut input_list = vec![];
@@ -361,6 +396,7 @@ struct CircuitInfo {
 
 pub fn build_circuit(vcp: VCP, flag: CompilationFlags, version: &str) -> Circuit {
     use crate::ir_processing::set_arena_size_in_calls;
+    use program_structure::utils::constants::UsefulConstants;
     if flag.main_inputs_log {
         write_main_inputs_log(&vcp);
     }
@@ -368,7 +404,9 @@ pub fn build_circuit(vcp: VCP, flag: CompilationFlags, version: &str) -> Circuit
     let mut circuit = Circuit::default();
     circuit.wasm_producer = initialize_wasm_producer(&vcp, &template_database, flag.wat_flag, version);
     circuit.c_producer = initialize_c_producer(&vcp, &template_database, version);
+    circuit.llvm_producer = initialize_llvm_producer(&vcp, &template_database, version);
 
+    let prime = UsefulConstants::new(&vcp.prime).get_p().clone();
     let field_tracker = FieldTracker::new();
     let circuit_info = CircuitInfo {
         template_database,
@@ -376,18 +414,33 @@ pub fn build_circuit(vcp: VCP, flag: CompilationFlags, version: &str) -> Circuit
         functions: vcp.quick_knowledge,
     };
 
-    let (field_tracker, string_table) =
+    let (field_tracker, string_table, assert_messages) =
         build_template_instances(&mut circuit, &circuit_info, vcp.templates, field_tracker);
-    let (field_tracker, function_to_arena_size, table_string_to_usize) =
-        build_function_instances(&mut circuit, &circuit_info, vcp.functions, field_tracker,string_table);
+    let (mut field_tracker, function_to_arena_size, table_string_to_usize, assert_messages) =
+        build_function_instances(&mut circuit, &circuit_info, vcp.functions, field_tracker, string_table, assert_messages);
+    if flag.witness_optimization > 0 {
+        optimize_witness_code(&mut circuit, &mut field_tracker, &prime, flag.witness_optimization);
+    }
 
     let table_usize_to_string = create_table_usize_to_string(table_string_to_usize);
     circuit.wasm_producer.set_string_table(table_usize_to_string.clone());
-    circuit.c_producer.set_string_table(table_usize_to_string);
+    circuit.c_producer.set_string_table(table_usize_to_string.clone());
+    circuit.llvm_producer.set_string_table(table_usize_to_string);
+    circuit.wasm_producer.set_assert_message_list(assert_messages.clone());
+    circuit.c_producer.set_assert_message_list(assert_messages.clone());
+    circuit.llvm_producer.set_assert_message_list(assert_messages);
+    if flag.profile_witness {
+        let profile_list = build_profile_list(&circuit);
+        circuit.wasm_producer.profile_witness = true;
+        circuit.c_producer.profile_witness = true;
+        circuit.wasm_producer.set_profile_list(profile_list.clone());
+        circuit.c_producer.set_profile_list(profile_list);
+    }
     for i in 0..field_tracker.next_id() {
         let constant = field_tracker.get_constant(i).unwrap().clone();
         circuit.wasm_producer.field_tracking.push(constant.clone());
-        circuit.c_producer.field_tracking.push(constant);
+        circuit.c_producer.field_tracking.push(constant.clone());
+        circuit.llvm_producer.field_tracking.push(constant);
     }
     for fun in &mut circuit.functions {
         set_arena_size_in_calls(&mut fun.body, &function_to_arena_size);
@@ -399,6 +452,56 @@ pub fn build_circuit(vcp: VCP, flag: CompilationFlags, version: &str) -> Circuit
     circuit
 }
 
+fn optimize_witness_code(circuit: &mut Circuit, field_tracker: &mut FieldTracker, prime: &BigInt, level: usize) {
+    use crate::ir_processing::*;
+    if level >= 2 {
+        let mut inlinable = InlinableFunctions::new();
+        for fun in &circuit.functions {
+            if let Some(function) = inlinable_function(&fun.params, &fun.body) {
+                inlinable.insert(fun.header.clone(), function);
+            }
+        }
+        for fun in &mut circuit.functions {
+            inline_calls(&mut fun.body, &inlinable);
+        }
+        for tem in &mut circuit.templates {
+            inline_calls(&mut tem.body, &inlinable);
+        }
+    }
+    for fun in &mut circuit.functions {
+        let body = std::mem::take(&mut fun.body);
+        fun.body = propagate_constants(body, field_tracker, prime, level >= 2);
+        fun.max_number_of_ops_in_expression = build_auxiliary_stack(&mut fun.body);
+    }
+    for tem in &mut circuit.templates {
+        let body = std::mem::take(&mut tem.body);
+        tem.body = propagate_constants(body, field_tracker, prime, level >= 2);
+        tem.expression_stack_depth = build_auxiliary_stack(&mut tem.body);
+    }
+}
+
+// templates are indexed by their id and functions by their profile_id
+fn build_profile_list(circuit: &Circuit) -> ProfileList {
+    let mut profile_list = ProfileList::new();
+    for template in &circuit.templates {
+        debug_assert_eq!(template.id, profile_list.len());
+        profile_list.push(ProfileEntry {
+            kind: ProfileKind::Template,
+            header: template.header.clone(),
+            name: template.name.clone(),
+        });
+    }
+    for function in &circuit.functions {
+        debug_assert_eq!(function.profile_id, profile_list.len());
+        profile_list.push(ProfileEntry {
+            kind: ProfileKind::Function,
+            header: function.header.clone(),
+            name: function.name.clone(),
+        });
+    }
+    profile_list
+}
+
 pub fn create_table_usize_to_string( string_table : HashMap<String,usize>) -> Vec<String> {
     let size = string_table.len();
     let mut table_usize_to_string =  vec![String::new(); size];
@@ -2,19 +2,26 @@ use super::function::{FunctionCode, FunctionCodeInfo};
 use super::template::{TemplateCode, TemplateCodeInfo};
 use super::types::*;
 use crate::hir::very_concrete_program::VCP;
+use crate::intermediate_representation::ir_text;
 use crate::translating_traits::*;
 use code_producers::c_elements::*;
+use code_producers::llvm_elements::*;
 use code_producers::wasm_elements::*;
 use std::io::Write;
 
 pub struct CompilationFlags {
     pub main_inputs_log: bool,
     pub wat_flag:bool,
+    pub profile_witness: bool,
+    // 0: no optimization, 1: constant propagation and dead code removal,
+    // 2: also loop unrolling and inlining of tiny functions
+    pub witness_optimization: usize,
 }
 
 pub struct Circuit {
     pub wasm_producer: WASMProducer,
     pub c_producer: CProducer,
+    pub llvm_producer: LLVMProducer,
     pub templates: Vec<TemplateCode>,
     pub functions: Vec<FunctionCode>,
 }
@@ -24,6 +31,7 @@ impl Default for Circuit {
         Circuit {
             c_producer: CProducer::default(),
             wasm_producer: WASMProducer::default(),
+            llvm_producer: LLVMProducer::default(),
             templates: Vec::new(),
             functions: Vec::new(),
         }
@@ -35,7 +43,7 @@ impl WriteWasm for Circuit {
         use code_producers::wasm_elements::wasm_code_generator::*;
         let mut code = vec![];
         code.push("(module".to_string());
-        let mut code_aux = generate_imports_list();
+        let mut code_aux = generate_imports_list(producer);
         code.append(&mut code_aux);
         code_aux = generate_memory_def_list(&producer);
         code.append(&mut code_aux);
@@ -45,7 +53,7 @@ impl WriteWasm for Circuit {
 
         code_aux = generate_types_list();
         code.append(&mut code_aux);
-        code_aux = generate_exports_list();
+        code_aux = generate_exports_list(producer);
         code.append(&mut code_aux);
 
         code_aux = fr_code(&producer.prime_str);
@@ -108,6 +116,9 @@ impl WriteWasm for Circuit {
         code_aux = build_log_message_generator(&producer);
         code.append(&mut code_aux);
 
+        code_aux = get_profile_entry_generator(producer);
+        code.append(&mut code_aux);
+
         // Actual code from the program
 
         for f in &self.functions {
@@ -137,7 +148,7 @@ impl WriteWasm for Circuit {
         writer.write_all("(module".as_bytes()).map_err(|_| {})?;
         //writer.flush().map_err(|_| {})?;
 
-        let mut code_aux = generate_imports_list();
+        let mut code_aux = generate_imports_list(producer);
         let mut code = merge_code(code_aux);
         writer.write_all(code.as_bytes()).map_err(|_| {})?;
         //writer.flush().map_err(|_| {})?;
@@ -157,7 +168,7 @@ impl WriteWasm for Circuit {
         writer.write_all(code.as_bytes()).map_err(|_| {})?;
         //writer.flush().map_err(|_| {})?;
 
-        code_aux = generate_exports_list();
+        code_aux = generate_exports_list(producer);
         code = merge_code(code_aux);
         writer.write_all(code.as_bytes()).map_err(|_| {})?;
         //writer.flush().map_err(|_| {})?;
@@ -262,6 +273,11 @@ impl WriteWasm for Circuit {
         writer.write_all(code.as_bytes()).map_err(|_| {})?;
         //writer.flush().map_err(|_| {})?;
 
+        code_aux = get_profile_entry_generator(producer);
+        code = merge_code(code_aux);
+        writer.write_all(code.as_bytes()).map_err(|_| {})?;
+        //writer.flush().map_err(|_| {})?;
+
         // Actual code from the program
 
         for f in &self.functions {
@@ -360,6 +376,8 @@ impl WriteC for Circuit {
             producer.get_io_map().len()
         ));
         //code.append(&mut generate_message_list_def(producer, producer.get_message_list()));
+        code.append(&mut generate_assert_message_table(producer.get_assert_message_list()));
+        code.append(&mut generate_profile_table(producer));
         
         // Functions to release the memory
         let mut release_component_code = generate_function_release_memory_component();
@@ -478,6 +496,8 @@ impl WriteC for Circuit {
             producer.get_io_map().len()
         ));
         //code.append(&mut generate_message_list_def(producer, producer.get_message_list()));
+        code.append(&mut generate_assert_message_table(producer.get_assert_message_list()));
+        code.append(&mut generate_profile_table(producer));
         
         // Functions to release the memory
         let mut release_component_code = generate_function_release_memory_component();
@@ -569,10 +589,12 @@ impl Circuit {
         self.templates[id].as_ref()
     }
     pub fn produce_ir_string_for_template(&self, id: ID) -> String {
-        self.templates[id].to_string()
+        let template = &self.templates[id];
+        format!("; template {}\n{}", template.header, ir_text::write_list(&template.body))
     }
     pub fn produce_ir_string_for_function(&self, id: ID) -> String {
-        self.functions[id].to_string()
+        let function = &self.functions[id];
+        format!("; function {}\n{}", function.header, ir_text::write_list(&function.body))
     }
     pub fn produce_c<W: Write>(&self, c_folder: &str, run_name: &str, c_circuit: &mut W, c_dat: &mut W) -> Result<(), ()> {
 	use std::path::Path;
@@ -583,11 +605,44 @@ impl Circuit {
         c_code_generator::generate_calcwit_hpp_file(&c_folder_path).map_err(|_err| {})?;
         c_code_generator::generate_fr_cpp_file(&c_folder_path, &self.c_producer.prime_str).map_err(|_err| {})?;
         c_code_generator::generate_calcwit_cpp_file(&c_folder_path).map_err(|_err| {})?;
-        c_code_generator::generate_fr_asm_file(&c_folder_path, &self.c_producer.prime_str).map_err(|_err| {})?;
+        if c_code_generator::has_asm_field(&self.c_producer.prime_str) {
+            c_code_generator::generate_fr_asm_file(&c_folder_path, &self.c_producer.prime_str).map_err(|_err| {})?;
+        } else {
+            c_code_generator::generate_fr_raw_cpp_file(&c_folder_path, &self.c_producer.prime_str).map_err(|_err| {})?;
+        }
         c_code_generator::generate_make_file(&c_folder_path,run_name,&self.c_producer).map_err(|_err| {})?;
         c_code_generator::generate_dat_file(c_dat, &self.c_producer).map_err(|_err| {})?;
         self.write_c(c_circuit, &self.c_producer)
     }
+    pub fn produce_llvm_ir<W: Write>(&self, llvm_folder: &str, run_name: &str, writer: &mut W) -> Result<(), ()> {
+        use code_producers::llvm_elements::llvm_code_generator;
+        use std::path::Path;
+        let llvm_folder_path = Path::new(llvm_folder).to_path_buf();
+        llvm_code_generator::generate_main_c_file(&llvm_folder_path).map_err(|_err| {})?;
+        llvm_code_generator::generate_circom_h_file(&llvm_folder_path).map_err(|_err| {})?;
+        llvm_code_generator::generate_fr_h_file(&llvm_folder_path, &self.llvm_producer).map_err(|_err| {})?;
+        llvm_code_generator::generate_fr_c_file(&llvm_folder_path).map_err(|_err| {})?;
+        llvm_code_generator::generate_make_file(&llvm_folder_path, run_name).map_err(|_err| {})?;
+        self.write_llvm_ir(writer, &self.llvm_producer)
+    }
+    fn write_llvm_ir<T: Write>(&self, writer: &mut T, producer: &LLVMProducer) -> Result<(), ()> {
+        use code_producers::llvm_elements::llvm_code_generator::*;
+        let mut code = generate_module_prologue(producer);
+        code.append(&mut generate_constant_table(producer));
+        code.append(&mut generate_run_table(producer));
+        code.append(&mut generate_io_map(producer));
+        code.append(&mut generate_circuit_description(producer));
+        code.push("".to_string());
+        for f in &self.functions {
+            code.append(&mut f.produce_llvm_ir(producer));
+        }
+        for t in &self.templates {
+            code.append(&mut t.produce_llvm_ir(producer));
+        }
+        code.push(generate_run_function(producer));
+        writer.write_all(merge_code(code).as_bytes()).map_err(|_| {})?;
+        writer.flush().map_err(|_| {})
+    }
     pub fn produce_wasm<W: Write>(&self, js_folder: &str, _wasm_name: &str, writer: &mut W) -> Result<(), ()> {
 	use std::path::Path;
 	let js_folder_path = Path::new(js_folder).to_path_buf();
@@ -3,6 +3,7 @@ use crate::hir::very_concrete_program::Param;
 use crate::intermediate_representation::InstructionList;
 use crate::translating_traits::*;
 use code_producers::c_elements::*;
+use code_producers::llvm_elements::*;
 use code_producers::wasm_elements::*;
 //use std::io::Write;
 
@@ -16,6 +17,8 @@ pub struct FunctionCodeInfo {
     pub body: InstructionList,
     pub max_number_of_vars: usize,
     pub max_number_of_ops_in_expression: usize,
+    // position in the profile list, after all the template instances
+    pub profile_id: usize,
 }
 
 impl ToString for FunctionCodeInfo {
@@ -33,7 +36,11 @@ impl WriteWasm for FunctionCodeInfo {
         use code_producers::wasm_elements::wasm_code_generator::*;
         //to be revised
         let mut instructions = vec![];
-        let funcdef = format!("(func ${} (type $_t_i32i32ri32)", self.header);
+        let funcdef = if producer.get_profile_witness() {
+            format!("(func ${} (type $_t_i32i32ri32)", profiled_body_name(&self.header))
+        } else {
+            format!("(func ${} (type $_t_i32i32ri32)", self.header)
+        };
         instructions.push(funcdef);
         instructions.push(format!("(param {} i32)", producer.get_result_address_tag()));
         instructions.push(format!("(param {} i32)", producer.get_result_size_tag()));
@@ -84,6 +91,12 @@ impl WriteWasm for FunctionCodeInfo {
         }
         instructions.push(set_constant("0"));	
         instructions.push(")".to_string());
+        if producer.get_profile_witness() {
+            let params = [producer.get_result_address_tag(), producer.get_result_size_tag()];
+            let mut wrapper =
+                profile_wrapper_generator(&self.header, "$_t_i32i32ri32", &params, self.profile_id);
+            instructions.append(&mut wrapper);
+        }
         instructions
     }
 }
@@ -100,6 +113,9 @@ impl WriteC for FunctionCodeInfo {
             declare_dest_size(),
         ];
         let mut body = vec![];
+        if producer.get_profile_witness() {
+            body.push(format!("{};", declare_profile_scope(self.profile_id)));
+        }
         body.push(format!("{};", declare_circuit_constants()));
         body.push(format!("{};", declare_expaux(self.max_number_of_ops_in_expression)));
         body.push(format!("{};", declare_my_template_name_function(&self.name)));
@@ -114,6 +130,30 @@ impl WriteC for FunctionCodeInfo {
 }
 
 impl FunctionCodeInfo {
+    pub fn produce_llvm_ir(&self, producer: &LLVMProducer) -> Vec<String> {
+        use llvm_code_generator::*;
+        let mut context = LLVMContext::new(&self.header, &self.name);
+        // my_id is the component that called the function, shown when an assert fails
+        let header = format!(
+            "define void @{}(ptr {}, i32 {}, ptr {}, i32 {})",
+            self.header, LVAR, MY_ID, FUNCTION_DESTINATION, FUNCTION_DESTINATION_SIZE
+        );
+        let mut body = vec![];
+        for t in &self.body {
+            let (mut instructions_body, _) = t.produce_llvm_ir(producer, &mut context);
+            body.append(&mut instructions_body);
+        }
+        // every path of a function ends in a return
+        body.push("unreachable".to_string());
+        let mut entry = vec![label("entry")];
+        entry.push(format!("{} = alloca {}", EXPAUX, fr_array(self.max_number_of_ops_in_expression)));
+        entry.append(&mut context.take_allocas());
+        entry.append(&mut body);
+        let mut code = context.take_constants();
+        code.push(build_function(header, entry));
+        code
+    }
+
     pub fn wrap(self) -> FunctionCode {
         FunctionCode::new(self)
     }
@@ -3,3 +3,4 @@ pub mod circuit;
 pub mod function;
 pub mod template;
 pub mod types;
+pub mod witness_interpreter;
@@ -1,6 +1,7 @@
 use crate::intermediate_representation::InstructionList;
 use crate::translating_traits::*;
 use code_producers::c_elements::*;
+use code_producers::llvm_elements::*;
 use code_producers::wasm_elements::*;
 
 type TemplateID = usize;
@@ -75,7 +76,12 @@ impl WriteWasm for TemplateCodeInfo {
 
         // run function code
 
-        let funcdef2 = format!("(func ${}_run (type $_t_i32ri32)", self.header);
+        let run_name = format!("{}_run", self.header);
+        let funcdef2 = if producer.get_profile_witness() {
+            format!("(func ${} (type $_t_i32ri32)", profiled_body_name(&run_name))
+        } else {
+            format!("(func ${} (type $_t_i32ri32)", run_name)
+        };
         instructions.push(funcdef2);
         instructions.push(format!(" (param {} i32)", producer.get_offset_tag()));
 	instructions.push("(result i32)".to_string()); //state 0 = OK; > 0 error
@@ -143,6 +149,11 @@ impl WriteWasm for TemplateCodeInfo {
         instructions.append(&mut free_stack_code);
         instructions.push(set_constant("0"));	
         instructions.push(")".to_string());
+        if producer.get_profile_witness() {
+            let params = [producer.get_offset_tag()];
+            let mut wrapper = profile_wrapper_generator(&run_name, "$_t_i32ri32", &params, self.id);
+            instructions.append(&mut wrapper);
+        }
         instructions
     }
 }
@@ -185,7 +196,7 @@ impl TemplateCodeInfo {
             "{}->componentMemory[{}].templateName = \"{}\";",
             CIRCOM_CALC_WIT,
 	        component_offset(),
-            &self.name.to_string()
+            escape_string(&self.name)
         ));
         create_body.push(format!(
             "{}->componentMemory[{}].signalStart = {};",
@@ -275,6 +286,9 @@ impl TemplateCodeInfo {
         run_params.push(declare_ctx_index());
         run_params.push(declare_circom_calc_wit());
         let mut run_body = vec![];
+        if producer.get_profile_witness() {
+            run_body.push(format!("{};", declare_profile_scope(self.id)));
+        }
         run_body.push(format!("{};", declare_signal_values()));
         run_body.push(format!("{};", declare_my_signal_start()));
         run_body.push(format!("{};", declare_my_template_name()));
@@ -339,6 +353,76 @@ impl TemplateCodeInfo {
         vec![create_fun, run_fun]
     }
 
+    pub fn produce_llvm_ir(&self, producer: &LLVMProducer) -> Vec<String> {
+        use llvm_code_generator::*;
+        // both functions share the context, so the name of the template is declared once
+        let mut context = LLVMContext::new(&self.header, &self.name);
+
+        let create_header = format!(
+            "define void @{}_create(i32 {}, i32 {}, ptr {}, i32 {})",
+            self.header, SIGNAL_OFFSET, COMPONENT_OFFSET, COMPONENT_NAME_PARAM, COMPONENT_FATHER
+        );
+        let mut create_body = vec![label("entry")];
+        create_body.push(format!("{} = load ptr, ptr {}", COMPONENTS, G_COMPONENTS));
+        let fields = [
+            (COMPONENT_TEMPLATE_ID, "i32", self.id.to_string()),
+            (COMPONENT_SIGNAL_START, "i32", SIGNAL_OFFSET.to_string()),
+            (COMPONENT_INPUT_COUNTER, "i32", self.number_of_inputs.to_string()),
+            (COMPONENT_ID_FATHER, "i32", COMPONENT_FATHER.to_string()),
+            (COMPONENT_TEMPLATE_NAME, "ptr", context.get_name()),
+            (COMPONENT_NAME, "ptr", COMPONENT_NAME_PARAM.to_string()),
+        ];
+        for (field, ty, value) in fields {
+            let (mut field_instructions, address) = component_field(COMPONENT_OFFSET, field, &mut context);
+            create_body.append(&mut field_instructions);
+            create_body.push(store(ty, &value, &address));
+        }
+        let subcomponents = context.fresh_value();
+        create_body.push(format!(
+            "{} = call ptr @circom_new_subcomponents(i32 {})",
+            subcomponents, self.number_of_components
+        ));
+        let (mut field_instructions, address) =
+            component_field(COMPONENT_OFFSET, COMPONENT_SUBCOMPONENTS, &mut context);
+        create_body.append(&mut field_instructions);
+        create_body.push(store("ptr", &subcomponents, &address));
+        // if has no inputs should be runned
+        if self.number_of_inputs == 0 {
+            create_body.push(build_call(&format!("@{}_run", self.header), vec![typed("i32", COMPONENT_OFFSET)]));
+        }
+        create_body.push("ret void".to_string());
+        let create_fun = build_function(create_header, create_body);
+
+        let run_header = format!("define void @{}_run(i32 {})", self.header, CTX_INDEX);
+        let mut run_body = vec![];
+        for t in &self.body {
+            let (mut instructions_body, _) = t.produce_llvm_ir(producer, &mut context);
+            run_body.append(&mut instructions_body);
+        }
+        run_body.push("ret void".to_string());
+        let mut entry = vec![label("entry")];
+        entry.push(format!("{} = alloca {}", LVAR, fr_array(self.var_stack_depth)));
+        entry.push(format!("{} = alloca {}", EXPAUX, fr_array(self.expression_stack_depth)));
+        entry.append(&mut context.take_allocas());
+        entry.push(format!("{} = load ptr, ptr {}", SIGNALS, G_SIGNALS));
+        entry.push(format!("{} = load ptr, ptr {}", COMPONENTS, G_COMPONENTS));
+        entry.push(format!("{} = add i32 {}, 0", MY_ID, CTX_INDEX));
+        let (mut signal_start, start) = load_component_field(MY_ID, COMPONENT_SIGNAL_START, "i32", &mut context);
+        entry.append(&mut signal_start);
+        entry.push(format!("{} = add i32 {}, 0", MY_SIGNAL_START, start));
+        let (mut my_subcomponents, subcomponents) =
+            load_component_field(MY_ID, COMPONENT_SUBCOMPONENTS, "ptr", &mut context);
+        entry.append(&mut my_subcomponents);
+        entry.push(format!("{} = getelementptr inbounds i32, ptr {}, i32 0", MY_SUBCOMPONENTS, subcomponents));
+        entry.append(&mut run_body);
+        let run_fun = build_function(run_header, entry);
+
+        let mut code = context.take_constants();
+        code.push(create_fun);
+        code.push(run_fun);
+        code
+    }
+
     pub fn wrap(self) -> TemplateCode {
         TemplateCode::new(self)
     }
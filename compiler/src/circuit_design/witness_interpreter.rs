@@ -0,0 +1,518 @@
+use super::circuit::Circuit;
+use super::function::FunctionCodeInfo;
+use super::template::TemplateCodeInfo;
+use crate::intermediate_representation::ir_interface::*;
+use circom_algebra::modular_arithmetic;
+use code_producers::llvm_elements::LLVMProducer;
+use num_bigint_dig::BigInt;
+use num_traits::{One, ToPrimitive, Zero};
+use std::collections::HashMap;
+
+// Runs the code of the witness generator of a circuit without inputs, in the
+// same way as the generated C, WebAssembly and LLVM code, so that the witness of
+// a circuit can be computed by the compiler itself. The executor of the
+// constraint generation handles the signals as symbols and only computes the
+// values known at compilation time, so it cannot give the values assigned
+// with <--. The tests of circom test_user.rs check that the witness is the one
+// computed by the WebAssembly code of the same circuits.
+
+pub struct WitnessRun {
+    // lines written by the logs of the circuit
+    pub logs: Vec<String>,
+    // value of every signal, the position 0 holds the constant 1
+    pub result: Result<Vec<BigInt>, WitnessFailure>,
+}
+
+pub struct WitnessFailure {
+    pub message: String,
+    // file:line:column of the failed check, when it is known
+    pub location: Option<String>,
+    // values of the signals of the failed check
+    pub values: Vec<(String, BigInt)>,
+}
+
+struct Component {
+    template_id: usize,
+    signal_start: usize,
+    input_counter: usize,
+    father: usize,
+    name: String,
+    subcomponents: Vec<usize>,
+}
+
+struct Frame<'a> {
+    vars: Vec<BigInt>,
+    my_id: usize,
+    name: &'a str,
+}
+
+enum Memory {
+    Variable,
+    Signal,
+}
+
+type Execution<T> = Result<T, WitnessFailure>;
+
+pub fn compute_witness(circuit: &Circuit) -> WitnessRun {
+    let producer = &circuit.llvm_producer;
+    let field = BigInt::parse_bytes(producer.get_prime().as_bytes(), 10).unwrap();
+    let mut interpreter = Interpreter {
+        producer,
+        constants: producer
+            .get_field_constant_list()
+            .iter()
+            .map(|value| BigInt::parse_bytes(value.as_bytes(), 10).unwrap())
+            .collect(),
+        templates: circuit.templates.iter().map(|t| (t.id, t.as_ref())).collect(),
+        functions: circuit.functions.iter().map(|f| (f.header.as_str(), f.as_ref())).collect(),
+        signals: vec![BigInt::zero(); producer.get_total_number_of_signals()],
+        components: Vec::with_capacity(producer.get_number_of_components()),
+        logs: Vec::new(),
+        field,
+    };
+    interpreter.signals[0] = BigInt::one();
+    let result = interpreter.run_main().map(|_| interpreter.signals.clone());
+    WitnessRun { logs: interpreter.logs, result }
+}
+
+struct Interpreter<'a> {
+    producer: &'a LLVMProducer,
+    field: BigInt,
+    constants: Vec<BigInt>,
+    templates: HashMap<usize, &'a TemplateCodeInfo>,
+    functions: HashMap<&'a str, &'a FunctionCodeInfo>,
+    signals: Vec<BigInt>,
+    components: Vec<Component>,
+    logs: Vec<String>,
+}
+
+impl<'a> Interpreter<'a> {
+    fn run_main(&mut self) -> Execution<()> {
+        let main = self.producer.get_main_header();
+        let template_id = self.templates.values().find(|t| t.header == main).map(|t| t.id).unwrap();
+        let signal_start = self.producer.main_signal_offset;
+        self.create(template_id, signal_start, 0, "main".to_string(), 0)?;
+        // templates without inputs are run when created
+        if self.producer.get_number_of_main_inputs() > 0 {
+            self.run(0)?;
+        }
+        Ok(())
+    }
+
+    fn create(&mut self, template_id: usize, signal_start: usize, id: usize, name: String, father: usize) -> Execution<()> {
+        let template = self.templates[&template_id];
+        let component = Component {
+            template_id,
+            signal_start,
+            input_counter: template.number_of_inputs,
+            father,
+            name,
+            subcomponents: vec![0; template.number_of_components],
+        };
+        if id >= self.components.len() {
+            self.components.resize_with(id + 1, || Component {
+                template_id: 0,
+                signal_start: 0,
+                input_counter: 0,
+                father: 0,
+                name: String::new(),
+                subcomponents: Vec::new(),
+            });
+        }
+        self.components[id] = component;
+        if template.number_of_inputs == 0 {
+            self.run(id)?;
+        }
+        Ok(())
+    }
+
+    fn run(&mut self, id: usize) -> Execution<()> {
+        let template = self.templates[&self.components[id].template_id];
+        let mut frame = Frame { vars: vec![BigInt::zero(); template.var_stack_depth], my_id: id, name: &template.name };
+        self.execute_list(&template.body, &mut frame)?;
+        Ok(())
+    }
+
+    // Returns the value of the return instruction reached, if any
+    fn execute_list(&mut self, list: &InstructionList, frame: &mut Frame) -> Execution<Option<Vec<BigInt>>> {
+        for instruction in list {
+            if let Some(value) = self.execute(instruction, frame)? {
+                return Ok(Some(value));
+            }
+        }
+        Ok(None)
+    }
+
+    fn execute(&mut self, instruction: &Instruction, frame: &mut Frame) -> Execution<Option<Vec<BigInt>>> {
+        use Instruction::*;
+        match instruction {
+            Branch(bucket) => {
+                let cond = self.evaluate(&bucket.cond, frame)?;
+                if modular_arithmetic::as_bool(&cond[0], &self.field) {
+                    self.execute_list(&bucket.if_branch, frame)
+                } else {
+                    self.execute_list(&bucket.else_branch, frame)
+                }
+            }
+            Loop(bucket) => {
+                loop {
+                    let cond = self.evaluate(&bucket.continue_condition, frame)?;
+                    if !modular_arithmetic::as_bool(&cond[0], &self.field) {
+                        return Ok(None);
+                    }
+                    if let Some(value) = self.execute_list(&bucket.body, frame)? {
+                        return Ok(Some(value));
+                    }
+                }
+            }
+            Return(bucket) => {
+                let mut value = self.evaluate(&bucket.value, frame)?;
+                // the caller decides how many elements of the result are kept
+                if bucket.with_size <= 1 {
+                    value.truncate(1);
+                }
+                Ok(Some(value))
+            }
+            _ => {
+                self.evaluate(instruction, frame)?;
+                Ok(None)
+            }
+        }
+    }
+
+    fn evaluate(&mut self, instruction: &Instruction, frame: &mut Frame) -> Execution<Vec<BigInt>> {
+        use Instruction::*;
+        match instruction {
+            Value(bucket) => match bucket.parse_as {
+                ValueType::U32 => Ok(vec![BigInt::from(bucket.value)]),
+                ValueType::BigInt => Ok(vec![self.constants[bucket.value].clone()]),
+            },
+            Load(bucket) => {
+                let (memory, index, _) = self.address(&bucket.address_type, &bucket.src, frame)?;
+                self.read(memory, index, bucket.context.size, frame, bucket.line)
+            }
+            Store(bucket) => {
+                let (memory, index, sub_component) = self.address(&bucket.dest_address_type, &bucket.dest, frame)?;
+                let value = self.evaluate(&bucket.src, frame)?;
+                self.write(memory, index, &value, bucket.context.size, frame, bucket.line)?;
+                if let Some(sub_component) = sub_component {
+                    self.update_inputs(&bucket.dest_address_type, sub_component, bucket.context.size)?;
+                }
+                Ok(vec![])
+            }
+            Compute(bucket) => {
+                let mut operands = Vec::with_capacity(bucket.stack.len());
+                for operand in &bucket.stack {
+                    operands.push(self.evaluate(operand, frame)?);
+                }
+                self.compute(bucket, &operands, frame)
+            }
+            Call(bucket) => self.call(bucket, frame),
+            CreateCmp(bucket) => {
+                self.create_components(bucket, frame)?;
+                Ok(vec![])
+            }
+            Assert(bucket) => {
+                let value = self.evaluate(&bucket.evaluate, frame)?;
+                if modular_arithmetic::as_bool(&value[0], &self.field) {
+                    return Ok(vec![]);
+                }
+                let message = self.producer.get_assert_message_list().get(bucket.assert_id).cloned();
+                let mut values = Vec::new();
+                if let Some(message) = &message {
+                    for (signal, label) in bucket.signals.iter().zip(&message.signals) {
+                        let value = self.evaluate(signal, frame)?;
+                        values.push((label.clone(), value[0].clone()));
+                    }
+                }
+                Err(WitnessFailure {
+                    message: format!(
+                        "Failed assert in template/function {} line {}. Followed trace of components: {}",
+                        frame.name,
+                        bucket.line,
+                        self.trace(frame.my_id)
+                    ),
+                    location: message.map(|message| format!("{}: {}", message.location(), message.expression)),
+                    values,
+                })
+            }
+            Log(bucket) => {
+                let mut line = Vec::new();
+                for argument in &bucket.argsprint {
+                    match argument {
+                        LogBucketArg::LogExp(expression) => line.push(self.evaluate(expression, frame)?[0].to_string()),
+                        LogBucketArg::LogStr(id) => line.push(self.producer.get_string_table()[*id].clone()),
+                    }
+                }
+                self.logs.push(line.join(" "));
+                Ok(vec![])
+            }
+            Branch(_) | Loop(_) | Return(_) => {
+                self.execute(instruction, frame)?;
+                Ok(vec![])
+            }
+        }
+    }
+
+    // Memory and position of the first element accessed, together with the
+    // subcomponent that is accessed if any
+    fn address(
+        &mut self,
+        address_type: &AddressType,
+        location: &LocationRule,
+        frame: &mut Frame,
+    ) -> Execution<(Memory, usize, Option<usize>)> {
+        let sub_component = if let AddressType::SubcmpSignal { cmp_address, .. } = address_type {
+            let index = self.evaluate_address(cmp_address, frame)?;
+            let subcomponents = &self.components[frame.my_id].subcomponents;
+            match subcomponents.get(index) {
+                Some(id) => Some(*id),
+                None => return Err(self.failure(frame, cmp_address.get_line(), "Subcomponent index out of bounds")),
+            }
+        } else {
+            None
+        };
+        let index = match location {
+            LocationRule::Indexed { location, .. } => self.evaluate_address(location, frame)?,
+            LocationRule::Mapped { signal_code, indexes } => {
+                // only subcomponent signals are mapped
+                let template_id = self.components[sub_component.unwrap()].template_id;
+                let mut values = Vec::with_capacity(indexes.len());
+                for index in indexes {
+                    values.push(self.evaluate_address(index, frame)?);
+                }
+                let definition = &self.producer.get_io_map()[&template_id][*signal_code];
+                let mut map_index = values.first().cloned().unwrap_or(0);
+                for (i, value) in values.iter().enumerate().skip(1) {
+                    map_index = map_index * definition.lengths[i - 1] + value;
+                }
+                definition.offset + map_index
+            }
+        };
+        Ok(match address_type {
+            AddressType::Variable => (Memory::Variable, index, None),
+            AddressType::Signal => (Memory::Signal, self.components[frame.my_id].signal_start + index, None),
+            AddressType::SubcmpSignal { .. } => {
+                let id = sub_component.unwrap();
+                (Memory::Signal, self.components[id].signal_start + index, Some(id))
+            }
+        })
+    }
+
+    fn evaluate_address(&mut self, instruction: &Instruction, frame: &mut Frame) -> Execution<usize> {
+        let value = self.evaluate(instruction, frame)?;
+        value[0].to_usize().ok_or_else(|| self.failure(frame, instruction.get_line(), "Invalid index"))
+    }
+
+    fn read(&self, memory: Memory, index: usize, size: usize, frame: &Frame, line: usize) -> Execution<Vec<BigInt>> {
+        let source = match memory {
+            Memory::Variable => &frame.vars,
+            Memory::Signal => &self.signals,
+        };
+        match source.get(index..index + size) {
+            Some(values) => Ok(values.to_vec()),
+            None => Err(self.failure(frame, line, "Index out of bounds")),
+        }
+    }
+
+    fn write(&mut self, memory: Memory, index: usize, value: &[BigInt], size: usize, frame: &mut Frame, line: usize) -> Execution<()> {
+        let destination = match memory {
+            Memory::Variable => &mut frame.vars,
+            Memory::Signal => &mut self.signals,
+        };
+        if value.len() < size || destination.len() < index + size {
+            return Err(self.failure(frame, line, "Index out of bounds"));
+        }
+        destination[index..index + size].clone_from_slice(&value[..size]);
+        Ok(())
+    }
+
+    // After size inputs of a subcomponent are written its input counter is
+    // decreased, and the subcomponent is run when it was the last input
+    fn update_inputs(&mut self, address_type: &AddressType, sub_component: usize, size: usize) -> Execution<()> {
+        let status = match address_type {
+            AddressType::SubcmpSignal { input_information: InputInformation::Input { status }, .. } => status,
+            _ => return Ok(()),
+        };
+        let component = &mut self.components[sub_component];
+        component.input_counter = component.input_counter.saturating_sub(size);
+        let run = match status {
+            StatusInput::NoLast => false,
+            StatusInput::Last => true,
+            StatusInput::Unknown => component.input_counter == 0,
+        };
+        if run {
+            self.run(sub_component)?;
+        }
+        Ok(())
+    }
+
+    fn create_components(&mut self, bucket: &CreateCmpBucket, frame: &mut Frame) -> Execution<()> {
+        let base = self.evaluate_address(&bucket.sub_cmp_id, frame)?;
+        let complete_array = bucket.defined_positions.len() == bucket.number_of_cmp;
+        let iterations = if complete_array { bucket.number_of_cmp } else { bucket.defined_positions.len() };
+        let mut cmp_num = frame.my_id + bucket.component_offset + 1;
+        let mut csoffset = self.components[frame.my_id].signal_start + bucket.signal_offset;
+        for counter in 0..iterations {
+            let position = if complete_array { counter } else { bucket.defined_positions[counter].0 };
+            let name = if bucket.number_of_cmp > 1 {
+                component_name(&bucket.name_subcomponent, position, &bucket.dimensions)
+            } else {
+                bucket.name_subcomponent.clone()
+            };
+            self.create(bucket.template_id, csoffset, cmp_num, name, frame.my_id)?;
+            match self.components[frame.my_id].subcomponents.get_mut(base + position) {
+                Some(subcomponent) => *subcomponent = cmp_num,
+                None => return Err(self.failure(frame, bucket.line, "Subcomponent index out of bounds")),
+            }
+            csoffset += bucket.signal_offset_jump;
+            cmp_num += bucket.component_offset_jump;
+        }
+        Ok(())
+    }
+
+    fn call(&mut self, bucket: &CallBucket, frame: &mut Frame) -> Execution<Vec<BigInt>> {
+        // the arguments are copied to the arena, that becomes the variables of the function
+        let mut arena = Vec::with_capacity(bucket.arena_size);
+        for (argument, argument_type) in bucket.arguments.iter().zip(&bucket.argument_types) {
+            let mut value = self.evaluate(argument, frame)?;
+            value.resize(argument_type.size, BigInt::zero());
+            arena.append(&mut value);
+        }
+        let function = self.functions[bucket.symbol.as_str()];
+        if arena.len() < bucket.arena_size.max(function.max_number_of_vars) {
+            arena.resize(bucket.arena_size.max(function.max_number_of_vars), BigInt::zero());
+        }
+        let mut function_frame = Frame { vars: arena, my_id: frame.my_id, name: &function.name };
+        let mut result = self.execute_list(&function.body, &mut function_frame)?.unwrap_or_default();
+        match &bucket.return_info {
+            ReturnType::Intermediate { .. } => {
+                result.resize(1, BigInt::zero());
+                Ok(result)
+            }
+            ReturnType::Final(data) => {
+                let (memory, index, sub_component) = self.address(&data.dest_address_type, &data.dest, frame)?;
+                result.resize(data.context.size, BigInt::zero());
+                self.write(memory, index, &result, data.context.size, frame, bucket.line)?;
+                if let Some(sub_component) = sub_component {
+                    self.update_inputs(&data.dest_address_type, sub_component, data.context.size)?;
+                }
+                Ok(vec![])
+            }
+        }
+    }
+
+    fn compute(&self, bucket: &ComputeBucket, operands: &[Vec<BigInt>], frame: &Frame) -> Execution<Vec<BigInt>> {
+        use modular_arithmetic::*;
+        use OperatorType::*;
+        let field = &self.field;
+        let a = &operands[0][0];
+        let b = operands.get(1).map(|operand| &operand[0]);
+        let result = match (bucket.op, b) {
+            (AddAddress, Some(b)) => a + b,
+            (MulAddress, Some(b)) => a * b,
+            (ToAddress, _) => a.clone(),
+            (PrefixSub, _) => prefix_sub(a, field),
+            (BoolNot, _) => not(a, field),
+            (Complement, _) => complement(a, field),
+            (Eq(n), Some(_)) if n > 1 => {
+                let equal = operands[0].iter().zip(&operands[1]).take(n).all(|(a, b)| a == b);
+                BigInt::from(equal as u8)
+            }
+            (op, Some(b)) => match op {
+                Mul => mul(a, b, field),
+                Add => add(a, b, field),
+                Sub => sub(a, b, field),
+                Pow => pow(a, b, field),
+                Div | IntDiv | Mod => {
+                    let result = match op {
+                        Div => div(a, b, field),
+                        IntDiv => idiv(a, b, field),
+                        _ => mod_op(a, b, field),
+                    };
+                    result.map_err(|_| self.failure(frame, bucket.line, "Division by zero"))?
+                }
+                ShiftL => shift(a, b, true, field),
+                ShiftR => shift(a, b, false, field),
+                LesserEq => lesser_eq(a, b, field),
+                GreaterEq => greater_eq(a, b, field),
+                Lesser => lesser(a, b, field),
+                Greater => greater(a, b, field),
+                Eq(_) => eq(a, b, field),
+                NotEq => not_eq(a, b, field),
+                BoolOr => bool_or(a, b, field),
+                BoolAnd => bool_and(a, b, field),
+                BitOr => bit_or(a, b, field),
+                BitAnd => bit_and(a, b, field),
+                BitXor => bit_xor(a, b, field),
+                _ => unreachable!(),
+            },
+            _ => unreachable!(),
+        };
+        Ok(vec![result])
+    }
+
+    fn trace(&self, id: usize) -> String {
+        let component = &self.components[id];
+        if id == 0 {
+            component.name.clone()
+        } else {
+            format!("{}.{}", self.trace(component.father), component.name)
+        }
+    }
+
+    fn failure(&self, frame: &Frame, line: usize, message: &str) -> WitnessFailure {
+        WitnessFailure {
+            message: format!(
+                "{} in template/function {} line {}. Followed trace of components: {}",
+                message,
+                frame.name,
+                line,
+                self.trace(frame.my_id)
+            ),
+            location: None,
+            values: Vec::new(),
+        }
+    }
+}
+
+// Name of the component at position of a flattened array of components
+fn component_name(name: &str, mut position: usize, dimensions: &[usize]) -> String {
+    let mut indexes = vec![0; dimensions.len()];
+    for (i, dimension) in dimensions.iter().enumerate().rev() {
+        indexes[i] = position % dimension;
+        position /= dimension;
+    }
+    let indexes: Vec<String> = indexes.iter().map(|index| format!("[{}]", index)).collect();
+    format!("{}{}", name, indexes.join(""))
+}
+
+// Shifts by amounts over p/2 are shifts in the other direction by p - amount,
+// and shifts by the number of bits of p or more give 0, as in the field code
+fn shift(value: &BigInt, amount: &BigInt, left: bool, field: &BigInt) -> BigInt {
+    let half = field / 2;
+    let (amount, left) = if *amount > half { (field - amount, !left) } else { (amount.clone(), left) };
+    let bits = field.bits();
+    match amount.to_usize() {
+        Some(amount) if amount < bits => {
+            if left {
+                let mask = (BigInt::one() << bits) - 1;
+                ((value << amount) & mask) % field
+            } else {
+                value >> amount
+            }
+        }
+        _ => BigInt::zero(),
+    }
+}
+
+// Complement of the bits of value up to the number of bits of p
+fn complement(value: &BigInt, field: &BigInt) -> BigInt {
+    let mask = (BigInt::one() << field.bits()) - 1;
+    let result = mask - value;
+    if result >= *field {
+        result - field
+    } else {
+        result
+    }
+}
@@ -1,4 +1,5 @@
 pub use crate::circuit_design::circuit::{Circuit, CompilationFlags};
+pub use crate::circuit_design::witness_interpreter::{WitnessFailure, WitnessRun};
 pub use crate::hir::very_concrete_program::VCP;
 use std::fs::File;
 use std::io::BufWriter;
@@ -7,10 +8,17 @@ pub struct Config {
     pub debug_output: bool,
     pub produce_input_log: bool,
     pub wat_flag: bool,
+    pub profile_witness: bool,
+    pub witness_optimization: usize,
 }
 
 pub fn run_compiler(vcp: VCP, config: Config, version: &str) -> Result<Circuit, ()> {
-    let flags = CompilationFlags { main_inputs_log: config.produce_input_log, wat_flag: config.wat_flag };
+    let flags = CompilationFlags {
+        main_inputs_log: config.produce_input_log,
+        wat_flag: config.wat_flag,
+        profile_witness: config.profile_witness,
+        witness_optimization: config.witness_optimization,
+    };
     let circuit = Circuit::build(vcp, flags, version);
     if config.debug_output {
         produce_debug_output(&circuit)?;
@@ -42,6 +50,22 @@ pub fn write_c(circuit: &Circuit, c_folder: &str, c_run_name: &str, c_file: &str
     circuit.produce_c(c_folder, c_run_name, &mut c_file, &mut dat_file)
 }
 
+pub fn write_llvm_ir(circuit: &Circuit, llvm_folder: &str, llvm_run_name: &str, ll_file: &str) -> Result<(), ()> {
+    use std::path::Path;
+    if Path::new(llvm_folder).is_dir() {
+        std::fs::remove_dir_all(llvm_folder).map_err(|_err| {})?;
+    }
+    std::fs::create_dir(llvm_folder).map_err(|_err| {})?;
+    let ll_file = File::create(ll_file).map_err(|_err| {})?;
+    let mut ll_file = BufWriter::new(ll_file);
+    circuit.produce_llvm_ir(llvm_folder, llvm_run_name, &mut ll_file)
+}
+
+// Computes the witness of a circuit whose main component has no inputs
+pub fn compute_witness(circuit: &Circuit) -> WitnessRun {
+    crate::circuit_design::witness_interpreter::compute_witness(circuit)
+}
+
 fn produce_debug_output(circuit: &Circuit) -> Result<(), ()> {
     use std::io::Write;
     use std::path::Path;
@@ -429,6 +429,8 @@ fn rmv_sugar(fresh_variable: &str, expr: Expression, buffer: &mut Vec<Statement>
         xtype: VariableType::Var,
         name: fresh_variable.to_string(),
         dimensions: vec![],
+        var_type: VarType::Field,
+        is_kept: false,
     };
     let initialization = Substitution {
         meta: initialization_meta,
@@ -537,6 +539,8 @@ fn split_return(stmt: Statement, id: usize) -> ReturnSplit {
             name: id.to_string(),
             dimensions: expr_lengths,
             is_constant: false,
+            var_type: VarType::Field,
+            is_kept: false,
         };
         let substitution = Substitution {
             meta: substitution_meta,
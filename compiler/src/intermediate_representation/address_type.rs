@@ -1,4 +1,7 @@
 use super::ir_interface::*;
+use crate::translating_traits::*;
+use code_producers::llvm_elements::llvm_code_generator::LLVMContext;
+use code_producers::llvm_elements::*;
 
 #[derive(Clone)]
 pub enum StatusInput {
@@ -30,3 +33,112 @@ impl ToString for AddressType {
         }
     }
 }
+
+// Address of the first element written or read through address_type and location. When
+// the access is to a subcomponent its position in the component memory is also returned.
+pub fn produce_llvm_ir_address(
+    address_type: &AddressType,
+    location: &LocationRule,
+    producer: &LLVMProducer,
+    context: &mut LLVMContext,
+) -> (Vec<String>, String, Option<String>) {
+    use llvm_code_generator::*;
+    let mut instructions = vec![];
+    let sub_component = if let AddressType::SubcmpSignal { cmp_address, .. } = address_type {
+        let (mut cmp_instructions, cmp_index) = cmp_address.produce_llvm_ir(producer, context);
+        instructions.append(&mut cmp_instructions);
+        let (mut id_instructions, id) = subcomponent_id(&cmp_index, context);
+        instructions.append(&mut id_instructions);
+        Some(id)
+    } else {
+        None
+    };
+    let index = match location {
+        LocationRule::Indexed { location, .. } => {
+            let (mut index_instructions, index) = location.produce_llvm_ir(producer, context);
+            instructions.append(&mut index_instructions);
+            index
+        }
+        LocationRule::Mapped { signal_code, indexes } => {
+            // only subcomponent signals are mapped
+            let mut values = vec![];
+            for index in indexes {
+                let (mut index_instructions, value) = index.produce_llvm_ir(producer, context);
+                instructions.append(&mut index_instructions);
+                values.push(value);
+            }
+            let sub_component = sub_component.as_ref().unwrap();
+            let (mut map_instructions, offset) = mapped_signal_offset(sub_component, *signal_code, values, context);
+            instructions.append(&mut map_instructions);
+            offset
+        }
+    };
+    let (mut address_instructions, address) = match address_type {
+        AddressType::Variable => element_address(LVAR, &index, context),
+        AddressType::Signal => signal_address(&index, context),
+        AddressType::SubcmpSignal { .. } => {
+            subcomponent_signal_address(sub_component.as_ref().unwrap(), &index, context)
+        }
+    };
+    instructions.append(&mut address_instructions);
+    (instructions, address, sub_component)
+}
+
+// After size inputs of a subcomponent are written its input counter is decreased,
+// and the subcomponent is run when it was the last input
+pub fn produce_llvm_ir_input_update(
+    address_type: &AddressType,
+    location: &LocationRule,
+    sub_component: &str,
+    size: usize,
+    context: &mut LLVMContext,
+) -> Vec<String> {
+    use llvm_code_generator::*;
+    let status = match address_type {
+        AddressType::SubcmpSignal { input_information: InputInformation::Input { status }, .. } => status,
+        _ => return vec![],
+    };
+    let (mut instructions, counter_address) = component_field(sub_component, COMPONENT_INPUT_COUNTER, context);
+    let (mut counter_instructions, counter) = load("i32", &counter_address, context);
+    instructions.append(&mut counter_instructions);
+    let remaining = context.fresh_value();
+    instructions.push(format!("{} = sub i32 {}, {}", remaining, counter, size));
+    instructions.push(store("i32", &remaining, &counter_address));
+    if let StatusInput::NoLast = status {
+        return instructions;
+    }
+    let mut run = vec![];
+    match location {
+        LocationRule::Indexed { template_header, .. } => {
+            let run_function = format!("@{}_run", template_header.as_ref().unwrap());
+            run.push(build_call(&run_function, vec![typed("i32", sub_component)]));
+        }
+        LocationRule::Mapped { .. } => {
+            // the template of the subcomponent is only known at execution
+            let (mut template_instructions, template_id) =
+                load_component_field(sub_component, COMPONENT_TEMPLATE_ID, "i32", context);
+            run.append(&mut template_instructions);
+            let entry = context.fresh_value();
+            run.push(format!("{} = getelementptr inbounds ptr, ptr {}, i32 {}", entry, G_RUN_TABLE, template_id));
+            let (mut function_instructions, run_function) = load("ptr", &entry, context);
+            run.append(&mut function_instructions);
+            run.push(build_call(&run_function, vec![typed("i32", sub_component)]));
+        }
+    }
+    match status {
+        StatusInput::NoLast => {}
+        StatusInput::Last => instructions.append(&mut run),
+        StatusInput::Unknown => {
+            let is_last = context.fresh_value();
+            let run_label = context.fresh_label("run");
+            let end_label = context.fresh_label("run_end");
+            instructions.push(format!("{} = icmp eq i32 {}, 0", is_last, remaining));
+            instructions.push(conditional_branch(&is_last, &run_label, &end_label));
+            instructions.push(label(&run_label));
+            instructions.append(&mut run);
+            instructions.push(branch(&end_label));
+            instructions.push(label(&end_label));
+        }
+    }
+    instructions
+}
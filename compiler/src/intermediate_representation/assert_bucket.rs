@@ -1,6 +1,8 @@
 use super::ir_interface::*;
 use crate::translating_traits::*;
 use code_producers::c_elements::*;
+use code_producers::llvm_elements::llvm_code_generator::LLVMContext;
+use code_producers::llvm_elements::*;
 use code_producers::wasm_elements::*;
 
 #[derive(Clone)]
@@ -8,6 +10,10 @@ pub struct AssertBucket {
     pub line: usize,
     pub message_id: usize,
     pub evaluate: InstructionPointer,
+    // entry of the assert message table describing this check
+    pub assert_id: usize,
+    // loads of the signals whose values are shown if the check fails
+    pub signals: InstructionList,
 }
 
 impl IntoInstruction for AssertBucket {
@@ -36,7 +42,15 @@ impl ToString for AssertBucket {
         let line = self.line.to_string();
         let template_id = self.message_id.to_string();
         let evaluate = self.evaluate.to_string();
-        format!("ASSERT(line: {},template_id: {},evaluate: {})", line, template_id, evaluate)
+        let assert_id = self.assert_id.to_string();
+        let mut signals = String::new();
+        for signal in &self.signals {
+            signals = format!("{}{};", signals, signal.to_string());
+        }
+        format!(
+            "ASSERT(line: {},template_id: {},evaluate: {},assert_id: {},signals: {})",
+            line, template_id, evaluate, assert_id, signals
+        )
     }
 }
 
@@ -56,6 +70,27 @@ impl WriteWasm for AssertBucket {
         instructions.push(set_constant(&self.line.to_string()));
         instructions.push(call("$buildBufferMessage"));
         instructions.push(call("$printErrorMessage"));
+        instructions.push(set_constant(&producer.get_assert_message_position(self.assert_id, 0).to_string()));
+        instructions.push(call("$buildLogMessage"));
+        instructions.push(call("$printErrorMessage"));
+        // the values of the signals are shown through the log channel
+        for (k, signal) in self.signals.iter().enumerate() {
+            let label = producer.get_assert_message_position(self.assert_id, k + 1);
+            instructions.push(set_constant(&label.to_string()));
+            instructions.push(call("$buildLogMessage"));
+            instructions.push(call("$writeBufferMessage"));
+            let mut instructions_signal = signal.produce_wasm(producer);
+            instructions.append(&mut instructions_signal);
+            instructions.push(call("$copyFr2SharedRWMemory"));
+            instructions.push(call("$showSharedRWMemory"));
+            instructions.push(set_constant(&producer.get_message_buffer_start().to_string()));
+            instructions.push(set_constant("0x0000000a"));
+            instructions.push(store32(None)); // stores \n000
+            instructions.push(set_constant(&producer.get_message_buffer_counter_position().to_string()));
+            instructions.push(set_constant("0"));
+            instructions.push(store32(None));
+            instructions.push(call("$writeBufferMessage"));
+        }
         instructions.push(set_constant(&exception_code_assert_fail().to_string()));
         instructions.push(add_return());
         instructions.push(add_end());
@@ -71,11 +106,69 @@ impl WriteC for AssertBucket {
         use c_code_generator::*;
         let (prologue, value) = self.evaluate.produce_c(producer, parallel);
         let is_true = build_call("Fr_isTrue".to_string(), vec![value]);
-        let if_condition = format!("if (!{}) {};", is_true, build_failed_assert_message(self.line));    
-        let assertion = format!("{};", build_call("assert".to_string(), vec![is_true]));
+        let mut failed = vec![
+            format!("{};", build_failed_assert_message(self.line)),
+            format!("{};", build_failed_assert_location(self.assert_id)),
+        ];
+        let labels = &producer.get_assert_message_list()[self.assert_id].signals;
+        for (signal, label) in self.signals.iter().zip(labels) {
+            let (mut signal_code, signal_result) = signal.produce_c(producer, parallel);
+            let to_string_call = build_call("Fr_element2str".to_string(), vec![signal_result]);
+            failed.append(&mut signal_code);
+            failed.push("{".to_string());
+            failed.push(format!("char* temp = {};", to_string_call));
+            failed.push(format!("std::cout << \"  {} = \" << temp << std::endl;", escape_string(label)));
+            failed.push("delete [] temp;".to_string());
+            failed.push("}".to_string());
+        }
         let mut assert_c = prologue;
-        assert_c.push(if_condition);
-        assert_c.push(assertion);
+        // the signal loads may reuse the auxiliary stack, so the result is kept first
+        assert_c.push("{".to_string());
+        assert_c.push(format!("bool {} = {};", ASSERT_HOLDS, is_true));
+        assert_c.push(format!("if (!{}) {{", ASSERT_HOLDS));
+        assert_c.append(&mut failed);
+        assert_c.push("}".to_string());
+        assert_c.push(format!("{};", build_call("assert".to_string(), vec![ASSERT_HOLDS.to_string()])));
+        assert_c.push("}".to_string());
         (assert_c, "".to_string())
     }
 }
+
+impl WriteLLVMIR for AssertBucket {
+    fn produce_llvm_ir(&self, producer: &LLVMProducer, context: &mut LLVMContext) -> (Vec<String>, String) {
+        use llvm_code_generator::*;
+        let (mut instructions, value) = self.evaluate.produce_llvm_ir(producer, context);
+        // the signal loads may reuse the auxiliary stack, so the result is kept first
+        let (mut condition_instructions, condition) = is_true(&value, context);
+        instructions.append(&mut condition_instructions);
+        let ok_label = context.fresh_label("assert_ok");
+        let failed_label = context.fresh_label("assert_failed");
+        instructions.push(conditional_branch(&condition, &ok_label, &failed_label));
+        instructions.push(label(&failed_label));
+        instructions.push(build_call(
+            "@circom_assert_failed",
+            vec![
+                typed("ptr", &context.get_name()),
+                typed("i32", &self.line.to_string()),
+                typed("i32", &self.assert_id.to_string()),
+                typed("i32", MY_ID),
+            ],
+        ));
+        let labels = &producer.get_assert_message_list()[self.assert_id].signals;
+        for (i, (signal, _)) in self.signals.iter().zip(labels).enumerate() {
+            let (mut signal_instructions, signal_value) = signal.produce_llvm_ir(producer, context);
+            instructions.append(&mut signal_instructions);
+            instructions.push(build_call(
+                "@circom_assert_signal",
+                vec![
+                    typed("ptr", &assert_signal_label_global(self.assert_id, i)),
+                    typed("ptr", &signal_value),
+                ],
+            ));
+        }
+        instructions.push(build_call("@circom_abort", vec![]));
+        instructions.push("unreachable".to_string());
+        instructions.push(label(&ok_label));
+        (instructions, "".to_string())
+    }
+}
@@ -1,6 +1,8 @@
 use super::ir_interface::*;
 use crate::translating_traits::*;
 use code_producers::c_elements::*;
+use code_producers::llvm_elements::llvm_code_generator::LLVMContext;
+use code_producers::llvm_elements::*;
 use code_producers::wasm_elements::*;
 
 #[derive(Clone)]
@@ -121,3 +123,30 @@ impl WriteC for BranchBucket {
         (c_branch, "".to_string())
     }
 }
+
+impl WriteLLVMIR for BranchBucket {
+    fn produce_llvm_ir(&self, producer: &LLVMProducer, context: &mut LLVMContext) -> (Vec<String>, String) {
+        use llvm_code_generator::*;
+        let (mut instructions, cond) = self.cond.produce_llvm_ir(producer, context);
+        let (mut condition_instructions, condition) = is_true(&cond, context);
+        instructions.append(&mut condition_instructions);
+        let if_label = context.fresh_label("if_then");
+        let else_label = context.fresh_label("if_else");
+        let end_label = context.fresh_label("if_end");
+        instructions.push(conditional_branch(&condition, &if_label, &else_label));
+        instructions.push(label(&if_label));
+        for instr in &self.if_branch {
+            let (mut instr_code, _) = instr.produce_llvm_ir(producer, context);
+            instructions.append(&mut instr_code);
+        }
+        instructions.push(branch(&end_label));
+        instructions.push(label(&else_label));
+        for instr in &self.else_branch {
+            let (mut instr_code, _) = instr.produce_llvm_ir(producer, context);
+            instructions.append(&mut instr_code);
+        }
+        instructions.push(branch(&end_label));
+        instructions.push(label(&end_label));
+        (instructions, "".to_string())
+    }
+}
@@ -1,6 +1,8 @@
 use super::ir_interface::*;
 use crate::translating_traits::*;
 use code_producers::c_elements::*;
+use code_producers::llvm_elements::llvm_code_generator::LLVMContext;
+use code_producers::llvm_elements::*;
 use code_producers::wasm_elements::*;
 
 #[derive(Clone)]
@@ -648,3 +650,52 @@ impl WriteC for CallBucket {
         (prologue, result)
     }
 }
+
+impl WriteLLVMIR for CallBucket {
+    fn produce_llvm_ir(&self, producer: &LLVMProducer, context: &mut LLVMContext) -> (Vec<String>, String) {
+        use llvm_code_generator::*;
+        let mut instructions = vec![];
+        // the arguments are copied to the arena, that becomes the lvar of the function
+        let arena = context.alloca(&fr_array(self.arena_size));
+        let mut count = 0;
+        for (argument, argument_type) in self.arguments.iter().zip(&self.argument_types) {
+            let (mut argument_instructions, src) = argument.produce_llvm_ir(producer, context);
+            instructions.append(&mut argument_instructions);
+            let (mut position_instructions, position) = element_address(&arena, &count.to_string(), context);
+            instructions.append(&mut position_instructions);
+            instructions.push(copy_elements(&position, &src, argument_type.size, producer));
+            count += argument_type.size;
+        }
+        let function = format!("@{}", self.symbol);
+        let mut call_arguments = vec![typed("ptr", &arena), typed("i32", MY_ID)];
+        match &self.return_info {
+            ReturnType::Intermediate { op_aux_no } => {
+                let (mut result_instructions, result) = element_address(EXPAUX, &op_aux_no.to_string(), context);
+                instructions.append(&mut result_instructions);
+                call_arguments.push(typed("ptr", &result));
+                call_arguments.push(typed("i32", "1"));
+                instructions.push(build_call(&function, call_arguments));
+                (instructions, result)
+            }
+            ReturnType::Final(data) => {
+                let (mut dest_instructions, dest, sub_component) =
+                    produce_llvm_ir_address(&data.dest_address_type, &data.dest, producer, context);
+                instructions.append(&mut dest_instructions);
+                call_arguments.push(typed("ptr", &dest));
+                call_arguments.push(typed("i32", &data.context.size.to_string()));
+                instructions.push(build_call(&function, call_arguments));
+                if let Some(sub_component) = sub_component {
+                    let mut update = produce_llvm_ir_input_update(
+                        &data.dest_address_type,
+                        &data.dest,
+                        &sub_component,
+                        data.context.size,
+                        context,
+                    );
+                    instructions.append(&mut update);
+                }
+                (instructions, "".to_string())
+            }
+        }
+    }
+}
@@ -1,6 +1,8 @@
 use super::ir_interface::*;
 use crate::translating_traits::*;
 use code_producers::c_elements::*;
+use code_producers::llvm_elements::llvm_code_generator::LLVMContext;
+use code_producers::llvm_elements::*;
 use code_producers::wasm_elements::*;
 
 #[derive(Copy, Clone, PartialEq, Eq)]
@@ -388,3 +390,77 @@ impl WriteC for ComputeBucket {
         (compute_c, result)
     }
 }
+
+impl WriteLLVMIR for ComputeBucket {
+    fn produce_llvm_ir(&self, producer: &LLVMProducer, context: &mut LLVMContext) -> (Vec<String>, String) {
+        use llvm_code_generator::*;
+        fn get_fr_op(op_type: OperatorType) -> &'static str {
+            match op_type {
+                OperatorType::Add => "@Fr_add",
+                OperatorType::Div => "@Fr_div",
+                OperatorType::Mul => "@Fr_mul",
+                OperatorType::Sub => "@Fr_sub",
+                OperatorType::Pow => "@Fr_pow",
+                OperatorType::IntDiv => "@Fr_idiv",
+                OperatorType::Mod => "@Fr_mod",
+                OperatorType::ShiftL => "@Fr_shl",
+                OperatorType::ShiftR => "@Fr_shr",
+                OperatorType::LesserEq => "@Fr_leq",
+                OperatorType::GreaterEq => "@Fr_geq",
+                OperatorType::Lesser => "@Fr_lt",
+                OperatorType::Greater => "@Fr_gt",
+                OperatorType::Eq(_) => "@Fr_eq",
+                OperatorType::NotEq => "@Fr_neq",
+                OperatorType::BoolOr => "@Fr_lor",
+                OperatorType::BoolAnd => "@Fr_land",
+                OperatorType::BitOr => "@Fr_bor",
+                OperatorType::BitAnd => "@Fr_band",
+                OperatorType::BitXor => "@Fr_bxor",
+                OperatorType::PrefixSub => "@Fr_neg",
+                OperatorType::BoolNot => "@Fr_lnot",
+                OperatorType::Complement => "@Fr_bnot",
+                _ => unreachable!(),
+            }
+        }
+
+        let mut instructions = vec![];
+        let mut operands = vec![];
+        for instr in &self.stack {
+            let (mut instr_code, operand) = instr.produce_llvm_ir(producer, context);
+            instructions.append(&mut instr_code);
+            operands.push(operand);
+        }
+        let result = match &self.op {
+            OperatorType::AddAddress | OperatorType::MulAddress => {
+                let operation = if self.op == OperatorType::AddAddress { "add" } else { "mul" };
+                let result = context.fresh_value();
+                instructions.push(format!("{} = {} i32 {}, {}", result, operation, operands[0], operands[1]));
+                result
+            }
+            OperatorType::ToAddress => {
+                let result = context.fresh_value();
+                instructions.push(format!("{} = call i32 @Fr_toInt(ptr {})", result, operands[0]));
+                result
+            }
+            _ => {
+                let (mut result_code, result) =
+                    element_address(EXPAUX, &self.op_aux_no.to_string(), context);
+                instructions.append(&mut result_code);
+                let mut arguments = vec![typed("ptr", &result)];
+                for operand in &operands {
+                    arguments.push(typed("ptr", operand));
+                }
+                if let OperatorType::Eq(n) = self.op {
+                    if n > 1 {
+                        arguments.push(typed("i32", &n.to_string()));
+                        instructions.push(build_call("@Fr_eqn", arguments));
+                        return (instructions, result);
+                    }
+                }
+                instructions.push(build_call(get_fr_op(self.op), arguments));
+                result
+            }
+        };
+        (instructions, result)
+    }
+}
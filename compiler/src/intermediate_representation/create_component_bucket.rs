@@ -1,6 +1,8 @@
 use super::ir_interface::*;
 use crate::translating_traits::*;
 use code_producers::c_elements::*;
+use code_producers::llvm_elements::llvm_code_generator::LLVMContext;
+use code_producers::llvm_elements::*;
 use code_producers::wasm_elements::*;
 
 #[derive(Clone)]
@@ -302,4 +304,112 @@ impl WriteC for CreateCmpBucket {
         instructions.push("}".to_string());
         (instructions, "".to_string())
     }
-}
\ No newline at end of file
+}
+impl WriteLLVMIR for CreateCmpBucket {
+    fn produce_llvm_ir(&self, producer: &LLVMProducer, context: &mut LLVMContext) -> (Vec<String>, String) {
+        use llvm_code_generator::*;
+        let complete_array: bool = self.defined_positions.len() == self.number_of_cmp;
+        let (mut instructions, aux_create) = self.sub_cmp_id.produce_llvm_ir(producer, context);
+        let iterations = if complete_array { self.number_of_cmp } else { self.defined_positions.len() };
+        if iterations == 0 {
+            return (instructions, "".to_string());
+        }
+        let counter = context.alloca("i32");
+        let csoffset = context.alloca("i32");
+        let cmp_num = context.alloca("i32");
+        let first_cmp_num = context.fresh_value();
+        instructions.push(format!("{} = add i32 {}, {}", first_cmp_num, MY_ID, self.component_offset + 1));
+        instructions.push(store("i32", &first_cmp_num, &cmp_num));
+        let first_csoffset = context.fresh_value();
+        instructions.push(format!("{} = add i32 {}, {}", first_csoffset, MY_SIGNAL_START, self.signal_offset));
+        instructions.push(store("i32", &first_csoffset, &csoffset));
+        instructions.push(store("i32", "0", &counter));
+        // if there are empty components only the defined positions are traversed
+        let positions = if complete_array {
+            None
+        } else {
+            let values: Vec<String> = self.defined_positions.iter().map(|(x, _)| typed("i32", &x.to_string())).collect();
+            let ty = format!("[{} x i32]", self.defined_positions.len());
+            Some(context.private_constant(&ty, &format!("[{}]", values.join(", "))))
+        };
+        let name = context.string_constant(&self.name_subcomponent);
+        let dimensions = if self.number_of_cmp > 1 {
+            let values: Vec<String> = self.dimensions.iter().map(|x| typed("i32", &x.to_string())).collect();
+            let ty = format!("[{} x i32]", self.dimensions.len());
+            Some(context.private_constant(&ty, &format!("[{}]", values.join(", "))))
+        } else {
+            None
+        };
+
+        let cond_label = context.fresh_label("create_cond");
+        let body_label = context.fresh_label("create_body");
+        let end_label = context.fresh_label("create_end");
+        instructions.push(branch(&cond_label));
+        instructions.push(label(&cond_label));
+        let (mut counter_instructions, counter_value) = load("i32", &counter, context);
+        instructions.append(&mut counter_instructions);
+        let continue_loop = context.fresh_value();
+        instructions.push(format!("{} = icmp ult i32 {}, {}", continue_loop, counter_value, iterations));
+        instructions.push(conditional_branch(&continue_loop, &body_label, &end_label));
+        instructions.push(label(&body_label));
+        let position = if let Some(positions) = &positions {
+            let position_address = context.fresh_value();
+            instructions.push(format!(
+                "{} = getelementptr inbounds i32, ptr {}, i32 {}",
+                position_address, positions, counter_value
+            ));
+            let (mut position_instructions, position) = load("i32", &position_address, context);
+            instructions.append(&mut position_instructions);
+            position
+        } else {
+            counter_value.clone()
+        };
+        let component_name = if let Some(dimensions) = &dimensions {
+            let component_name = context.fresh_value();
+            instructions.push(format!(
+                "{} = call ptr @circom_component_name(ptr {}, i32 {}, ptr {}, i32 {})",
+                component_name,
+                name,
+                position,
+                dimensions,
+                self.dimensions.len()
+            ));
+            component_name
+        } else {
+            name
+        };
+        let (mut csoffset_instructions, csoffset_value) = load("i32", &csoffset, context);
+        instructions.append(&mut csoffset_instructions);
+        let (mut cmp_num_instructions, cmp_num_value) = load("i32", &cmp_num, context);
+        instructions.append(&mut cmp_num_instructions);
+        instructions.push(build_call(
+            &format!("@{}_create", self.symbol),
+            vec![
+                typed("i32", &csoffset_value),
+                typed("i32", &cmp_num_value),
+                typed("ptr", &component_name),
+                typed("i32", MY_ID),
+            ],
+        ));
+        let subcomponent_index = context.fresh_value();
+        instructions.push(format!("{} = add i32 {}, {}", subcomponent_index, aux_create, position));
+        let subcomponent_address = context.fresh_value();
+        instructions.push(format!(
+            "{} = getelementptr inbounds i32, ptr {}, i32 {}",
+            subcomponent_address, MY_SUBCOMPONENTS, subcomponent_index
+        ));
+        instructions.push(store("i32", &cmp_num_value, &subcomponent_address));
+        let next_csoffset = context.fresh_value();
+        instructions.push(format!("{} = add i32 {}, {}", next_csoffset, csoffset_value, self.signal_offset_jump));
+        instructions.push(store("i32", &next_csoffset, &csoffset));
+        let next_cmp_num = context.fresh_value();
+        instructions.push(format!("{} = add i32 {}, {}", next_cmp_num, cmp_num_value, self.component_offset_jump));
+        instructions.push(store("i32", &next_cmp_num, &cmp_num));
+        let next_counter = context.fresh_value();
+        instructions.push(format!("{} = add i32 {}, 1", next_counter, counter_value));
+        instructions.push(store("i32", &next_counter, &counter));
+        instructions.push(branch(&cond_label));
+        instructions.push(label(&end_label));
+        (instructions, "".to_string())
+    }
+}
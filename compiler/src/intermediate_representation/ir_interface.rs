@@ -1,4 +1,6 @@
-pub use super::address_type::{AddressType, InputInformation, StatusInput};
+pub use super::address_type::{
+    produce_llvm_ir_address, produce_llvm_ir_input_update, AddressType, InputInformation, StatusInput,
+};
 pub use super::assert_bucket::AssertBucket;
 pub use super::branch_bucket::BranchBucket;
 pub use super::call_bucket::{CallBucket, FinalData, ReturnType};
@@ -16,6 +18,8 @@ pub use super::value_bucket::ValueBucket;
 
 use crate::translating_traits::*;
 use code_producers::c_elements::*;
+use code_producers::llvm_elements::llvm_code_generator::LLVMContext;
+use code_producers::llvm_elements::*;
 use code_producers::wasm_elements::*;
 
 pub trait IntoInstruction {
@@ -145,6 +149,25 @@ impl WriteC for Instruction {
     }
 }
 
+impl WriteLLVMIR for Instruction {
+    fn produce_llvm_ir(&self, producer: &LLVMProducer, context: &mut LLVMContext) -> (Vec<String>, String) {
+        use Instruction::*;
+        match self {
+            Value(v) => v.produce_llvm_ir(producer, context),
+            Load(v) => v.produce_llvm_ir(producer, context),
+            Store(v) => v.produce_llvm_ir(producer, context),
+            Compute(v) => v.produce_llvm_ir(producer, context),
+            Call(v) => v.produce_llvm_ir(producer, context),
+            Branch(v) => v.produce_llvm_ir(producer, context),
+            Return(v) => v.produce_llvm_ir(producer, context),
+            Loop(v) => v.produce_llvm_ir(producer, context),
+            Assert(v) => v.produce_llvm_ir(producer, context),
+            CreateCmp(v) => v.produce_llvm_ir(producer, context),
+            Log(v) => v.produce_llvm_ir(producer, context),
+        }
+    }
+}
+
 impl ToString for Instruction {
     fn to_string(&self) -> String {
         use Instruction::*;
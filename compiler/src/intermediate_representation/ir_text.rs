@@ -0,0 +1,946 @@
+//! Textual syntax of the intermediate representation.
+//!
+//! Every instruction is written as a parenthesized node: a head, then positional
+//! atoms, then `key=value` attributes and finally the child nodes. Atoms are words
+//! (`add`, `3`, `true`) or quoted strings (`"Main_1"`), and `;` starts a comment
+//! that runs until the end of the line. The attributes `line`, `msg` (message id)
+//! and `aux` (op_aux_no) are optional and default to 0.
+//!
+//! ```text
+//! (value u32 N)                                   ; N is a plain number
+//! (value bigint N)                                ; N is the id of a field constant
+//! (load size=S ADDRESS LOCATION)
+//! (store size=S output=BOOL ADDRESS LOCATION INSTR)
+//! (compute OP INSTR...)                           ; eq also takes n=SIZE
+//! (call "SYMBOL" arena=N (arg size=S INSTR)... RETURN)
+//!     RETURN := (intermediate) | (final size=S output=BOOL ADDRESS LOCATION)
+//! (branch INSTR (then INSTR...) (else INSTR...))
+//! (return size=S INSTR)
+//! (assert id=N INSTR (signals INSTR...))
+//! (log ARG...)                                    ; ARG := (exp INSTR) | (str N)
+//! (loop INSTR (body INSTR...))
+//! (create_cmp "SYMBOL" name="NAME" template_id=N cmp_unique_id=N
+//!     signal_offset=N signal_offset_jump=N component_offset=N component_offset_jump=N
+//!     number_of_cmp=N has_inputs=BOOL mixed_not_uniform_parallel=BOOL
+//!     uniform_parallel=OPTBOOL INSTR (dimensions N...) (defined (position N BOOL)...))
+//!
+//! ADDRESS  := (variable) | (signal)
+//!           | (subcmp output=BOOL parallel=OPTBOOL input=INPUT INSTR)
+//! LOCATION := (indexed INSTR) | (indexed header="HEADER" INSTR) | (mapped code=N INSTR...)
+//! OPTBOOL  := none | true | false
+//! INPUT    := none | last | no_last | unknown
+//! OP       := mul | div | add | sub | pow | int_div | mod | shift_l | shift_r
+//!           | lesser_eq | greater_eq | lesser | greater | eq | not_eq | bool_or
+//!           | bool_and | bit_or | bit_and | bit_xor | prefix_sub | bool_not
+//!           | complement | to_address | mul_address | add_address
+//! ```
+//!
+//! A list of instructions is written as a sequence of nodes.
+use super::ir_interface::*;
+
+// Nodes that fit in this width are written in a single line
+const LINE_WIDTH: usize = 100;
+
+pub fn write_list(code: &InstructionList) -> String {
+    let mut text = String::new();
+    for instruction in code {
+        write_node(&instruction_node(instruction), 0, &mut text);
+        text.push('\n');
+    }
+    text
+}
+
+pub fn write_instruction(instruction: &Instruction) -> String {
+    let mut text = String::new();
+    write_node(&instruction_node(instruction), 0, &mut text);
+    text
+}
+
+pub fn parse_list(text: &str) -> Result<InstructionList, String> {
+    let nodes = Reader::new(text)?.read_all()?;
+    nodes.iter().map(|n| parse_instruction(n).map(|i| i.allocate())).collect()
+}
+
+pub fn parse_instruction_text(text: &str) -> Result<Instruction, String> {
+    let nodes = Reader::new(text)?.read_all()?;
+    if nodes.len() != 1 {
+        return Err(format!("expected a single instruction, found {}", nodes.len()));
+    }
+    parse_instruction(&nodes[0])
+}
+
+#[derive(Clone, PartialEq, Eq)]
+enum Atom {
+    Word(String),
+    Text(String),
+}
+
+struct Node {
+    line: usize,
+    head: String,
+    atoms: Vec<Atom>,
+    attributes: Vec<(String, Atom)>,
+    children: Vec<Node>,
+}
+
+impl Node {
+    fn new(head: &str) -> Node {
+        Node { line: 0, head: head.to_string(), atoms: vec![], attributes: vec![], children: vec![] }
+    }
+    fn atom(mut self, word: impl ToString) -> Node {
+        self.atoms.push(Atom::Word(word.to_string()));
+        self
+    }
+    fn text(mut self, text: &str) -> Node {
+        self.atoms.push(Atom::Text(text.to_string()));
+        self
+    }
+    fn attr(mut self, key: &str, value: impl ToString) -> Node {
+        self.attributes.push((key.to_string(), Atom::Word(value.to_string())));
+        self
+    }
+    fn attr_text(mut self, key: &str, value: &str) -> Node {
+        self.attributes.push((key.to_string(), Atom::Text(value.to_string())));
+        self
+    }
+    fn meta(self, line: usize, message_id: usize) -> Node {
+        self.optional("line", line).optional("msg", message_id)
+    }
+    fn optional(self, key: &str, value: usize) -> Node {
+        if value == 0 { self } else { self.attr(key, value) }
+    }
+    fn child(mut self, node: Node) -> Node {
+        self.children.push(node);
+        self
+    }
+    fn instructions<'a>(mut self, code: impl IntoIterator<Item = &'a InstructionPointer>) -> Node {
+        self.children.extend(code.into_iter().map(|i| instruction_node(i)));
+        self
+    }
+}
+
+// Writer
+
+fn instruction_node(instruction: &Instruction) -> Node {
+    use Instruction::*;
+    match instruction {
+        Value(b) => {
+            let kind = match b.parse_as {
+                ValueType::U32 => "u32",
+                ValueType::BigInt => "bigint",
+            };
+            Node::new("value").atom(kind).atom(b.value).meta(b.line, b.message_id).optional("aux", b.op_aux_no)
+        }
+        Load(b) => Node::new("load")
+            .meta(b.line, b.message_id)
+            .attr("size", b.context.size)
+            .child(address_node(&b.address_type))
+            .child(location_node(&b.src)),
+        Store(b) => Node::new("store")
+            .meta(b.line, b.message_id)
+            .attr("size", b.context.size)
+            .attr("output", b.dest_is_output)
+            .child(address_node(&b.dest_address_type))
+            .child(location_node(&b.dest))
+            .child(instruction_node(&b.src)),
+        Compute(b) => {
+            let node = Node::new("compute").atom(operator_name(b.op));
+            let node = if let OperatorType::Eq(n) = b.op { node.attr("n", n) } else { node };
+            node.meta(b.line, b.message_id).optional("aux", b.op_aux_no).instructions(&b.stack)
+        }
+        Call(b) => {
+            let mut node = Node::new("call").text(&b.symbol).meta(b.line, b.message_id).attr("arena", b.arena_size);
+            for (argument, context) in b.arguments.iter().zip(&b.argument_types) {
+                node = node.child(Node::new("arg").attr("size", context.size).child(instruction_node(argument)));
+            }
+            let return_node = match &b.return_info {
+                ReturnType::Intermediate { op_aux_no } => Node::new("intermediate").optional("aux", *op_aux_no),
+                ReturnType::Final(data) => Node::new("final")
+                    .attr("size", data.context.size)
+                    .attr("output", data.dest_is_output)
+                    .child(address_node(&data.dest_address_type))
+                    .child(location_node(&data.dest)),
+            };
+            node.child(return_node)
+        }
+        Branch(b) => Node::new("branch")
+            .meta(b.line, b.message_id)
+            .child(instruction_node(&b.cond))
+            .child(Node::new("then").instructions(&b.if_branch))
+            .child(Node::new("else").instructions(&b.else_branch)),
+        Return(b) => Node::new("return")
+            .meta(b.line, b.message_id)
+            .attr("size", b.with_size)
+            .child(instruction_node(&b.value)),
+        Assert(b) => Node::new("assert")
+            .meta(b.line, b.message_id)
+            .attr("id", b.assert_id)
+            .child(instruction_node(&b.evaluate))
+            .child(Node::new("signals").instructions(&b.signals)),
+        Log(b) => {
+            let mut node = Node::new("log").meta(b.line, b.message_id);
+            for arg in &b.argsprint {
+                node = node.child(match arg {
+                    LogBucketArg::LogExp(exp) => Node::new("exp").child(instruction_node(exp)),
+                    LogBucketArg::LogStr(id) => Node::new("str").atom(id),
+                });
+            }
+            node
+        }
+        Loop(b) => Node::new("loop")
+            .meta(b.line, b.message_id)
+            .child(instruction_node(&b.continue_condition))
+            .child(Node::new("body").instructions(&b.body)),
+        CreateCmp(b) => {
+            let mut dimensions = Node::new("dimensions");
+            for d in &b.dimensions {
+                dimensions = dimensions.atom(d);
+            }
+            let mut defined = Node::new("defined");
+            for (position, parallel) in &b.defined_positions {
+                defined = defined.child(Node::new("position").atom(position).atom(parallel));
+            }
+            Node::new("create_cmp")
+                .text(&b.symbol)
+                .meta(b.line, b.message_id)
+                .attr_text("name", &b.name_subcomponent)
+                .attr("template_id", b.template_id)
+                .attr("cmp_unique_id", b.cmp_unique_id)
+                .attr("signal_offset", b.signal_offset)
+                .attr("signal_offset_jump", b.signal_offset_jump)
+                .attr("component_offset", b.component_offset)
+                .attr("component_offset_jump", b.component_offset_jump)
+                .attr("number_of_cmp", b.number_of_cmp)
+                .attr("has_inputs", b.has_inputs)
+                .attr("mixed_not_uniform_parallel", b.is_part_mixed_array_not_uniform_parallel)
+                .attr("uniform_parallel", optional_bool_name(b.uniform_parallel))
+                .child(instruction_node(&b.sub_cmp_id))
+                .child(dimensions)
+                .child(defined)
+        }
+    }
+}
+
+fn address_node(xtype: &AddressType) -> Node {
+    match xtype {
+        AddressType::Variable => Node::new("variable"),
+        AddressType::Signal => Node::new("signal"),
+        AddressType::SubcmpSignal { cmp_address, uniform_parallel_value, is_output, input_information } => {
+            let input = match input_information {
+                InputInformation::NoInput => "none",
+                InputInformation::Input { status: StatusInput::Last } => "last",
+                InputInformation::Input { status: StatusInput::NoLast } => "no_last",
+                InputInformation::Input { status: StatusInput::Unknown } => "unknown",
+            };
+            Node::new("subcmp")
+                .attr("output", is_output)
+                .attr("parallel", optional_bool_name(*uniform_parallel_value))
+                .attr("input", input)
+                .child(instruction_node(cmp_address))
+        }
+    }
+}
+
+fn location_node(location: &LocationRule) -> Node {
+    match location {
+        LocationRule::Indexed { location, template_header } => {
+            let node = Node::new("indexed");
+            let node = match template_header {
+                Some(header) => node.attr_text("header", header),
+                None => node,
+            };
+            node.child(instruction_node(location))
+        }
+        LocationRule::Mapped { signal_code, indexes } => {
+            Node::new("mapped").attr("code", signal_code).instructions(indexes)
+        }
+    }
+}
+
+const OPERATORS: [(&str, OperatorType); 26] = [
+    ("mul", OperatorType::Mul),
+    ("div", OperatorType::Div),
+    ("add", OperatorType::Add),
+    ("sub", OperatorType::Sub),
+    ("pow", OperatorType::Pow),
+    ("int_div", OperatorType::IntDiv),
+    ("mod", OperatorType::Mod),
+    ("shift_l", OperatorType::ShiftL),
+    ("shift_r", OperatorType::ShiftR),
+    ("lesser_eq", OperatorType::LesserEq),
+    ("greater_eq", OperatorType::GreaterEq),
+    ("lesser", OperatorType::Lesser),
+    ("greater", OperatorType::Greater),
+    ("eq", OperatorType::Eq(1)),
+    ("not_eq", OperatorType::NotEq),
+    ("bool_or", OperatorType::BoolOr),
+    ("bool_and", OperatorType::BoolAnd),
+    ("bit_or", OperatorType::BitOr),
+    ("bit_and", OperatorType::BitAnd),
+    ("bit_xor", OperatorType::BitXor),
+    ("prefix_sub", OperatorType::PrefixSub),
+    ("bool_not", OperatorType::BoolNot),
+    ("complement", OperatorType::Complement),
+    ("to_address", OperatorType::ToAddress),
+    ("mul_address", OperatorType::MulAddress),
+    ("add_address", OperatorType::AddAddress),
+];
+
+fn operator_name(op: OperatorType) -> &'static str {
+    let op = if let OperatorType::Eq(_) = op { OperatorType::Eq(1) } else { op };
+    OPERATORS.iter().find(|(_, o)| *o == op).unwrap().0
+}
+
+fn optional_bool_name(value: Option<bool>) -> &'static str {
+    match value {
+        None => "none",
+        Some(true) => "true",
+        Some(false) => "false",
+    }
+}
+
+fn atom_string(atom: &Atom) -> String {
+    match atom {
+        Atom::Word(w) => w.clone(),
+        Atom::Text(t) => format!("\"{}\"", t.replace('\\', "\\\\").replace('"', "\\\"")),
+    }
+}
+
+fn inline_string(node: &Node) -> String {
+    let mut parts = vec![node.head.clone()];
+    parts.extend(node.atoms.iter().map(atom_string));
+    parts.extend(node.attributes.iter().map(|(k, v)| format!("{}={}", k, atom_string(v))));
+    parts.extend(node.children.iter().map(inline_string));
+    format!("({})", parts.join(" "))
+}
+
+fn is_block(node: &Node) -> bool {
+    matches!(node.head.as_str(), "then" | "else" | "body" | "signals") && !node.children.is_empty()
+}
+
+fn write_node(node: &Node, indent: usize, text: &mut String) {
+    let inline = inline_string(node);
+    if indent + inline.len() <= LINE_WIDTH && !node.children.iter().any(is_block) && !is_block(node) {
+        text.push_str(&inline);
+        return;
+    }
+    let mut parts = vec![node.head.clone()];
+    parts.extend(node.atoms.iter().map(atom_string));
+    parts.extend(node.attributes.iter().map(|(k, v)| format!("{}={}", k, atom_string(v))));
+    text.push('(');
+    text.push_str(&parts.join(" "));
+    for child in &node.children {
+        text.push('\n');
+        text.push_str(&" ".repeat(indent + 2));
+        write_node(child, indent + 2, text);
+    }
+    text.push(')');
+}
+
+// Reader
+
+#[derive(Clone, PartialEq, Eq)]
+enum Token {
+    Open,
+    Close,
+    Equal,
+    Atom(Atom),
+}
+
+struct Reader {
+    tokens: Vec<(usize, Token)>,
+    next: usize,
+}
+
+impl Reader {
+    fn new(text: &str) -> Result<Reader, String> {
+        let mut tokens = Vec::new();
+        let mut line = 1;
+        let mut chars = text.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '\n' => line += 1,
+                c if c.is_whitespace() => {}
+                ';' => {
+                    while chars.peek().is_some_and(|c| *c != '\n') {
+                        chars.next();
+                    }
+                }
+                '(' => tokens.push((line, Token::Open)),
+                ')' => tokens.push((line, Token::Close)),
+                '=' => tokens.push((line, Token::Equal)),
+                '"' => {
+                    let mut content = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('"') => break,
+                            Some('\\') => match chars.next() {
+                                Some(escaped) => content.push(escaped),
+                                None => return Err(format!("line {}: unterminated string", line)),
+                            },
+                            Some(c) => {
+                                if c == '\n' {
+                                    line += 1;
+                                }
+                                content.push(c)
+                            }
+                            None => return Err(format!("line {}: unterminated string", line)),
+                        }
+                    }
+                    tokens.push((line, Token::Atom(Atom::Text(content))));
+                }
+                c => {
+                    let mut word = c.to_string();
+                    while let Some(c) = chars.peek() {
+                        if c.is_whitespace() || matches!(c, '(' | ')' | '=' | ';' | '"') {
+                            break;
+                        }
+                        word.push(*c);
+                        chars.next();
+                    }
+                    tokens.push((line, Token::Atom(Atom::Word(word))));
+                }
+            }
+        }
+        Ok(Reader { tokens, next: 0 })
+    }
+
+    fn read_all(&mut self) -> Result<Vec<Node>, String> {
+        let mut nodes = Vec::new();
+        while self.next < self.tokens.len() {
+            nodes.push(self.read_node()?);
+        }
+        Ok(nodes)
+    }
+
+    fn read_node(&mut self) -> Result<Node, String> {
+        let line = self.expect(Token::Open)?;
+        let head = match self.advance() {
+            Some((_, Token::Atom(Atom::Word(head)))) => head,
+            _ => return Err(format!("line {}: expected the name of a node", line)),
+        };
+        let mut node = Node::new(&head);
+        node.line = line;
+        loop {
+            match self.peek() {
+                Some(Token::Close) => {
+                    self.advance();
+                    return Ok(node);
+                }
+                Some(Token::Open) => node.children.push(self.read_node()?),
+                Some(Token::Atom(atom)) => {
+                    self.advance();
+                    if let Some(Token::Equal) = self.peek() {
+                        self.advance();
+                        let key = match atom {
+                            Atom::Word(key) => key,
+                            Atom::Text(_) => return Err(format!("line {}: invalid attribute name", line)),
+                        };
+                        match self.advance() {
+                            Some((_, Token::Atom(value))) => node.attributes.push((key, value)),
+                            _ => return Err(format!("line {}: expected a value for {}", line, key)),
+                        }
+                    } else {
+                        node.atoms.push(atom);
+                    }
+                }
+                Some(Token::Equal) => return Err(format!("line {}: unexpected =", line)),
+                None => return Err(format!("line {}: unclosed ({}", line, head)),
+            }
+        }
+    }
+
+    fn peek(&self) -> Option<Token> {
+        self.tokens.get(self.next).map(|(_, t)| t.clone())
+    }
+
+    fn advance(&mut self) -> Option<(usize, Token)> {
+        let token = self.tokens.get(self.next).cloned();
+        self.next += 1;
+        token
+    }
+
+    fn expect(&mut self, expected: Token) -> Result<usize, String> {
+        match self.advance() {
+            Some((line, token)) if token == expected => Ok(line),
+            Some((line, _)) => Err(format!("line {}: expected (", line)),
+            None => Err("unexpected end of input".to_string()),
+        }
+    }
+}
+
+// Parser
+
+impl Node {
+    fn error<T>(&self, message: &str) -> Result<T, String> {
+        Err(format!("line {}: {} in ({})", self.line, message, self.head))
+    }
+    fn expect_head(&self, heads: &[&str]) -> Result<(), String> {
+        if heads.contains(&self.head.as_str()) { Ok(()) } else { self.error(&format!("expected one of {:?}", heads)) }
+    }
+    fn word(&self, position: usize) -> Result<&str, String> {
+        match self.atoms.get(position) {
+            Some(Atom::Word(w)) => Ok(w),
+            _ => self.error(&format!("missing argument {}", position + 1)),
+        }
+    }
+    fn text_atom(&self, position: usize) -> Result<String, String> {
+        match self.atoms.get(position) {
+            Some(Atom::Text(t)) => Ok(t.clone()),
+            _ => self.error(&format!("missing string argument {}", position + 1)),
+        }
+    }
+    fn number_atom(&self, position: usize) -> Result<usize, String> {
+        let word = self.word(position)?;
+        word.parse().or_else(|_| self.error(&format!("invalid number {}", word)))
+    }
+    fn find(&self, key: &str) -> Option<&Atom> {
+        self.attributes.iter().find(|(k, _)| k == key).map(|(_, v)| v)
+    }
+    fn word_attr(&self, key: &str) -> Result<&str, String> {
+        match self.find(key) {
+            Some(Atom::Word(w)) => Ok(w),
+            _ => self.error(&format!("missing attribute {}", key)),
+        }
+    }
+    fn text_attr(&self, key: &str) -> Result<Option<String>, String> {
+        match self.find(key) {
+            Some(Atom::Text(t)) => Ok(Some(t.clone())),
+            Some(Atom::Word(_)) => self.error(&format!("attribute {} must be a string", key)),
+            None => Ok(None),
+        }
+    }
+    fn number(&self, key: &str) -> Result<usize, String> {
+        let word = self.word_attr(key)?;
+        word.parse().or_else(|_| self.error(&format!("invalid number {}={}", key, word)))
+    }
+    fn number_or_zero(&self, key: &str) -> Result<usize, String> {
+        if self.find(key).is_some() { self.number(key) } else { Ok(0) }
+    }
+    fn boolean(&self, key: &str) -> Result<bool, String> {
+        parse_bool(self.word_attr(key)?).map_or_else(|| self.error(&format!("invalid boolean {}", key)), Ok)
+    }
+    fn optional_bool(&self, key: &str) -> Result<Option<bool>, String> {
+        match self.word_attr(key)? {
+            "none" => Ok(None),
+            other => parse_bool(other).map(Some).map_or_else(|| self.error(&format!("invalid value of {}", key)), Ok),
+        }
+    }
+    fn line_and_message(&self) -> Result<(usize, usize), String> {
+        Ok((self.number_or_zero("line")?, self.number_or_zero("msg")?))
+    }
+    fn child_at(&self, position: usize) -> Result<&Node, String> {
+        self.children.get(position).map_or_else(|| self.error(&format!("missing child {}", position + 1)), Ok)
+    }
+    fn expect_children(&self, number: usize) -> Result<(), String> {
+        if self.children.len() == number { Ok(()) } else { self.error(&format!("expected {} children", number)) }
+    }
+}
+
+fn parse_bool(word: &str) -> Option<bool> {
+    match word {
+        "true" => Some(true),
+        "false" => Some(false),
+        _ => None,
+    }
+}
+
+fn parse_pointer(node: &Node) -> Result<InstructionPointer, String> {
+    parse_instruction(node).map(|i| i.allocate())
+}
+
+fn parse_block(node: &Node, head: &str) -> Result<InstructionList, String> {
+    node.expect_head(&[head])?;
+    node.children.iter().map(parse_pointer).collect()
+}
+
+fn parse_instruction(node: &Node) -> Result<Instruction, String> {
+    let (line, message_id) = node.line_and_message()?;
+    let instruction = match node.head.as_str() {
+        "value" => {
+            let parse_as = match node.word(0)? {
+                "u32" => ValueType::U32,
+                "bigint" => ValueType::BigInt,
+                _ => return node.error("expected u32 or bigint"),
+            };
+            node.expect_children(0)?;
+            ValueBucket { line, message_id, parse_as, op_aux_no: node.number_or_zero("aux")?, value: node.number_atom(1)? }
+                .into_instruction()
+        }
+        "load" => {
+            node.expect_children(2)?;
+            LoadBucket {
+                line,
+                message_id,
+                context: InstrContext { size: node.number("size")? },
+                address_type: parse_address(node.child_at(0)?)?,
+                src: parse_location(node.child_at(1)?)?,
+            }
+            .into_instruction()
+        }
+        "store" => {
+            node.expect_children(3)?;
+            StoreBucket {
+                line,
+                message_id,
+                context: InstrContext { size: node.number("size")? },
+                dest_is_output: node.boolean("output")?,
+                dest_address_type: parse_address(node.child_at(0)?)?,
+                dest: parse_location(node.child_at(1)?)?,
+                src: parse_pointer(node.child_at(2)?)?,
+            }
+            .into_instruction()
+        }
+        "compute" => {
+            let name = node.word(0)?;
+            let op = match OPERATORS.iter().find(|(n, _)| *n == name) {
+                Some((_, OperatorType::Eq(_))) => OperatorType::Eq(node.number("n")?),
+                Some((_, op)) => *op,
+                None => return node.error(&format!("unknown operator {}", name)),
+            };
+            ComputeBucket {
+                line,
+                message_id,
+                op,
+                op_aux_no: node.number_or_zero("aux")?,
+                stack: node.children.iter().map(parse_pointer).collect::<Result<_, _>>()?,
+            }
+            .into_instruction()
+        }
+        "call" => {
+            let (return_node, arg_nodes) = match node.children.split_last() {
+                Some(split) => split,
+                None => return node.error("missing return information"),
+            };
+            let mut arguments = InstructionList::new();
+            let mut argument_types = Vec::new();
+            for arg in arg_nodes {
+                arg.expect_head(&["arg"])?;
+                arg.expect_children(1)?;
+                argument_types.push(InstrContext { size: arg.number("size")? });
+                arguments.push(parse_pointer(arg.child_at(0)?)?);
+            }
+            return_node.expect_head(&["intermediate", "final"])?;
+            let return_info = if return_node.head == "intermediate" {
+                ReturnType::Intermediate { op_aux_no: return_node.number_or_zero("aux")? }
+            } else {
+                return_node.expect_children(2)?;
+                ReturnType::Final(FinalData {
+                    context: InstrContext { size: return_node.number("size")? },
+                    dest_is_output: return_node.boolean("output")?,
+                    dest_address_type: parse_address(return_node.child_at(0)?)?,
+                    dest: parse_location(return_node.child_at(1)?)?,
+                })
+            };
+            CallBucket {
+                line,
+                message_id,
+                symbol: node.text_atom(0)?,
+                argument_types,
+                arguments,
+                arena_size: node.number("arena")?,
+                return_info,
+            }
+            .into_instruction()
+        }
+        "branch" => {
+            node.expect_children(3)?;
+            BranchBucket {
+                line,
+                message_id,
+                cond: parse_pointer(node.child_at(0)?)?,
+                if_branch: parse_block(node.child_at(1)?, "then")?,
+                else_branch: parse_block(node.child_at(2)?, "else")?,
+            }
+            .into_instruction()
+        }
+        "return" => {
+            node.expect_children(1)?;
+            ReturnBucket { line, message_id, with_size: node.number("size")?, value: parse_pointer(node.child_at(0)?)? }
+                .into_instruction()
+        }
+        "assert" => {
+            node.expect_children(2)?;
+            AssertBucket {
+                line,
+                message_id,
+                evaluate: parse_pointer(node.child_at(0)?)?,
+                assert_id: node.number("id")?,
+                signals: parse_block(node.child_at(1)?, "signals")?,
+            }
+            .into_instruction()
+        }
+        "log" => {
+            let mut argsprint = Vec::new();
+            for arg in &node.children {
+                arg.expect_head(&["exp", "str"])?;
+                if arg.head == "exp" {
+                    arg.expect_children(1)?;
+                    argsprint.push(LogBucketArg::LogExp(parse_pointer(arg.child_at(0)?)?));
+                } else {
+                    argsprint.push(LogBucketArg::LogStr(arg.number_atom(0)?));
+                }
+            }
+            LogBucket { line, message_id, argsprint }.into_instruction()
+        }
+        "loop" => {
+            node.expect_children(2)?;
+            LoopBucket {
+                line,
+                message_id,
+                continue_condition: parse_pointer(node.child_at(0)?)?,
+                body: parse_block(node.child_at(1)?, "body")?,
+            }
+            .into_instruction()
+        }
+        "create_cmp" => {
+            node.expect_children(3)?;
+            let dimensions_node = node.child_at(1)?;
+            dimensions_node.expect_head(&["dimensions"])?;
+            let mut dimensions = Vec::new();
+            for position in 0..dimensions_node.atoms.len() {
+                dimensions.push(dimensions_node.number_atom(position)?);
+            }
+            let defined_node = node.child_at(2)?;
+            defined_node.expect_head(&["defined"])?;
+            let mut defined_positions = Vec::new();
+            for position in &defined_node.children {
+                position.expect_head(&["position"])?;
+                let parallel = parse_bool(position.word(1)?).map_or_else(|| position.error("invalid boolean"), Ok)?;
+                defined_positions.push((position.number_atom(0)?, parallel));
+            }
+            CreateCmpBucket {
+                line,
+                message_id,
+                template_id: node.number("template_id")?,
+                cmp_unique_id: node.number("cmp_unique_id")?,
+                symbol: node.text_atom(0)?,
+                sub_cmp_id: parse_pointer(node.child_at(0)?)?,
+                name_subcomponent: node.text_attr("name")?.map_or_else(|| node.error("missing attribute name"), Ok)?,
+                defined_positions,
+                is_part_mixed_array_not_uniform_parallel: node.boolean("mixed_not_uniform_parallel")?,
+                uniform_parallel: node.optional_bool("uniform_parallel")?,
+                dimensions,
+                signal_offset: node.number("signal_offset")?,
+                signal_offset_jump: node.number("signal_offset_jump")?,
+                component_offset: node.number("component_offset")?,
+                component_offset_jump: node.number("component_offset_jump")?,
+                number_of_cmp: node.number("number_of_cmp")?,
+                has_inputs: node.boolean("has_inputs")?,
+            }
+            .into_instruction()
+        }
+        _ => return node.error("unknown instruction"),
+    };
+    Ok(instruction)
+}
+
+fn parse_address(node: &Node) -> Result<AddressType, String> {
+    node.expect_head(&["variable", "signal", "subcmp"])?;
+    match node.head.as_str() {
+        "variable" => Ok(AddressType::Variable),
+        "signal" => Ok(AddressType::Signal),
+        _ => {
+            node.expect_children(1)?;
+            let input_information = match node.word_attr("input")? {
+                "none" => InputInformation::NoInput,
+                "last" => InputInformation::Input { status: StatusInput::Last },
+                "no_last" => InputInformation::Input { status: StatusInput::NoLast },
+                "unknown" => InputInformation::Input { status: StatusInput::Unknown },
+                _ => return node.error("invalid value of input"),
+            };
+            Ok(AddressType::SubcmpSignal {
+                cmp_address: parse_pointer(node.child_at(0)?)?,
+                uniform_parallel_value: node.optional_bool("parallel")?,
+                is_output: node.boolean("output")?,
+                input_information,
+            })
+        }
+    }
+}
+
+fn parse_location(node: &Node) -> Result<LocationRule, String> {
+    node.expect_head(&["indexed", "mapped"])?;
+    if node.head == "indexed" {
+        node.expect_children(1)?;
+        Ok(LocationRule::Indexed { location: parse_pointer(node.child_at(0)?)?, template_header: node.text_attr("header")? })
+    } else {
+        Ok(LocationRule::Mapped {
+            signal_code: node.number("code")?,
+            indexes: node.children.iter().map(parse_pointer).collect::<Result<_, _>>()?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SNIPPET: &str = r#"
+; every kind of instruction
+(create_cmp "Mul_0" line=4 name="m" template_id=0 cmp_unique_id=0 signal_offset=3
+  signal_offset_jump=3 component_offset=0 component_offset_jump=1 number_of_cmp=2
+  has_inputs=true mixed_not_uniform_parallel=false uniform_parallel=false
+  (value u32 0) (dimensions 2) (defined (position 0 false) (position 1 true)))
+(store line=3 size=1 output=false (variable) (indexed (value u32 0)) (value bigint 1))
+(loop line=5
+  (compute lesser line=5 aux=1 (load size=1 (variable) (indexed (value u32 0))) (value bigint 2))
+  (body
+    (store size=1 output=false
+      (subcmp output=false parallel=none input=unknown
+        (compute to_address (load size=1 (variable) (indexed (value u32 0)))))
+      (indexed header="Mul_0" (value u32 1))
+      (load size=1 (signal) (mapped code=2 (value u32 0))))
+    (call "sq_0" arena=2 (arg size=1 (value bigint 3))
+      (final size=1 output=false (variable) (indexed (value u32 1))))
+    (store size=1 output=false (variable) (indexed (value u32 0))
+      (compute add aux=1 (load size=1 (variable) (indexed (value u32 0))) (value bigint 1)))))
+(branch line=7 (compute eq n=1 (value bigint 1) (value bigint 1))
+  (then (log line=7 (str 0) (exp (call "sq_0" arena=2 (arg size=1 (value bigint 3)) (intermediate aux=1)))))
+  (else))
+(assert line=8 id=0 (compute not_eq (value bigint 1) (value bigint 0))
+  (signals (load size=1 (signal) (indexed (value u32 0)))))
+(return line=9 size=1 (compute prefix_sub (value bigint 2)))
+"#;
+
+    #[test]
+    fn round_trip() {
+        let code = parse_list(SNIPPET).unwrap_or_else(|e| panic!("{}", e));
+        assert_eq!(code.len(), 6);
+        let written = write_list(&code);
+        let reparsed = parse_list(&written).unwrap_or_else(|e| panic!("{}", e));
+        assert_eq!(written, write_list(&reparsed));
+        if let Instruction::Loop(b) = &*code[2] {
+            assert_eq!(b.body.len(), 3);
+        } else {
+            panic!("expected a loop");
+        }
+    }
+
+    #[test]
+    fn reports_errors() {
+        match parse_list("\n(value u32)") {
+            Err(message) => assert!(message.starts_with("line 2")),
+            Ok(_) => panic!("expected an error"),
+        }
+        assert!(parse_list("(compute frobnicate)").is_err());
+        assert!(parse_list("(store size=1 (variable)").is_err());
+        assert!(parse_instruction_text("(value u32 1) (value u32 2)").is_err());
+    }
+
+    // Parses the text and the text written back from it, which must be the
+    // same when written again
+    fn reparsed(text: &str) -> Instruction {
+        let instruction = parse_instruction_text(text).unwrap_or_else(|e| panic!("{}", e));
+        let written = write_instruction(&instruction);
+        let reparsed = parse_instruction_text(&written).unwrap_or_else(|e| panic!("{}", e));
+        assert_eq!(write_instruction(&reparsed), written);
+        reparsed
+    }
+
+    fn value(instruction: &Instruction) -> usize {
+        match instruction {
+            Instruction::Value(b) => b.value,
+            _ => panic!("expected a value"),
+        }
+    }
+
+    #[test]
+    fn create_cmp_round_trip() {
+        let text = r#"(create_cmp "Sum_3" line=12 msg=2 name="sums" template_id=3 cmp_unique_id=7
+            signal_offset=11 signal_offset_jump=4 component_offset=5 component_offset_jump=2
+            number_of_cmp=6 has_inputs=false mixed_not_uniform_parallel=true uniform_parallel=none
+            (value u32 9) (dimensions 2 3) (defined (position 1 true) (position 4 false)))"#;
+        let b = match reparsed(text) {
+            Instruction::CreateCmp(b) => b,
+            _ => panic!("expected a create_cmp"),
+        };
+        assert_eq!((b.line, b.message_id), (12, 2));
+        assert_eq!((b.symbol.as_str(), b.name_subcomponent.as_str()), ("Sum_3", "sums"));
+        assert_eq!((b.template_id, b.cmp_unique_id), (3, 7));
+        assert_eq!((b.signal_offset, b.signal_offset_jump), (11, 4));
+        assert_eq!((b.component_offset, b.component_offset_jump), (5, 2));
+        assert_eq!(b.number_of_cmp, 6);
+        assert!(!b.has_inputs);
+        assert!(b.is_part_mixed_array_not_uniform_parallel);
+        assert_eq!(b.uniform_parallel, None);
+        assert_eq!(value(&b.sub_cmp_id), 9);
+        assert_eq!(b.dimensions, vec![2, 3]);
+        assert_eq!(b.defined_positions, vec![(1, true), (4, false)]);
+        for (parallel, expected) in [("true", Some(true)), ("false", Some(false))].iter() {
+            let text = text.replace("uniform_parallel=none", &format!("uniform_parallel={}", parallel));
+            match reparsed(&text) {
+                Instruction::CreateCmp(b) => assert_eq!(b.uniform_parallel, *expected),
+                _ => panic!("expected a create_cmp"),
+            }
+        }
+    }
+
+    #[test]
+    fn subcmp_addresses_round_trip() {
+        let inputs = ["none", "last", "no_last", "unknown"];
+        let parallels = [("none", None), ("true", Some(true)), ("false", Some(false))];
+        for (position, input) in inputs.iter().enumerate() {
+            for (parallel, uniform_parallel) in parallels.iter() {
+                for output in [true, false].iter() {
+                    let text = format!(
+                        "(load size=1 (subcmp output={} parallel={} input={} (value u32 {})) (indexed (value u32 1)))",
+                        output, parallel, input, position
+                    );
+                    let address = match reparsed(&text) {
+                        Instruction::Load(b) => b.address_type,
+                        _ => panic!("expected a load"),
+                    };
+                    match address {
+                        AddressType::SubcmpSignal { cmp_address, uniform_parallel_value, is_output, input_information } => {
+                            assert_eq!(value(&cmp_address), position);
+                            assert_eq!(uniform_parallel_value, *uniform_parallel);
+                            assert_eq!(is_output, *output);
+                            let read = match input_information {
+                                InputInformation::NoInput => "none",
+                                InputInformation::Input { status: StatusInput::Last } => "last",
+                                InputInformation::Input { status: StatusInput::NoLast } => "no_last",
+                                InputInformation::Input { status: StatusInput::Unknown } => "unknown",
+                            };
+                            assert_eq!(read, *input);
+                        }
+                        _ => panic!("expected a subcmp address"),
+                    }
+                }
+            }
+        }
+        let text = "(load size=1 (subcmp output=true parallel=none input=first (value u32 0)) (indexed (value u32 1)))";
+        assert!(parse_instruction_text(text).is_err());
+    }
+
+    #[test]
+    fn locations_round_trip() {
+        let text = "(store size=3 output=true (signal) (mapped code=4 (value u32 0) (value u32 3) (value u32 1)) (value bigint 0))";
+        let b = match reparsed(text) {
+            Instruction::Store(b) => b,
+            _ => panic!("expected a store"),
+        };
+        assert!(b.dest_is_output);
+        assert_eq!(b.context.size, 3);
+        match &b.dest {
+            LocationRule::Mapped { signal_code, indexes } => {
+                assert_eq!(*signal_code, 4);
+                assert_eq!(indexes.iter().map(|index| value(index)).collect::<Vec<_>>(), vec![0, 3, 1]);
+            }
+            _ => panic!("expected a mapped location"),
+        }
+        match reparsed("(load size=1 (signal) (mapped code=2))") {
+            Instruction::Load(LoadBucket { src: LocationRule::Mapped { signal_code: 2, indexes }, .. }) => {
+                assert!(indexes.is_empty())
+            }
+            _ => panic!("expected a load of a mapped location without indexes"),
+        }
+        match reparsed(r#"(load size=1 (signal) (indexed header="Mul_0" (value u32 2)))"#) {
+            Instruction::Load(LoadBucket { src: LocationRule::Indexed { location, template_header }, .. }) => {
+                assert_eq!(value(&location), 2);
+                assert_eq!(template_header, Some("Mul_0".to_string()));
+            }
+            _ => panic!("expected a load of an indexed location"),
+        }
+    }
+}
@@ -1,6 +1,8 @@
 use super::ir_interface::*;
 use crate::translating_traits::*;
 use code_producers::c_elements::*;
+use code_producers::llvm_elements::llvm_code_generator::LLVMContext;
+use code_producers::llvm_elements::*;
 use code_producers::wasm_elements::*;
 
 #[derive(Clone)]
@@ -307,3 +309,12 @@ impl WriteC for LoadBucket {
         (prologue, access)
     }
 }
+
+impl WriteLLVMIR for LoadBucket {
+    fn produce_llvm_ir(&self, producer: &LLVMProducer, context: &mut LLVMContext) -> (Vec<String>, String) {
+        // the witness generator runs sequentially, outputs of subcomponents are always set
+        let (instructions, address, _) =
+            produce_llvm_ir_address(&self.address_type, &self.src, producer, context);
+        (instructions, address)
+    }
+}
@@ -1,6 +1,8 @@
 use super::ir_interface::*;
 use crate::translating_traits::*;
 use code_producers::c_elements::*;
+use code_producers::llvm_elements::llvm_code_generator::LLVMContext;
+use code_producers::llvm_elements::*;
 use code_producers::wasm_elements::*;
 
 
@@ -160,3 +162,28 @@ impl WriteC for LogBucket {
         (log_c, "".to_string())
     }
 }
+
+impl WriteLLVMIR for LogBucket {
+    fn produce_llvm_ir(&self, producer: &LLVMProducer, context: &mut LLVMContext) -> (Vec<String>, String) {
+        use llvm_code_generator::*;
+        let mut instructions = vec![];
+        for (i, print) in self.argsprint.iter().enumerate() {
+            if i > 0 {
+                instructions.push(build_call("@circom_log_separator", vec![]));
+            }
+            match print {
+                LogBucketArg::LogExp(exp) => {
+                    let (mut exp_instructions, value) = exp.produce_llvm_ir(producer, context);
+                    instructions.append(&mut exp_instructions);
+                    instructions.push(build_call("@circom_log_element", vec![typed("ptr", &value)]));
+                }
+                LogBucketArg::LogStr(id) => {
+                    instructions
+                        .push(build_call("@circom_log_string", vec![typed("ptr", &string_table_global(*id))]));
+                }
+            }
+        }
+        instructions.push(build_call("@circom_log_end", vec![]));
+        (instructions, "".to_string())
+    }
+}
@@ -1,6 +1,8 @@
 use super::ir_interface::*;
 use crate::translating_traits::*;
 use code_producers::c_elements::*;
+use code_producers::llvm_elements::llvm_code_generator::LLVMContext;
+use code_producers::llvm_elements::*;
 use code_producers::wasm_elements::*;
 
 #[derive(Clone)]
@@ -90,3 +92,26 @@ impl WriteC for LoopBucket {
         (loop_c, "".to_string())
     }
 }
+
+impl WriteLLVMIR for LoopBucket {
+    fn produce_llvm_ir(&self, producer: &LLVMProducer, context: &mut LLVMContext) -> (Vec<String>, String) {
+        use llvm_code_generator::*;
+        let cond_label = context.fresh_label("loop_cond");
+        let body_label = context.fresh_label("loop_body");
+        let end_label = context.fresh_label("loop_end");
+        let mut instructions = vec![branch(&cond_label), label(&cond_label)];
+        let (mut cond_instructions, cond) = self.continue_condition.produce_llvm_ir(producer, context);
+        instructions.append(&mut cond_instructions);
+        let (mut condition_instructions, condition) = is_true(&cond, context);
+        instructions.append(&mut condition_instructions);
+        instructions.push(conditional_branch(&condition, &body_label, &end_label));
+        instructions.push(label(&body_label));
+        for instr in &self.body {
+            let (mut instr_code, _) = instr.produce_llvm_ir(producer, context);
+            instructions.append(&mut instr_code);
+        }
+        instructions.push(branch(&cond_label));
+        instructions.push(label(&end_label));
+        (instructions, "".to_string())
+    }
+}
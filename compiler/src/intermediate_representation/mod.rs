@@ -14,5 +14,8 @@ mod types;
 mod value_bucket;
 
 pub mod ir_interface;
+// the reader is only used to build test inputs for now
+#[allow(dead_code)]
+pub mod ir_text;
 pub mod translate;
 pub use ir_interface::{Instruction, InstructionList, InstructionPointer};
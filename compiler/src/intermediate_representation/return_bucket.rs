@@ -1,6 +1,8 @@
 use super::ir_interface::*;
 use crate::translating_traits::*;
 use code_producers::c_elements::*;
+use code_producers::llvm_elements::llvm_code_generator::LLVMContext;
+use code_producers::llvm_elements::*;
 use code_producers::wasm_elements::*;
 
 #[derive(Clone)]
@@ -112,3 +114,23 @@ impl WriteC for ReturnBucket {
         (instructions, "".to_string())
     }
 }
+
+impl WriteLLVMIR for ReturnBucket {
+    fn produce_llvm_ir(&self, producer: &LLVMProducer, context: &mut LLVMContext) -> (Vec<String>, String) {
+        use llvm_code_generator::*;
+        let (mut instructions, src) = self.value.produce_llvm_ir(producer, context);
+        if self.with_size > 1 {
+            // the caller decides how many elements of the result are kept
+            let mut copy =
+                copy_elements_dynamic(FUNCTION_DESTINATION, &src, FUNCTION_DESTINATION_SIZE, producer, context);
+            instructions.append(&mut copy);
+        } else {
+            instructions.push(copy_elements(FUNCTION_DESTINATION, &src, 1, producer));
+        }
+        instructions.push("ret void".to_string());
+        // the code that follows a return is unreachable but needs a block
+        let dead_label = context.fresh_label("after_return");
+        instructions.push(label(&dead_label));
+        (instructions, "".to_string())
+    }
+}
@@ -1,6 +1,8 @@
 use super::ir_interface::*;
 use crate::translating_traits::*;
 use code_producers::c_elements::*;
+use code_producers::llvm_elements::llvm_code_generator::LLVMContext;
+use code_producers::llvm_elements::*;
 use code_producers::wasm_elements::*;
 
 #[derive(Clone)]
@@ -548,3 +550,25 @@ impl WriteC for StoreBucket {
         (prologue, "".to_string())
     }
 }
+
+impl WriteLLVMIR for StoreBucket {
+    fn produce_llvm_ir(&self, producer: &LLVMProducer, context: &mut LLVMContext) -> (Vec<String>, String) {
+        use llvm_code_generator::*;
+        let (mut instructions, dest, sub_component) =
+            produce_llvm_ir_address(&self.dest_address_type, &self.dest, producer, context);
+        let (mut src_instructions, src) = self.src.produce_llvm_ir(producer, context);
+        instructions.append(&mut src_instructions);
+        instructions.push(copy_elements(&dest, &src, self.context.size, producer));
+        if let Some(sub_component) = sub_component {
+            let mut update = produce_llvm_ir_input_update(
+                &self.dest_address_type,
+                &self.dest,
+                &sub_component,
+                self.context.size,
+                context,
+            );
+            instructions.append(&mut update);
+        }
+        (instructions, "".to_string())
+    }
+}
@@ -1,6 +1,7 @@
 use super::ir_interface::*;
 use crate::hir::very_concrete_program::*;
 use crate::intermediate_representation::log_bucket::LogBucketArg;
+use code_producers::components::{AssertMessage, AssertMessageList};
 use constant_tracking::ConstantTracker;
 use num_bigint_dig::BigInt;
 use program_structure::ast::*;
@@ -102,6 +103,8 @@ struct State {
     code: InstructionList,
     // string_table
     string_table: HashMap<String, usize>,
+    // source information of the asserts and constraint checks
+    assert_messages: AssertMessageList,
 }
 
 impl State {
@@ -127,6 +130,7 @@ impl State {
             max_stack_depth: 0,
             code: vec![],
             string_table : HashMap::new(),
+            assert_messages: AssertMessageList::new(),
         }
     }
     fn reserve(fresh: &mut usize, size: usize) -> usize {
@@ -588,6 +592,7 @@ fn translate_constraint_equality(stmt: Statement, state: &mut State, context: &C
             ProcessedSymbol::new(def, state, context).length
         } else {1};
         
+        let (lhe_copy, rhe_copy) = (lhe.clone(), rhe.clone());
         let lhe_pointer = translate_expression(lhe, state, context);
         let rhe_pointer = translate_expression(rhe, state, context);
         let stack = vec![lhe_pointer, rhe_pointer];
@@ -599,9 +604,15 @@ fn translate_constraint_equality(stmt: Statement, state: &mut State, context: &C
             stack,
         }
         .allocate();
-        let assert_instruction =
-            AssertBucket { line: starts_at, message_id: state.message_id, evaluate: equality }
-                .allocate();
+        let (assert_id, signals) = register_assert(&meta, &meta, &[&lhe_copy, &rhe_copy], state, context);
+        let assert_instruction = AssertBucket {
+            line: starts_at,
+            message_id: state.message_id,
+            evaluate: equality,
+            assert_id,
+            signals,
+        }
+        .allocate();
         state.code.push(assert_instruction);
     } else {
         unimplemented!()
@@ -612,12 +623,88 @@ fn translate_assert(stmt: Statement, state: &mut State, context: &Context) {
     use Statement::Assert;
     if let Assert { meta, arg, .. } = stmt {
         let line = context.files.get_line(meta.start, meta.get_file_id()).unwrap();
+        let (assert_id, signals) = register_assert(&meta, arg.get_meta(), &[&arg], state, context);
         let code = translate_expression(arg, state, context);
-        let assert = AssertBucket { line, message_id: state.message_id, evaluate: code }.allocate();
+        let assert = AssertBucket {
+            line,
+            message_id: state.message_id,
+            evaluate: code,
+            assert_id,
+            signals,
+        }
+        .allocate();
         state.code.push(assert);
     }
 }
 
+// Adds the source information of a check to the assert message table and
+// builds the loads of the scalar signals it involves
+fn register_assert(
+    meta: &Meta,
+    expression_meta: &Meta,
+    checked: &[&Expression],
+    state: &mut State,
+    context: &Context,
+) -> (usize, InstructionList) {
+    let file_id = meta.get_file_id();
+    let mut involved = vec![];
+    for expression in checked {
+        collect_involved_signals(expression, &mut involved);
+    }
+    let mut names = vec![];
+    let mut signals = InstructionList::new();
+    for signal in involved {
+        let name = source_text(signal.get_meta(), context);
+        if names.contains(&name) {
+            continue;
+        }
+        if let Expression::Variable { meta, name: symbol, access } = signal {
+            let def = SymbolDef { meta: meta.clone(), symbol: symbol.clone(), acc: access.clone() };
+            let processed = ProcessedSymbol::new(def, state, context);
+            if processed.length == 1 {
+                names.push(name);
+                signals.push(processed.into_load(state));
+            }
+        }
+    }
+    let message = AssertMessage {
+        file: context.files.get_file_name(file_id).unwrap_or_default(),
+        line: context.files.get_line(meta.start, file_id).unwrap_or(0),
+        column: context.files.get_column(meta.start, file_id).unwrap_or(0),
+        expression: source_text(expression_meta, context),
+        signals: names,
+    };
+    let assert_id = state.assert_messages.len();
+    state.assert_messages.push(message);
+    (assert_id, signals)
+}
+
+fn collect_involved_signals<'a>(expression: &'a Expression, involved: &mut Vec<&'a Expression>) {
+    use Expression::*;
+    match expression {
+        Variable { meta, .. } if meta.get_type_knowledge().get_reduces_to() == TypeReduction::Signal => {
+            involved.push(expression);
+        }
+        InfixOp { lhe, rhe, .. } => {
+            collect_involved_signals(lhe, involved);
+            collect_involved_signals(rhe, involved);
+        }
+        PrefixOp { rhe, .. } => collect_involved_signals(rhe, involved),
+        Call { args, .. } => {
+            for arg in args {
+                collect_involved_signals(arg, involved);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn source_text(meta: &Meta, context: &Context) -> String {
+    let text = context.files.get_source(meta.start..meta.end, meta.get_file_id()).unwrap_or_default();
+    let text = text.trim_end().trim_end_matches(';');
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
 fn translate_log(stmt: Statement, state: &mut State, context: &Context) {
     use Statement::LogCall;
     if let LogCall { meta, args, .. } = stmt {
@@ -1369,6 +1456,7 @@ pub struct CodeInfo<'a> {
     pub field_tracker: FieldTracker,
     pub component_to_parallel: HashMap<String, ParallelClusters>,
     pub string_table: HashMap<String, usize>,
+    pub assert_messages: AssertMessageList,
     pub signals_to_tags: BTreeMap<String, TagInfo>,
 }
 
@@ -1380,6 +1468,7 @@ pub struct CodeOutput {
     pub code: InstructionList,
     pub constant_tracker: FieldTracker,
     pub string_table: HashMap<String, usize>,
+    pub assert_messages: AssertMessageList,
 }
 
 pub fn translate_code(body: Statement, code_info: CodeInfo) -> CodeOutput {
@@ -1392,6 +1481,7 @@ pub fn translate_code(body: Statement, code_info: CodeInfo) -> CodeOutput {
         code_info.signals_to_tags,
     );
     state.string_table = code_info.string_table;
+    state.assert_messages = code_info.assert_messages;
     initialize_components(&mut state, code_info.components);
     initialize_signals(&mut state, code_info.signals);
     initialize_constants(&mut state, code_info.constants);
@@ -1421,6 +1511,7 @@ pub fn translate_code(body: Statement, code_info: CodeInfo) -> CodeOutput {
         stack_depth: state.max_stack_depth,
         signal_depth: state.signal_stack,
         constant_tracker: state.field_tracker,
-        string_table : state.string_table
+        string_table : state.string_table,
+        assert_messages: state.assert_messages,
     }
 }
@@ -1,6 +1,8 @@
 use super::ir_interface::*;
 use crate::translating_traits::*;
 use code_producers::c_elements::*;
+use code_producers::llvm_elements::llvm_code_generator::LLVMContext;
+use code_producers::llvm_elements::*;
 use code_producers::wasm_elements::*;
 
 #[derive(Clone)]
@@ -85,3 +87,20 @@ impl WriteC for ValueBucket {
         }
     }
 }
+
+impl WriteLLVMIR for ValueBucket {
+    fn produce_llvm_ir(&self, producer: &LLVMProducer, _context: &mut LLVMContext) -> (Vec<String>, String) {
+        use llvm_code_generator::*;
+        match self.parse_as {
+            ValueType::U32 => (vec![], self.value.to_string()),
+            ValueType::BigInt => {
+                let constants = fr_array(producer.get_field_constant_list().len());
+                let access = format!(
+                    "getelementptr inbounds ({}, ptr {}, i32 0, i32 {})",
+                    constants, G_CONSTANTS, self.value
+                );
+                (vec![], access)
+            }
+        }
+    }
+}
@@ -108,7 +108,9 @@ pub fn build_log(bucket: &mut LogBucket, fresh: usize) -> usize {
 }
 
 pub fn build_assert(bucket: &mut AssertBucket, fresh: usize) -> usize {
-    build_instruction(&mut bucket.evaluate, fresh)
+    let in_eval = build_instruction(&mut bucket.evaluate, fresh);
+    let in_signals = build_list(&mut bucket.signals, fresh);
+    std::cmp::max(in_eval, in_signals)
 }
 
 pub fn build_store(bucket: &mut StoreBucket, fresh: usize) -> usize {
@@ -0,0 +1,55 @@
+use crate::intermediate_representation::ir_interface::OperatorType;
+use circom_algebra::modular_arithmetic;
+use num_bigint_dig::BigInt;
+use num_traits::{ToPrimitive, Zero};
+
+// Shifts by larger amounts are left for the witness generator
+const MAX_FOLDED_SHIFT: usize = 256;
+
+// Evaluates op over constant field elements. Returns None whenever the operation
+// can not be safely evaluated at compile time, the instruction is kept in that case.
+pub fn fold_operation(op: OperatorType, operands: &[BigInt], field: &BigInt) -> Option<BigInt> {
+    use modular_arithmetic::*;
+    use OperatorType::*;
+    let operands: Vec<BigInt> = operands.iter().map(|v| ((v % field) + field) % field).collect();
+    match (op, operands.as_slice()) {
+        (PrefixSub, [a]) => Some(prefix_sub(a, field)),
+        (BoolNot, [a]) => Some(not(a, field)),
+        (Mul, [a, b]) => Some(mul(a, b, field)),
+        (Add, [a, b]) => Some(add(a, b, field)),
+        (Sub, [a, b]) => Some(sub(a, b, field)),
+        (Pow, [a, b]) => Some(pow(a, b, field)),
+        (Div, [a, b]) if !b.is_zero() => div(a, b, field).ok(),
+        (IntDiv, [a, b]) if !b.is_zero() => idiv(a, b, field).ok(),
+        (Mod, [a, b]) if !b.is_zero() => mod_op(a, b, field).ok(),
+        (ShiftL, [a, b]) if folded_shift(b, field) => shift_l(a, b, field).ok(),
+        (ShiftR, [a, b]) if folded_shift(b, field) => shift_r(a, b, field).ok(),
+        (LesserEq, [a, b]) => Some(lesser_eq(a, b, field)),
+        (GreaterEq, [a, b]) => Some(greater_eq(a, b, field)),
+        (Lesser, [a, b]) => Some(lesser(a, b, field)),
+        (Greater, [a, b]) => Some(greater(a, b, field)),
+        (Eq(1), [a, b]) => Some(eq(a, b, field)),
+        (NotEq, [a, b]) => Some(not_eq(a, b, field)),
+        (BoolOr, [a, b]) => Some(bool_or(a, b, field)),
+        (BoolAnd, [a, b]) => Some(bool_and(a, b, field)),
+        (BitOr, [a, b]) => Some(bit_or(a, b, field)),
+        (BitAnd, [a, b]) => Some(bit_and(a, b, field)),
+        (BitXor, [a, b]) => Some(bit_xor(a, b, field)),
+        // Complement depends on the bit length used by the generated code
+        _ => None,
+    }
+}
+
+pub fn is_true(value: &BigInt, field: &BigInt) -> bool {
+    modular_arithmetic::as_bool(value, field)
+}
+
+// Field elements that are used as addresses by the generated code
+pub fn as_address(value: &BigInt) -> Option<usize> {
+    value.to_i32().filter(|v| *v >= 0).map(|v| v as usize)
+}
+
+fn folded_shift(amount: &BigInt, field: &BigInt) -> bool {
+    let bound = BigInt::from(MAX_FOLDED_SHIFT);
+    *amount <= bound || (field - amount) <= bound
+}
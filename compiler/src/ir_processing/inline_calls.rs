@@ -0,0 +1,205 @@
+use crate::hir::very_concrete_program::Param;
+use crate::intermediate_representation::ir_interface::*;
+use std::collections::HashMap;
+
+// Functions whose returned expression has more instructions are always called
+const MAX_INLINED_SIZE: usize = 8;
+
+// A function whose body is a single return of an expression over its scalar parameters.
+// Parameter i is stored in the variable i of the function.
+#[derive(Clone)]
+pub struct InlinableFunction {
+    number_of_params: usize,
+    expression: InstructionPointer,
+}
+
+pub type InlinableFunctions = HashMap<String, InlinableFunction>;
+
+pub fn inlinable_function(params: &[Param], body: &InstructionList) -> Option<InlinableFunction> {
+    if params.iter().any(|p| !p.length.is_empty()) || body.len() != 1 {
+        return None;
+    }
+    if let Instruction::Return(bucket) = &*body[0] {
+        let size = expression_size(&bucket.value, params.len())?;
+        if bucket.with_size == 1 && size <= MAX_INLINED_SIZE {
+            return Some(InlinableFunction {
+                number_of_params: params.len(),
+                expression: bucket.value.clone(),
+            });
+        }
+    }
+    None
+}
+
+fn expression_size(instruction: &Instruction, number_of_params: usize) -> Option<usize> {
+    use Instruction::*;
+    match instruction {
+        Value(_) => Some(1),
+        Load(_) => param_loaded(instruction).filter(|p| *p < number_of_params).map(|_| 1),
+        Compute(b) if !b.op.is_address_op() => {
+            let mut size = 1;
+            for operand in &b.stack {
+                size += expression_size(operand, number_of_params)?;
+            }
+            Some(size)
+        }
+        _ => None,
+    }
+}
+
+fn param_loaded(instruction: &Instruction) -> Option<usize> {
+    if let Instruction::Load(b) = instruction {
+        if let (AddressType::Variable, LocationRule::Indexed { location, .. }) = (&b.address_type, &b.src) {
+            if let Instruction::Value(v) = &**location {
+                if v.parse_as == ValueType::U32 && b.context.size == 1 {
+                    return Some(v.value);
+                }
+            }
+        }
+    }
+    None
+}
+
+fn count_uses(instruction: &Instruction, uses: &mut Vec<usize>) {
+    if let Some(param) = param_loaded(instruction) {
+        uses[param] += 1;
+    } else if let Instruction::Compute(b) = instruction {
+        for operand in &b.stack {
+            count_uses(operand, uses);
+        }
+    }
+}
+
+fn is_simple(instruction: &Instruction) -> bool {
+    matches!(instruction, Instruction::Value(_) | Instruction::Load(_))
+}
+
+// The returned expression with the parameters replaced by the arguments of the call.
+// None when an argument that is not simple would be evaluated a different number of times.
+fn instantiate(function: &InlinableFunction, call: &CallBucket) -> Option<InstructionPointer> {
+    if call.arguments.len() != function.number_of_params
+        || call.argument_types.iter().any(|t| t.size != 1)
+    {
+        return None;
+    }
+    let mut uses = vec![0; function.number_of_params];
+    count_uses(&function.expression, &mut uses);
+    for (argument, used) in call.arguments.iter().zip(uses) {
+        if used != 1 && !is_simple(argument) {
+            return None;
+        }
+    }
+    let mut expression = function.expression.clone();
+    substitute(&mut expression, &call.arguments, call.line, call.message_id);
+    Some(expression)
+}
+
+fn substitute(instruction: &mut InstructionPointer, arguments: &InstructionList, line: usize, message_id: usize) {
+    if let Some(param) = param_loaded(instruction) {
+        *instruction = arguments[param].clone();
+        return;
+    }
+    match &mut **instruction {
+        Instruction::Compute(b) => {
+            b.line = line;
+            b.message_id = message_id;
+            for operand in &mut b.stack {
+                substitute(operand, arguments, line, message_id);
+            }
+        }
+        Instruction::Value(b) => {
+            b.line = line;
+            b.message_id = message_id;
+        }
+        _ => unreachable!(),
+    }
+}
+
+fn inline_call(call: &CallBucket, functions: &InlinableFunctions) -> Option<Instruction> {
+    use ReturnType::*;
+    let expression = instantiate(functions.get(&call.symbol)?, call)?;
+    match &call.return_info {
+        Intermediate { .. } => Some(*expression),
+        Final(data) if data.context.size == 1 => {
+            let store = StoreBucket {
+                line: call.line,
+                message_id: call.message_id,
+                context: data.context,
+                dest_is_output: data.dest_is_output,
+                dest_address_type: data.dest_address_type.clone(),
+                dest: data.dest.clone(),
+                src: expression,
+            };
+            Some(store.into_instruction())
+        }
+        Final(_) => None,
+    }
+}
+
+pub fn visit_list(instructions: &mut InstructionList, functions: &InlinableFunctions) {
+    for instruction in instructions {
+        visit_instruction(instruction, functions);
+    }
+}
+
+pub fn visit_instruction(instruction: &mut InstructionPointer, functions: &InlinableFunctions) {
+    use Instruction::*;
+    match &mut **instruction {
+        Value(_) => {}
+        Load(b) => {
+            visit_address_type(&mut b.address_type, functions);
+            visit_location(&mut b.src, functions);
+        }
+        Store(b) => {
+            visit_address_type(&mut b.dest_address_type, functions);
+            visit_location(&mut b.dest, functions);
+            visit_instruction(&mut b.src, functions);
+        }
+        Compute(b) => visit_list(&mut b.stack, functions),
+        Call(b) => {
+            visit_list(&mut b.arguments, functions);
+            if let ReturnType::Final(data) = &mut b.return_info {
+                visit_address_type(&mut data.dest_address_type, functions);
+                visit_location(&mut data.dest, functions);
+            }
+            if let Some(inlined) = inline_call(b, functions) {
+                **instruction = inlined;
+            }
+        }
+        Branch(b) => {
+            visit_instruction(&mut b.cond, functions);
+            visit_list(&mut b.if_branch, functions);
+            visit_list(&mut b.else_branch, functions);
+        }
+        Return(b) => visit_instruction(&mut b.value, functions),
+        Assert(b) => {
+            visit_instruction(&mut b.evaluate, functions);
+            visit_list(&mut b.signals, functions);
+        }
+        Log(b) => {
+            for arg in &mut b.argsprint {
+                if let LogBucketArg::LogExp(exp) = arg {
+                    visit_instruction(exp, functions);
+                }
+            }
+        }
+        Loop(b) => {
+            visit_instruction(&mut b.continue_condition, functions);
+            visit_list(&mut b.body, functions);
+        }
+        CreateCmp(b) => visit_instruction(&mut b.sub_cmp_id, functions),
+    }
+}
+
+fn visit_address_type(xtype: &mut AddressType, functions: &InlinableFunctions) {
+    if let AddressType::SubcmpSignal { cmp_address, .. } = xtype {
+        visit_instruction(cmp_address, functions);
+    }
+}
+
+fn visit_location(location: &mut LocationRule, functions: &InlinableFunctions) {
+    match location {
+        LocationRule::Indexed { location, .. } => visit_instruction(location, functions),
+        LocationRule::Mapped { indexes, .. } => visit_list(indexes, functions),
+    }
+}
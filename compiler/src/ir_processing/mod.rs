@@ -2,9 +2,17 @@ mod build_stack;
 mod reduce_stack;
 mod set_arena_size;
 mod build_inputs_info;
+mod fold_constants;
+mod inline_calls;
+mod propagate_constants;
+use crate::hir::very_concrete_program::Param;
 use crate::intermediate_representation::ir_interface::InstructionList;
+use crate::intermediate_representation::translate::FieldTracker;
+use num_bigint_dig::BigInt;
 use std::collections::{HashMap, HashSet};
 
+pub use inline_calls::{InlinableFunction, InlinableFunctions};
+
 pub fn reduce_intermediate_operations(code: InstructionList) -> InstructionList {
     reduce_stack::reduce_list(code)
 }
@@ -24,3 +32,21 @@ pub fn build_inputs_info(code: &mut InstructionList){
     build_inputs_info::visit_list(code, &mut HashSet::new(), &mut HashSet::new(),false, false);
 }
 
+
+pub fn inlinable_function(params: &[Param], body: &InstructionList) -> Option<InlinableFunction> {
+    inline_calls::inlinable_function(params, body)
+}
+
+pub fn inline_calls(code: &mut InstructionList, functions: &InlinableFunctions) {
+    inline_calls::visit_list(code, functions);
+}
+
+pub fn propagate_constants(
+    code: InstructionList,
+    field_tracker: &mut FieldTracker,
+    field: &BigInt,
+    unroll_loops: bool,
+) -> InstructionList {
+    let mut env = propagate_constants::Environment { field_tracker, field, unroll_loops };
+    propagate_constants::optimize_code(code, &mut env)
+}
@@ -0,0 +1,426 @@
+use super::fold_constants::{as_address, fold_operation, is_true};
+use crate::intermediate_representation::ir_interface::*;
+use crate::intermediate_representation::translate::FieldTracker;
+use num_bigint_dig::BigInt;
+use std::collections::HashMap;
+
+// Loops are only unrolled if they finish within these bounds
+const MAX_UNROLLED_ITERATIONS: usize = 64;
+const MAX_UNROLLED_SIZE: usize = 256;
+
+// Scalar variables whose value is known at this point of the code
+type KnownVariables = HashMap<usize, BigInt>;
+
+pub struct Environment<'a> {
+    pub field_tracker: &'a mut FieldTracker,
+    pub field: &'a BigInt,
+    pub unroll_loops: bool,
+}
+
+pub fn optimize_code(code: InstructionList, env: &mut Environment) -> InstructionList {
+    optimize_list(code, env, &mut KnownVariables::new())
+}
+
+fn optimize_list(list: InstructionList, env: &mut Environment, known: &mut KnownVariables) -> InstructionList {
+    let mut optimized = InstructionList::with_capacity(list.len());
+    for instruction in list {
+        let is_return = matches!(*instruction, Instruction::Return(_));
+        optimize_instruction(*instruction, env, known, &mut optimized);
+        if is_return {
+            // the rest of the block is unreachable
+            break;
+        }
+    }
+    optimized
+}
+
+fn optimize_instruction(
+    instruction: Instruction,
+    env: &mut Environment,
+    known: &mut KnownVariables,
+    optimized: &mut InstructionList,
+) {
+    use Instruction::*;
+    match instruction {
+        Store(b) => optimized.push(optimize_store(b, env, known)),
+        Call(b) => optimized.push(optimize_call(b, env, known)),
+        Branch(b) => optimize_branch(b, env, known, optimized),
+        Loop(b) => optimize_loop(b, env, known, optimized),
+        Return(mut b) => {
+            b.value = optimize_expression(*b.value, env, known);
+            optimized.push(b.allocate());
+        }
+        Assert(mut b) => {
+            b.evaluate = optimize_expression(*b.evaluate, env, known);
+            b.signals = b.signals.into_iter().map(|s| optimize_expression(*s, env, known)).collect();
+            let holds = constant_value(&b.evaluate, env).is_some_and(|v| is_true(&v, env.field));
+            if !holds {
+                optimized.push(b.allocate());
+            }
+        }
+        Log(mut b) => {
+            let mut argsprint = Vec::with_capacity(b.argsprint.len());
+            for arg in b.argsprint {
+                match arg {
+                    LogBucketArg::LogExp(exp) => {
+                        argsprint.push(LogBucketArg::LogExp(optimize_expression(*exp, env, known)))
+                    }
+                    LogBucketArg::LogStr(s) => argsprint.push(LogBucketArg::LogStr(s)),
+                }
+            }
+            b.argsprint = argsprint;
+            optimized.push(b.allocate());
+        }
+        CreateCmp(mut b) => {
+            b.sub_cmp_id = optimize_expression(*b.sub_cmp_id, env, known);
+            optimized.push(b.allocate());
+        }
+        other => optimized.push(optimize_expression(other, env, known)),
+    }
+}
+
+fn optimize_store(mut bucket: StoreBucket, env: &mut Environment, known: &mut KnownVariables) -> InstructionPointer {
+    bucket.src = optimize_expression(*bucket.src, env, known);
+    bucket.dest_address_type = optimize_address_type(bucket.dest_address_type, env, known);
+    bucket.dest = optimize_location(bucket.dest, env, known);
+    if let AddressType::Variable = bucket.dest_address_type {
+        let value = if bucket.context.size == 1 { constant_value(&bucket.src, env) } else { None };
+        assign_variable(&bucket.dest, bucket.context.size, value, known);
+    }
+    bucket.allocate()
+}
+
+fn optimize_call(mut bucket: CallBucket, env: &mut Environment, known: &mut KnownVariables) -> InstructionPointer {
+    bucket.arguments = bucket.arguments.into_iter().map(|a| optimize_expression(*a, env, known)).collect();
+    if let ReturnType::Final(mut data) = bucket.return_info {
+        data.dest_address_type = optimize_address_type(data.dest_address_type, env, known);
+        data.dest = optimize_location(data.dest, env, known);
+        if let AddressType::Variable = data.dest_address_type {
+            assign_variable(&data.dest, data.context.size, None, known);
+        }
+        bucket.return_info = ReturnType::Final(data);
+    }
+    bucket.allocate()
+}
+
+fn optimize_branch(
+    mut bucket: BranchBucket,
+    env: &mut Environment,
+    known: &mut KnownVariables,
+    optimized: &mut InstructionList,
+) {
+    bucket.cond = optimize_expression(*bucket.cond, env, known);
+    if let Some(cond) = constant_value(&bucket.cond, env) {
+        let taken = if is_true(&cond, env.field) { bucket.if_branch } else { bucket.else_branch };
+        optimized.append(&mut optimize_list(taken, env, known));
+        return;
+    }
+    let mut known_else = known.clone();
+    bucket.if_branch = optimize_list(bucket.if_branch, env, known);
+    bucket.else_branch = optimize_list(bucket.else_branch, env, &mut known_else);
+    known.retain(|var, value| known_else.get(var) == Some(value));
+    optimized.push(bucket.allocate());
+}
+
+fn optimize_loop(
+    mut bucket: LoopBucket,
+    env: &mut Environment,
+    known: &mut KnownVariables,
+    optimized: &mut InstructionList,
+) {
+    if env.unroll_loops && !contains_return(&bucket.body) {
+        let mut known_unrolled = known.clone();
+        if let Some(mut unrolled) = unroll_loop(&bucket, env, &mut known_unrolled) {
+            *known = known_unrolled;
+            optimized.append(&mut unrolled);
+            return;
+        }
+    }
+    let entry_cond = optimize_expression(*bucket.continue_condition.clone(), env, known);
+    if constant_value(&entry_cond, env).is_some_and(|v| !is_true(&v, env.field)) {
+        return;
+    }
+    invalidate_assigned_variables(&bucket.body, known);
+    bucket.continue_condition = optimize_expression(*bucket.continue_condition, env, known);
+    bucket.body = optimize_list(bucket.body, env, &mut known.clone());
+    optimized.push(bucket.allocate());
+}
+
+// Returns the instructions executed by the loop if its condition can be decided in every iteration
+fn unroll_loop(bucket: &LoopBucket, env: &mut Environment, known: &mut KnownVariables) -> Option<InstructionList> {
+    let mut unrolled = InstructionList::new();
+    for _ in 0..=MAX_UNROLLED_ITERATIONS {
+        let cond = optimize_expression(*bucket.continue_condition.clone(), env, known);
+        if !is_true(&constant_value(&cond, env)?, env.field) {
+            return Some(unrolled);
+        }
+        unrolled.append(&mut optimize_list(bucket.body.clone(), env, known));
+        if code_size(&unrolled) > MAX_UNROLLED_SIZE {
+            return None;
+        }
+    }
+    None
+}
+
+fn optimize_expression(
+    instruction: Instruction,
+    env: &mut Environment,
+    known: &mut KnownVariables,
+) -> InstructionPointer {
+    use Instruction::*;
+    match instruction {
+        Load(mut b) => {
+            b.address_type = optimize_address_type(b.address_type, env, known);
+            b.src = optimize_location(b.src, env, known);
+            if let (AddressType::Variable, 1) = (&b.address_type, b.context.size) {
+                let value = constant_location(&b.src).and_then(|var| known.get(&var)).cloned();
+                if let Some(value) = value {
+                    return field_value(&value, b.line, b.message_id, env);
+                }
+            }
+            b.allocate()
+        }
+        Compute(mut b) => {
+            b.stack = b.stack.into_iter().map(|o| optimize_expression(*o, env, known)).collect();
+            fold_compute(b, env)
+        }
+        Call(b) => optimize_call(b, env, known),
+        other => other.allocate(),
+    }
+}
+
+fn fold_compute(bucket: ComputeBucket, env: &mut Environment) -> InstructionPointer {
+    use OperatorType::*;
+    let folded = match bucket.op {
+        ToAddress => constant_value(&bucket.stack[0], env).and_then(|v| as_address(&v)),
+        AddAddress | MulAddress => {
+            match (address_value(&bucket.stack[0]), address_value(&bucket.stack[1])) {
+                (Some(a), Some(b)) if bucket.op == AddAddress => Some(a + b),
+                (Some(a), Some(b)) => Some(a * b),
+                _ => None,
+            }
+        }
+        op => {
+            let operands: Option<Vec<BigInt>> = bucket.stack.iter().map(|o| constant_value(o, env)).collect();
+            if let Some(value) = operands.and_then(|ops| fold_operation(op, &ops, env.field)) {
+                return field_value(&value, bucket.line, bucket.message_id, env);
+            }
+            None
+        }
+    };
+    match folded {
+        Some(value) => ValueBucket {
+            line: bucket.line,
+            message_id: bucket.message_id,
+            parse_as: ValueType::U32,
+            op_aux_no: 0,
+            value,
+        }
+        .allocate(),
+        None => bucket.allocate(),
+    }
+}
+
+fn optimize_address_type(xtype: AddressType, env: &mut Environment, known: &mut KnownVariables) -> AddressType {
+    match xtype {
+        AddressType::SubcmpSignal { cmp_address, uniform_parallel_value, is_output, input_information } => {
+            AddressType::SubcmpSignal {
+                cmp_address: optimize_expression(*cmp_address, env, known),
+                uniform_parallel_value,
+                is_output,
+                input_information,
+            }
+        }
+        other => other,
+    }
+}
+
+fn optimize_location(location: LocationRule, env: &mut Environment, known: &mut KnownVariables) -> LocationRule {
+    match location {
+        LocationRule::Indexed { location, template_header } => {
+            LocationRule::Indexed { location: optimize_expression(*location, env, known), template_header }
+        }
+        LocationRule::Mapped { signal_code, indexes } => LocationRule::Mapped {
+            signal_code,
+            indexes: indexes.into_iter().map(|i| optimize_expression(*i, env, known)).collect(),
+        },
+    }
+}
+
+fn assign_variable(dest: &LocationRule, size: usize, value: Option<BigInt>, known: &mut KnownVariables) {
+    match constant_location(dest) {
+        Some(var) => {
+            for v in var..var + size {
+                known.remove(&v);
+            }
+            if let Some(value) = value {
+                known.insert(var, value);
+            }
+        }
+        None => known.clear(),
+    }
+}
+
+fn invalidate_assigned_variables(code: &InstructionList, known: &mut KnownVariables) {
+    use Instruction::*;
+    for instruction in code {
+        match &**instruction {
+            Store(b) => {
+                if let AddressType::Variable = b.dest_address_type {
+                    assign_variable(&b.dest, b.context.size, None, known);
+                }
+            }
+            Call(b) => {
+                if let ReturnType::Final(data) = &b.return_info {
+                    if let AddressType::Variable = data.dest_address_type {
+                        assign_variable(&data.dest, data.context.size, None, known);
+                    }
+                }
+            }
+            Branch(b) => {
+                invalidate_assigned_variables(&b.if_branch, known);
+                invalidate_assigned_variables(&b.else_branch, known);
+            }
+            Loop(b) => invalidate_assigned_variables(&b.body, known),
+            _ => {}
+        }
+    }
+}
+
+fn contains_return(code: &InstructionList) -> bool {
+    use Instruction::*;
+    code.iter().any(|instruction| match &**instruction {
+        Return(_) => true,
+        Branch(b) => contains_return(&b.if_branch) || contains_return(&b.else_branch),
+        Loop(b) => contains_return(&b.body),
+        _ => false,
+    })
+}
+
+fn code_size(code: &InstructionList) -> usize {
+    use Instruction::*;
+    code.iter()
+        .map(|instruction| match &**instruction {
+            Branch(b) => 1 + code_size(&b.if_branch) + code_size(&b.else_branch),
+            Loop(b) => 1 + code_size(&b.body),
+            _ => 1,
+        })
+        .sum()
+}
+
+fn constant_location(location: &LocationRule) -> Option<usize> {
+    if let LocationRule::Indexed { location, .. } = location {
+        address_value(location)
+    } else {
+        None
+    }
+}
+
+fn address_value(instruction: &Instruction) -> Option<usize> {
+    match instruction {
+        Instruction::Value(v) if v.parse_as == ValueType::U32 => Some(v.value),
+        _ => None,
+    }
+}
+
+fn constant_value(instruction: &Instruction, env: &Environment) -> Option<BigInt> {
+    match instruction {
+        Instruction::Value(v) if v.parse_as == ValueType::BigInt => {
+            let constant = env.field_tracker.get_constant(v.value)?;
+            BigInt::parse_bytes(constant.as_bytes(), 10)
+        }
+        _ => None,
+    }
+}
+
+fn field_value(value: &BigInt, line: usize, message_id: usize, env: &mut Environment) -> InstructionPointer {
+    let value = env.field_tracker.insert(value.to_str_radix(10));
+    ValueBucket { line, message_id, parse_as: ValueType::BigInt, op_aux_no: 0, value }.allocate()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intermediate_representation::ir_text::{parse_list, write_list};
+
+    #[test]
+    fn removes_decided_branches() {
+        let mut field_tracker = FieldTracker::new();
+        for constant in ["0", "1", "5"] {
+            field_tracker.insert(constant.to_string());
+        }
+        let code = parse_list(
+            "(store size=1 output=false (variable) (indexed (value u32 0)) (value bigint 2))
+             (branch (compute lesser (load size=1 (variable) (indexed (value u32 0))) (value bigint 1))
+               (then (store size=1 output=false (variable) (indexed (value u32 1)) (value bigint 0)))
+               (else (store size=1 output=false (variable) (indexed (value u32 1)) (value bigint 1))))",
+        )
+        .unwrap_or_else(|e| panic!("{}", e));
+        let field = BigInt::from(101);
+        let mut env = Environment { field_tracker: &mut field_tracker, field: &field, unroll_loops: false };
+        let optimized = optimize_code(code, &mut env);
+        assert_eq!(
+            write_list(&optimized),
+            "(store size=1 output=false (variable) (indexed (value u32 0)) (value bigint 2))\n\
+             (store size=1 output=false (variable) (indexed (value u32 1)) (value bigint 1))\n"
+        );
+    }
+
+    fn optimized(code: &str, unroll_loops: bool) -> String {
+        let mut field_tracker = FieldTracker::new();
+        for constant in ["0", "1", "2"] {
+            field_tracker.insert(constant.to_string());
+        }
+        let code = parse_list(code).unwrap_or_else(|e| panic!("{}", e));
+        let field = BigInt::from(101);
+        let mut env = Environment { field_tracker: &mut field_tracker, field: &field, unroll_loops };
+        write_list(&optimize_code(code, &mut env))
+    }
+
+    // i = 0; while (i < 2) { out[i] <-- i; i += 1; } out[2] <-- i;
+    const LOOP: &str = "(store size=1 output=false (variable) (indexed (value u32 0)) (value bigint 0))
+         (loop (compute lesser (load size=1 (variable) (indexed (value u32 0))) (value bigint 2))
+           (body
+             (store size=1 output=false (signal)
+               (indexed (compute to_address (load size=1 (variable) (indexed (value u32 0)))))
+               (load size=1 (variable) (indexed (value u32 0))))
+             (store size=1 output=false (variable) (indexed (value u32 0))
+               (compute add (load size=1 (variable) (indexed (value u32 0))) (value bigint 1)))))
+         (store size=1 output=false (signal) (indexed (value u32 2)) (load size=1 (variable) (indexed (value u32 0))))";
+
+    #[test]
+    fn unrolls_loops_whose_condition_is_known() {
+        assert_eq!(
+            optimized(LOOP, true),
+            "(store size=1 output=false (variable) (indexed (value u32 0)) (value bigint 0))\n\
+             (store size=1 output=false (signal) (indexed (value u32 0)) (value bigint 0))\n\
+             (store size=1 output=false (variable) (indexed (value u32 0)) (value bigint 1))\n\
+             (store size=1 output=false (signal) (indexed (value u32 1)) (value bigint 1))\n\
+             (store size=1 output=false (variable) (indexed (value u32 0)) (value bigint 2))\n\
+             (store size=1 output=false (signal) (indexed (value u32 2)) (value bigint 2))\n"
+        );
+    }
+
+    #[test]
+    fn keeps_loops_without_unrolling() {
+        // the variables assigned in the loop are not known in it or after it
+        assert_eq!(optimized(LOOP, false), write_list(&parse_list(LOOP).unwrap()));
+    }
+
+    #[test]
+    fn removes_asserts_that_hold_and_unreachable_code() {
+        let code = "(assert id=0 (compute eq n=1 (value bigint 1) (value bigint 1)) (signals))
+             (assert id=1 (compute eq n=1 (load size=1 (signal) (indexed (value u32 0))) (value bigint 1))
+               (signals (load size=1 (signal) (indexed (value u32 0)))))
+             (return size=1 (compute add (value bigint 1) (value bigint 1)))
+             (store size=1 output=false (variable) (indexed (value u32 0)) (value bigint 0))";
+        assert_eq!(
+            optimized(code, false),
+            "(assert id=1\n  \
+               (compute eq n=1 (load size=1 (signal) (indexed (value u32 0))) (value bigint 1))\n  \
+               (signals\n    \
+                 (load size=1 (signal) (indexed (value u32 0)))))\n\
+             (return size=1 (value bigint 2))\n"
+        );
+    }
+}
@@ -102,6 +102,7 @@ pub fn reduce_log(mut bucket: LogBucket) -> Instruction {
 
 pub fn reduce_assert(mut bucket: AssertBucket) -> Instruction {
     bucket.evaluate = Allocate::allocate(reduce_instruction(*bucket.evaluate));
+    bucket.signals = reduce_list(bucket.signals);
     IntoInstruction::into_instruction(bucket)
 }
 
@@ -1,4 +1,6 @@
 use code_producers::c_elements::*;
+use code_producers::llvm_elements::llvm_code_generator::LLVMContext;
+use code_producers::llvm_elements::*;
 use code_producers::wasm_elements::*;
 use std::io::Write;
 
@@ -27,3 +29,13 @@ pub trait WriteWasm {
         writer.flush().map_err(|_| {})
     }
 }
+
+pub trait WriteLLVMIR {
+    /*
+        returns (x, y) where:
+            x: llvm instructions produced.
+            y: if the instructions in x compute some value, the operand holding it:
+               a ptr for field elements and an i32 for addresses.
+    */
+    fn produce_llvm_ir(&self, producer: &LLVMProducer, context: &mut LLVMContext) -> (Vec<String>, String);
+}
@@ -17,12 +17,15 @@ use super::environment_utils::{
 use program_structure::constants::UsefulConstants;
 
 use super::execution_data::analysis::Analysis;
-use super::execution_data::{ExecutedProgram, ExecutedTemplate, PreExecutedTemplate, NodePointer};
+use super::execution_data::{add_index_to_symbol, ExecutedProgram, ExecutedTemplate, PreExecutedTemplate, NodePointer};
 use super::{ast::*, ArithmeticError, FileID, ProgramArchive, Report, ReportCode, ReportCollection};
+use circom_algebra::modular_arithmetic;
 use circom_algebra::num_bigint::BigInt;
+use circom_algebra::num_traits::Zero;
 use std::collections::{HashMap, BTreeMap};
 use std::mem;
 use crate::FlagsExecution;
+use dag::ConstraintLocation;
 type AExpr = ArithmeticExpressionGen<String>;
 type AnonymousComponentsInfo = BTreeMap<String, (Meta, Vec<Expression>)>;
 
@@ -95,6 +98,10 @@ enum ExecutionError {
     NonQuadraticConstraint,
     FalseAssert,
     ArraySizeTooBig,
+    WrongArgumentDimensions { template: String, argument: String, expected: Vec<usize>, found: Vec<usize> },
+    NotARelationSignal { signal: String },
+    RelationExponentTooBig,
+    LookupParameterTooBig { template: String, parameter: String },
 }
 
 enum ExecutionWarning {
@@ -111,11 +118,12 @@ pub fn constraint_execution(
     let main_file_id = program_archive.get_file_id_main();
     let mut runtime_information =
         RuntimeInformation::new(*main_file_id, program_archive.id_max, prime);
+    check_u32_vars(program_archive, runtime_information.constants.get_p())?;
     use Expression::Call;
 
     runtime_information.public_inputs = program_archive.get_public_inputs_main_component().clone();
 
-    let folded_value_result = if let Call { id, args, .. } = &program_archive.get_main_expression()
+    let folded_value_result = if let Call { meta, id, args } = &program_archive.get_main_expression()
     {
         let mut arg_values = Vec::new();
 
@@ -132,7 +140,16 @@ pub fn constraint_execution(
             arg_values.push(safe_unwrap_to_arithmetic_slice(f_arg.unwrap(), line!()));
             // improve
         }
-        execute_template_call_complete(
+        check_template_arguments(
+            meta,
+            id,
+            &arg_values,
+            program_archive,
+            &mut runtime_information,
+            flags,
+            &mut path_analyser,
+        )
+        .and_then(|_| execute_template_call_complete(
             id,
             arg_values,
             BTreeMap::new(),
@@ -140,7 +157,7 @@ pub fn constraint_execution(
             &mut runtime_information,
             flags,
             &mut path_analyser,
-        )
+        ))
     } else {
         unreachable!("The main expression should be a call.");
     };
@@ -154,6 +171,29 @@ pub fn constraint_execution(
     }
 }
 
+// The u32 operations are reduced modulo 2^32 in the field, which is only
+// right if the product of two u32 values is smaller than the prime
+fn check_u32_vars(program_archive: &ProgramArchive, prime: &BigInt) -> Result<(), ReportCollection> {
+    let max_u32 = BigInt::from(u32::MAX);
+    if *prime > &max_u32 * &max_u32 {
+        return Result::Ok(());
+    }
+    let mut reports = ReportCollection::new();
+    for (file_id, location) in &program_archive.u32_vars {
+        let mut report = Report::error(
+            "u32 vars need a prime larger than (2^32 - 1)^2, the arithmetic of the chosen prime would overflow".to_string(),
+            ReportCode::InvalidVarType,
+        );
+        report.add_primary(location.clone(), *file_id, "found here".to_string());
+        reports.push(report);
+    }
+    if reports.is_empty() {
+        Result::Ok(())
+    } else {
+        Result::Err(reports)
+    }
+}
+
 pub fn execute_constant_expression(
     expression: &Expression,
     program_archive: &ProgramArchive,
@@ -210,7 +250,7 @@ fn execute_statement(
             debug_assert!(possible_fold.is_none());
             possible_fold
         }
-        Declaration { meta, xtype, name, dimensions, .. } => {
+        Declaration { meta, xtype, name, dimensions, is_kept, .. } => {
             match xtype {
                 VariableType::AnonymousComponent => {
                     execute_anonymous_component_declaration(
@@ -266,6 +306,7 @@ fn execute_statement(
                             &usable_dimensions,
                             tag_list,
                             *signal_type,
+                            *is_kept,
                             &mut runtime.environment,
                             actual_node,
                         ),
@@ -316,8 +357,7 @@ fn execute_statement(
                             &runtime.call_trace,
                         )?;
 
-                        let full_symbol =
-                            format!("{}{}", constrained.left, create_index_appendix(&access_left),);
+                        let full_symbol = create_index_appendix(&constrained.left, &access_left);
                         if let AssignOp::AssignConstraintSignal = op {
                             if value_right.is_nonquadratic() {
                                 let err = Result::Err(ExecutionError::NonQuadraticConstraint);
@@ -333,7 +373,7 @@ fn execute_statement(
                                 let expr = AExpr::sub(&symbol, &value_right, &p);
                                 let ctr = AExpr::transform_expression_to_constraint_form(expr, &p)
                                     .unwrap();
-                                node.add_constraint(ctr);
+                                node.add_constraint(ctr, constraint_location(meta, program_archive));
                             }
                         } else if let AssignOp::AssignSignal = op {
                             // needs fix, check case arrays
@@ -376,6 +416,17 @@ fn execute_statement(
             }
             Option::None
         }
+        ConstraintEquality { lhe, rhe, .. } if matches!(actual_node, Some(node) if node.is_custom_gate) => {
+            let signals = actual_node.as_ref().unwrap().ordered_signals.clone();
+            let left = execute_relation(lhe, &signals, program_archive, runtime, flags, path_analyser)?;
+            let right = execute_relation(rhe, &signals, program_archive, runtime, flags, path_analyser)?;
+            let field = runtime.constants.get_p().clone();
+            let relation = relation_add(left, relation_scale(right, &BigInt::from(-1), &field), &field);
+            let terms = relation.into_iter().filter(|(_, coefficient)| !coefficient.is_zero());
+            let relation = terms.map(|(signals, coefficient)| (coefficient, signals)).collect();
+            actual_node.as_mut().unwrap().relations.push(relation);
+            Option::None
+        }
         ConstraintEquality { meta, lhe, rhe, .. } => {
             debug_assert!(actual_node.is_some());
             let f_left = execute_expression(lhe, program_archive, runtime, flags, path_analyser)?;
@@ -421,7 +472,7 @@ fn execute_statement(
                 )
                 .unwrap();
                 if let Option::Some(node) = actual_node {
-                    node.add_constraint(constraint_expression);
+                    node.add_constraint(constraint_expression, constraint_location(meta, program_archive));
                 }
             }
             Option::None
@@ -733,8 +784,8 @@ fn execute_expression(
                 FoldedValue { arithmetic_slice, ..FoldedValue::default() }
             }
         }
-        Call { id, args, .. } => {
-            let (value, can_simplify) = execute_call(id, args, program_archive, runtime, flags, path_analyser)?;
+        Call { meta, id, args, .. } => {
+            let (value, can_simplify) = execute_call(meta, id, args, program_archive, runtime, flags, path_analyser)?;
             can_be_simplified = can_simplify;
             value
         }
@@ -765,6 +816,7 @@ fn execute_expression(
 //************************************************* Statement execution support *************************************************
 
 fn execute_call(
+    meta: &Meta,
     id: &String,
     args: &Vec<Expression>,
     program_archive: &ProgramArchive,
@@ -799,11 +851,78 @@ fn execute_call(
         Ok(folded_result)
     } else {
         // in this case we preexecute and check if it needs tags
+        check_template_arguments(meta, id, &arg_values, program_archive, runtime, flags, path_analyser)?;
         let folded_result = preexecute_template_call(id, &arg_values, program_archive, runtime)?;
         Ok((folded_result, true))
     }
 }
 
+// Checks that the arrays given to the parameters annotated with dimensions
+// have them. The dimensions can use the values of the previous parameters.
+fn check_template_arguments(
+    meta: &Meta,
+    id: &str,
+    arg_values: &[AExpressionSlice],
+    program_archive: &ProgramArchive,
+    runtime: &mut RuntimeInformation,
+    flags: FlagsExecution,
+    path_analyser: &mut PathAnalyser,
+) -> Result<(), ()> {
+    let template_data = program_archive.get_template_data(id);
+    if template_data.get_dimensions_of_params().iter().all(|dimensions| dimensions.is_empty()) {
+        return Result::Ok(());
+    }
+    let new_environment = prepare_environment_for_call(id, arg_values, program_archive);
+    let previous_environment = std::mem::replace(&mut runtime.environment, new_environment);
+    let previous_id = std::mem::replace(&mut runtime.current_file, template_data.get_file_id());
+    let mut expected_dimensions = Vec::new();
+    let mut result = Result::Ok(());
+    for dimensions in template_data.get_dimensions_of_params() {
+        result = execute_parameter_dimensions(meta, dimensions, program_archive, runtime, flags, path_analyser)
+            .map(|usable_dimensions| expected_dimensions.push(usable_dimensions));
+        if result.is_err() {
+            break;
+        }
+    }
+    runtime.environment = previous_environment;
+    runtime.current_file = previous_id;
+    result?;
+    let arguments = template_data.get_name_of_params().iter().zip(arg_values).zip(expected_dimensions);
+    for ((argument, value), expected) in arguments {
+        if !expected.is_empty() && value.route() != expected.as_slice() {
+            let err = Result::Err(ExecutionError::WrongArgumentDimensions {
+                template: id.to_string(),
+                argument: argument.clone(),
+                expected,
+                found: value.route().to_vec(),
+            });
+            treat_result_with_execution_error(err, meta, &mut runtime.runtime_errors, &runtime.call_trace)?;
+        }
+    }
+    Result::Ok(())
+}
+
+fn execute_parameter_dimensions(
+    meta: &Meta,
+    dimensions: &[Expression],
+    program_archive: &ProgramArchive,
+    runtime: &mut RuntimeInformation,
+    flags: FlagsExecution,
+    path_analyser: &mut PathAnalyser,
+) -> Result<Vec<SliceCapacity>, ()> {
+    let mut arithmetic_values = Vec::new();
+    for dimension in dimensions {
+        let f_dimension = execute_expression(dimension, program_archive, runtime, flags, path_analyser)?;
+        arithmetic_values.push(safe_unwrap_to_single_arithmetic_expression(f_dimension, line!()));
+    }
+    if let Option::Some(usable_dimensions) = cast_indexing(&arithmetic_values) {
+        Result::Ok(usable_dimensions)
+    } else {
+        let err = Result::Err(ExecutionError::ArraySizeTooBig);
+        treat_result_with_execution_error(err, meta, &mut runtime.runtime_errors, &runtime.call_trace)
+    }
+}
+
 fn execute_template_call_complete(
     id: &String,
     arg_values: Vec<AExpressionSlice>,
@@ -869,6 +988,7 @@ fn execute_signal_declaration(
     dimensions: &[SliceCapacity],
     list_tags: &Vec<String>,
     signal_type: SignalType,
+    is_kept: bool,
     environment: &mut ExecutionEnvironment,
     actual_node: &mut Option<ExecutedTemplate>,
 ) {
@@ -879,6 +999,9 @@ fn execute_signal_declaration(
     }
     if let Option::Some(node) = actual_node {
         node.add_ordered_signal(signal_name, dimensions);
+        if is_kept {
+            node.add_kept_signal(signal_name, dimensions);
+        }
         match signal_type {
             Input => {
                 if let Some(tags_input) = node.tag_instances().get(signal_name) {
@@ -1568,35 +1691,147 @@ fn execute_delayed_declarations(
     Result::Ok(())
 }
 
+// Line of the statement that generates a constraint, 0 if it is unknown
+fn constraint_location(meta: &Meta, program_archive: &ProgramArchive) -> ConstraintLocation {
+    let line = meta.file_id.and_then(|file_id| program_archive.file_library.get_line(meta.start, file_id));
+    ConstraintLocation { file: meta.file_id, line: line.unwrap_or(0) }
+}
+
 //************************************************* Expression execution support *************************************************
 
 fn create_component_symbol(symbol: &str, access_information: &AccessingInformation) -> String {
-    let mut appendix = "".to_string();
-    let bf_signal = create_index_appendix(&access_information.before_signal);
-    appendix.push_str(&bf_signal);
-    format!("{}{}", symbol, appendix)
+    create_index_appendix(symbol, &access_information.before_signal)
 }
 
 fn create_symbol(symbol: &str, access_information: &AccessingInformation) -> String {
-    let mut appendix = "".to_string();
-    let bf_signal = create_index_appendix(&access_information.before_signal);
-    let af_signal = create_index_appendix(&access_information.after_signal);
-    appendix.push_str(&bf_signal);
+    let mut full_symbol = create_index_appendix(symbol, &access_information.before_signal);
     if let Option::Some(signal_accessed) = &access_information.signal_access {
-        let signal = format!(".{}", signal_accessed);
-        appendix.push_str(&signal);
+        let signal = format!("{}.{}", full_symbol, signal_accessed);
+        full_symbol = create_index_appendix(&signal, &access_information.after_signal);
+    }
+    full_symbol
+}
+
+// Polynomial over the positions of the signals of a custom gate, given by the
+// coefficient of each product of signals
+type Relation = BTreeMap<Vec<usize>, BigInt>;
+
+fn relation_constant(value: BigInt) -> Relation {
+    let mut relation = Relation::new();
+    relation.insert(Vec::new(), value);
+    relation
+}
+
+fn relation_add(mut left: Relation, right: Relation, field: &BigInt) -> Relation {
+    for (signals, coefficient) in right {
+        let sum = match left.get(&signals) {
+            Some(current) => modular_arithmetic::add(current, &coefficient, field),
+            None => coefficient,
+        };
+        left.insert(signals, sum);
+    }
+    left
+}
+
+fn relation_scale(relation: Relation, factor: &BigInt, field: &BigInt) -> Relation {
+    relation.into_iter().map(|(signals, coefficient)| {
+        (signals, modular_arithmetic::mul(&coefficient, factor, field))
+    }).collect()
+}
+
+fn relation_mul(left: &Relation, right: &Relation, field: &BigInt) -> Relation {
+    let mut product = Relation::new();
+    for (left_signals, left_coefficient) in left {
+        for (right_signals, right_coefficient) in right {
+            let mut signals = left_signals.clone();
+            signals.extend_from_slice(right_signals);
+            signals.sort_unstable();
+            let coefficient = modular_arithmetic::mul(left_coefficient, right_coefficient, field);
+            product = relation_add(product, vec![(signals, coefficient)].into_iter().collect(), field);
+        }
+    }
+    product
+}
+
+// Evaluates an expression of the relation of a custom gate, whose syntax is
+// checked by the type analysis: its signals are found among the ordered
+// signals of the gate and its other subexpressions are constants
+fn execute_relation(
+    expr: &Expression,
+    signals: &[String],
+    program_archive: &ProgramArchive,
+    runtime: &mut RuntimeInformation,
+    flags: FlagsExecution,
+    path_analyser: &mut PathAnalyser,
+) -> Result<Relation, ()> {
+    use Expression::*;
+    use ExpressionInfixOpcode::*;
+    let field = runtime.constants.get_p().clone();
+    match expr {
+        InfixOp { meta, lhe, infix_op: Pow, rhe } => {
+            let base = execute_relation(lhe, signals, program_archive, runtime, flags, path_analyser)?;
+            let f_exponent = execute_expression(rhe, program_archive, runtime, flags, path_analyser)?;
+            let exponent = safe_unwrap_to_single_arithmetic_expression(f_exponent, line!());
+            let exponent = match cast_index(&exponent) {
+                Some(exponent) => exponent,
+                None => {
+                    let err = Result::Err(ExecutionError::RelationExponentTooBig);
+                    return treat_result_with_execution_error(err, meta, &mut runtime.runtime_errors, &runtime.call_trace);
+                }
+            };
+            let mut power = relation_constant(BigInt::from(1));
+            for _ in 0..exponent {
+                power = relation_mul(&power, &base, &field);
+            }
+            Result::Ok(power)
+        }
+        InfixOp { lhe, infix_op, rhe, .. } if matches!(infix_op, Add | Sub | Mul) => {
+            let left = execute_relation(lhe, signals, program_archive, runtime, flags, path_analyser)?;
+            let right = execute_relation(rhe, signals, program_archive, runtime, flags, path_analyser)?;
+            Result::Ok(match infix_op {
+                Add => relation_add(left, right, &field),
+                Sub => relation_add(left, relation_scale(right, &BigInt::from(-1), &field), &field),
+                _ => relation_mul(&left, &right, &field),
+            })
+        }
+        PrefixOp { prefix_op: ExpressionPrefixOpcode::Sub, rhe, .. } => {
+            let value = execute_relation(rhe, signals, program_archive, runtime, flags, path_analyser)?;
+            Result::Ok(relation_scale(value, &BigInt::from(-1), &field))
+        }
+        Variable { meta, name, access } if ExecutionEnvironment::has_signal(&runtime.environment, name) => {
+            let mut indexing = Vec::new();
+            for access in access {
+                if let Access::ArrayAccess(index) = access {
+                    let f_index = execute_expression(index, program_archive, runtime, flags, path_analyser)?;
+                    let index = safe_unwrap_to_single_arithmetic_expression(f_index, line!());
+                    indexing.push(cast_index(&index).unwrap_or(usize::MAX));
+                }
+            }
+            let symbol = create_index_appendix(name, &indexing);
+            match signals.iter().position(|signal| *signal == symbol) {
+                Some(position) => Result::Ok(vec![(vec![position], BigInt::from(1))].into_iter().collect()),
+                None => {
+                    let err = Result::Err(ExecutionError::NotARelationSignal { signal: symbol });
+                    treat_result_with_execution_error(err, meta, &mut runtime.runtime_errors, &runtime.call_trace)
+                }
+            }
+        }
+        _ => {
+            let f_value = execute_expression(expr, program_archive, runtime, flags, path_analyser)?;
+            match safe_unwrap_to_single_arithmetic_expression(f_value, line!()) {
+                AExpr::Number { value } => Result::Ok(relation_constant(value)),
+                _ => unreachable!(),
+            }
+        }
     }
-    appendix.push_str(&af_signal);
-    format!("{}{}", symbol, appendix)
 }
 
-fn create_index_appendix(indexing: &[usize]) -> String {
-    let mut appendix = "".to_string();
+fn create_index_appendix(symbol: &str, indexing: &[usize]) -> String {
+    let mut full_symbol = symbol.to_string();
     for index in indexing {
-        let index = format!("[{}]", index);
-        appendix.push_str(&index);
+        full_symbol = add_index_to_symbol(&full_symbol, *index);
     }
-    appendix
+    full_symbol
 }
 
 fn execute_variable(
@@ -1649,7 +1884,13 @@ fn execute_signal(
     //FIXME: check template contained in program
     let template_name = runtime.call_trace.last().unwrap();
     //TODO: need to report at end to not show duplicates.
-    if let Some(lint) = lint_all_paths_to_signal(&program_archive, template_name, symbol, meta) {
+    // the built-in gadgets are not linted, their warnings could not be acted on
+    let lint = if program_archive.gadgets.contains(template_name) {
+        None
+    } else {
+        lint_all_paths_to_signal(&program_archive, template_name, symbol, meta)
+    };
+    if let Some(lint) = lint {
             let err: Result<(), ExecutionWarning> = Err(ExecutionWarning::UnassignedSignalOnExecutionPath(lint));
             treat_result_with_execution_warning(err, meta, &mut runtime.runtime_errors, &runtime.call_trace)?;
     }
@@ -1767,7 +2008,7 @@ fn unfold_signals(current: String, dim: usize, lengths: &[usize], result: &mut V
         result.push(current);
     } else {
         for i in 0..lengths[dim] {
-            unfold_signals(format!("{}[{}]", current, i), dim + 1, lengths, result)
+            unfold_signals(add_index_to_symbol(&current, i), dim + 1, lengths, result)
         }
     }
 }
@@ -2016,7 +2257,14 @@ fn execute_template_call(
             Ok(_) => {}
         }
 
-        let new_node = node_wrap.unwrap();
+        let mut new_node = node_wrap.unwrap();
+        if program_archive.custom_gates && program_archive.gadgets.contains(id) {
+            if let Err(parameter) = new_node.set_lookup() {
+                let err = Result::Err(ExecutionError::LookupParameterTooBig { template: id.to_string(), parameter });
+                let meta = program_archive.get_template_data(id).get_body().get_meta();
+                treat_result_with_execution_error(err, meta, &mut runtime.runtime_errors, &runtime.call_trace)?;
+            }
+        }
         let analysis = std::mem::replace(&mut runtime.analysis, analysis);
         let node_pointer = runtime.exec_program.add_node_to_scheme(new_node, analysis);
         node_pointer
@@ -2580,6 +2828,33 @@ fn treat_result_with_execution_error<C>(
                     "The size of the array is expected to be a usize".to_string(),
                     ReportCode::RuntimeError,
                 ),
+                NotARelationSignal { signal } => Report::error(
+                    format!("The relation accesses {}, which is not a signal of the custom template", signal),
+                    ReportCode::RuntimeError,
+                ),
+                RelationExponentTooBig => Report::error(
+                    "The exponent of the relation is expected to be a usize".to_string(),
+                    ReportCode::RuntimeError,
+                ),
+                LookupParameterTooBig { template, parameter } => Report::error(
+                    format!("The parameter {} of {} must fit in 32 bits to be exported as a lookup", parameter, template),
+                    ReportCode::RuntimeError,
+                ),
+                WrongArgumentDimensions { template, argument, expected, found } => {
+                    let show = |dimensions: Vec<usize>| -> String {
+                        dimensions.iter().map(|dimension| format!("[{}]", dimension)).collect()
+                    };
+                    Report::error(
+                        format!(
+                            "The argument {} of {} must be an array of dimensions {}, but its dimensions are {}",
+                            argument,
+                            template,
+                            show(expected),
+                            show(found)
+                        ),
+                        ReportCode::RuntimeError,
+                    )
+                }
             };
             add_report_to_runtime(report, meta, runtime_errors, call_trace, None);
             Result::Err(())
@@ -2649,3 +2924,114 @@ fn add_report_to_runtime(
     report.add_note(trace);
     runtime_errors.push(report);
 }
+
+#[cfg(test)]
+mod test {
+    use super::{constraint_execution, relation_add, relation_mul, relation_scale, Relation};
+    use crate::FlagsExecution;
+    use circom_algebra::modular_arithmetic;
+    use circom_algebra::num_bigint::BigInt;
+    use constraint_writers::r1cs_writer::GateRelation;
+    use program_structure::error_code::ReportCode;
+    use program_structure::constants::UsefulConstants;
+    use program_structure::program_archive::ProgramArchive;
+    use std::path::PathBuf;
+    use type_analysis::check_types::check_types;
+    const FIELD: &str = "257";
+    const PRIME: &str = "bn128";
+
+    fn relation(terms: Vec<(Vec<usize>, i64)>) -> Relation {
+        terms.into_iter().map(|(signals, coefficient)| (signals, BigInt::from(coefficient))).collect()
+    }
+
+    fn parse(file: &str) -> ProgramArchive {
+        let mut path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        path.push(file);
+        let (mut program_archive, _) = parser::run_parser(
+            path.to_str().unwrap().to_owned(),
+            env!("CARGO_PKG_VERSION"),
+            vec![path],
+        )
+        .ok()
+        .unwrap();
+        check_types(&mut program_archive).ok().unwrap();
+        program_archive
+    }
+
+    // value of the relation for the given values of the signals of the gate
+    fn evaluate(relation: &GateRelation, values: &[u64], field: &BigInt) -> BigInt {
+        let mut value = BigInt::from(0);
+        for (coefficient, signals) in relation {
+            let mut term = coefficient.clone();
+            for signal in signals {
+                term = modular_arithmetic::mul(&term, &BigInt::from(values[*signal]), field);
+            }
+            value = modular_arithmetic::add(&value, &term, field);
+        }
+        value
+    }
+
+    #[test]
+    fn relation_product() {
+        let field = BigInt::parse_bytes(FIELD.as_bytes(), 10).unwrap();
+        // (x0 + 2) * (x0 - x1) = x0^2 - x0*x1 + 2*x0 - 2*x1
+        let left = relation(vec![(vec![0], 1), (vec![], 2)]);
+        let right = relation_add(relation(vec![(vec![0], 1)]), relation_scale(relation(vec![(vec![1], 1)]), &BigInt::from(-1), &field), &field);
+        let product = relation_mul(&left, &right, &field);
+        let expected = relation(vec![(vec![0, 0], 1), (vec![0, 1], 256), (vec![0], 2), (vec![1], 255)]);
+        assert_eq!(product, expected);
+        let zero = relation_mul(&product, &relation(vec![(vec![], 0)]), &field);
+        assert!(zero.values().all(|coefficient| *coefficient == BigInt::from(0)));
+        // the signals of a term are sorted, so that x1*x0 and x0*x1 are the same term
+        let commuted = relation_mul(&relation(vec![(vec![1], 1)]), &relation(vec![(vec![0], 3)]), &field);
+        assert_eq!(commuted, relation(vec![(vec![0, 1], 3)]));
+    }
+
+    #[test]
+    fn custom_gate_relation() {
+        let program_archive = parse("tests/fixtures/custom-gate-relation.circom");
+        let flags = FlagsExecution { verbose: false, inspect: false };
+        let (program, _) = constraint_execution(&program_archive, flags, &PRIME.to_string()).ok().unwrap();
+        let gate = program.model.iter().find(|node| node.is_custom_gate).unwrap();
+        assert_eq!(gate.ordered_signals, vec!["x", "c[0]", "c[1]", "y"]);
+        assert_eq!(gate.relations.len(), 2);
+        let field = UsefulConstants::new(PRIME).get_p().clone();
+        // y = 7 * (x + c[1])^2 and c[0] * c[1] - 3 = -2 * c[0]
+        let satisfied = [1, 1, 1, 28];
+        assert!(gate.relations.iter().all(|relation| evaluate(relation, &satisfied, &field) == BigInt::from(0)));
+        let unsatisfied = [1, 1, 1, 27];
+        assert_ne!(evaluate(&gate.relations[0], &unsatisfied, &field), BigInt::from(0));
+        assert_eq!(evaluate(&gate.relations[1], &unsatisfied, &field), BigInt::from(0));
+        let unsatisfied = [1, 2, 1, 28];
+        assert_ne!(evaluate(&gate.relations[1], &unsatisfied, &field), BigInt::from(0));
+    }
+
+    #[test]
+    fn u32_vars_need_a_large_prime() {
+        let program_archive = parse("tests/fixtures/u32-vars.circom");
+        let flags = FlagsExecution { verbose: false, inspect: false };
+        // (2^32 - 1)^2 overflows the field of 2^31 - 2^27 + 1
+        let errors = match constraint_execution(&program_archive, flags, &"2013265921".to_string()) {
+            Ok(_) => panic!("x * x overflows the field"),
+            Err(errors) => errors,
+        };
+        // one report for each declaration of x and y
+        assert_eq!(errors.len(), 2);
+        assert!(errors.iter().all(|error| *error.get_code() == ReportCode::InvalidVarType));
+        assert!(constraint_execution(&program_archive, flags, &"goldilocks".to_string()).is_ok());
+        assert!(constraint_execution(&program_archive, flags, &PRIME.to_string()).is_ok());
+    }
+
+    #[test]
+    fn custom_gate_relation_arity() {
+        let program_archive = parse("tests/fixtures/custom-gate-relation-arity.circom");
+        let flags = FlagsExecution { verbose: false, inspect: false };
+        let errors = match constraint_execution(&program_archive, flags, &PRIME.to_string()) {
+            Ok(_) => panic!("the relation of Sum(2) accesses in[2]"),
+            Err(errors) => errors,
+        };
+        assert_eq!(errors.len(), 1);
+        assert_eq!(*errors[0].get_code(), ReportCode::RuntimeError);
+        assert_eq!(errors[0].get_message(), "The relation accesses in[2], which is not a signal of the custom template");
+    }
+}
@@ -132,6 +132,12 @@ impl ExecutedProgram {
             temp_instances.push(tmp_instance);
         }
 
+        let mut file_id = 0;
+        while let Some(name) = program.file_library.get_file_name(file_id) {
+            dag.file_names.push(name);
+            file_id += 1;
+        }
+
         temp_instances[dag.main_id()].is_not_parallel_component = true;
         dag.clean_constraints();
         if flags.inspect{
@@ -1,12 +1,15 @@
 use super::type_definitions::*;
 use circom_algebra::algebra::ArithmeticExpression;
 use compiler::hir::very_concrete_program::*;
-use dag::DAG;
+use constraint_writers::r1cs_writer::{GateRelation, LookupTable};
+use dag::{ConstraintLocation, DAG};
 use num_bigint::BigInt;
+use num_traits::ToPrimitive;
 use program_structure::ast::{SignalType, Statement};
 use std::collections::{HashMap, HashSet};
 use crate::execution_data::AExpressionSlice;
 use crate::execution_data::TagInfo;
+use crate::execution_data::add_index_to_symbol;
 
 
 struct Connexion {
@@ -69,6 +72,7 @@ pub struct ExecutedTemplate {
     pub intermediates: SignalCollector,
     pub ordered_signals: Vec<String>,
     pub constraints: Vec<Constraint>,
+    pub constraint_locations: Vec<ConstraintLocation>,
     pub components: ComponentCollector,
     pub number_of_components: usize,
     pub public_inputs: HashSet<String>,
@@ -78,7 +82,12 @@ pub struct ExecutedTemplate {
     pub is_parallel: bool,
     pub has_parallel_sub_cmp: bool,
     pub is_custom_gate: bool,
+    // relations declared by the constraints of a custom template
+    pub relations: Vec<GateRelation>,
+    // table that replaces the constraints of a gadget exported as a lookup
+    pub lookup: Option<LookupTable>,
     pub underscored_signals: Vec<String>,
+    pub kept_signals: Vec<String>,
     connexions: Vec<Connexion>,
 }
 
@@ -100,6 +109,8 @@ impl ExecutedTemplate {
             is_parallel,
             has_parallel_sub_cmp: false,
             is_custom_gate,
+            relations: Vec::new(),
+            lookup: None,
             code: code.clone(),
             template_name: name,
             parameter_instances: instance,
@@ -110,10 +121,12 @@ impl ExecutedTemplate {
             intermediates: SignalCollector::new(),
             ordered_signals: Vec::new(),
             constraints: Vec::new(),
+            constraint_locations: Vec::new(),
             components: ComponentCollector::new(),
             number_of_components: 0,
             connexions: Vec::new(),
             underscored_signals: Vec::new(),
+            kept_signals: Vec::new(),
         }
     }
 
@@ -123,6 +136,31 @@ impl ExecutedTemplate {
             && self.tag_instances == *tag_context
     }
 
+    // Exports a standard range check or lookup template as its table, where
+    // the parameters of the template have already been checked by its body.
+    // Returns the parameter that does not fit in the 32 bits of the r1cs file.
+    pub fn set_lookup(&mut self) -> Result<(), String> {
+        let parameter = |name: &str| {
+            let (_, values) = self.parameter_instances[name].clone().destruct();
+            as_big_int(values)
+        };
+        let number = |name: &str| {
+            parameter(name)[0].to_u32().map(|value| value as usize).ok_or_else(|| name.to_string())
+        };
+        // the gadgets can be included in a namespace
+        let table = if self.template_name.rsplit('.').next() == Some("RangeCheck") {
+            LookupTable::Range { bits: number("n")? }
+        } else {
+            let number_of_rows = number("n")?;
+            let columns = number("k")?;
+            let values = parameter("table");
+            let rows = (0..number_of_rows).map(|i| values[i * columns..(i + 1) * columns].to_vec()).collect();
+            LookupTable::Rows { columns, rows }
+        };
+        self.lookup = Some(table);
+        Ok(())
+    }
+
     pub fn add_arrow(&mut self, component_name: String, data: SubComponentData) {
         let cnn =
             Connexion { full_name: component_name, inspect: data, dag_offset: 0, dag_component_offset: 0, dag_jump: 0, dag_component_jump: 0};
@@ -142,26 +180,17 @@ impl ExecutedTemplate {
     }
 
     pub fn add_ordered_signal(&mut self, signal_name: &str, dimensions: &[usize]) {
-        fn generate_symbols(name: String, current: usize, dimensions: &[usize]) -> Vec<String> {
-            let symbol_name = name.clone();
-            if current == dimensions.len() {
-                vec![name]
-            } else {
-                let mut generated_symbols = vec![];
-                let mut index = 0;
-                while index < dimensions[current] {
-                    let new_name = format!("{}[{}]", symbol_name, index);
-                    generated_symbols.append(&mut generate_symbols(new_name, current + 1, dimensions));
-                    index += 1;
-                }
-                generated_symbols
-            }
-        }
-        for signal in generate_symbols(signal_name.to_string(), 0, dimensions) {
+        for signal in indexed_symbols(signal_name.to_string(), 0, dimensions) {
             self.ordered_signals.push(signal);
         }
     }
 
+    pub fn add_kept_signal(&mut self, signal_name: &str, dimensions: &[usize]) {
+        for signal in indexed_symbols(signal_name.to_string(), 0, dimensions) {
+            self.kept_signals.push(signal);
+        }
+    }
+
     pub fn add_tag_signal(&mut self, signal_name: &str, tag_name: &str, value: Option<BigInt>){
         let tags_signal = self.signal_to_tags.get_mut(signal_name);
         if tags_signal.is_none(){
@@ -178,8 +207,9 @@ impl ExecutedTemplate {
         self.number_of_components += dimensions.iter().fold(1, |p, c| p * (*c));
     }
 
-    pub fn add_constraint(&mut self, constraint: Constraint) {
+    pub fn add_constraint(&mut self, constraint: Constraint, location: ConstraintLocation) {
         self.constraints.push(constraint);
+        self.constraint_locations.push(location);
     }
 
     pub fn add_underscored_signal(&mut self, signal: &str) {
@@ -229,6 +259,12 @@ impl ExecutedTemplate {
             self.is_parallel,
             self.is_custom_gate
         );
+        if self.is_custom_gate {
+            dag.set_relations(self.relations.clone());
+        }
+        if let Some(table) = &self.lookup {
+            dag.set_lookup(table.clone());
+        }
         self.build_signals(dag);
         self.build_connexions(dag);
         self.build_constraints(dag);
@@ -285,17 +321,24 @@ impl ExecutedTemplate {
         dag.set_number_of_subcomponents_indexes(self.number_of_components);
     }
     fn build_constraints(&self, dag: &mut DAG) {
-        
-        for c in &self.constraints {
-            let correspondence = dag.get_main().unwrap().correspondence();
-            let cc = Constraint::apply_correspondence(c, correspondence);
-            dag.add_constraint(cc);
+        // the constraints of a gadget exported as a lookup are replaced by its table
+        if self.lookup.is_none() {
+            for (c, location) in self.constraints.iter().zip(&self.constraint_locations) {
+                let correspondence = dag.get_main().unwrap().correspondence();
+                let cc = Constraint::apply_correspondence(c, correspondence);
+                dag.add_constraint(cc, *location);
+            }
         }
         for s in &self.underscored_signals{
             let correspondence = dag.get_main().unwrap().correspondence();
             let new_s = correspondence.get(s).unwrap().clone();
             dag.add_underscored_signal(new_s);
         }
+        for s in &self.kept_signals {
+            let correspondence = dag.get_main().unwrap().correspondence();
+            let new_s = *correspondence.get(s).unwrap();
+            dag.add_kept_signal(new_s);
+        }
     }
 
     pub fn export_to_circuit(self, instances: &mut [TemplateInstance]) -> TemplateInstance {
@@ -343,7 +386,11 @@ impl ExecutedTemplate {
             arguments
         }
 
-        let header = format!("{}_{}", self.template_name, instances.len());
+        // names qualified by a namespace (X.Name) or specialized with functions
+        // (Map<X.f>) are not valid identifiers in the generated code
+        let name: String =
+            self.template_name.chars().map(|c| if c.is_ascii_alphanumeric() || c == '_' { c } else { '_' }).collect();
+        let header = format!("{}_{}", name, instances.len());
         let clusters = build_clusters(&self, instances);
         let triggers = build_triggers(instances, self.connexions);
         let components = build_components(self.components);
@@ -405,6 +452,20 @@ impl ExecutedTemplate {
     }
 }
 
+// The names of the signals of an array, in the order of their indexes
+fn indexed_symbols(name: String, current: usize, dimensions: &[usize]) -> Vec<String> {
+    if current == dimensions.len() {
+        vec![name]
+    } else {
+        let mut generated_symbols = vec![];
+        for index in 0..dimensions[current] {
+            let new_name = add_index_to_symbol(&name, index);
+            generated_symbols.append(&mut indexed_symbols(new_name, current + 1, dimensions));
+        }
+        generated_symbols
+    }
+}
+
 struct SignalConfig<'a> {
     is_public: bool,
     signal_type: usize,
@@ -427,7 +488,7 @@ fn generate_symbols(dag: &mut DAG, state: State, config: &SignalConfig) {
         let mut index = 0;
         while index < config.dimensions[state.dim] {
             let new_state =
-                State { name: format!("{}[{}]", state.name, index), dim: state.dim + 1 };
+                State { name: add_index_to_symbol(&state.name, index), dim: state.dim + 1 };
             generate_symbols(dag, new_state, config);
             index += 1;
         }
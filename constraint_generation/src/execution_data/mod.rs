@@ -9,3 +9,13 @@ pub mod executed_program;
 pub mod executed_template;
 mod filters;
 pub mod type_definitions;
+
+// Adds an index to the name of a signal. The fields of a bus array are named
+// with [] in place of the dimensions of the bus (p[].x), which are the first
+// ones to be indexed.
+pub fn add_index_to_symbol(symbol: &str, index: usize) -> String {
+    match symbol.find("[]") {
+        Some(position) => format!("{}[{}]{}", &symbol[..position], index, &symbol[position + 2..]),
+        None => format!("{}[{}]", symbol, index),
+    }
+}
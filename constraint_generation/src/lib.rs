@@ -11,7 +11,7 @@ use circom_algebra::algebra::{ArithmeticError, ArithmeticExpression};
 use compiler::hir::very_concrete_program::VCP;
 use constraint_list::ConstraintList;
 use constraint_writers::ConstraintExporter;
-use dag::DAG;
+use dag::{SimplificationStrategy, Statistics, StatisticsFormat, DAG};
 use execution_data::executed_program::ExportResult;
 use execution_data::ExecutedProgram;
 use program_structure::ast::{self};
@@ -28,10 +28,18 @@ pub struct BuildConfig {
     pub flag_s: bool,
     pub flag_f: bool,
     pub flag_p: bool,
+    // threads of the simplification, one per core when it is None
+    pub no_threads: Option<usize>,
     pub flag_verbose: bool,
     pub flag_old_heuristics: bool,
+    pub strategy: SimplificationStrategy,
+    pub keep: Vec<String>,
+    pub public_layout: Vec<String>,
+    pub provenance: bool,
     pub inspect_constraints: bool,
     pub prime: String,
+    pub statistics: Option<StatisticsFormat>,
+    pub statistics_file: String,
 }
 
 #[derive(Debug, Copy, Clone)]
@@ -58,8 +66,17 @@ pub fn build_circuit(program: ProgramArchive, config: BuildConfig) -> BuildRespo
     if config.inspect_constraints {
         Report::print_reports(&warnings, &files);
     }
+    if !config.public_layout.is_empty() {
+        dag.set_public_layout(&config.public_layout).map_err(|msg| {
+            eprintln!("{}", Colour::Red.paint(msg));
+        })?;
+    }
+    let mut statistics = config.statistics.map(|_| Statistics::new(&dag));
     if config.flag_f {
         sync_dag_and_vcp(&mut vcp, &mut dag);
+        if let Some(statistics) = &statistics {
+            report_statistics(statistics, &config)?;
+        }
         if config.flag_json_sub { 
             use constraint_writers::json_writer::SubstitutionJSON;
             let substitution_log = SubstitutionJSON::new(&config.json_substitutions).unwrap();
@@ -70,6 +87,11 @@ pub fn build_circuit(program: ProgramArchive, config: BuildConfig) -> BuildRespo
         Result::Ok((Box::new(dag), vcp))
     } else {
         let list = simplification_process(&mut vcp, dag, &config);
+        list.costs().print(config.strategy);
+        if let Some(statistics) = &mut statistics {
+            statistics.set_simplified(&list);
+            report_statistics(statistics, &config)?;
+        }
         if config.flag_json_sub { 
             println!("{} {}", Colour::Green.paint("Written successfully:"), config.json_substitutions);
         };
@@ -77,6 +99,40 @@ pub fn build_circuit(program: ProgramArchive, config: BuildConfig) -> BuildRespo
     }
 }
 
+fn report_statistics(statistics: &Statistics, config: &BuildConfig) -> Result<(), ()> {
+    match config.statistics {
+        Some(StatisticsFormat::Table) => {
+            statistics.print_table();
+            Result::Ok(())
+        }
+        Some(StatisticsFormat::Json) => {
+            if let Result::Ok(()) = statistics.write_json(&config.statistics_file) {
+                println!("{} {}", Colour::Green.paint("Written successfully:"), config.statistics_file);
+                Result::Ok(())
+            } else {
+                eprintln!("{}", Colour::Red.paint("Could not write the output in the given path"));
+                Result::Err(())
+            }
+        }
+        None => Result::Ok(()),
+    }
+}
+
+// Builds the circuit of a test. Its constraints are not simplified, so that
+// every one of them can be checked against the witness computed for the test.
+pub fn build_test_circuit(program: ProgramArchive, prime: &String) -> Result<(DAG, VCP), ()> {
+    let files = program.file_library.clone();
+    let flags = FlagsExecution { verbose: false, inspect: false };
+    let (exe, _) = execute::constraint_execution(&program, flags, prime).map_err(|r| {
+        Report::print_reports(&r, &files);
+    })?;
+    let (mut dag, mut vcp, _) = export(exe, program, flags).map_err(|r| {
+        Report::print_reports(&r, &files);
+    })?;
+    sync_dag_and_vcp(&mut vcp, &mut dag);
+    Result::Ok((dag, vcp))
+}
+
 type InstantiationResponse = Result<(ExecutedProgram, ReportCollection), ReportCollection>;
 fn instantiation(program: &ProgramArchive, flags: FlagsExecution, prime: &String) -> InstantiationResponse {
     let execution_result = execute::constraint_execution(&program, flags, prime);
@@ -107,13 +163,403 @@ fn simplification_process(vcp: &mut VCP, dag: DAG, config: &BuildConfig) -> Cons
     let flags = SimplificationFlags {
         flag_s: config.flag_s,
         parallel_flag: config.flag_p,
+        no_threads: config.no_threads,
         port_substitution: config.flag_json_sub,
         json_substitutions: config.json_substitutions.clone(),
         no_rounds: config.no_rounds,
         flag_old_heuristics: config.flag_old_heuristics,
+        strategy: config.strategy,
+        keep: config.keep.clone(),
+        provenance: config.provenance,
         prime : config.prime.clone(),
     };
     let list = DAG::map_to_list(dag, flags);
     VCP::add_witness_list(vcp, Rc::new(list.get_witness_as_vec()));
     list
 }
+
+#[cfg(test)]
+mod test {
+    use super::{build_circuit, build_test_circuit, BuildConfig};
+    use circom_algebra::num_bigint::{BigInt, Sign};
+    use dag::{SimplificationStrategy, StatisticsFormat};
+    use program_structure::constants::UsefulConstants;
+    use std::collections::HashMap;
+    use std::path::PathBuf;
+    use type_analysis::check_types::check_types;
+
+    fn program(fixture: &str) -> program_structure::program_archive::ProgramArchive {
+        let mut path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        path.push("tests/fixtures");
+        path.push(fixture);
+        let (mut program_archive, _) =
+            parser::run_parser(path.to_str().unwrap().to_owned(), env!("CARGO_PKG_VERSION"), vec![path])
+                .ok()
+                .unwrap();
+        check_types(&mut program_archive).ok().unwrap();
+        program_archive
+    }
+
+    fn config() -> BuildConfig {
+        BuildConfig {
+            no_rounds: usize::MAX,
+            flag_json_sub: false,
+            json_substitutions: String::new(),
+            flag_s: false,
+            flag_f: false,
+            flag_p: false,
+            no_threads: None,
+            flag_verbose: false,
+            flag_old_heuristics: false,
+            strategy: SimplificationStrategy::Wires,
+            keep: Vec::new(),
+            public_layout: Vec::new(),
+            provenance: false,
+            inspect_constraints: false,
+            prime: "bn128".to_string(),
+            statistics: None,
+            statistics_file: String::new(),
+        }
+    }
+
+    // The r1cs, sym and substitutions files of the circuit, simplified with
+    // the given threads
+    fn written(no_threads: usize, run: usize) -> Vec<Vec<u8>> {
+        let output = std::env::temp_dir().join(format!("simplification_{}_{}", std::process::id(), run));
+        let r1cs = output.with_extension("r1cs");
+        let sym = output.with_extension("sym");
+        let substitutions = output.with_extension("json");
+        let config = BuildConfig {
+            flag_json_sub: true,
+            json_substitutions: substitutions.to_str().unwrap().to_string(),
+            no_threads: Some(no_threads),
+            ..config()
+        };
+        let (exporter, _) = build_circuit(program("simplification.circom"), config).unwrap();
+        exporter.r1cs(r1cs.to_str().unwrap(), false, None).unwrap();
+        exporter.sym(sym.to_str().unwrap()).unwrap();
+        let mut written = Vec::new();
+        for file in [r1cs, sym, substitutions].iter() {
+            written.push(std::fs::read(file).unwrap());
+            std::fs::remove_file(file).unwrap();
+        }
+        written
+    }
+
+    #[test]
+    fn written_circuit_does_not_depend_on_the_threads_or_the_hash_seeds() {
+        let expected = written(1, 0);
+        for (run, no_threads) in [1, 1, 2, 4, 8, 8].iter().copied().enumerate() {
+            // every thread has its own random keys for the hash maps
+            let output = std::thread::spawn(move || written(no_threads, run + 1)).join().unwrap();
+            assert!(output == expected, "the output with {} threads is different", no_threads);
+        }
+    }
+
+    // The statistics written by --stats=json, without simplification with -O0
+    fn statistics(flag_f: bool) -> json::JsonValue {
+        let file = std::env::temp_dir().join(format!("statistics_{}_{}.json", std::process::id(), flag_f));
+        let config = BuildConfig {
+            flag_f,
+            statistics: Some(StatisticsFormat::Json),
+            statistics_file: file.to_str().unwrap().to_string(),
+            ..config()
+        };
+        build_circuit(program("statistics.circom"), config).unwrap();
+        let statistics = json::parse(&std::fs::read_to_string(&file).unwrap()).unwrap();
+        std::fs::remove_file(&file).unwrap();
+        statistics
+    }
+
+    // constraints, non linear, linear, inputs, outputs and intermediates
+    fn counts(slot: &json::JsonValue) -> [usize; 6] {
+        let names = ["constraints", "non_linear", "linear", "inputs", "outputs", "intermediates"];
+        let mut counts = [0; 6];
+        for (count, name) in counts.iter_mut().zip(names.iter()) {
+            *count = slot[*name].as_usize().unwrap();
+        }
+        counts
+    }
+
+    #[test]
+    fn statistics_count_the_constraints_and_signals_of_each_instance() {
+        let statistics = statistics(false);
+        let instances = &statistics["instances"];
+        let names: Vec<&str> = instances.members().map(|instance| instance["instance"].as_str().unwrap()).collect();
+        assert_eq!(names, vec!["Sum(2)", "Square()", "Main()"]);
+        // the simplification keeps the signals of s in place of the ones of
+        // sq and main that are equal to them, so the three constraints left
+        // are counted in Sum(2)
+        let sum = &instances[0];
+        assert_eq!(sum["parameters"][0].as_str(), Some("2"));
+        assert_eq!(counts(&sum["before"]), [1, 0, 1, 2, 1, 0]);
+        assert_eq!(counts(&sum["after"]), [3, 3, 0, 1, 1, 0]);
+        // the two components of Square are counted in its instance
+        let square = &instances[1];
+        assert_eq!(square["components"].as_usize(), Some(2));
+        assert_eq!(counts(&square["before"]), [4, 2, 2, 2, 2, 2]);
+        assert_eq!(counts(&square["after"]), [0, 0, 0, 0, 0, 0]);
+        let main = &instances[2];
+        assert_eq!(main["subcomponents"].as_usize(), Some(3));
+        assert_eq!(counts(&main["before"]), [5, 1, 4, 1, 1, 0]);
+        assert_eq!(counts(&main["after"]), [0, 0, 0, 1, 1, 0]);
+        let templates: Vec<(&str, usize)> = statistics["templates"]
+            .members()
+            .map(|template| (template["template"].as_str().unwrap(), template["components"].as_usize().unwrap()))
+            .collect();
+        assert_eq!(templates, vec![("Main", 1), ("Square", 2), ("Sum", 1)]);
+    }
+
+    #[test]
+    fn statistics_without_simplification_are_the_same_after_it() {
+        let statistics = statistics(true);
+        for instance in statistics["instances"].members() {
+            assert_eq!(counts(&instance["after"]), counts(&instance["before"]));
+        }
+    }
+
+    const LAYOUT: [&str; 3] = ["main.o2", "main.o1", "main.b"];
+
+    // the coefficients of the wires in the linear expressions a, b and c
+    type R1CSConstraint = [HashMap<usize, BigInt>; 3];
+
+    // The sections of an r1cs file by their type
+    fn r1cs_sections(bytes: &[u8]) -> HashMap<u32, &[u8]> {
+        let mut sections = HashMap::new();
+        let mut at = 12;
+        while at < bytes.len() {
+            let kind = u32::from_le_bytes([bytes[at], bytes[at + 1], bytes[at + 2], bytes[at + 3]]);
+            let mut size = [0; 8];
+            size.copy_from_slice(&bytes[at + 4..at + 12]);
+            let size = u64::from_le_bytes(size) as usize;
+            sections.insert(kind, &bytes[at + 12..at + 12 + size]);
+            at += 12 + size;
+        }
+        sections
+    }
+
+    // The files written for a circuit: the sections of the r1cs file, with
+    // the constraints over the wires and the labels of the wires, and the
+    // witness of the signals in the sym file
+    struct Written {
+        sections: HashMap<u32, Vec<u8>>,
+        constraints: Vec<R1CSConstraint>,
+        labels: Vec<usize>,
+        witness: HashMap<String, usize>,
+        witness_list: Vec<usize>,
+    }
+
+    fn number(bytes: &[u8], at: usize, size: usize) -> BigInt {
+        BigInt::from_bytes_le(Sign::Plus, &bytes[at..at + size])
+    }
+
+    fn length(bytes: &[u8], at: usize, size: usize) -> usize {
+        number(bytes, at, size).to_string().parse().unwrap()
+    }
+
+    fn write(fixture: &str, config: BuildConfig, custom_gates: bool) -> Written {
+        let output = std::env::temp_dir().join(format!("{}_{}_{}", fixture, std::process::id(), config.flag_f));
+        let r1cs = output.with_extension("r1cs");
+        let sym = output.with_extension("sym");
+        let (exporter, vcp) = build_circuit(program(fixture), config).unwrap();
+        exporter.r1cs(r1cs.to_str().unwrap(), custom_gates, None).unwrap();
+        exporter.sym(sym.to_str().unwrap()).unwrap();
+        let bytes = std::fs::read(&r1cs).unwrap();
+        let sections: HashMap<u32, Vec<u8>> =
+            r1cs_sections(&bytes).into_iter().map(|(kind, section)| (kind, section.to_vec())).collect();
+        let n8 = length(&sections[&1], 0, 4);
+        let labels = sections[&3].chunks(8).map(|label| length(label, 0, 8)).collect();
+        let mut constraints = Vec::new();
+        let section = &sections[&2];
+        let mut at = 0;
+        while at < section.len() {
+            let mut constraint: R1CSConstraint = Default::default();
+            for linear in constraint.iter_mut() {
+                let terms = length(section, at, 4);
+                at += 4;
+                for _ in 0..terms {
+                    linear.insert(length(section, at, 4), number(section, at + 4, n8));
+                    at += 4 + n8;
+                }
+            }
+            constraints.push(constraint);
+        }
+        let witness = std::fs::read_to_string(&sym)
+            .unwrap()
+            .lines()
+            .filter_map(|line| {
+                // the signals removed by the simplification have no witness, -1
+                let fields: Vec<&str> = line.split(',').collect();
+                fields[1].parse().ok().map(|witness| (fields[3].to_string(), witness))
+            })
+            .collect();
+        std::fs::remove_file(&r1cs).unwrap();
+        std::fs::remove_file(&sym).unwrap();
+        Written { sections, constraints, labels, witness, witness_list: vcp.witness_list.to_vec() }
+    }
+
+    // Whether the constraints hold for the values of the wires
+    fn satisfied(constraints: &[R1CSConstraint], values: &[BigInt]) -> bool {
+        let p = UsefulConstants::new("bn128").get_p().clone();
+        let value = |linear: &HashMap<usize, BigInt>| -> BigInt {
+            linear.iter().map(|(wire, coefficient)| coefficient * &values[*wire]).sum::<BigInt>() % &p
+        };
+        constraints.iter().all(|[a, b, c]| (value(a) * value(b) - value(c)) % &p == BigInt::from(0))
+    }
+
+    fn assert_laid_out(flag_f: bool) {
+        let config = BuildConfig {
+            flag_f,
+            public_layout: LAYOUT.iter().map(|entry| entry.to_string()).collect(),
+            ..config()
+        };
+        let Written { constraints, labels, witness, witness_list, .. } = write("public-layout.circom", config, false);
+        // o1, o2, a, b[0], b[1], c and t are the signals 1 to 7
+        assert_eq!(labels[..6], [0, 2, 1, 4, 5, 3]);
+        assert_eq!(witness_list[..6], [0, 2, 1, 4, 5, 3]);
+        let positions: Vec<usize> =
+            ["main.o2", "main.o1", "main.b[0]", "main.b[1]", "main.a"].iter().map(|name| witness[*name]).collect();
+        assert_eq!(positions, vec![1, 2, 3, 4, 5]);
+        // the constraints are satisfied by the values of the signals on their
+        // wires, and not when the outputs are swapped
+        let on_wires = |values: [i64; 8]| -> Vec<BigInt> {
+            labels.iter().map(|label| BigInt::from(values[*label])).collect()
+        };
+        assert!(!constraints.is_empty());
+        assert!(satisfied(&constraints, &on_wires([1, 42, 11, 2, 3, 5, 7, 6])));
+        assert!(!satisfied(&constraints, &on_wires([1, 11, 42, 2, 3, 5, 7, 6])));
+    }
+
+    #[test]
+    fn public_layout_orders_the_wires_of_the_circuit() {
+        assert_laid_out(true);
+    }
+
+    #[test]
+    fn public_layout_orders_the_wires_of_the_simplified_circuit() {
+        assert_laid_out(false);
+    }
+
+    fn set_public_layout(layout: &[&str]) -> Result<Vec<usize>, String> {
+        let (mut dag, _) = build_test_circuit(program("public-layout.circom"), &"bn128".to_string()).unwrap();
+        let layout: Vec<String> = layout.iter().map(|entry| entry.to_string()).collect();
+        dag.set_public_layout(&layout)?;
+        Result::Ok((1..=5).map(|signal| dag.wire(signal)).collect())
+    }
+
+    #[test]
+    fn public_layout_places_the_signals_that_are_not_in_it_after_the_others() {
+        // the wires of o1, o2, a, b[0] and b[1]
+        assert_eq!(set_public_layout(&["main.b"]), Result::Ok(vec![1, 2, 5, 3, 4]));
+        assert_eq!(set_public_layout(&["main.o2", "main.b[1]"]), Result::Ok(vec![2, 1, 4, 5, 3]));
+        assert_eq!(set_public_layout(&LAYOUT), Result::Ok(vec![2, 1, 5, 3, 4]));
+    }
+
+    #[test]
+    fn public_layout_errors() {
+        let error = |layout: &[&str]| set_public_layout(layout).err().unwrap();
+        assert_eq!(error(&["main.d"]), "main.d is not a signal of the main component");
+        assert_eq!(error(&["o1"]), "o1 is not a signal of the main component");
+        assert_eq!(error(&["main.b[2]"]), "main.b[2] is not a signal of the main component");
+        assert_eq!(error(&["main.c"]), "main.c is not a public signal of the main component");
+        assert_eq!(error(&["main.b", "main.b[1]"]), "main.b[1] appears more than once in the layout");
+        assert_eq!(error(&["main.o1", "main.o1"]), "main.o1 appears more than once in the layout");
+        assert_eq!(error(&["main.a", "main.o1"]), "the output main.o1 goes after a public input in the layout");
+    }
+
+    // Whether the constraints hold for some values of the bits, 0 or 1, and
+    // the given values of the other signals. The bits are constrained to be 0
+    // or 1, so no other values of them can satisfy the constraints. The
+    // signals removed by the simplification are skipped.
+    fn satisfiable(written: &Written, values: &[(&str, i64)], bits: &[&str]) -> bool {
+        let mut wires = vec![None; written.labels.len()];
+        wires[0] = Some(BigInt::from(1));
+        for (name, value) in values {
+            if let Some(wire) = written.witness.get(*name) {
+                wires[*wire] = Some(BigInt::from(*value));
+            }
+        }
+        let bits: Vec<usize> = bits.iter().filter_map(|name| written.witness.get(*name).copied()).collect();
+        (0..1 << bits.len()).any(|assignment| {
+            for (position, wire) in bits.iter().enumerate() {
+                wires[*wire] = Some(BigInt::from((assignment >> position) & 1));
+            }
+            let values: Vec<BigInt> = wires.iter().map(|value| value.clone().unwrap()).collect();
+            satisfied(&written.constraints, &values)
+        })
+    }
+
+    #[test]
+    fn gadgets_without_lookups_are_expanded_into_sound_constraints() {
+        let bits = [
+            "main.r.bits[0]",
+            "main.r.bits[1]",
+            "main.r.bits[2]",
+            "main.r.bits[3]",
+            "main.l.selectors[0]",
+            "main.l.selectors[1]",
+        ];
+        // the range check and the lookup add 5 constraints each, and the
+        // simplification leaves the non linear ones
+        for (flag_f, no_constraints) in [(true, 14), (false, 6)].iter().copied() {
+            let written = write("gadgets.circom", BuildConfig { flag_f, ..config() }, false);
+            assert_eq!(written.constraints.len(), no_constraints);
+            assert!(!written.sections.contains_key(&7));
+            let satisfiable = |x: i64, input: [i64; 2]| {
+                let values = [
+                    ("main.x", x),
+                    ("main.r.in", x),
+                    ("main.in[0]", input[0]),
+                    ("main.in[1]", input[1]),
+                    ("main.l.in[0]", input[0]),
+                    ("main.l.in[1]", input[1]),
+                    ("main.y", x * input[0]),
+                ];
+                satisfiable(&written, &values, &bits)
+            };
+            assert!(satisfiable(5, [0, 1]));
+            assert!(satisfiable(15, [1, 0]));
+            assert!(!satisfiable(16, [1, 0]));
+            assert!(!satisfiable(-1, [1, 0]));
+            assert!(!satisfiable(5, [2, -1]));
+            if flag_f {
+                // the simplification replaces in[1] by 1 - in[0]
+                assert!(!satisfiable(5, [1, 1]));
+                assert!(!satisfiable(5, [0, 0]));
+            }
+        }
+    }
+
+    #[test]
+    fn gadgets_with_lookups_are_written_in_the_lookups_section() {
+        for (flag_f, no_constraints) in [(true, 4), (false, 1)].iter().copied() {
+            let written = write("gadgets-lookups.circom", BuildConfig { flag_f, ..config() }, true);
+            // only y <== x * in[0] is left after the simplification
+            assert_eq!(written.constraints.len(), no_constraints);
+            assert!(!written.witness.contains_key("main.r.bits[0]"));
+            assert!(!written.witness.contains_key("main.l.selectors[0]"));
+            let wire = |name: &str| written.witness[name] as u64;
+            let u32s = |values: &[u32]| values.iter().flat_map(|v| v.to_le_bytes()).collect::<Vec<u8>>();
+            let field = |value: u8| {
+                let mut bytes = vec![0; 32];
+                bytes[0] = value;
+                bytes
+            };
+            // the tables in the order of the signals of their components, l
+            // with the rows [0, 1] and [1, 0] and r with RangeCheck(4)
+            let mut section = u32s(&[2, 1, 2, 2]);
+            for value in [0, 1, 1, 0].iter() {
+                section.extend(field(*value));
+            }
+            section.extend(u32s(&[0, 4]));
+            // applied to l.in and to r.in
+            section.extend(u32s(&[2, 0, 2]));
+            section.extend(wire("main.l.in[0]").to_le_bytes());
+            section.extend(wire("main.l.in[1]").to_le_bytes());
+            section.extend(u32s(&[1, 1]));
+            section.extend(wire("main.r.in").to_le_bytes());
+            assert_eq!(section.len(), 196);
+            assert_eq!(written.sections[&7], section);
+        }
+    }
+}
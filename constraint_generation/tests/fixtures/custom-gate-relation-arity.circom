@@ -0,0 +1,29 @@
+pragma circom 2.1.8;
+pragma custom_templates;
+
+template custom Sum(n) {
+  signal input in[n];
+  signal output out;
+
+  var sum = 0;
+  for (var i = 0; i < n; i++) {
+    sum += in[i];
+  }
+  out <-- sum;
+  out === in[0] + in[1] + in[2];
+}
+
+template Main() {
+  signal input a[3];
+  signal input b[2];
+  signal output out[2];
+
+  component three = Sum(3);
+  three.in <== a;
+  out[0] <== three.out;
+  component two = Sum(2);
+  two.in <== b;
+  out[1] <== two.out;
+}
+
+component main = Main();
@@ -0,0 +1,18 @@
+pragma circom 2.1.8;
+pragma custom_templates;
+
+// A range check and a lookup, expanded into constraints or exported as lookups
+include "circom:gadgets";
+
+template Main() {
+    signal input x;
+    signal input in[2];
+    signal output y;
+    component r = RangeCheck(4);
+    r.in <== x;
+    component l = Lookup(2, 2, [[0, 1], [1, 0]]);
+    l.in <== in;
+    y <== x * in[0];
+}
+
+component main = Main();
@@ -0,0 +1,17 @@
+pragma circom 2.1.0;
+
+// Outputs, public inputs and private signals to be placed by --public_layout
+
+template Main() {
+    signal input a;
+    signal input b[2];
+    signal input c;
+    signal output o1;
+    signal output o2;
+    signal t;
+    t <== a * b[0];
+    o1 <== t * c;
+    o2 <== t + b[1];
+}
+
+component main {public [a, b]} = Main();
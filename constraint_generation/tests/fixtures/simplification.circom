@@ -0,0 +1,45 @@
+pragma circom 2.1.0;
+
+// Linear clusters of several sizes, chains of equalities and non-linear
+// constraints over them
+
+template Mix(n) {
+    signal input in[n];
+    signal output out[n];
+    signal t[n];
+    for (var i = 0; i < n; i++) {
+        t[i] <== in[i] + 2 * in[(i + 1) % n] + 3;
+    }
+    for (var i = 0; i < n; i++) {
+        out[i] <== t[i] * t[(i + 3) % n];
+    }
+}
+
+template Chain(n) {
+    signal input in;
+    signal output out;
+    signal c[n];
+    c[0] <== in;
+    for (var i = 1; i < n; i++) {
+        c[i] <== c[i - 1];
+    }
+    out <== c[n - 1] * in;
+}
+
+template Main(m, n) {
+    signal input a[n];
+    signal output out[m];
+    component mix[m];
+    component chain[m];
+    for (var j = 0; j < m; j++) {
+        mix[j] = Mix(n);
+        chain[j] = Chain(j + 2);
+        for (var i = 0; i < n; i++) {
+            mix[j].in[i] <== j == 0 ? a[i] : mix[j - 1].out[i] + a[(i + j) % n];
+        }
+        chain[j].in <== mix[j].out[j % n];
+        out[j] <== chain[j].out + mix[j].out[0];
+    }
+}
+
+component main = Main(8, 12);
@@ -0,0 +1,38 @@
+pragma circom 2.1.0;
+
+// Two components of the same template instance, whose linear constraints
+// are removed by the simplification
+
+template Square() {
+    signal input in;
+    signal output out;
+    signal copy;
+    copy <== in;
+    out <== copy * in;
+}
+
+template Sum(n) {
+    signal input in[n];
+    signal output out;
+    var acc = 0;
+    for (var i = 0; i < n; i++) {
+        acc += in[i];
+    }
+    out <== acc;
+}
+
+template Main() {
+    signal input x;
+    signal output y;
+    component sq[2];
+    sq[0] = Square();
+    sq[0].in <== x;
+    sq[1] = Square();
+    sq[1].in <== sq[0].out;
+    component s = Sum(2);
+    s.in[0] <== sq[0].out;
+    s.in[1] <== sq[1].out;
+    y <== s.out * x;
+}
+
+component main = Main();
@@ -0,0 +1,10 @@
+pragma circom 2.1.8;
+
+template Square() {
+  signal output o;
+  var u32 x = 4294967295;
+  var u32 y = x * x;
+  o <== y;
+}
+
+component main = Square();
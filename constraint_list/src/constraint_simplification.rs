@@ -1,11 +1,41 @@
 // Uncomment lines 163, 165, 336 and 338 to print cluster information
-use super::{ConstraintStorage, EncodingIterator, SEncoded, Simplifier, A, C, S};
+use super::{ConstraintStorage, EncodingIterator, Origins, SEncoded, Simplifier, SubstitutionOrigins, Traced, A, C, S};
 use crate::SignalMap;
 use circom_algebra::num_bigint::BigInt;
+use circom_algebra::simplification_utils::{fast_encoded_constraint_substitution, Heuristics};
 use constraint_writers::json_writer::SubstitutionJSON;
 use std::collections::{HashMap, HashSet, LinkedList, BTreeSet};
 use std::sync::Arc;
 
+// How the linear simplification chooses the signals it removes, the costs of
+// the result are different for each prover
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub enum SimplificationStrategy {
+    // removes a signal with every linear constraint where it is possible, so
+    // the witness has as few wires as possible
+    Wires,
+    // removes a signal only when it does not increase the number of terms of
+    // the constraints
+    Terms,
+}
+
+impl SimplificationStrategy {
+    pub fn name(&self) -> &'static str {
+        match self {
+            SimplificationStrategy::Wires => "wires",
+            SimplificationStrategy::Terms => "terms",
+        }
+    }
+
+    fn heuristics(&self, use_old_heuristics: bool, occurrences: impl FnOnce() -> Occurrences) -> Heuristics {
+        match self {
+            SimplificationStrategy::Wires if use_old_heuristics => Heuristics::Greatest,
+            SimplificationStrategy::Wires => Heuristics::Occurrences,
+            SimplificationStrategy::Terms => Heuristics::NonLinearTerms(Arc::new(occurrences())),
+        }
+    }
+}
+
 fn log_substitutions(substitutions: &LinkedList<S>, writer: &mut Option<SubstitutionJSON>) {
     use super::json_porting::port_substitution;
     if let Some(w) = writer {
@@ -16,15 +46,60 @@ fn log_substitutions(substitutions: &LinkedList<S>, writer: &mut Option<Substitu
     }
 }
 
+fn merge_origins(origins: &mut Origins, other: &Origins) {
+    if !other.is_empty() {
+        origins.extend(other.iter().cloned());
+        origins.sort_unstable();
+        origins.dedup();
+    }
+}
+
+// Pairs the constraints with their origins, given in the same order. Without
+// provenance there are no origins and every constraint gets an empty list.
+fn trace(constraints: LinkedList<C>, origins: LinkedList<Origins>) -> LinkedList<Traced> {
+    let mut origins = origins.into_iter();
+    constraints.into_iter().map(|constraint| (constraint, origins.next().unwrap_or_default())).collect()
+}
+
+// The substitutions obtained from some constraints come from their origins
+fn record_origins(substitutions: &LinkedList<S>, from: &Origins, origins: &mut SubstitutionOrigins) {
+    if !from.is_empty() {
+        for substitution in substitutions {
+            origins.insert(*substitution.from(), from.clone());
+        }
+    }
+}
+
+// Applies the substitutions to the constraint, that takes the origins of the
+// substitutions it uses
+pub fn substitute(constraint: &mut Traced, substitutions: &SEncoded, origins: &SubstitutionOrigins, field: &BigInt) {
+    let (constraint, constraint_origins) = constraint;
+    let used: Vec<usize> = if origins.is_empty() {
+        Vec::new()
+    } else {
+        C::take_cloned_signals(constraint).into_iter().filter(|signal| substitutions.contains_key(signal)).collect()
+    };
+    if fast_encoded_constraint_substitution(constraint, substitutions, field) {
+        C::fix_constraint(constraint, field);
+        for signal in used {
+            if let Some(origins) = origins.get(&signal) {
+                merge_origins(constraint_origins, origins);
+            }
+        }
+    }
+}
+
 #[derive(Default, Clone)]
 struct Cluster {
     constraints: LinkedList<C>,
+    origins: Origins,
     num_signals: usize
 }
 impl Cluster {
-    pub fn new(constraint: C, num_signals: usize) -> Cluster {
+    pub fn new(constraint: C, origins: Origins, num_signals: usize) -> Cluster {
         let mut new = Cluster::default();
         LinkedList::push_back(&mut new.constraints, constraint);
+        new.origins = origins;
         new.num_signals = num_signals;
         new
     }
@@ -33,6 +108,8 @@ impl Cluster {
         let mut result = Cluster::default();
         LinkedList::append(&mut result.constraints, &mut c0.constraints);
         LinkedList::append(&mut result.constraints, &mut c1.constraints);
+        merge_origins(&mut result.origins, &c0.origins);
+        merge_origins(&mut result.origins, &c1.origins);
         result.num_signals = c0.num_signals + c1.num_signals - 1;
         result
     }
@@ -42,7 +119,7 @@ impl Cluster {
     }
 }
 
-fn build_clusters(linear: LinkedList<C>, no_vars: usize) -> Vec<Cluster> {
+fn build_clusters(linear: LinkedList<Traced>, no_vars: usize) -> Vec<Cluster> {
     type ClusterArena = Vec<Option<Cluster>>;
     type ClusterPath = Vec<usize>;
     fn shrink_jumps_and_find(c_to_c: &mut ClusterPath, org: usize) -> usize {
@@ -72,11 +149,11 @@ fn build_clusters(linear: LinkedList<C>, no_vars: usize) -> Vec<Cluster> {
     let mut arena = ClusterArena::with_capacity(no_linear);
     let mut cluster_to_current = ClusterPath::with_capacity(no_linear);
     let mut signal_to_cluster = vec![no_linear; no_vars];
-    for constraint in linear {
+    for (constraint, constraint_origins) in linear {
         if !constraint.is_empty(){
-            let signals = C::take_cloned_signals(&constraint);
+            let signals = C::take_cloned_signals_ordered(&constraint);
             let dest = ClusterArena::len(&arena);
-            ClusterArena::push(&mut arena, Some(Cluster::new(constraint, signals.len())));
+            ClusterArena::push(&mut arena, Some(Cluster::new(constraint, constraint_origins, signals.len())));
             Vec::push(&mut cluster_to_current, dest);
             for signal in signals {
                 let prev = signal_to_cluster[signal];
@@ -156,7 +233,7 @@ fn eq_cluster_simplification(
         let mut cons = LinkedList::new();
         let mut subs = LinkedList::new();
         let (mut remains, mut min_remains) = (BTreeSet::new(), None);
-        let (mut remove, mut min_remove) = (HashSet::new(), None);
+        let (mut remove, mut min_remove) = (BTreeSet::new(), None);
         for c in cluster.constraints {
             for signal in C::take_cloned_signals_ordered(&c) {
                 if HashSet::contains(&forbidden, &signal) {
@@ -164,7 +241,7 @@ fn eq_cluster_simplification(
                     min_remains = Some(min_remains.map_or(signal, |s| std::cmp::min(s, signal)));
                 } else {
                     min_remove = Some(min_remove.map_or(signal, |s| std::cmp::min(s, signal)));
-                    HashSet::insert(&mut remove, signal);
+                    BTreeSet::insert(&mut remove, signal);
                 }
             }
         }
@@ -174,7 +251,7 @@ fn eq_cluster_simplification(
             signal
         } else {
             let signal = min_remove.unwrap();
-            HashSet::remove(&mut remove, &signal);
+            BTreeSet::remove(&mut remove, &signal);
             signal
         };
 
@@ -195,31 +272,37 @@ fn eq_cluster_simplification(
     }
 }
 
+// The results of the clusters are joined in the order of the clusters, not in
+// the order the threads end, so the output does not depend on the threads
 fn eq_simplification(
-    equalities: LinkedList<C>,
+    equalities: LinkedList<Traced>,
     forbidden: Arc<HashSet<usize>>,
     no_vars: usize,
     field: &BigInt,
     substitution_log: &mut Option<SubstitutionJSON>,
-) -> (LinkedList<S>, LinkedList<C>) {
+    substitution_origins: &mut SubstitutionOrigins,
+    no_threads: usize,
+) -> (LinkedList<S>, LinkedList<Traced>) {
     use std::sync::mpsc;
     use threadpool::ThreadPool;
     let field = Arc::new(field.clone());
     let mut constraints = LinkedList::new();
     let mut substitutions = LinkedList::new();
-    let clusters = build_clusters(equalities, no_vars);
+    let mut clusters = build_clusters(equalities, no_vars);
     let (cluster_tx, simplified_rx) = mpsc::channel();
-    let pool = ThreadPool::new(num_cpus::get());
+    let pool = ThreadPool::new(no_threads);
     let no_clusters = Vec::len(&clusters);
     // println!("Clusters: {}", no_clusters);
     let mut single_clusters = 0;
     let mut id = 0;
     let mut aux_constraints = vec![LinkedList::new(); clusters.len()];
+    let mut aux_substitutions = vec![LinkedList::new(); clusters.len()];
+    let aux_origins: Vec<_> = clusters.iter_mut().map(|cluster| std::mem::take(&mut cluster.origins)).collect();
     for cluster in clusters {
         if Cluster::size(&cluster) == 1 {
-            let (mut subs, cons) = eq_cluster_simplification(cluster, &forbidden, &field);
+            let (subs, cons) = eq_cluster_simplification(cluster, &forbidden, &field);
             aux_constraints[id] = cons;
-            LinkedList::append(&mut substitutions, &mut subs);
+            aux_substitutions[id] = subs;
             single_clusters += 1;
         } else {
             let cluster_tx = cluster_tx.clone();
@@ -239,32 +322,40 @@ fn eq_simplification(
     // println!("{} clusters were of size 1", single_clusters);
     ThreadPool::join(&pool);
     for _ in 0..(no_clusters - single_clusters) {
-        let (id, (mut subs, cons)) = simplified_rx.recv().unwrap();
+        let (id, (subs, cons)) = simplified_rx.recv().unwrap();
         aux_constraints[id] = cons;
-        LinkedList::append(&mut substitutions, &mut subs);
+        aux_substitutions[id] = subs;
     }
     for id in 0..no_clusters {
-        LinkedList::append(&mut constraints, &mut aux_constraints[id]);
+        // the results of a cluster come from all the origins of the cluster
+        record_origins(&aux_substitutions[id], &aux_origins[id], substitution_origins);
+        let cluster_constraints = std::mem::take(&mut aux_constraints[id]);
+        constraints.extend(cluster_constraints.into_iter().map(|constraint| (constraint, aux_origins[id].clone())));
+        LinkedList::append(&mut substitutions, &mut aux_substitutions[id]);
     }
     log_substitutions(&substitutions, substitution_log);
     (substitutions, constraints)
 }
 
 fn constant_eq_simplification(
-    c_eq: LinkedList<C>,
+    c_eq: LinkedList<Traced>,
     forbidden: &HashSet<usize>,
     field: &BigInt,
     substitution_log: &mut Option<SubstitutionJSON>,
-) -> (LinkedList<S>, LinkedList<C>) {
+    substitution_origins: &mut SubstitutionOrigins,
+) -> (LinkedList<S>, LinkedList<Traced>) {
     let mut cons = LinkedList::new();
     let mut subs = LinkedList::new();
-    for constraint in c_eq {
+    for (constraint, origins) in c_eq {
         let mut signals: Vec<_> = C::take_cloned_signals_ordered(&constraint).iter().cloned().collect();
         let signal = signals.pop().unwrap();
         if HashSet::contains(&forbidden, &signal) {
-            LinkedList::push_back(&mut cons, constraint);
+            LinkedList::push_back(&mut cons, (constraint, origins));
         } else {
             let sub = C::clear_signal_from_linear(constraint, &signal, field);
+            if !origins.is_empty() {
+                substitution_origins.insert(signal, origins);
+            }
             LinkedList::push_back(&mut subs, sub);
         }
     }
@@ -272,14 +363,16 @@ fn constant_eq_simplification(
     (subs, cons)
 }
 
+// As in eq_simplification, the results are joined in the order of the clusters
 fn linear_simplification(
     log: &mut Option<SubstitutionJSON>,
-    linear: LinkedList<C>,
+    clusters: Vec<Cluster>,
     forbidden: Arc<HashSet<usize>>,
-    no_labels: usize,
     field: &BigInt,
-    use_old_heuristics: bool,
-) -> (LinkedList<S>, LinkedList<C>) {
+    heuristics: Heuristics,
+    substitution_origins: &mut SubstitutionOrigins,
+    no_threads: usize,
+) -> (LinkedList<S>, LinkedList<Traced>) {
     use circom_algebra::simplification_utils::full_simplification;
     use circom_algebra::simplification_utils::Config;
     use std::sync::mpsc;
@@ -288,26 +381,27 @@ fn linear_simplification(
     // println!("Cluster simplification");
     let mut cons = LinkedList::new();
     let mut substitutions = LinkedList::new();
-    let clusters = build_clusters(linear, no_labels);
     let (cluster_tx, simplified_rx) = mpsc::channel();
-    let pool = ThreadPool::new(num_cpus::get());
+    let pool = ThreadPool::new(no_threads);
     let no_clusters = Vec::len(&clusters);
     // println!("Clusters: {}", no_clusters);
+    let mut cluster_origins = Vec::with_capacity(no_clusters);
     let mut id = 0;
     for cluster in clusters {
+        cluster_origins.push(cluster.origins);
         let cluster_tx = cluster_tx.clone();
         let config = Config {
             field: field.clone(),
             constraints: cluster.constraints,
             forbidden: Arc::clone(&forbidden),
             num_signals: cluster.num_signals,
-            use_old_heuristics,
+            heuristics: heuristics.clone(),
         };
         let job = move || {
             // println!("cluster: {}", id);
             let result = full_simplification(config);
             // println!("End of cluster: {}", id);
-            cluster_tx.send(result).unwrap();
+            cluster_tx.send((id, result)).unwrap();
         };
         ThreadPool::execute(&pool, job);
         let _ = id;
@@ -315,16 +409,24 @@ fn linear_simplification(
     }
     ThreadPool::join(&pool);
 
+    let mut results: Vec<_> = (0..no_clusters).map(|_| None).collect();
     for _ in 0..no_clusters {
-        let mut result = simplified_rx.recv().unwrap();
+        let (id, result) = simplified_rx.recv().unwrap();
+        results[id] = Some(result);
+    }
+    for (result, origins) in results.into_iter().zip(&cluster_origins) {
+        let mut result = result.unwrap();
         log_substitutions(&result.substitutions, log);
-        LinkedList::append(&mut cons, &mut result.constraints);
+        record_origins(&result.substitutions, origins, substitution_origins);
+        cons.extend(result.constraints.into_iter().map(|constraint| (constraint, origins.clone())));
         LinkedList::append(&mut substitutions, &mut result.substitutions);
     }
     (substitutions, cons)
 }
 
 type SignalToConstraints = HashMap<usize, LinkedList<usize>>;
+// Occurrences of the signals in the non-linear constraints
+type Occurrences = HashMap<usize, usize>;
 fn build_non_linear_signal_map(non_linear: &ConstraintStorage) -> SignalToConstraints {
     let mut map = SignalToConstraints::new();
     for c_id in non_linear.get_ids() {
@@ -346,13 +448,17 @@ fn apply_substitution_to_map(
     storage: &mut ConstraintStorage,
     map: &mut SignalToConstraints,
     substitutions: &LinkedList<S>,
+    stored_origins: &mut HashMap<usize, Origins>,
+    substitution_origins: &SubstitutionOrigins,
     field: &BigInt,
-) -> LinkedList<C> {
+) -> LinkedList<Traced> {
     fn constraint_processing(
         storage: &mut ConstraintStorage,
         map: &mut SignalToConstraints,
         c_ids: &LinkedList<usize>,
         substitution: &S,
+        stored_origins: &mut HashMap<usize, Origins>,
+        origins: Option<&Origins>,
         field: &BigInt,
     ) -> LinkedList<usize> {
         let mut linear = LinkedList::new();
@@ -365,6 +471,9 @@ fn apply_substitution_to_map(
             if C::is_linear(&constraint) {
                 linear.push_back(c_id);
             }
+            if let Some(origins) = origins {
+                merge_origins(stored_origins.entry(c_id).or_default(), origins);
+            }
             storage.replace(c_id, constraint);
             for signal in &signals {
                 if let Some(list) = map.get_mut(&signal) {
@@ -382,22 +491,30 @@ fn apply_substitution_to_map(
     let mut linear_id = LinkedList::new();
     for substitution in substitutions {
         if let Some(c_ids) = map.get(substitution.from()).cloned() {
-            let mut new_linear = constraint_processing(storage, map, &c_ids, substitution, field);
+            let origins = substitution_origins.get(substitution.from());
+            let mut new_linear =
+                constraint_processing(storage, map, &c_ids, substitution, stored_origins, origins, field);
             linear_id.append(&mut new_linear);
         }
     }
+    // a constraint can become linear with several substitutions, it is taken once
     let mut linear = LinkedList::new();
     for c_id in linear_id {
         let constraint = storage.read_constraint(c_id).unwrap();
-        linear.push_back(constraint);
-        storage.replace(c_id, C::empty());
+        if !C::is_empty(&constraint) {
+            let origins = stored_origins.remove(&c_id).unwrap_or_default();
+            linear.push_back((constraint, origins));
+            storage.replace(c_id, C::empty());
+        }
     }
     linear
 }
 
+// The relevant signals are the ones in the non-linear constraints, with the
+// number of their occurrences
 fn build_relevant_set(
     mut iter: EncodingIterator,
-    relevant: &mut HashSet<usize>,
+    relevant: &mut Occurrences,
     renames: &SEncoded,
     deletes: &SEncoded,
 ) {
@@ -417,7 +534,7 @@ fn build_relevant_set(
         for signal in C::take_cloned_signals(&c) {
             let signal = unwrapped_signal(renames, signal).unwrap_or(signal);
             if !SEncoded::contains_key(deletes, &signal) {
-                HashSet::insert(relevant, signal);
+                *relevant.entry(signal).or_insert(0) += 1;
             }
         }
     }
@@ -428,10 +545,10 @@ fn build_relevant_set(
     }
 }
 
-fn remove_not_relevant(substitutions: &mut SEncoded, relevant: &HashSet<usize>) {
+fn remove_not_relevant(substitutions: &mut SEncoded, relevant: &Occurrences) {
     let signals: Vec<_> = substitutions.keys().cloned().collect();
     for signal in signals {
-        if !HashSet::contains(&relevant, &signal) {
+        if !relevant.contains_key(&signal) {
             SEncoded::remove(substitutions, &signal);
         }
     }
@@ -439,10 +556,9 @@ fn remove_not_relevant(substitutions: &mut SEncoded, relevant: &HashSet<usize>)
 
 
 // returns the constraints, the assignment of the witness and the number of inputs in the witness
-pub fn simplification(smp: &mut Simplifier) -> (ConstraintStorage, SignalMap, usize) {
+pub fn simplification(smp: &mut Simplifier, no_threads: usize) -> (ConstraintStorage, SignalMap, usize) {
     use super::non_linear_utils::obtain_and_simplify_non_linear;
     use circom_algebra::simplification_utils::build_encoded_fast_substitutions;
-    use circom_algebra::simplification_utils::fast_encoded_constraint_substitution;
     use std::time::SystemTime;
 
     let mut substitution_log =
@@ -453,13 +569,28 @@ pub fn simplification(smp: &mut Simplifier) -> (ConstraintStorage, SignalMap, us
         };
     let apply_linear = !smp.flag_s;
     let use_old_heuristics = smp.flag_old_heuristics;
+    let strategy = smp.strategy;
     let field = smp.field.clone();
     let forbidden = Arc::new(std::mem::replace(&mut smp.forbidden, HashSet::with_capacity(0)));
     let no_labels = Simplifier::no_labels(smp);
-    let equalities = std::mem::replace(&mut smp.equalities, LinkedList::new());
     let max_signal = smp.max_signal;
-    let mut cons_equalities = std::mem::replace(&mut smp.cons_equalities, LinkedList::new());
-    let mut linear = std::mem::replace(&mut smp.linear, LinkedList::new());
+    let mut provenance = smp.provenance.take();
+    let (equalities_origins, cons_equalities_origins, linear_origins, mut non_linear_origins) =
+        match &mut provenance {
+            Some(p) => (
+                std::mem::take(&mut p.equalities),
+                std::mem::take(&mut p.cons_equalities),
+                std::mem::take(&mut p.linear),
+                std::mem::take(&mut p.non_linear),
+            ),
+            None => Default::default(),
+        };
+    let equalities = trace(std::mem::take(&mut smp.equalities), equalities_origins);
+    let mut cons_equalities = trace(std::mem::take(&mut smp.cons_equalities), cons_equalities_origins);
+    let mut linear = trace(std::mem::take(&mut smp.linear), linear_origins);
+    // the origins of the constraints in the storage, by their ids
+    let mut stored_origins = HashMap::new();
+    let mut substitution_origins = SubstitutionOrigins::new();
     let mut deleted = HashSet::new();
     let mut lconst = LinkedList::new();
     let mut no_rounds = smp.no_rounds;
@@ -468,7 +599,7 @@ pub fn simplification(smp: &mut Simplifier) -> (ConstraintStorage, SignalMap, us
     let relevant_signals = {
         // println!("Creating first relevant set");
         let now = SystemTime::now();
-        let mut relevant = HashSet::new();
+        let mut relevant = Occurrences::new();
         let iter = EncodingIterator::new(&smp.dag_encoding);
         let s_sub = HashMap::with_capacity(0);
         let c_sub = HashMap::with_capacity(0);
@@ -487,19 +618,17 @@ pub fn simplification(smp: &mut Simplifier) -> (ConstraintStorage, SignalMap, us
             no_labels,
             &field,
             &mut substitution_log,
+            &mut substitution_origins,
+            no_threads,
         );
 
         LinkedList::append(&mut lconst, &mut cons);
         let mut substitutions = build_encoded_fast_substitutions(subs);
         for constraint in &mut linear {
-            if fast_encoded_constraint_substitution(constraint, &substitutions, &field){
-                C::fix_constraint(constraint, &field);
-            }
+            substitute(constraint, &substitutions, &substitution_origins, &field);
         }
         for constraint in &mut cons_equalities {
-            if fast_encoded_constraint_substitution(constraint, &substitutions, &field){
-                C::fix_constraint(constraint, &field);
-            }
+            substitute(constraint, &substitutions, &substitution_origins, &field);
         }
         for signal in substitutions.keys().cloned() {
             deleted.insert(signal);
@@ -513,14 +642,17 @@ pub fn simplification(smp: &mut Simplifier) -> (ConstraintStorage, SignalMap, us
     let cons_substitutions = {
         // println!("Start of constant assignment simplification");
         let now = SystemTime::now();
-        let (subs, mut cons) =
-            constant_eq_simplification(cons_equalities, &forbidden, &field, &mut substitution_log);
+        let (subs, mut cons) = constant_eq_simplification(
+            cons_equalities,
+            &forbidden,
+            &field,
+            &mut substitution_log,
+            &mut substitution_origins,
+        );
         LinkedList::append(&mut lconst, &mut cons);
         let substitutions = build_encoded_fast_substitutions(subs);
         for constraint in &mut linear {
-            if fast_encoded_constraint_substitution(constraint, &substitutions, &field){
-                C::fix_constraint(constraint, &field);
-            }
+            substitute(constraint, &substitutions, &substitution_origins, &field);
         }
         for signal in substitutions.keys().cloned() {
             deleted.insert(signal);
@@ -533,7 +665,7 @@ pub fn simplification(smp: &mut Simplifier) -> (ConstraintStorage, SignalMap, us
     let relevant_signals = {
         // println!("Start building relevant");
         let now = SystemTime::now();
-        let mut relevant = HashSet::new();
+        let mut relevant = Occurrences::new();
         let iter = EncodingIterator::new(&smp.dag_encoding);
         build_relevant_set(iter, &mut relevant, &single_substitutions, &cons_substitutions);
         let _dur = now.elapsed().unwrap().as_millis();
@@ -543,20 +675,22 @@ pub fn simplification(smp: &mut Simplifier) -> (ConstraintStorage, SignalMap, us
 
     let linear_substitutions = if apply_linear {
         let now = SystemTime::now();
+        let heuristics = strategy.heuristics(use_old_heuristics, || relevant_signals.clone());
         let (subs, mut cons) = linear_simplification(
             &mut substitution_log,
-            linear,
+            build_clusters(linear, no_labels),
             Arc::clone(&forbidden),
-            no_labels,
             &field,
-            use_old_heuristics,
+            heuristics,
+            &mut substitution_origins,
+            no_threads,
         );
         // println!("Building substitution map");
         let now0 = SystemTime::now();
         let mut only_relevant = LinkedList::new();
         for substitution in subs {
             deleted.insert(*substitution.from());
-            if relevant_signals.contains(substitution.from()) {
+            if relevant_signals.contains_key(substitution.from()) {
                 only_relevant.push_back(substitution);
             }
         }
@@ -567,9 +701,7 @@ pub fn simplification(smp: &mut Simplifier) -> (ConstraintStorage, SignalMap, us
         // println!("End of cluster simplification: {} ms", dur);
         LinkedList::append(&mut lconst, &mut cons);
         for constraint in &mut lconst {
-            if fast_encoded_constraint_substitution(constraint, &substitutions, &field){
-                C::fix_constraint(constraint, &field);
-            }
+            substitute(constraint, &substitutions, &substitution_origins, &field);
         }
         substitutions
     } else {
@@ -586,7 +718,15 @@ pub fn simplification(smp: &mut Simplifier) -> (ConstraintStorage, SignalMap, us
         LinkedList::push_back(&mut frames, linear_substitutions);
         let iter = EncodingIterator::new(&smp.dag_encoding);
         let mut storage = ConstraintStorage::new();
-        let with_linear = obtain_and_simplify_non_linear(iter, &mut storage, &frames, &field);
+        let with_linear = obtain_and_simplify_non_linear(
+            iter,
+            &mut storage,
+            &frames,
+            &field,
+            &mut non_linear_origins,
+            &mut stored_origins,
+            &substitution_origins,
+        );
         crate::state_utils::empty_encoding_constraints(&mut smp.dag_encoding);
         let _dur = now.elapsed().unwrap().as_millis();
         // println!("Storages built in {} ms", dur);
@@ -611,21 +751,30 @@ pub fn simplification(smp: &mut Simplifier) -> (ConstraintStorage, SignalMap, us
     while apply_round {
         let now = SystemTime::now();
         // println!("Number of linear constraints: {}", linear.len());
+        let heuristics = strategy.heuristics(use_old_heuristics, || {
+            non_linear_map.iter().map(|(signal, constraints)| (*signal, constraints.len())).collect()
+        });
         let (substitutions, mut constants) = linear_simplification(
             &mut substitution_log,
-            linear,
+            build_clusters(linear, no_labels),
             Arc::clone(&forbidden),
-            no_labels,
             &field,
-            use_old_heuristics,
+            heuristics,
+            &mut substitution_origins,
+            no_threads,
         );
 
         for sub in &substitutions {
             deleted.insert(*sub.from());
         }
         lconst.append(&mut constants);
-        for constraint in &mut lconst {
+        for (constraint, origins) in &mut lconst {
             for substitution in &substitutions {
+                if let Some(from_origins) = substitution_origins.get(substitution.from()) {
+                    if C::take_signals(constraint).contains(substitution.from()) {
+                        merge_origins(origins, from_origins);
+                    }
+                }
                 C::apply_substitution(constraint, substitution, &field);
             }
             C::fix_constraint(constraint, &field);
@@ -634,6 +783,8 @@ pub fn simplification(smp: &mut Simplifier) -> (ConstraintStorage, SignalMap, us
             &mut constraint_storage,
             &mut non_linear_map,
             &substitutions,
+            &mut stored_origins,
+            &substitution_origins,
             &field,
         );
         round_id += 1;
@@ -643,10 +794,13 @@ pub fn simplification(smp: &mut Simplifier) -> (ConstraintStorage, SignalMap, us
         // println!("Iteration no {} took {} ms", round_id, dur);
     }
 
-    for constraint in linear {
+    for (constraint, origins) in linear {
         if remove_unused {
             let signals =  C::take_cloned_signals(&constraint);
             let c_id = constraint_storage.add_constraint(constraint);
+            if !origins.is_empty() {
+                stored_origins.insert(c_id, origins);
+            }
             for signal in signals {
                 if let Some(list) = non_linear_map.get_mut(&signal) {
                     list.push_back(c_id);
@@ -658,14 +812,20 @@ pub fn simplification(smp: &mut Simplifier) -> (ConstraintStorage, SignalMap, us
             }
         }
         else{
-            constraint_storage.add_constraint(constraint);
+            let c_id = constraint_storage.add_constraint(constraint);
+            if !origins.is_empty() {
+                stored_origins.insert(c_id, origins);
+            }
         }
     }
-    for mut constraint in lconst {
+    for (mut constraint, origins) in lconst {
         if remove_unused{
             C::fix_constraint(&mut constraint, &field);
             let signals =  C::take_cloned_signals(&constraint);
             let c_id = constraint_storage.add_constraint(constraint);
+            if !origins.is_empty() {
+                stored_origins.insert(c_id, origins);
+            }
             for signal in signals {
                 if let Some(list) = non_linear_map.get_mut(&signal) {
                     list.push_back(c_id);
@@ -678,15 +838,22 @@ pub fn simplification(smp: &mut Simplifier) -> (ConstraintStorage, SignalMap, us
         }
         else{
             C::fix_constraint(&mut constraint, &field);
-            constraint_storage.add_constraint(constraint);
+            let c_id = constraint_storage.add_constraint(constraint);
+            if !origins.is_empty() {
+                stored_origins.insert(c_id, origins);
+            }
         }
     }
 
-    let erased = crate::non_linear_simplification::simplify(
-        &mut constraint_storage,
-        &forbidden,
-        &field
-    );
+    let erased = if apply_linear {
+        crate::non_linear_simplification::simplify(
+            &mut constraint_storage,
+            &forbidden,
+            &field
+        )
+    } else {
+        LinkedList::new()
+    };
 
     for signal in erased {
         deleted.insert(signal);
@@ -720,6 +887,11 @@ pub fn simplification(smp: &mut Simplifier) -> (ConstraintStorage, SignalMap, us
     }
 
 
+    if let Some(p) = &mut provenance {
+        p.stored = stored_origins;
+    }
+    smp.provenance = provenance;
+
     if let Some(w) = substitution_log {
         w.end().unwrap();
     }
@@ -729,3 +901,174 @@ pub fn simplification(smp: &mut Simplifier) -> (ConstraintStorage, SignalMap, us
 
 
 
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{ConstraintOrigins, DAGEncoding, EncodingNode};
+    use circom_algebra::num_traits::Zero;
+
+    const FIELD: &str = "2305843009213693951";
+    const MINUS_ONE: u64 = 2305843009213693950;
+
+    fn field() -> BigInt {
+        BigInt::parse_bytes(FIELD.as_bytes(), 10).unwrap()
+    }
+
+    fn linear(terms: &[(usize, u64)]) -> C {
+        let mut coefficients = HashMap::new();
+        coefficients.insert(C::constant_coefficient(), BigInt::zero());
+        for (signal, value) in terms {
+            coefficients.insert(*signal, BigInt::from(*value));
+        }
+        let mut constraint = A::transform_expression_to_constraint_form(A::Linear { coefficients }, &field()).unwrap();
+        C::fix_constraint(&mut constraint, &field());
+        constraint
+    }
+
+    fn quadratic(a: usize, b: usize, c: usize) -> C {
+        let expression = |signal: usize, value: i64| {
+            let mut expression = HashMap::new();
+            expression.insert(C::constant_coefficient(), BigInt::zero());
+            expression.insert(signal, BigInt::from(value));
+            expression
+        };
+        let arith = A::Quadratic { a: expression(a, 1), b: expression(b, 1), c: expression(c, 1) };
+        let mut constraint = A::transform_expression_to_constraint_form(arith, &field()).unwrap();
+        C::fix_constraint(&mut constraint, &field());
+        constraint
+    }
+
+    fn build_simplifier(
+        linear: LinkedList<C>,
+        equalities: LinkedList<C>,
+        non_linear: LinkedList<C>,
+        forbidden: HashSet<usize>,
+        max_signal: usize,
+    ) -> Simplifier {
+        let node = EncodingNode {
+            id: 0,
+            name: "Main()".to_string(),
+            parameters: Vec::new(),
+            signals: Vec::new(),
+            ordered_signals: Vec::new(),
+            non_linear,
+            is_custom_gate: false,
+            relations: Vec::new(),
+            lookup: None,
+        };
+        Simplifier {
+            field: field(),
+            dag_encoding: DAGEncoding { init: 0, no_constraints: 0, nodes: vec![node], adjacency: vec![Vec::new()] },
+            no_public_inputs: 0,
+            no_public_outputs: 0,
+            no_private_inputs: 10,
+            forbidden,
+            public_wires: HashMap::new(),
+            cons_equalities: LinkedList::new(),
+            equalities,
+            linear,
+            provenance: None,
+            max_signal,
+            no_rounds: usize::MAX,
+            parallel_flag: false,
+            no_threads: None,
+            flag_s: false,
+            flag_old_heuristics: false,
+            strategy: SimplificationStrategy::Wires,
+            port_substitution: true,
+            json_substitutions: String::new(),
+        }
+    }
+
+    fn terms(storage: &ConstraintStorage) -> usize {
+        let mut terms = 0;
+        for id in storage.get_ids() {
+            let c = storage.read_constraint(id).unwrap();
+            terms += c.a().len() + c.b().len() + c.c().len();
+        }
+        terms
+    }
+
+    #[test]
+    fn terms_strategy_keeps_the_signals_in_many_non_linear_constraints() {
+        // x = a + b + c and x * y[i] = out[i], a, b and c are 1, 2 and 3, x
+        // is 4, y[i] is 5 + i and out[i] is 15 + i
+        let linear_constraints = LinkedList::from([linear(&[(4, 1), (1, MINUS_ONE), (2, MINUS_ONE), (3, MINUS_ONE)])]);
+        let non_linear: LinkedList<C> = (0..10).map(|i| quadratic(4, 5 + i, 15 + i)).collect();
+        let forbidden: HashSet<usize> = (0..1).chain(5..25).collect();
+        let mut costs = Vec::new();
+        for strategy in [SimplificationStrategy::Wires, SimplificationStrategy::Terms] {
+            let mut smp = build_simplifier(linear_constraints.clone(), LinkedList::new(), non_linear.clone(), forbidden.clone(), 25);
+            smp.strategy = strategy;
+            smp.port_substitution = false;
+            let (storage, map, _) = simplification(&mut smp, 1);
+            costs.push((terms(&storage), map.contains_key(&4)));
+        }
+        // the wires strategy replaces x by a + b + c in every constraint
+        assert_eq!(costs[0], (50, false));
+        assert_eq!(costs[1], (30, true));
+    }
+
+    #[test]
+    fn merged_constraints_have_the_origins_of_all_of_them() {
+        // o1 = a + b, o2 = a - b, o3 = a + 2*b and a * a = sq, where o1, o2,
+        // o3 and sq are 1, 2, 3 and 6, a is 4 and b is 5
+        let linear_constraints = LinkedList::from([
+            linear(&[(1, 1), (4, MINUS_ONE), (5, MINUS_ONE)]),
+            linear(&[(2, 1), (4, MINUS_ONE), (5, 1)]),
+            linear(&[(3, 1), (4, MINUS_ONE), (5, MINUS_ONE - 1)]),
+        ]);
+        let non_linear = LinkedList::from([quadratic(4, 4, 6)]);
+        let forbidden: HashSet<usize> = vec![0, 1, 2, 3, 6].into_iter().collect();
+        let mut smp = build_simplifier(linear_constraints, LinkedList::new(), non_linear, forbidden, 7);
+        smp.port_substitution = false;
+        smp.provenance = Some(ConstraintOrigins {
+            linear: LinkedList::from([vec![0], vec![1], vec![2]]),
+            non_linear: LinkedList::from([3]),
+            ..ConstraintOrigins::default()
+        });
+        let (storage, _, _) = simplification(&mut smp, 1);
+        let stored = smp.provenance.unwrap().stored;
+        let mut origins: Vec<_> = storage
+            .get_ids()
+            .into_iter()
+            .map(|id| {
+                let mut origins = stored[&id].clone();
+                origins.sort();
+                origins
+            })
+            .collect();
+        origins.sort();
+        // a and b are removed, the constraint left over o1, o2 and o3 comes
+        // from the three linear ones, and so does the substitution of a in
+        // the non-linear one
+        assert_eq!(origins, vec![vec![0, 1, 2], vec![0, 1, 2, 3]]);
+    }
+
+    #[test]
+    fn substituted_constraints_have_the_origins_of_the_substitutions() {
+        // x = y, z = y + 1 and z * z = sq, where x, y, z and sq are 2, 3, 4
+        // and 6: y is replaced by x, then z by x + 1 and the non-linear
+        // constraint becomes (x + 1) * (x + 1) = sq
+        let equalities = LinkedList::from([linear(&[(2, 1), (3, MINUS_ONE)])]);
+        let linear_constraints = LinkedList::from([linear(&[(0, 1), (3, 1), (4, MINUS_ONE)])]);
+        let non_linear = LinkedList::from([quadratic(4, 4, 6)]);
+        let forbidden: HashSet<usize> = vec![0, 6].into_iter().collect();
+        let mut smp = build_simplifier(linear_constraints, equalities, non_linear, forbidden, 7);
+        smp.port_substitution = false;
+        smp.provenance = Some(ConstraintOrigins {
+            equalities: LinkedList::from([vec![0]]),
+            linear: LinkedList::from([vec![1]]),
+            non_linear: LinkedList::from([2]),
+            ..ConstraintOrigins::default()
+        });
+        let (storage, _, _) = simplification(&mut smp, 1);
+        let stored = smp.provenance.unwrap().stored;
+        let ids = storage.get_ids();
+        assert_eq!(ids.len(), 1);
+        let constraint = storage.read_constraint(ids[0]).unwrap();
+        assert!(!C::take_signals(&constraint).contains(&4));
+        assert_eq!(stored[&ids[0]], vec![0, 1, 2]);
+    }
+}
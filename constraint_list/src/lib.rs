@@ -2,8 +2,10 @@ use std::collections::{HashMap, HashSet, LinkedList};
 
 use circom_algebra::constraint_storage::ConstraintStorage;
 use circom_algebra::num_bigint::BigInt;
+use circom_algebra::num_traits::Zero;
 use constraint_writers::debug_writer::DebugWriter;
-use constraint_writers::ConstraintExporter;
+use constraint_writers::r1cs_writer::{GateRelation, LookupTable};
+use constraint_writers::{ConstraintExporter, ConstraintOrigin, Provenance};
 
 mod constraint_simplification;
 mod json_porting;
@@ -13,12 +15,20 @@ mod state_utils;
 mod sym_porting;
 mod non_linear_simplification;
 
+pub use constraint_simplification::SimplificationStrategy;
+
 type C = circom_algebra::algebra::Constraint<usize>;
 type S = circom_algebra::algebra::Substitution<usize>;
 type A = circom_algebra::algebra::ArithmeticExpression<usize>;
 type SignalMap = HashMap<usize, usize>;
 type SEncoded = HashMap<usize, A>;
 type SFrames = LinkedList<SEncoded>;
+// positions of the origins of a constraint
+type Origins = Vec<usize>;
+// a constraint together with its origins, empty without provenance
+type Traced = (C, Origins);
+// origins of the substitutions, given by the signal they remove
+type SubstitutionOrigins = HashMap<usize, Origins>;
 
 pub struct SignalInfo {
     pub name: String,
@@ -32,6 +42,8 @@ pub struct EncodingNode {
     pub ordered_signals: Vec<usize>,
     pub non_linear: LinkedList<C>,
     pub is_custom_gate: bool,
+    pub relations: Vec<GateRelation>,
+    pub lookup: Option<LookupTable>,
 }
 
 pub struct EncodingEdge {
@@ -107,6 +119,23 @@ impl<'a> EncodingIterator<'a> {
     }
 }
 
+// Origins of the constraints of the simplification. Each list follows the
+// order of the constraints it describes, the non-linear constraints go in the
+// order of the iterator of the encoding. The simplification pairs every
+// constraint with its origins: the constraints merged by the simplification
+// have the origins of all of them, and the ones changed by a substitution
+// take the origins of the constraints the substitution comes from.
+#[derive(Default)]
+pub struct ConstraintOrigins {
+    pub origins: Vec<ConstraintOrigin>,
+    pub linear: LinkedList<Origins>,
+    pub equalities: LinkedList<Origins>,
+    pub cons_equalities: LinkedList<Origins>,
+    pub non_linear: LinkedList<usize>,
+    // origins of the constraints in the storage of the simplification
+    pub stored: HashMap<usize, Origins>,
+}
+
 pub struct Simplifier {
     pub field: BigInt,
     pub dag_encoding: DAGEncoding,
@@ -114,22 +143,35 @@ pub struct Simplifier {
     pub no_public_outputs: usize,
     pub no_private_inputs: usize,
     pub forbidden: HashSet<usize>,
+    // wires of the public signals that are not in their default position
+    pub public_wires: HashMap<usize, usize>,
     pub cons_equalities: LinkedList<C>,
     pub equalities: LinkedList<C>,
     pub linear: LinkedList<C>,
+    // only when the provenance of the constraints is tracked
+    pub provenance: Option<ConstraintOrigins>,
     //  Signals in [witness_len, Vec::len(&signal_map)) are the ones deleted
     pub max_signal: usize,
     // Flags
     pub no_rounds: usize,
     pub parallel_flag: bool,
+    // threads of the simplification, one per core when it is None
+    pub no_threads: Option<usize>,
     pub flag_s: bool,
     pub flag_old_heuristics: bool,
+    pub strategy: SimplificationStrategy,
     pub port_substitution: bool,
     pub json_substitutions: String,
 }
 impl Simplifier {
     pub fn simplify_constraints(mut self) -> ConstraintList {
-        let (portable, map, private_inputs_witness) = constraint_simplification::simplification(&mut self);
+        let no_threads = self.no_threads.unwrap_or_else(num_cpus::get);
+        let (portable, mut map, private_inputs_witness) =
+            constraint_simplification::simplification(&mut self, no_threads);
+        // the public signals are never removed, so they keep their wires
+        for (signal, wire) in &self.public_wires {
+            map.insert(*signal, *wire);
+        }
         ConstraintList {
             field: self.field,
             dag_encoding: self.dag_encoding,
@@ -140,6 +182,7 @@ impl Simplifier {
             no_labels: self.max_signal,
             constraints: portable,
             signal_map: map,
+            provenance: self.provenance,
         }
     }
 
@@ -163,11 +206,26 @@ pub struct ConstraintList {
     pub no_labels: usize,
     //  Signals in [witness_len, Vec::len(&signal_map)) are the ones deleted
     pub signal_map: SignalMap,
+    pub provenance: Option<ConstraintOrigins>,
 }
 
 impl ConstraintExporter for ConstraintList {
-    fn r1cs(&self, out: &str, custom_gates: bool) -> Result<(), ()> {
-        r1cs_porting::port_r1cs(self, out, custom_gates)
+    fn r1cs(&self, out: &str, custom_gates: bool, provenance: Option<&Provenance>) -> Result<(), ()> {
+        r1cs_porting::port_r1cs(self, out, custom_gates, provenance).map_err(|_err| {})
+    }
+
+    fn provenance(&self) -> Provenance {
+        let mut provenance = Provenance { origins: Vec::new(), constraints: Vec::new() };
+        if let Some(tracked) = &self.provenance {
+            provenance.origins = tracked.origins.clone();
+            for c_id in self.constraints.get_ids() {
+                let mut origins = tracked.stored.get(&c_id).cloned().unwrap_or_default();
+                origins.sort();
+                origins.dedup();
+                provenance.constraints.push(origins);
+            }
+        }
+        provenance
     }
 
     fn json_constraints(&self, writer: &DebugWriter) -> Result<(), ()> {
@@ -179,7 +237,51 @@ impl ConstraintExporter for ConstraintList {
     }
 }
 
+// The costs of the simplified constraints, the terms are the coefficients
+// different from 0 in A, B and C
+pub struct SimplificationCosts {
+    pub no_non_linear: usize,
+    pub no_linear: usize,
+    pub no_wires: usize,
+    pub no_terms: usize,
+    pub no_non_linear_terms: usize,
+}
+
+impl SimplificationCosts {
+    pub fn print(&self, strategy: SimplificationStrategy) {
+        println!("simplification strategy: {}", strategy.name());
+        println!("constraints: {} ({} non-linear, {} linear)", self.no_non_linear + self.no_linear, self.no_non_linear, self.no_linear);
+        println!("terms: {} ({} in non-linear constraints)", self.no_terms, self.no_non_linear_terms);
+        println!("wires: {}", self.no_wires);
+    }
+}
+
 impl ConstraintList {
+    pub fn costs(&self) -> SimplificationCosts {
+        let mut costs = SimplificationCosts {
+            no_non_linear: 0,
+            no_linear: 0,
+            no_wires: self.no_wires(),
+            no_terms: 0,
+            no_non_linear_terms: 0,
+        };
+        for id in self.constraints.get_ids() {
+            let constraint = self.constraints.read_constraint(id).unwrap();
+            let terms: usize = [constraint.a(), constraint.b(), constraint.c()]
+                .iter()
+                .map(|expression| expression.values().filter(|value| !value.is_zero()).count())
+                .sum();
+            costs.no_terms += terms;
+            if C::is_linear(&constraint) {
+                costs.no_linear += 1;
+            } else {
+                costs.no_non_linear += 1;
+                costs.no_non_linear_terms += terms;
+            }
+        }
+        costs
+    }
+
     pub fn get_witness(&self) -> &SignalMap {
         &self.signal_map
     }
@@ -1,12 +1,325 @@
+use super::{A, C};
+use circom_algebra::modular_arithmetic;
 use circom_algebra::num_bigint::BigInt;
-use circom_algebra::constraint_storage::ConstraintStorage;
-use std::collections::{HashSet, LinkedList};
+use circom_algebra::num_traits::Zero;
+use circom_algebra::constraint_storage::{ConstraintID, ConstraintStorage};
+use std::collections::{BTreeMap, HashMap, HashSet, LinkedList};
 
+// A constraint A*B - C = 0 is seen as a polynomial of degree two, a linear
+// combination of monomials. A monomial (i, j) with i <= j is the product of
+// the signals i and j, where the signal 0 is the constant 1, so (0, j) is the
+// signal j alone and (0, 0) is the constant term.
+type Monomial = (usize, usize);
+type Polynomial = BTreeMap<Monomial, BigInt>;
 
+fn is_quadratic(monomial: &Monomial) -> bool {
+    monomial.0 != C::constant_coefficient()
+}
+
+fn expand(constraint: &C, field: &BigInt) -> Polynomial {
+    let mut polynomial = Polynomial::new();
+    let mut add = |monomial: Monomial, value: BigInt| {
+        let entry = polynomial.entry(monomial).or_insert_with(BigInt::zero);
+        *entry = modular_arithmetic::add(entry, &value, field);
+    };
+    for (i, a) in constraint.a() {
+        for (j, b) in constraint.b() {
+            let monomial = if i <= j { (*i, *j) } else { (*j, *i) };
+            add(monomial, modular_arithmetic::mul(a, b, field));
+        }
+    }
+    for (k, c) in constraint.c() {
+        add((C::constant_coefficient(), *k), modular_arithmetic::prefix_sub(c, field));
+    }
+    polynomial.retain(|_, value| !value.is_zero());
+    polynomial
+}
+
+// Rows in echelon form indexed by their leading monomial, the greatest one,
+// whose coefficient is 1. The quadratic monomials are greater than the linear
+// ones, so a row is linear once its leading monomial is.
+struct Echelon {
+    field: BigInt,
+    rows: HashMap<Monomial, Polynomial>,
+    // monomials stored in all the rows
+    size: usize,
+}
+
+impl Echelon {
+    // Reduces the polynomial with the rows until its leading monomial is not
+    // the leading monomial of a row. It reduces to zero when it is a linear
+    // combination of the rows.
+    fn reduce(&self, mut polynomial: Polynomial) -> Polynomial {
+        while let Some((leading, coefficient)) = polynomial.iter().next_back() {
+            let row = match self.rows.get(leading) {
+                Some(row) => row,
+                None => break,
+            };
+            let factor = coefficient.clone();
+            for (monomial, value) in row {
+                let entry = polynomial.entry(*monomial).or_insert_with(BigInt::zero);
+                let scaled = modular_arithmetic::mul(&factor, value, &self.field);
+                *entry = modular_arithmetic::sub(entry, &scaled, &self.field);
+            }
+            polynomial.retain(|_, value| !value.is_zero());
+        }
+        polynomial
+    }
+
+    fn insert(&mut self, mut polynomial: Polynomial) {
+        let (leading, coefficient) = polynomial.iter().next_back().unwrap();
+        let leading = *leading;
+        // the leading coefficient is not zero, so it has an inverse
+        let inverse = modular_arithmetic::div(&BigInt::from(1), coefficient, &self.field).ok().unwrap();
+        for value in polynomial.values_mut() {
+            *value = modular_arithmetic::mul(value, &inverse, &self.field);
+        }
+        self.size += polynomial.len();
+        self.rows.insert(leading, polynomial);
+    }
+}
+
+fn into_linear_constraint(polynomial: &Polynomial, field: &BigInt) -> C {
+    let mut coefficients = HashMap::new();
+    // the expressions always have a constant coefficient, maybe 0
+    coefficients.insert(C::constant_coefficient(), BigInt::zero());
+    for ((_, signal), value) in polynomial {
+        coefficients.insert(*signal, value.clone());
+    }
+    let mut constraint = A::transform_expression_to_constraint_form(A::Linear { coefficients }, field).unwrap();
+    C::fix_constraint(&mut constraint, field);
+    constraint
+}
+
+// Removes the constraints that are linear combinations of the others, which
+// includes the constraints that are equal up to a scalar factor, and replaces
+// the non-linear constraints that are a linear constraint plus a combination
+// of the others by that linear constraint. The removed constraints are left
+// empty. The result are the signals that no longer appear in any constraint,
+// the forbidden signals are never removed.
 pub fn simplify(
-    _storage: &mut ConstraintStorage,
-    _forbidden: &HashSet<usize>,
-    _field: &BigInt
+    storage: &mut ConstraintStorage,
+    forbidden: &HashSet<usize>,
+    field: &BigInt
+) -> LinkedList<usize> {
+    eliminate(storage, forbidden, field, MAX_MONOMIALS)
+}
+
+// The rows fill in with the products of the signals of the constraints they
+// are reduced with, which for large circuits can take more memory than the
+// rest of the compilation. Once the rows hold this many monomials the
+// elimination stops and the remaining constraints are left as they are.
+const MAX_MONOMIALS: usize = 1 << 22;
+
+fn eliminate(
+    storage: &mut ConstraintStorage,
+    forbidden: &HashSet<usize>,
+    field: &BigInt,
+    max_monomials: usize,
 ) -> LinkedList<usize> {
-    LinkedList::new()
+    let mut echelon = Echelon { field: field.clone(), rows: HashMap::new(), size: 0 };
+    let mut before = HashSet::new();
+    let mut after = HashSet::new();
+    let mut changes: Vec<(ConstraintID, C)> = Vec::new();
+    // the linear constraints go first, so the non-linear ones are reduced by them
+    let mut ids: Vec<(bool, ConstraintID)> = Vec::new();
+    for id in storage.get_ids() {
+        let constraint = storage.read_constraint(id).unwrap();
+        ids.push((!C::is_linear(&constraint), id));
+    }
+    ids.sort();
+    for (_, id) in ids {
+        let constraint = storage.read_constraint(id).unwrap();
+        let signals = constraint.take_cloned_signals();
+        before.extend(signals.iter().cloned());
+        if echelon.size > max_monomials {
+            after.extend(signals);
+            continue;
+        }
+        let polynomial = expand(&constraint, field);
+        let was_quadratic = polynomial.keys().any(is_quadratic);
+        let reduced = echelon.reduce(polynomial);
+        if reduced.is_empty() {
+            changes.push((id, C::empty()));
+            continue;
+        }
+        // a constant different from 0 has no solution, the constraint is kept
+        let only_constant = reduced.keys().all(|monomial| *monomial == (0, 0));
+        if was_quadratic && !only_constant && !reduced.keys().any(is_quadratic) {
+            let linear = into_linear_constraint(&reduced, field);
+            after.extend(linear.take_cloned_signals());
+            changes.push((id, linear));
+        } else {
+            after.extend(signals);
+        }
+        echelon.insert(reduced);
+    }
+    for (id, constraint) in changes {
+        storage.replace(id, constraint);
+    }
+    let mut erased: Vec<usize> = before
+        .into_iter()
+        .filter(|signal| *signal != C::constant_coefficient())
+        .filter(|signal| !after.contains(signal) && !forbidden.contains(signal))
+        .collect();
+    erased.sort_unstable();
+    erased.into_iter().collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const FIELD: &str = "257";
+
+    fn field() -> BigInt {
+        BigInt::parse_bytes(FIELD.as_bytes(), 10).unwrap()
+    }
+
+    fn expression(terms: &[(usize, i64)]) -> HashMap<usize, BigInt> {
+        let mut expression = HashMap::new();
+        expression.insert(C::constant_coefficient(), BigInt::zero());
+        for (signal, value) in terms {
+            expression.insert(*signal, modular_arithmetic::add(&BigInt::from(*value), &BigInt::zero(), &field()));
+        }
+        expression
+    }
+
+    // (a) * (b) = c, the expression a * b - c is 0
+    fn quadratic(a: &[(usize, i64)], b: &[(usize, i64)], c: &[(usize, i64)]) -> C {
+        let minus_c: Vec<_> = c.iter().map(|(signal, value)| (*signal, -value)).collect();
+        let arith = A::Quadratic { a: expression(a), b: expression(b), c: expression(&minus_c) };
+        let mut constraint = A::transform_expression_to_constraint_form(arith, &field()).unwrap();
+        C::fix_constraint(&mut constraint, &field());
+        constraint
+    }
+
+    fn holds(constraint: &C, witness: &[i64]) -> bool {
+        let evaluate = |expression: &HashMap<usize, BigInt>| {
+            let mut result = BigInt::zero();
+            for (signal, value) in expression {
+                let term = modular_arithmetic::mul(value, &BigInt::from(witness[*signal]), &field());
+                result = modular_arithmetic::add(&result, &term, &field());
+            }
+            result
+        };
+        let ab = modular_arithmetic::mul(&evaluate(constraint.a()), &evaluate(constraint.b()), &field());
+        modular_arithmetic::sub(&ab, &evaluate(constraint.c()), &field()).is_zero()
+    }
+
+    fn simplified(constraints: Vec<C>, forbidden: &[usize]) -> (Vec<C>, LinkedList<usize>) {
+        simplified_up_to(constraints, forbidden, MAX_MONOMIALS)
+    }
+
+    fn simplified_up_to(constraints: Vec<C>, forbidden: &[usize], max_monomials: usize) -> (Vec<C>, LinkedList<usize>) {
+        let mut storage = ConstraintStorage::new();
+        for constraint in constraints {
+            storage.add_constraint(constraint);
+        }
+        let forbidden = forbidden.iter().cloned().collect();
+        let erased = eliminate(&mut storage, &forbidden, &field(), max_monomials);
+        storage.extract_with(&|c| C::is_empty(c));
+        let remaining = storage.get_ids().into_iter().map(|id| storage.read_constraint(id).unwrap()).collect();
+        (remaining, erased)
+    }
+
+    #[test]
+    fn removes_constraints_equal_up_to_a_factor() {
+        // x1 * x2 = x3 and 2*x1 * 3*x2 = 6*x3
+        let constraints = vec![
+            quadratic(&[(1, 1)], &[(2, 1)], &[(3, 1)]),
+            quadratic(&[(1, 2)], &[(2, 3)], &[(3, 6)]),
+        ];
+        let witness = [1, 4, 5, 20];
+        assert!(constraints.iter().all(|c| holds(c, &witness)));
+        let (remaining, erased) = simplified(constraints, &[]);
+        assert_eq!(remaining.len(), 1);
+        assert!(remaining.iter().all(|c| holds(c, &witness)));
+        assert!(!holds(&remaining[0], &[1, 4, 5, 21]));
+        assert!(erased.is_empty());
+    }
+
+    #[test]
+    fn removes_linear_combinations_of_other_constraints() {
+        // x1 * x2 = x3, x1 * x4 = x5 and x1 * (x2 + x4) = x3 + x5
+        let constraints = vec![
+            quadratic(&[(1, 1)], &[(2, 1)], &[(3, 1)]),
+            quadratic(&[(1, 1)], &[(4, 1)], &[(5, 1)]),
+            quadratic(&[(1, 1)], &[(2, 1), (4, 1)], &[(3, 1), (5, 1)]),
+        ];
+        let witness = [1, 3, 5, 15, 7, 21];
+        let (remaining, _) = simplified(constraints, &[]);
+        assert_eq!(remaining.len(), 2);
+        assert!(remaining.iter().all(|c| holds(c, &witness)));
+    }
+
+    #[test]
+    fn replaces_quadratic_constraints_that_are_linear() {
+        // x1 * x2 = x3, x1 * x2 = x4 + 1 and 2*x1 * x2 = x5, the last two are
+        // x4 + 1 = x3 and x5 = 2*x3
+        let constraints = vec![
+            quadratic(&[(1, 1)], &[(2, 1)], &[(3, 1)]),
+            quadratic(&[(1, 1)], &[(2, 1)], &[(4, 1), (0, 1)]),
+            quadratic(&[(1, 2)], &[(2, 1)], &[(5, 1)]),
+        ];
+        let witness = [1, 2, 3, 6, 5, 12];
+        let (remaining, _) = simplified(constraints, &[]);
+        assert_eq!(remaining.len(), 3);
+        assert_eq!(remaining.iter().filter(|c| C::is_linear(c)).count(), 2);
+        assert!(remaining.iter().all(|c| holds(c, &witness)));
+        assert!(!remaining.iter().all(|c| holds(c, &[1, 2, 3, 6, 6, 12])));
+        assert!(!remaining.iter().all(|c| holds(c, &[1, 2, 3, 6, 5, 13])));
+    }
+
+    #[test]
+    fn linear_constraints_keep_their_constant_term() {
+        // x1 * x2 = x3 + 5, x1 * x2 = x4 and x1 * x2 = 3*x5, the last two are
+        // x4 = x3 + 5 and 3*x5 = x3 + 5, and x1 * x2 = x3 + x6 + 5, which is
+        // x6 = 0 without any constant term
+        let constraints = vec![
+            quadratic(&[(1, 1)], &[(2, 1)], &[(3, 1), (0, 5)]),
+            quadratic(&[(1, 1)], &[(2, 1)], &[(4, 1)]),
+            quadratic(&[(1, 1)], &[(2, 1)], &[(5, 3)]),
+            quadratic(&[(1, 1)], &[(2, 1)], &[(3, 1), (6, 1), (0, 5)]),
+        ];
+        let witness = [1, 2, 4, 3, 8, 174, 0];
+        assert!(constraints.iter().all(|c| holds(c, &witness)));
+        let (remaining, _) = simplified(constraints, &[]);
+        assert_eq!(remaining.len(), 4);
+        assert_eq!(remaining.iter().filter(|c| C::is_linear(c)).count(), 3);
+        assert!(remaining.iter().all(|c| holds(c, &witness)));
+        assert!(!remaining.iter().all(|c| holds(c, &[1, 2, 4, 3, 3, 174, 0])));
+        assert!(!remaining.iter().all(|c| holds(c, &[1, 2, 4, 3, 8, 1, 0])));
+        assert!(!remaining.iter().all(|c| holds(c, &[1, 2, 4, 3, 8, 174, 1])));
+    }
+
+    #[test]
+    fn stops_when_the_rows_reach_the_maximum_of_monomials() {
+        // x1 * x2 = x3, 2*x1 * 3*x2 = 6*x3 and x1 * x2 = x4, the first row
+        // already has the 2 monomials x1*x2 and x3
+        let constraints = || vec![
+            quadratic(&[(1, 1)], &[(2, 1)], &[(3, 1)]),
+            quadratic(&[(1, 2)], &[(2, 3)], &[(3, 6)]),
+            quadratic(&[(1, 1)], &[(2, 1)], &[(4, 1)]),
+        ];
+        let (remaining, erased) = simplified_up_to(constraints(), &[], 1);
+        assert_eq!(remaining.len(), 3);
+        assert_eq!(remaining.iter().filter(|c| C::is_linear(c)).count(), 0);
+        assert!(erased.is_empty());
+        let (remaining, _) = simplified_up_to(constraints(), &[], 2);
+        assert_eq!(remaining.len(), 2);
+        assert_eq!(remaining.iter().filter(|c| C::is_linear(c)).count(), 1);
+    }
+
+    #[test]
+    fn keeps_independent_constraints() {
+        let constraints = vec![
+            quadratic(&[(1, 1)], &[(1, 1)], &[(2, 1)]),
+            quadratic(&[(2, 1)], &[(1, 1)], &[(3, 1)]),
+            quadratic(&[], &[], &[(3, 1), (4, -1)]),
+        ];
+        let (remaining, erased) = simplified(constraints, &[1, 4]);
+        assert_eq!(remaining.len(), 3);
+        assert!(erased.is_empty());
+    }
 }
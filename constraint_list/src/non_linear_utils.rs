@@ -1,30 +1,42 @@
-use super::{ConstraintStorage, EncodingIterator, SFrames, C};
+use super::constraint_simplification::substitute;
+use super::{ConstraintStorage, EncodingIterator, Origins, SFrames, SubstitutionOrigins, Traced, C};
 use circom_algebra::num_bigint::BigInt;
-use circom_algebra::simplification_utils::fast_encoded_constraint_substitution;
-use std::collections::LinkedList;
+use std::collections::{HashMap, LinkedList};
 
+// The origins of the non-linear constraints are taken in the order of the
+// iterator, the ones of the constraints that stay in the storage are given by
+// their ids
 pub fn obtain_and_simplify_non_linear(
     mut iter: EncodingIterator,
     storage: &mut ConstraintStorage,
     frames: &SFrames,
     field: &BigInt,
-) -> LinkedList<C> {
+    origins: &mut LinkedList<usize>,
+    stored_origins: &mut HashMap<usize, Origins>,
+    substitution_origins: &SubstitutionOrigins,
+) -> LinkedList<Traced> {
     let mut linear = LinkedList::new();
     let (_, non_linear) = EncodingIterator::take(&mut iter);
-    for mut constraint in non_linear {
+    for constraint in non_linear {
+        let mut constraint = (constraint, origins.pop_front().into_iter().collect());
         for frame in frames {
-            fast_encoded_constraint_substitution(&mut constraint, frame, &field);
+            substitute(&mut constraint, frame, substitution_origins, field);
         }
-        C::fix_constraint(&mut constraint, &field);
-        if C::is_linear(&constraint) {
+        C::fix_constraint(&mut constraint.0, &field);
+        if C::is_linear(&constraint.0) {
             linear.push_back(constraint);
         } else {
-            storage.add_constraint(constraint);
+            let (constraint, constraint_origins) = constraint;
+            let c_id = storage.add_constraint(constraint);
+            if !constraint_origins.is_empty() {
+                stored_origins.insert(c_id, constraint_origins);
+            }
         }
     }
     for edge in EncodingIterator::edges(&iter) {
         let next = EncodingIterator::next(&iter, edge);
-        let mut linear_in_next = obtain_and_simplify_non_linear(next, storage, frames, field);
+        let mut linear_in_next =
+            obtain_and_simplify_non_linear(next, storage, frames, field, origins, stored_origins, substitution_origins);
         linear.append(&mut linear_in_next);
     }
     linear
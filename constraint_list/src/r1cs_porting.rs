@@ -1,7 +1,9 @@
 use super::{ConstraintList, C, EncodingIterator, SignalMap};
-use constraint_writers::r1cs_writer::{ConstraintSection, CustomGatesAppliedData, HeaderData, R1CSWriter, SignalSection};
+use constraint_writers::r1cs_writer::{ConstraintSection, CustomGatesAppliedData, HeaderData, LookupsData, R1CSWriter, SignalSection};
+use constraint_writers::Provenance;
+use std::io;
 
-pub fn port_r1cs(list: &ConstraintList, output: &str, custom_gates: bool) -> Result<(), ()> {
+pub fn port_r1cs(list: &ConstraintList, output: &str, custom_gates: bool, provenance: Option<&Provenance>) -> io::Result<()> {
     use constraint_writers::log_writer::Log;
     let field_size = if list.field.bits() % 64 == 0 {
         list.field.bits() / 8
@@ -16,7 +18,9 @@ pub fn port_r1cs(list: &ConstraintList, output: &str, custom_gates: bool) -> Res
     log.no_public_inputs = list.no_public_inputs;
     log.no_public_outputs = list.no_public_outputs;
 
-    let r1cs = R1CSWriter::new(output.to_string(), field_size, custom_gates)?;
+    let relations = custom_gates && list.dag_encoding.nodes.iter().any(|node| !node.relations.is_empty());
+    let lookups = list.dag_encoding.nodes.iter().any(|node| node.lookup.is_some());
+    let r1cs = R1CSWriter::new(output.to_string(), field_size, custom_gates, relations, lookups, provenance.is_some())?;
     let mut constraint_section = R1CSWriter::start_constraints_section(r1cs)?;
     let mut written = 0;
 
@@ -51,12 +55,13 @@ pub fn port_r1cs(list: &ConstraintList, output: &str, custom_gates: bool) -> Res
         SignalSection::write_signal_usize(&mut signal_section, id)?;
     }
     let r1cs = signal_section.end_section()?;
-    if !custom_gates {
-	R1CSWriter::finish_writing(r1cs)?;
+    let r1cs = if !custom_gates {
+        r1cs
     } else {
         let mut custom_gates_used_section = R1CSWriter::start_custom_gates_used_section(r1cs)?;
-        let (usage_data, occurring_order) = {
+        let (usage_data, relations_data, occurring_order) = {
             let mut usage_data = vec![];
+            let mut relations_data = vec![];
             let mut occurring_order = vec![];
             for node in &list.dag_encoding.nodes {
                 if node.is_custom_gate {
@@ -64,9 +69,10 @@ pub fn port_r1cs(list: &ConstraintList, output: &str, custom_gates: bool) -> Res
                     occurring_order.push(name.clone());
                     while name.pop() != Some('(') {};
                     usage_data.push((name, node.parameters.clone()));
+                    relations_data.push(node.relations.clone());
                 }
             }
-            (usage_data, occurring_order)
+            (usage_data, relations_data, occurring_order)
         };
         custom_gates_used_section.write_custom_gates_usages(usage_data)?;
         let r1cs = custom_gates_used_section.end_section()?;
@@ -117,8 +123,55 @@ pub fn port_r1cs(list: &ConstraintList, output: &str, custom_gates: bool) -> Res
         };
         custom_gates_applied_section.write_custom_gates_applications(application_data)?;
         let r1cs = custom_gates_applied_section.end_section()?;
-	R1CSWriter::finish_writing(r1cs)?;
-    }
+        if relations {
+            let mut custom_gates_relations_section =
+                R1CSWriter::start_custom_gates_relations_section(r1cs)?;
+            custom_gates_relations_section.write_custom_gates_relations(relations_data)?;
+            custom_gates_relations_section.end_section()?
+        } else {
+            r1cs
+        }
+    };
+    let r1cs = if lookups {
+        let mut lookups_data = (vec![], vec![]);
+        let iterator = EncodingIterator::new(&list.dag_encoding);
+        write_lookups_data(iterator, &list.signal_map, &mut lookups_data);
+        let mut lookups_section = R1CSWriter::start_lookups_section(r1cs)?;
+        lookups_section.write_lookups(lookups_data)?;
+        lookups_section.end_section()?
+    } else {
+        r1cs
+    };
+    let r1cs = if let Some(provenance) = provenance {
+        let mut provenance_section = R1CSWriter::start_provenance_section(r1cs)?;
+        provenance_section.write_provenance(provenance)?;
+        provenance_section.end_section()?
+    } else {
+        r1cs
+    };
+    R1CSWriter::finish_writing(r1cs)?;
     Log::print(&log);
     Ok(())
 }
+
+// Collects the different tables of the lookups and, following the tree, the
+// wires each lookup checks against its table
+fn write_lookups_data(iterator: EncodingIterator, map: &SignalMap, data: &mut LookupsData) {
+    let node = &iterator.encoding.nodes[iterator.node_id];
+    if let Some(table) = &node.lookup {
+        let (tables, applications) = data;
+        let index = match tables.iter().position(|t| t == table) {
+            Some(index) => index,
+            None => {
+                tables.push(table.clone());
+                tables.len() - 1
+            }
+        };
+        let wires = (1..=table.arity()).map(|signal| *map.get(&(signal + iterator.offset)).unwrap()).collect();
+        applications.push((index, wires));
+    }
+    for edge in EncodingIterator::edges(&iterator) {
+        let next = EncodingIterator::next(&iterator, edge);
+        write_lookups_data(next, map, data);
+    }
+}
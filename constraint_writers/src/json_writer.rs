@@ -1,5 +1,6 @@
+use super::Provenance;
 use std::fs::File;
-use std::io::{BufWriter, Write};
+use std::io::{self, BufWriter, Write};
 
 pub struct ConstraintJSON {
     writer_constraints: BufWriter<File>,
@@ -96,3 +97,30 @@ impl SubstitutionJSON {
         self.writer_substitutions.flush().map_err(|_err| {})
     }
 }
+
+// Each origin and the origins of each constraint are written in a line
+pub fn write_provenance(file: &str, provenance: &Provenance) -> io::Result<()> {
+    let mut writer = BufWriter::new(File::create(file)?);
+    writer.write_all(b"{\n\"origins\": [")?;
+    for (index, origin) in provenance.origins.iter().enumerate() {
+        let origin = json::object! {
+            "file": origin.file.as_str(),
+            "line": origin.line,
+            "component": origin.component.as_str(),
+            "template": origin.template.as_str(),
+            "constraint": origin.constraint.as_str(),
+        };
+        let separator = if index == 0 { "\n" } else { ",\n" };
+        writer.write_all(separator.as_bytes())?;
+        writer.write_all(origin.dump().as_bytes())?;
+    }
+    writer.write_all(b"\n],\n\"constraints\": [")?;
+    for (index, origins) in provenance.constraints.iter().enumerate() {
+        let origins: Vec<String> = origins.iter().map(|origin| origin.to_string()).collect();
+        let separator = if index == 0 { "\n" } else { ",\n" };
+        writer.write_all(separator.as_bytes())?;
+        writer.write_all(format!("[{}]", origins.join(",")).as_bytes())?;
+    }
+    writer.write_all(b"\n]\n}")?;
+    writer.flush()
+}
@@ -4,8 +4,28 @@ pub mod log_writer;
 pub mod r1cs_writer;
 pub mod sym_writer;
 
+// Statement of the program that generates a constraint of the circuit
+#[derive(Clone)]
+pub struct ConstraintOrigin {
+    pub file: String,
+    pub line: usize,
+    pub component: String,
+    pub template: String,
+    // the constraint before the simplification, with the names of its signals
+    pub constraint: String,
+}
+
+// The constraints of the circuit, in the order of the r1cs file, are given by
+// the positions of the origins they come from. A constraint that results from
+// merging several constraints in the simplification has all their origins.
+pub struct Provenance {
+    pub origins: Vec<ConstraintOrigin>,
+    pub constraints: Vec<Vec<usize>>,
+}
+
 pub trait ConstraintExporter {
-    fn r1cs(&self, out: &str, custom_gates: bool) -> Result<(), ()>;
+    fn r1cs(&self, out: &str, custom_gates: bool, provenance: Option<&Provenance>) -> Result<(), ()>;
+    fn provenance(&self) -> Provenance;
     fn json_constraints(&self, writer: &debug_writer::DebugWriter) -> Result<(), ()>;
     fn sym(&self, out: &str) -> Result<(), ()>;
 }
@@ -1,9 +1,10 @@
+use super::Provenance;
 use circom_algebra::num_bigint::BigInt;
 use std::collections::HashMap;
 use std::fs::File;
-use std::io::{BufWriter, Seek, SeekFrom, Write};
+use std::io::{self, BufWriter, Seek, SeekFrom, Write};
 
-const SECTIONS: u8 = 5;
+const SECTIONS: u8 = 8;
 const MAGIC: &[u8] = b"r1cs";
 const VERSION: &[u8] = &[1, 0, 0, 0];
 const HEADER_TYPE: &[u8] = &[1, 0, 0, 0];
@@ -11,6 +12,9 @@ const CONSTRAINT_TYPE: &[u8] = &[2, 0, 0, 0];
 const WIRE2LABEL_TYPE: &[u8] = &[3, 0, 0, 0];
 const CUSTOM_GATES_USED_TYPE: &[u8] = &[4, 0, 0, 0];
 const CUSTOM_GATES_APPLIED_TYPE: &[u8] = &[5, 0, 0, 0];
+const PROVENANCE_TYPE: &[u8] = &[6, 0, 0, 0];
+const LOOKUPS_TYPE: &[u8] = &[7, 0, 0, 0];
+const CUSTOM_GATES_RELATIONS_TYPE: &[u8] = &[8, 0, 0, 0];
 const PLACE_HOLDER: &[u8] = &[3, 3, 3, 3, 3, 3, 3, 3];
 
 fn into_format(number: &[u8], with_bytes: usize) -> (Vec<u8>, usize) {
@@ -27,21 +31,21 @@ fn bigint_as_bytes(number: &BigInt, with_bytes: usize) -> (Vec<u8>, usize) {
     into_format(&value, with_bytes)
 }
 
-fn initialize_section(writer: &mut BufWriter<File>, header: &[u8]) -> Result<u64, ()> {
-    writer.write_all(header).map_err(|_err| {})?;
+fn initialize_section(writer: &mut BufWriter<File>, header: &[u8]) -> io::Result<u64> {
+    writer.write_all(header)?;
     //writer.flush().map_err(|_err| {})?;
-    let go_back = writer.seek(SeekFrom::Current(0)).map_err(|_err| {})?;
-    writer.write_all(PLACE_HOLDER).map_err(|_| {})?;
+    let go_back = writer.seek(SeekFrom::Current(0))?;
+    writer.write_all(PLACE_HOLDER)?;
     //writer.flush().map_err(|_err| {})?;
     Result::Ok(go_back)
 }
 
-fn end_section(writer: &mut BufWriter<File>, go_back: u64, size: usize) -> Result<(), ()> {
-    let go_back_1 = writer.seek(SeekFrom::Current(0)).map_err(|_err| {})?;
-    writer.seek(SeekFrom::Start(go_back)).map_err(|_err| {})?;
+fn end_section(writer: &mut BufWriter<File>, go_back: u64, size: usize) -> io::Result<()> {
+    let go_back_1 = writer.seek(SeekFrom::Current(0))?;
+    writer.seek(SeekFrom::Start(go_back))?;
     let (stream, _) = bigint_as_bytes(&BigInt::from(size), 8);
-    writer.write_all(&stream).map_err(|_err| {})?;
-    writer.seek(SeekFrom::Start(go_back_1)).map_err(|_err| {})?;
+    writer.write_all(&stream)?;
+    writer.seek(SeekFrom::Start(go_back_1))?;
     //writer.flush().map_err(|_| {})
     Result::Ok(())
 }
@@ -77,25 +81,25 @@ fn write_constraint<T>(
     b: &HashMap<T, BigInt>,
     c: &HashMap<T, BigInt>,
     field_size: usize,
-) -> Result<usize, ()> where T: AsRef<[u8]> + std::cmp::Ord + std::hash::Hash {
+) -> io::Result<usize> where T: AsRef<[u8]> + std::cmp::Ord + std::hash::Hash {
     let (block_a, size_a) = obtain_linear_combination_block(a, field_size);
     let (block_b, size_b) = obtain_linear_combination_block(b, field_size);
     let (block_c, size_c) = obtain_linear_combination_block(c, field_size);
-    file.write_all(&block_a).map_err(|_err| {})?;
+    file.write_all(&block_a)?;
     //file.flush().map_err(|_err| {})?;
-    file.write_all(&block_b).map_err(|_err| {})?;
+    file.write_all(&block_b)?;
     //file.flush().map_err(|_err| {})?;
-    file.write_all(&block_c).map_err(|_err| {})?;
+    file.write_all(&block_c)?;
     //file.flush().map_err(|_err| {})?;
     Result::Ok(size_a + size_b + size_c)
 }
 
-fn initialize_file(writer: &mut BufWriter<File>, num_sections: u8) -> Result<(), ()> {
-    writer.write_all(MAGIC).map_err(|_err| {})?;
+fn initialize_file(writer: &mut BufWriter<File>, num_sections: u8) -> io::Result<()> {
+    writer.write_all(MAGIC)?;
     //writer.flush().map_err(|_err| {})?;
-    writer.write_all(VERSION).map_err(|_err| {})?;
+    writer.write_all(VERSION)?;
     //writer.flush().map_err(|_err| {})?;
-    writer.write_all(&[num_sections, 0, 0, 0]).map_err(|_err| {})?;
+    writer.write_all(&[num_sections, 0, 0, 0])?;
     //writer.flush().map_err(|_err| {})?;
     Result::Ok(())
 }
@@ -152,21 +156,54 @@ pub struct CustomGatesAppliedSection {
     sections: [bool; SECTIONS as usize]
 }
 
+pub struct ProvenanceSection {
+    writer: BufWriter<File>,
+    go_back: u64,
+    size: usize,
+    index: usize,
+    field_size: usize,
+    sections: [bool; SECTIONS as usize]
+}
+
+pub struct LookupsSection {
+    writer: BufWriter<File>,
+    go_back: u64,
+    size: usize,
+    index: usize,
+    field_size: usize,
+    sections: [bool; SECTIONS as usize]
+}
+
+pub struct CustomGatesRelationsSection {
+    writer: BufWriter<File>,
+    go_back: u64,
+    size: usize,
+    index: usize,
+    field_size: usize,
+    sections: [bool; SECTIONS as usize]
+}
+
 impl R1CSWriter {
     pub fn new(
         output_file: String,
         field_size: usize,
-        custom_gates: bool
-    ) -> Result<R1CSWriter, ()> {
+        custom_gates: bool,
+        relations: bool,
+        lookups: bool,
+        provenance: bool
+    ) -> io::Result<R1CSWriter> {
         let sections = [false; SECTIONS as usize];
-        let num_sections: u8 = if custom_gates { 5 } else { 3 };
-        let mut writer =
-            File::create(&output_file).map_err(|_err| {}).map(|f| BufWriter::new(f))?;
+        let num_sections: u8 = 3
+            + if custom_gates { 2 } else { 0 }
+            + if relations { 1 } else { 0 }
+            + if lookups { 1 } else { 0 }
+            + if provenance { 1 } else { 0 };
+        let mut writer = BufWriter::new(File::create(&output_file)?);
         initialize_file(&mut writer, num_sections)?;
         Result::Ok(R1CSWriter { writer, sections, field_size })
     }
 
-    pub fn start_header_section(mut r1cs: R1CSWriter) -> Result<HeaderSection, ()> {
+    pub fn start_header_section(mut r1cs: R1CSWriter) -> io::Result<HeaderSection> {
         let start = initialize_section(&mut r1cs.writer, HEADER_TYPE)?;
         Result::Ok(HeaderSection {
             writer: r1cs.writer,
@@ -178,7 +215,7 @@ impl R1CSWriter {
         })
     }
 
-    pub fn start_constraints_section(mut r1cs: R1CSWriter) -> Result<ConstraintSection, ()> {
+    pub fn start_constraints_section(mut r1cs: R1CSWriter) -> io::Result<ConstraintSection> {
         let start = initialize_section(&mut r1cs.writer, CONSTRAINT_TYPE)?;
         Result::Ok(ConstraintSection {
             number_of_constraints: 0,
@@ -191,7 +228,7 @@ impl R1CSWriter {
         })
     }
 
-    pub fn start_signal_section(mut r1cs: R1CSWriter) -> Result<SignalSection, ()> {
+    pub fn start_signal_section(mut r1cs: R1CSWriter) -> io::Result<SignalSection> {
         let start = initialize_section(&mut r1cs.writer, WIRE2LABEL_TYPE)?;
         Result::Ok(SignalSection {
             writer: r1cs.writer,
@@ -203,7 +240,7 @@ impl R1CSWriter {
         })
     }
 
-    pub fn start_custom_gates_used_section(mut r1cs: R1CSWriter) -> Result<CustomGatesUsedSection, ()> {
+    pub fn start_custom_gates_used_section(mut r1cs: R1CSWriter) -> io::Result<CustomGatesUsedSection> {
         let start = initialize_section(&mut r1cs.writer, CUSTOM_GATES_USED_TYPE)?;
         Result::Ok(CustomGatesUsedSection {
             writer: r1cs.writer,
@@ -215,7 +252,7 @@ impl R1CSWriter {
         })
     }
 
-    pub fn start_custom_gates_applied_section(mut r1cs: R1CSWriter) -> Result<CustomGatesAppliedSection, ()> {
+    pub fn start_custom_gates_applied_section(mut r1cs: R1CSWriter) -> io::Result<CustomGatesAppliedSection> {
         let start = initialize_section(&mut r1cs.writer, CUSTOM_GATES_APPLIED_TYPE)?;
         Result::Ok(CustomGatesAppliedSection {
             writer: r1cs.writer,
@@ -227,8 +264,44 @@ impl R1CSWriter {
         })
     }
 
-    pub fn finish_writing(mut r1cs: R1CSWriter) -> Result<(), ()> {
-	r1cs.writer.flush().map_err(|_err| {})
+    pub fn start_provenance_section(mut r1cs: R1CSWriter) -> io::Result<ProvenanceSection> {
+        let start = initialize_section(&mut r1cs.writer, PROVENANCE_TYPE)?;
+        Result::Ok(ProvenanceSection {
+            writer: r1cs.writer,
+            go_back: start,
+            size: 0,
+            index: 5,
+            field_size: r1cs.field_size,
+            sections: r1cs.sections
+        })
+    }
+
+    pub fn start_lookups_section(mut r1cs: R1CSWriter) -> io::Result<LookupsSection> {
+        let start = initialize_section(&mut r1cs.writer, LOOKUPS_TYPE)?;
+        Result::Ok(LookupsSection {
+            writer: r1cs.writer,
+            go_back: start,
+            size: 0,
+            index: 6,
+            field_size: r1cs.field_size,
+            sections: r1cs.sections
+        })
+    }
+
+    pub fn start_custom_gates_relations_section(mut r1cs: R1CSWriter) -> io::Result<CustomGatesRelationsSection> {
+        let start = initialize_section(&mut r1cs.writer, CUSTOM_GATES_RELATIONS_TYPE)?;
+        Result::Ok(CustomGatesRelationsSection {
+            writer: r1cs.writer,
+            go_back: start,
+            size: 0,
+            index: 7,
+            field_size: r1cs.field_size,
+            sections: r1cs.sections
+        })
+    }
+
+    pub fn finish_writing(mut r1cs: R1CSWriter) -> io::Result<()> {
+	r1cs.writer.flush()
     }
 }
 
@@ -243,11 +316,11 @@ pub struct HeaderData {
 }
 
 impl HeaderSection {
-    pub fn write_section(&mut self, data: HeaderData) -> Result<(), ()> {
+    pub fn write_section(&mut self, data: HeaderData) -> io::Result<()> {
         let (field_stream, bytes_field) = bigint_as_bytes(&data.field, self.field_size);
         let (length_stream, bytes_size) = bigint_as_bytes(&BigInt::from(self.field_size), 4);
-        self.writer.write_all(&length_stream).map_err(|_err| {})?;
-        self.writer.write_all(&field_stream).map_err(|_err| {})?;
+        self.writer.write_all(&length_stream)?;
+        self.writer.write_all(&field_stream)?;
         //self.writer.flush().map_err(|_err| {})?;
         self.size += bytes_field + bytes_size;
 
@@ -262,13 +335,13 @@ impl HeaderSection {
         for data in &data_stream {
             let (stream, size) = bigint_as_bytes(&BigInt::from(data[0]), data[1]);
             self.size += size;
-            self.writer.write_all(&stream).map_err(|_err| {})?;
+            self.writer.write_all(&stream)?;
             //self.writer.flush().map_err(|_err| {})?;
         }
         Result::Ok(())
     }
 
-    pub fn end_section(mut self) -> Result<R1CSWriter, ()> {
+    pub fn end_section(mut self) -> io::Result<R1CSWriter> {
         end_section(&mut self.writer, self.go_back, self.size)?;
         let mut sections = self.sections;
         let index = self.index;
@@ -284,7 +357,7 @@ impl ConstraintSection {
         a: &Constraint,
         b: &Constraint,
         c: &Constraint,
-    ) -> Result<(), ()> {
+    ) -> io::Result<()> {
         let field_size = self.field_size;
         let mut r1cs_a = HashMap::new();
         for (k, v) in a {
@@ -307,7 +380,7 @@ impl ConstraintSection {
         Result::Ok(())
     }
 
-    pub fn end_section(mut self) -> Result<R1CSWriter, ()> {
+    pub fn end_section(mut self) -> io::Result<R1CSWriter> {
         end_section(&mut self.writer, self.go_back, self.size)?;
         let mut sections = self.sections;
         let index = self.index;
@@ -328,19 +401,19 @@ impl SignalSection {
     pub fn write_signal<T>(
         &mut self,
         bytes: &T
-    ) -> Result<(), ()> where T: AsRef<[u8]> {
+    ) -> io::Result<()> where T: AsRef<[u8]> {
         let (bytes, size) = into_format(bytes.as_ref(), 8);
         self.size += size;
-        self.writer.write_all(&bytes).map_err(|_err| {})//?;
+        self.writer.write_all(&bytes)//?;
         //self.writer.flush().map_err(|_err| {})
     }
 
-    pub fn write_signal_usize(&mut self, signal: usize) -> Result<(), ()> {
+    pub fn write_signal_usize(&mut self, signal: usize) -> io::Result<()> {
         let (_, as_bytes) = BigInt::from(signal).to_bytes_le();
         SignalSection::write_signal(self, &as_bytes)
     }
 
-    pub fn end_section(mut self) -> Result<R1CSWriter, ()> {
+    pub fn end_section(mut self) -> io::Result<R1CSWriter> {
         end_section(&mut self.writer, self.go_back, self.size)?;
         let mut sections = self.sections;
         let index = self.index;
@@ -355,20 +428,20 @@ impl SignalSection {
 
 pub type CustomGatesUsedData = Vec<(String, Vec<BigInt>)>;
 impl CustomGatesUsedSection {
-    pub fn write_custom_gates_usages(&mut self, data: CustomGatesUsedData) -> Result<(), ()> {
+    pub fn write_custom_gates_usages(&mut self, data: CustomGatesUsedData) -> io::Result<()> {
         let no_custom_gates = data.len();
         let (no_custom_gates_stream, no_custom_gates_size) =
             bigint_as_bytes(&BigInt::from(no_custom_gates), 4);
         self.size += no_custom_gates_size;
-        self.writer.write_all(&no_custom_gates_stream).map_err(|_err| {})?;
+        self.writer.write_all(&no_custom_gates_stream)?;
         //self.writer.flush().map_err(|_err| {})?;
 
         for custom_gate in data {
             let custom_gate_name = custom_gate.0;
             let custom_gate_name_stream = custom_gate_name.as_bytes();
             self.size += custom_gate_name_stream.len() + 1;
-            self.writer.write_all(custom_gate_name_stream).map_err(|_err| {})?;
-            self.writer.write_all(&[0]).map_err(|_err| {})?;
+            self.writer.write_all(custom_gate_name_stream)?;
+            self.writer.write_all(&[0])?;
             //self.writer.flush().map_err(|_err| {})?;
 
             let custom_gate_parameters = custom_gate.1;
@@ -376,13 +449,13 @@ impl CustomGatesUsedSection {
             let (no_custom_gate_parameters_stream, no_custom_gate_parameters_size) =
                 bigint_as_bytes(&BigInt::from(no_custom_gate_parameters), 4);
             self.size += no_custom_gate_parameters_size;
-            self.writer.write_all(&no_custom_gate_parameters_stream).map_err(|_err| {})?;
+            self.writer.write_all(&no_custom_gate_parameters_stream)?;
             //self.writer.flush().map_err(|_err| {})?;
 
             for parameter in custom_gate_parameters {
                 let (parameter_stream, parameter_size) = bigint_as_bytes(&parameter, self.field_size);
                 self.size += parameter_size;
-                self.writer.write(&parameter_stream).map_err(|_err| {})?;
+                self.writer.write_all(&parameter_stream)?;
                 //self.writer.flush().map_err(|_err| {})?;
             }
         }
@@ -390,7 +463,7 @@ impl CustomGatesUsedSection {
         Result::Ok(())
     }
 
-    pub fn end_section(mut self) -> Result<R1CSWriter, ()> {
+    pub fn end_section(mut self) -> io::Result<R1CSWriter> {
         end_section(&mut self.writer, self.go_back, self.size)?;
         let mut sections = self.sections;
         let index = self.index;
@@ -405,12 +478,12 @@ impl CustomGatesUsedSection {
 
 pub type CustomGatesAppliedData = Vec<(usize, Vec<usize>)>;
 impl CustomGatesAppliedSection {
-    pub fn write_custom_gates_applications(&mut self, data: CustomGatesAppliedData) -> Result<(), ()> {
+    pub fn write_custom_gates_applications(&mut self, data: CustomGatesAppliedData) -> io::Result<()> {
         let no_custom_gate_applications = data.len();
         let (no_custom_gate_applications_stream, no_custom_gate_applications_size) =
             bigint_as_bytes(&BigInt::from(no_custom_gate_applications), 4);
         self.size += no_custom_gate_applications_size;
-        self.writer.write_all(&no_custom_gate_applications_stream).map_err(|_err| {})?;
+        self.writer.write_all(&no_custom_gate_applications_stream)?;
         //self.writer.flush().map_err(|_err| {})?;
 
         for custom_gate_application in data {
@@ -418,7 +491,7 @@ impl CustomGatesAppliedSection {
             let (custom_gate_index_stream, custom_gate_index_size) =
                 bigint_as_bytes(&BigInt::from(custom_gate_index), 4);
             self.size += custom_gate_index_size;
-            self.writer.write_all(&custom_gate_index_stream).map_err(|_err| {})?;
+            self.writer.write_all(&custom_gate_index_stream)?;
             //self.writer.flush().map_err(|_err| {})?;
 
             let custom_gate_signals = custom_gate_application.1;
@@ -426,13 +499,13 @@ impl CustomGatesAppliedSection {
             let (no_custom_gate_signals_stream, no_custom_gate_signals_size) =
                 bigint_as_bytes(&BigInt::from(no_custom_gate_signals), 4);
             self.size += no_custom_gate_signals_size;
-            self.writer.write_all(&no_custom_gate_signals_stream).map_err(|_err| {})?;
+            self.writer.write_all(&no_custom_gate_signals_stream)?;
             //self.writer.flush().map_err(|_err| {})?;
 
             for signal in custom_gate_signals {
                 let (signal_stream, signal_size) = bigint_as_bytes(&BigInt::from(signal), 8);
                 self.size += signal_size;
-                self.writer.write(&signal_stream).map_err(|_err| {})?;
+                self.writer.write_all(&signal_stream)?;
                 //self.writer.flush().map_err(|_err| {})?;
             }
         }
@@ -440,7 +513,7 @@ impl CustomGatesAppliedSection {
         Result::Ok(())
     }
 
-    pub fn end_section(mut self) -> Result<R1CSWriter, ()> {
+    pub fn end_section(mut self) -> io::Result<R1CSWriter> {
         end_section(&mut self.writer, self.go_back, self.size)?;
         let mut sections = self.sections;
         let index = self.index;
@@ -452,3 +525,206 @@ impl CustomGatesAppliedSection {
         })
     }
 }
+
+impl ProvenanceSection {
+    fn write_string(&mut self, string: &str) -> io::Result<()> {
+        self.size += string.len() + 1;
+        self.writer.write_all(string.as_bytes())?;
+        self.writer.write_all(&[0])
+    }
+
+    fn write_number(&mut self, number: usize) -> io::Result<()> {
+        let (stream, size) = bigint_as_bytes(&BigInt::from(number), 4);
+        self.size += size;
+        self.writer.write_all(&stream)
+    }
+
+    // The origins go first, then each constraint is given by the number of
+    // its origins followed by their positions
+    pub fn write_provenance(&mut self, data: &Provenance) -> io::Result<()> {
+        self.write_number(data.origins.len())?;
+        for origin in &data.origins {
+            self.write_string(&origin.file)?;
+            self.write_number(origin.line)?;
+            self.write_string(&origin.component)?;
+            self.write_string(&origin.template)?;
+            self.write_string(&origin.constraint)?;
+        }
+        for origins in &data.constraints {
+            self.write_number(origins.len())?;
+            for origin in origins {
+                self.write_number(*origin)?;
+            }
+        }
+        Result::Ok(())
+    }
+
+    pub fn end_section(mut self) -> io::Result<R1CSWriter> {
+        end_section(&mut self.writer, self.go_back, self.size)?;
+        let mut sections = self.sections;
+        let index = self.index;
+        sections[index] = true;
+        Result::Ok(R1CSWriter {
+            writer: self.writer,
+            field_size: self.field_size,
+            sections
+        })
+    }
+}
+
+// The values a lookup checks its signals against
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum LookupTable {
+    // the values below 2^bits, checked against a single signal
+    Range { bits: usize },
+    // the rows of a table, checked against one signal per column
+    Rows { columns: usize, rows: Vec<Vec<BigInt>> },
+}
+
+impl LookupTable {
+    // number of signals of each application of the table
+    pub fn arity(&self) -> usize {
+        match self {
+            LookupTable::Range { .. } => 1,
+            LookupTable::Rows { columns, .. } => *columns,
+        }
+    }
+}
+
+// The tables go first, then each application is given by the position of
+// its table and the wires it checks
+pub type LookupsData = (Vec<LookupTable>, Vec<(usize, Vec<usize>)>);
+impl LookupsSection {
+    fn write_number(&mut self, number: usize, with_bytes: usize) -> io::Result<()> {
+        let (stream, size) = bigint_as_bytes(&BigInt::from(number), with_bytes);
+        self.size += size;
+        self.writer.write_all(&stream)
+    }
+
+    pub fn write_lookups(&mut self, (tables, applications): LookupsData) -> io::Result<()> {
+        self.write_number(tables.len(), 4)?;
+        for table in tables {
+            match table {
+                LookupTable::Range { bits } => {
+                    self.write_number(0, 4)?;
+                    self.write_number(bits, 4)?;
+                }
+                LookupTable::Rows { columns, rows } => {
+                    self.write_number(1, 4)?;
+                    self.write_number(columns, 4)?;
+                    self.write_number(rows.len(), 4)?;
+                    for value in rows.iter().flatten() {
+                        let (stream, size) = bigint_as_bytes(value, self.field_size);
+                        self.size += size;
+                        self.writer.write_all(&stream)?;
+                    }
+                }
+            }
+        }
+        self.write_number(applications.len(), 4)?;
+        for (table, wires) in applications {
+            self.write_number(table, 4)?;
+            self.write_number(wires.len(), 4)?;
+            for wire in wires {
+                self.write_number(wire, 8)?;
+            }
+        }
+        Result::Ok(())
+    }
+
+    pub fn end_section(mut self) -> io::Result<R1CSWriter> {
+        end_section(&mut self.writer, self.go_back, self.size)?;
+        let mut sections = self.sections;
+        let index = self.index;
+        sections[index] = true;
+        Result::Ok(R1CSWriter {
+            writer: self.writer,
+            field_size: self.field_size,
+            sections
+        })
+    }
+}
+
+// A relation of a custom gate states that the sum of its terms is zero, where
+// each term is a coefficient times the signals of an application of the gate
+// at the given positions
+pub type GateRelation = Vec<(BigInt, Vec<usize>)>;
+// The relations of each custom gate, in the order of the custom gates section
+pub type CustomGatesRelationsData = Vec<Vec<GateRelation>>;
+impl CustomGatesRelationsSection {
+    fn write_number(&mut self, number: usize) -> io::Result<()> {
+        let (stream, size) = bigint_as_bytes(&BigInt::from(number), 4);
+        self.size += size;
+        self.writer.write_all(&stream)
+    }
+
+    pub fn write_custom_gates_relations(&mut self, data: CustomGatesRelationsData) -> io::Result<()> {
+        self.write_number(data.len())?;
+        for relations in data {
+            self.write_number(relations.len())?;
+            for relation in relations {
+                self.write_number(relation.len())?;
+                for (coefficient, signals) in relation {
+                    let (coefficient_stream, coefficient_size) = bigint_as_bytes(&coefficient, self.field_size);
+                    self.size += coefficient_size;
+                    self.writer.write_all(&coefficient_stream)?;
+                    self.write_number(signals.len())?;
+                    for signal in signals {
+                        self.write_number(signal)?;
+                    }
+                }
+            }
+        }
+        Result::Ok(())
+    }
+
+    pub fn end_section(mut self) -> io::Result<R1CSWriter> {
+        end_section(&mut self.writer, self.go_back, self.size)?;
+        let mut sections = self.sections;
+        let index = self.index;
+        sections[index] = true;
+        Result::Ok(R1CSWriter {
+            writer: self.writer,
+            field_size: self.field_size,
+            sections
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{LookupTable, R1CSWriter};
+    use circom_algebra::num_bigint::BigInt;
+
+    #[test]
+    fn lookups_are_written_after_their_tables() {
+        let file = std::env::temp_dir().join(format!("lookups_{}.r1cs", std::process::id()));
+        let output = file.to_str().unwrap().to_string();
+        let tables = vec![
+            LookupTable::Range { bits: 8 },
+            LookupTable::Rows { columns: 2, rows: vec![vec![BigInt::from(1), BigInt::from(2)]] },
+        ];
+        let applications = vec![(1, vec![3, 4]), (0, vec![5])];
+        let r1cs = R1CSWriter::new(output, 8, false, false, true, false).unwrap();
+        let mut lookups = R1CSWriter::start_lookups_section(r1cs).unwrap();
+        lookups.write_lookups((tables, applications)).unwrap();
+        R1CSWriter::finish_writing(lookups.end_section().unwrap()).unwrap();
+        let bytes = std::fs::read(&file).unwrap();
+        std::fs::remove_file(&file).unwrap();
+
+        let u32s = |values: &[u32]| values.iter().flat_map(|v| v.to_le_bytes()).collect::<Vec<u8>>();
+        let mut section = u32s(&[2, 0, 8, 1, 2, 1]);
+        section.extend(1u64.to_le_bytes());
+        section.extend(2u64.to_le_bytes());
+        section.extend(u32s(&[2, 1, 2]));
+        section.extend(3u64.to_le_bytes());
+        section.extend(4u64.to_le_bytes());
+        section.extend(u32s(&[0, 1]));
+        section.extend(5u64.to_le_bytes());
+        let mut expected = b"r1cs".to_vec();
+        expected.extend(u32s(&[1, 4, 7]));
+        expected.extend((section.len() as u64).to_le_bytes());
+        expected.extend(section);
+        assert_eq!(bytes, expected);
+    }
+}
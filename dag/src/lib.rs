@@ -1,13 +1,17 @@
 mod constraint_correctness_analysis;
 mod json_porting;
 mod map_to_constraint_list;
+mod provenance_porting;
 mod r1cs_porting;
+mod statistics_porting;
 mod sym_porting;
+mod witness_check;
 mod witness_producer;
 use circom_algebra::num_bigint::BigInt;
 use constraint_list::ConstraintList;
 use constraint_writers::debug_writer::DebugWriter;
-use constraint_writers::ConstraintExporter;
+use constraint_writers::r1cs_writer::{GateRelation, LookupTable};
+use constraint_writers::{ConstraintExporter, Provenance};
 use program_structure::constants::UsefulConstants;
 use program_structure::error_definition::ReportCollection;
 use std::collections::{HashMap, HashSet};
@@ -16,8 +20,20 @@ type Constraint = circom_algebra::algebra::Constraint<usize>;
 type Substitution = circom_algebra::algebra::Substitution<usize>;
 type Range = std::ops::Range<usize>;
 
+pub use constraint_list::SimplificationStrategy;
+pub use statistics_porting::{Statistics, StatisticsFormat};
+pub use witness_check::{Tampering, UnderConstrainedSignal, UnsatisfiedConstraint};
+
 pub type FastSubAccess = HashMap<usize, Substitution>;
 
+// Statement of the program where a constraint is generated, the file is
+// the position of its name in the file names of the dag
+#[derive(Clone, Copy, Default)]
+pub struct ConstraintLocation {
+    pub file: Option<usize>,
+    pub line: usize,
+}
+
 pub struct Tree<'a> {
     dag: &'a DAG,
     pub field: BigInt,
@@ -28,6 +44,7 @@ pub struct Tree<'a> {
     pub forbidden: HashSet<usize>,
     pub id_to_name: HashMap<usize, String>,
     pub constraints: Vec<Constraint>,
+    pub locations: Vec<ConstraintLocation>,
 }
 
 impl<'a> Tree<'a> {
@@ -39,6 +56,7 @@ impl<'a> Tree<'a> {
         let offset = dag.get_entry().unwrap().in_number;
         let path = dag.get_entry().unwrap().label.clone();
         let constraints = root.constraints.clone();
+        let locations = root.constraint_locations.clone();
         let mut id_to_name = HashMap::new();
         let mut signals: Vec<_> = Vec::new();
         let forbidden: HashSet<_> =
@@ -50,7 +68,7 @@ impl<'a> Tree<'a> {
             }
         }
         signals.sort();
-        Tree { field, dag, path, offset, node_id, signals, forbidden, id_to_name, constraints }
+        Tree { field, dag, path, offset, node_id, signals, forbidden, id_to_name, constraints, locations }
     }
 
     pub fn go_to_subtree(current: &'a Tree, edge: &Edge) -> Tree<'a> {
@@ -70,13 +88,15 @@ impl<'a> Tree<'a> {
             }
         }
         signals.sort();
-        let constraints: Vec<_> = node
-            .constraints
-            .iter()
-            .filter(|c| !c.is_empty())
-            .map(|c| Constraint::apply_offset(c, offset))
-            .collect();
-        Tree { field, dag, path, offset, node_id, signals, forbidden, id_to_name, constraints }
+        let mut constraints = Vec::new();
+        let mut locations = Vec::new();
+        for (c, location) in node.constraints.iter().zip(&node.constraint_locations) {
+            if !c.is_empty() {
+                constraints.push(Constraint::apply_offset(c, offset));
+                locations.push(*location);
+            }
+        }
+        Tree { field, dag, path, offset, node_id, signals, forbidden, id_to_name, constraints, locations }
     }
 
     pub fn get_edges(tree: &'a Tree) -> &'a Vec<Edge> {
@@ -149,10 +169,16 @@ pub struct Node {
     forbidden_if_main: HashSet<usize>,
     io_signals: Vec<usize>,
     constraints: Vec<Constraint>,
+    constraint_locations: Vec<ConstraintLocation>,
     underscored_signals: Vec<usize>,
+    kept_signals: Vec<usize>,
     is_parallel: bool,
     has_parallel_sub_cmp: bool,
     is_custom_gate: bool,
+    // relations declared by a custom gate over its ordered signals
+    relations: Vec<GateRelation>,
+    // table of a gadget exported as a lookup, whose inputs are checked against it
+    lookup: Option<LookupTable>,
     number_of_subcomponents_indexes: usize,
 }
 
@@ -215,18 +241,31 @@ impl Node {
         self.intermediates_length += 1;
     }
 
-    fn add_constraint(&mut self, constraint: Constraint) {
-        self.constraints.push(constraint)
+    fn add_constraint(&mut self, constraint: Constraint, location: ConstraintLocation) {
+        self.constraints.push(constraint);
+        self.constraint_locations.push(location)
     }
 
     fn add_underscored_signal(&mut self, signal: usize) {
         self.underscored_signals.push(signal)
     }
 
+    fn add_kept_signal(&mut self, signal: usize) {
+        self.kept_signals.push(signal)
+    }
+
     fn set_number_of_subcomponents_indexes(&mut self, number_scmp: usize) {
         self.number_of_subcomponents_indexes = number_scmp
     }
 
+    fn set_lookup(&mut self, table: LookupTable) {
+        self.lookup = Some(table)
+    }
+
+    fn set_relations(&mut self, relations: Vec<GateRelation>) {
+        self.relations = relations
+    }
+
     pub fn parameters(&self) -> &Vec<BigInt> {
         &self.parameters
     }
@@ -287,6 +326,14 @@ impl Node {
         self.is_custom_gate
     }
 
+    pub fn relations(&self) -> &Vec<GateRelation> {
+        &self.relations
+    }
+
+    pub fn lookup(&self) -> Option<&LookupTable> {
+        self.lookup.as_ref()
+    }
+
     pub fn number_of_subcomponents_indexes(&self) -> usize {
         self.number_of_subcomponents_indexes
     }
@@ -297,11 +344,19 @@ pub struct DAG {
     pub nodes: Vec<Node>,
     pub adjacency: Vec<Vec<Edge>>,
     pub prime: String,
+    // wires of the public signals that are not in their default position
+    pub public_wires: HashMap<usize, usize>,
+    // names of the files of the program, indexed by their ids
+    pub file_names: Vec<String>,
 }
 
 impl ConstraintExporter for DAG {
-    fn r1cs(&self, out: &str, custom_gates: bool) -> Result<(), ()> {
-        DAG::generate_r1cs_output(self, out, custom_gates)
+    fn r1cs(&self, out: &str, custom_gates: bool, provenance: Option<&Provenance>) -> Result<(), ()> {
+        DAG::generate_r1cs_output(self, out, custom_gates, provenance)
+    }
+
+    fn provenance(&self) -> Provenance {
+        provenance_porting::provenance(self)
     }
 
     fn json_constraints(&self, writer: &DebugWriter) -> Result<(), ()> {
@@ -320,6 +375,8 @@ impl DAG {
             one_signal: 0,
             nodes: Vec::new(),
             adjacency: Vec::new(),
+            public_wires: HashMap::new(),
+            file_names: Vec::new(),
         }
     }
 
@@ -402,9 +459,9 @@ impl DAG {
         }
     }
 
-    pub fn add_constraint(&mut self, constraint: Constraint) {
+    pub fn add_constraint(&mut self, constraint: Constraint, location: ConstraintLocation) {
         if let Option::Some(node) = self.get_mut_main() {
-            node.add_constraint(constraint);
+            node.add_constraint(constraint, location);
         }
     }
 
@@ -414,12 +471,30 @@ impl DAG {
         }
     }
 
+    pub fn add_kept_signal(&mut self, signal: usize) {
+        if let Option::Some(node) = self.get_mut_main() {
+            node.add_kept_signal(signal);
+        }
+    }
+
     pub fn set_number_of_subcomponents_indexes(&mut self, number_scmp: usize){
         if let Option::Some(node) = self.get_mut_main() {
             node.set_number_of_subcomponents_indexes(number_scmp);
         }
     }
 
+    pub fn set_lookup(&mut self, table: LookupTable) {
+        if let Option::Some(node) = self.get_mut_main() {
+            node.set_lookup(table);
+        }
+    }
+
+    pub fn set_relations(&mut self, relations: Vec<GateRelation>) {
+        if let Option::Some(node) = self.get_mut_main() {
+            node.set_relations(relations);
+        }
+    }
+
     pub fn get_node(&self, id: usize) -> Option<&Node> {
         if id < self.nodes.len() {
             Some(&self.nodes[id])
@@ -471,8 +546,8 @@ impl DAG {
         constraint_correctness_analysis::clean_constraints(&mut self.nodes);
     }
 
-    pub fn generate_r1cs_output(&self, output_file: &str, custom_gates: bool) -> Result<(), ()> {
-        r1cs_porting::write(self, output_file, custom_gates)
+    pub fn generate_r1cs_output(&self, output_file: &str, custom_gates: bool, provenance: Option<&Provenance>) -> Result<(), ()> {
+        r1cs_porting::write(self, output_file, custom_gates, provenance).map_err(|_err| {})
     }
 
     pub fn generate_sym_output(&self, output_file: &str) -> Result<(), ()> {
@@ -483,10 +558,87 @@ impl DAG {
         json_porting::port_constraints(self, debug)
     }
 
+    // Places the public signals of the main component in the order of the
+    // layout, where they are named as in the sym file and an array stands for
+    // all its signals. The outputs go before the public inputs, as in the r1cs
+    // header, and the public signals that are not in the layout go after the
+    // ones of their kind that are.
+    pub fn set_public_layout(&mut self, layout: &[String]) -> Result<(), String> {
+        let main = self.get_main().unwrap();
+        let no_outputs = main.number_of_outputs();
+        let no_public = no_outputs + main.number_of_public_inputs();
+        let mut ordered = Vec::new();
+        let mut placed = HashSet::new();
+        for entry in layout {
+            let not_found = || format!("{} is not a signal of the main component", entry);
+            let name = entry.strip_prefix("main.").ok_or_else(not_found)?;
+            let mut signals: Vec<usize> = match main.signal_correspondence.get(name) {
+                Some(signal) => vec![*signal],
+                None => main
+                    .signal_correspondence
+                    .iter()
+                    .filter(|(other, _)| {
+                        other.starts_with(name) && matches!(other[name.len()..].chars().next(), Some('[') | Some('.'))
+                    })
+                    .map(|(_, signal)| *signal)
+                    .collect(),
+            };
+            if signals.is_empty() {
+                return Result::Err(not_found());
+            }
+            signals.sort_unstable();
+            for signal in signals {
+                if signal > no_public {
+                    return Result::Err(format!("{} is not a public signal of the main component", entry));
+                }
+                if signal <= no_outputs && ordered.iter().any(|placed| *placed > no_outputs) {
+                    return Result::Err(format!("the output {} goes after a public input in the layout", entry));
+                }
+                if !placed.insert(signal) {
+                    return Result::Err(format!("{} appears more than once in the layout", entry));
+                }
+                ordered.push(signal);
+            }
+        }
+        ordered.extend((1..=no_public).filter(|signal| !placed.contains(signal)));
+        ordered.sort_by_key(|signal| *signal > no_outputs);
+        self.public_wires = ordered
+            .into_iter()
+            .enumerate()
+            .map(|(position, signal)| (signal, position + 1))
+            .filter(|(signal, wire)| signal != wire)
+            .collect();
+        Result::Ok(())
+    }
+
+    pub fn wire(&self, signal: usize) -> usize {
+        self.public_wires.get(&signal).copied().unwrap_or(signal)
+    }
+
+    // The constraint over the wires of its signals
+    fn constraint_wires(&self, constraint: &Constraint) -> Constraint {
+        let wires: HashMap<usize, usize> =
+            constraint.take_cloned_signals().into_iter().map(|signal| (signal, self.wire(signal))).collect();
+        Constraint::apply_correspondence(constraint, &wires)
+    }
+
     pub fn produce_witness(&self) -> Vec<usize> {
         witness_producer::produce_witness(self)
     }
 
+    pub fn check_witness(&self, witness: &[BigInt]) -> Vec<UnsatisfiedConstraint> {
+        witness_check::check(self, witness)
+    }
+
+    // The witness must satisfy the constraints of the circuit
+    pub fn find_under_constrained_signals(
+        &self,
+        witness: &[BigInt],
+        tampering: &Tampering,
+    ) -> Result<Vec<UnderConstrainedSignal>, String> {
+        witness_check::find_under_constrained(self, witness, tampering)
+    }
+
     fn get_mut_main(&mut self) -> Option<&mut Node> {
         self.nodes.last_mut()
     }
@@ -540,8 +692,15 @@ pub struct SimplificationFlags {
     pub no_rounds: usize,
     pub flag_s: bool,
     pub parallel_flag: bool,
+    // threads of the simplification, one per core when it is None
+    pub no_threads: Option<usize>,
     pub port_substitution: bool,
     pub json_substitutions: String,
     pub flag_old_heuristics: bool,
+    pub strategy: SimplificationStrategy,
+    // patterns of the qualified names of the signals that are never removed
+    pub keep: Vec<String>,
+    // the origins of the constraints are kept through the simplification
+    pub provenance: bool,
     pub prime : String,
 }
@@ -1,5 +1,5 @@
-use super::{Constraint, Edge, Node, SimplificationFlags, Tree, DAG};
-use constraint_list::{ConstraintList, DAGEncoding, EncodingEdge, EncodingNode, SignalInfo, Simplifier};
+use super::{provenance_porting, Constraint, Edge, Node, SimplificationFlags, Tree, DAG};
+use constraint_list::{ConstraintList, ConstraintOrigins, DAGEncoding, EncodingEdge, EncodingNode, SignalInfo, Simplifier};
 use program_structure::utils::constants::UsefulConstants;
 use std::collections::{HashSet, LinkedList};
 #[derive(Default)]
@@ -7,38 +7,99 @@ struct CHolder {
     linear: LinkedList<Constraint>,
     equalities: LinkedList<Constraint>,
     constant_equalities: LinkedList<Constraint>,
+    // with provenance, the origins of the constraints, which are numbered in
+    // the order they are visited
+    origins: Option<ConstraintOrigins>,
+    no_visited: usize,
+}
+
+// A * in a pattern matches any sequence of characters, including the dots
+// and the indexes of the qualified names
+fn matches_pattern(pattern: &str, name: &str) -> bool {
+    let (pattern, name) = (pattern.as_bytes(), name.as_bytes());
+    let (mut p, mut n) = (0, 0);
+    let mut star = None;
+    while n < name.len() {
+        if p < pattern.len() && pattern[p] == b'*' {
+            star = Some((p, n));
+            p += 1;
+        } else if p < pattern.len() && pattern[p] == name[n] {
+            p += 1;
+            n += 1;
+        } else if let Some((star_p, star_n)) = star {
+            p = star_p + 1;
+            n = star_n + 1;
+            star = Some((star_p, star_n + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|c| *c == b'*')
 }
 
 fn map_tree(
     tree: &Tree,
     witness: &mut Vec<usize>,
     c_holder: &mut CHolder,
-    forbidden: &mut HashSet<usize>
+    forbidden: &mut HashSet<usize>,
+    keep: &[String]
 ) -> usize {
     let mut no_constraints = 0;
+    let node = &tree.dag.nodes[tree.node_id];
 
     for signal in &tree.signals {
         Vec::push(witness, *signal);
-        if tree.dag.nodes[tree.node_id].is_custom_gate {
+        if node.is_custom_gate {
             forbidden.insert(*signal);
         }
     }
+    if let Some(table) = &node.lookup {
+        for signal in 1..=table.arity() {
+            forbidden.insert(signal + tree.offset);
+        }
+    }
+    for signal in &node.kept_signals {
+        forbidden.insert(*signal + tree.offset);
+    }
+    if !keep.is_empty() {
+        for signal in &tree.signals {
+            let name = format!("{}.{}", tree.path, tree.id_to_name[signal]);
+            if keep.iter().any(|pattern| matches_pattern(pattern, &name)) {
+                forbidden.insert(*signal);
+            }
+        }
+    }
 
     for constraint in &tree.constraints {
+        let origin = c_holder.no_visited;
+        c_holder.no_visited += 1;
+        let origins = c_holder.origins.as_mut();
         if Constraint::is_constant_equality(constraint) {
             LinkedList::push_back(&mut c_holder.constant_equalities, constraint.clone());
+            if let Some(origins) = origins {
+                origins.cons_equalities.push_back(vec![origin]);
+            }
         } else if Constraint::is_equality(constraint, &tree.field) {
             LinkedList::push_back(&mut c_holder.equalities, constraint.clone());
+            if let Some(origins) = origins {
+                origins.equalities.push_back(vec![origin]);
+            }
         } else if Constraint::is_linear(constraint) {
             LinkedList::push_back(&mut c_holder.linear, constraint.clone());
+            if let Some(origins) = origins {
+                origins.linear.push_back(vec![origin]);
+            }
         } else {
             no_constraints += 1;
+            if let Some(origins) = origins {
+                origins.non_linear.push_back(origin);
+            }
         }
     }
 
     for edge in Tree::get_edges(tree) {
         let subtree = Tree::go_to_subtree(tree, edge);
-        no_constraints += map_tree(&subtree, witness, c_holder, forbidden);
+        no_constraints += map_tree(&subtree, witness, c_holder, forbidden, keep);
     }
     no_constraints
 }
@@ -100,6 +161,8 @@ fn map_node_to_encoding(id: usize, node: Node) -> EncodingNode {
         ordered_signals,
         non_linear,
         is_custom_gate: node.is_custom_gate,
+        relations: node.relations,
+        lookup: node.lookup,
     }
 }
 
@@ -118,9 +181,14 @@ pub fn map(dag: DAG, flags: SimplificationFlags) -> ConstraintList {
     let no_public_outputs = dag.public_outputs();
     let no_private_inputs = dag.private_inputs();
     let mut forbidden = dag.get_main().unwrap().forbidden_if_main.clone();
+    let public_wires = dag.public_wires.clone();
     let mut c_holder = CHolder::default();
+    if flags.provenance {
+        let origins = provenance_porting::origins(&dag);
+        c_holder.origins = Some(ConstraintOrigins { origins, ..ConstraintOrigins::default() });
+    }
     let mut signal_map = vec![0];
-    let no_constraints = map_tree(&Tree::new(&dag), &mut signal_map, &mut c_holder, &mut forbidden);
+    let no_constraints = map_tree(&Tree::new(&dag), &mut signal_map, &mut c_holder, &mut forbidden, &flags.keep);
     let max_signal = Vec::len(&signal_map);
     let name_encoding = produce_encoding(no_constraints, init_id, dag.nodes, dag.adjacency);
     let _dur = now.elapsed().unwrap().as_millis();
@@ -131,17 +199,38 @@ pub fn map(dag: DAG, flags: SimplificationFlags) -> ConstraintList {
         no_public_outputs,
         no_private_inputs,
         forbidden,
+        public_wires,
         max_signal,
         dag_encoding: name_encoding,
         linear: c_holder.linear,
         equalities: c_holder.equalities,
         cons_equalities: c_holder.constant_equalities,
+        provenance: c_holder.origins,
         no_rounds: flags.no_rounds,
         flag_s: flags.flag_s,
         parallel_flag: flags.parallel_flag,
+        no_threads: flags.no_threads,
         flag_old_heuristics: flags.flag_old_heuristics,
+        strategy: flags.strategy,
         port_substitution: flags.port_substitution,
         json_substitutions: flags.json_substitutions,
     }
     .simplify_constraints()
 }
+
+#[cfg(test)]
+mod test {
+    use super::matches_pattern;
+
+    #[test]
+    fn patterns_match_the_qualified_names() {
+        assert!(matches_pattern("main.u", "main.u"));
+        assert!(!matches_pattern("main.u", "main.u[0]"));
+        assert!(matches_pattern("main.u*", "main.u[0]"));
+        assert!(matches_pattern("main.hasher*.out", "main.hasher[2].out"));
+        assert!(!matches_pattern("main.hasher*.out", "main.hasher[2].out[1]"));
+        assert!(matches_pattern("*.out*", "main.a.b.out[1]"));
+        assert!(matches_pattern("*", "main.a"));
+        assert!(!matches_pattern("main.*.in", "main.in"));
+    }
+}
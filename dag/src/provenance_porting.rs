@@ -0,0 +1,37 @@
+use super::witness_check::{display, Checker};
+use super::{ConstraintLocation, Tree, DAG};
+use constraint_writers::{ConstraintOrigin, Provenance};
+
+fn collect_locations(tree: &Tree, locations: &mut Vec<ConstraintLocation>) {
+    locations.extend(tree.locations.iter().cloned());
+    for edge in Tree::get_edges(tree) {
+        collect_locations(&Tree::go_to_subtree(tree, edge), locations);
+    }
+}
+
+// The origins of the constraints of the dag, in the order they are written
+// in its r1cs file
+pub fn origins(dag: &DAG) -> Vec<ConstraintOrigin> {
+    let checker = Checker::new(dag);
+    let mut locations = Vec::new();
+    collect_locations(&Tree::new(dag), &mut locations);
+    let mut origins = Vec::with_capacity(locations.len());
+    for ((node, constraint), location) in checker.constraints.iter().zip(locations) {
+        let file = location.file.and_then(|file| dag.file_names.get(file)).cloned().unwrap_or_default();
+        origins.push(ConstraintOrigin {
+            file,
+            line: location.line,
+            component: checker.nodes[*node].path.clone(),
+            template: checker.nodes[*node].template.clone(),
+            constraint: display(constraint, &checker.names, &checker.field),
+        });
+    }
+    origins
+}
+
+// Each constraint of the dag is its own origin
+pub fn provenance(dag: &DAG) -> Provenance {
+    let origins = origins(dag);
+    let constraints = (0..origins.len()).map(|origin| vec![origin]).collect();
+    Provenance { origins, constraints }
+}
@@ -1,8 +1,10 @@
 use super::{Constraint, Tree, DAG};
 use constraint_writers::log_writer::Log;
-use constraint_writers::r1cs_writer::{ConstraintSection, CustomGatesAppliedData, HeaderData, R1CSWriter};
+use constraint_writers::r1cs_writer::{ConstraintSection, CustomGatesAppliedData, HeaderData, LookupsData, R1CSWriter};
+use constraint_writers::Provenance;
+use std::io;
 
-pub fn write(dag: &DAG, output: &str, custom_gates: bool) -> Result<(), ()> {
+pub fn write(dag: &DAG, output: &str, custom_gates: bool, provenance: Option<&Provenance>) -> io::Result<()> {
     let tree = Tree::new(dag);
     let field_size = if tree.field.bits() % 64 == 0 {
         tree.field.bits() / 8
@@ -10,7 +12,9 @@ pub fn write(dag: &DAG, output: &str, custom_gates: bool) -> Result<(), ()> {
         (tree.field.bits() / 64 + 1) * 8
     };
     let mut log = Log::new();
-    let r1cs = R1CSWriter::new(output.to_string(), field_size, custom_gates)?;
+    let relations = custom_gates && dag.nodes.iter().any(|node| !node.relations().is_empty());
+    let lookups = dag.nodes.iter().any(|node| node.lookup().is_some());
+    let r1cs = R1CSWriter::new(output.to_string(), field_size, custom_gates, relations, lookups, provenance.is_some())?;
 
     let mut constraint_section = R1CSWriter::start_constraints_section(r1cs)?;
     let wires = write_constraint_section(&mut constraint_section, &mut log, &tree)? + 1; // adding 1 to include the signal used to represent value 1 in the field (signal one)
@@ -40,17 +44,18 @@ pub fn write(dag: &DAG, output: &str, custom_gates: bool) -> Result<(), ()> {
     let r1cs = header_section.end_section()?;
 
     let mut signal_section = R1CSWriter::start_signal_section(r1cs)?;
-    for signal in 0..labels {
+    for signal in dag.produce_witness() {
         signal_section.write_signal_usize(signal)?;
     }
     let r1cs = signal_section.end_section()?;
     
-    if !custom_gates {
-	R1CSWriter::finish_writing(r1cs)?;
+    let r1cs = if !custom_gates {
+        r1cs
     } else {
         let mut custom_gates_used_section = R1CSWriter::start_custom_gates_used_section(r1cs)?;
-        let (usage_data, occurring_order) = {
+        let (usage_data, relations_data, occurring_order) = {
             let mut usage_data = vec![];
+            let mut relations_data = vec![];
             let mut occurring_order = vec![];
             for node in &dag.nodes {
                 if node.is_custom_gate() {
@@ -58,9 +63,10 @@ pub fn write(dag: &DAG, output: &str, custom_gates: bool) -> Result<(), ()> {
                     occurring_order.push(name.clone());
                     while name.pop() != Some('(') {};
                     usage_data.push((name, node.parameters().clone()));
+                    relations_data.push(node.relations().clone());
                 }
             }
-            (usage_data, occurring_order)
+            (usage_data, relations_data, occurring_order)
         };
         custom_gates_used_section.write_custom_gates_usages(usage_data)?;
         let r1cs = custom_gates_used_section.end_section()?;
@@ -88,7 +94,7 @@ pub fn write(dag: &DAG, output: &str, custom_gates: bool) -> Result<(), ()> {
                     let mut signals = vec![];
                     for signal in &node.ordered_signals {
                         let signal_numbering = node.signal_correspondence.get(signal).unwrap();
-                        signals.push(*signal_numbering + tree.offset);
+                        signals.push(tree.dag.wire(*signal_numbering + tree.offset));
                     }
                     application_data.push((node.template_name.clone(), signals));
                 } else {
@@ -105,8 +111,32 @@ pub fn write(dag: &DAG, output: &str, custom_gates: bool) -> Result<(), ()> {
         };
         custom_gates_applied_section.write_custom_gates_applications(application_data)?;
         let r1cs = custom_gates_applied_section.end_section()?;
-        R1CSWriter::finish_writing(r1cs)?;
-    }
+        if relations {
+            let mut custom_gates_relations_section =
+                R1CSWriter::start_custom_gates_relations_section(r1cs)?;
+            custom_gates_relations_section.write_custom_gates_relations(relations_data)?;
+            custom_gates_relations_section.end_section()?
+        } else {
+            r1cs
+        }
+    };
+    let r1cs = if lookups {
+        let mut lookups_data = (vec![], vec![]);
+        write_lookups_data(&tree, &mut lookups_data);
+        let mut lookups_section = R1CSWriter::start_lookups_section(r1cs)?;
+        lookups_section.write_lookups(lookups_data)?;
+        lookups_section.end_section()?
+    } else {
+        r1cs
+    };
+    let r1cs = if let Some(provenance) = provenance {
+        let mut provenance_section = R1CSWriter::start_provenance_section(r1cs)?;
+        provenance_section.write_provenance(provenance)?;
+        provenance_section.end_section()?
+    } else {
+        r1cs
+    };
+    R1CSWriter::finish_writing(r1cs)?;
 
     Log::print(&log);
     Result::Ok(())
@@ -116,7 +146,7 @@ fn write_constraint_section(
     constraint_section: &mut ConstraintSection,
     log: &mut Log,
     tree: &Tree,
-) -> Result<usize, ()> {
+) -> io::Result<usize> {
     let mut no_signals = tree.signals.len();
     for c in &tree.constraints {
         if Constraint::is_linear(c) {
@@ -124,7 +154,12 @@ fn write_constraint_section(
         } else {
             log.no_non_linear += 1;
         }
-        ConstraintSection::write_constraint_usize(constraint_section, c.a(), c.b(), c.c())?;
+        if tree.dag.public_wires.is_empty() {
+            ConstraintSection::write_constraint_usize(constraint_section, c.a(), c.b(), c.c())?;
+        } else {
+            let c = tree.dag.constraint_wires(c);
+            ConstraintSection::write_constraint_usize(constraint_section, c.a(), c.b(), c.c())?;
+        }
     }
     for edge in Tree::get_edges(tree) {
         let subtree = Tree::go_to_subtree(tree, edge);
@@ -133,3 +168,63 @@ fn write_constraint_section(
     }
     Result::Ok(no_signals)
 }
+
+// Collects the different tables of the lookups and, following the tree, the
+// wires each lookup checks against its table
+fn write_lookups_data(tree: &Tree, data: &mut LookupsData) {
+    let node = &tree.dag.nodes[tree.node_id];
+    if let Some(table) = node.lookup() {
+        let (tables, applications) = data;
+        let index = match tables.iter().position(|t| t == table) {
+            Some(index) => index,
+            None => {
+                tables.push(table.clone());
+                tables.len() - 1
+            }
+        };
+        let wires = (1..=table.arity()).map(|signal| tree.dag.wire(signal + tree.offset)).collect();
+        applications.push((index, wires));
+    }
+    for edge in Tree::get_edges(tree) {
+        let subtree = Tree::go_to_subtree(tree, edge);
+        write_lookups_data(&subtree, data);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{write_lookups_data, Tree, DAG};
+    use circom_algebra::num_bigint::BigInt;
+    use constraint_writers::r1cs_writer::{LookupTable, LookupsData};
+
+    #[test]
+    fn lookups_share_their_tables_and_check_the_wires_of_their_inputs() {
+        let range = LookupTable::Range { bits: 4 };
+        let xor = LookupTable::Rows {
+            columns: 2,
+            rows: vec![vec![BigInt::from(0), BigInt::from(1)], vec![BigInt::from(1), BigInt::from(0)]],
+        };
+        let mut dag = DAG::new(&"bn128".to_string());
+        dag.add_node("RangeCheck".to_string(), vec![BigInt::from(4)], vec!["in".to_string()], false, false);
+        dag.add_input("in".to_string(), false);
+        dag.set_lookup(range.clone());
+        let signals = vec!["in[0]".to_string(), "in[1]".to_string()];
+        dag.add_node("Lookup".to_string(), vec![], signals, false, false);
+        dag.add_input("in[0]".to_string(), false);
+        dag.add_input("in[1]".to_string(), false);
+        dag.set_lookup(xor.clone());
+        dag.add_node("Main".to_string(), vec![], vec!["a".to_string(), "b".to_string()], false, false);
+        dag.add_input("a".to_string(), true);
+        dag.add_input("b".to_string(), false);
+        dag.add_edge(0, "r", false);
+        dag.add_edge(1, "l", false);
+        dag.add_edge(0, "s", false);
+
+        let mut data: LookupsData = (Vec::new(), Vec::new());
+        write_lookups_data(&Tree::new(&dag), &mut data);
+        let (tables, applications) = data;
+        assert_eq!(tables, vec![range, xor]);
+        // main.a and main.b are the wires 1 and 2, followed by the inputs of r, l and s
+        assert_eq!(applications, vec![(0, vec![3]), (1, vec![4, 5]), (0, vec![6])]);
+    }
+}
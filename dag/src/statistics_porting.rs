@@ -1,71 +1,284 @@
-use super::DAG;
-use constraint_writers::debug_writer::DebugWriter;
+use super::{Constraint, DAG};
+use constraint_list::ConstraintList;
+use json::JsonValue;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{self, Write};
 
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub enum StatisticsFormat {
+    Json,
+    Table,
+}
+
+// Counts of the components of a template instance, added over all of them
+#[derive(Default, Clone)]
 struct SlotStatistics {
-    number_of_constraints: usize,
-    number_of_intermediates: usize,
+    number_of_non_linear: usize,
+    number_of_linear: usize,
     number_of_inputs: usize,
     number_of_outputs: usize,
-    number_of_components: usize,
-    times_reached: usize
+    number_of_intermediates: usize,
 }
-impl Default for SlotStatistics {
-    fn default() -> Self {
+
+impl SlotStatistics {
+    fn add_constraint(&mut self, constraint: &Constraint) {
+        if Constraint::is_linear(constraint) {
+            self.number_of_linear += 1;
+        } else {
+            self.number_of_non_linear += 1;
+        }
+    }
+
+    fn add(&mut self, other: &SlotStatistics) {
+        self.number_of_non_linear += other.number_of_non_linear;
+        self.number_of_linear += other.number_of_linear;
+        self.number_of_inputs += other.number_of_inputs;
+        self.number_of_outputs += other.number_of_outputs;
+        self.number_of_intermediates += other.number_of_intermediates;
+    }
+
+    fn times(&self, factor: usize) -> SlotStatistics {
         SlotStatistics {
-            number_of_constraints: 0,
-            number_of_intermediates: 0,
-            number_of_inputs: 0,
-            number_of_outputs: 0,
-            number_of_components: 0,
-            times_reached: 0
+            number_of_non_linear: self.number_of_non_linear * factor,
+            number_of_linear: self.number_of_linear * factor,
+            number_of_inputs: self.number_of_inputs * factor,
+            number_of_outputs: self.number_of_outputs * factor,
+            number_of_intermediates: self.number_of_intermediates * factor,
+        }
+    }
+
+    fn number_of_constraints(&self) -> usize {
+        self.number_of_non_linear + self.number_of_linear
+    }
+
+    fn number_of_signals(&self) -> usize {
+        self.number_of_inputs + self.number_of_outputs + self.number_of_intermediates
+    }
+
+    fn to_json(&self) -> JsonValue {
+        json::object! {
+            "constraints": self.number_of_constraints(),
+            "non_linear": self.number_of_non_linear,
+            "linear": self.number_of_linear,
+            "inputs": self.number_of_inputs,
+            "outputs": self.number_of_outputs,
+            "intermediates": self.number_of_intermediates,
         }
     }
 }
 
-pub fn write_statistics(dag: &DAG,debug: &DebugWriter) -> Result<(),()>{
-    use std::io::Write;
-    let mut writer = debug.build_statistics_file()?;
-    let mut node_statistics = Vec::with_capacity(dag.number_of_nodes());
-    for _i in 0..dag.number_of_nodes() {
-        node_statistics.push(SlotStatistics::default());
+// A node of the DAG is a template instance, a template with the values of
+// its parameters, and it is the instance of one or more components
+struct InstanceStatistics {
+    // the name of the template with the values of the parameters
+    instance_name: String,
+    template_name: String,
+    parameters: Vec<String>,
+    number_of_components: usize,
+    number_of_subcomponents: usize,
+    number_of_outputs: usize,
+    number_of_inputs: usize,
+    // edges of the node as (first signal, last signal, node), the signals
+    // relative to the node
+    edges: Vec<(usize, usize, usize)>,
+    before: SlotStatistics,
+    after: SlotStatistics,
+}
+
+pub struct Statistics {
+    main: usize,
+    offset: usize,
+    instances: Vec<InstanceStatistics>,
+}
+
+impl Statistics {
+    // The statistics before the simplification, the ones after it are the
+    // same until they are set
+    pub fn new(dag: &DAG) -> Statistics {
+        let main = dag.main_id();
+        let mut number_of_components = vec![0; dag.number_of_nodes()];
+        number_of_components[main] = 1;
+        // the subcomponents of a node are always created before it
+        for node in (0..dag.number_of_nodes()).rev() {
+            for edge in &dag.adjacency[node] {
+                number_of_components[edge.goes_to] += number_of_components[node];
+            }
+        }
+        let mut instances = Vec::with_capacity(dag.number_of_nodes());
+        for (id, node) in dag.nodes.iter().enumerate() {
+            let mut before = SlotStatistics {
+                number_of_inputs: node.number_of_inputs(),
+                number_of_outputs: node.number_of_outputs(),
+                number_of_intermediates: node.number_of_intermediates(),
+                ..SlotStatistics::default()
+            };
+            for constraint in node.constraints().iter().filter(|c| !c.is_empty()) {
+                before.add_constraint(constraint);
+            }
+            let before = before.times(number_of_components[id]);
+            instances.push(InstanceStatistics {
+                instance_name: node.template_name.clone(),
+                template_name: node.template_name.split('(').next().unwrap().to_string(),
+                parameters: node.parameters().iter().map(|p| p.to_string()).collect(),
+                number_of_components: number_of_components[id],
+                number_of_subcomponents: dag.adjacency[id].len(),
+                number_of_outputs: node.number_of_outputs(),
+                number_of_inputs: node.number_of_inputs(),
+                edges: dag.adjacency[id].iter().map(|e| (e.in_number + 1, e.out_number, e.goes_to)).collect(),
+                after: before.clone(),
+                before,
+            });
+        }
+        Statistics { main, offset: dag.get_entry().unwrap().in_number, instances }
+    }
+
+    // A constraint of the simplified circuit is counted in the deepest
+    // component that declares one of its signals, since the simplification
+    // replaces the signals of the inner components by the ones of the outer
+    // components when they are equal. A signal is counted in the component
+    // that declares it.
+    pub fn set_simplified(&mut self, list: &ConstraintList) {
+        for instance in &mut self.instances {
+            instance.after = SlotStatistics::default();
+        }
+        for id in list.constraints.get_ids() {
+            let constraint = list.constraints.read_constraint(id).unwrap();
+            let mut signals: Vec<usize> = constraint.take_cloned_signals().into_iter().filter(|s| *s != 0).collect();
+            signals.sort_unstable();
+            let deepest = signals.iter().map(|signal| self.owner(*signal)).max_by_key(|(_, _, depth)| *depth);
+            let node = deepest.map_or(self.main, |(node, _, _)| node);
+            self.instances[node].after.add_constraint(&constraint);
+        }
+        for signal in list.signal_map.keys().filter(|s| **s != 0) {
+            let (node, offset, _) = self.owner(*signal);
+            let instance = &mut self.instances[node];
+            let local = signal - offset;
+            if local <= instance.number_of_outputs {
+                instance.after.number_of_outputs += 1;
+            } else if local <= instance.number_of_outputs + instance.number_of_inputs {
+                instance.after.number_of_inputs += 1;
+            } else {
+                instance.after.number_of_intermediates += 1;
+            }
+        }
+    }
+
+    // The node, the offset and the depth of the component that declares the signal
+    fn owner(&self, signal: usize) -> (usize, usize, usize) {
+        let (mut node, mut offset, mut depth) = (self.main, self.offset, 0);
+        loop {
+            let local = signal - offset;
+            let edges = &self.instances[node].edges;
+            let position = edges.partition_point(|(start, _, _)| *start <= local);
+            match position.checked_sub(1).map(|p| edges[p]) {
+                Some((start, end, goes_to)) if local <= end => {
+                    offset += start - 1;
+                    node = goes_to;
+                    depth += 1;
+                }
+                _ => return (node, offset, depth),
+            }
+        }
     }
-    for i in 0..dag.number_of_nodes() {
-        node_statistics[i].number_of_outputs = dag.nodes[i].number_of_outputs();
-        node_statistics[i].number_of_inputs = dag.nodes[i].number_of_inputs();
-        node_statistics[i].number_of_intermediates = dag.nodes[i].number_of_intermediates();
-        node_statistics[i].number_of_constraints = dag.nodes[i].constraints().len();
-        node_statistics[i].number_of_components = dag.adjacency[i].len();
-        for arrow in &dag.adjacency[i] {
-            let pointer = arrow.goes_to;
-            node_statistics[pointer].times_reached += 1;
+
+    fn templates(&self) -> BTreeMap<&str, (usize, usize, SlotStatistics, SlotStatistics)> {
+        let mut templates: BTreeMap<&str, (usize, usize, SlotStatistics, SlotStatistics)> = BTreeMap::new();
+        for instance in self.instances.iter().filter(|i| i.number_of_components > 0) {
+            let entry = templates.entry(&instance.template_name).or_default();
+            entry.0 += 1;
+            entry.1 += instance.number_of_components;
+            entry.2.add(&instance.before);
+            entry.3.add(&instance.after);
         }
+        templates
     }
-    for (index,node_statistic) in node_statistics.iter().enumerate(){
-        let splitter = "***************************************\n".to_string();
-        let title = format!("NODE NUMBER: {}\n",index);
-        let number_of_constraints = format!("Number of constraints: {}\n",node_statistic.number_of_constraints);
-        let number_of_intermediates = format!("Number of intermediates: {}\n", node_statistic.number_of_intermediates);
-        let number_of_inputs = format!("Number of inputs: {}\n",node_statistic.number_of_inputs);
-        let number_of_outputs = format!("Number of outputs: {}\n",node_statistic.number_of_outputs);
-        let number_of_components = format!("Number of components: {}\n",node_statistic.number_of_components);
-        let times_reached = format!("Times reached: {}\n",node_statistic.times_reached);
-
-        writer.write_all(splitter.as_bytes()).map_err(|_err| {})?;
-        writer.flush().map_err(|_err| {})?;
-        writer.write_all(title.as_bytes()).map_err(|_err| {})?;
-        writer.flush().map_err(|_err| {})?;
-        writer.write_all(number_of_constraints.as_bytes()).map_err(|_err| {})?;
-        writer.flush().map_err(|_err| {})?;
-        writer.write_all(number_of_intermediates.as_bytes()).map_err(|_err| {})?;
-        writer.flush().map_err(|_err| {})?;
-        writer.write_all(number_of_inputs.as_bytes()).map_err(|_err| {})?;
-        writer.flush().map_err(|_err| {})?;
-        writer.write_all(number_of_outputs.as_bytes()).map_err(|_err| {})?;
-        writer.flush().map_err(|_err| {})?;
-        writer.write_all(number_of_components.as_bytes()).map_err(|_err| {})?;
-        writer.flush().map_err(|_err| {})?;
-        writer.write_all(times_reached.as_bytes()).map_err(|_err| {})?;
-        writer.flush().map_err(|_err| {})?;
+
+    // The instances with more constraints after the simplification go first
+    fn ordered_instances(&self) -> Vec<&InstanceStatistics> {
+        let mut instances: Vec<_> = self.instances.iter().filter(|i| i.number_of_components > 0).collect();
+        instances.sort_by_key(|i| std::cmp::Reverse(i.after.number_of_constraints()));
+        instances
+    }
+
+    pub fn to_json(&self) -> JsonValue {
+        let instances: Vec<JsonValue> = self
+            .ordered_instances()
+            .into_iter()
+            .map(|instance| {
+                json::object! {
+                    "instance": instance.instance_name.as_str(),
+                    "template": instance.template_name.as_str(),
+                    "parameters": instance.parameters.clone(),
+                    "components": instance.number_of_components,
+                    "subcomponents": instance.number_of_subcomponents,
+                    "before": instance.before.to_json(),
+                    "after": instance.after.to_json(),
+                }
+            })
+            .collect();
+        let templates: Vec<JsonValue> = self
+            .templates()
+            .into_iter()
+            .map(|(name, (number_of_instances, number_of_components, before, after))| {
+                json::object! {
+                    "template": name,
+                    "instances": number_of_instances,
+                    "components": number_of_components,
+                    "before": before.to_json(),
+                    "after": after.to_json(),
+                }
+            })
+            .collect();
+        json::object! { "instances": instances, "templates": templates }
+    }
+
+    pub fn write_json(&self, file: &str) -> io::Result<()> {
+        let mut writer = File::create(file)?;
+        writer.write_all(json::stringify_pretty(self.to_json(), 2).as_bytes())?;
+        writer.flush()
+    }
+
+    pub fn print_table(&self) {
+        print!("{}", self.table());
+    }
+
+    // The instances and the templates with their number of components and
+    // their constraints and signals before and after the simplification
+    pub fn table(&self) -> String {
+        let header = ["components", "constraints", "after", "signals", "after"];
+        let mut rows = Vec::new();
+        for instance in self.ordered_instances() {
+            rows.push((instance.instance_name.clone(), instance.number_of_components, &instance.before, &instance.after));
+        }
+        let templates = self.templates();
+        let mut template_rows = Vec::new();
+        for (name, (_, number_of_components, before, after)) in &templates {
+            template_rows.push((name.to_string(), *number_of_components, before, after));
+        }
+        template_rows.sort_by_key(|row| std::cmp::Reverse(row.3.number_of_constraints()));
+        let width = rows.iter().chain(template_rows.iter()).map(|row| row.0.len()).max().unwrap_or(0).max(8);
+        let section = |title: &str, rows: &[(String, usize, &SlotStatistics, &SlotStatistics)]| {
+            let mut table = String::new();
+            table.push_str(&format!("{:<width$}", title, width = width));
+            for column in &header {
+                table.push_str(&format!("  {:>11}", column));
+            }
+            table.push('\n');
+            for (name, number_of_components, before, after) in rows {
+                table.push_str(&format!(
+                    "{:<width$}  {:>11}  {:>11}  {:>11}  {:>11}  {:>11}\n",
+                    name,
+                    number_of_components,
+                    before.number_of_constraints(),
+                    after.number_of_constraints(),
+                    before.number_of_signals(),
+                    after.number_of_signals(),
+                    width = width
+                ));
+            }
+            table
+        };
+        format!("{}\n{}", section("instance", &rows), section("template", &template_rows))
     }
-    Result::Ok(())
-}
\ No newline at end of file
+}
@@ -17,7 +17,7 @@ fn visit_tree(tree: &Tree, dot_sym: &mut SymFile) -> Result<(), ()> {
         let name = HashMap::get(&tree.id_to_name, signal).unwrap();
         let symbol = format!("{}.{}", tree.path, name);
         let original = signal.as_();
-        let witness = original;
+        let witness = tree.dag.wire(*signal).as_();
         let node_id = tree.node_id.as_();
         let sym_elem = SymElem { original, witness, node_id, symbol };
         SymFile::write_sym_elem(dot_sym, sym_elem)?;
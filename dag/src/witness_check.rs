@@ -0,0 +1,307 @@
+use super::{Constraint, Tree, DAG};
+use circom_algebra::modular_arithmetic;
+use circom_algebra::num_bigint::{BigInt, RandBigInt};
+use circom_algebra::num_traits::Zero;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+// Constraint of the circuit that does not hold for a witness
+pub struct UnsatisfiedConstraint {
+    pub component: String,
+    pub template: String,
+    // the constraint written with the names of its signals
+    pub constraint: String,
+}
+
+// Signals of the witness that are changed to check that the constraints
+// reject the change. A signal without value is given a random one.
+pub enum Tampering {
+    AllSignals,
+    Signals(Vec<(String, Option<BigInt>)>),
+}
+
+// Signal that can take a value different from the computed one while every
+// constraint of the circuit still holds
+pub struct UnderConstrainedSignal {
+    pub signal: String,
+    pub computed: BigInt,
+    pub value: BigInt,
+}
+
+pub(super) struct CheckedNode {
+    pub(super) path: String,
+    pub(super) template: String,
+}
+
+pub(super) struct Checker {
+    pub(super) field: BigInt,
+    root: String,
+    pub(super) names: BTreeMap<usize, String>,
+    pub(super) nodes: Vec<CheckedNode>,
+    // constraints with the node they belong to
+    pub(super) constraints: Vec<(usize, Constraint)>,
+    // constraints where each signal appears
+    occurrences: HashMap<usize, Vec<usize>>,
+}
+
+impl Checker {
+    pub(super) fn new(dag: &DAG) -> Checker {
+        let tree = Tree::new(dag);
+        let mut checker = Checker {
+            field: tree.field.clone(),
+            root: tree.path.clone(),
+            names: BTreeMap::new(),
+            nodes: Vec::new(),
+            constraints: Vec::new(),
+            occurrences: HashMap::new(),
+        };
+        checker.collect(&tree);
+        for (index, (_, constraint)) in checker.constraints.iter().enumerate() {
+            for signal in constraint.take_signals() {
+                checker.occurrences.entry(*signal).or_default().push(index);
+            }
+        }
+        checker
+    }
+
+    fn collect(&mut self, tree: &Tree) {
+        for signal in &tree.signals {
+            let local = signal - tree.offset;
+            let name = tree.id_to_name.get(signal).or_else(|| tree.id_to_name.get(&local));
+            if let Some(name) = name {
+                self.names.insert(*signal, format!("{}.{}", tree.path, name));
+            }
+        }
+        let node = self.nodes.len();
+        self.nodes.push(CheckedNode {
+            path: tree.path.clone(),
+            template: tree.dag.nodes[tree.node_id].template_name.clone(),
+        });
+        for constraint in &tree.constraints {
+            self.constraints.push((node, constraint.clone()));
+        }
+        for edge in Tree::get_edges(tree) {
+            self.collect(&Tree::go_to_subtree(tree, edge));
+        }
+    }
+
+    fn holds(&self, constraint: &Constraint, witness: &[BigInt]) -> bool {
+        let a = evaluate(constraint.a(), witness, &self.field);
+        let b = evaluate(constraint.b(), witness, &self.field);
+        let c = evaluate(constraint.c(), witness, &self.field);
+        modular_arithmetic::sub(&modular_arithmetic::mul(&a, &b, &self.field), &c, &self.field).is_zero()
+    }
+
+    // Name of the signal from the component that is checked, without its path
+    fn relative_name(&self, signal: usize) -> String {
+        let name = &self.names[&signal];
+        name.strip_prefix(&format!("{}.", self.root)).unwrap_or(name).to_string()
+    }
+
+    fn find_signal(&self, name: &str) -> Option<usize> {
+        self.names.keys().cloned().find(|signal| self.relative_name(*signal) == name)
+    }
+
+    // Checks if the constraints where the changed signals appear hold, every
+    // other constraint holds for the given witness
+    fn accepts_changes(&self, witness: &mut [BigInt], changes: &[(usize, BigInt)]) -> bool {
+        let mut affected: BTreeSet<usize> = BTreeSet::new();
+        let mut computed = Vec::new();
+        for (signal, value) in changes {
+            computed.push((*signal, std::mem::replace(&mut witness[*signal], value.clone())));
+            affected.extend(self.occurrences.get(signal).into_iter().flatten().cloned());
+        }
+        let accepted = affected.iter().all(|index| self.holds(&self.constraints[*index].1, witness));
+        for (signal, value) in computed.into_iter().rev() {
+            witness[signal] = value;
+        }
+        accepted
+    }
+}
+
+// The witness is indexed by signal number, the position 0 holds the constant 1
+pub fn check(dag: &DAG, witness: &[BigInt]) -> Vec<UnsatisfiedConstraint> {
+    let checker = Checker::new(dag);
+    let mut unsatisfied = Vec::new();
+    for (node, constraint) in &checker.constraints {
+        if !checker.holds(constraint, witness) {
+            unsatisfied.push(UnsatisfiedConstraint {
+                component: checker.nodes[*node].path.clone(),
+                template: checker.nodes[*node].template.clone(),
+                constraint: display(constraint, &checker.names, &checker.field),
+            });
+        }
+    }
+    unsatisfied
+}
+
+// Changes the signals of a witness that satisfies the constraints. With
+// AllSignals, each signal is changed on its own to a random value, and the
+// given signals are changed together otherwise. The error is the name of a
+// tampered signal that does not exist.
+pub fn find_under_constrained(
+    dag: &DAG,
+    witness: &[BigInt],
+    tampering: &Tampering,
+) -> Result<Vec<UnderConstrainedSignal>, String> {
+    let checker = Checker::new(dag);
+    let mut witness = witness.to_vec();
+    let mut rng = rand::thread_rng();
+    let mut random_value = |computed: &BigInt| loop {
+        let value = rng.gen_bigint_range(&BigInt::zero(), &checker.field);
+        if value != *computed {
+            break value;
+        }
+    };
+    let mut under_constrained = Vec::new();
+    match tampering {
+        Tampering::AllSignals => {
+            for signal in checker.names.keys() {
+                let value = random_value(&witness[*signal]);
+                if checker.accepts_changes(&mut witness, &[(*signal, value.clone())]) {
+                    let computed = witness[*signal].clone();
+                    under_constrained.push(UnderConstrainedSignal { signal: checker.relative_name(*signal), computed, value });
+                }
+            }
+        }
+        Tampering::Signals(signals) => {
+            let mut changes = Vec::new();
+            for (name, value) in signals {
+                let signal = checker.find_signal(name).ok_or_else(|| name.clone())?;
+                let value = match value {
+                    Some(value) => modular_arithmetic::add(value, &BigInt::zero(), &checker.field),
+                    None => random_value(&witness[signal]),
+                };
+                changes.push((signal, value));
+            }
+            // changing a signal to the computed value does not tamper the witness
+            changes.retain(|(signal, value)| witness[*signal] != *value);
+            if !changes.is_empty() && checker.accepts_changes(&mut witness, &changes) {
+                for (signal, value) in changes {
+                    let computed = witness[signal].clone();
+                    under_constrained.push(UnderConstrainedSignal { signal: checker.relative_name(signal), computed, value });
+                }
+            }
+        }
+    }
+    Result::Ok(under_constrained)
+}
+
+fn evaluate(expression: &HashMap<usize, BigInt>, witness: &[BigInt], field: &BigInt) -> BigInt {
+    let mut result = BigInt::zero();
+    for (signal, coefficient) in expression {
+        let value = witness.get(*signal).cloned().unwrap_or_else(BigInt::zero);
+        result = modular_arithmetic::add(&result, &modular_arithmetic::mul(coefficient, &value, field), field);
+    }
+    result
+}
+
+pub(super) fn display(constraint: &Constraint, names: &BTreeMap<usize, String>, field: &BigInt) -> String {
+    let c = display_expression(constraint.c(), names, field);
+    if Constraint::is_linear(constraint) {
+        format!("{} = 0", c)
+    } else {
+        let a = display_expression(constraint.a(), names, field);
+        let b = display_expression(constraint.b(), names, field);
+        format!("({}) * ({}) - ({}) = 0", a, b, c)
+    }
+}
+
+fn display_expression(expression: &HashMap<usize, BigInt>, names: &BTreeMap<usize, String>, field: &BigInt) -> String {
+    let mut signals: Vec<&usize> = expression.keys().collect();
+    signals.sort();
+    let mut text = String::new();
+    for signal in signals {
+        let coefficient = &expression[signal];
+        if coefficient.is_zero() {
+            continue;
+        }
+        // coefficients over p/2 are shown as negative numbers
+        let negative = coefficient > &(field / 2);
+        let magnitude = if negative { field - coefficient } else { coefficient.clone() };
+        let term = if *signal == 0 {
+            magnitude.to_string()
+        } else {
+            let name = names.get(signal).cloned().unwrap_or_else(|| format!("signal {}", signal));
+            if magnitude == BigInt::from(1) {
+                name
+            } else {
+                format!("{}*{}", magnitude, name)
+            }
+        };
+        if text.is_empty() {
+            text = if negative { format!("-{}", term) } else { term };
+        } else {
+            text = format!("{} {} {}", text, if negative { "-" } else { "+" }, term);
+        }
+    }
+    if text.is_empty() {
+        "0".to_string()
+    } else {
+        text
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Tampering, DAG};
+    use crate::ConstraintLocation;
+    use circom_algebra::algebra::ArithmeticExpression;
+    use circom_algebra::num_bigint::BigInt;
+    use program_structure::constants::UsefulConstants;
+    use std::collections::HashMap;
+
+    const PRIME: &str = "bn128";
+
+    // out = in * in, where free is in no constraint
+    fn square() -> DAG {
+        let field = UsefulConstants::new(PRIME).get_p().clone();
+        let mut dag = DAG::new(&PRIME.to_string());
+        let signals = vec!["out".to_string(), "in".to_string(), "free".to_string()];
+        dag.add_node("Square()".to_string(), vec![], signals, false, false);
+        dag.add_output("out".to_string());
+        dag.add_input("in".to_string(), false);
+        dag.add_intermediate("free".to_string());
+        // the expressions always have the constant term, the signal 0
+        let signal = |signal: usize, value: BigInt| HashMap::from([(0, BigInt::from(0)), (signal, value)]);
+        let a = signal(2, BigInt::from(1));
+        let c = signal(1, &field - 1);
+        let expression = ArithmeticExpression::Quadratic { a: a.clone(), b: a, c };
+        let constraint = ArithmeticExpression::transform_expression_to_constraint_form(expression, &field).unwrap();
+        dag.add_constraint(constraint, ConstraintLocation { file: None, line: 1 });
+        dag
+    }
+
+    fn witness(values: &[u64]) -> Vec<BigInt> {
+        values.iter().map(|value| BigInt::from(*value)).collect()
+    }
+
+    #[test]
+    fn unsatisfied_constraints_are_shown_with_the_names_of_their_signals() {
+        let dag = square();
+        assert!(dag.check_witness(&witness(&[1, 9, 3, 5])).is_empty());
+        let unsatisfied = dag.check_witness(&witness(&[1, 10, 3, 5]));
+        assert_eq!(unsatisfied.len(), 1);
+        assert_eq!(unsatisfied[0].template, "Square()");
+        assert_eq!(unsatisfied[0].constraint, "(main.in) * (main.in) - (main.out) = 0");
+    }
+
+    #[test]
+    fn signals_out_of_the_constraints_are_under_constrained() {
+        let dag = square();
+        let witness = witness(&[1, 9, 3, 5]);
+        let under_constrained = dag.find_under_constrained_signals(&witness, &Tampering::AllSignals).unwrap();
+        let signals: Vec<&str> = under_constrained.iter().map(|signal| signal.signal.as_str()).collect();
+        assert_eq!(signals, vec!["free"]);
+        assert_eq!(under_constrained[0].computed, BigInt::from(5));
+        // out and in can only change together
+        let tampering = |changes: &[(&str, u64)]| {
+            Tampering::Signals(changes.iter().map(|(name, value)| (name.to_string(), Some(BigInt::from(*value)))).collect())
+        };
+        let changed = dag.find_under_constrained_signals(&witness, &tampering(&[("out", 16)])).unwrap();
+        assert!(changed.is_empty());
+        let changed = dag.find_under_constrained_signals(&witness, &tampering(&[("out", 16), ("in", 4)])).unwrap();
+        assert_eq!(changed.len(), 2);
+        let missing = dag.find_under_constrained_signals(&witness, &tampering(&[("missing", 1)]));
+        assert_eq!(missing.err(), Some("missing".to_string()));
+    }
+}
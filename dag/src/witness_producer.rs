@@ -4,6 +4,9 @@ pub fn produce_witness(dag: &DAG) -> Vec<usize> {
     let mut witness = vec![0];
     let tree = Tree::new(dag);
     produce_tree_witness(&tree, &mut witness);
+    for (signal, wire) in &dag.public_wires {
+        witness[*wire] = *signal;
+    }
     Vec::shrink_to_fit(&mut witness);
     witness
 }
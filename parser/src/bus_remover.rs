@@ -0,0 +1,1079 @@
+use program_structure::ast::*;
+use program_structure::error_code::ReportCode;
+use program_structure::error_definition::{Report, ReportCollection};
+use program_structure::expression_builders::*;
+use program_structure::file_definition::FileID;
+use program_structure::statement_builders::*;
+use std::collections::HashMap;
+
+// Buses are removed before building the program archive. Every signal whose
+// type is a bus is replaced by one signal for each field of the bus, named
+// after the path to the field: a bus p with fields x and y becomes the signals
+// p.x and p.y. The dimensions of the bus are placed before the dimensions of
+// the field and written as [] in the name of the field (p[].x), so that the
+// symbols of the field are generated as p[0].x, p[1].x and so on.
+
+pub fn remove_buses(
+    definitions: &mut [(FileID, Vec<Definition>)],
+    main_component: &mut MainComponent,
+) -> Result<(), ReportCollection> {
+    let mut reports = Vec::new();
+    let mut buses = BusLibrary::new();
+    for (file_id, file_definitions) in definitions.iter() {
+        for definition in file_definitions {
+            if let Definition::Bus { meta, name, args, body, .. } = definition {
+                match BusData::new(meta, args, body, *file_id) {
+                    Ok(data) => {
+                        if buses.insert(name.clone(), data).is_some() {
+                            reports.push(error(meta, *file_id, format!("The bus {} is declared twice", name)));
+                        }
+                    }
+                    Err(report) => reports.push(*report),
+                }
+            }
+        }
+    }
+    for (name, data) in &buses {
+        for field in &data.fields {
+            if let FieldKind::Bus(inner, _) = &field.kind {
+                if !buses.contains_key(inner) {
+                    reports.push(error(&data.meta, data.file_id, format!("The bus {} does not exist", inner)));
+                }
+            }
+        }
+        if reports.is_empty() && !check_bus_cycles(&buses, name, &mut Vec::new()) {
+            reports.push(error(&data.meta, data.file_id, format!("The bus {} contains itself", name)));
+        }
+    }
+    let mut templates = HashMap::new();
+    for (file_id, file_definitions) in definitions.iter() {
+        for definition in file_definitions {
+            match definition {
+                Definition::Template { meta, name, body, .. } => {
+                    if buses.contains_key(name) {
+                        reports.push(error(meta, *file_id, format!("{} is declared as a bus and as a template", name)));
+                    }
+                    let mut signals = TemplateBuses::default();
+                    signals.collect(body);
+                    for (bus, meta) in &signals.declared {
+                        if !buses.contains_key(bus) {
+                            reports.push(error(meta, *file_id, format!("The bus {} does not exist", bus)));
+                        }
+                    }
+                    templates.insert(name.clone(), signals);
+                }
+                Definition::Function { meta, name, body, .. } => {
+                    if buses.contains_key(name) {
+                        reports.push(error(meta, *file_id, format!("{} is declared as a bus and as a function", name)));
+                    }
+                    if declares_bus(body) {
+                        reports.push(error(meta, *file_id, "Buses cannot be declared in functions".to_string()));
+                    }
+                }
+                Definition::Bus { .. } | Definition::Test { .. } | Definition::Constant { .. } => {}
+            }
+        }
+    }
+    if !reports.is_empty() {
+        return Err(reports);
+    }
+    if buses.is_empty() {
+        return Ok(());
+    }
+
+    for (file_id, file_definitions) in definitions.iter_mut() {
+        file_definitions.retain(|definition| !matches!(definition, Definition::Bus { .. }));
+        for definition in file_definitions.iter_mut() {
+            if let Definition::Template { name, body, .. } = definition {
+                let context = Context {
+                    buses: &buses,
+                    templates: &templates,
+                    local: templates.get(name).unwrap(),
+                    components: collect_components(body, &templates),
+                    file_id: *file_id,
+                };
+                let old_body = std::mem::replace(body, build_block(body.get_meta().clone(), Vec::new()));
+                match context.statement(old_body) {
+                    Ok(mut stmts) => *body = stmts.pop().unwrap(),
+                    Err(report) => reports.push(*report),
+                }
+            }
+        }
+    }
+    if let (public, Expression::Call { id, .. }) = main_component {
+        if let Some(signals) = templates.get(id) {
+            let mut expanded = Vec::new();
+            for name in public.iter() {
+                match signals.signals.get(name) {
+                    Some((bus, dimensions, _)) => {
+                        let prefix = field_prefix(name, *dimensions);
+                        for leaf in buses.leaves(bus, None) {
+                            expanded.push(format!("{}.{}", prefix, leaf.path));
+                        }
+                    }
+                    None => expanded.push(name.clone()),
+                }
+            }
+            *public = expanded;
+        }
+    }
+    if reports.is_empty() {
+        Ok(())
+    } else {
+        Err(reports)
+    }
+}
+
+fn error(meta: &Meta, file_id: FileID, msg: String) -> Report {
+    let mut report = Report::error(msg, ReportCode::BusError);
+    report.add_primary(meta.location.clone(), file_id, "found here".to_string());
+    report
+}
+
+// The name of a bus or bus field with its dimensions written as []
+fn field_prefix(name: &str, dimensions: usize) -> String {
+    format!("{}{}", name, "[]".repeat(dimensions))
+}
+
+enum FieldKind {
+    Signal(TagList),
+    Bus(String, Vec<Expression>),
+}
+
+struct Field {
+    name: String,
+    dimensions: Vec<Expression>,
+    kind: FieldKind,
+}
+
+struct BusData {
+    meta: Meta,
+    file_id: FileID,
+    params: Vec<String>,
+    fields: Vec<Field>,
+}
+
+impl BusData {
+    fn new(meta: &Meta, params: &[String], body: &Statement, file_id: FileID) -> Result<BusData, Box<Report>> {
+        let mut fields: Vec<Field> = Vec::new();
+        let blocks = match body {
+            Statement::Block { stmts, .. } => stmts,
+            _ => unreachable!(),
+        };
+        for block in blocks {
+            if let Statement::InitializationBlock { initializations, .. } = block {
+                let mut initializations = initializations.iter();
+                while let Some(init) = initializations.next() {
+                    if let Statement::Declaration { meta, xtype, name, dimensions, .. } = init {
+                        let kind = match xtype {
+                            VariableType::Signal(SignalType::Intermediate, tags) => FieldKind::Signal(tags.clone()),
+                            VariableType::Bus(bus, SignalType::Intermediate, _) => {
+                                match initializations.next() {
+                                    Some(Statement::Substitution { rhe: Expression::Call { args, .. }, .. }) => {
+                                        FieldKind::Bus(bus.clone(), args.clone())
+                                    }
+                                    _ => unreachable!(),
+                                }
+                            }
+                            _ => {
+                                return Err(Box::new(error(meta, file_id, format!("The field {} of a bus cannot be an input or an output", name))));
+                            }
+                        };
+                        if fields.iter().any(|field| field.name == *name) {
+                            return Err(Box::new(error(meta, file_id, format!("The field {} is declared twice", name))));
+                        }
+                        fields.push(Field { name: name.clone(), dimensions: dimensions.clone(), kind });
+                    }
+                }
+            }
+        }
+        if fields.is_empty() {
+            return Err(Box::new(error(meta, file_id, "A bus must have at least one field".to_string())));
+        }
+        Ok(BusData { meta: meta.clone(), file_id, params: params.to_vec(), fields })
+    }
+
+    fn field(&self, name: &str) -> Option<&Field> {
+        self.fields.iter().find(|field| field.name == name)
+    }
+}
+
+// A signal field reachable from a bus: the path from the bus to the field,
+// the dimensions of the path and the tags of the field
+struct Leaf {
+    path: String,
+    dimensions: Vec<Expression>,
+    tags: TagList,
+}
+
+type BusLibrary = HashMap<String, BusData>;
+
+trait Leaves {
+    fn leaves(&self, bus: &str, args: Option<&[Expression]>) -> Vec<Leaf>;
+}
+
+impl Leaves for BusLibrary {
+    // When the arguments are not given the dimensions of the leaves are left
+    // in terms of the parameters of the bus; only their paths are used then.
+    fn leaves(&self, bus: &str, args: Option<&[Expression]>) -> Vec<Leaf> {
+        let data = self.get(bus).unwrap();
+        let instantiate = |expression: &Expression| match args {
+            Some(args) => substitute(expression, &data.params, args),
+            None => expression.clone(),
+        };
+        let mut leaves = Vec::new();
+        for field in &data.fields {
+            let dimensions: Vec<Expression> = field.dimensions.iter().map(instantiate).collect();
+            match &field.kind {
+                FieldKind::Signal(tags) => {
+                    leaves.push(Leaf { path: field.name.clone(), dimensions, tags: tags.clone() });
+                }
+                FieldKind::Bus(inner, inner_args) => {
+                    let inner_args: Vec<Expression> = inner_args.iter().map(instantiate).collect();
+                    let inner_args = args.map(|_| inner_args.as_slice());
+                    let prefix = field_prefix(&field.name, field.dimensions.len());
+                    for leaf in self.leaves(inner, inner_args) {
+                        let mut leaf_dimensions = dimensions.clone();
+                        leaf_dimensions.extend(leaf.dimensions);
+                        leaves.push(Leaf {
+                            path: format!("{}.{}", prefix, leaf.path),
+                            dimensions: leaf_dimensions,
+                            tags: leaf.tags,
+                        });
+                    }
+                }
+            }
+        }
+        leaves
+    }
+}
+
+fn check_bus_cycles(buses: &BusLibrary, bus: &str, visiting: &mut Vec<String>) -> bool {
+    if visiting.iter().any(|b| b == bus) {
+        return false;
+    }
+    visiting.push(bus.to_string());
+    let acyclic = buses.get(bus).unwrap().fields.iter().all(|field| match &field.kind {
+        FieldKind::Bus(inner, _) => buses.contains_key(inner) && check_bus_cycles(buses, inner, visiting),
+        FieldKind::Signal(_) => true,
+    });
+    visiting.pop();
+    acyclic
+}
+
+// Replaces the parameters of a bus by the arguments of its declaration
+fn substitute(expression: &Expression, params: &[String], args: &[Expression]) -> Expression {
+    use Expression::*;
+    let mut expression = expression.clone();
+    fn walk(expression: &mut Expression, params: &[String], args: &[Expression]) {
+        match expression {
+            InfixOp { lhe, rhe, .. } => {
+                walk(lhe, params, args);
+                walk(rhe, params, args);
+            }
+            PrefixOp { rhe, .. } | ParallelOp { rhe, .. } => walk(rhe, params, args),
+            InlineSwitchOp { cond, if_true, if_false, .. } => {
+                walk(cond, params, args);
+                walk(if_true, params, args);
+                walk(if_false, params, args);
+            }
+            Variable { name, access, .. } => {
+                for acc in access.iter_mut() {
+                    if let Access::ArrayAccess(index) = acc {
+                        walk(index, params, args);
+                    }
+                }
+                if let Some(position) = params.iter().position(|param| param == name) {
+                    match args.get(position) {
+                        Some(Variable { name: arg, access: arg_access, .. }) => {
+                            let mut new_access = arg_access.clone();
+                            new_access.append(access);
+                            *name = arg.clone();
+                            *access = new_access;
+                        }
+                        Some(arg) if access.is_empty() => *expression = arg.clone(),
+                        _ => {}
+                    }
+                }
+            }
+            Number(..) => {}
+            Call { args: call_args, .. } => {
+                for arg in call_args {
+                    walk(arg, params, args);
+                }
+            }
+            AnonymousComp { params: comp_params, signals, .. } => {
+                for arg in comp_params.iter_mut().chain(signals.iter_mut()) {
+                    walk(arg, params, args);
+                }
+            }
+            ArrayInLine { values, .. } | Tuple { values, .. } => {
+                for value in values {
+                    walk(value, params, args);
+                }
+            }
+            UniformArray { value, dimension, .. } => {
+                walk(value, params, args);
+                walk(dimension, params, args);
+            }
+        }
+    }
+    walk(&mut expression, params, args);
+    expression
+}
+
+// The bus signals declared by a template: bus, number of dimensions and type,
+// and the order in which its inputs and outputs are declared
+#[derive(Default)]
+struct TemplateBuses {
+    signals: HashMap<String, (String, usize, SignalType)>,
+    declared: Vec<(String, Meta)>,
+    inputs: Vec<String>,
+    outputs: Vec<String>,
+}
+
+impl TemplateBuses {
+    fn collect(&mut self, stmt: &Statement) {
+        use Statement::*;
+        match stmt {
+            IfThenElse { if_case, else_case, .. } => {
+                self.collect(if_case);
+                if let Some(else_case) = else_case {
+                    self.collect(else_case);
+                }
+            }
+            While { stmt, .. } => self.collect(stmt),
+            Block { stmts, .. } => stmts.iter().for_each(|stmt| self.collect(stmt)),
+            InitializationBlock { initializations, .. } => {
+                initializations.iter().for_each(|stmt| self.collect(stmt))
+            }
+            Declaration { meta, xtype, name, dimensions, .. } => {
+                let signal_type = match xtype {
+                    VariableType::Bus(bus, signal_type, _) => {
+                        self.signals.insert(name.clone(), (bus.clone(), dimensions.len(), *signal_type));
+                        self.declared.push((bus.clone(), meta.clone()));
+                        *signal_type
+                    }
+                    VariableType::Signal(signal_type, _) => *signal_type,
+                    _ => return,
+                };
+                match signal_type {
+                    SignalType::Input => self.inputs.push(name.clone()),
+                    SignalType::Output => self.outputs.push(name.clone()),
+                    SignalType::Intermediate => {}
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn io_bus(&self, signal: &str) -> Option<&(String, usize, SignalType)> {
+        self.signals.get(signal).filter(|(_, _, signal_type)| *signal_type != SignalType::Intermediate)
+    }
+}
+
+fn declares_bus(stmt: &Statement) -> bool {
+    use Statement::*;
+    match stmt {
+        IfThenElse { if_case, else_case, .. } => {
+            declares_bus(if_case) || else_case.as_ref().is_some_and(|s| declares_bus(s))
+        }
+        While { stmt, .. } => declares_bus(stmt),
+        Block { stmts, .. } => stmts.iter().any(declares_bus),
+        InitializationBlock { xtype: VariableType::Bus(..), .. } => true,
+        InitializationBlock { initializations, .. } => initializations.iter().any(declares_bus),
+        _ => false,
+    }
+}
+
+// The templates instantiated by each component of a template
+fn collect_components(body: &Statement, templates: &HashMap<String, TemplateBuses>) -> HashMap<String, Vec<String>> {
+    fn walk(stmt: &Statement, templates: &HashMap<String, TemplateBuses>, result: &mut HashMap<String, Vec<String>>) {
+        use Statement::*;
+        match stmt {
+            IfThenElse { if_case, else_case, .. } => {
+                walk(if_case, templates, result);
+                if let Some(else_case) = else_case {
+                    walk(else_case, templates, result);
+                }
+            }
+            While { stmt, .. } => walk(stmt, templates, result),
+            Block { stmts, .. } | InitializationBlock { initializations: stmts, .. } => {
+                stmts.iter().for_each(|stmt| walk(stmt, templates, result))
+            }
+            Substitution { var, rhe: Expression::Call { id, .. }, .. } if templates.contains_key(id) => {
+                let entry = result.entry(var.clone()).or_default();
+                if !entry.contains(id) {
+                    entry.push(id.clone());
+                }
+            }
+            _ => {}
+        }
+    }
+    let mut result = HashMap::new();
+    walk(body, templates, &mut result);
+    result
+}
+
+// A bus reached through a variable: either a local bus or a bus signal of a
+// component, possibly followed by accesses to some of its fields
+struct BusValue {
+    meta: Meta,
+    bus: String,
+    // component accessed and its accesses, if the bus belongs to a component
+    component: Option<(String, Vec<Access>)>,
+    // path to the current bus, without its dimensions
+    path: String,
+    dimensions: usize,
+    indexes: Vec<Access>,
+}
+
+impl BusValue {
+    fn field(&self, leaf: &str) -> Expression {
+        let name = format!("{}.{}", field_prefix(&self.path, self.dimensions), leaf);
+        match &self.component {
+            None => build_variable(self.meta.clone(), name, self.indexes.clone()),
+            Some((component, access)) => {
+                let mut access = access.clone();
+                access.push(Access::ComponentAccess(name));
+                access.extend(self.indexes.iter().cloned());
+                build_variable(self.meta.clone(), component.clone(), access)
+            }
+        }
+    }
+}
+
+enum Resolved {
+    Signal(Expression),
+    Bus(BusValue),
+}
+
+struct Context<'a> {
+    buses: &'a BusLibrary,
+    templates: &'a HashMap<String, TemplateBuses>,
+    local: &'a TemplateBuses,
+    components: HashMap<String, Vec<String>>,
+    file_id: FileID,
+}
+
+impl Context<'_> {
+    fn error(&self, meta: &Meta, msg: String) -> Box<Report> {
+        Box::new(error(meta, self.file_id, msg))
+    }
+
+    fn fields(&self, value: &BusValue) -> Vec<Expression> {
+        self.buses.leaves(&value.bus, None).iter().map(|leaf| value.field(&leaf.path)).collect()
+    }
+
+    // The bus of a signal of a component, looked up in the templates assigned to it
+    fn component_bus(&self, meta: &Meta, component: &str, signal: &str) -> Result<Option<(String, usize)>, Box<Report>> {
+        let mut found: Option<(String, usize)> = None;
+        for template in self.components.get(component).into_iter().flatten() {
+            let bus = self.templates.get(template).and_then(|t| t.io_bus(signal));
+            let bus = bus.map(|(bus, dimensions, _)| (bus.clone(), *dimensions));
+            if let (Some(previous), Some(current)) = (&found, &bus) {
+                if previous != current {
+                    return Err(self.error(meta, format!("The signal {} of the component {} has different buses in the templates assigned to it", signal, component)));
+                }
+            }
+            found = found.or(bus);
+        }
+        Ok(found)
+    }
+
+    fn resolve(&self, meta: Meta, name: String, access: Vec<Access>) -> Result<Resolved, Box<Report>> {
+        let access = self.accesses(access)?;
+        let (mut value, rest) = if let Some((bus, dimensions, _)) = self.local.signals.get(&name) {
+            let value = BusValue {
+                meta: meta.clone(),
+                bus: bus.clone(),
+                component: None,
+                path: name.clone(),
+                dimensions: *dimensions,
+                indexes: Vec::new(),
+            };
+            (value, access)
+        } else {
+            let position = access.iter().position(|acc| matches!(acc, Access::ComponentAccess(_)));
+            let found = match position {
+                Some(position) => match &access[position] {
+                    Access::ComponentAccess(signal) => self.component_bus(&meta, &name, signal)?.map(|bus| (position, signal.clone(), bus)),
+                    _ => unreachable!(),
+                },
+                None => None,
+            };
+            match found {
+                Some((position, signal, (bus, dimensions))) => {
+                    let mut access = access;
+                    let rest = access.split_off(position + 1);
+                    access.pop();
+                    let value = BusValue {
+                        meta: meta.clone(),
+                        bus,
+                        component: Some((name.clone(), access)),
+                        path: signal,
+                        dimensions,
+                        indexes: Vec::new(),
+                    };
+                    (value, rest)
+                }
+                None => return Ok(Resolved::Signal(build_variable(meta, name, access))),
+            }
+        };
+        // the indexes of the buses in the path come before the ones of their fields
+        let mut dimensions = value.dimensions;
+        let mut rest = rest.into_iter();
+        while let Some(acc) = rest.next() {
+            match acc {
+                Access::ArrayAccess(_) if value.indexes.len() < dimensions => value.indexes.push(acc),
+                Access::ArrayAccess(_) => {
+                    return Err(self.error(&meta, format!("Too many indexes in the access to the bus {}", value.path)));
+                }
+                Access::ComponentAccess(field_name) => {
+                    if value.indexes.len() < dimensions {
+                        return Err(self.error(&meta, format!("All the dimensions of {} must be accessed before its field {}", value.path, field_name)));
+                    }
+                    let field = match self.buses.get(&value.bus).unwrap().field(&field_name) {
+                        Some(field) => field,
+                        None => {
+                            return Err(self.error(&meta, format!("The bus {} does not have a field named {}", value.bus, field_name)));
+                        }
+                    };
+                    let path = format!("{}.{}", field_prefix(&value.path, value.dimensions), field_name);
+                    match &field.kind {
+                        FieldKind::Bus(inner, _) => {
+                            value.bus = inner.clone();
+                            value.path = path;
+                            value.dimensions = field.dimensions.len();
+                            dimensions += value.dimensions;
+                        }
+                        FieldKind::Signal(_) => {
+                            // the remaining accesses are indexes of the field or a tag
+                            let mut indexes = std::mem::take(&mut value.indexes);
+                            indexes.extend(rest);
+                            let expression = match value.component {
+                                None => build_variable(meta, path, indexes),
+                                Some((component, mut access)) => {
+                                    access.push(Access::ComponentAccess(path));
+                                    access.extend(indexes);
+                                    build_variable(meta, component, access)
+                                }
+                            };
+                            return Ok(Resolved::Signal(expression));
+                        }
+                    }
+                }
+            }
+        }
+        Ok(Resolved::Bus(value))
+    }
+
+    fn resolve_expression(&self, expression: Expression) -> Result<Resolved, Box<Report>> {
+        match expression {
+            Expression::Variable { meta, name, access } => self.resolve(meta, name, access),
+            expression => Ok(Resolved::Signal(self.expression(expression)?)),
+        }
+    }
+
+    fn accesses(&self, access: Vec<Access>) -> Result<Vec<Access>, Box<Report>> {
+        let mut result = Vec::new();
+        for acc in access {
+            match acc {
+                Access::ArrayAccess(index) => result.push(Access::ArrayAccess(self.expression(index)?)),
+                acc => result.push(acc),
+            }
+        }
+        Ok(result)
+    }
+
+    fn expressions(&self, expressions: Vec<Expression>) -> Result<Vec<Expression>, Box<Report>> {
+        expressions.into_iter().map(|e| self.expression(e)).collect()
+    }
+
+    fn boxed(&self, expression: Box<Expression>) -> Result<Box<Expression>, Box<Report>> {
+        Ok(Box::new(self.expression(*expression)?))
+    }
+
+    fn expression(&self, expression: Expression) -> Result<Expression, Box<Report>> {
+        use Expression::*;
+        let result = match expression {
+            InfixOp { meta, lhe, infix_op, rhe } => {
+                InfixOp { meta, lhe: self.boxed(lhe)?, infix_op, rhe: self.boxed(rhe)? }
+            }
+            PrefixOp { meta, prefix_op, rhe } => PrefixOp { meta, prefix_op, rhe: self.boxed(rhe)? },
+            ParallelOp { meta, rhe } => ParallelOp { meta, rhe: self.boxed(rhe)? },
+            InlineSwitchOp { meta, cond, if_true, if_false } => InlineSwitchOp {
+                meta,
+                cond: self.boxed(cond)?,
+                if_true: self.boxed(if_true)?,
+                if_false: self.boxed(if_false)?,
+            },
+            Variable { meta, name, access } => match self.resolve(meta, name, access)? {
+                Resolved::Signal(expression) => expression,
+                Resolved::Bus(value) => {
+                    return Err(self.error(&value.meta, format!("The bus {} can only be used in assignments and constraints of whole buses", value.path)));
+                }
+            },
+            Number(..) => expression,
+            Call { meta, id, args } => Call { meta, id, args: self.expressions(args)? },
+            AnonymousComp { meta, id, is_parallel, params, signals, names } => {
+                self.anonymous_component(meta, id, is_parallel, params, signals, names)?
+            }
+            ArrayInLine { meta, values } => ArrayInLine { meta, values: self.expressions(values)? },
+            Tuple { meta, values } => Tuple { meta, values: self.expressions(values)? },
+            UniformArray { meta, value, dimension } => {
+                UniformArray { meta, value: self.boxed(value)?, dimension: self.boxed(dimension)? }
+            }
+        };
+        Ok(result)
+    }
+
+    // The bus inputs of an anonymous component receive the fields of the bus
+    fn anonymous_component(
+        &self,
+        meta: Meta,
+        id: String,
+        is_parallel: bool,
+        params: Vec<Expression>,
+        signals: Vec<Expression>,
+        names: Option<Vec<(AssignOp, String)>>,
+    ) -> Result<Expression, Box<Report>> {
+        let params = self.expressions(params)?;
+        let template = match self.templates.get(&id) {
+            Some(template) => template,
+            None => return Ok(build_anonymous_component(meta, id, params, self.expressions(signals)?, names, is_parallel)),
+        };
+        let input_names: Vec<String> = match &names {
+            Some(names) => names.iter().map(|(_, name)| name.clone()).collect(),
+            None => template.inputs.clone(),
+        };
+        let mut new_signals = Vec::new();
+        let mut new_names = Vec::new();
+        for (position, signal) in signals.into_iter().enumerate() {
+            let input = input_names.get(position);
+            let op = names.as_ref().map(|names| names[position].0);
+            match input.and_then(|input| template.io_bus(input).map(|bus| (input, bus))) {
+                Some((input, (bus, dimensions, _))) => {
+                    let value = self.bus_value(signal, bus)?;
+                    let prefix = field_prefix(input, *dimensions);
+                    for leaf in self.buses.leaves(bus, None) {
+                        new_signals.push(value.field(&leaf.path));
+                        if let Some(op) = op {
+                            new_names.push((op, format!("{}.{}", prefix, leaf.path)));
+                        }
+                    }
+                }
+                None => {
+                    new_signals.push(self.expression(signal)?);
+                    if let (Some(op), Some(input)) = (op, input) {
+                        new_names.push((op, input.clone()));
+                    }
+                }
+            }
+        }
+        let names = names.map(|_| new_names);
+        Ok(build_anonymous_component(meta, id, params, new_signals, names, is_parallel))
+    }
+
+    fn bus_value(&self, expression: Expression, bus: &str) -> Result<BusValue, Box<Report>> {
+        let meta = expression.get_meta().clone();
+        match self.resolve_expression(expression)? {
+            Resolved::Bus(value) if value.bus == *bus => Ok(value),
+            Resolved::Bus(value) => Err(self.error(&meta, format!("A bus {} is expected instead of a bus {}", bus, value.bus))),
+            Resolved::Signal(_) => Err(self.error(&meta, format!("A bus {} is expected", bus))),
+        }
+    }
+
+    // The single bus output of the template of an anonymous component
+    fn anonymous_output(&self, expression: &Expression) -> Option<&String> {
+        if let Expression::AnonymousComp { id, .. } = expression {
+            let template = self.templates.get(id)?;
+            if template.outputs.len() == 1 {
+                return template.io_bus(&template.outputs[0]).map(|(bus, _, _)| bus);
+            }
+        }
+        None
+    }
+
+    fn statements(&self, stmts: Vec<Statement>) -> Result<Vec<Statement>, Box<Report>> {
+        let mut result = Vec::new();
+        for stmt in stmts {
+            result.append(&mut self.statement(stmt)?);
+        }
+        Ok(result)
+    }
+
+    fn single(&self, stmt: Statement) -> Result<Statement, Box<Report>> {
+        let meta = stmt.get_meta().clone();
+        let mut stmts = self.statement(stmt)?;
+        if stmts.len() == 1 {
+            Ok(stmts.pop().unwrap())
+        } else {
+            Ok(build_block(meta, stmts))
+        }
+    }
+
+    fn statement(&self, stmt: Statement) -> Result<Vec<Statement>, Box<Report>> {
+        use Statement::*;
+        let result = match stmt {
+            IfThenElse { meta, cond, if_case, else_case } => {
+                let else_case = match else_case {
+                    Some(else_case) => Some(Box::new(self.single(*else_case)?)),
+                    None => None,
+                };
+                IfThenElse { meta, cond: self.expression(cond)?, if_case: Box::new(self.single(*if_case)?), else_case }
+            }
+            While { meta, cond, stmt } => {
+                While { meta, cond: self.expression(cond)?, stmt: Box::new(self.single(*stmt)?) }
+            }
+            Return { meta, value } => Return { meta, value: self.expression(value)? },
+            InitializationBlock { xtype: VariableType::Bus(bus, signal_type, tags), initializations, .. } => {
+                return self.bus_declaration(bus, signal_type, tags, initializations);
+            }
+            InitializationBlock { meta, xtype, initializations } => {
+                InitializationBlock { meta, xtype, initializations: self.statements(initializations)? }
+            }
+            Declaration { meta, xtype, name, dimensions, is_constant, var_type, is_kept } => {
+                Declaration { meta, xtype, name, dimensions: self.expressions(dimensions)?, is_constant, var_type, is_kept }
+            }
+            Substitution { meta, var, access, op, rhe } => {
+                let lhe = build_variable(meta.clone(), var, access);
+                return self.assignment(meta, lhe, op, rhe);
+            }
+            MultSubstitution { meta, lhe, op, rhe } => return self.assignment(meta, lhe, op, rhe),
+            UnderscoreSubstitution { meta, op, rhe } => UnderscoreSubstitution { meta, op, rhe: self.expression(rhe)? },
+            ConstraintEquality { meta, lhe, rhe } => {
+                let mut constraints = Vec::new();
+                for (lhe, rhe) in self.pairs(&meta, lhe, rhe)? {
+                    constraints.push(build_constraint_equality(meta.clone(), lhe, rhe));
+                }
+                return Ok(constraints);
+            }
+            LogCall { meta, args } => {
+                let mut new_args = Vec::new();
+                for arg in args {
+                    match arg {
+                        LogArgument::LogExp(expression) => match self.resolve_expression(expression)? {
+                            Resolved::Signal(expression) => new_args.push(LogArgument::LogExp(expression)),
+                            Resolved::Bus(value) => {
+                                new_args.extend(self.fields(&value).into_iter().map(LogArgument::LogExp))
+                            }
+                        },
+                        arg => new_args.push(arg),
+                    }
+                }
+                LogCall { meta, args: new_args }
+            }
+            Block { meta, stmts } => Block { meta, stmts: self.statements(stmts)? },
+            Assert { meta, arg } => Assert { meta, arg: self.expression(arg)? },
+        };
+        Ok(vec![result])
+    }
+
+    // Pairs the fields of two buses, or the elements of two tuples
+    fn pairs(&self, meta: &Meta, lhe: Expression, rhe: Expression) -> Result<Vec<(Expression, Expression)>, Box<Report>> {
+        match (lhe, rhe) {
+            (Expression::Tuple { values: lhs, .. }, Expression::Tuple { values: rhs, .. }) if lhs.len() == rhs.len() => {
+                let mut pairs = Vec::new();
+                for (lhe, rhe) in lhs.into_iter().zip(rhs) {
+                    pairs.append(&mut self.pairs(meta, lhe, rhe)?);
+                }
+                Ok(pairs)
+            }
+            (lhe, rhe) => match (self.resolve_expression(lhe)?, self.resolve_expression(rhe)?) {
+                (Resolved::Signal(lhe), Resolved::Signal(rhe)) => Ok(vec![(lhe, rhe)]),
+                (Resolved::Bus(lhs), Resolved::Bus(rhs)) if lhs.bus == rhs.bus => {
+                    Ok(self.fields(&lhs).into_iter().zip(self.fields(&rhs)).collect())
+                }
+                (Resolved::Bus(lhs), Resolved::Bus(rhs)) => {
+                    Err(self.error(meta, format!("A bus {} cannot be combined with a bus {}", lhs.bus, rhs.bus)))
+                }
+                _ => Err(self.error(meta, "A bus can only be combined with another bus".to_string())),
+            },
+        }
+    }
+
+    fn assignment(&self, meta: Meta, lhe: Expression, op: AssignOp, rhe: Expression) -> Result<Vec<Statement>, Box<Report>> {
+        fn build(meta: Meta, lhe: Expression, op: AssignOp, rhe: Expression) -> Statement {
+            match lhe {
+                Expression::Variable { name, access, .. } => build_substitution(meta, name, access, op, rhe),
+                lhe => build_mult_substitution(meta, lhe, op, rhe),
+            }
+        }
+        // the outputs of an anonymous component are assigned as a tuple
+        if rhe.is_anonymous_comp() {
+            let lhs = match lhe {
+                Expression::Tuple { meta: tuple_meta, values } => {
+                    let mut new_values = Vec::new();
+                    for value in values {
+                        match self.resolve_expression(value)? {
+                            Resolved::Signal(value) => new_values.push(value),
+                            Resolved::Bus(value) => new_values.append(&mut self.fields(&value)),
+                        }
+                    }
+                    build_tuple(tuple_meta, new_values)
+                }
+                lhe => match self.resolve_expression(lhe)? {
+                    Resolved::Signal(lhe) => lhe,
+                    Resolved::Bus(value) => {
+                        if self.anonymous_output(&rhe) != Some(&value.bus) {
+                            return Err(self.error(&meta, format!("The anonymous component must have a single output of bus {}", value.bus)));
+                        }
+                        build_tuple(value.meta.clone(), self.fields(&value))
+                    }
+                },
+            };
+            return Ok(vec![build(meta, lhs, op, self.expression(rhe)?)]);
+        }
+        let pairs = self.pairs(&meta, lhe, rhe)?;
+        if pairs.len() > 1 && op == AssignOp::AssignVar {
+            return Err(self.error(&meta, "Buses can only be assigned with the operators <== and <--".to_string()));
+        }
+        Ok(pairs.into_iter().map(|(lhe, rhe)| build(meta.clone(), lhe, op, rhe)).collect())
+    }
+
+    fn bus_declaration(
+        &self,
+        bus: String,
+        signal_type: SignalType,
+        tags: TagList,
+        initializations: Vec<Statement>,
+    ) -> Result<Vec<Statement>, Box<Report>> {
+        let mut declarations = Vec::new();
+        let mut assignments = Vec::new();
+        let mut initializations = initializations.into_iter();
+        while let Some(init) = initializations.next() {
+            match init {
+                Statement::Declaration { meta, name, dimensions, .. } => {
+                    let args = match initializations.next() {
+                        Some(Statement::Substitution { rhe: Expression::Call { args, .. }, .. }) => args,
+                        _ => unreachable!(),
+                    };
+                    let data = self.buses.get(&bus).unwrap();
+                    if args.len() != data.params.len() {
+                        return Err(self.error(&meta, format!("The bus {} takes {} arguments", bus, data.params.len())));
+                    }
+                    let args = self.expressions(args)?;
+                    let dimensions = self.expressions(dimensions)?;
+                    let prefix = field_prefix(&name, dimensions.len());
+                    for leaf in self.buses.leaves(&bus, Some(&args)) {
+                        let mut leaf_tags = tags.clone();
+                        for tag in leaf.tags {
+                            if !leaf_tags.contains(&tag) {
+                                leaf_tags.push(tag);
+                            }
+                        }
+                        let xtype = VariableType::Signal(signal_type, leaf_tags);
+                        let mut leaf_dimensions = dimensions.clone();
+                        leaf_dimensions.extend(leaf.dimensions);
+                        let declaration = build_declaration(
+                            meta.clone(),
+                            xtype.clone(),
+                            format!("{}.{}", prefix, leaf.path),
+                            leaf_dimensions,
+                        );
+                        declarations.push(build_initialization_block(meta.clone(), xtype, vec![declaration]));
+                    }
+                }
+                init => assignments.append(&mut self.statement(init)?),
+            }
+        }
+        declarations.append(&mut assignments);
+        Ok(declarations)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::parser_logic;
+
+    // The fields of the buses have names that can not be written in the
+    // source, so the bodies are compared line by line with a short printing
+    // of the statements used by the tests
+    fn expression(expression: &Expression) -> String {
+        let list = |values: &[Expression]| values.iter().map(self::expression).collect::<Vec<_>>().join(", ");
+        match expression {
+            Expression::Variable { name, access, .. } => {
+                let mut text = name.clone();
+                for access in access {
+                    match access {
+                        Access::ComponentAccess(field) => text.push_str(&format!(".{}", field)),
+                        Access::ArrayAccess(index) => text.push_str(&format!("[{}]", self::expression(index))),
+                    }
+                }
+                text
+            }
+            Expression::Number(_, value) => value.to_string(),
+            Expression::Call { id, args, .. } => format!("{}({})", id, list(args)),
+            Expression::AnonymousComp { id, params, signals, .. } => format!("{}({})({})", id, list(params), list(signals)),
+            Expression::Tuple { values, .. } => format!("({})", list(values)),
+            _ => "?".to_string(),
+        }
+    }
+
+    fn lines(stmt: &Statement, lines: &mut Vec<String>) {
+        let op = |op: &AssignOp| match op {
+            AssignOp::AssignVar => "=",
+            AssignOp::AssignSignal => "<--",
+            AssignOp::AssignConstraintSignal => "<==",
+        };
+        match stmt {
+            Statement::Block { stmts, .. } | Statement::InitializationBlock { initializations: stmts, .. } => {
+                for stmt in stmts {
+                    self::lines(stmt, lines);
+                }
+            }
+            Statement::Declaration { xtype, name, dimensions, .. } => {
+                let kind = match xtype {
+                    VariableType::Signal(SignalType::Input, _) => "input",
+                    VariableType::Signal(SignalType::Output, _) => "output",
+                    VariableType::Signal(SignalType::Intermediate, _) => "signal",
+                    VariableType::Component => "component",
+                    _ => "var",
+                };
+                let dimensions: String = dimensions.iter().map(|d| format!("[{}]", expression(d))).collect();
+                lines.push(format!("{} {}{}", kind, name, dimensions));
+            }
+            Statement::Substitution { var, access, op: assign, rhe, .. } => {
+                let lhe = build_variable(Meta::new(0, 0), var.clone(), access.clone());
+                lines.push(format!("{} {} {}", expression(&lhe), op(assign), expression(rhe)));
+            }
+            Statement::MultSubstitution { lhe, op: assign, rhe, .. } => {
+                lines.push(format!("{} {} {}", expression(lhe), op(assign), expression(rhe)));
+            }
+            Statement::ConstraintEquality { lhe, rhe, .. } => {
+                lines.push(format!("{} === {}", expression(lhe), expression(rhe)));
+            }
+            Statement::LogCall { args, .. } => {
+                let args: Vec<String> = args
+                    .iter()
+                    .map(|arg| match arg {
+                        LogArgument::LogExp(arg) => expression(arg),
+                        LogArgument::LogStr(text) => format!("\"{}\"", text),
+                    })
+                    .collect();
+                lines.push(format!("log({})", args.join(", ")));
+            }
+            _ => lines.push("?".to_string()),
+        }
+    }
+
+    // The printed body of every template and the public signals of the main
+    // component, or the messages of the errors
+    type Removed = (Vec<(String, Vec<String>)>, Vec<String>);
+
+    fn removed(src: &str) -> Result<Removed, Vec<String>> {
+        let program = parser_logic::parse_file(src, 0).unwrap_or_else(|_| panic!("the source does not parse: {}", src));
+        let mut definitions = vec![(0, program.definitions)];
+        let mut main = program.main_component.unwrap();
+        remove_buses(&mut definitions, &mut main)
+            .map_err(|reports| reports.iter().map(|report| report.get_message().clone()).collect::<Vec<_>>())?;
+        let templates = definitions
+            .pop()
+            .unwrap()
+            .1
+            .iter()
+            .map(|definition| match definition {
+                Definition::Template { name, body, .. } => {
+                    let mut body_lines = Vec::new();
+                    lines(body, &mut body_lines);
+                    (name.clone(), body_lines)
+                }
+                _ => panic!("the buses are not removed"),
+            })
+            .collect();
+        Ok((templates, main.0))
+    }
+
+    const BUSES: &str = "
+        bus Point() {
+            signal x;
+            signal y;
+        }
+        bus Segment(n) {
+            Point() ends[2];
+            signal flags[n];
+        }
+    ";
+
+    #[test]
+    fn buses_are_replaced_by_their_fields() {
+        let src = format!(
+            "{}
+            template Mirror() {{
+                input Point() p;
+                output Point() q;
+                q.x <== p.y;
+                q.y <== p.x;
+            }}
+            template Main(n) {{
+                input Point() a;
+                input Segment(n) s[2];
+                output Point() m;
+                Point() mid;
+                component mi = Mirror();
+                mi.p <== a;
+                mid <== mi.q;
+                m <== Mirror()(s[1].ends[0]);
+                m === a;
+                log(mid);
+            }}
+            component main {{public [a, s]}} = Main(3);",
+            BUSES
+        );
+        let (templates, public) = removed(&src).unwrap_or_else(|reports| panic!("{:?}", reports));
+        let expected = vec![
+            ("Mirror", vec!["input p.x", "input p.y", "output q.x", "output q.y", "q.x <== p.y", "q.y <== p.x"]),
+            (
+                "Main",
+                vec![
+                    "input a.x",
+                    "input a.y",
+                    "input s[].ends[].x[2][2]",
+                    "input s[].ends[].y[2][2]",
+                    "input s[].flags[2][n]",
+                    "output m.x",
+                    "output m.y",
+                    "signal mid.x",
+                    "signal mid.y",
+                    "component mi",
+                    "mi = Mirror()",
+                    "mi.p.x <== a.x",
+                    "mi.p.y <== a.y",
+                    "mid.x <== mi.q.x",
+                    "mid.y <== mi.q.y",
+                    "(m.x, m.y) <== Mirror()(s[].ends[].x[1][0], s[].ends[].y[1][0])",
+                    "m.x === a.x",
+                    "m.y === a.y",
+                    "log(mid.x, mid.y)",
+                ],
+            ),
+        ];
+        let expected: Vec<(String, Vec<String>)> = expected
+            .into_iter()
+            .map(|(name, lines)| (name.to_string(), lines.into_iter().map(str::to_string).collect()))
+            .collect();
+        assert_eq!(templates, expected);
+        assert_eq!(public, vec!["a.x", "a.y", "s[].ends[].x", "s[].ends[].y", "s[].flags"]);
+    }
+
+    #[test]
+    fn wrong_uses_of_buses_are_reported() {
+        let template = |body: &str| format!("{}\ntemplate A() {{ {} }}\ncomponent main = A();", BUSES, body);
+        let cases = vec![
+            (format!("{}\nbus B() {{ B() b; }}\ntemplate A() {{}}\ncomponent main = A();", BUSES), "The bus B contains itself"),
+            (format!("{}\nbus B() {{ signal input x; }}\ntemplate A() {{}}\ncomponent main = A();", BUSES), "The field x of a bus cannot be an input or an output"),
+            (template("Line() l;"), "The bus Line does not exist"),
+            (template("Point() p; Segment(2) s; p <== s;"), "A bus Point cannot be combined with a bus Segment"),
+            (template("Point() p; signal z; z <== p + 1;"), "The bus p can only be used in assignments and constraints of whole buses"),
+            (template("Point() p; Point() q; p = q;"), "Buses can only be assigned with the operators <== and <--"),
+            (template("Segment(2) s; signal z; z <== s.ends.x;"), "All the dimensions of s.ends must be accessed before its field x"),
+            (template("Point() p; signal z; z <== p.z;"), "The bus Point does not have a field named z"),
+            (template("Point(1) p;"), "The bus Point takes 0 arguments"),
+        ];
+        for (src, message) in cases {
+            assert_eq!(removed(&src).err(), Some(vec![message.to_string()]), "{}", message);
+        }
+    }
+}
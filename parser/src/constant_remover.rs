@@ -0,0 +1,552 @@
+use crate::include_logic::Namespace;
+use crate::namespace_logic::{qualified_name, AliasMap};
+use program_structure::ast::*;
+use program_structure::error_code::ReportCode;
+use program_structure::error_definition::{Report, ReportCollection};
+use program_structure::file_definition::FileID;
+use std::collections::{HashMap, HashSet};
+
+// File level constants are replaced by their values before the buses are
+// removed, so they can be used in any expression of the program: array
+// dimensions, fields of buses or the arguments of the main component. Inside a
+// file, N is the constant of the namespace of the file and X.N the constant N
+// of the file included with alias X. Parameters and local symbols can not take
+// the name of a constant, and neither the file level constants nor the ones
+// declared in a body with const can be assigned.
+
+// The namespace and the include aliases of every file
+pub type FileScopes = HashMap<FileID, (Namespace, AliasMap)>;
+
+struct ConstantData {
+    meta: Meta,
+    file_id: FileID,
+    value: Expression,
+}
+
+pub fn remove_constants(
+    definitions: &mut [(FileID, Vec<Definition>)],
+    main_component: &mut MainComponent,
+    main_id: FileID,
+    scopes: &FileScopes,
+) -> Result<(), ReportCollection> {
+    let mut reports = Vec::new();
+    let mut constants = HashMap::new();
+    for (file_id, file_definitions) in definitions.iter_mut() {
+        for definition in file_definitions.iter() {
+            if let Definition::Constant { meta, name, value } = definition {
+                let data = ConstantData { meta: meta.clone(), file_id: *file_id, value: value.clone() };
+                if constants.insert(name.clone(), data).is_some() {
+                    let msg = format!("The constant {} is declared twice", name);
+                    reports.push(error(meta, *file_id, msg, ReportCode::ConstantRedeclared));
+                }
+            }
+        }
+        file_definitions.retain(|definition| !matches!(definition, Definition::Constant { .. }));
+    }
+    let mut values = HashMap::new();
+    let mut names: Vec<&String> = constants.keys().collect();
+    names.sort();
+    for name in names {
+        resolve_constant(name, &constants, scopes, &mut values, &mut Vec::new(), &mut reports);
+    }
+    if !reports.is_empty() {
+        return Err(reports);
+    }
+    for (file_id, file_definitions) in definitions.iter_mut() {
+        for definition in file_definitions {
+            let mut replacer = Replacer::new(&values, scopes, *file_id);
+            match definition {
+                Definition::Template { meta, args, args_dimensions, body, .. } => {
+                    for (arg, dimensions) in args.iter().zip(args_dimensions.iter_mut()) {
+                        dimensions.iter_mut().for_each(|dimension| replacer.expression(dimension));
+                        replacer.declare(meta, arg, Local::Parameter);
+                    }
+                    replacer.statement(body);
+                }
+                Definition::Function { meta, args, body, .. }
+                | Definition::Bus { meta, args, body, .. } => {
+                    for arg in args.iter() {
+                        replacer.declare(meta, arg, Local::Parameter);
+                    }
+                    replacer.statement(body);
+                }
+                Definition::Test { body, .. } => replacer.statement(body),
+                Definition::Constant { .. } => {}
+            }
+            reports.append(&mut replacer.reports);
+        }
+    }
+    let mut replacer = Replacer::new(&values, scopes, main_id);
+    replacer.expression(&mut main_component.1);
+    reports.append(&mut replacer.reports);
+    if reports.is_empty() {
+        Ok(())
+    } else {
+        Err(reports)
+    }
+}
+
+// Computes the value of a constant replacing the constants it uses, the
+// constants being resolved are kept in the stack to find cycles
+fn resolve_constant(
+    name: &str,
+    constants: &HashMap<String, ConstantData>,
+    scopes: &FileScopes,
+    values: &mut HashMap<String, Expression>,
+    stack: &mut Vec<String>,
+    reports: &mut ReportCollection,
+) -> bool {
+    if values.contains_key(name) {
+        return true;
+    }
+    let data = &constants[name];
+    if stack.iter().any(|visited| visited == name) {
+        let msg = format!("The value of the constant {} depends on itself", name);
+        reports.push(error(&data.meta, data.file_id, msg, ReportCode::NotAConstantValue));
+        return false;
+    }
+    let mut value = data.value.clone();
+    if matches!(value, Expression::ArrayInLine { .. } | Expression::UniformArray { .. }) {
+        let msg = format!("The value of the constant {} must be a number", name);
+        reports.push(error(&data.meta, data.file_id, msg, ReportCode::NotAConstantValue));
+        return false;
+    }
+    let mut used = Vec::new();
+    let scope = Replacer::new(values, scopes, data.file_id);
+    if !scope.is_constant_value(&value, &mut used) {
+        let msg = format!("The value of the constant {} can only use numbers, other constants and calls to functions", name);
+        reports.push(error(&data.meta, data.file_id, msg, ReportCode::NotAConstantValue));
+        return false;
+    }
+    if let Some(unknown) = used.iter().find(|used| !constants.contains_key(*used)) {
+        let msg = format!("The value of the constant {} uses {}, that is not a constant", name, unknown);
+        reports.push(error(&data.meta, data.file_id, msg, ReportCode::NotAConstantValue));
+        return false;
+    }
+    stack.push(name.to_string());
+    let resolved = used.iter().all(|used| resolve_constant(used, constants, scopes, values, stack, reports));
+    stack.pop();
+    if !resolved {
+        return false;
+    }
+    let mut replacer = Replacer::new(values, scopes, data.file_id);
+    replacer.expression(&mut value);
+    values.insert(name.to_string(), value);
+    true
+}
+
+#[derive(Copy, Clone, PartialEq)]
+enum Local {
+    Parameter,
+    Constant,
+    Variable,
+}
+
+struct Replacer<'a> {
+    values: &'a HashMap<String, Expression>,
+    namespace: &'a Namespace,
+    aliases: &'a AliasMap,
+    file_id: FileID,
+    locals: Vec<HashMap<String, Local>>,
+    reports: ReportCollection,
+}
+
+impl<'a> Replacer<'a> {
+    fn new(values: &'a HashMap<String, Expression>, scopes: &'a FileScopes, file_id: FileID) -> Replacer<'a> {
+        let (namespace, aliases) = &scopes[&file_id];
+        Replacer { values, namespace, aliases, file_id, locals: vec![HashMap::new()], reports: Vec::new() }
+    }
+
+    fn local(&self, name: &str) -> Option<Local> {
+        self.locals.iter().rev().find_map(|scope| scope.get(name).cloned())
+    }
+
+    fn declare(&mut self, meta: &Meta, name: &str, local: Local) {
+        if self.constant_name(name, &[]).is_some() {
+            let msg = format!("{} is the name of a constant and can not be declared again", name);
+            self.reports.push(error(meta, self.file_id, msg, ReportCode::ConstantRedeclared));
+        }
+        self.locals.last_mut().unwrap().insert(name.to_string(), local);
+    }
+
+    // The qualified name of the constant a variable refers to, together with
+    // the number of accesses that belong to the name. A name qualified by an
+    // alias always refers to a constant, even if it does not exist.
+    fn constant_name(&self, name: &str, access: &[Access]) -> Option<(String, usize)> {
+        if self.local(name).is_some() {
+            return None;
+        }
+        let unqualified = match self.namespace {
+            Some(namespace) => qualified_name(namespace, name),
+            None => name.to_string(),
+        };
+        if self.values.contains_key(&unqualified) {
+            return Some((unqualified, 0));
+        }
+        if let (Some(namespace), Some(Access::ComponentAccess(field))) = (self.aliases.get(name), access.first()) {
+            return Some((qualified_name(namespace, field), 1));
+        }
+        None
+    }
+
+    // Checks that the value of a constant is known at compile time, collecting the constants it uses
+    fn is_constant_value(&self, expr: &Expression, used: &mut Vec<String>) -> bool {
+        use Expression::*;
+        match expr {
+            Number(..) => true,
+            InfixOp { lhe, rhe, .. } => self.is_constant_value(lhe, used) && self.is_constant_value(rhe, used),
+            PrefixOp { rhe, .. } => self.is_constant_value(rhe, used),
+            InlineSwitchOp { cond, if_true, if_false, .. } => {
+                self.is_constant_value(cond, used)
+                    && self.is_constant_value(if_true, used)
+                    && self.is_constant_value(if_false, used)
+            }
+            Call { args, .. } => args.iter().all(|arg| self.is_constant_value(arg, used)),
+            Variable { name, access, .. } => match self.local(name) {
+                Some(Local::Parameter) | Some(Local::Constant) => {
+                    access.iter().all(|acc| match acc {
+                        Access::ArrayAccess(index) => self.is_constant_value(index, used),
+                        Access::ComponentAccess(_) => false,
+                    })
+                }
+                Some(Local::Variable) => false,
+                None => {
+                    let unqualified = match self.namespace {
+                        Some(namespace) => qualified_name(namespace, name),
+                        None => name.to_string(),
+                    };
+                    let constant = match (self.aliases.get(name), access.as_slice()) {
+                        (_, []) => unqualified,
+                        (Some(namespace), [Access::ComponentAccess(field)]) => qualified_name(namespace, field),
+                        _ => return false,
+                    };
+                    used.push(constant);
+                    true
+                }
+            },
+            _ => false,
+        }
+    }
+
+    fn statement(&mut self, stmt: &mut Statement) {
+        use Statement::*;
+        match stmt {
+            IfThenElse { cond, if_case, else_case, .. } => {
+                self.expression(cond);
+                self.statement(if_case);
+                if let Some(else_case) = else_case {
+                    self.statement(else_case);
+                }
+            }
+            While { cond, stmt, .. } => {
+                self.expression(cond);
+                self.statement(stmt);
+            }
+            Return { value, .. } => self.expression(value),
+            InitializationBlock { initializations, .. } => {
+                // the first assignment of a constant in its block is its initialization
+                let mut initializing = HashSet::new();
+                for init in initializations {
+                    match init {
+                        Declaration { is_constant: true, name, .. } => {
+                            initializing.insert(name.clone());
+                            self.statement(init);
+                        }
+                        Substitution { meta, var, rhe, .. } if initializing.remove(var) => {
+                            if !self.is_constant_value(rhe, &mut Vec::new()) {
+                                let msg = format!(
+                                    "The value of the constant {} can only use numbers, parameters, constants and calls to functions",
+                                    var
+                                );
+                                self.reports.push(error(meta, self.file_id, msg, ReportCode::NotAConstantValue));
+                            }
+                            self.expression(rhe);
+                        }
+                        _ => self.statement(init),
+                    }
+                }
+            }
+            Declaration { meta, name, dimensions, is_constant, .. } => {
+                for dimension in dimensions {
+                    self.expression(dimension);
+                }
+                let local = if *is_constant { Local::Constant } else { Local::Variable };
+                self.declare(meta, name, local);
+            }
+            Substitution { meta, var, access, rhe, .. } => {
+                self.assigned(meta, var, &[]);
+                self.accesses(access);
+                self.expression(rhe);
+            }
+            MultSubstitution { lhe, rhe, .. } => {
+                self.assigned_expression(lhe);
+                self.expression(rhe);
+            }
+            ConstraintEquality { lhe, rhe, .. } => {
+                self.expression(lhe);
+                self.expression(rhe);
+            }
+            UnderscoreSubstitution { rhe, .. } => self.expression(rhe),
+            LogCall { args, .. } => {
+                for arg in args {
+                    if let LogArgument::LogExp(exp) = arg {
+                        self.expression(exp);
+                    }
+                }
+            }
+            Block { stmts, .. } => {
+                self.locals.push(HashMap::new());
+                for stmt in stmts {
+                    self.statement(stmt);
+                }
+                self.locals.pop();
+            }
+            Assert { arg, .. } => self.expression(arg),
+        }
+    }
+
+    fn assigned(&mut self, meta: &Meta, var: &str, access: &[Access]) {
+        let constant = self.local(var) == Some(Local::Constant) || self.constant_name(var, access).is_some();
+        if constant {
+            let msg = format!("{} is a constant and can not be assigned", var);
+            self.reports.push(error(meta, self.file_id, msg, ReportCode::ConstantAssignment));
+        }
+    }
+
+    // The left side of an assignment to several symbols
+    fn assigned_expression(&mut self, expr: &mut Expression) {
+        match expr {
+            Expression::Variable { meta, name, access } => {
+                let meta = meta.clone();
+                self.assigned(&meta, name, access);
+                self.accesses(access);
+            }
+            Expression::Tuple { values, .. } => {
+                for value in values {
+                    self.assigned_expression(value);
+                }
+            }
+            _ => self.expression(expr),
+        }
+    }
+
+    fn accesses(&mut self, access: &mut [Access]) {
+        for acc in access {
+            if let Access::ArrayAccess(exp) = acc {
+                self.expression(exp);
+            }
+        }
+    }
+
+    fn expression(&mut self, expr: &mut Expression) {
+        use Expression::*;
+        match expr {
+            InfixOp { lhe, rhe, .. } => {
+                self.expression(lhe);
+                self.expression(rhe);
+            }
+            PrefixOp { rhe, .. } | ParallelOp { rhe, .. } => self.expression(rhe),
+            InlineSwitchOp { cond, if_true, if_false, .. } => {
+                self.expression(cond);
+                self.expression(if_true);
+                self.expression(if_false);
+            }
+            Variable { meta, name, access } => {
+                let replaced = match self.constant_name(name, access) {
+                    Some((constant, qualifier)) => match self.values.get(&constant) {
+                        Some(value) if access.len() == qualifier => Ok(value.clone()),
+                        Some(_) => Err(format!("The constant {} is a number and has no accesses", name)),
+                        None => Err(format!("{} has no constant {}", name, constant.rsplit('.').next().unwrap())),
+                    },
+                    None => {
+                        self.accesses(access);
+                        return;
+                    }
+                };
+                match replaced {
+                    Ok(mut value) => {
+                        relocate(&mut value, meta);
+                        *expr = value;
+                    }
+                    Err(msg) => self.reports.push(error(meta, self.file_id, msg, ReportCode::NotAConstantValue)),
+                }
+            }
+            Number(..) => {}
+            Call { args, .. } => {
+                for arg in args {
+                    self.expression(arg);
+                }
+            }
+            AnonymousComp { params, signals, .. } => {
+                for param in params {
+                    self.expression(param);
+                }
+                for signal in signals {
+                    self.expression(signal);
+                }
+            }
+            ArrayInLine { values, .. } | Tuple { values, .. } => {
+                for value in values {
+                    self.expression(value);
+                }
+            }
+            UniformArray { value, dimension, .. } => {
+                self.expression(value);
+                self.expression(dimension);
+            }
+        }
+    }
+}
+
+// The value of a constant takes the location where the constant is used
+fn relocate(expr: &mut Expression, meta: &Meta) {
+    use Expression::*;
+    let located = expr.get_mut_meta();
+    located.start = meta.start;
+    located.end = meta.end;
+    located.location = meta.location.clone();
+    match expr {
+        InfixOp { lhe, rhe, .. } => {
+            relocate(lhe, meta);
+            relocate(rhe, meta);
+        }
+        PrefixOp { rhe, .. } | ParallelOp { rhe, .. } => relocate(rhe, meta),
+        InlineSwitchOp { cond, if_true, if_false, .. } => {
+            relocate(cond, meta);
+            relocate(if_true, meta);
+            relocate(if_false, meta);
+        }
+        Variable { access, .. } => {
+            for acc in access {
+                if let Access::ArrayAccess(exp) = acc {
+                    relocate(exp, meta);
+                }
+            }
+        }
+        Number(..) => {}
+        Call { args: values, .. } | ArrayInLine { values, .. } | Tuple { values, .. } => {
+            for value in values {
+                relocate(value, meta);
+            }
+        }
+        AnonymousComp { params, signals, .. } => {
+            for value in params.iter_mut().chain(signals.iter_mut()) {
+                relocate(value, meta);
+            }
+        }
+        UniformArray { value, dimension, .. } => {
+            relocate(value, meta);
+            relocate(dimension, meta);
+        }
+    }
+}
+
+fn error(meta: &Meta, file_id: FileID, msg: String, code: ReportCode) -> Report {
+    let mut report = Report::error(msg, code);
+    report.add_primary(meta.location.clone(), file_id, "found here".to_string());
+    report
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::namespace_logic::apply_namespace;
+    use crate::parser_logic;
+
+    fn parse(src: &str, file_id: FileID) -> AST {
+        parser_logic::parse_file(src, file_id).unwrap_or_else(|_| panic!("the source does not parse: {}", src))
+    }
+
+    // Removes the constants of a main file and of a library included in it
+    // with alias L, which is loaded in the namespace L
+    fn removed(main: &str, library: &str) -> Result<(Vec<Definition>, MainComponent), Vec<String>> {
+        let mut main = parse(main, 0);
+        let mut library = parse(library, 1);
+        let aliases: AliasMap = vec![("L".to_string(), "L".to_string())].into_iter().collect();
+        let namespace = Some("L".to_string());
+        assert!(apply_namespace(&mut library.definitions, None, &namespace, &AliasMap::new(), 1).is_empty());
+        assert!(apply_namespace(&mut main.definitions, main.main_component.as_mut(), &None, &aliases, 0).is_empty());
+        let scopes: FileScopes =
+            vec![(0, (None, aliases)), (1, (namespace, AliasMap::new()))].into_iter().collect();
+        let mut definitions = vec![(0, main.definitions), (1, library.definitions)];
+        let mut main_component = main.main_component.unwrap();
+        remove_constants(&mut definitions, &mut main_component, 0, &scopes)
+            .map_err(|reports| reports.iter().map(|report| report.get_message().clone()).collect::<Vec<_>>())?;
+        Ok((definitions.swap_remove(0).1, main_component))
+    }
+
+    const LIBRARY: &str = "
+        const N = 4;
+        const M = N * 2;
+    ";
+
+    #[test]
+    fn constants_are_replaced_by_their_values() {
+        let (definitions, main) = removed(
+            "
+            const SIZE = L.M + K;
+            const K = double(3);
+            function double(x) { return 2 * x; }
+            template A(n) {
+                signal input in[SIZE];
+                signal output out;
+                const total = n + SIZE;
+                var acc = total;
+                for (var i = 0; i < SIZE; i++) {
+                    acc += in[i] * L.N;
+                }
+                out <== acc;
+            }
+            component main = A(SIZE);
+            ",
+            LIBRARY,
+        )
+        .unwrap_or_else(|reports| panic!("{:?}", reports));
+        let expected = parse(
+            "
+            function double(x) { return 2 * x; }
+            template A(n) {
+                signal input in[(4 * 2) + double(3)];
+                signal output out;
+                const total = n + ((4 * 2) + double(3));
+                var acc = total;
+                for (var i = 0; i < (4 * 2) + double(3); i++) {
+                    acc += in[i] * 4;
+                }
+                out <== acc;
+            }
+            component main = A((4 * 2) + double(3));
+            ",
+            0,
+        );
+        assert!(definitions == expected.definitions);
+        assert!(main == expected.main_component.unwrap());
+    }
+
+    #[test]
+    fn wrong_constants_are_reported() {
+        let template = |body: &str| format!("template A() {{ {} }}\ncomponent main = A();", body);
+        let cases: Vec<(String, &[&str])> = vec![
+            ("const C = 1;\nconst C = 2;\n".to_string() + &template(""), &["The constant C is declared twice"]),
+            (
+                "const C = D;\nconst D = C;\n".to_string() + &template(""),
+                &["The value of the constant C depends on itself", "The value of the constant D depends on itself"],
+            ),
+            ("const C = [1, 2];\n".to_string() + &template(""), &["The value of the constant C must be a number"]),
+            ("const C = X;\n".to_string() + &template(""), &["The value of the constant C uses X, that is not a constant"]),
+            ("const C = 1;\n".to_string() + &template("var C = 2;"), &["C is the name of a constant and can not be declared again"]),
+            ("const C = 1;\n".to_string() + &template("C = 2;"), &["C is a constant and can not be assigned"]),
+            ("const C = 1;\n".to_string() + &template("var x = C[0];"), &["The constant C is a number and has no accesses"]),
+            (template("var x = L.P;"), &["L has no constant P"]),
+            (template("const c = 1; c = 2;"), &["c is a constant and can not be assigned"]),
+            (
+                template("signal s; const c = s;"),
+                &["The value of the constant c can only use numbers, parameters, constants and calls to functions"],
+            ),
+        ];
+        for (src, messages) in cases {
+            let messages: Vec<String> = messages.iter().map(|message| message.to_string()).collect();
+            assert_eq!(removed(&src, LIBRARY).err(), Some(messages), "{}", src);
+        }
+    }
+}
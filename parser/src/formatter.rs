@@ -0,0 +1,806 @@
+use super::parser_logic::{self, Comment};
+use program_structure::ast::*;
+use program_structure::error_code::ReportCode;
+use program_structure::error_definition::{Report, ReportCollection};
+use program_structure::file_definition::FileID;
+use regex::Regex;
+
+// Prints a circom file in the canonical layout: four spaces of indentation,
+// one statement per line, spaces around binary operators and after commas,
+// braces on the line of the statement they open and a blank line between
+// definitions. The comments and the single blank lines that separate
+// statements are kept.
+//
+// The file is printed from its AST, where some constructions are already
+// rewritten by the parser (for loops into while loops, x += e into
+// x = x + e, a ==> b into b <== a and declarations into single nodes).
+// They are recognized through the locations of the nodes in the source,
+// which also give the parentheses written in the expressions and the
+// numbers as they are written.
+//
+// The printed file is parsed again and its tree compared with the tree of
+// the source, so that a file is never formatted into a different program.
+pub fn format_file(src: &str, file_id: FileID) -> Result<String, ReportCollection> {
+    let (ast, preprocessed) = parser_logic::parse_file_with_comments(src, file_id)?;
+    let mut formatter = Formatter {
+        src,
+        text: &preprocessed.text,
+        comments: &preprocessed.comments,
+        next_comment: 0,
+        out: String::new(),
+        indent: 0,
+        last_end: 0,
+        at_block_start: true,
+    };
+    formatter.file(&ast);
+    match parser_logic::parse_file(&formatter.out, file_id) {
+        Ok(formatted) if formatted == ast => Ok(formatter.out),
+        _ => {
+            let mut report = Report::error(
+                "The formatted file is not the same program as the source, the file can not be formatted".to_string(),
+                ReportCode::FormatChangesProgram,
+            );
+            report.add_primary(0..0, file_id, "here".to_string());
+            Err(vec![report])
+        }
+    }
+}
+
+const INDENT: &str = "    ";
+
+// Binding power of the expressions, the higher the tighter
+const PARALLEL: usize = 0;
+const SWITCH: usize = 1;
+const PREFIX: usize = 12;
+const ATOM: usize = 13;
+
+enum Item<'a> {
+    Pragma(String),
+    Include(&'a Include),
+    Definition(&'a Definition),
+    Main(&'a MainComponent),
+}
+
+struct Formatter<'a> {
+    src: &'a str,
+    text: &'a str,
+    comments: &'a [Comment],
+    next_comment: usize,
+    out: String,
+    indent: usize,
+    last_end: usize,
+    at_block_start: bool,
+}
+
+impl Formatter<'_> {
+    fn file(&mut self, ast: &AST) {
+        let mut items = Vec::new();
+        let pragma = Regex::new(r"\bpragma\b[^;]*;").unwrap();
+        for found in pragma.find_iter(self.text) {
+            let words: Vec<&str> = found.as_str().trim_end_matches(';').split_whitespace().collect();
+            let text = format!("{};", words.join(" ")).replace(" . ", ".");
+            items.push((found.start(), found.end(), Item::Pragma(text)));
+        }
+        for include in &ast.includes {
+            items.push((include.meta.start, include.meta.end, Item::Include(include)));
+        }
+        for definition in &ast.definitions {
+            let meta = match definition {
+                Definition::Template { meta, .. }
+                | Definition::Function { meta, .. }
+                | Definition::Bus { meta, .. }
+                | Definition::Test { meta, .. }
+                | Definition::Constant { meta, .. } => meta,
+            };
+            items.push((meta.start, meta.end, Item::Definition(definition)));
+        }
+        if let Some(main) = &ast.main_component {
+            let meta = main.1.get_meta();
+            let start = self.text[..meta.start].rfind("component").unwrap_or(meta.start);
+            items.push((start, meta.end, Item::Main(main)));
+        }
+        items.sort_by_key(|(start, ..)| *start);
+
+        let mut previous: Option<&Item> = None;
+        for (start, end, item) in &items {
+            let grouped = matches!(
+                (previous, item),
+                (Some(Item::Pragma(_)), Item::Pragma(_))
+                    | (Some(Item::Include(_)), Item::Include(_))
+                    | (
+                        Some(Item::Definition(Definition::Constant { .. })),
+                        Item::Definition(Definition::Constant { .. })
+                    )
+            );
+            if previous.is_some() && !grouped {
+                self.blank_line();
+            }
+            self.comments_before(*start);
+            self.separate(*start);
+            match item {
+                Item::Pragma(text) => self.line(text),
+                Item::Include(include) => {
+                    let alias = include.alias.as_ref().map_or(String::new(), |alias| format!(" as {}", alias));
+                    self.line(&format!("include \"{}\"{};", include.path, alias));
+                }
+                Item::Definition(definition) => self.definition(definition),
+                Item::Main((public, call)) => {
+                    let public = if public.is_empty() {
+                        String::new()
+                    } else {
+                        format!(" {{public [{}]}}", public.join(", "))
+                    };
+                    self.line(&format!("component main{} = {};", public, self.expression(call)));
+                }
+            }
+            self.last_end = self.last_end.max(*end);
+            self.trailing_comment();
+            previous = Some(item);
+        }
+        self.comments_before(self.src.len());
+    }
+
+    fn definition(&mut self, definition: &Definition) {
+        let (head, body) = match definition {
+            Definition::Constant { name, value, .. } => {
+                let line = format!("const {} = {};", name, self.expression(value));
+                return self.line(&line);
+            }
+            Definition::Template { name, args, args_dimensions, args_functions, body, parallel, is_custom_gate, is_inline, .. } => {
+                let inline = if *is_inline { "inline " } else { "" };
+                let custom = if *is_custom_gate { "custom " } else { "" };
+                let parallel = if *parallel { "parallel " } else { "" };
+                let args: Vec<String> = args
+                    .iter()
+                    .zip(args_dimensions)
+                    .zip(args_functions)
+                    .map(|((arg, dimensions), is_function)| {
+                        if *is_function {
+                            format!("function {}", arg)
+                        } else {
+                            format!("{}{}", arg, self.dimensions(dimensions))
+                        }
+                    })
+                    .collect();
+                (format!("{}template {}{}{}({})", inline, custom, parallel, name, args.join(", ")), body)
+            }
+            Definition::Function { name, args, body, .. } => {
+                (format!("function {}({})", name, args.join(", ")), body)
+            }
+            Definition::Bus { name, args, body, .. } => (format!("bus {}({})", name, args.join(", ")), body),
+            Definition::Test { name, body, .. } => (format!("test \"{}\"", name), body),
+        };
+        self.branch(head, body);
+    }
+
+    // Output
+
+    fn line(&mut self, text: &str) {
+        for _ in 0..self.indent {
+            self.out.push_str(INDENT);
+        }
+        self.out.push_str(text);
+        self.out.push('\n');
+        self.at_block_start = false;
+    }
+
+    fn blank_line(&mut self) {
+        if !self.out.is_empty() && !self.out.ends_with("\n\n") {
+            self.out.push('\n');
+        }
+    }
+
+    fn last_line_closes_block(&self) -> bool {
+        let last = self.out.trim_end_matches('\n').rsplit('\n').next().unwrap_or("");
+        last.trim() == "}"
+    }
+
+    fn pop_line(&mut self) {
+        let trimmed = self.out.trim_end_matches('\n').len();
+        let start = self.out[..trimmed].rfind('\n').map_or(0, |i| i + 1);
+        self.out.truncate(start);
+    }
+
+    // Keeps a blank line of the source between the last printed element and the next one
+    fn separate(&mut self, start: usize) {
+        if self.at_block_start || start <= self.last_end {
+            return;
+        }
+        let lines: Vec<&str> = self.src[self.last_end..start].split('\n').collect();
+        if lines.len() > 2 && lines[1..lines.len() - 1].iter().any(|line| line.trim().is_empty()) {
+            self.blank_line();
+        }
+    }
+
+    fn comment_text(&self, comment: &Comment) -> &str {
+        self.src[comment.start..comment.end].trim_end()
+    }
+
+    fn comments_before(&mut self, position: usize) {
+        while let Some(comment) = self.comments.get(self.next_comment) {
+            if comment.start >= position {
+                break;
+            }
+            self.separate(comment.start);
+            let text = self.comment_text(comment).to_string();
+            let mut lines = text.split('\n');
+            self.line(lines.next().unwrap().trim_end());
+            for line in lines {
+                self.out.push_str(line.trim_end());
+                self.out.push('\n');
+            }
+            self.last_end = self.last_end.max(comment.end);
+            self.next_comment += 1;
+        }
+    }
+
+    // A comment in the line where the last printed element ends stays at the end of the line
+    fn trailing_comment(&mut self) {
+        if let Some(comment) = self.comments.get(self.next_comment) {
+            let same_line = comment.start >= self.last_end
+                && self.src[self.last_end..comment.start].chars().all(|c| c == ' ' || c == '\t' || c == ';');
+            if same_line {
+                let text = self.comment_text(comment).to_string();
+                self.out.pop();
+                self.out.push(' ');
+                self.out.push_str(&text);
+                self.out.push('\n');
+                self.last_end = comment.end;
+                self.next_comment += 1;
+            }
+        }
+    }
+
+    // Statements
+
+    fn block(&mut self, stmts: &[Statement], end: usize) {
+        self.indent += 1;
+        self.at_block_start = true;
+        for stmt in stmts {
+            self.statement(stmt);
+        }
+        self.comments_before(end);
+        self.indent -= 1;
+        self.last_end = self.last_end.max(end);
+    }
+
+    // Prints head followed by a statement, in the same line when it is a block
+    fn branch(&mut self, head: String, body: &Statement) {
+        match body {
+            Statement::Block { meta, stmts } if !self.is_for_loop(body) => {
+                self.line(&format!("{} {{", head));
+                self.block(stmts, meta.end);
+                self.line("}");
+            }
+            _ => {
+                self.line(&head);
+                self.indent += 1;
+                self.statement(body);
+                self.indent -= 1;
+            }
+        }
+    }
+
+    fn statement(&mut self, stmt: &Statement) {
+        let meta = stmt.get_meta();
+        self.comments_before(meta.start);
+        self.separate(meta.start);
+        self.statement_content(stmt, "");
+        self.last_end = self.last_end.max(meta.end);
+        self.trailing_comment();
+    }
+
+    fn statement_content(&mut self, stmt: &Statement, prefix: &str) {
+        use Statement::*;
+        match stmt {
+            IfThenElse { cond, if_case, else_case, .. } => {
+                self.branch(format!("{}if ({})", prefix, self.expression(cond)), if_case);
+                if let Some(else_case) = else_case {
+                    self.comments_before(else_case.get_meta().start);
+                    let head = if self.last_line_closes_block() {
+                        self.pop_line();
+                        "} else"
+                    } else {
+                        "else"
+                    };
+                    if let IfThenElse { .. } = else_case.as_ref() {
+                        self.statement_content(else_case, &format!("{} ", head));
+                    } else {
+                        self.branch(head.to_string(), else_case);
+                    }
+                }
+            }
+            While { cond, stmt, .. } => {
+                self.branch(format!("while ({})", self.expression(cond)), stmt);
+            }
+            Block { meta, stmts } => {
+                if let Some((init, cond, step, body)) = self.for_loop(stmt) {
+                    let head = format!(
+                        "for ({}; {}; {})",
+                        self.simple_statement(init),
+                        self.expression(cond),
+                        self.simple_statement(step)
+                    );
+                    self.branch(head, body);
+                } else {
+                    self.line("{");
+                    self.block(stmts, meta.end);
+                    self.line("}");
+                }
+            }
+            _ => {
+                let text = format!("{};", self.simple_statement(stmt));
+                self.line(&text);
+            }
+        }
+    }
+
+    fn is_for_loop(&self, stmt: &Statement) -> bool {
+        self.for_loop(stmt).is_some()
+    }
+
+    // A for loop is parsed as a block with the initialization and a while
+    // whose body is a block with the body of the loop and the step
+    fn for_loop<'b>(&self, stmt: &'b Statement) -> Option<(&'b Statement, &'b Expression, &'b Statement, &'b Statement)> {
+        if let Statement::Block { meta, stmts } = stmt {
+            if !self.starts_with_keyword(meta.start, "for") {
+                return None;
+            }
+            if let [init, Statement::While { cond, stmt, .. }] = stmts.as_slice() {
+                if let Statement::Block { stmts, .. } = stmt.as_ref() {
+                    if let [body, step] = stmts.as_slice() {
+                        return Some((init, cond, step, body));
+                    }
+                }
+            }
+        }
+        None
+    }
+
+    fn starts_with_keyword(&self, position: usize, keyword: &str) -> bool {
+        let rest = &self.text[position..];
+        rest.starts_with(keyword)
+            && !rest[keyword.len()..].starts_with(|c: char| c.is_ascii_alphanumeric() || c == '_' || c == '$')
+    }
+
+    fn simple_statement(&self, stmt: &Statement) -> String {
+        use Statement::*;
+        match stmt {
+            Return { value, .. } => format!("return {}", self.expression(value)),
+            InitializationBlock { meta, xtype, initializations } => {
+                self.declaration(meta, xtype, initializations)
+            }
+            Declaration { xtype, name, dimensions, is_kept, .. } => {
+                format!("{} {}{}", self.variable_type(xtype, None, *is_kept), name, self.dimensions(dimensions))
+            }
+            Substitution { meta, var, access, op, rhe } => {
+                let target = format!("{}{}", var, self.accesses(access));
+                if let Some(shortcut) = self.assign_shortcut(meta, op, rhe) {
+                    return format!("{}{}", target, shortcut);
+                }
+                if *op != AssignOp::AssignVar && rhe.get_meta().start == meta.start {
+                    return format!("{} {} {}", self.expression(rhe), flipped_op(op), target);
+                }
+                format!("{} {} {}", target, assign_op(op), self.expression(rhe))
+            }
+            MultSubstitution { meta, lhe, op, rhe } => {
+                if let Expression::Tuple { values, .. } = lhe {
+                    if values.is_empty() {
+                        return self.expression(rhe);
+                    }
+                }
+                if *op != AssignOp::AssignVar && rhe.get_meta().start == meta.start {
+                    return format!("{} {} {}", self.expression(rhe), flipped_op(op), self.expression(lhe));
+                }
+                format!("{} {} {}", self.expression(lhe), assign_op(op), self.expression(rhe))
+            }
+            UnderscoreSubstitution { op, rhe, .. } => format!("_ {} {}", assign_op(op), self.expression(rhe)),
+            ConstraintEquality { lhe, rhe, .. } => {
+                format!("{} === {}", self.expression(lhe), self.expression(rhe))
+            }
+            LogCall { args, .. } => {
+                let args: Vec<String> = args
+                    .iter()
+                    .map(|arg| match arg {
+                        LogArgument::LogStr(text) => format!("\"{}\"", text),
+                        LogArgument::LogExp(exp) => self.expression(exp),
+                    })
+                    .collect();
+                format!("log({})", args.join(", "))
+            }
+            Assert { arg, .. } => format!("assert({})", self.expression(arg)),
+            IfThenElse { .. } | While { .. } | Block { .. } => unreachable!(),
+        }
+    }
+
+    // x += e, x++ and x-- are parsed as x = x op e where the operation and
+    // its left operand take the location of the whole statement
+    fn assign_shortcut(&self, meta: &Meta, op: &AssignOp, rhe: &Expression) -> Option<String> {
+        if let (AssignOp::AssignVar, Expression::InfixOp { meta: infix_meta, lhe, infix_op, rhe }) = (op, rhe) {
+            if infix_meta.start != meta.start || lhe.get_meta().start != meta.start {
+                return None;
+            }
+            if let Expression::Number(number_meta, _) = rhe.as_ref() {
+                if number_meta.start == meta.start {
+                    return match infix_op {
+                        ExpressionInfixOpcode::Add => Some("++".to_string()),
+                        _ => Some("--".to_string()),
+                    };
+                }
+            }
+            return Some(format!(" {}= {}", infix_symbol(infix_op), self.expression(rhe)));
+        }
+        None
+    }
+
+    // Groups the single nodes of a declaration back into its symbols. The
+    // declaration of a bus is followed by the assignment of the bus with its
+    // arguments and a var without initialization by the assignment of 0.
+    fn declaration(&self, meta: &Meta, xtype: &VariableType, initializations: &[Statement]) -> String {
+        let is_bus = matches!(xtype, VariableType::Bus(..));
+        let mut symbols: Vec<String> = Vec::new();
+        let mut bus_args = None;
+        let mut tuple = None;
+        let mut expects_bus = false;
+        for stmt in initializations {
+            match stmt {
+                Statement::Declaration { name, dimensions, .. } => {
+                    symbols.push(format!("{}{}", name, self.dimensions(dimensions)));
+                    expects_bus = is_bus;
+                }
+                Statement::Substitution { rhe, .. } if expects_bus => {
+                    if let Expression::Call { args, .. } = rhe {
+                        bus_args.get_or_insert_with(|| self.expressions(args));
+                    }
+                    expects_bus = false;
+                }
+                Statement::Substitution { op, rhe, .. } => {
+                    let synthetic = *xtype == VariableType::Var && rhe.get_meta().start == meta.start;
+                    if let (false, Some(symbol)) = (synthetic, symbols.last_mut()) {
+                        symbol.push_str(&format!(" {} {}", assign_op(op), self.expression(rhe)));
+                    }
+                }
+                Statement::MultSubstitution { op, rhe, .. } => {
+                    tuple = Some(format!(" {} {}", assign_op(op), self.expression(rhe)));
+                }
+                _ => {}
+            }
+        }
+        let constant = initializations.iter().any(|stmt| matches!(stmt, Statement::Declaration { is_constant: true, .. }));
+        let kept = initializations.iter().any(|stmt| matches!(stmt, Statement::Declaration { is_kept: true, .. }));
+        let var_type = initializations.iter().find_map(|stmt| match stmt {
+            Statement::Declaration { var_type, .. } => Some(*var_type),
+            _ => None,
+        });
+        let head = match (constant, var_type) {
+            (true, _) => "const".to_string(),
+            (false, Some(VarType::U32)) => "var u32".to_string(),
+            (false, Some(VarType::Bool)) => "var bool".to_string(),
+            _ => self.variable_type(xtype, bus_args.as_deref(), kept),
+        };
+        match tuple {
+            Some(init) => format!("{} ({}){}", head, symbols.join(", "), init),
+            None => format!("{} {}", head, symbols.join(", ")),
+        }
+    }
+
+    fn variable_type(&self, xtype: &VariableType, bus_args: Option<&str>, is_kept: bool) -> String {
+        fn tags(list: &TagList) -> String {
+            if list.is_empty() {
+                String::new()
+            } else {
+                format!(" {{{}}}", list.join(", "))
+            }
+        }
+        match xtype {
+            VariableType::Var => "var".to_string(),
+            VariableType::Component | VariableType::AnonymousComponent => "component".to_string(),
+            VariableType::Signal(signal_type, list) => {
+                let keep = if is_kept { " keep" } else { "" };
+                format!("signal{}{}{}", signal_type_prefix(signal_type), keep, tags(list))
+            }
+            VariableType::Bus(bus, signal_type, list) => {
+                let head = format!("{}{}", signal_type_prefix(signal_type), tags(list));
+                format!("{} {}({})", head, bus, bus_args.unwrap_or("")).trim_start().to_string()
+            }
+        }
+    }
+
+    // Expressions
+
+    fn expressions(&self, expressions: &[Expression]) -> String {
+        let values: Vec<String> = expressions.iter().map(|e| self.expression(e)).collect();
+        values.join(", ")
+    }
+
+    fn dimensions(&self, dimensions: &[Expression]) -> String {
+        dimensions.iter().map(|d| format!("[{}]", self.expression(d))).collect()
+    }
+
+    fn accesses(&self, access: &[Access]) -> String {
+        access
+            .iter()
+            .map(|acc| match acc {
+                Access::ArrayAccess(index) => format!("[{}]", self.expression(index)),
+                Access::ComponentAccess(name) => format!(".{}", name),
+            })
+            .collect()
+    }
+
+    fn expression(&self, expression: &Expression) -> String {
+        use Expression::*;
+        match expression {
+            InfixOp { meta, lhe, infix_op, rhe } => {
+                let level = infix_level(infix_op);
+                let lhe = self.operand(lhe, meta.start, |l| l < level);
+                let rhe = self.operand(rhe, lhe_end(expression), |l| l <= level);
+                format!("{} {} {}", lhe, infix_symbol(infix_op), rhe)
+            }
+            PrefixOp { meta, prefix_op, rhe } => {
+                let symbol = match prefix_op {
+                    ExpressionPrefixOpcode::Sub => "-",
+                    ExpressionPrefixOpcode::BoolNot => "!",
+                    ExpressionPrefixOpcode::Complement => "~",
+                };
+                format!("{}{}", symbol, self.operand(rhe, meta.start, |l| l < ATOM))
+            }
+            InlineSwitchOp { meta, cond, if_true, if_false } => {
+                let needs = |l| l <= SWITCH;
+                format!(
+                    "{} ? {} : {}",
+                    self.operand(cond, meta.start, needs),
+                    self.operand(if_true, cond.get_meta().end, needs),
+                    self.operand(if_false, if_true.get_meta().end, needs)
+                )
+            }
+            ParallelOp { meta, rhe } => {
+                format!("parallel {}", self.operand(rhe, meta.start, |l| l == PARALLEL))
+            }
+            Variable { name, access, .. } => format!("{}{}", name, self.accesses(access)),
+            Number(meta, value) => {
+                let written = self.text.get(meta.start..meta.end).unwrap_or("").trim();
+                if !written.is_empty() && written.chars().all(|c| c.is_ascii_alphanumeric()) {
+                    written.to_string()
+                } else {
+                    value.to_string()
+                }
+            }
+            Call { id, args, .. } => format!("{}({})", id, self.expressions(args)),
+            AnonymousComp { id, is_parallel, params, signals, names, .. } => {
+                let inputs = match names {
+                    Some(names) => {
+                        let inputs: Vec<String> = names
+                            .iter()
+                            .zip(signals)
+                            .map(|((op, name), signal)| format!("{} {} {}", name, assign_op(op), self.expression(signal)))
+                            .collect();
+                        inputs.join(", ")
+                    }
+                    None => self.expressions(signals),
+                };
+                let parallel = if *is_parallel { "parallel " } else { "" };
+                format!("{}{}({})({})", parallel, id, self.expressions(params), inputs)
+            }
+            ArrayInLine { values, .. } => format!("[{}]", self.expressions(values)),
+            Tuple { values, .. } => format!("({})", self.expressions(values)),
+            UniformArray { value, .. } => self.expression(value),
+        }
+    }
+
+    // An operand keeps the parentheses written around it in the source and
+    // gets them when its binding power requires them
+    fn operand(&self, operand: &Expression, from: usize, needs_parentheses: impl Fn(usize) -> bool) -> String {
+        let text = self.expression(operand);
+        let start = operand.get_meta().start;
+        let written = from < start && self.text.get(from..start).is_some_and(|gap| gap.contains('('));
+        if written || needs_parentheses(level(operand)) {
+            format!("({})", text)
+        } else {
+            text
+        }
+    }
+}
+
+fn lhe_end(expression: &Expression) -> usize {
+    match expression {
+        Expression::InfixOp { lhe, .. } => lhe.get_meta().end,
+        _ => expression.get_meta().start,
+    }
+}
+
+fn level(expression: &Expression) -> usize {
+    match expression {
+        Expression::ParallelOp { .. } => PARALLEL,
+        Expression::InlineSwitchOp { .. } => SWITCH,
+        Expression::InfixOp { infix_op, .. } => infix_level(infix_op),
+        Expression::PrefixOp { .. } => PREFIX,
+        _ => ATOM,
+    }
+}
+
+fn infix_level(op: &ExpressionInfixOpcode) -> usize {
+    use ExpressionInfixOpcode::*;
+    match op {
+        BoolOr => 2,
+        BoolAnd => 3,
+        Eq | NotEq | Lesser | Greater | LesserEq | GreaterEq => 4,
+        BitOr => 5,
+        BitXor => 6,
+        BitAnd => 7,
+        ShiftL | ShiftR => 8,
+        Add | Sub => 9,
+        Mul | Div | IntDiv | Mod => 10,
+        Pow => 11,
+    }
+}
+
+fn infix_symbol(op: &ExpressionInfixOpcode) -> &'static str {
+    use ExpressionInfixOpcode::*;
+    match op {
+        Mul => "*",
+        Div => "/",
+        Add => "+",
+        Sub => "-",
+        Pow => "**",
+        IntDiv => "\\",
+        Mod => "%",
+        ShiftL => "<<",
+        ShiftR => ">>",
+        LesserEq => "<=",
+        GreaterEq => ">=",
+        Lesser => "<",
+        Greater => ">",
+        Eq => "==",
+        NotEq => "!=",
+        BoolOr => "||",
+        BoolAnd => "&&",
+        BitOr => "|",
+        BitAnd => "&",
+        BitXor => "^",
+    }
+}
+
+fn assign_op(op: &AssignOp) -> &'static str {
+    match op {
+        AssignOp::AssignVar => "=",
+        AssignOp::AssignSignal => "<--",
+        AssignOp::AssignConstraintSignal => "<==",
+    }
+}
+
+fn flipped_op(op: &AssignOp) -> &'static str {
+    match op {
+        AssignOp::AssignSignal => "-->",
+        _ => "==>",
+    }
+}
+
+fn signal_type_prefix(signal_type: &SignalType) -> &'static str {
+    match signal_type {
+        SignalType::Input => " input",
+        SignalType::Output => " output",
+        SignalType::Intermediate => "",
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // Formats the source checking that the result is formatted into itself
+    fn format(src: &str) -> String {
+        let formatted = format_file(src, 0).unwrap_or_else(|_| panic!("the source is not formatted"));
+        let again = format_file(&formatted, 0).unwrap_or_else(|_| panic!("the formatted source is not formatted"));
+        assert_eq!(again, formatted);
+        formatted
+    }
+
+    fn parse(src: &str) -> AST {
+        parser_logic::parse_file(src, 0).unwrap_or_else(|_| panic!("the source does not parse"))
+    }
+
+    #[test]
+    fn trees_are_compared_up_to_their_locations() {
+        let tree = parse("template A() { var x = 1 * (2 + 3); }");
+        assert!(tree == parse("template   A() {\n\n    var x = 1*(2+3);\n}\n"));
+        assert!(tree == parse("template A() { var x = 1 * ((2 + 3)); }"));
+        assert!(tree != parse("template A() { var x = 1 * 2 + 3; }"));
+        assert!(tree != parse("template A() { var x = 1 * (2 + 4); }"));
+        assert!(tree != parse("template B() { var x = 1 * (2 + 3); }"));
+    }
+
+    #[test]
+    fn parentheses_are_kept_where_they_are_written() {
+        let src = "function f(a, b, c) {\n\
+            return (a+b)*c - (a-(b-c)) + a-b-c + 2**3**a + (-a) * -b + (a > b ? a : b) + ((a << 1) & b) + (a*b);\n\
+            }\n";
+        assert_eq!(
+            format(src),
+            "function f(a, b, c) {\n    \
+            return (a + b) * c - (a - (b - c)) + a - b - c + 2 ** 3 ** a + (-a) * -b + (a > b ? a : b) + ((a << 1) & b) + (a * b);\n\
+            }\n"
+        );
+    }
+
+    #[test]
+    fn comments_are_kept_in_place() {
+        let src = "// the file\n\
+            pragma circom 2.1.5;\n\
+            /* a template\n   on two lines */\n\
+            template A() {\n\
+            signal input a; // the input\n\
+            \n\n\
+            // the output\n\
+            signal output b;   b <== a * a; /* squared */\n\
+            }\n\
+            // the end\n";
+        assert_eq!(
+            format(src),
+            "// the file\n\
+            pragma circom 2.1.5;\n\
+            \n\
+            /* a template\n   on two lines */\n\
+            template A() {\n    \
+            signal input a; // the input\n\
+            \n    \
+            // the output\n    \
+            signal output b;\n    \
+            b <== a * a; /* squared */\n\
+            }\n\
+            // the end\n"
+        );
+    }
+
+    #[test]
+    fn tuples_are_printed_as_written() {
+        let src = "template A() {\n\
+            signal input a; signal input b;\n\
+            var x; var y;\n\
+            (x,y) = (a,b);\n\
+            (x , _) = ( 1 , 2 );\n\
+            }\n";
+        assert_eq!(
+            format(src),
+            "template A() {\n    \
+            signal input a;\n    \
+            signal input b;\n    \
+            var x;\n    \
+            var y;\n    \
+            (x, y) = (a, b);\n    \
+            (x, _) = (1, 2);\n\
+            }\n"
+        );
+    }
+
+    #[test]
+    fn anonymous_components_are_printed_with_their_signals() {
+        let src = "template Pair() { signal input a; signal input b; signal output c; signal output d; c <== a; d <== b; }\n\
+            template A() {\n\
+            signal input x;\n\
+            signal y <== Pair()(x,x*x);\n\
+            signal z; signal w;\n\
+            (z,w) <== Pair()(b<==x,a<==y);\n\
+            _ <== parallel Pair()(x , y);\n\
+            }\n";
+        assert_eq!(
+            format(src),
+            "template Pair() {\n    \
+            signal input a;\n    \
+            signal input b;\n    \
+            signal output c;\n    \
+            signal output d;\n    \
+            c <== a;\n    \
+            d <== b;\n\
+            }\n\
+            \n\
+            template A() {\n    \
+            signal input x;\n    \
+            signal y <== Pair()(x, x * x);\n    \
+            signal z;\n    \
+            signal w;\n    \
+            (z, w) <== Pair()(b <== x, a <== y);\n    \
+            _ <== parallel Pair()(x, y);\n\
+            }\n"
+        );
+    }
+}
@@ -0,0 +1,197 @@
+use crate::constant_remover::FileScopes;
+use crate::inline_remover::{walk_expression, walk_expressions};
+use crate::namespace_logic::qualified_name;
+use program_structure::ast::*;
+use program_structure::error_code::ReportCode;
+use program_structure::error_definition::{Report, ReportCollection};
+use program_structure::file_definition::FileID;
+use std::collections::{HashMap, HashSet};
+
+// The parameters of a template declared as `function f` receive functions.
+// Every instantiation of such a template is replaced by the instantiation of
+// a copy of it specialized with the functions it receives, named after them
+// (Map<double>), in which the calls to f are calls to the given function and
+// that only keeps the parameters that receive values. The functions are
+// given by their names: f for the function of the namespace of the file and
+// X.f for the one of the file included with alias X. The templates that
+// receive functions and are never instantiated are not kept.
+
+pub fn remove_function_parameters(
+    definitions: &mut [(FileID, Vec<Definition>)],
+    main_component: &mut MainComponent,
+    main_id: FileID,
+    scopes: &FileScopes,
+) -> Result<(), ReportCollection> {
+    let mut functions = HashSet::new();
+    let mut generics = HashMap::new();
+    for (file_id, file_definitions) in definitions.iter_mut() {
+        for definition in file_definitions.iter() {
+            match definition {
+                Definition::Function { name, .. } => {
+                    functions.insert(name.clone());
+                }
+                Definition::Template { name, args_functions, .. } if args_functions.contains(&true) => {
+                    generics.insert(name.clone(), (*file_id, definition.clone()));
+                }
+                _ => {}
+            }
+        }
+        file_definitions.retain(
+            |definition| !matches!(definition, Definition::Template { args_functions, .. } if args_functions.contains(&true)),
+        );
+    }
+    if generics.is_empty() {
+        return Ok(());
+    }
+    let mut specializer = Specializer {
+        functions,
+        generics,
+        scopes,
+        specialized: HashSet::new(),
+        pending: Vec::new(),
+        reports: Vec::new(),
+    };
+    for (file_id, file_definitions) in definitions.iter_mut() {
+        for definition in file_definitions {
+            specializer.definition(*file_id, definition);
+        }
+    }
+    walk_expression(&mut main_component.1, &mut |expr| specializer.instantiation(main_id, expr));
+    // the specialized templates can instantiate other templates that receive functions
+    while let Some((file_id, mut definition)) = specializer.pending.pop() {
+        specializer.definition(file_id, &mut definition);
+        if let Some((_, file_definitions)) = definitions.iter_mut().find(|(id, _)| *id == file_id) {
+            file_definitions.push(definition);
+        }
+    }
+    if specializer.reports.is_empty() {
+        Ok(())
+    } else {
+        Err(specializer.reports)
+    }
+}
+
+struct Specializer<'a> {
+    functions: HashSet<String>,
+    // the templates that receive functions, with the file they belong to
+    generics: HashMap<String, (FileID, Definition)>,
+    scopes: &'a FileScopes,
+    specialized: HashSet<String>,
+    // specialized templates whose bodies are not yet specialized
+    pending: Vec<(FileID, Definition)>,
+    reports: ReportCollection,
+}
+
+impl Specializer<'_> {
+    fn definition(&mut self, file_id: FileID, definition: &mut Definition) {
+        match definition {
+            Definition::Template { body, .. }
+            | Definition::Function { body, .. }
+            | Definition::Bus { body, .. }
+            | Definition::Test { body, .. } => {
+                walk_expressions(body, &mut |expr| self.instantiation(file_id, expr));
+            }
+            Definition::Constant { .. } => {}
+        }
+    }
+
+    // Replaces an instantiation of a template that receives functions
+    fn instantiation(&mut self, file_id: FileID, expr: &mut Expression) {
+        let (meta, id, args) = match expr {
+            Expression::Call { meta, id, args } if self.generics.contains_key(id) => (meta, id, args),
+            Expression::AnonymousComp { meta, id, params, .. } if self.generics.contains_key(id) => (meta, id, params),
+            _ => return,
+        };
+        let (generic_file, generic) = self.generics[id].clone();
+        let (params, args_functions) = match &generic {
+            Definition::Template { args, args_functions, .. } => (args.clone(), args_functions.clone()),
+            _ => unreachable!(),
+        };
+        if params.len() != args.len() {
+            let msg = format!("The template {} receives {} arguments, but {} are given", id, params.len(), args.len());
+            self.reports.push(error(meta, file_id, msg, ReportCode::TemplateWrongNumberOfArguments));
+            return;
+        }
+        let mut received = Vec::new();
+        for ((param, arg), is_function) in params.iter().zip(args.iter()).zip(args_functions.iter()) {
+            if !*is_function {
+                continue;
+            }
+            match self.function_name(file_id, arg) {
+                Some(function) => received.push(function),
+                None => {
+                    let msg = format!("The argument {} of the template {} must be the name of a function", param, id);
+                    self.reports.push(error(meta, file_id, msg, ReportCode::InvalidFunctionArgument));
+                    return;
+                }
+            }
+        }
+        let name = format!("{}<{}>", id, received.join(","));
+        if self.specialized.insert(name.clone()) {
+            let mut definition = generic;
+            specialize(&mut definition, &name, &received);
+            self.pending.push((generic_file, definition));
+        }
+        *args = args.drain(..).zip(args_functions.iter()).filter(|(_, f)| !**f).map(|(arg, _)| arg).collect();
+        *id = name;
+    }
+
+    // The function an argument refers to: the name of a function of the
+    // namespace of the file, X.f for the file included with alias X or the
+    // qualified name of a function given to a specialized template
+    fn function_name(&self, file_id: FileID, arg: &Expression) -> Option<String> {
+        let (namespace, aliases) = &self.scopes[&file_id];
+        let function = match arg {
+            Expression::Variable { name, access, .. } => match access.as_slice() {
+                [] => match namespace {
+                    Some(namespace) if self.functions.contains(&qualified_name(namespace, name)) => {
+                        qualified_name(namespace, name)
+                    }
+                    _ => name.clone(),
+                },
+                [Access::ComponentAccess(field)] => qualified_name(aliases.get(name)?, field),
+                _ => return None,
+            },
+            _ => return None,
+        };
+        if self.functions.contains(&function) {
+            Some(function)
+        } else {
+            None
+        }
+    }
+}
+
+// Turns a copy of a template that receives functions into its specialization
+fn specialize(definition: &mut Definition, specialized: &str, received: &[String]) {
+    if let Definition::Template { name, args, args_dimensions, args_functions, body, .. } = definition {
+        *name = specialized.to_string();
+        let parameters = args.iter().zip(args_functions.iter()).filter(|(_, f)| **f).map(|(arg, _)| arg.clone());
+        let substitution: HashMap<String, String> = parameters.zip(received.iter().cloned()).collect();
+        let kept = args_functions.iter().map(|is_function| !is_function);
+        *args = args.drain(..).zip(kept.clone()).filter(|(_, keep)| *keep).map(|(arg, _)| arg).collect();
+        *args_dimensions =
+            args_dimensions.drain(..).zip(kept).filter(|(_, keep)| *keep).map(|(dimensions, _)| dimensions).collect();
+        args_functions.retain(|is_function| !is_function);
+        walk_expressions(body, &mut |expr| match expr {
+            Expression::Call { id, .. } => {
+                if let Some(function) = substitution.get(id) {
+                    *id = function.clone();
+                }
+            }
+            // the parameter given as the argument of another template that receives functions
+            Expression::Variable { name, access, .. } if access.is_empty() => {
+                if let Some(function) = substitution.get(name) {
+                    *name = function.clone();
+                }
+            }
+            _ => {}
+        });
+    }
+}
+
+fn error(meta: &Meta, file_id: FileID, msg: String, code: ReportCode) -> Report {
+    let mut report = Report::error(msg, code);
+    report.add_primary(meta.location.clone(), file_id, "found here".to_string());
+    report
+}
@@ -0,0 +1,30 @@
+use program_structure::ast::Definition;
+use std::path::Path;
+
+// The standard range check and lookup templates are loaded with
+// include "circom:gadgets" like any other file, in the namespace of the file
+// that includes them or, with an alias, in a namespace of their own. When the
+// main file has the custom templates pragma they are exported as lookups, and
+// the loaded templates only declare their inputs, so that the witness has no
+// signals left out of the lookup.
+pub const GADGETS_INCLUDE: &str = "circom:gadgets";
+const GADGETS_FILE: &str = "gadgets.circom (built-in)";
+const GADGETS_SOURCE: &str = include_str!("gadgets.circom");
+const LOOKUP_GADGETS_SOURCE: &str = include_str!("lookup_gadgets.circom");
+
+pub fn is_gadgets_include(path: &Path) -> bool {
+    path == Path::new(GADGETS_INCLUDE)
+}
+
+// The name and the source of the built-in file of the gadgets
+pub fn gadgets_file(lookups: bool) -> (String, String) {
+    let source = if lookups { LOOKUP_GADGETS_SOURCE } else { GADGETS_SOURCE };
+    (GADGETS_FILE.to_string(), source.to_string())
+}
+
+pub fn gadget_names(definitions: &[Definition]) -> impl Iterator<Item = String> + '_ {
+    definitions.iter().filter_map(|definition| match definition {
+        Definition::Template { name, .. } => Some(name.clone()),
+        _ => None,
+    })
+}
@@ -0,0 +1,47 @@
+pragma circom 2.1.0;
+
+// Standard templates recognized by the compiler, expanded into the
+// constraints below. With the custom templates pragma the templates of
+// lookup_gadgets.circom are loaded instead and exported as lookups.
+
+// in is below 2**n, expanded into the n bits of in
+template RangeCheck(n) {
+    signal input in;
+    signal bits[n];
+    var sum = 0;
+    var e2 = 1;
+    for (var i = 0; i < n; i++) {
+        bits[i] <-- (in >> i) & 1;
+        bits[i] * (bits[i] - 1) === 0;
+        sum += bits[i] * e2;
+        e2 = e2 + e2;
+    }
+    sum === in;
+}
+
+// in is one of the n rows of k columns of table, expanded into a selector
+// bit for each row of which exactly one is set
+template Lookup(n, k, table[n][k]) {
+    signal input in[k];
+    signal selectors[n];
+    var found = 0;
+    var total = 0;
+    var columns[k];
+    for (var i = 0; i < n; i++) {
+        var equal = 1;
+        for (var j = 0; j < k; j++) {
+            equal = equal * (in[j] == table[i][j]);
+        }
+        selectors[i] <-- equal * (1 - found);
+        found = found + equal * (1 - found);
+        selectors[i] * (selectors[i] - 1) === 0;
+        total += selectors[i];
+        for (var j = 0; j < k; j++) {
+            columns[j] += selectors[i] * table[i][j];
+        }
+    }
+    total === 1;
+    for (var j = 0; j < k; j++) {
+        columns[j] === in[j];
+    }
+}
@@ -1,61 +1,127 @@
+use crate::gadget_logic::is_gadgets_include;
+use crate::package_logic::Packages;
 use program_structure::ast::produce_report_with_message;
 use program_structure::error_code::ReportCode;
 use program_structure::error_definition::Report;
 use std::collections::{HashMap, HashSet};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+
+// The namespace a file is loaded into: None stands for the global namespace,
+// otherwise it is the prefix given to the names defined in the file
+pub type Namespace = Option<String>;
 
 pub struct FileStack {
     current_location: PathBuf,
-    black_paths: HashSet<PathBuf>,
-    stack: Vec<PathBuf>,
+    current_namespace: Namespace,
+    black_paths: HashSet<(PathBuf, Namespace)>,
+    stack: Vec<(PathBuf, Namespace)>,
+    namespaces: HashMap<PathBuf, String>,
 }
 
 impl FileStack {
     pub fn new(src: PathBuf) -> FileStack {
         let mut location = src.clone();
         location.pop();
-        FileStack { current_location: location, black_paths: HashSet::new(), stack: vec![src] }
+        FileStack {
+            current_location: location,
+            current_namespace: None,
+            black_paths: HashSet::new(),
+            stack: vec![(src, None)],
+            namespaces: HashMap::new(),
+        }
     }
 
+    // Returns the path of the included file and, if the include has an alias,
+    // the namespace in which the file is loaded. A file included with an alias
+    // is loaded once per path in its own namespace together with the files it
+    // includes without alias; any other include is loaded in the namespace of
+    // the current file. Includes that start with the name of a dependency of
+    // circom.toml are resolved in the dependency, and it is an error if they
+    // also name a different file in the include paths.
     pub fn add_include(
         f_stack: &mut FileStack,
         name: String,
+        alias: Option<String>,
         libraries: &Vec<PathBuf>,
-    ) -> Result<String, Report> {
+        packages: &Packages,
+    ) -> Result<(String, Namespace), Report> {
         let mut libraries2 = Vec::new();
         libraries2.push(f_stack.current_location.clone());
         libraries2.append(&mut libraries.clone());
-        for lib in libraries2 {
-            let mut path = PathBuf::new();
-            path.push(lib);
-            path.push(name.clone());
-            let path = std::fs::canonicalize(path);
-            match path {
-                Err(_) => {}
-                Ok(path) => {
+        let mut found = None;
+        if is_gadgets_include(Path::new(&name)) {
+            // the built-in gadgets are not searched in the include paths
+            found = Some(PathBuf::from(&name));
+        } else {
+            for lib in libraries2 {
+                let mut path = PathBuf::new();
+                path.push(lib);
+                path.push(name.clone());
+                if let Ok(path) = std::fs::canonicalize(path) {
                     if path.is_file() {
-                        if !f_stack.black_paths.contains(&path) {
-                            f_stack.stack.push(path.clone());
-                        }
-                        return Result::Ok(path.to_str().unwrap().to_string());
+                        found = Some(path);
+                        break;
                     }
                 }
             }
         }
-        Result::Err(produce_report_with_message(ReportCode::IncludeNotFound, name))
+        let path = match (found, packages.resolve(&name)) {
+            (Some(path), Some(dependency_path)) if path != dependency_path => {
+                let mut report = Report::error(
+                    format!(
+                        "The include {} is ambiguous: it names {} in the dependency {} and {} in the include paths",
+                        name,
+                        dependency_path.display(),
+                        packages.dependency_of(&name).unwrap(),
+                        path.display()
+                    ),
+                    ReportCode::AmbiguousInclude,
+                );
+                report.add_note("Rename the dependency in circom.toml or the conflicting directory".to_string());
+                return Result::Err(report);
+            }
+            (Some(path), _) | (None, Some(path)) => path,
+            (None, None) => return Result::Err(produce_report_with_message(ReportCode::IncludeNotFound, name)),
+        };
+        let namespace = match &alias {
+            Some(alias) => Some(FileStack::namespace_of(f_stack, &path, alias.clone())),
+            None => f_stack.current_namespace.clone(),
+        };
+        let entry = (path.clone(), namespace.clone());
+        if !f_stack.black_paths.contains(&entry) {
+            f_stack.stack.push(entry);
+        }
+        let alias_namespace = if alias.is_some() { namespace } else { None };
+        Result::Ok((path.to_str().unwrap().to_string(), alias_namespace))
+    }
+
+    fn namespace_of(f_stack: &mut FileStack, path: &PathBuf, alias: String) -> String {
+        if let Some(namespace) = f_stack.namespaces.get(path) {
+            return namespace.clone();
+        }
+        let used: HashSet<&String> = f_stack.namespaces.values().collect();
+        let mut namespace = alias.clone();
+        let mut i = 1;
+        while used.contains(&namespace) {
+            namespace = format!("{}_{}", alias, i);
+            i += 1;
+        }
+        f_stack.namespaces.insert(path.clone(), namespace.clone());
+        namespace
     }
 
-    pub fn take_next(f_stack: &mut FileStack) -> Option<PathBuf> {
+    pub fn take_next(f_stack: &mut FileStack) -> Option<(PathBuf, Namespace)> {
         loop {
             match f_stack.stack.pop() {
                 None => {
                     break None;
                 }
-                Some(file) if !f_stack.black_paths.contains(&file) => {
-                    f_stack.current_location = file.clone();
+                Some(entry) if !f_stack.black_paths.contains(&entry) => {
+                    f_stack.current_location = entry.0.clone();
                     f_stack.current_location.pop();
-                    f_stack.black_paths.insert(file.clone());
-                    break Some(file);
+                    f_stack.current_namespace = entry.1.clone();
+                    f_stack.black_paths.insert(entry.clone());
+                    break Some(entry);
                 }
                 _ => {}
             }
@@ -0,0 +1,713 @@
+use program_structure::ast::*;
+use program_structure::error_code::ReportCode;
+use program_structure::error_definition::{Report, ReportCollection};
+use program_structure::program_archive::ProgramArchive;
+use program_structure::statement_builders::*;
+use std::collections::HashSet;
+
+// The components of inline templates are expanded into the templates that
+// instantiate them, so they produce no component: the signals of an inlined
+// component z become intermediate signals z.s of the caller and the other
+// symbols of the inline template are renamed the same way. The statements of
+// the inline template up to the declaration of its last input or output take
+// the place of the instantiation and the rest are placed after the last
+// statement of the caller that assigns one of its inputs, as the body of a
+// component runs once its inputs are known. The components that are arrays,
+// that are instantiated more than once or out of the outermost block of the
+// template are kept as components.
+
+struct Instantiation {
+    component: String,
+    template: String,
+    // position in the outermost block of the caller, if it is there
+    index: Option<usize>,
+    is_array: bool,
+}
+
+pub fn expand_inline_templates(program_archive: &mut ProgramArchive) -> Result<ReportCollection, ReportCollection> {
+    let mut names: Vec<String> = program_archive.templates.keys().cloned().collect();
+    names.sort();
+    let mut errors = ReportCollection::new();
+    for name in &names {
+        let data = program_archive.get_template_data(name);
+        if data.is_inline() && data.is_custom_gate() {
+            let mut report =
+                Report::error(format!("The custom template {} can not be inline", name), ReportCode::InlineCustomTemplate);
+            let meta = data.get_body().get_meta();
+            report.add_primary(meta.location.clone(), meta.get_file_id(), "found here".to_string());
+            errors.push(report);
+        }
+    }
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+    let mut expander = Expander { expanded: HashSet::new(), stack: Vec::new(), warnings: ReportCollection::new() };
+    for name in &names {
+        expander.template(name, program_archive);
+    }
+    Ok(expander.warnings)
+}
+
+struct Expander {
+    expanded: HashSet<String>,
+    // templates being expanded, a recursive instantiation is not inlined
+    stack: Vec<String>,
+    warnings: ReportCollection,
+}
+
+impl Expander {
+    fn template(&mut self, name: &str, program_archive: &mut ProgramArchive) {
+        if self.expanded.contains(name) || self.stack.iter().any(|visiting| visiting == name) {
+            return;
+        }
+        self.stack.push(name.to_string());
+        // the inline templates are expanded before they are copied
+        let mut called = Vec::new();
+        for stmt in program_archive.get_template_data(name).get_body_as_vec() {
+            walk_expressions(&mut stmt.clone(), &mut |expr| {
+                if let Expression::Call { id, .. } = expr {
+                    called.push(id.clone());
+                }
+            });
+        }
+        for callee in called {
+            if program_archive.contains_template(&callee) && program_archive.get_template_data(&callee).is_inline() {
+                self.template(&callee, program_archive);
+            }
+        }
+        let mut stmts = program_archive.get_template_data(name).get_body_as_vec().clone();
+        let mut rejected = HashSet::new();
+        while let Some((component, template, index)) = self.next_inlined(&stmts, program_archive, &mut rejected) {
+            inline_component(&mut stmts, index, &component, &template, program_archive);
+        }
+        *program_archive.templates.get_mut(name).unwrap().get_mut_body_as_vec() = stmts;
+        self.stack.pop();
+        self.expanded.insert(name.to_string());
+    }
+
+    // The next component of an inline template that can be expanded, the
+    // ones that can not are reported once
+    fn next_inlined(
+        &mut self,
+        stmts: &[Statement],
+        program_archive: &ProgramArchive,
+        rejected: &mut HashSet<String>,
+    ) -> Option<(String, String, usize)> {
+        let mut instantiations = Vec::new();
+        for (index, stmt) in stmts.iter().enumerate() {
+            find_instantiations(stmt, Some(index), program_archive, &mut instantiations);
+        }
+        for instantiation in &instantiations {
+            if rejected.contains(&instantiation.component) {
+                continue;
+            }
+            let count = instantiations.iter().filter(|other| other.component == instantiation.component).count();
+            let dimensions = declared_dimensions(stmts, &instantiation.component);
+            let reason = if instantiation.is_array || dimensions.is_some_and(|dimensions| dimensions > 0) {
+                Some("it is an array of components")
+            } else if dimensions.is_none() {
+                Some("it is declared out of the outermost block of the template")
+            } else if count > 1 {
+                Some("it is instantiated more than once")
+            } else if instantiation.index.is_none() {
+                Some("it is instantiated out of the outermost block of the template")
+            } else if self.stack.contains(&instantiation.template) {
+                Some("its template instantiates itself")
+            } else {
+                None
+            };
+            match (reason, instantiation.index) {
+                (None, Some(index)) => {
+                    return Some((instantiation.component.clone(), instantiation.template.clone(), index));
+                }
+                (Some(reason), _) => {
+                    let msg = format!("The component {} of the inline template {} is not inlined because {}", instantiation.component, instantiation.template, reason);
+                    let mut report = Report::warning(msg, ReportCode::ComponentNotInlined);
+                    let meta = stmts[0].get_meta();
+                    if let Some(meta) = instantiation_meta(stmts, &instantiation.component) {
+                        report.add_primary(meta.location.clone(), meta.get_file_id(), "instantiated here".to_string());
+                    } else {
+                        report.add_primary(meta.location.clone(), meta.get_file_id(), "found here".to_string());
+                    }
+                    self.warnings.push(report);
+                    rejected.insert(instantiation.component.clone());
+                }
+                (None, None) => unreachable!(),
+            }
+        }
+        None
+    }
+}
+
+fn inline_component(
+    stmts: &mut Vec<Statement>,
+    index: usize,
+    component: &str,
+    template: &str,
+    program_archive: &mut ProgramArchive,
+) {
+    let data = program_archive.get_template_data(template);
+    let inline_file = data.get_file_id();
+    let mut locals: HashSet<String> = data.get_name_of_params().iter().cloned().collect();
+    for stmt in data.get_body_as_vec() {
+        walk_statements(&mut stmt.clone(), &mut |stmt| {
+            if let Statement::Declaration { name, .. } = stmt {
+                locals.insert(name.clone());
+            }
+        });
+    }
+    let renamer = Renamer { prefix: format!("{}.", component), locals };
+    let inputs: HashSet<String> = data.get_inputs().keys().cloned().collect();
+    let params: Vec<(String, Vec<Expression>)> =
+        data.get_name_of_params().iter().cloned().zip(data.get_dimensions_of_params().iter().cloned()).collect();
+    let mut body = data.get_body_as_vec().clone();
+    let last_signal = body.iter().rposition(declares_io_signal);
+    let mut suffix = body.split_off(last_signal.map_or(0, |position| position + 1));
+    let mut prefix = body;
+
+    remove_component_declaration(stmts, component);
+    let (meta, args) = take_instantiation(&mut stmts[index], component);
+    let caller_file = meta.get_file_id();
+    let mut inserted = Vec::new();
+    for ((param, mut dimensions), arg) in params.into_iter().zip(args) {
+        for dimension in dimensions.iter_mut() {
+            walk_expression(dimension, &mut |expr| renamer.expression(expr));
+            dimension.fill(inline_file, &mut program_archive.id_max);
+        }
+        let name = renamer.name(&param);
+        let mut declaration = build_declaration(meta.clone(), VariableType::Var, name.clone(), Vec::new());
+        declaration.fill(caller_file, &mut program_archive.id_max);
+        if let Statement::Declaration { dimensions: declared, .. } = &mut declaration {
+            *declared = dimensions;
+        }
+        let mut substitution = build_substitution(meta.clone(), name, Vec::new(), AssignOp::AssignVar, arg);
+        substitution.fill(caller_file, &mut program_archive.id_max);
+        inserted.push(build_initialization_block(meta.clone(), VariableType::Var, vec![declaration, substitution]));
+    }
+    for stmt in prefix.iter_mut().chain(suffix.iter_mut()) {
+        renamer.statement(stmt);
+        stmt.fill(inline_file, &mut program_archive.id_max);
+    }
+    inserted.append(&mut prefix);
+    let last_inserted = index + inserted.len();
+    stmts.splice(index + 1..index + 1, inserted);
+    let last_input = (last_inserted + 1..stmts.len())
+        .rev()
+        .find(|position| assigns_input(&stmts[*position], component, &inputs))
+        .unwrap_or(last_inserted);
+    stmts.splice(last_input + 1..last_input + 1, suffix);
+    for stmt in stmts.iter_mut() {
+        access_inlined_signals(stmt, component);
+    }
+}
+
+struct Renamer {
+    prefix: String,
+    locals: HashSet<String>,
+}
+
+impl Renamer {
+    fn name(&self, name: &str) -> String {
+        format!("{}{}", self.prefix, name)
+    }
+
+    fn rename(&self, name: &mut String) {
+        if self.locals.contains(name) {
+            *name = self.name(name);
+        }
+    }
+
+    // The inputs and outputs of the inlined template are signals of the caller
+    fn statement(&self, stmt: &mut Statement) {
+        walk_statements(stmt, &mut |stmt| match stmt {
+            Statement::Declaration { name, xtype, .. } => {
+                self.rename(name);
+                intermediate(xtype);
+            }
+            Statement::InitializationBlock { xtype, .. } => intermediate(xtype),
+            Statement::Substitution { var, .. } => self.rename(var),
+            _ => {}
+        });
+        walk_expressions(stmt, &mut |expr| self.expression(expr));
+    }
+
+    fn expression(&self, expr: &mut Expression) {
+        if let Expression::Variable { name, .. } = expr {
+            self.rename(name);
+        }
+    }
+}
+
+fn intermediate(xtype: &mut VariableType) {
+    if let VariableType::Signal(signal_type, _) = xtype {
+        *signal_type = SignalType::Intermediate;
+    }
+}
+
+fn find_instantiations(
+    stmt: &Statement,
+    index: Option<usize>,
+    program_archive: &ProgramArchive,
+    instantiations: &mut Vec<Instantiation>,
+) {
+    use Statement::*;
+    match stmt {
+        Substitution { var, access, rhe: Expression::Call { id, .. }, .. }
+            if program_archive.contains_template(id) && program_archive.get_template_data(id).is_inline() =>
+        {
+            instantiations.push(Instantiation {
+                component: var.clone(),
+                template: id.clone(),
+                index,
+                is_array: !access.is_empty(),
+            });
+        }
+        InitializationBlock { initializations, .. } => {
+            for init in initializations {
+                find_instantiations(init, index, program_archive, instantiations);
+            }
+        }
+        IfThenElse { if_case, else_case, .. } => {
+            find_instantiations(if_case, None, program_archive, instantiations);
+            if let Some(else_case) = else_case {
+                find_instantiations(else_case, None, program_archive, instantiations);
+            }
+        }
+        While { stmt, .. } => find_instantiations(stmt, None, program_archive, instantiations),
+        Block { stmts, .. } => {
+            for stmt in stmts {
+                find_instantiations(stmt, None, program_archive, instantiations);
+            }
+        }
+        _ => {}
+    }
+}
+
+// Number of dimensions of a component declared in the outermost block
+fn declared_dimensions(stmts: &[Statement], component: &str) -> Option<usize> {
+    let mut dimensions = None;
+    for stmt in stmts {
+        walk_initializations(stmt, &mut |stmt| {
+            if let Statement::Declaration { xtype: VariableType::Component, name, dimensions: declared, .. } = stmt {
+                if name == component {
+                    dimensions = Some(declared.len());
+                }
+            }
+        });
+    }
+    dimensions
+}
+
+fn instantiation_meta<'a>(stmts: &'a [Statement], component: &str) -> Option<&'a Meta> {
+    fn find<'a>(stmt: &'a Statement, component: &str) -> Option<&'a Meta> {
+        use Statement::*;
+        match stmt {
+            Substitution { meta, var, rhe: Expression::Call { .. }, .. } if var == component => Some(meta),
+            InitializationBlock { initializations: stmts, .. } | Block { stmts, .. } => {
+                stmts.iter().find_map(|stmt| find(stmt, component))
+            }
+            IfThenElse { if_case, else_case, .. } => {
+                find(if_case, component).or_else(|| else_case.as_ref().and_then(|stmt| find(stmt, component)))
+            }
+            While { stmt, .. } => find(stmt, component),
+            _ => None,
+        }
+    }
+    stmts.iter().find_map(|stmt| find(stmt, component))
+}
+
+fn remove_component_declaration(stmts: &mut [Statement], component: &str) {
+    for stmt in stmts {
+        if let Statement::InitializationBlock { initializations, .. } = stmt {
+            initializations.retain(|init| {
+                !matches!(init, Statement::Declaration { xtype: VariableType::Component, name, .. } if name == component)
+            });
+        }
+    }
+}
+
+// Removes the instantiation of the component, leaving an empty block in its
+// place, and gives the location and the arguments of the call
+fn take_instantiation(stmt: &mut Statement, component: &str) -> (Meta, Vec<Expression>) {
+    let meta = stmt.get_meta().clone();
+    let empty = build_initialization_block(meta, VariableType::Component, Vec::new());
+    let taken = match stmt {
+        Statement::InitializationBlock { initializations, .. } => {
+            let position = initializations
+                .iter()
+                .position(|init| matches!(init, Statement::Substitution { var, .. } if var == component))
+                .unwrap();
+            initializations.remove(position)
+        }
+        _ => std::mem::replace(stmt, empty),
+    };
+    if let Statement::Substitution { rhe: Expression::Call { meta, args, .. }, .. } = taken {
+        (meta, args)
+    } else {
+        unreachable!()
+    }
+}
+
+fn declares_io_signal(stmt: &Statement) -> bool {
+    let mut declares = false;
+    walk_initializations(stmt, &mut |stmt| {
+        if let Statement::Declaration { xtype: VariableType::Signal(signal_type, _), .. } = stmt {
+            declares = declares || *signal_type != SignalType::Intermediate;
+        }
+    });
+    declares
+}
+
+fn assigns_input(stmt: &Statement, component: &str, inputs: &HashSet<String>) -> bool {
+    let is_input = |name: &String, access: &[Access]| {
+        name == component && matches!(access.first(), Some(Access::ComponentAccess(signal)) if inputs.contains(signal))
+    };
+    let mut assigns = false;
+    walk_statements(&mut stmt.clone(), &mut |stmt| match stmt {
+        Statement::Substitution { var, access, .. } => assigns = assigns || is_input(var, access),
+        Statement::MultSubstitution { lhe, .. } => walk_expression(lhe, &mut |expr| {
+            if let Expression::Variable { name, access, .. } = expr {
+                assigns = assigns || is_input(name, access);
+            }
+        }),
+        _ => {}
+    });
+    assigns
+}
+
+// c.s becomes the signal c.s of the caller
+fn access_inlined_signals(stmt: &mut Statement, component: &str) {
+    fn merge(name: &mut String, access: &mut Vec<Access>, component: &str) {
+        if name == component {
+            if let Some(Access::ComponentAccess(signal)) = access.first() {
+                *name = format!("{}.{}", component, signal);
+                access.remove(0);
+            }
+        }
+    }
+    walk_statements(stmt, &mut |stmt| {
+        if let Statement::Substitution { var, access, .. } = stmt {
+            merge(var, access, component);
+        }
+    });
+    walk_expressions(stmt, &mut |expr| {
+        if let Expression::Variable { name, access, .. } = expr {
+            merge(name, access, component);
+        }
+    });
+}
+
+// Applies f to the statement and to the statements it contains
+fn walk_statements(stmt: &mut Statement, f: &mut dyn FnMut(&mut Statement)) {
+    use Statement::*;
+    f(stmt);
+    match stmt {
+        IfThenElse { if_case, else_case, .. } => {
+            walk_statements(if_case, f);
+            if let Some(else_case) = else_case {
+                walk_statements(else_case, f);
+            }
+        }
+        While { stmt, .. } => walk_statements(stmt, f),
+        InitializationBlock { initializations: stmts, .. } | Block { stmts, .. } => {
+            for stmt in stmts {
+                walk_statements(stmt, f);
+            }
+        }
+        _ => {}
+    }
+}
+
+// Applies f to the declarations of the statement that are not in an inner block
+fn walk_initializations(stmt: &Statement, f: &mut dyn FnMut(&Statement)) {
+    match stmt {
+        Statement::InitializationBlock { initializations, .. } => {
+            for init in initializations {
+                walk_initializations(init, f);
+            }
+        }
+        _ => f(stmt),
+    }
+}
+
+// Applies f to every expression of the statement
+pub(crate) fn walk_expressions(stmt: &mut Statement, f: &mut dyn FnMut(&mut Expression)) {
+    use Statement::*;
+    match stmt {
+        IfThenElse { cond, if_case, else_case, .. } => {
+            walk_expression(cond, f);
+            walk_expressions(if_case, f);
+            if let Some(else_case) = else_case {
+                walk_expressions(else_case, f);
+            }
+        }
+        While { cond, stmt, .. } => {
+            walk_expression(cond, f);
+            walk_expressions(stmt, f);
+        }
+        InitializationBlock { initializations: stmts, .. } | Block { stmts, .. } => {
+            for stmt in stmts {
+                walk_expressions(stmt, f);
+            }
+        }
+        Declaration { dimensions, .. } => {
+            for dimension in dimensions {
+                walk_expression(dimension, f);
+            }
+        }
+        Substitution { access, rhe, .. } => {
+            walk_access(access, f);
+            walk_expression(rhe, f);
+        }
+        MultSubstitution { lhe, rhe, .. } | ConstraintEquality { lhe, rhe, .. } => {
+            walk_expression(lhe, f);
+            walk_expression(rhe, f);
+        }
+        Return { value: expr, .. } | UnderscoreSubstitution { rhe: expr, .. } | Assert { arg: expr, .. } => {
+            walk_expression(expr, f)
+        }
+        LogCall { args, .. } => {
+            for arg in args {
+                if let LogArgument::LogExp(expr) = arg {
+                    walk_expression(expr, f);
+                }
+            }
+        }
+    }
+}
+
+fn walk_access(access: &mut [Access], f: &mut dyn FnMut(&mut Expression)) {
+    for acc in access {
+        if let Access::ArrayAccess(index) = acc {
+            walk_expression(index, f);
+        }
+    }
+}
+
+// Applies f to the expression and to the expressions it contains
+pub(crate) fn walk_expression(expr: &mut Expression, f: &mut dyn FnMut(&mut Expression)) {
+    use Expression::*;
+    f(expr);
+    match expr {
+        InfixOp { lhe, rhe, .. } => {
+            walk_expression(lhe, f);
+            walk_expression(rhe, f);
+        }
+        PrefixOp { rhe, .. } | ParallelOp { rhe, .. } => walk_expression(rhe, f),
+        InlineSwitchOp { cond, if_true, if_false, .. } => {
+            walk_expression(cond, f);
+            walk_expression(if_true, f);
+            walk_expression(if_false, f);
+        }
+        Variable { access, .. } => walk_access(access, f),
+        Number(..) => {}
+        Call { args: values, .. } | ArrayInLine { values, .. } | Tuple { values, .. } => {
+            for value in values {
+                walk_expression(value, f);
+            }
+        }
+        AnonymousComp { params, signals, .. } => {
+            for value in params.iter_mut().chain(signals.iter_mut()) {
+                walk_expression(value, f);
+            }
+        }
+        UniformArray { value, dimension, .. } => {
+            walk_expression(value, f);
+            walk_expression(dimension, f);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::parser_logic;
+    use program_structure::file_definition::FileLibrary;
+
+    fn expanded(src: &str) -> Result<(ProgramArchive, Vec<String>), Vec<String>> {
+        let messages = |reports: ReportCollection| reports.iter().map(|report| report.get_message().clone()).collect();
+        let ast = parser_logic::parse_file(src, 0).unwrap_or_else(|_| panic!("the source does not parse: {}", src));
+        let mut program_archive =
+            ProgramArchive::new(FileLibrary::new(), 0, ast.main_component.unwrap(), vec![(0, ast.definitions)], true)
+                .unwrap_or_else(|_| panic!("the program can not be built: {}", src));
+        let warnings = expand_inline_templates(&mut program_archive).map_err(messages)?;
+        Ok((program_archive, messages(warnings)))
+    }
+
+    // The inlined signals have names that can not be written in the source, so
+    // the dots of the names are written as underscores in the expected body,
+    // and the empty blocks left by the instantiations are dropped
+    fn body(program_archive: &ProgramArchive, name: &str) -> Vec<Statement> {
+        let mut stmts = program_archive.get_template_data(name).get_body_as_vec().clone();
+        stmts.retain(|stmt| !matches!(stmt, Statement::InitializationBlock { initializations, .. } if initializations.is_empty()));
+        for stmt in stmts.iter_mut() {
+            walk_statements(stmt, &mut |stmt| match stmt {
+                Statement::Declaration { name, .. } | Statement::Substitution { var: name, .. } => {
+                    *name = name.replace('.', "_");
+                }
+                _ => {}
+            });
+            walk_expressions(stmt, &mut |expr| {
+                if let Expression::Variable { name, .. } = expr {
+                    *name = name.replace('.', "_");
+                }
+            });
+        }
+        stmts
+    }
+
+    fn expected(src: &str) -> Vec<Statement> {
+        let src = format!("template Main() {{ {} }} component main = Main();", src);
+        let ast = parser_logic::parse_file(&src, 0).unwrap_or_else(|_| panic!("the source does not parse: {}", src));
+        match ast.definitions.into_iter().next() {
+            Some(Definition::Template { body: Statement::Block { stmts, .. }, .. }) => stmts,
+            _ => unreachable!(),
+        }
+    }
+
+    const SQUARE: &str = "
+        inline template Square() {
+            signal input in;
+            signal output out;
+            out <== in * in;
+        }
+    ";
+
+    #[test]
+    fn inline_components_are_expanded_in_the_caller() {
+        let src = format!(
+            "{}
+            inline template Scale(k) {{
+                signal input in;
+                signal output out;
+                var twice = 2 * k;
+                out <== twice * in;
+            }}
+            template Main() {{
+                signal input a;
+                signal output b;
+                component sq = Square();
+                component s = Scale(3);
+                s.in <== a;
+                sq.in <== s.out;
+                b <== sq.out;
+            }}
+            component main = Main();
+            ",
+            SQUARE
+        );
+        let (program_archive, warnings) = expanded(&src).unwrap_or_else(|errors| panic!("{:?}", errors));
+        assert!(warnings.is_empty(), "{:?}", warnings);
+        let expected = expected(
+            "
+            signal input a;
+            signal output b;
+            signal sq_in;
+            signal sq_out;
+            var s_k = 3;
+            signal s_in;
+            signal s_out;
+            s_in <== a;
+            var s_twice = 2 * s_k;
+            s_out <== s_twice * s_in;
+            sq_in <== s_out;
+            sq_out <== sq_in * sq_in;
+            b <== sq_out;
+            ",
+        );
+        assert!(body(&program_archive, "Main") == expected);
+    }
+
+    #[test]
+    fn inline_templates_are_expanded_before_they_are_copied() {
+        let src = format!(
+            "{}
+            inline template Cube() {{
+                signal input in;
+                signal output out;
+                component sq = Square();
+                sq.in <== in;
+                out <== sq.out * in;
+            }}
+            template Main() {{
+                signal input a;
+                signal output b;
+                component c = Cube();
+                c.in <== a;
+                b <== c.out;
+            }}
+            component main = Main();
+            ",
+            SQUARE
+        );
+        let (program_archive, warnings) = expanded(&src).unwrap_or_else(|errors| panic!("{:?}", errors));
+        assert!(warnings.is_empty(), "{:?}", warnings);
+        let expected = expected(
+            "
+            signal input a;
+            signal output b;
+            signal c_in;
+            signal c_out;
+            c_in <== a;
+            signal c_sq_in;
+            signal c_sq_out;
+            c_sq_in <== c_in;
+            c_sq_out <== c_sq_in * c_sq_in;
+            c_out <== c_sq_out * c_in;
+            b <== c_out;
+            ",
+        );
+        assert!(body(&program_archive, "Main") == expected);
+    }
+
+    #[test]
+    fn components_that_can_not_be_inlined_are_kept() {
+        let cases = [
+            (
+                "component sq[2]; sq[0] = Square(); sq[1] = Square();",
+                "The component sq of the inline template Square is not inlined because it is an array of components",
+            ),
+            (
+                "component sq; sq = Square(); sq = Square();",
+                "The component sq of the inline template Square is not inlined because it is instantiated more than once",
+            ),
+            (
+                "component sq; if (1 == 1) { sq = Square(); }",
+                "The component sq of the inline template Square is not inlined because it is instantiated out of the outermost block of the template",
+            ),
+            (
+                "if (1 == 1) { component sq = Square(); }",
+                "The component sq of the inline template Square is not inlined because it is declared out of the outermost block of the template",
+            ),
+        ];
+        for (body, message) in cases.iter() {
+            let src = format!("{} template Main() {{ {} }} component main = Main();", SQUARE, body);
+            let (program_archive, warnings) = expanded(&src).unwrap_or_else(|errors| panic!("{:?}", errors));
+            assert_eq!(warnings, vec![message.to_string()], "{}", src);
+            assert!(self::body(&program_archive, "Main") == expected(body), "{}", src);
+        }
+        let src = "
+            inline template Loop() {
+                signal input in;
+                component l = Loop();
+            }
+            component main = Loop();
+        ";
+        let (_, warnings) = expanded(src).unwrap_or_else(|errors| panic!("{:?}", errors));
+        assert_eq!(warnings, vec!["The component l of the inline template Loop is not inlined because its template instantiates itself"]);
+    }
+
+    #[test]
+    fn custom_templates_can_not_be_inline() {
+        let src = "
+            inline template custom Gate() {
+                signal input in;
+            }
+            component main = Gate();
+        ";
+        match expanded(src) {
+            Ok(_) => panic!("no error for {}", src),
+            Err(errors) => assert_eq!(errors, vec!["The custom template Gate can not be inline"]),
+        }
+    }
+}
@@ -7,19 +7,39 @@ extern crate lalrpop_util;
 
 lalrpop_mod!(pub lang);
 
+mod bus_remover;
+mod constant_remover;
+mod formatter;
+mod function_parameter_remover;
+mod gadget_logic;
 mod include_logic;
+mod inline_remover;
+mod namespace_logic;
+mod package_logic;
 mod parser_logic;
 mod syntax_sugar_remover;
+mod typed_var_remover;
 
+use bus_remover::remove_buses;
+use constant_remover::{remove_constants, FileScopes};
+pub use formatter::format_file;
+use function_parameter_remover::remove_function_parameters;
+use gadget_logic::{gadget_names, gadgets_file, is_gadgets_include};
 use include_logic::{FileStack, IncludesGraph};
-use program_structure::ast::{produce_compiler_version_report, produce_report, produce_report_with_message, produce_version_warning_report, Expression};
+use namespace_logic::{apply_namespace, AliasMap};
+use package_logic::Packages;
+use program_structure::ast::{build_template, produce_compiler_version_report, produce_report, produce_report_with_message, produce_version_warning_report, Definition, Expression, MainComponent};
+use program_structure::expression_builders::build_call;
 use program_structure::error_code::ReportCode;
 use program_structure::error_definition::ReportCollection;
 use program_structure::error_definition::Report;
-use program_structure::file_definition::{FileLibrary};
+use program_structure::file_definition::{FileID, FileLibrary, FileLocation};
 use program_structure::program_archive::ProgramArchive;
+use std::collections::HashSet;
 use std::path::{PathBuf, Path};
 use syntax_sugar_remover::{apply_syntactic_sugar};
+use inline_remover::expand_inline_templates;
+use typed_var_remover::remove_typed_vars;
 
 use std::str::FromStr;
 
@@ -56,41 +76,98 @@ pub fn find_file(
     (found, path, src, crr_str_file, reports)
 }
 
-pub fn run_parser(
+// Result of parsing the main file and the files it includes
+struct ParsedFiles {
+    file_library: FileLibrary,
+    definitions: Vec<(FileID, Vec<Definition>)>,
+    main_components: Vec<(FileID, MainComponent, bool)>,
+    includes_graph: IncludesGraph,
+    // the tests of the main file, the ones of the included files are ignored
+    tests: Vec<Definition>,
+    // whether the main file has the custom templates pragma
+    custom_gates: bool,
+    // the standard templates included by the program
+    gadgets: HashSet<String>,
+    scopes: FileScopes,
+    warnings: ReportCollection,
+}
+
+fn parse_files(
     file: String,
     version: &str,
     link_libraries: Vec<PathBuf>,
-) -> Result<(ProgramArchive, ReportCollection), (FileLibrary, ReportCollection)> {
+) -> Result<ParsedFiles, (FileLibrary, ReportCollection)> {
     let mut file_library = FileLibrary::new();
     let mut definitions = Vec::new();
     let mut main_components = Vec::new();
+    let mut tests = Vec::new();
+    let mut custom_gates = false;
+    let mut gadgets = HashSet::new();
+    let mut scopes = FileScopes::new();
+    let packages = Packages::load(Path::new(&file)).map_err(|e| (file_library.clone(), vec![*e]))?;
     let mut file_stack = FileStack::new(PathBuf::from(file));
     let mut includes_graph = IncludesGraph::new();
     let mut warnings = Vec::new();
     let mut link_libraries2 = link_libraries.clone();
     let mut ext_link_libraries = vec![Path::new("").to_path_buf()];
     ext_link_libraries.append(&mut link_libraries2);
-    while let Some(crr_file) = FileStack::take_next(&mut file_stack) {
-        let (found, path, src, crr_str_file, reports) =
-            find_file(crr_file, ext_link_libraries.clone());
+    while let Some((crr_file, namespace)) = FileStack::take_next(&mut file_stack) {
+        let is_gadgets = is_gadgets_include(&crr_file);
+        let (found, path, src, crr_str_file, reports) = if is_gadgets {
+            // the main file is the first one, so its pragma is already known
+            let (path, src) = gadgets_file(custom_gates);
+            (true, path, src, crr_file, Vec::new())
+        } else {
+            find_file(crr_file, ext_link_libraries.clone())
+        };
         if !found {
             return Result::Err((file_library.clone(), reports));
         }
         let file_id = file_library.add_file(path.clone(), src.clone());
-        let program =
+        let mut program =
             parser_logic::parse_file(&src, file_id).map_err(|e| (file_library.clone(), e))?;
+        includes_graph.add_node(crr_str_file, program.custom_gates, program.custom_gates_declared);
+        let mut aliases = AliasMap::new();
+        for include in &program.includes {
+            let (path_include, alias_namespace) = FileStack::add_include(
+                &mut file_stack,
+                include.path.clone(),
+                include.alias.clone(),
+                &link_libraries.clone(),
+                &packages,
+            )
+            .map_err(|e| (file_library.clone(), vec![e]))?;
+            if let (Some(alias), Some(alias_namespace)) = (&include.alias, alias_namespace) {
+                aliases.insert(alias.clone(), alias_namespace);
+            }
+            if !is_gadgets_include(Path::new(&path_include)) {
+                includes_graph.add_edge(path_include).map_err(|e| (file_library.clone(), vec![e]))?;
+            }
+        }
+        let namespace_reports = apply_namespace(
+            &mut program.definitions,
+            program.main_component.as_mut(),
+            &namespace,
+            &aliases,
+            file_id,
+        );
+        if !namespace_reports.is_empty() {
+            return Result::Err((file_library.clone(), namespace_reports));
+        }
+        scopes.insert(file_id, (namespace, aliases));
+        if is_gadgets {
+            gadgets.extend(gadget_names(&program.definitions));
+        }
         if let Some(main) = program.main_component {
             main_components.push((file_id, main, program.custom_gates));
         }
-        includes_graph.add_node(crr_str_file, program.custom_gates, program.custom_gates_declared);
-        let includes = program.includes;
-        definitions.push((file_id, program.definitions));
-        for include in includes {
-            let path_include =
-                FileStack::add_include(&mut file_stack, include.clone(), &link_libraries.clone())
-                    .map_err(|e| (file_library.clone(), vec![e]))?;
-            includes_graph.add_edge(path_include).map_err(|e| (file_library.clone(), vec![e]))?;
+        let (file_tests, file_definitions): (Vec<_>, Vec<_>) =
+            program.definitions.into_iter().partition(|definition| matches!(definition, Definition::Test { .. }));
+        if file_id == 0 {
+            tests = file_tests;
+            custom_gates = program.custom_gates;
         }
+        definitions.push((file_id, file_definitions));
         warnings.append(
             &mut check_number_version(
                 path.clone(),
@@ -108,7 +185,16 @@ pub fn run_parser(
             .map_err(|e| (file_library.clone(), vec![e]))?
         }
     }
+    Ok(ParsedFiles { file_library, definitions, main_components, includes_graph, tests, custom_gates, gadgets, scopes, warnings })
+}
 
+pub fn run_parser(
+    file: String,
+    version: &str,
+    link_libraries: Vec<PathBuf>,
+) -> Result<(ProgramArchive, ReportCollection), (FileLibrary, ReportCollection)> {
+    let ParsedFiles { file_library, definitions, mut main_components, includes_graph, gadgets, scopes, mut warnings, .. } =
+        parse_files(file, version, link_libraries)?;
     if main_components.len() == 0 {
         let report = produce_report(ReportCode::NoMainFoundInProject,0..0, 0);
         warnings.push(report);
@@ -118,43 +204,133 @@ pub fn run_parser(
         warnings.push(report);
         Err((file_library, warnings))
     } else {
-        let mut errors: ReportCollection = includes_graph.get_problematic_paths().iter().map(|path|
-            Report::error(
-                format!(
-                    "Missing custom templates pragma in file {} because of the following chain of includes {}",
-                    path.last().unwrap().display(),
-                    IncludesGraph::display_path(path)
-                ),
-                ReportCode::CustomGatesPragmaError
-            )
-        ).collect();
-        if errors.len() > 0 {
-            warnings.append(& mut errors);
-            Err((file_library, warnings))
-        } else {
-            let (main_id, main_component, custom_gates) = main_components.pop().unwrap();
-            let result_program_archive = ProgramArchive::new(
-                file_library,
-                main_id,
-                main_component,
-                definitions,
-                custom_gates,
+        let main = main_components.pop().unwrap();
+        build_program(file_library, &includes_graph, &scopes, main, definitions, &gadgets, warnings)
+    }
+}
+
+pub struct TestCase {
+    pub name: String,
+    pub file_id: FileID,
+    pub location: FileLocation,
+    pub program: Result<(ProgramArchive, ReportCollection), (FileLibrary, ReportCollection)>,
+}
+
+// Builds a program for every test of the file, where the body of the test is
+// the body of a template without arguments that is the main component. The
+// main component of the file, if any, is ignored.
+pub fn run_parser_for_tests(
+    file: String,
+    version: &str,
+    link_libraries: Vec<PathBuf>,
+) -> Result<(Vec<TestCase>, FileLibrary, ReportCollection), (FileLibrary, ReportCollection)> {
+    let ParsedFiles { file_library, definitions, includes_graph, tests, custom_gates, gadgets, scopes, warnings, .. } =
+        parse_files(file, version, link_libraries)?;
+    let mut cases = Vec::new();
+    for test in tests {
+        if let Definition::Test { meta, name, body } = test {
+            // quotes can not appear in identifiers, so the template never clashes with others
+            let template = format!("test \"{}\"", name);
+            let mut test_definitions = definitions.clone();
+            test_definitions[0].1.push(build_template(
+                meta.clone(),
+                template.clone(),
+                Vec::new(),
+                meta.start..meta.start,
+                body,
+                false,
+                false,
+                false,
+            ));
+            let main_component = (Vec::new(), build_call(meta.clone(), template, Vec::new()));
+            let program = build_program(
+                file_library.clone(),
+                &includes_graph,
+                &scopes,
+                (0, main_component, custom_gates),
+                test_definitions,
+                &gadgets,
+                Vec::new(),
             );
-            match result_program_archive {
-                Err((lib, mut rep)) => {
-                    warnings.append(&mut rep);
-                    Err((lib, warnings))
-                }
-                Ok(mut program_archive) => {
-                    let lib = program_archive.get_file_library().clone();
-                    let program_archive_result = apply_syntactic_sugar( &mut program_archive);
-                    match program_archive_result {
-                        Result::Err(v) => {
-                            warnings.push(v);
-                            Result::Err((lib,warnings))},
-                        Result::Ok(_) => Ok((program_archive, warnings)),
+            cases.push(TestCase { name, file_id: 0, location: meta.location, program });
+        }
+    }
+    Ok((cases, file_library, warnings))
+}
+
+fn build_program(
+    file_library: FileLibrary,
+    includes_graph: &IncludesGraph,
+    scopes: &FileScopes,
+    (main_id, mut main_component, custom_gates): (FileID, MainComponent, bool),
+    mut definitions: Vec<(FileID, Vec<Definition>)>,
+    gadgets: &HashSet<String>,
+    mut warnings: ReportCollection,
+) -> Result<(ProgramArchive, ReportCollection), (FileLibrary, ReportCollection)> {
+    let mut errors: ReportCollection = includes_graph.get_problematic_paths().iter().map(|path|
+        Report::error(
+            format!(
+                "Missing custom templates pragma in file {} because of the following chain of includes {}",
+                path.last().unwrap().display(),
+                IncludesGraph::display_path(path)
+            ),
+            ReportCode::CustomGatesPragmaError
+        )
+    ).collect();
+    if errors.len() > 0 {
+        warnings.append(& mut errors);
+        return Err((file_library, warnings));
+    }
+    if let Err(mut reports) = remove_function_parameters(&mut definitions, &mut main_component, main_id, scopes) {
+        warnings.append(&mut reports);
+        return Err((file_library, warnings));
+    }
+    if let Err(mut reports) = remove_constants(&mut definitions, &mut main_component, main_id, scopes) {
+        warnings.append(&mut reports);
+        return Err((file_library, warnings));
+    }
+    if let Err(mut reports) = remove_buses(&mut definitions, &mut main_component) {
+        warnings.append(&mut reports);
+        return Err((file_library, warnings));
+    }
+    let u32_vars = match remove_typed_vars(&mut definitions) {
+        Ok(u32_vars) => u32_vars,
+        Err(mut reports) => {
+            warnings.append(&mut reports);
+            return Err((file_library, warnings));
+        }
+    };
+    let result_program_archive = ProgramArchive::new(
+        file_library,
+        main_id,
+        main_component,
+        definitions,
+        custom_gates,
+    );
+    match result_program_archive {
+        Err((lib, mut rep)) => {
+            warnings.append(&mut rep);
+            Err((lib, warnings))
+        }
+        Ok(mut program_archive) => {
+            program_archive.gadgets = gadgets.clone();
+            program_archive.u32_vars = u32_vars;
+            let lib = program_archive.get_file_library().clone();
+            let program_archive_result = apply_syntactic_sugar( &mut program_archive);
+            match program_archive_result {
+                Result::Err(v) => {
+                    warnings.push(v);
+                    Result::Err((lib,warnings))},
+                Result::Ok(_) => match expand_inline_templates(&mut program_archive) {
+                    Result::Err(mut reports) => {
+                        warnings.append(&mut reports);
+                        Result::Err((lib, warnings))
+                    }
+                    Result::Ok(mut reports) => {
+                        warnings.append(&mut reports);
+                        Ok((program_archive, warnings))
                     }
-                }
+                },
             }
         }
     }
@@ -0,0 +1,26 @@
+pragma circom 2.1.0;
+
+// Standard templates recognized by the compiler, as they are loaded when the
+// main file has the custom templates pragma. They are exported as lookups in
+// the r1cs file, so they only declare the inputs checked by the lookup and
+// check their values when the witness is computed.
+
+// in is below 2**n
+template RangeCheck(n) {
+    signal input in;
+    assert(in >> n == 0);
+}
+
+// in is one of the n rows of k columns of table
+template Lookup(n, k, table[n][k]) {
+    signal input in[k];
+    var found = 0;
+    for (var i = 0; i < n; i++) {
+        var equal = 1;
+        for (var j = 0; j < k; j++) {
+            equal = equal * (in[j] == table[i][j]);
+        }
+        found = found + equal * (1 - found);
+    }
+    assert(found == 1);
+}
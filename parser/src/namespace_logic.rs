@@ -0,0 +1,308 @@
+use crate::include_logic::Namespace;
+use program_structure::ast::*;
+use program_structure::error_code::ReportCode;
+use program_structure::error_definition::{Report, ReportCollection};
+use program_structure::file_definition::FileID;
+use std::collections::HashMap;
+
+// Maps the aliases used in the includes of a file to the namespaces
+// in which the included files were loaded
+pub type AliasMap = HashMap<String, String>;
+
+pub fn qualified_name(namespace: &str, name: &str) -> String {
+    format!("{}.{}", namespace, name)
+}
+
+// Renames the definitions of a file loaded in a namespace and resolves the
+// names of the called functions and templates and of the declared buses:
+// unqualified names refer to the namespace of the file and qualified names
+// (X.Name) to the namespace of the include with alias X. The constants used
+// are resolved when they are replaced, once every file is parsed.
+pub fn apply_namespace(
+    definitions: &mut Vec<Definition>,
+    main_component: Option<&mut MainComponent>,
+    namespace: &Namespace,
+    aliases: &AliasMap,
+    file_id: FileID,
+) -> ReportCollection {
+    let mut resolver = Resolver { namespace, aliases, file_id, functions: Vec::new() };
+    let mut reports = Vec::new();
+    for definition in definitions {
+        match definition {
+            Definition::Template { name, args, args_dimensions, args_functions, body, .. } => {
+                if let Some(namespace) = namespace {
+                    *name = qualified_name(namespace, name);
+                }
+                for dimension in args_dimensions.iter_mut().flatten() {
+                    resolver.expression(dimension, &mut reports);
+                }
+                resolver.functions =
+                    args.iter().zip(args_functions.iter()).filter(|(_, f)| **f).map(|(arg, _)| arg.clone()).collect();
+                resolver.statement(body, &mut reports);
+                resolver.functions.clear();
+            }
+            Definition::Function { name, body, .. }
+            | Definition::Bus { name, body, .. } => {
+                if let Some(namespace) = namespace {
+                    *name = qualified_name(namespace, name);
+                }
+                resolver.statement(body, &mut reports);
+            }
+            Definition::Test { body, .. } => resolver.statement(body, &mut reports),
+            Definition::Constant { name, value, .. } => {
+                if let Some(namespace) = namespace {
+                    *name = qualified_name(namespace, name);
+                }
+                resolver.expression(value, &mut reports);
+            }
+        }
+    }
+    if let Some((_, init)) = main_component {
+        resolver.expression(init, &mut reports);
+    }
+    reports
+}
+
+struct Resolver<'a> {
+    namespace: &'a Namespace,
+    aliases: &'a AliasMap,
+    file_id: FileID,
+    // the parameters of the template that receive functions, whose calls are not resolved
+    functions: Vec<String>,
+}
+
+impl Resolver<'_> {
+    fn resolve(&self, id: &mut String, meta: &Meta, reports: &mut ReportCollection) {
+        match id.split_once('.') {
+            Some((alias, name)) => match self.aliases.get(alias) {
+                Some(namespace) => *id = qualified_name(namespace, name),
+                None => {
+                    let mut report = Report::error(
+                        format!("{} is not the alias of any include in this file", alias),
+                        ReportCode::UndefinedNamespace,
+                    );
+                    report.add_primary(
+                        meta.location.clone(),
+                        self.file_id,
+                        "Unknown namespace used here".to_string(),
+                    );
+                    reports.push(report);
+                }
+            },
+            None => {
+                if let Some(namespace) = self.namespace {
+                    if !self.functions.contains(id) {
+                        *id = qualified_name(namespace, id);
+                    }
+                }
+            }
+        }
+    }
+
+    fn statement(&self, stmt: &mut Statement, reports: &mut ReportCollection) {
+        use Statement::*;
+        match stmt {
+            IfThenElse { cond, if_case, else_case, .. } => {
+                self.expression(cond, reports);
+                self.statement(if_case, reports);
+                if let Some(else_case) = else_case {
+                    self.statement(else_case, reports);
+                }
+            }
+            While { cond, stmt, .. } => {
+                self.expression(cond, reports);
+                self.statement(stmt, reports);
+            }
+            Return { value, .. } => self.expression(value, reports),
+            InitializationBlock { meta, xtype, initializations } => {
+                self.variable_type(xtype, meta, reports);
+                for init in initializations {
+                    self.statement(init, reports);
+                }
+            }
+            Declaration { meta, xtype, dimensions, .. } => {
+                self.variable_type(xtype, meta, reports);
+                for dimension in dimensions {
+                    self.expression(dimension, reports);
+                }
+            }
+            Substitution { access, rhe, .. } => {
+                self.accesses(access, reports);
+                self.expression(rhe, reports);
+            }
+            MultSubstitution { lhe, rhe, .. } | ConstraintEquality { lhe, rhe, .. } => {
+                self.expression(lhe, reports);
+                self.expression(rhe, reports);
+            }
+            UnderscoreSubstitution { rhe, .. } => self.expression(rhe, reports),
+            LogCall { args, .. } => {
+                for arg in args {
+                    if let LogArgument::LogExp(exp) = arg {
+                        self.expression(exp, reports);
+                    }
+                }
+            }
+            Block { stmts, .. } => {
+                for stmt in stmts {
+                    self.statement(stmt, reports);
+                }
+            }
+            Assert { arg, .. } => self.expression(arg, reports),
+        }
+    }
+
+    fn variable_type(&self, xtype: &mut VariableType, meta: &Meta, reports: &mut ReportCollection) {
+        if let VariableType::Bus(bus, ..) = xtype {
+            self.resolve(bus, meta, reports);
+        }
+    }
+
+    fn accesses(&self, access: &mut Vec<Access>, reports: &mut ReportCollection) {
+        for acc in access {
+            if let Access::ArrayAccess(exp) = acc {
+                self.expression(exp, reports);
+            }
+        }
+    }
+
+    fn expression(&self, expr: &mut Expression, reports: &mut ReportCollection) {
+        use Expression::*;
+        match expr {
+            InfixOp { lhe, rhe, .. } => {
+                self.expression(lhe, reports);
+                self.expression(rhe, reports);
+            }
+            PrefixOp { rhe, .. } | ParallelOp { rhe, .. } => self.expression(rhe, reports),
+            InlineSwitchOp { cond, if_true, if_false, .. } => {
+                self.expression(cond, reports);
+                self.expression(if_true, reports);
+                self.expression(if_false, reports);
+            }
+            Variable { access, .. } => self.accesses(access, reports),
+            Number(..) => {}
+            Call { meta, id, args } => {
+                self.resolve(id, meta, reports);
+                for arg in args {
+                    self.expression(arg, reports);
+                }
+            }
+            AnonymousComp { meta, id, params, signals, .. } => {
+                self.resolve(id, meta, reports);
+                for param in params {
+                    self.expression(param, reports);
+                }
+                for signal in signals {
+                    self.expression(signal, reports);
+                }
+            }
+            ArrayInLine { values, .. } | Tuple { values, .. } => {
+                for value in values {
+                    self.expression(value, reports);
+                }
+            }
+            UniformArray { value, dimension, .. } => {
+                self.expression(value, reports);
+                self.expression(dimension, reports);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::parser_logic;
+
+    fn parse(src: &str) -> AST {
+        parser_logic::parse_file(src, 0).unwrap_or_else(|_| panic!("the source does not parse"))
+    }
+
+    // The name and the body of every template and function
+    fn bodies(definitions: &[Definition]) -> Vec<(String, Statement)> {
+        definitions
+            .iter()
+            .filter_map(|definition| match definition {
+                Definition::Template { name, body, .. } | Definition::Function { name, body, .. } => {
+                    Some((name.clone(), body.clone()))
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn aliases(pairs: &[(&str, &str)]) -> AliasMap {
+        pairs.iter().map(|(alias, namespace)| (alias.to_string(), namespace.to_string())).collect()
+    }
+
+    const LIBRARY: &str = "
+        function twice(x) { return 2 * x; }
+        template Square() {
+            signal input in;
+            signal output out;
+            out <== in * twice(in);
+        }
+        template Apply(function f) {
+            signal input in;
+            signal output out[2];
+            component s = Square();
+            s.in <== f(in);
+            out[0] <== s.out;
+            out[1] <== O.Square()(twice(in));
+        }
+    ";
+
+    #[test]
+    fn definitions_are_qualified_with_their_namespace() {
+        let mut program = parse(LIBRARY);
+        let reports =
+            apply_namespace(&mut program.definitions, None, &Some("L".to_string()), &aliases(&[("O", "Other")]), 0);
+        assert!(reports.is_empty());
+        // the calls are written as they are resolved, the parameters of the
+        // templates that receive functions are left as they are
+        let expected = parse(
+            "
+            function twice(x) { return 2 * x; }
+            template Square() {
+                signal input in;
+                signal output out;
+                out <== in * L.twice(in);
+            }
+            template Apply(function f) {
+                signal input in;
+                signal output out[2];
+                component s = L.Square();
+                s.in <== f(in);
+                out[0] <== s.out;
+                out[1] <== Other.Square()(L.twice(in));
+            }
+            ",
+        );
+        let names: Vec<String> = bodies(&program.definitions).into_iter().map(|(name, _)| name).collect();
+        assert_eq!(names, vec!["L.twice", "L.Square", "L.Apply"]);
+        for ((_, body), (_, expected)) in bodies(&program.definitions).iter().zip(bodies(&expected.definitions).iter()) {
+            assert!(body == expected);
+        }
+    }
+
+    #[test]
+    fn the_global_namespace_only_resolves_the_aliases() {
+        let mut program = parse(&format!("{}\ncomponent main = O.Square();", LIBRARY));
+        let main = program.main_component.as_mut();
+        let reports = apply_namespace(&mut program.definitions, main, &None, &aliases(&[("O", "Other")]), 0);
+        assert!(reports.is_empty());
+        let expected = parse(&LIBRARY.replace("O.Square", "Other.Square"));
+        assert!(bodies(&program.definitions) == bodies(&expected.definitions));
+        match &program.main_component.unwrap().1 {
+            Expression::Call { id, .. } => assert_eq!(id, "Other.Square"),
+            _ => panic!("the main component is not a call"),
+        }
+    }
+
+    #[test]
+    fn unknown_aliases_are_reported() {
+        let mut program = parse(LIBRARY);
+        let reports = apply_namespace(&mut program.definitions, None, &None, &AliasMap::new(), 0);
+        let codes: Vec<ReportCode> = reports.iter().map(|report| *report.get_code()).collect();
+        assert_eq!(codes, vec![ReportCode::UndefinedNamespace]);
+    }
+}
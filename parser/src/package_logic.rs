@@ -0,0 +1,479 @@
+use program_structure::error_code::ReportCode;
+use program_structure::error_definition::Report;
+use serde_derive::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+// A package is a directory with a circom.toml manifest that declares the
+// libraries the circuits of the package depend on:
+//
+//   [package]
+//   name = "wallet"
+//   version = "0.3.0"
+//
+//   [dependencies]
+//   circomlib = { path = "../node_modules/circomlib/circuits", version = "2.0.5" }
+//   poseidon = { vendored = "vendor/poseidon", version = "1.0.0", hash = "sha256:..." }
+//
+// An include whose first component is the name of a dependency, like
+// include "circomlib/poseidon.circom", is resolved inside the directory of the
+// dependency. The dependencies of the dependencies that are packages are
+// resolved too, and every name must resolve to a single library in the whole
+// tree. The resolved dependencies are recorded in circom.lock next to the
+// manifest together with the hash of their content, so that any later change
+// in the libraries is reported instead of silently compiled.
+
+pub const MANIFEST: &str = "circom.toml";
+pub const LOCKFILE: &str = "circom.lock";
+const LOCKFILE_HEADER: &str = "# This file is generated by circom from the dependencies of circom.toml.\n# Remove an entry to accept a change in the content of its library.\n\n";
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Manifest {
+    package: Option<PackageInfo>,
+    #[serde(default)]
+    dependencies: BTreeMap<String, DependencySpec>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PackageInfo {
+    #[allow(dead_code)]
+    name: String,
+    version: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct DependencySpec {
+    path: Option<String>,
+    vendored: Option<String>,
+    version: Option<String>,
+    hash: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Default, PartialEq)]
+struct Lockfile {
+    #[serde(default, rename = "dependency")]
+    dependencies: Vec<LockedDependency>,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+struct LockedDependency {
+    name: String,
+    version: Option<String>,
+    source: String,
+    hash: String,
+}
+
+#[derive(Deserialize)]
+struct PackageJson {
+    version: Option<String>,
+}
+
+pub struct Dependency {
+    directory: PathBuf,
+    version: Option<String>,
+    source: String,
+    hash: String,
+    manifest: PathBuf,
+}
+
+#[derive(Default)]
+pub struct Packages {
+    dependencies: BTreeMap<String, Dependency>,
+}
+
+impl Packages {
+    // Looks for a circom.toml in the directory of the main file and its
+    // ancestors; without manifest there are no dependencies.
+    pub fn load(main_file: &Path) -> Result<Packages, Box<Report>> {
+        let start = fs::canonicalize(main_file)
+            .ok()
+            .and_then(|file| file.parent().map(Path::to_path_buf))
+            .unwrap_or_else(|| PathBuf::from("."));
+        let root = match start.ancestors().find(|dir| dir.join(MANIFEST).is_file()) {
+            Some(root) => root.to_path_buf(),
+            None => return Ok(Packages::default()),
+        };
+        let mut packages = Packages::default();
+        let mut visited = Vec::new();
+        packages.add_package(&root, &root, &mut visited)?;
+        packages.check_lockfile(&root)?;
+        Ok(packages)
+    }
+
+    // Returns the file of a dependency named by the include, if any
+    pub fn resolve(&self, include: &str) -> Option<PathBuf> {
+        let (name, rest) = include.split_once('/')?;
+        let dependency = self.dependencies.get(name)?;
+        fs::canonicalize(dependency.directory.join(rest)).ok().filter(|path| path.is_file())
+    }
+
+    pub fn dependency_of(&self, include: &str) -> Option<&str> {
+        let (name, _) = include.split_once('/')?;
+        self.dependencies.get_key_value(name).map(|(name, _)| name.as_str())
+    }
+
+    fn add_package(&mut self, root: &Path, directory: &Path, visited: &mut Vec<PathBuf>) -> Result<(), Box<Report>> {
+        if visited.iter().any(|dir| dir == directory) {
+            return Ok(());
+        }
+        visited.push(directory.to_path_buf());
+        let manifest_path = directory.join(MANIFEST);
+        let manifest = read_manifest(&manifest_path)?;
+        for (name, spec) in manifest.dependencies {
+            let dependency = resolve_dependency(root, &manifest_path, &name, spec)?;
+            let dependency_dir = dependency.directory.clone();
+            if let Some(previous) = self.dependencies.get(&name) {
+                if previous.hash != dependency.hash || previous.version != dependency.version {
+                    return Err(package_error(format!(
+                        "The dependency {} is resolved to two different libraries: {} ({}) in {} and {} ({}) in {}",
+                        name,
+                        previous.directory.display(),
+                        display_version(&previous.version),
+                        previous.manifest.display(),
+                        dependency.directory.display(),
+                        display_version(&dependency.version),
+                        dependency.manifest.display(),
+                    )));
+                }
+                continue;
+            }
+            self.dependencies.insert(name, dependency);
+            if dependency_dir.join(MANIFEST).is_file() {
+                self.add_package(root, &dependency_dir, visited)?;
+            }
+        }
+        Ok(())
+    }
+
+    // Compares the dependencies with the ones recorded in circom.lock and
+    // records the new ones
+    fn check_lockfile(&self, root: &Path) -> Result<(), Box<Report>> {
+        let lock_path = root.join(LOCKFILE);
+        let previous = match fs::read_to_string(&lock_path) {
+            Ok(text) => toml::from_str::<Lockfile>(&text).map_err(|e| {
+                package_error(format!("Could not read {}: {}", lock_path.display(), e))
+            })?,
+            Err(_) => Lockfile::default(),
+        };
+        let mut lockfile = Lockfile::default();
+        for (name, dependency) in &self.dependencies {
+            let locked = previous.dependencies.iter().find(|locked| {
+                &locked.name == name && locked.source == dependency.source && locked.version == dependency.version
+            });
+            if let Some(locked) = locked {
+                if locked.hash != dependency.hash {
+                    let mut report = package_error(format!(
+                        "The content of the dependency {} in {} does not match the hash recorded in {}",
+                        name,
+                        dependency.directory.display(),
+                        lock_path.display()
+                    ));
+                    report.add_note(format!("Remove the entry of {} from {} to accept the change", name, LOCKFILE));
+                    return Err(report);
+                }
+            }
+            lockfile.dependencies.push(LockedDependency {
+                name: name.clone(),
+                version: dependency.version.clone(),
+                source: dependency.source.clone(),
+                hash: dependency.hash.clone(),
+            });
+        }
+        if lockfile != previous || !lock_path.is_file() {
+            let text = toml::to_string(&lockfile)
+                .map_err(|e| package_error(format!("Could not write {}: {}", lock_path.display(), e)))?;
+            fs::write(&lock_path, format!("{}{}", LOCKFILE_HEADER, text))
+                .map_err(|e| package_error(format!("Could not write {}: {}", lock_path.display(), e)))?;
+        }
+        Ok(())
+    }
+}
+
+fn read_manifest(path: &Path) -> Result<Manifest, Box<Report>> {
+    let text = fs::read_to_string(path)
+        .map_err(|e| package_error(format!("Could not read {}: {}", path.display(), e)))?;
+    toml::from_str(&text).map_err(|e| package_error(format!("Invalid manifest {}: {}", path.display(), e)))
+}
+
+fn resolve_dependency(root: &Path, manifest: &Path, name: &str, spec: DependencySpec) -> Result<Dependency, Box<Report>> {
+    let (kind, relative) = match (&spec.path, &spec.vendored) {
+        (Some(path), None) => ("path", path),
+        (None, Some(vendored)) => ("vendored", vendored),
+        _ => {
+            return Err(package_error(format!(
+                "The dependency {} in {} must have either a path or a vendored directory",
+                name,
+                manifest.display()
+            )))
+        }
+    };
+    let base = manifest.parent().unwrap();
+    let directory = fs::canonicalize(base.join(relative))
+        .ok()
+        .filter(|dir| dir.is_dir())
+        .ok_or_else(|| {
+            package_error(format!(
+                "The directory {} of the dependency {} in {} does not exist",
+                relative,
+                name,
+                manifest.display()
+            ))
+        })?;
+    let hash = content_hash(&directory)?;
+    if kind == "vendored" && (spec.version.is_none() || spec.hash.is_none()) {
+        return Err(package_error(format!(
+            "The vendored dependency {} in {} must declare its version and hash",
+            name,
+            manifest.display()
+        )));
+    }
+    if let Some(declared) = &spec.hash {
+        if *declared != hash {
+            let mut report = package_error(format!(
+                "The content of the dependency {} in {} does not match the hash declared in {}",
+                name,
+                directory.display(),
+                manifest.display()
+            ));
+            report.add_note(format!("The hash of its content is {}", hash));
+            return Err(report);
+        }
+    }
+    let found = library_version(&directory);
+    let version = match (spec.version, found) {
+        (Some(declared), Some(found)) if declared != found => {
+            return Err(package_error(format!(
+                "The dependency {} in {} requires version {} but {} contains version {}",
+                name,
+                manifest.display(),
+                declared,
+                directory.display(),
+                found
+            )))
+        }
+        (declared, found) => declared.or(found),
+    };
+    Ok(Dependency {
+        source: format!("{}+{}", kind, relative_path(root, &directory).display()),
+        directory,
+        version,
+        hash,
+        manifest: manifest.to_path_buf(),
+    })
+}
+
+// The version of a library is the one of its circom.toml or, for libraries
+// distributed with npm like circomlib, of the package.json of the library or
+// of its parent directory
+fn library_version(directory: &Path) -> Option<String> {
+    if let Ok(manifest) = read_manifest(&directory.join(MANIFEST)) {
+        return manifest.package.and_then(|package| package.version);
+    }
+    for dir in directory.ancestors().take(2) {
+        if let Ok(text) = fs::read_to_string(dir.join("package.json")) {
+            return serde_json::from_str::<PackageJson>(&text).ok().and_then(|package| package.version);
+        }
+    }
+    None
+}
+
+// Hash of the circom files of the directory and its subdirectories
+fn content_hash(directory: &Path) -> Result<String, Box<Report>> {
+    let mut files = Vec::new();
+    collect_circom_files(directory, &mut files)
+        .map_err(|e| package_error(format!("Could not read {}: {}", directory.display(), e)))?;
+    files.sort();
+    let mut hasher = Sha256::new();
+    for file in files {
+        let content = fs::read(&file)
+            .map_err(|e| package_error(format!("Could not read {}: {}", file.display(), e)))?;
+        let name = relative_path(directory, &file);
+        hasher.update(name.to_string_lossy().replace('\\', "/").as_bytes());
+        hasher.update([0]);
+        hasher.update((content.len() as u64).to_le_bytes());
+        hasher.update(&content);
+    }
+    let digest: String = hasher.finalize().iter().map(|byte| format!("{:02x}", byte)).collect();
+    Ok(format!("sha256:{}", digest))
+}
+
+fn collect_circom_files(directory: &Path, files: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    for entry in fs::read_dir(directory)? {
+        let path = entry?.path();
+        let hidden = path.file_name().is_some_and(|name| name.to_string_lossy().starts_with('.'));
+        if hidden {
+            continue;
+        }
+        if path.is_dir() {
+            collect_circom_files(&path, files)?;
+        } else if path.extension().is_some_and(|ext| ext == "circom") {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+fn relative_path(from: &Path, to: &Path) -> PathBuf {
+    let from: Vec<Component> = from.components().collect();
+    let to: Vec<Component> = to.components().collect();
+    let common = from.iter().zip(&to).take_while(|(a, b)| a == b).count();
+    let mut result = PathBuf::new();
+    for _ in common..from.len() {
+        result.push("..");
+    }
+    for component in &to[common..] {
+        result.push(component);
+    }
+    result
+}
+
+fn display_version(version: &Option<String>) -> String {
+    version.as_ref().map_or("no version".to_string(), |version| format!("version {}", version))
+}
+
+fn package_error(msg: String) -> Box<Report> {
+    Box::new(Report::error(msg, ReportCode::PackageError))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // Writes the files of a project in a new temporary directory
+    fn project(name: &str, files: &[(&str, &str)]) -> PathBuf {
+        let root = std::env::temp_dir().join(format!("circom_packages_{}_{}", name, std::process::id()));
+        if root.exists() {
+            fs::remove_dir_all(&root).unwrap();
+        }
+        for (file, content) in files {
+            let path = root.join(file);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, content).unwrap();
+        }
+        fs::canonicalize(root).unwrap()
+    }
+
+    fn load_error(main: &Path) -> String {
+        match Packages::load(main) {
+            Ok(_) => panic!("the packages are loaded"),
+            Err(report) => report.get_message().clone(),
+        }
+    }
+
+    const LIBRARY: &str = "template Hash() {}";
+
+    #[test]
+    fn includes_are_resolved_in_their_dependencies() {
+        let root = project(
+            "resolved",
+            &[
+                ("app/circom.toml", "[package]\nname = \"app\"\n\n[dependencies]\nlib = { path = \"../lib\", version = \"1.0.0\" }\n"),
+                ("app/main.circom", "include \"lib/hash.circom\";"),
+                ("lib/circom.toml", "[package]\nname = \"lib\"\nversion = \"1.0.0\"\n"),
+                ("lib/hash.circom", LIBRARY),
+            ],
+        );
+        let packages = Packages::load(&root.join("app/main.circom")).ok().unwrap();
+        assert_eq!(packages.resolve("lib/hash.circom"), Some(root.join("lib/hash.circom")));
+        assert_eq!(packages.resolve("lib/missing.circom"), None);
+        assert_eq!(packages.resolve("other/hash.circom"), None);
+        assert_eq!(packages.dependency_of("lib/missing.circom"), Some("lib"));
+        let lockfile: Lockfile = toml::from_str(&fs::read_to_string(root.join("app").join(LOCKFILE)).unwrap()).unwrap();
+        let locked = &lockfile.dependencies[0];
+        assert_eq!(lockfile.dependencies.len(), 1);
+        assert_eq!((locked.name.as_str(), locked.version.as_deref()), ("lib", Some("1.0.0")));
+        assert_eq!(locked.source, "path+../lib");
+        assert_eq!(locked.hash, content_hash(&root.join("lib")).ok().unwrap());
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn changes_in_the_locked_dependencies_are_reported() {
+        let root = project(
+            "locked",
+            &[
+                ("app/circom.toml", "[dependencies]\nlib = { path = \"../lib\" }\n"),
+                ("app/main.circom", ""),
+                ("lib/hash.circom", LIBRARY),
+            ],
+        );
+        let main = root.join("app/main.circom");
+        assert!(Packages::load(&main).is_ok());
+        // other files than the circom ones are not part of the content
+        fs::write(root.join("lib/README.md"), "the library").unwrap();
+        assert!(Packages::load(&main).is_ok());
+        fs::write(root.join("lib/hash.circom"), "template Hash(n) {}").unwrap();
+        assert!(load_error(&main).contains("does not match the hash recorded in"));
+        fs::remove_file(root.join("app").join(LOCKFILE)).unwrap();
+        assert!(Packages::load(&main).is_ok());
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn versions_and_hashes_of_the_dependencies_are_checked() {
+        let files = |dependency: &str| {
+            vec![
+                ("app/circom.toml".to_string(), format!("[dependencies]\nlib = {}\n", dependency)),
+                ("app/main.circom".to_string(), String::new()),
+                ("lib/circuits/hash.circom".to_string(), LIBRARY.to_string()),
+                ("lib/package.json".to_string(), "{ \"version\": \"2.0.5\" }".to_string()),
+            ]
+        };
+        let load = |name: &str, dependency: &str| {
+            let files = files(dependency);
+            let files: Vec<(&str, &str)> = files.iter().map(|(file, content)| (file.as_str(), content.as_str())).collect();
+            let root = project(name, &files);
+            let result = Packages::load(&root.join("app/main.circom")).map_err(|report| report.get_message().clone());
+            let hash = content_hash(&root.join("lib/circuits")).ok().unwrap();
+            fs::remove_dir_all(root).unwrap();
+            (result, hash)
+        };
+        // the version of a library distributed with npm is the one of its package.json
+        assert!(load("npm", "{ path = \"../lib/circuits\", version = \"2.0.5\" }").0.is_ok());
+        let (result, _) = load("version", "{ path = \"../lib/circuits\", version = \"2.1.0\" }");
+        assert!(result.err().unwrap().contains("requires version 2.1.0 but"));
+        let (result, _) = load("unhashed", "{ vendored = \"../lib/circuits\", version = \"2.0.5\" }");
+        assert!(result.err().unwrap().contains("must declare its version and hash"));
+        let (result, hash) = load("hashed", "{ vendored = \"../lib/circuits\", version = \"2.0.5\", hash = \"sha256:00\" }");
+        assert!(result.err().unwrap().contains("does not match the hash declared in"));
+        let vendored = format!("{{ vendored = \"../lib/circuits\", version = \"2.0.5\", hash = \"{}\" }}", hash);
+        assert!(load("vendored", &vendored).0.is_ok());
+        let (result, _) = load("sourceless", "{ version = \"2.0.5\" }");
+        assert!(result.err().unwrap().contains("must have either a path or a vendored directory"));
+    }
+
+    #[test]
+    fn every_dependency_is_a_single_library() {
+        let root = project(
+            "single",
+            &[
+                ("app/circom.toml", "[dependencies]\nlib = { path = \"../lib\" }\nother = { path = \"../other\" }\n"),
+                ("app/main.circom", ""),
+                ("lib/hash.circom", LIBRARY),
+                ("other/circom.toml", "[dependencies]\nlib = { path = \"../fork\" }\n"),
+                ("other/other.circom", "include \"lib/hash.circom\";"),
+                ("fork/hash.circom", "template Hash(n) {}"),
+            ],
+        );
+        let main = root.join("app/main.circom");
+        assert!(load_error(&main).contains("The dependency lib is resolved to two different libraries"));
+        // the same content in another directory is the same library
+        fs::write(root.join("fork/hash.circom"), LIBRARY).unwrap();
+        assert!(Packages::load(&main).is_ok());
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn there_are_no_dependencies_without_manifest() {
+        let root = project("manifestless", &[("main.circom", "")]);
+        let packages = Packages::load(&root.join("main.circom")).ok().unwrap();
+        assert!(packages.dependencies.is_empty());
+        assert!(!root.join(LOCKFILE).exists());
+        fs::remove_dir_all(root).unwrap();
+    }
+}
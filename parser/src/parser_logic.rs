@@ -5,25 +5,43 @@ use program_structure::error_code::ReportCode;
 use program_structure::error_definition::{ReportCollection, Report};
 use program_structure::file_definition::FileID;
 
-pub fn preprocess(expr: &str, file_id: FileID) -> Result<String, ReportCollection> {
+// A comment removed by the preprocessing, given by the bytes it takes in the source
+#[derive(Clone)]
+pub struct Comment {
+    pub start: usize,
+    pub end: usize,
+}
+
+// The source without comments, where the comments are replaced by spaces
+// so that the locations of the tokens do not change, and the comments
+pub struct Preprocessed {
+    pub text: String,
+    pub comments: Vec<Comment>,
+}
+
+pub fn preprocess(expr: &str, file_id: FileID) -> Result<Preprocessed, ReportCollection> {
     let mut pp = String::new();
+    let mut comments = Vec::new();
     let mut state = 0;
     let mut loc = 0;
     let mut block_start = 0;
+    let mut comment_start = 0;
 
-    let mut it = expr.chars();
-    while let Some(c0) = it.next() {
+    let mut it = expr.char_indices();
+    while let Some((i0, c0)) = it.next() {
         loc += 1;
         match (state, c0) {
             (0, '/') => {
                 loc += 1;
-                match it.next() {
+                match it.next().map(|(_, c)| c) {
                     Some('/') => {
+                        comment_start = i0;
                         state = 1;
                         pp.push(' ');
                         pp.push(' ');
                     }
                     Some('*') => {
+                        comment_start = i0;
                         block_start = loc;
                         state = 2;
                         pp.push(' ');
@@ -41,24 +59,26 @@ pub fn preprocess(expr: &str, file_id: FileID) -> Result<String, ReportCollectio
             }
             (0, _) => pp.push(c0),
             (1, '\n') => {
+                comments.push(Comment { start: comment_start, end: i0 });
                 pp.push(c0);
                 state = 0;
             }
             (2, '*') => {
                 loc += 1;
                 let mut next = it.next();
-                while next == Some('*') {
+                while next.map(|(_, c)| c) == Some('*') {
                     pp.push(' ');
                     loc += 1;
                     next = it.next();
                 }
                 match next {
-                    Some('/') => {
+                    Some((i, '/')) => {
+                        comments.push(Comment { start: comment_start, end: i + 1 });
                         pp.push(' ');
                         pp.push(' ');
                         state = 0;
                     }
-                    Some(c) => {
+                    Some((_, c)) => {
                         pp.push(' ');
                         for _i in 0..c.len_utf8() {
                             pp.push(' ');
@@ -74,23 +94,32 @@ pub fn preprocess(expr: &str, file_id: FileID) -> Result<String, ReportCollectio
             }
         }
     }
+    if state == 1 {
+        comments.push(Comment { start: comment_start, end: expr.len() });
+    }
     if state == 2 {
         Err(vec![
             produce_report(ReportCode::UnclosedComment,  block_start..block_start, file_id)
         ])
     } else {
-        Ok(pp)
+        Ok(Preprocessed { text: pp, comments })
     }
 }
 
 pub fn parse_file(src: &str, file_id: FileID) -> Result<AST, ReportCollection> {
+    parse_file_with_comments(src, file_id).map(|(ast, _)| ast)
+}
+
+// Parses a file keeping the source without comments and its comments,
+// which are needed to print the file back
+pub fn parse_file_with_comments(src: &str, file_id: FileID) -> Result<(AST, Preprocessed), ReportCollection> {
     use lalrpop_util::ParseError::*;
 
     let mut errors = Vec::new();
     let preprocess = preprocess(src, file_id)?;
 
     let ast = lang::ParseAstParser::new()
-        .parse(file_id, &mut errors, &preprocess)
+        .parse(file_id, &mut errors, &preprocess.text)
         // TODO: is this always fatal?
         .map_err(|parse_error| match parse_error {
             InvalidToken { location } => 
@@ -118,7 +147,7 @@ pub fn parse_file(src: &str, file_id: FileID) -> Result<AST, ReportCollection> {
         return Err(errors.into_iter().collect());
     }
 
-    Ok(ast)
+    Ok((ast, preprocess))
 }
 
 fn produce_generic_report(format: String, token: std::ops::Range<usize>, file_id: usize) -> Report {
@@ -127,3 +156,101 @@ fn produce_generic_report(format: String, token: std::ops::Range<usize>, file_id
     report
 }
 
+
+#[cfg(test)]
+mod test {
+    use super::parse_file;
+    use program_structure::ast::{Definition, Statement, VarType, VariableType};
+
+    // The keywords added after 2.1.8 name symbols everywhere they can not
+    // start their construction
+    const SOURCE: &str = r#"
+        pragma circom 2.1.8;
+        include "library.circom" as as;
+        function keep(test, as) {
+            var bool = test;
+            var u32 = as;
+            var test2 = bool + u32;
+            return test2;
+        }
+        const const = 2;
+        inline template inline() {
+            signal input keep;
+            signal input test[2];
+            signal keep as;
+            signal output keep u32;
+            var u32 bool = 3;
+            var bool u32b = 1;
+            const inline = 4;
+            var test3 = inline + const;
+            as <== keep * test[0];
+            u32 <== as + test[1];
+        }
+        test "keywords" {
+            var test = 3;
+            component c = inline();
+        }
+    "#;
+
+    // The name, whether it is a signal, its type, whether it is kept and
+    // whether it is constant, for every declaration of a block
+    fn declarations(stmt: &Statement, found: &mut Vec<(String, bool, VarType, bool, bool)>) {
+        match stmt {
+            Statement::Block { stmts, .. } => stmts.iter().for_each(|stmt| declarations(stmt, found)),
+            Statement::InitializationBlock { initializations, .. } => {
+                initializations.iter().for_each(|stmt| declarations(stmt, found))
+            }
+            Statement::Declaration { name, xtype, var_type, is_kept, is_constant, .. } => {
+                let is_signal = matches!(xtype, VariableType::Signal(..));
+                found.push((name.clone(), is_signal, *var_type, *is_kept, *is_constant));
+            }
+            _ => {}
+        }
+    }
+
+    #[test]
+    fn contextual_keywords_name_symbols() {
+        let ast = parse_file(SOURCE, 0).unwrap_or_else(|reports| {
+            panic!("{:?}", reports.iter().map(|report| report.get_message().clone()).collect::<Vec<_>>())
+        });
+        assert_eq!(ast.includes[0].alias, Some("as".to_string()));
+        let names: Vec<&str> = ast
+            .definitions
+            .iter()
+            .map(|definition| match definition {
+                Definition::Template { name, .. }
+                | Definition::Function { name, .. }
+                | Definition::Bus { name, .. }
+                | Definition::Test { name, .. }
+                | Definition::Constant { name, .. } => name.as_str(),
+            })
+            .collect();
+        assert_eq!(names, vec!["keep", "const", "inline", "keywords"]);
+        let (args, body) = match &ast.definitions[0] {
+            Definition::Function { args, body, .. } => (args, body),
+            _ => unreachable!(),
+        };
+        assert_eq!(args, &vec!["test".to_string(), "as".to_string()]);
+        let mut found = Vec::new();
+        declarations(body, &mut found);
+        let field = |name: &str| (name.to_string(), false, VarType::Field, false, false);
+        assert_eq!(found, vec![field("bool"), field("u32"), field("test2")]);
+        let body = match &ast.definitions[2] {
+            Definition::Template { body, is_inline: true, .. } => body,
+            _ => panic!("inline template inline() is an inline template"),
+        };
+        let mut found = Vec::new();
+        declarations(body, &mut found);
+        let expected = vec![
+            ("keep".to_string(), true, VarType::Field, false, false),
+            ("test".to_string(), true, VarType::Field, false, false),
+            ("as".to_string(), true, VarType::Field, true, false),
+            ("u32".to_string(), true, VarType::Field, true, false),
+            ("bool".to_string(), false, VarType::U32, false, false),
+            ("u32b".to_string(), false, VarType::Bool, false, false),
+            ("inline".to_string(), false, VarType::Field, false, true),
+            ("test3".to_string(), false, VarType::Field, false, false),
+        ];
+        assert_eq!(found, expected);
+    }
+}
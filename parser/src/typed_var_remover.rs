@@ -0,0 +1,651 @@
+use num_bigint::BigInt;
+use num_traits::{ToPrimitive, Zero};
+use program_structure::ast::*;
+use program_structure::error_code::ReportCode;
+use program_structure::error_definition::{Report, ReportCollection};
+use program_structure::expression_builders::*;
+use program_structure::file_definition::{FileID, FileLocation};
+use std::collections::HashMap;
+
+// Every backend computes with field elements, so the operations on typed vars
+// are rewritten into field operations whose results stay in the range of the
+// type: the results of u32 operations are reduced modulo 2^32 and bool values
+// are 0 or 1. An operation can not mix values of different types, but numbers
+// take the type of the other operand and an assignment converts the value to
+// the type of the assigned var: u32 vars take the value modulo 2^32 and bool
+// vars take 1 for any value different from 0. The reductions modulo 2^32 are
+// only right when the product of two u32 values does not overflow the field,
+// so the declarations of u32 vars are returned to be checked with the prime.
+
+const U32_BITS: usize = 32;
+
+#[derive(Copy, Clone, PartialEq)]
+enum Value {
+    // numbers and operations on numbers, that take the type they are used with
+    Number,
+    Of(VarType),
+}
+
+pub fn remove_typed_vars(
+    definitions: &mut [(FileID, Vec<Definition>)],
+) -> Result<Vec<(FileID, FileLocation)>, ReportCollection> {
+    let mut reports = ReportCollection::new();
+    let mut u32_vars = Vec::new();
+    for (file_id, file_definitions) in definitions.iter_mut() {
+        for definition in file_definitions {
+            let mut remover =
+                Remover { file_id: *file_id, scopes: vec![HashMap::new()], reports: Vec::new(), u32_vars: Vec::new() };
+            match definition {
+                Definition::Template { body, .. }
+                | Definition::Function { body, .. }
+                | Definition::Bus { body, .. }
+                | Definition::Test { body, .. } => remover.statement(body),
+                Definition::Constant { .. } => {}
+            }
+            reports.append(&mut remover.reports);
+            u32_vars.append(&mut remover.u32_vars);
+        }
+    }
+    if reports.is_empty() {
+        Ok(u32_vars)
+    } else {
+        Err(reports)
+    }
+}
+
+struct Remover {
+    file_id: FileID,
+    // type and number of dimensions of the symbols declared in each block
+    scopes: Vec<HashMap<String, (VarType, usize)>>,
+    reports: ReportCollection,
+    u32_vars: Vec<(FileID, FileLocation)>,
+}
+
+impl Remover {
+    // Parameters hold field elements
+    fn symbol(&self, name: &str) -> (VarType, usize) {
+        self.scopes.iter().rev().find_map(|scope| scope.get(name).cloned()).unwrap_or((VarType::Field, 0))
+    }
+
+    fn error(&mut self, meta: &Meta, msg: String) {
+        let mut report = Report::error(msg, ReportCode::InvalidVarType);
+        report.add_primary(meta.location.clone(), self.file_id, "found here".to_string());
+        self.reports.push(report);
+    }
+
+    fn statement(&mut self, stmt: &mut Statement) {
+        use Statement::*;
+        match stmt {
+            IfThenElse { cond, if_case, else_case, .. } => {
+                self.expression(cond);
+                self.statement(if_case);
+                if let Some(else_case) = else_case {
+                    self.statement(else_case);
+                }
+            }
+            While { cond, stmt, .. } => {
+                self.expression(cond);
+                self.statement(stmt);
+            }
+            Return { value, .. } => {
+                self.expression(value);
+            }
+            InitializationBlock { initializations, .. } => {
+                for init in initializations {
+                    self.statement(init);
+                }
+            }
+            Declaration { meta, xtype, name, dimensions, var_type, .. } => {
+                for dimension in dimensions.iter_mut() {
+                    self.expression(dimension);
+                }
+                let var_type = if *xtype == VariableType::Var { *var_type } else { VarType::Field };
+                if var_type == VarType::U32 {
+                    self.u32_vars.push((self.file_id, meta.location.clone()));
+                }
+                self.scopes.last_mut().unwrap().insert(name.clone(), (var_type, dimensions.len()));
+            }
+            Substitution { var, access, op, rhe, .. } => {
+                self.accesses(access);
+                let (var_type, dimensions) = self.symbol(var);
+                if *op == AssignOp::AssignVar && var_type != VarType::Field {
+                    let indexes = access.iter().filter(|acc| matches!(acc, Access::ArrayAccess(..))).count();
+                    self.convert(rhe, var_type, dimensions.saturating_sub(indexes));
+                } else {
+                    self.expression(rhe);
+                }
+            }
+            MultSubstitution { lhe, rhe, .. } => {
+                self.assigned_tuple(lhe);
+                self.expression(rhe);
+            }
+            ConstraintEquality { lhe, rhe, .. } => {
+                self.expression(lhe);
+                self.expression(rhe);
+            }
+            UnderscoreSubstitution { rhe, .. } => {
+                self.expression(rhe);
+            }
+            LogCall { args, .. } => {
+                for arg in args {
+                    if let LogArgument::LogExp(exp) = arg {
+                        self.expression(exp);
+                    }
+                }
+            }
+            Block { stmts, .. } => {
+                self.scopes.push(HashMap::new());
+                for stmt in stmts {
+                    self.statement(stmt);
+                }
+                self.scopes.pop();
+            }
+            Assert { arg, .. } => {
+                self.expression(arg);
+            }
+        }
+    }
+
+    // The values of a tuple are assigned without conversion
+    fn assigned_tuple(&mut self, lhe: &mut Expression) {
+        match lhe {
+            Expression::Variable { meta, name, access } => {
+                self.accesses(access);
+                let var_type = self.symbol(name).0;
+                if var_type != VarType::Field {
+                    let msg = format!("The {} var {} can not be assigned with a tuple", type_name(var_type), name);
+                    let meta = meta.clone();
+                    self.error(&meta, msg);
+                }
+            }
+            Expression::Tuple { values, .. } => {
+                for value in values {
+                    self.assigned_tuple(value);
+                }
+            }
+            _ => {
+                self.expression(lhe);
+            }
+        }
+    }
+
+    fn accesses(&mut self, access: &mut [Access]) {
+        for acc in access {
+            if let Access::ArrayAccess(exp) = acc {
+                self.expression(exp);
+            }
+        }
+    }
+
+    // Converts the value assigned to a var of the given type, the arrays are
+    // converted element by element
+    fn convert(&mut self, expr: &mut Expression, var_type: VarType, dimensions: usize) {
+        use Expression::*;
+        match expr {
+            ArrayInLine { values, .. } => {
+                for value in values {
+                    self.convert(value, var_type, dimensions.saturating_sub(1));
+                }
+            }
+            UniformArray { value, dimension, .. } => {
+                self.expression(dimension);
+                self.convert(value, var_type, dimensions.saturating_sub(1));
+            }
+            InlineSwitchOp { cond, if_true, if_false, .. } => {
+                self.expression(cond);
+                self.convert(if_true, var_type, dimensions);
+                self.convert(if_false, var_type, dimensions);
+            }
+            _ => match self.expression(expr) {
+                Value::Number => self.adapt_number(expr, var_type),
+                Value::Of(value_type) if value_type == var_type => {}
+                Value::Of(value_type) if dimensions > 0 => {
+                    let msg = format!(
+                        "An array of {} values can not be assigned to a {} array, its elements must be assigned one by one",
+                        type_name(value_type),
+                        type_name(var_type)
+                    );
+                    self.error(expr.get_meta(), msg);
+                }
+                Value::Of(VarType::Bool) if var_type == VarType::U32 => {}
+                Value::Of(_) => {
+                    let meta = expr.get_meta().clone();
+                    let value = std::mem::replace(expr, build_number(meta.clone(), BigInt::zero()));
+                    *expr = match var_type {
+                        VarType::U32 => wrap(meta, value),
+                        _ => build_infix(meta.clone(), value, ExpressionInfixOpcode::NotEq, number(&meta, 0)),
+                    };
+                }
+            },
+        }
+    }
+
+    // Replaces a number used as a typed value by its value in the type
+    fn adapt_number(&mut self, expr: &mut Expression, var_type: VarType) {
+        if var_type == VarType::Field {
+            return;
+        }
+        match u32_value(expr) {
+            Some(value) => {
+                let value = if var_type == VarType::Bool && !value.is_zero() { BigInt::from(1) } else { value };
+                *expr = build_number(expr.get_meta().clone(), value);
+            }
+            None => {
+                let msg = format!("This number can not be computed as a {} value", type_name(var_type));
+                self.error(expr.get_meta(), msg);
+            }
+        }
+    }
+
+    // The type of the operands of an operation
+    fn operands(&mut self, meta: &Meta, lhe: &mut Expression, left: Value, rhe: &mut Expression, right: Value) -> Value {
+        match (left, right) {
+            (Value::Number, Value::Number) => Value::Number,
+            (Value::Of(var_type), Value::Number) => {
+                self.adapt_number(rhe, var_type);
+                left
+            }
+            (Value::Number, Value::Of(var_type)) => {
+                self.adapt_number(lhe, var_type);
+                right
+            }
+            (Value::Of(left_type), Value::Of(right_type)) => {
+                if left_type != right_type {
+                    let msg = format!(
+                        "Operations can not mix {} and {} values, assign the value to a var of the other type to convert it",
+                        type_name(left_type),
+                        type_name(right_type)
+                    );
+                    self.error(meta, msg);
+                }
+                left
+            }
+        }
+    }
+
+    fn expression(&mut self, expr: &mut Expression) -> Value {
+        use Expression::*;
+        match expr {
+            Number(..) => Value::Number,
+            Variable { name, access, .. } => {
+                self.accesses(access);
+                if access.iter().any(|acc| matches!(acc, Access::ComponentAccess(..))) {
+                    Value::Of(VarType::Field)
+                } else {
+                    Value::Of(self.symbol(name).0)
+                }
+            }
+            InfixOp { meta, lhe, infix_op, rhe } => {
+                let left = self.expression(lhe);
+                let right = self.expression(rhe);
+                let meta = meta.clone();
+                let infix_op = *infix_op;
+                match self.operands(&meta, lhe, left, rhe, right) {
+                    Value::Number if is_arithmetic(infix_op) => Value::Number,
+                    Value::Number | Value::Of(VarType::Field) => Value::Of(VarType::Field),
+                    Value::Of(VarType::Bool) => match infix_op {
+                        ExpressionInfixOpcode::BoolAnd
+                        | ExpressionInfixOpcode::BoolOr
+                        | ExpressionInfixOpcode::Eq
+                        | ExpressionInfixOpcode::NotEq => Value::Of(VarType::Bool),
+                        _ => {
+                            self.error(&meta, "This operator is not defined for bool values".to_string());
+                            Value::Of(VarType::Bool)
+                        }
+                    },
+                    Value::Of(VarType::U32) => {
+                        let lowered = std::mem::replace(expr, build_number(meta.clone(), BigInt::zero()));
+                        if let InfixOp { lhe, rhe, .. } = lowered {
+                            match u32_infix(meta.clone(), *lhe, infix_op, *rhe) {
+                                Ok((lowered, var_type)) => {
+                                    *expr = lowered;
+                                    return Value::Of(var_type);
+                                }
+                                Err(lowered) => *expr = *lowered,
+                            }
+                        }
+                        self.error(&meta, "This operator is not defined for u32 values".to_string());
+                        Value::Of(VarType::U32)
+                    }
+                }
+            }
+            PrefixOp { meta, prefix_op, rhe } => {
+                let value = self.expression(rhe);
+                let meta = meta.clone();
+                let prefix_op = *prefix_op;
+                match (value, prefix_op) {
+                    (Value::Number, ExpressionPrefixOpcode::BoolNot) => Value::Of(VarType::Field),
+                    (Value::Number, _) | (Value::Of(VarType::Field), _) => value,
+                    (Value::Of(VarType::Bool), ExpressionPrefixOpcode::BoolNot) => value,
+                    (Value::Of(VarType::U32), ExpressionPrefixOpcode::Sub) => {
+                        let rhe = std::mem::replace(rhe.as_mut(), number(&meta, 0));
+                        let negated = build_infix(meta.clone(), modulus(&meta), ExpressionInfixOpcode::Sub, rhe);
+                        *expr = wrap(meta, negated);
+                        value
+                    }
+                    (Value::Of(VarType::U32), ExpressionPrefixOpcode::Complement) => {
+                        let rhe = std::mem::replace(rhe.as_mut(), number(&meta, 0));
+                        let max = build_number(meta.clone(), max_u32());
+                        *expr = build_infix(meta, max, ExpressionInfixOpcode::Sub, rhe);
+                        value
+                    }
+                    (Value::Of(var_type), _) => {
+                        let msg = format!("This operator is not defined for {} values", type_name(var_type));
+                        self.error(&meta, msg);
+                        value
+                    }
+                }
+            }
+            InlineSwitchOp { meta, cond, if_true, if_false } => {
+                self.expression(cond);
+                let left = self.expression(if_true);
+                let right = self.expression(if_false);
+                let meta = meta.clone();
+                self.operands(&meta, if_true, left, if_false, right)
+            }
+            ArrayInLine { meta, values } => {
+                let types: Vec<Value> = values.iter_mut().map(|value| self.expression(value)).collect();
+                let mut array_type = Value::Number;
+                for value_type in types.iter() {
+                    if let (Value::Number, Value::Of(..)) = (array_type, value_type) {
+                        array_type = *value_type;
+                    } else if *value_type != Value::Number && *value_type != array_type {
+                        let meta = meta.clone();
+                        self.error(&meta, "The elements of an array must have the same type".to_string());
+                    }
+                }
+                if let Value::Of(var_type) = array_type {
+                    for (value, value_type) in values.iter_mut().zip(types) {
+                        if value_type == Value::Number {
+                            self.adapt_number(value, var_type);
+                        }
+                    }
+                }
+                array_type
+            }
+            UniformArray { value, dimension, .. } => {
+                self.expression(dimension);
+                self.expression(value)
+            }
+            ParallelOp { rhe, .. } => {
+                self.expression(rhe);
+                Value::Of(VarType::Field)
+            }
+            Call { args: values, .. } | Tuple { values, .. } => {
+                for value in values {
+                    self.expression(value);
+                }
+                Value::Of(VarType::Field)
+            }
+            AnonymousComp { params, signals, .. } => {
+                for value in params.iter_mut().chain(signals.iter_mut()) {
+                    self.expression(value);
+                }
+                Value::Of(VarType::Field)
+            }
+        }
+    }
+}
+
+// Operations on u32 values whose results are not always below 2^32 are reduced,
+// and the error gives back the operation when it is not defined for u32 values
+fn u32_infix(
+    meta: Meta,
+    lhe: Expression,
+    infix_op: ExpressionInfixOpcode,
+    rhe: Expression,
+) -> Result<(Expression, VarType), Box<Expression>> {
+    use ExpressionInfixOpcode::*;
+    let lowered = match infix_op {
+        Add | Mul => wrap(meta.clone(), build_infix(meta, lhe, infix_op, rhe)),
+        Sub => {
+            let shifted = build_infix(meta.clone(), lhe, Add, modulus(&meta));
+            wrap(meta.clone(), build_infix(meta, shifted, Sub, rhe))
+        }
+        Div | IntDiv => build_infix(meta, lhe, IntDiv, rhe),
+        Mod | ShiftR | BitAnd | BitOr | BitXor => build_infix(meta, lhe, infix_op, rhe),
+        ShiftL => {
+            let overflows = build_infix(meta.clone(), rhe.clone(), GreaterEq, number(&meta, U32_BITS));
+            let shifted = wrap(meta.clone(), build_infix(meta.clone(), lhe, ShiftL, rhe));
+            build_inline_switch_op(meta.clone(), overflows, number(&meta, 0), shifted)
+        }
+        Lesser | Greater | LesserEq | GreaterEq | Eq | NotEq => {
+            return Ok((build_infix(meta, lhe, infix_op, rhe), VarType::Bool));
+        }
+        Pow | BoolAnd | BoolOr => return Err(Box::new(build_infix(meta, lhe, infix_op, rhe))),
+    };
+    Ok((lowered, VarType::U32))
+}
+
+// Value of an operation on numbers with the semantics of u32 values, if it is
+// defined
+fn u32_value(expr: &Expression) -> Option<BigInt> {
+    use ExpressionInfixOpcode::*;
+    let size = BigInt::from(1) << U32_BITS;
+    match expr {
+        Expression::Number(_, value) => Some(value % &size),
+        Expression::PrefixOp { prefix_op, rhe, .. } => {
+            let value = u32_value(rhe)?;
+            match prefix_op {
+                ExpressionPrefixOpcode::Sub => Some((&size - value) % &size),
+                ExpressionPrefixOpcode::Complement => Some(max_u32() - value),
+                ExpressionPrefixOpcode::BoolNot => None,
+            }
+        }
+        Expression::InfixOp { lhe, infix_op, rhe, .. } => {
+            let left = u32_value(lhe)?;
+            let right = u32_value(rhe)?;
+            let shift = right.to_usize().filter(|shift| *shift < U32_BITS);
+            match infix_op {
+                Add => Some((left + right) % &size),
+                Sub => Some((left + &size - right) % &size),
+                Mul => Some((left * right) % &size),
+                Pow => Some(left.modpow(&right, &size)),
+                Div | IntDiv if !right.is_zero() => Some(left / right),
+                Mod if !right.is_zero() => Some(left % right),
+                ShiftL => Some(shift.map_or_else(BigInt::zero, |shift| (left << shift) % &size)),
+                ShiftR => Some(shift.map_or_else(BigInt::zero, |shift| left >> shift)),
+                BitAnd => Some(left & right),
+                BitOr => Some(left | right),
+                BitXor => Some(left ^ right),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+fn is_arithmetic(infix_op: ExpressionInfixOpcode) -> bool {
+    use ExpressionInfixOpcode::*;
+    matches!(infix_op, Mul | Div | Add | Sub | Pow | IntDiv | Mod | ShiftL | ShiftR | BitOr | BitAnd | BitXor)
+}
+
+fn type_name(var_type: VarType) -> &'static str {
+    match var_type {
+        VarType::Field => "field",
+        VarType::U32 => "u32",
+        VarType::Bool => "bool",
+    }
+}
+
+fn max_u32() -> BigInt {
+    BigInt::from(u32::MAX)
+}
+
+fn number(meta: &Meta, value: usize) -> Expression {
+    build_number(meta.clone(), BigInt::from(value))
+}
+
+fn modulus(meta: &Meta) -> Expression {
+    build_number(meta.clone(), BigInt::from(1) << U32_BITS)
+}
+
+fn wrap(meta: Meta, expr: Expression) -> Expression {
+    let size = modulus(&meta);
+    build_infix(meta, expr, ExpressionInfixOpcode::Mod, size)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::parser_logic;
+
+    fn parse(src: &str) -> Vec<Definition> {
+        parser_logic::parse_file(src, 0)
+            .unwrap_or_else(|_| panic!("the source does not parse: {}", src))
+            .definitions
+    }
+
+    fn removed(src: &str) -> Result<Vec<Definition>, Vec<String>> {
+        let mut definitions = vec![(0, parse(src))];
+        remove_typed_vars(&mut definitions).map_err(|reports| {
+            reports.iter().map(|report| report.get_message().clone()).collect::<Vec<_>>()
+        })?;
+        Ok(definitions.swap_remove(0).1)
+    }
+
+    #[test]
+    fn the_declarations_of_u32_vars_are_returned() {
+        let src = "function f(a) { var u32 x = a; var bool b = 1; var y = 2; var u32 z[2]; return x; }";
+        let mut definitions = vec![(0, parse(src))];
+        let u32_vars = remove_typed_vars(&mut definitions).ok().unwrap();
+        let declared = u32_vars.iter().map(|(_, location)| &src[location.clone()]).collect::<Vec<_>>();
+        assert_eq!(declared, vec!["var u32 x = a", "var u32 z[2]"]);
+    }
+
+    fn assert_removed(src: &str, expected: &str) {
+        let definitions = removed(src).unwrap_or_else(|reports| panic!("{:?}", reports));
+        assert!(
+            definitions == parse(expected),
+            "the typed vars of {} are not removed as in {}",
+            src,
+            expected
+        );
+    }
+
+    #[test]
+    fn numbers_take_their_value_in_the_type() {
+        assert_removed(
+            "
+            function f() {
+                var u32 a = -1;
+                var u32 b = 4294967295 + 2;
+                var u32 c = 0 - 3;
+                var u32 d = 65536 * 65537;
+                var u32 e = 1 << 32;
+                var u32 g = 1 << 31;
+                var u32 h = ~0;
+                var u32 k = 7 / 2;
+                var u32 l = 4294967296;
+                var bool t = 5;
+                var bool u = 0;
+                return a;
+            }
+            ",
+            "
+            function f() {
+                var u32 a = 4294967295;
+                var u32 b = 1;
+                var u32 c = 4294967293;
+                var u32 d = 65536;
+                var u32 e = 0;
+                var u32 g = 2147483648;
+                var u32 h = 4294967295;
+                var u32 k = 3;
+                var u32 l = 0;
+                var bool t = 1;
+                var bool u = 0;
+                return a;
+            }
+            ",
+        );
+    }
+
+    #[test]
+    fn u32_operations_are_reduced_modulo_2_32() {
+        assert_removed(
+            "
+            function f(x) {
+                var u32 a = 1;
+                var u32 b = 2;
+                var u32 c = a + b;
+                c = a - b;
+                c = a * b;
+                c = a / b;
+                c = a << b;
+                c = a >> b;
+                c = ~a;
+                c = -a;
+                c = a + 4294967297;
+                var bool t = a < b;
+                return c;
+            }
+            ",
+            "
+            function f(x) {
+                var u32 a = 1;
+                var u32 b = 2;
+                var u32 c = (a + b) % 4294967296;
+                c = ((a + 4294967296) - b) % 4294967296;
+                c = (a * b) % 4294967296;
+                c = a \\ b;
+                c = b >= 32 ? 0 : (a << b) % 4294967296;
+                c = a >> b;
+                c = 4294967295 - a;
+                c = (4294967296 - a) % 4294967296;
+                c = (a + 1) % 4294967296;
+                var bool t = a < b;
+                return c;
+            }
+            ",
+        );
+    }
+
+    #[test]
+    fn assignments_convert_the_values() {
+        assert_removed(
+            "
+            function f(x) {
+                var u32 a = x;
+                var bool t = x;
+                var u32 b = t;
+                var u32 v[2] = [x, 3];
+                var y = a;
+                return y;
+            }
+            ",
+            "
+            function f(x) {
+                var u32 a = x % 4294967296;
+                var bool t = x != 0;
+                var u32 b = t;
+                var u32 v[2] = [x % 4294967296, 3];
+                var y = a;
+                return y;
+            }
+            ",
+        );
+    }
+
+    #[test]
+    fn wrong_uses_of_typed_vars_are_reported() {
+        let cases = [
+            ("var u32 a = 1; var b = a + x;", "Operations can not mix u32 and field values, assign the value to a var of the other type to convert it"),
+            ("var u32 a = 1; var bool t = 1; var b = a + t;", "Operations can not mix u32 and bool values, assign the value to a var of the other type to convert it"),
+            ("var u32 a = 2; var b = a ** 2;", "This operator is not defined for u32 values"),
+            ("var bool t = 1; var b = t + 1;", "This operator is not defined for bool values"),
+            ("var u32 a = 2; var b = !a;", "This operator is not defined for u32 values"),
+            ("var u32 a; var b; (a, b) = (x, x);", "The u32 var a can not be assigned with a tuple"),
+            ("var v[2] = [x, x]; var u32 w[2] = v;", "An array of field values can not be assigned to a u32 array, its elements must be assigned one by one"),
+            ("var u32 a = 1; var v[2] = [a, x];", "The elements of an array must have the same type"),
+            ("var u32 a = 1 / 0;", "This number can not be computed as a u32 value"),
+        ];
+        for (body, message) in cases.iter() {
+            let src = format!("function f(x) {{ {} return x; }}", body);
+            match removed(&src) {
+                Ok(_) => panic!("no report for {}", src),
+                Err(reports) => assert_eq!(reports, vec![message.to_string()], "{}", src),
+            }
+        }
+    }
+}
@@ -20,6 +20,16 @@ pub fn build_main_component(public: Vec<String>, call: Expression) -> MainCompon
 
 pub type Version = (usize, usize, usize);
 
+#[derive(Clone, PartialEq)]
+pub struct Include {
+    pub meta: Meta,
+    pub path: String,
+    pub alias: Option<String>,
+}
+pub fn build_include(meta: Meta, path: String, alias: Option<String>) -> Include {
+    Include { meta, path, alias }
+}
+
 #[derive(Clone)]
 pub struct Meta {
     pub elem_id: usize,
@@ -80,14 +90,21 @@ impl Meta {
         self.file_id = Option::Some(file_id);
     }
 }
+// The metas are not part of the structure of the tree, two trees are equal
+// when they are equal up to the locations and the knowledge of their nodes
+impl PartialEq for Meta {
+    fn eq(&self, _other: &Meta) -> bool {
+        true
+    }
+}
 
-#[derive(Clone)]
+#[derive(Clone, PartialEq)]
 pub struct AST {
     pub meta: Meta,
     pub compiler_version: Option<Version>,
     pub custom_gates: bool,
     pub custom_gates_declared: bool,
-    pub includes: Vec<String>,
+    pub includes: Vec<Include>,
     pub definitions: Vec<Definition>,
     pub main_component: Option<MainComponent>,
 }
@@ -96,7 +113,7 @@ impl AST {
     pub fn new(
         meta: Meta,
         pragmas: Vec<Pragma>,
-        includes: Vec<String>,
+        includes: Vec<Include>,
         definitions: Vec<Definition>,
         main_component: Option<MainComponent>,
     ) -> (AST,Vec<Report>) {
@@ -143,10 +160,15 @@ pub enum Definition {
         meta: Meta,
         name: String,
         args: Vec<String>,
+        // dimensions annotated for each argument, empty when it has none
+        args_dimensions: Vec<Vec<Expression>>,
+        // whether each argument receives a function instead of a value
+        args_functions: Vec<bool>,
         arg_location: FileLocation,
         body: Statement,
         parallel: bool,
         is_custom_gate: bool,
+        is_inline: bool,
     },
     Function {
         meta: Meta,
@@ -155,17 +177,99 @@ pub enum Definition {
         arg_location: FileLocation,
         body: Statement,
     },
+    Bus {
+        meta: Meta,
+        name: String,
+        args: Vec<String>,
+        arg_location: FileLocation,
+        body: Statement,
+    },
+    // Test blocks are only kept by `circom test`, that runs each of them as
+    // the main component of its own circuit
+    Test {
+        meta: Meta,
+        name: String,
+        body: Statement,
+    },
+    // File level constants are replaced by their values in the parser
+    Constant {
+        meta: Meta,
+        name: String,
+        value: Expression,
+    },
+}
+// As the metas, the locations of the arguments are left out of the comparison
+impl PartialEq for Definition {
+    fn eq(&self, other: &Definition) -> bool {
+        use Definition::*;
+        match (self, other) {
+            (
+                Template { name, args, args_dimensions, args_functions, body, parallel, is_custom_gate, is_inline, .. },
+                Template {
+                    name: other_name,
+                    args: other_args,
+                    args_dimensions: other_dimensions,
+                    args_functions: other_functions,
+                    body: other_body,
+                    parallel: other_parallel,
+                    is_custom_gate: other_custom_gate,
+                    is_inline: other_inline,
+                    ..
+                },
+            ) => {
+                name == other_name
+                    && args == other_args
+                    && args_dimensions == other_dimensions
+                    && args_functions == other_functions
+                    && body == other_body
+                    && parallel == other_parallel
+                    && is_custom_gate == other_custom_gate
+                    && is_inline == other_inline
+            }
+            (Function { name, args, body, .. }, Function { name: other_name, args: other_args, body: other_body, .. })
+            | (Bus { name, args, body, .. }, Bus { name: other_name, args: other_args, body: other_body, .. }) => {
+                name == other_name && args == other_args && body == other_body
+            }
+            (Test { name, body, .. }, Test { name: other_name, body: other_body, .. }) => {
+                name == other_name && body == other_body
+            }
+            (Constant { name, value, .. }, Constant { name: other_name, value: other_value, .. }) => {
+                name == other_name && value == other_value
+            }
+            _ => false,
+        }
+    }
 }
 pub fn build_template(
     meta: Meta,
     name: String,
-    args: Vec<String>,
+    args: Vec<(String, Vec<Expression>, bool)>,
     arg_location: FileLocation,
     body: Statement,
     parallel: bool,
     is_custom_gate: bool,
+    is_inline: bool,
 ) -> Definition {
-    Definition::Template { meta, name, args, arg_location, body, parallel, is_custom_gate }
+    let mut names = Vec::new();
+    let mut args_dimensions = Vec::new();
+    let mut args_functions = Vec::new();
+    for (name, dimensions, is_function) in args {
+        names.push(name);
+        args_dimensions.push(dimensions);
+        args_functions.push(is_function);
+    }
+    Definition::Template {
+        meta,
+        name,
+        args: names,
+        args_dimensions,
+        args_functions,
+        arg_location,
+        body,
+        parallel,
+        is_custom_gate,
+        is_inline,
+    }
 }
 
 pub fn build_function(
@@ -178,7 +282,25 @@ pub fn build_function(
     Definition::Function { meta, name, args, arg_location, body }
 }
 
-#[derive(Clone)]
+pub fn build_bus(
+    meta: Meta,
+    name: String,
+    args: Vec<String>,
+    arg_location: FileLocation,
+    body: Statement,
+) -> Definition {
+    Definition::Bus { meta, name, args, arg_location, body }
+}
+
+pub fn build_test(meta: Meta, name: String, body: Statement) -> Definition {
+    Definition::Test { meta, name, body }
+}
+
+pub fn build_constant(meta: Meta, name: String, value: Expression) -> Definition {
+    Definition::Constant { meta, name, value }
+}
+
+#[derive(Clone, PartialEq)]
 pub enum Statement {
     IfThenElse {
         meta: Meta,
@@ -206,6 +328,9 @@ pub enum Statement {
         name: String,
         dimensions: Vec<Expression>,
         is_constant: bool,
+        var_type: VarType,
+        // signals declared with keep, which the simplification never removes
+        is_kept: bool,
     },
     Substitution {
         meta: Meta,
@@ -253,15 +378,25 @@ pub enum SignalType {
 
 pub type TagList = Vec<String>;
 
+// Values held by a var, which are field elements unless it is declared with
+// one of the integer types
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum VarType {
+    Field,
+    U32,
+    Bool,
+}
+
 #[derive(Clone, PartialEq, Ord, PartialOrd, Eq)]
 pub enum VariableType {
     Var,
     Signal(SignalType, TagList),
     Component,
     AnonymousComponent,
+    Bus(String, SignalType, TagList),
 }
 
-#[derive(Clone)]
+#[derive(Clone, PartialEq)]
 pub enum Expression {
     InfixOp {
         meta: Meta,
@@ -318,7 +453,7 @@ pub enum Expression {
     },
 }
 
-#[derive(Clone)]
+#[derive(Clone, PartialEq)]
 pub enum Access {
     ComponentAccess(String),
     ArrayAccess(Expression),
@@ -378,7 +513,7 @@ pub enum TypeReduction {
     Tag,
 }
 
-#[derive(Clone)]
+#[derive(Clone, PartialEq)]
 pub enum LogArgument {
     LogStr(String),
     LogExp(Expression),
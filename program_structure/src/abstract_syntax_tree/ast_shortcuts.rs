@@ -86,6 +86,65 @@ pub fn split_declaration_into_single_nodes(
     build_initialization_block(meta, xtype, initializations)
 }
 
+// var u32 x, y; is split as an untyped declaration whose vars get the type
+pub fn split_typed_declaration_into_single_nodes(meta: Meta, var_type: VarType, symbols: Vec<Symbol>) -> Statement {
+    let mut block = split_declaration_into_single_nodes(meta, VariableType::Var, symbols, AssignOp::AssignVar);
+    if let Statement::InitializationBlock { initializations, .. } = &mut block {
+        for initialization in initializations {
+            if let Statement::Declaration { var_type: declared, .. } = initialization {
+                *declared = var_type;
+            }
+        }
+    }
+    block
+}
+
+// signal keep x, y; is split as a signal declaration whose signals are kept
+pub fn keep_declared_signals(mut block: Statement, is_kept: bool) -> Statement {
+    if let Statement::InitializationBlock { initializations, .. } = &mut block {
+        for initialization in initializations {
+            if let Statement::Declaration { is_kept: kept, .. } = initialization {
+                *kept = is_kept;
+            }
+        }
+    }
+    block
+}
+
+// const N = e; is the declaration of a variable marked as constant
+// followed by its initialization
+pub fn split_constant_declaration(meta: Meta, name: String, value: Expression) -> Statement {
+    let declaration = build_constant_declaration(meta.clone(), name.clone());
+    let substitution = build_substitution(meta.clone(), name, vec![], AssignOp::AssignVar, value);
+    build_initialization_block(meta, VariableType::Var, vec![declaration, substitution])
+}
+
+// A bus declaration is followed by the assignment of its bus, as in
+// component declarations, which keeps the arguments of the bus
+pub fn split_bus_declaration_into_single_nodes(
+    meta: Meta,
+    header: (VariableType, Expression),
+    symbols: Vec<Symbol>,
+    op: AssignOp,
+) -> Statement {
+    let (xtype, bus) = header;
+    let mut initializations = Vec::new();
+
+    for symbol in symbols {
+        let single_declaration =
+            build_declaration(meta.clone(), xtype.clone(), symbol.name.clone(), symbol.is_array);
+        initializations.push(single_declaration);
+        let bus_assignment =
+            build_substitution(meta.clone(), symbol.name.clone(), vec![], AssignOp::AssignVar, bus.clone());
+        initializations.push(bus_assignment);
+        if let Option::Some(init) = symbol.init {
+            let substitution = build_substitution(meta.clone(), symbol.name, vec![], op, init);
+            initializations.push(substitution);
+        }
+    }
+    build_initialization_block(meta, xtype, initializations)
+}
+
 pub fn split_declaration_into_single_nodes_and_multisubstitution(
     meta: Meta,
     xtype: VariableType,
@@ -35,8 +35,14 @@ pub fn build_declaration(
     name: String,
     dimensions: Vec<Expression>,
 ) -> Statement {
+    let is_constant = false;
+    Declaration { meta, xtype, name, dimensions, is_constant, var_type: VarType::Field, is_kept: false }
+}
+
+// Declaration of a variable written with const, whose value can not change
+pub fn build_constant_declaration(meta: Meta, name: String) -> Statement {
     let is_constant = true;
-    Declaration { meta, xtype, name, dimensions, is_constant }
+    Declaration { meta, xtype: VariableType::Var, name, dimensions: Vec::new(), is_constant, var_type: VarType::Field, is_kept: false }
 }
 
 pub fn build_substitution(
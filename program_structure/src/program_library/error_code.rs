@@ -14,6 +14,17 @@ pub enum ReportCode {
     UnrecognizedPragma,
     ExpectedIdentifier,
     IncludeNotFound,
+    UndefinedNamespace,
+    PackageError,
+    AmbiguousInclude,
+    NotAConstantValue,
+    ConstantAssignment,
+    ConstantRedeclared,
+    InvalidVarType,
+    InlineCustomTemplate,
+    ComponentNotInlined,
+    InvalidFunctionArgument,
+    FormatChangesProgram,
     IllegalExpression,
     MultiplePragma,
     NoCompilerVersionWarning,
@@ -25,6 +36,7 @@ pub enum ReportCode {
     WrongTypesInAssignOperationArrayTemplates,
     WrongTypesInAssignOperationDims(usize, usize),
     WrongNumberOfArguments(usize, usize),
+    WrongArgumentDimensions(usize, usize),
     UndefinedFunction,
     UndefinedTemplate,
     UninitializedSymbolInExpression,
@@ -100,6 +112,7 @@ pub enum ReportCode {
     CustomGateIntermediateSignalWarning,
     CustomGateConstraintError,
     CustomGateSubComponentError,
+    CustomGateRelationError,
     CustomGatesPragmaError,
     CustomGatesVersionError,
     AnonymousCompError,
@@ -109,6 +122,7 @@ pub enum ReportCode {
     LoopMayOverflow,
     UnderscoreWithNoSignalWarning,
     TupleError,
+    BusError,
     InvalidSignalTagAccess,
     UninitializedComponent,
 }
@@ -137,6 +151,17 @@ impl fmt::Display for ReportCode {
             MultiplePragma => "P1013",
             IncludeNotFound => "P1014",
             ExpectedIdentifier => "P1015",
+            UndefinedNamespace => "P1016",
+            PackageError => "P1017",
+            AmbiguousInclude => "P1018",
+            NotAConstantValue => "P1019",
+            ConstantAssignment => "P1020",
+            ConstantRedeclared => "P1021",
+            InvalidVarType => "P1022",
+            InlineCustomTemplate => "P1023",
+            ComponentNotInlined => "P1024",
+            InvalidFunctionArgument => "P1025",
+            FormatChangesProgram => "P1026",
             UndefinedFunction => "T2001",
             UndefinedTemplate => "T2002",
             UninitializedSymbolInExpression => "T2003",
@@ -204,6 +229,7 @@ impl fmt::Display for ReportCode {
             NonComputableExpression => "T20464",
             WrongNumberOfArguments(..) => "T20465",
             UninitializedComponent => "T20466",
+            WrongArgumentDimensions(..) => "T20467",
             UnassignedSignalOnExecutionPath => "T2037",
             // Constraint analysis codes
             UnconstrainedSignal => "CA01",
@@ -216,8 +242,10 @@ impl fmt::Display for ReportCode {
             CustomGateSubComponentError => "CG03",
             CustomGatesPragmaError => "CG04",
             CustomGatesVersionError => "CG05",
+            CustomGateRelationError => "CG06",
             AnonymousCompError => "TAC01",
             TupleError => "TAC02",
+            BusError => "BUS01",
             UnderscoreWithNoSignalWarning => "TAC03",
             AnonymousCompLint => "TAC04",
             ConstantSignalLint => "CA05",
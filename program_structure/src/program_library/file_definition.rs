@@ -31,6 +31,18 @@ impl FileLibrary {
             None => None,
         }
     }
+    pub fn get_column(&self, start: usize, file_id: FileID) -> Option<usize> {
+        let line_index = self.files.line_index(file_id, start)?;
+        self.files.column_number(file_id, line_index, start)
+    }
+    pub fn get_file_name(&self, file_id: FileID) -> Option<String> {
+        // paths are stored in their debug format, surrounded by quotes
+        self.files.name(file_id).map(|name| name.trim_matches('"').to_string())
+    }
+    pub fn get_source(&self, location: FileLocation, file_id: FileID) -> Option<String> {
+        let source = self.files.source(file_id)?;
+        source.get(location).map(|text| text.to_string())
+    }
     pub fn to_storage(&self) -> &FileStorage {
         &self.get_files()
     }
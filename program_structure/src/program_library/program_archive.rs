@@ -1,5 +1,5 @@
 use super::ast::{Definition, Expression, MainComponent};
-use super::file_definition::{FileID, FileLibrary};
+use super::file_definition::{FileID, FileLibrary, FileLocation};
 use super::function_data::{FunctionData, FunctionInfo};
 use super::program_merger::Merger;
 use super::template_data::{TemplateData, TemplateInfo};
@@ -21,6 +21,10 @@ pub struct ProgramArchive {
     pub public_inputs: Vec<String>,
     pub initial_template_call: Expression,
     pub custom_gates: bool,
+    // the standard range check and lookup templates added by the parser
+    pub gadgets: HashSet<String>,
+    // the declarations of u32 vars, whose operations need a large enough prime
+    pub u32_vars: Vec<(FileID, FileLocation)>,
 }
 impl ProgramArchive {
     pub fn new(
@@ -60,6 +64,8 @@ impl ProgramArchive {
                 function_keys,
                 template_keys,
                 custom_gates,
+                gadgets: HashSet::new(),
+                u32_vars: Vec::new(),
             })
         } else {
             Err((file_library, reports))
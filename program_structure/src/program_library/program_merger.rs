@@ -29,7 +29,7 @@ impl Merger {
         let mut reports = vec![];
         for definition in definitions {
             let (name, meta) = match definition {
-                Definition::Template { name, args, arg_location, body, meta, parallel, is_custom_gate } => {
+                Definition::Template { name, args, args_dimensions, arg_location, body, meta, parallel, is_custom_gate, is_inline, .. } => {
                     if self.contains_function(&name) || self.contains_template(&name) {
                         (Option::Some(name), meta)
                     } else {
@@ -39,10 +39,12 @@ impl Merger {
                             body,
                             args.len(),
                             args,
+                            args_dimensions,
                             arg_location,
                             &mut self.fresh_id,
                             parallel,
                             is_custom_gate,
+                            is_inline,
                         );
                         self.get_mut_template_info().insert(name.clone(), new_data);
                         (Option::None, meta)
@@ -65,6 +67,10 @@ impl Merger {
                         (Option::None, meta)
                     }
                 }
+                // buses are expanded into their signals by the parser
+                Definition::Bus { .. } => continue,
+                // tests are taken out and constants replaced by the parser
+                Definition::Test { .. } | Definition::Constant { .. } => continue,
             };
             if let Option::Some(definition_name) = name {
                 let mut report = Report::error(
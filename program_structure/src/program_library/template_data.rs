@@ -1,5 +1,5 @@
 use super::ast;
-use super::ast::{FillMeta, Statement};
+use super::ast::{Expression, FillMeta, Statement};
 use super::file_definition::FileID;
 use crate::file_definition::FileLocation;
 use std::collections::{HashMap, HashSet, BTreeMap};
@@ -16,11 +16,13 @@ pub struct TemplateData {
     body: Statement,
     num_of_params: usize,
     name_of_params: Vec<String>,
+    dimensions_of_params: Vec<Vec<Expression>>,
     param_location: FileLocation,
     input_signals: SignalInfo,
     output_signals: SignalInfo,
     is_parallel: bool,
     is_custom_gate: bool,
+    is_inline: bool,
     /* Only used to know the order in which signals are declared.*/
     input_declarations: SignalDeclarationOrder,
     output_declarations: SignalDeclarationOrder,
@@ -33,12 +35,17 @@ impl TemplateData {
         mut body: Statement,
         num_of_params: usize,
         name_of_params: Vec<String>,
+        mut dimensions_of_params: Vec<Vec<Expression>>,
         param_location: FileLocation,
         elem_id: &mut usize,
         is_parallel: bool,
         is_custom_gate: bool,
+        is_inline: bool,
     ) -> TemplateData {
         body.fill(file_id, elem_id);
+        for dimension in dimensions_of_params.iter_mut().flatten() {
+            dimension.fill(file_id, elem_id);
+        }
         let mut input_signals = SignalInfo::new();
         let mut output_signals = SignalInfo::new();
         let mut input_declarations =  SignalDeclarationOrder::new();
@@ -50,11 +57,13 @@ impl TemplateData {
             body,
             num_of_params,
             name_of_params,
+            dimensions_of_params,
             param_location,
             input_signals,
             output_signals,
             is_parallel,
             is_custom_gate,
+            is_inline,
             input_declarations,
             output_declarations
         }
@@ -66,11 +75,13 @@ impl TemplateData {
         body: Statement,
         num_of_params: usize,
         name_of_params: Vec<String>,
+        dimensions_of_params: Vec<Vec<Expression>>,
         param_location: FileLocation,
         input_signals: SignalInfo,
         output_signals: SignalInfo,
         is_parallel: bool,
         is_custom_gate: bool,
+        is_inline: bool,
         input_declarations :SignalDeclarationOrder,
         output_declarations : SignalDeclarationOrder
     ) -> TemplateData {
@@ -80,11 +91,13 @@ impl TemplateData {
             body,
             num_of_params,
             name_of_params,
+            dimensions_of_params,
             param_location,
             input_signals,
             output_signals,
             is_parallel,
             is_custom_gate,
+            is_inline,
             input_declarations,
             output_declarations
         }
@@ -123,6 +136,10 @@ impl TemplateData {
     pub fn get_name_of_params(&self) -> &Vec<String> {
         &self.name_of_params
     }
+    // Dimensions annotated for each parameter, empty when it has none
+    pub fn get_dimensions_of_params(&self) -> &Vec<Vec<Expression>> {
+        &self.dimensions_of_params
+    }
     pub fn get_input_info(&self, name: &str) -> Option<&(usize, TagInfo)> {
         self.input_signals.get(name)
     }
@@ -150,6 +167,10 @@ impl TemplateData {
     pub fn is_custom_gate(&self) -> bool {
         self.is_custom_gate
     }
+    // Inline templates are expanded into the templates that instantiate them
+    pub fn is_inline(&self) -> bool {
+        self.is_inline
+    }
 }
 
 fn fill_inputs_and_outputs(
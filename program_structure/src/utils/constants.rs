@@ -23,20 +23,127 @@ impl Clone for UsefulConstants {
 }
 
 impl UsefulConstants {
-    pub fn new(possible_prime: &String) -> UsefulConstants {
-        let prime_to_use = if possible_prime.eq("bn128") {P_BN128} 
-          else if possible_prime.eq("bls12381") { P_BLS12381} 
-          else if possible_prime.eq("goldilocks") { P_GOLDILOCKS} 
-          else if possible_prime.eq("grumpkin") { P_GRUMPKIN} 
-          else if possible_prime.eq("pallas") { P_PALLAS} 
-          else if possible_prime.eq("vesta") { P_VESTA} 
-          else if possible_prime.eq("secq256r1") { P_SECQ256R1}
-          else {unreachable!()};
-
-        UsefulConstants { p: BigInt::parse_bytes(prime_to_use.as_bytes(), 10).expect("can not parse p") }
+    pub fn new(possible_prime: &str) -> UsefulConstants {
+        let p = prime_from_name_or_value(possible_prime).expect("can not parse p");
+        UsefulConstants { p }
     }
     
     pub fn get_p(&self) -> &BigInt {
         &self.p
     }
 }
+
+pub const NAMED_PRIMES: [&str; 7] =
+    ["bn128", "bls12381", "goldilocks", "grumpkin", "pallas", "vesta", "secq256r1"];
+
+fn named_prime_value(name: &str) -> Option<&'static str> {
+    match name {
+        "bn128" => Some(P_BN128),
+        "bls12381" => Some(P_BLS12381),
+        "goldilocks" => Some(P_GOLDILOCKS),
+        "grumpkin" => Some(P_GRUMPKIN),
+        "pallas" => Some(P_PALLAS),
+        "vesta" => Some(P_VESTA),
+        "secq256r1" => Some(P_SECQ256R1),
+        _ => None,
+    }
+}
+
+pub fn is_named_prime(prime: &str) -> bool {
+    named_prime_value(prime).is_some()
+}
+
+// Accepts either one of the named curves or a value written in decimal or in hexadecimal with a 0x prefix
+pub fn prime_from_name_or_value(prime: &str) -> Option<BigInt> {
+    if let Some(value) = named_prime_value(prime) {
+        return BigInt::parse_bytes(value.as_bytes(), 10);
+    }
+    let (digits, radix) = match prime.strip_prefix("0x").or_else(|| prime.strip_prefix("0X")) {
+        Some(hex) => (hex, 16),
+        None => (prime, 10),
+    };
+    if digits.is_empty() || digits.starts_with('-') || digits.starts_with('+') {
+        return None;
+    }
+    BigInt::parse_bytes(digits.as_bytes(), radix)
+}
+
+// Returns the name of the curve whose scalar field is p, if any
+pub fn prime_name_of_value(p: &BigInt) -> Option<&'static str> {
+    NAMED_PRIMES.iter().copied().find(|name| prime_from_name_or_value(name).as_ref() == Some(p))
+}
+
+// Deterministic Miller-Rabin for p < 3.3 * 10^24 and probabilistic with a negligible error above it
+pub fn is_probable_prime(p: &BigInt) -> bool {
+    let zero = BigInt::from(0);
+    let one = BigInt::from(1);
+    let two = BigInt::from(2);
+    const BASES: [u32; 20] = [2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41, 43, 47, 53, 59, 61, 67, 71];
+    if p < &two {
+        return false;
+    }
+    for base in BASES.iter() {
+        let base = BigInt::from(*base);
+        if p == &base {
+            return true;
+        }
+        if (p % &base) == zero {
+            return false;
+        }
+    }
+    let p_minus_one = p - &one;
+    let mut d = p_minus_one.clone();
+    let mut s = 0;
+    while (&d % &two) == zero {
+        d /= &two;
+        s += 1;
+    }
+    'bases: for base in BASES.iter() {
+        let mut x = BigInt::from(*base).modpow(&d, p);
+        if x == one || x == p_minus_one {
+            continue;
+        }
+        for _ in 1..s {
+            x = x.modpow(&two, p);
+            if x == p_minus_one {
+                continue 'bases;
+            }
+        }
+        return false;
+    }
+    true
+}
+
+// Fields of characteristic 2 are not supported by the witness generators
+pub fn is_odd_prime(p: &BigInt) -> bool {
+    p > &BigInt::from(2) && is_probable_prime(p)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_named_and_numeric_primes() {
+        let bn128 = prime_from_name_or_value("bn128").unwrap();
+        let hex = "0x30644e72e131a029b85045b68181585d2833e84879b9709143e1f593f0000001";
+        assert_eq!(prime_from_name_or_value(hex), Some(bn128.clone()));
+        assert_eq!(prime_from_name_or_value(P_BN128), Some(bn128.clone()));
+        assert_eq!(prime_name_of_value(&bn128), Some("bn128"));
+        assert_eq!(prime_from_name_or_value("0x"), None);
+        assert_eq!(prime_from_name_or_value("-7"), None);
+        assert_eq!(prime_from_name_or_value("bn254"), None);
+    }
+
+    #[test]
+    fn recognizes_primes() {
+        for name in NAMED_PRIMES.iter() {
+            assert!(is_probable_prime(&prime_from_name_or_value(name).unwrap()));
+        }
+        assert!(is_probable_prime(&BigInt::from(2013265921u64)));
+        assert!(!is_probable_prime(&BigInt::from(3215031751u64)));
+        assert!(!is_probable_prime(&(BigInt::from(2013265921u64) * BigInt::from(2147483647u64))));
+        assert!(!is_probable_prime(&BigInt::from(1)));
+        assert!(!is_odd_prime(&BigInt::from(2)));
+    }
+}
@@ -1,36 +1,103 @@
 use program_structure::ast::*;
 use program_structure::error_code::ReportCode;
 use program_structure::error_definition::{Report, ReportCollection};
+use std::collections::HashSet;
+
+// The relation of a custom template is a polynomial over its input and output
+// signals, so its expressions only combine numbers, parameters and signals
+// with sums, products and powers. The indexes of the signals and the exponents
+// are constant expressions, that may use any operator over the parameters.
+fn relation_analysis(
+    expression: &Expression,
+    parameters: &[String],
+    signals: &HashSet<String>,
+    constant: bool,
+    errors: &mut ReportCollection
+) {
+    use Expression::*;
+    use ExpressionInfixOpcode::*;
+    let allowed = match expression {
+        Number(..) => true,
+        Variable { name, access, .. } => {
+            let is_signal = signals.contains(name) && !constant;
+            let mut allowed = parameters.contains(name) || is_signal;
+            for access in access {
+                match access {
+                    Access::ArrayAccess(index) => relation_analysis(index, parameters, signals, true, errors),
+                    Access::ComponentAccess(_) => allowed = false,
+                }
+            }
+            allowed
+        }
+        InfixOp { lhe, infix_op: Pow, rhe, .. } => {
+            relation_analysis(lhe, parameters, signals, constant, errors);
+            relation_analysis(rhe, parameters, signals, true, errors);
+            true
+        }
+        InfixOp { lhe, infix_op, rhe, .. } if constant || matches!(infix_op, Add | Sub | Mul) => {
+            relation_analysis(lhe, parameters, signals, constant, errors);
+            relation_analysis(rhe, parameters, signals, constant, errors);
+            true
+        }
+        PrefixOp { prefix_op, rhe, .. } if constant || *prefix_op == ExpressionPrefixOpcode::Sub => {
+            relation_analysis(rhe, parameters, signals, constant, errors);
+            true
+        }
+        _ => false,
+    };
+    if !allowed {
+        let meta = expression.get_meta();
+        let mut error = Report::error(
+            String::from("Expression not allowed in the relation of a custom template"),
+            ReportCode::CustomGateRelationError
+        );
+        error.add_primary(
+            meta.location.clone(),
+            meta.file_id.unwrap(),
+            if constant {
+                String::from("Only numbers and parameters are allowed in indexes and exponents")
+            } else {
+                String::from("Only numbers, parameters and input and output signals combined with +, - and * are allowed")
+            }
+        );
+        errors.push(error);
+    }
+}
 
 pub fn custom_gate_analysis(
     custom_gate_name: &str,
+    custom_gate_parameters: &[String],
     custom_gate_body: &Statement
 ) -> Result<ReportCollection, ReportCollection> {
     fn custom_gate_analysis(
         custom_gate_name: &str,
         stmt: &Statement,
+        (parameters, signals): (&[String], &mut HashSet<String>),
         errors: &mut ReportCollection,
         warnings: &mut ReportCollection
     ) {
         use Statement::*;
         match stmt {
             IfThenElse { if_case, else_case, .. } => {
-                custom_gate_analysis(custom_gate_name, if_case, warnings, errors);
+                custom_gate_analysis(custom_gate_name, if_case, (parameters, signals), errors, warnings);
                 if let Some(else_case_s) = else_case {
-                    custom_gate_analysis(custom_gate_name, else_case_s, errors, warnings);
+                    custom_gate_analysis(custom_gate_name, else_case_s, (parameters, signals), errors, warnings);
                 }
             }
             While { stmt, .. } => {
-                custom_gate_analysis(custom_gate_name, stmt, errors, warnings);
+                custom_gate_analysis(custom_gate_name, stmt, (parameters, signals), errors, warnings);
             }
             InitializationBlock { initializations, .. } => {
                 for stmt in initializations {
-                    custom_gate_analysis(custom_gate_name, stmt, errors, warnings);
+                    custom_gate_analysis(custom_gate_name, stmt, (parameters, signals), errors, warnings);
                 }
             }
             Declaration { meta, xtype, name, .. } => {
                 use VariableType::*;
                 match xtype {
+                    Signal(SignalType::Input, _) | Signal(SignalType::Output, _) => {
+                        signals.insert(name.clone());
+                    }
                     Signal(SignalType::Intermediate, _) => {
                         let mut warning = Report::warning(
                             String::from("Intermediate signal inside custom template"),
@@ -84,21 +151,14 @@ pub fn custom_gate_analysis(
                     _ => {}
                 }
             }
-            ConstraintEquality { meta, .. } => {
-                let mut error = Report::error(
-                    String::from("Added constraint inside custom template"),
-                    ReportCode::CustomGateConstraintError
-                );
-                error.add_primary(
-                    meta.location.clone(),
-                    meta.file_id.unwrap(),
-                    String::from("Added constraint")
-                );
-                errors.push(error);
+            // the constraints of a custom template declare the relation of the gate
+            ConstraintEquality { lhe, rhe, .. } => {
+                relation_analysis(lhe, parameters, signals, false, errors);
+                relation_analysis(rhe, parameters, signals, false, errors);
             }
             Block { stmts, .. } => {
                 for stmt in stmts {
-                    custom_gate_analysis(custom_gate_name, stmt, errors, warnings);
+                    custom_gate_analysis(custom_gate_name, stmt, (parameters, signals), errors, warnings);
                 }
             }
             UnderscoreSubstitution { meta, op, .. } => {
@@ -126,7 +186,13 @@ pub fn custom_gate_analysis(
     let mut warnings = vec![];
     let mut errors = vec![];
 
-    custom_gate_analysis(custom_gate_name, custom_gate_body, &mut errors, &mut warnings);
+    custom_gate_analysis(
+        custom_gate_name,
+        custom_gate_body,
+        (custom_gate_parameters, &mut HashSet::new()),
+        &mut errors,
+        &mut warnings
+    );
 
     if errors.is_empty() {
         Result::Ok(warnings)
@@ -289,6 +289,11 @@ impl AstVisitor for AnonComponentLinter {
             let template_name = lint.template_name.clone();
             let data = program.get_template_data(&template_name);
             let name = &lint.var;
+            // the rewrite can only be suggested when every input is assigned as a whole
+            let args = match fmt_component_args(data, &self.components.get(name).unwrap().0) {
+                Some(args) => args,
+                None => continue,
+            };
             lints.push(Lint {
                 error_code: ReportCode::AnonymousCompLint,
                 error_msg: format!("Anonymous component: `{}`", name),
@@ -296,7 +301,7 @@ impl AstVisitor for AnonComponentLinter {
                 msg: format!(
                     "You can use ({}) <== {template_name}()({});",
                     fmt_component_outs(data, &self.components.get(name).unwrap().1),
-                    fmt_component_args(data, &self.components.get(name).unwrap().0)
+                    args
                 ),
                 level: LintLevel::Note,
             });
@@ -383,7 +388,7 @@ impl StaticLinter {
                     self.walk_ast(stmt);
                 }
             }
-            Declaration { meta, xtype, name, dimensions, is_constant } => {
+            Declaration { meta, xtype, name, dimensions, is_constant, .. } => {
                 for l in self.linters.iter_mut() {
                     l.visit_declaration(&self.program, meta, xtype, name, dimensions, *is_constant);
                 }
@@ -433,13 +438,12 @@ fn print_expr(program: &ProgramArchive, e: &Expression) -> String {
 }
 
 /// fmt_component_args formats the arguments to a component.
-fn fmt_component_args(data: &TemplateData, inputs: &HashMap<String, String>) -> String {
-    //FIXME: unwrap()
+fn fmt_component_args(data: &TemplateData, inputs: &HashMap<String, String>) -> Option<String> {
     data.get_declaration_inputs()
         .iter()
-        .map(|v| inputs.get(&v.0).unwrap().to_owned())
-        .collect::<Vec<String>>()
-        .join(", ")
+        .map(|v| inputs.get(&v.0).cloned())
+        .collect::<Option<Vec<String>>>()
+        .map(|args| args.join(", "))
 }
 /// fmt_component_args formats the left-hand side values of an assignment from a component.
 fn fmt_component_outs(data: &TemplateData, outputs: &HashMap<String, String>) -> String {
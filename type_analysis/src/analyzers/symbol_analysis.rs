@@ -19,6 +19,7 @@ pub fn check_naming_correctness(program_archive: &ProgramArchive) -> Result<(),
             data.get_file_id(),
             data.get_param_location(),
             data.get_name_of_params(),
+            data.get_dimensions_of_params().as_slice(),
             data.get_body_as_vec(),
         );
         instances.push(instance);
@@ -28,6 +29,7 @@ pub fn check_naming_correctness(program_archive: &ProgramArchive) -> Result<(),
             data.get_file_id(),
             data.get_param_location(),
             data.get_name_of_params(),
+            [].as_slice(),
             data.get_body_as_vec(),
         );
         instances.push(instance);
@@ -35,11 +37,12 @@ pub fn check_naming_correctness(program_archive: &ProgramArchive) -> Result<(),
     if let Err(mut r) = analyze_main(program_archive) {
         reports.append(&mut r);
     }
-    for (file_id, param_location, params_names, body) in instances {
+    for (file_id, param_location, params_names, params_dimensions, body) in instances {
         let res = analyze_symbols(
             file_id,
             param_location,
             params_names,
+            params_dimensions,
             body,
             function_info,
             template_info,
@@ -98,6 +101,7 @@ pub fn analyze_symbols(
     file_id: FileID,
     param_location: FileLocation,
     params_names: &[String],
+    params_dimensions: &[Vec<Expression>],
     body: &[Statement],
     function_info: &FunctionInfo,
     template_info: &TemplateInfo,
@@ -106,7 +110,11 @@ pub fn analyze_symbols(
     let mut reports = ReportCollection::new();
     let mut environment = Environment::new();
     environment.push(Block::new());
-    for param in params_names.iter() {
+    for (index, param) in params_names.iter().enumerate() {
+        // the dimensions of a parameter can only use the previous ones
+        for dimension in params_dimensions.get(index).into_iter().flatten() {
+            analyze_expression(dimension, file_id, function_info, template_info, &mut reports, &environment);
+        }
         let success = add_symbol_to_block(&mut environment, param);
         param_name_collision = param_name_collision || !success;
     }
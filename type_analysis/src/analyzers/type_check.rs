@@ -167,6 +167,7 @@ fn type_statement(
                 VariableType::AnonymousComponent => analysis_information
                     .environment
                     .add_component(name, (meta.component_inference.clone(), dimensions.len())),
+                VariableType::Bus(..) => unreachable!("buses are expanded into signals by the parser"),
             }
         }
         Substitution { var, access, op, rhe, meta, .. } => {
@@ -677,6 +678,18 @@ fn type_expression(
                 }
                 concrete_types.push(arg_type.dim());
             }
+            if program_archive.contains_template(id) {
+                let annotations = program_archive.get_template_data(id).get_dimensions_of_params();
+                for ((arg_expr, dim), annotation) in args.iter().zip(concrete_types.iter()).zip(annotations) {
+                    if !annotation.is_empty() && annotation.len() != *dim {
+                        success = add_report_and_end(
+                            ReportCode::WrongArgumentDimensions(annotation.len(), *dim),
+                            arg_expr.get_meta(),
+                            &mut analysis_information.reports,
+                        );
+                    }
+                }
+            }
             if program_archive.contains_template(id) && success.is_err() {
                 return Result::Ok(FoldedType::template(id));
             }
@@ -1067,6 +1080,9 @@ fn add_report(error_code: ReportCode, meta: &Meta, reports: &mut ReportCollectio
         WrongNumberOfArguments(expected, got) => {
             format!("Expecting {} arguments, {} where obtained", expected, got)
         }
+        WrongArgumentDimensions(expected, got) => {
+            format!("Expecting an argument with {} dimensions, {} where obtained", expected, got)
+        }
         UninitializedComponent => "Trying to access to a signal of a component that has not been initialized".to_string(),
         NonCompatibleBranchTypes => "Inline switch operator branches types are non compatible".to_string(),
         e => panic!("Unimplemented error code: {}", e),
@@ -137,8 +137,9 @@ fn semantic_analyses(
             errors.append(&mut unknown_known_report);
         }
         if program_archive.get_template_data(template_name).is_custom_gate() {
-            let body = program_archive.get_template_data(template_name).get_body();
-            match custom_gate_analysis(template_name, body) {
+            let template_data = program_archive.get_template_data(template_name);
+            let body = template_data.get_body();
+            match custom_gate_analysis(template_name, template_data.get_name_of_params(), body) {
                 Result::Ok(mut custom_gate_report) => warnings.append(&mut custom_gate_report),
                 Result::Err(mut custom_gate_report) => errors.append(&mut custom_gate_report),
             }
@@ -0,0 +1,49 @@
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+    use type_analysis::check_types::check_types;
+    use program_structure::error_code::ReportCode;
+
+    struct TestRelation<'a> {
+        input: &'a str,
+        expected: Vec<ReportCode>,
+    }
+
+    #[test]
+    fn custom_gate_relations() {
+        let cases = vec![
+            TestRelation {
+                input: "tests/fixtures/custom-gate-relation.circom",
+                expected: vec![],
+            },
+            TestRelation {
+                input: "tests/fixtures/custom-gate-relation-division.circom",
+                expected: vec![ReportCode::CustomGateRelationError],
+            },
+            TestRelation {
+                input: "tests/fixtures/custom-gate-relation-variable.circom",
+                expected: vec![ReportCode::CustomGateRelationError],
+            },
+            TestRelation {
+                input: "tests/fixtures/custom-gate-relation-signal-index.circom",
+                expected: vec![ReportCode::NonQuadratic, ReportCode::CustomGateRelationError],
+            },
+        ];
+        for case in cases {
+            let mut path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+            path.push(case.input);
+            let (mut program_archive, _) = parser::run_parser(
+                path.to_str().unwrap().to_owned(),
+                env!("CARGO_PKG_VERSION"),
+                vec![path],
+            )
+            .ok()
+            .unwrap();
+            let errors: Vec<ReportCode> = match check_types(&mut program_archive) {
+                Ok(_) => vec![],
+                Err(reports) => reports.iter().map(|report| *report.get_code()).collect(),
+            };
+            assert_eq!(errors, case.expected, "{}", case.input);
+        }
+    }
+}
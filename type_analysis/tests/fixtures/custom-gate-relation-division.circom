@@ -0,0 +1,12 @@
+pragma circom 2.1.8;
+pragma custom_templates;
+
+template custom Inverse() {
+  signal input x;
+  signal output y;
+
+  y <-- 1 / x;
+  y === 1 / x;
+}
+
+component main = Inverse();
@@ -0,0 +1,13 @@
+pragma circom 2.1.8;
+pragma custom_templates;
+
+template custom Select() {
+  signal input s;
+  signal input x[2];
+  signal output y;
+
+  y <-- x[s];
+  y === x[s];
+}
+
+component main = Select();
@@ -0,0 +1,13 @@
+pragma circom 2.1.8;
+pragma custom_templates;
+
+template custom Double() {
+  signal input x;
+  signal output y;
+  var two = 2;
+
+  y <-- two * x;
+  y === two * x;
+}
+
+component main = Double();
@@ -0,0 +1,24 @@
+pragma circom 2.1.8;
+pragma custom_templates;
+
+template custom Pow(n, k) {
+  signal input x;
+  signal input c[2];
+  signal output y;
+
+  y <-- k * (x + c[n - 1]) ** n;
+  y === k * (x + c[n - 1]) ** n;
+  c[0] * c[1] - 3 === -2 * c[0];
+}
+
+template Main() {
+  signal input in[3];
+  signal output out;
+
+  component pow = Pow(2, 7);
+  pow.x <== in[0];
+  pow.c <== [in[1], in[2]];
+  out <== pow.y;
+}
+
+component main = Main();
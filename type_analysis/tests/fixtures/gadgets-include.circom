@@ -0,0 +1,11 @@
+pragma circom 2.1.8;
+pragma custom_templates;
+include "circom:gadgets" as G;
+template Main() {
+    signal input in[2];
+    component r = G.RangeCheck(4);
+    r.in <== in[0];
+    component l = G.Lookup(2, 2, [[0, 1], [1, 0]]);
+    l.in <== in;
+}
+component main = Main();
@@ -0,0 +1,7 @@
+pragma circom 2.1.8;
+template Main() {
+    signal input in;
+    component r = RangeCheck(4);
+    r.in <== in;
+}
+component main = Main();
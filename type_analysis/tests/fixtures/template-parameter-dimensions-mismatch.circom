@@ -0,0 +1,18 @@
+pragma circom 2.1.8;
+
+template Sum(n, values[n]) {
+    signal output out;
+    var sum = 0;
+    for (var i = 0; i < n; i++) {
+        sum += values[i];
+    }
+    out <== sum;
+}
+
+template Main() {
+    signal output out[2];
+    out[0] <== Sum(1, 5)();
+    out[1] <== Sum(2, [[1, 2], [3, 4]])();
+}
+
+component main = Main();
@@ -0,0 +1,27 @@
+pragma circom 2.1.8;
+
+template Sum(n, values[n]) {
+    signal output out;
+    var sum = 0;
+    for (var i = 0; i < n; i++) {
+        sum += values[i];
+    }
+    out <== sum;
+}
+
+template Trace(n, matrix[n][n]) {
+    signal output out;
+    var trace = 0;
+    for (var i = 0; i < n; i++) {
+        trace += matrix[i][i];
+    }
+    out <== trace;
+}
+
+template Main() {
+    signal output out[2];
+    out[0] <== Sum(3, [1, 2, 3])();
+    out[1] <== Trace(2, [[1, 2], [3, 4]])();
+}
+
+component main = Main();
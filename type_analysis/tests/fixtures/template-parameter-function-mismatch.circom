@@ -0,0 +1,21 @@
+pragma circom 2.1.8;
+
+function double(x) {
+    return 2 * x;
+}
+
+template Scale(n, function f) {
+    signal input in;
+    signal output out;
+    out <== f(n) * in;
+}
+
+template Main() {
+    signal input in;
+    signal output out[2];
+    var k = 2;
+    out[0] <== Scale(2, k)(in);
+    out[1] <== Scale(double)(in);
+}
+
+component main = Main();
@@ -0,0 +1,31 @@
+pragma circom 2.1.8;
+
+function double(x) {
+    return 2 * x;
+}
+
+function square(x) {
+    return x * x;
+}
+
+template Map(n, function f) {
+    signal input in[n];
+    signal output out[n];
+    for (var i = 0; i < n; i++) {
+        out[i] <== f(i) * in[i];
+    }
+}
+
+template Compose(function f, function g) {
+    signal input in[2];
+    signal output out[2];
+    out <== Map(2, g)(Map(2, f)(in));
+}
+
+template Main() {
+    signal input in[2];
+    signal output out[2];
+    out <== Compose(double, square)(in);
+}
+
+component main = Main();
@@ -0,0 +1,35 @@
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+    use type_analysis::check_types::check_types;
+    use program_structure::error_code::ReportCode;
+    use program_structure::program_archive::ProgramArchive;
+
+    fn parse(input: &str) -> Result<ProgramArchive, Vec<ReportCode>> {
+        let mut path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        path.push(input);
+        let result = parser::run_parser(path.to_str().unwrap().to_owned(), env!("CARGO_PKG_VERSION"), vec![path]);
+        let mut program_archive = match result {
+            Ok((program_archive, _)) => program_archive,
+            Err((_, reports)) => return Err(reports.iter().map(|report| *report.get_code()).collect()),
+        };
+        match check_types(&mut program_archive) {
+            Ok(_) => Ok(program_archive),
+            Err(reports) => Err(reports.iter().map(|report| *report.get_code()).collect()),
+        }
+    }
+
+    #[test]
+    fn gadgets_are_loaded_in_the_namespace_of_their_include() {
+        let program_archive = parse("tests/fixtures/gadgets-include.circom").unwrap();
+        let mut gadgets: Vec<_> = program_archive.gadgets.iter().cloned().collect();
+        gadgets.sort();
+        assert_eq!(gadgets, vec!["G.Lookup".to_string(), "G.RangeCheck".to_string()]);
+    }
+
+    #[test]
+    fn gadgets_are_not_defined_without_their_include() {
+        let codes = parse("tests/fixtures/gadgets-missing.circom").err().unwrap();
+        assert_eq!(codes, vec![ReportCode::NonExistentSymbol]);
+    }
+}
@@ -0,0 +1,59 @@
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+    use type_analysis::check_types::check_types;
+    use program_structure::error_code::ReportCode;
+
+    struct TestParameters<'a> {
+        input: &'a str,
+        expected: Vec<ReportCode>,
+    }
+
+    // The codes of the errors found parsing and type checking the file
+    fn errors(input: &str) -> Vec<ReportCode> {
+        let mut path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        path.push(input);
+        let result = parser::run_parser(path.to_str().unwrap().to_owned(), env!("CARGO_PKG_VERSION"), vec![path]);
+        match result {
+            Ok((mut program_archive, _)) => match check_types(&mut program_archive) {
+                Ok(_) => vec![],
+                Err(reports) => reports.iter().map(|report| *report.get_code()).collect(),
+            },
+            Err((_, reports)) => reports.iter().map(|report| *report.get_code()).collect(),
+        }
+    }
+
+    #[test]
+    fn template_parameter_dimensions() {
+        let cases = vec![
+            TestParameters {
+                input: "tests/fixtures/template-parameter-dimensions.circom",
+                expected: vec![],
+            },
+            TestParameters {
+                input: "tests/fixtures/template-parameter-dimensions-mismatch.circom",
+                expected: vec![ReportCode::WrongArgumentDimensions(1, 0), ReportCode::WrongArgumentDimensions(1, 2)],
+            },
+        ];
+        for case in cases {
+            assert_eq!(errors(case.input), case.expected, "{}", case.input);
+        }
+    }
+
+    #[test]
+    fn template_parameter_functions() {
+        let cases = vec![
+            TestParameters {
+                input: "tests/fixtures/template-parameter-function.circom",
+                expected: vec![],
+            },
+            TestParameters {
+                input: "tests/fixtures/template-parameter-function-mismatch.circom",
+                expected: vec![ReportCode::InvalidFunctionArgument, ReportCode::TemplateWrongNumberOfArguments],
+            },
+        ];
+        for case in cases {
+            assert_eq!(errors(case.input), case.expected, "{}", case.input);
+        }
+    }
+}